@@ -1,3 +1,1693 @@
-fn main() {
-    println!("TypedLua CLI - Coming soon!");
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::io::{IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+use typedlua_core::annotate::annotate_source;
+use typedlua_core::build::{
+    compile_modules, compile_source, BuildCache, CompiledModule, DependencyGraph, RebuildReason,
+};
+use typedlua_core::completion;
+use typedlua_core::config::{CompilerConfig, StrictLevel};
+use typedlua_core::config_check::check_config;
+use typedlua_core::diagnostics::{
+    CollectingDiagnosticHandler, Diagnostic, DiagnosticBus, DiagnosticHandler, DiagnosticLevel,
+    DiagnosticSubscriber, JsonDiagnosticHandler, TerminalRenderer,
+};
+use typedlua_core::doctest::{check_example, extract_examples};
+use typedlua_core::explain;
+use typedlua_core::fix::fix_source;
+use typedlua_core::format::format_source;
+use typedlua_core::fs::{FileSystem, RealFileSystem};
+use typedlua_core::grammar;
+use typedlua_core::grammar::tree_sitter::{self, SyntaxNode};
+use typedlua_core::inspect::{diff_types, dump_types, explain_type, DiffSource};
+use typedlua_core::lint::{lint_source, Linter};
+use typedlua_core::rename;
+use typedlua_core::repl::{Evaluation, Session};
+use typedlua_core::report::{BuildReporter, HtmlReport, ModuleSummary};
+use typedlua_core::resolve::{is_data_module, ModuleResolver};
+use typedlua_core::sarif::SarifReport;
+use typedlua_core::shake::Removal;
+
+#[derive(Parser)]
+#[command(name = "typedlua", version, about = "TypedLua compiler")]
+struct Cli {
+    /// Path to the project configuration file
+    #[arg(short, long, global = true)]
+    project: Option<PathBuf>,
+
+    /// How build, watch, bundle and lint write diagnostics: as text on
+    /// stderr, as one JSON record per line on stdout, or as a SARIF log on
+    /// stdout once the command is done
+    #[arg(long, global = true, value_enum, default_value_t = DiagnosticsFormat::Text)]
+    diagnostics_format: DiagnosticsFormat,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Write inferred types into source as explicit annotations
+    Annotate {
+        /// Files to annotate
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Rewrite the files in place instead of printing to stdout
+        #[arg(short, long)]
+        write: bool,
+    },
+
+    /// Compile files to Lua, skipping modules unchanged since the last build
+    Build {
+        /// Files to compile
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Compile every file without reading or updating the build cache
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Delete the build cache before compiling
+        #[arg(long)]
+        clear_cache: bool,
+
+        /// Also write a declaration file (`.d.tl`) for each module
+        #[arg(long)]
+        emit_declarations: bool,
+
+        /// Leave out declarations nothing refers to, listing each one
+        #[arg(long)]
+        report_removed: bool,
+
+        /// Minify the emitted Lua
+        #[arg(long)]
+        minify: bool,
+
+        /// Emit Lua for people to read, keeping names and doc comments and
+        /// marking where each block of statements came from
+        #[arg(long, conflicts_with = "minify")]
+        readable: bool,
+
+        /// Also run the lint rules on each module compiled
+        #[arg(long)]
+        lint: bool,
+
+        /// Reject `// @tl-ignore` comments, as the noSuppressions option
+        /// does
+        #[arg(long)]
+        no_suppressions: bool,
+
+        /// Write a report of the build's diagnostics, type coverage, module
+        /// graph and emit sizes
+        #[arg(long, value_name = "FORMAT")]
+        report: Option<ReportFormat>,
+
+        /// Where to write the report
+        #[arg(long, value_name = "FILE", default_value = "typedlua-report.html")]
+        report_file: PathBuf,
+
+        /// Keep running, rebuilding the modules each change to a file affects
+        #[arg(long, conflicts_with = "report")]
+        watch: bool,
+    },
+
+    /// Compile a program and every module it imports into one Lua file
+    Bundle {
+        /// The program's entry module, or with --out-dir, each entry point
+        #[arg(required = true)]
+        entries: Vec<PathBuf>,
+
+        /// File to write the bundle to instead of printing it to stdout
+        #[arg(short, long, conflicts_with = "out_dir")]
+        output: Option<PathBuf>,
+
+        /// Write one bundle per entry to this directory, named after the
+        /// entry, with the modules the entries share in a shared chunk
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+
+        /// Module name the entry bundles require the shared chunk by, and
+        /// its file name in --out-dir
+        #[arg(long, default_value = "shared")]
+        shared_name: String,
+
+        /// List the declarations tree shaking left out, and why
+        #[arg(long)]
+        report_removed: bool,
+
+        /// Minify the bundle
+        #[arg(long)]
+        minify: bool,
+    },
+
+    /// Check the code examples in doc comments against their modules
+    Doctest {
+        /// Files whose doc comments to check
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Also run each example with the `lua` interpreter
+        #[arg(long)]
+        run: bool,
+    },
+
+    /// Format source files in place
+    Fmt {
+        /// Files to format; with none, or `-`, format stdin to stdout
+        files: Vec<PathBuf>,
+
+        /// List the files that are not formatted instead of rewriting them,
+        /// failing if there are any
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Apply the fixes errors suggest. Only those that cannot change what
+    /// the program does are applied unless asked.
+    Fix {
+        /// Files to fix in place
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Also apply fixes that guess at the intent, such as a missing
+        /// `end` or a misspelled field
+        #[arg(long)]
+        unsafe_fixes: bool,
+    },
+
+    /// Run the lint rules over source files without compiling them
+    Lint {
+        /// Files to lint
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+
+    /// Print the resolved type of every top-level binding in a module
+    DumpTypes {
+        /// Module to inspect
+        file: PathBuf,
+
+        /// Also print the type of the innermost expression or name at this
+        /// byte offset
+        #[arg(long)]
+        offset: Option<usize>,
+    },
+
+    /// Describe a diagnostic code, such as TL2031, or a lint rule: what it
+    /// means, an example and common fixes. Lists every code without one.
+    Explain {
+        /// The code shown in brackets after a diagnostic's level
+        code: Option<String>,
+    },
+
+    /// Explain how the checker arrived at the type at a byte offset of a
+    /// module: the declaration and narrowings behind it, and why a type
+    /// mismatch reported there fails
+    ExplainType {
+        /// Module to inspect
+        file: PathBuf,
+
+        /// Byte offset of the expression or name to explain
+        #[arg(long)]
+        offset: usize,
+    },
+
+    /// Compare a type with the type expected of it member by member,
+    /// showing which members or parameters differ and why. Types are
+    /// resolved in the module's scope.
+    TypeDiff {
+        /// Module declaring the types
+        file: PathBuf,
+
+        /// The type to compare and the type expected of it, or only the
+        /// expected type with --offset
+        #[arg(num_args = 1..=2, required = true)]
+        types: Vec<String>,
+
+        /// Compare the type of the expression or name at this byte offset
+        #[arg(long)]
+        offset: Option<usize>,
+    },
+
+    /// Explain, from the build cache, why the next build compiles a module
+    /// again rather than reuse its cached output, and list what it depends
+    /// on
+    Why {
+        /// The module, or the Lua file it compiles to
+        file: PathBuf,
+    },
+
+    /// List completions at a byte offset of a module, one per line, for
+    /// editor integrations
+    Complete {
+        /// Module being edited
+        file: PathBuf,
+
+        /// Byte offset of the cursor
+        #[arg(long)]
+        offset: usize,
+    },
+
+    /// Rename a name and its uses. An exported name is renamed in the
+    /// module's declaration file and in the workspace modules importing it
+    /// too. The changes are printed as a diff unless `--write` is given.
+    Rename {
+        /// Module declaring or using the name
+        file: PathBuf,
+
+        /// Byte offset of the name
+        #[arg(long)]
+        offset: usize,
+
+        /// The new name
+        #[arg(long)]
+        to: String,
+
+        /// The workspace's modules, searched for imports of the name
+        workspace: Vec<PathBuf>,
+
+        /// Apply the changes instead of printing them
+        #[arg(short, long)]
+        write: bool,
+    },
+
+    /// Print the grammar the parser accepts, headed by its syntax version,
+    /// for keeping TextMate, tree-sitter and other editor grammars in sync
+    Grammar {
+        #[arg(long, value_enum, default_value_t = GrammarFormat::Ebnf)]
+        format: GrammarFormat,
+
+        /// Print only the syntax version
+        #[arg(long)]
+        syntax_version: bool,
+    },
+
+    /// Generate a tree-sitter grammar, and check one against the parser
+    TreeSitter {
+        #[command(subcommand)]
+        command: TreeSitterCommand,
+    },
+
+    /// Evaluate code interactively, printing each expression's value and
+    /// type. Runs in the `lua` interpreter found on the PATH.
+    Repl,
+
+    /// Inspect the project configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    /// One self-contained HTML page
+    Html,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GrammarFormat {
+    /// W3C EBNF, which railroad diagram generators read
+    Ebnf,
+    /// The rules and keywords, for generating editor grammars
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DiagnosticsFormat {
+    Text,
+    /// Code, level, message, file, span, related spans and suggested fixes
+    Json,
+    /// One SARIF 2.1.0 log, for code scanning dashboards
+    Sarif,
+}
+
+#[derive(Subcommand)]
+enum TreeSitterCommand {
+    /// Print a grammar.js generated from the syntax rules
+    Generate,
+
+    /// Parse files with a tree-sitter grammar, through the `tree-sitter`
+    /// CLI found on the PATH, and report where its trees disagree with the
+    /// parser on token boundaries or syntax errors
+    Check {
+        files: Vec<PathBuf>,
+
+        /// The directory holding the grammar
+        #[arg(long, default_value = ".")]
+        grammar_dir: PathBuf,
+
+        /// Write a corpus test for each file the grammar agrees on into
+        /// this directory
+        #[arg(long)]
+        corpus: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Validate the configuration and print the effective settings
+    Check,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let diagnostics_output = DiagnosticsOutput::new(cli.diagnostics_format);
+    let result = run(cli, &diagnostics_output);
+    diagnostics_output.finish();
+    result
+}
+
+fn run(cli: Cli, diagnostics_output: &DiagnosticsOutput) -> Result<()> {
+    let config = Arc::new(load_config(cli.project.as_deref(), diagnostics_output)?);
+    diagnostics_output.describe_rules(&config);
+
+    match cli.command {
+        Command::Annotate { files, write } => annotate(&files, write, config),
+        Command::Build {
+            files,
+            no_cache,
+            clear_cache,
+            emit_declarations,
+            report_removed,
+            minify,
+            readable,
+            lint,
+            no_suppressions,
+            report,
+            report_file,
+            watch: watching,
+        } => {
+            let mut config = config.as_ref().clone();
+            if emit_declarations {
+                config.compiler_options.emit_declarations = true;
+            }
+            if report_removed {
+                config.compiler_options.tree_shaking = true;
+            }
+            if minify {
+                config.compiler_options.minify = true;
+            }
+            if readable {
+                config.compiler_options.readable_output = true;
+            }
+            if lint {
+                config.lint.on_build = true;
+            }
+            if no_suppressions {
+                config.compiler_options.no_suppressions = true;
+            }
+            if watching {
+                if let DiagnosticsFormat::Sarif = diagnostics_output.format {
+                    anyhow::bail!("A SARIF log needs a build that finishes; use json with --watch");
+                }
+                return watch(&files, report_removed, diagnostics_output, Arc::new(config));
+            }
+            let mut reporter = report.map(|ReportFormat::Html| {
+                HtmlReport::new(&report_file, Arc::new(RealFileSystem::new()))
+            });
+            build(
+                &files,
+                no_cache,
+                clear_cache,
+                report_removed,
+                reporter
+                    .as_mut()
+                    .map(|reporter| reporter as &mut dyn BuildReporter),
+                diagnostics_output,
+                Arc::new(config),
+            )
+        }
+        Command::Bundle {
+            entries,
+            output,
+            out_dir,
+            shared_name,
+            report_removed,
+            minify,
+        } => {
+            let mut config = config.as_ref().clone();
+            if minify {
+                config.compiler_options.minify = true;
+            }
+            match (out_dir, entries.as_slice()) {
+                (Some(out_dir), _) => bundle_split(
+                    &entries,
+                    &out_dir,
+                    &shared_name,
+                    report_removed,
+                    diagnostics_output,
+                    Arc::new(config),
+                ),
+                (None, [entry]) => bundle(
+                    entry,
+                    output.as_deref(),
+                    report_removed,
+                    diagnostics_output,
+                    Arc::new(config),
+                ),
+                (None, _) => anyhow::bail!("Bundling several entries needs --out-dir"),
+            }
+        }
+        Command::Doctest { files, run } => doctest(&files, run, config),
+        Command::Fmt { files, check } => fmt(&files, check, config),
+        Command::Fix {
+            files,
+            unsafe_fixes,
+        } => fix(&files, unsafe_fixes, config),
+        Command::Lint { files } => lint(&files, diagnostics_output, config),
+        Command::DumpTypes { file, offset } => dump(&file, offset, config),
+        Command::Explain { code } => explain_code(code.as_deref(), config),
+        Command::ExplainType { file, offset } => explain(&file, offset, config),
+        Command::TypeDiff {
+            file,
+            types,
+            offset,
+        } => type_diff(&file, &types, offset, config),
+        Command::Why { file } => why(&file, config),
+        Command::Complete { file, offset } => complete(&file, offset),
+        Command::Rename {
+            file,
+            offset,
+            to,
+            workspace,
+            write,
+        } => rename_symbol(&file, offset, &to, &workspace, write, config),
+        Command::Grammar {
+            format,
+            syntax_version,
+        } => {
+            if syntax_version {
+                println!("{}", grammar::SYNTAX_VERSION);
+            } else {
+                match format {
+                    GrammarFormat::Ebnf => print!("{}", grammar::to_ebnf()),
+                    GrammarFormat::Json => println!("{}", grammar::to_json()),
+                }
+            }
+            Ok(())
+        }
+        Command::TreeSitter {
+            command: TreeSitterCommand::Generate,
+        } => {
+            print!("{}", tree_sitter::grammar_js());
+            Ok(())
+        }
+        Command::TreeSitter {
+            command:
+                TreeSitterCommand::Check {
+                    files,
+                    grammar_dir,
+                    corpus,
+                },
+        } => check_tree_sitter(&files, &grammar_dir, corpus.as_deref()),
+        Command::Repl => repl(config),
+        Command::Config {
+            command: ConfigCommand::Check,
+        } => {
+            print!("{}", serde_yaml::to_string(config.as_ref())?);
+            Ok(())
+        }
+    }
+}
+
+/// Load and validate the configuration file, reporting any problems in it
+fn load_config(
+    project: Option<&Path>,
+    diagnostics_output: &DiagnosticsOutput,
+) -> Result<CompilerConfig> {
+    let Some(path) = project else {
+        return Ok(CompilerConfig::default());
+    };
+    let source = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to load {}", path.display()))?;
+
+    let (config, diagnostics) = check_config(&source);
+    diagnostics_output.report(path, &diagnostics);
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.level == DiagnosticLevel::Error)
+        .count();
+    match config {
+        Some(config) if errors == 0 => Ok(config),
+        _ => anyhow::bail!("{} has {} configuration error(s)", path.display(), errors),
+    }
+}
+
+/// Print a diagnostic to stderr, framed with the lines it points at when
+/// the source it was reported against is at hand
+fn report_diagnostic(path: &Path, source: Option<&str>, diagnostic: &Diagnostic) {
+    if let Some(source) = source {
+        eprint!("{}", terminal_renderer(path, source).render(diagnostic));
+        return;
+    }
+    let code = diagnostic
+        .code
+        .as_ref()
+        .map(|code| format!("[{}]", code))
+        .unwrap_or_default();
+    eprintln!(
+        "{}:{}: {}{}: {}",
+        path.display(),
+        diagnostic.span,
+        diagnostic.level.as_str(),
+        code,
+        diagnostic.message
+    );
+}
+
+/// Renders diagnostics framed with the lines of `source`, the file at
+/// `path`, in color when stderr is a terminal
+fn terminal_renderer(path: &Path, source: &str) -> TerminalRenderer {
+    TerminalRenderer::new(std::io::stderr().is_terminal()).with_source(path, source)
+}
+
+/// Where diagnostics go, in the format `--diagnostics-format` chose
+struct DiagnosticsOutput {
+    format: DiagnosticsFormat,
+    /// The SARIF log, written once the command is done
+    sarif: Mutex<SarifReport>,
+}
+
+impl DiagnosticsOutput {
+    fn new(format: DiagnosticsFormat) -> Self {
+        DiagnosticsOutput {
+            format,
+            sarif: Mutex::new(SarifReport::new()),
+        }
+    }
+
+    /// Write the diagnostics reported for a file, or keep them for the
+    /// SARIF log
+    fn report(&self, path: &Path, diagnostics: &[Diagnostic]) {
+        match self.format {
+            DiagnosticsFormat::Text => {
+                let source = std::fs::read_to_string(path).ok();
+                for diagnostic in diagnostics {
+                    report_diagnostic(path, source.as_deref(), diagnostic);
+                }
+            }
+            DiagnosticsFormat::Json => {
+                let handler = JsonDiagnosticHandler::new(std::io::stdout()).with_file(path);
+                for diagnostic in diagnostics {
+                    handler.report(diagnostic.clone());
+                }
+            }
+            DiagnosticsFormat::Sarif => self
+                .sarif
+                .lock()
+                .unwrap()
+                .add_diagnostics(path, diagnostics),
+        }
+    }
+
+    /// List the lint rules the configuration runs in the SARIF log
+    fn describe_rules(&self, config: &CompilerConfig) {
+        if let DiagnosticsFormat::Sarif = self.format {
+            let linter = Linter::for_config(config);
+            self.sarif.lock().unwrap().add_lint_rules(&linter);
+        }
+    }
+
+    /// Write the SARIF log to stdout
+    fn finish(&self) {
+        if let DiagnosticsFormat::Sarif = self.format {
+            println!("{}", self.sarif.lock().unwrap().to_json());
+        }
+    }
+}
+
+fn report_removal(path: &Path, removal: &Removal) {
+    eprintln!(
+        "{}:{}:{}: removed {} '{}': {}",
+        path.display(),
+        removal.line,
+        removal.column,
+        removal.kind,
+        removal.name,
+        removal.reason
+    );
+}
+
+fn annotate(files: &[PathBuf], write: bool, config: Arc<CompilerConfig>) -> Result<()> {
+    let file_system = RealFileSystem::new();
+    for file in files {
+        let source = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+
+        let handler = Arc::new(DiagnosticBus::with_subscribers(vec![Arc::new(
+            terminal_renderer(file, &source),
+        )]));
+        let annotated = annotate_source(&source, config.clone(), handler)
+            .with_context(|| format!("Failed to annotate {}", file.display()))?;
+
+        if write {
+            // Leave files that need no annotations untouched
+            file_system
+                .write_if_changed(file, &annotated)
+                .with_context(|| format!("Failed to write {}", file.display()))?;
+        } else {
+            print!("{}", annotated);
+        }
+    }
+
+    Ok(())
+}
+
+fn build(
+    files: &[PathBuf],
+    no_cache: bool,
+    clear_cache: bool,
+    report_removed: bool,
+    mut reporter: Option<&mut dyn BuildReporter>,
+    diagnostics_output: &DiagnosticsOutput,
+    config: Arc<CompilerConfig>,
+) -> Result<()> {
+    let cache_dir = PathBuf::from(&config.compiler_options.cache_dir);
+    if clear_cache && cache_dir.exists() {
+        std::fs::remove_dir_all(&cache_dir)
+            .with_context(|| format!("Failed to clear {}", cache_dir.display()))?;
+    }
+    let file_system = Arc::new(RealFileSystem::new());
+    let cache = (!no_cache).then(|| BuildCache::new(&cache_dir, &config, file_system.clone()));
+
+    let mut sources = Vec::new();
+    for file in files {
+        let source = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        sources.push(source);
+    }
+    // Cached modules keep no diagnostics, so a report compiles everything
+    let cached: Vec<Option<CompiledModule>> = files
+        .iter()
+        .zip(&sources)
+        .map(|(file, source)| {
+            let cache = cache.as_ref().filter(|_| reporter.is_none())?;
+            cache.load(file, source)
+        })
+        .collect();
+
+    // Compile the modules missing from the cache in parallel, then report
+    // their diagnostics in file order
+    let stale: Vec<(&Path, &str)> = files
+        .iter()
+        .zip(&sources)
+        .zip(&cached)
+        .filter(|(_, cached)| cached.is_none())
+        .map(|((file, source), _)| (file.as_path(), source.as_str()))
+        .collect();
+    let mut compiled =
+        compile_modules(&stale, file_system.clone(), config.clone(), cache.as_ref()).into_iter();
+
+    let mut unchanged = 0;
+    // A report covers every module, so reporting builds go on past modules
+    // that fail and report the first failure at the end
+    let mut failure = None;
+    for ((file, source), cached) in files.iter().zip(&sources).zip(cached) {
+        let compiled = match cached {
+            Some(compiled) => {
+                unchanged += 1;
+                compiled
+            }
+            None => {
+                let (result, diagnostics) = compiled.next().expect("one result per module");
+                match diagnostics_output.format {
+                    DiagnosticsFormat::Text => {
+                        let renderer = terminal_renderer(file, source);
+                        for diagnostic in &diagnostics {
+                            renderer.notify(diagnostic);
+                        }
+                    }
+                    _ => diagnostics_output.report(file, &diagnostics),
+                }
+                if let Some(reporter) = reporter.as_mut() {
+                    reporter.module(ModuleSummary::new(
+                        file,
+                        source,
+                        result.as_ref().ok(),
+                        diagnostics,
+                    ));
+                }
+                let compiled = match result {
+                    Ok(compiled) => compiled,
+                    Err(error) if reporter.is_some() => {
+                        failure.get_or_insert(
+                            anyhow::Error::new(error)
+                                .context(format!("Failed to compile {}", file.display())),
+                        );
+                        continue;
+                    }
+                    Err(error) => {
+                        return Err(error)
+                            .with_context(|| format!("Failed to compile {}", file.display()))
+                    }
+                };
+                if let Some(cache) = &cache {
+                    cache
+                        .store(file, source, &compiled)
+                        .with_context(|| format!("Failed to cache {}", file.display()))?;
+                }
+                compiled
+            }
+        };
+
+        emit(
+            file,
+            &compiled,
+            report_removed,
+            file_system.as_ref(),
+            &config,
+        )?;
+    }
+
+    if let Some(reporter) = reporter {
+        reporter
+            .finish()
+            .context("Failed to write the build report")?;
+    }
+    if let Some(failure) = failure {
+        return Err(failure);
+    }
+    eprintln!(
+        "Compiled {} file(s), {} unchanged since the last build",
+        files.len(),
+        unchanged
+    );
+    Ok(())
+}
+
+/// Write a compiled module's Lua, source map and declaration file
+fn emit(
+    file: &Path,
+    compiled: &CompiledModule,
+    report_removed: bool,
+    file_system: &dyn FileSystem,
+    config: &CompilerConfig,
+) -> Result<()> {
+    if report_removed {
+        for removal in &compiled.removals {
+            report_removal(file, removal);
+        }
+    }
+    if config.compiler_options.no_emit {
+        return Ok(());
+    }
+    let output = output_path(file, config.compiler_options.out_dir.as_deref());
+    file_system
+        .write_if_changed(&output, &compiled.lua)
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+    if let Some(source_map) = &compiled.source_map {
+        let map_path = PathBuf::from(format!("{}.map", output.display()));
+        let json = source_map.to_json(&file_name(&output), &map_source(file, &output));
+        file_system
+            .write_if_changed(&map_path, &json)
+            .with_context(|| format!("Failed to write {}", map_path.display()))?;
+    }
+    if let Some(declarations) = &compiled.declarations {
+        let declaration_path = output.with_extension("d.tl");
+        file_system
+            .write_if_changed(&declaration_path, declarations)
+            .with_context(|| format!("Failed to write {}", declaration_path.display()))?;
+    }
+    Ok(())
+}
+
+/// The last build of a module in watch mode
+#[derive(Default)]
+struct WatchedModule {
+    diagnostics: Vec<Diagnostic>,
+    /// Why the module could not be read or written, if it could not
+    failure: Option<String>,
+}
+
+/// Build `files`, then rebuild the modules each change affects until
+/// interrupted: the changed modules and the modules importing them or a
+/// changed declaration file. Each rebuild clears the terminal and prints
+/// the diagnostics of every module, rebuilt or not, in file order, so the
+/// output always shows the whole build.
+fn watch(
+    files: &[PathBuf],
+    report_removed: bool,
+    diagnostics_output: &DiagnosticsOutput,
+    config: Arc<CompilerConfig>,
+) -> Result<()> {
+    let file_system = Arc::new(RealFileSystem::new());
+    let (sender, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).context("Failed to watch files")?;
+    let mut watched_dirs = HashSet::new();
+    // Graph paths are canonical, as the paths of file events are
+    let mut graph = DependencyGraph::new();
+    for file in files {
+        graph.update(&watch_path(file), Vec::new());
+        watch_dir(&mut watcher, &mut watched_dirs, file)?;
+    }
+    let mut modules: Vec<WatchedModule> = files.iter().map(|_| WatchedModule::default()).collect();
+
+    let mut pending: Vec<usize> = (0..files.len()).collect();
+    loop {
+        let mut sources = Vec::new();
+        for &index in &pending {
+            match std::fs::read_to_string(&files[index]) {
+                Ok(source) => sources.push((index, source)),
+                Err(error) => {
+                    modules[index] = WatchedModule {
+                        diagnostics: Vec::new(),
+                        failure: Some(format!("Failed to read: {}", error)),
+                    }
+                }
+            }
+        }
+        let stale: Vec<(&Path, &str)> = sources
+            .iter()
+            .map(|(index, source)| (files[*index].as_path(), source.as_str()))
+            .collect();
+        let results = compile_modules(&stale, file_system.clone(), config.clone(), None);
+        for ((index, _), (result, diagnostics)) in sources.iter().zip(results) {
+            let file = &files[*index];
+            let mut failure = None;
+            if let Ok(compiled) = result {
+                let dependencies = compiled.dependencies();
+                for path in &dependencies {
+                    watch_dir(&mut watcher, &mut watched_dirs, path)?;
+                }
+                graph.update(
+                    &watch_path(file),
+                    dependencies.iter().map(|path| watch_path(path)).collect(),
+                );
+                if let Err(error) = emit(
+                    file,
+                    &compiled,
+                    report_removed,
+                    file_system.as_ref(),
+                    &config,
+                ) {
+                    failure = Some(format!("{:#}", error));
+                }
+            }
+            modules[*index] = WatchedModule {
+                diagnostics,
+                failure,
+            };
+        }
+        if std::io::stderr().is_terminal() {
+            eprint!("\x1b[2J\x1b[H");
+        }
+        let (mut errors, mut warnings) = (0, 0);
+        for (file, module) in files.iter().zip(&modules) {
+            diagnostics_output.report(file, &module.diagnostics);
+            for diagnostic in &module.diagnostics {
+                match diagnostic.level {
+                    DiagnosticLevel::Error => errors += 1,
+                    DiagnosticLevel::Warning => warnings += 1,
+                    DiagnosticLevel::Info => {}
+                }
+            }
+            if let Some(failure) = &module.failure {
+                eprintln!("{}: error: {}", file.display(), failure);
+                errors += 1;
+            }
+        }
+        eprintln!(
+            "Rebuilt {} of {} file(s): {} error(s), {} warning(s). Watching for changes...",
+            pending.len(),
+            files.len(),
+            errors,
+            warnings
+        );
+
+        // Wait for a change, then let the burst of events one save makes
+        // settle before rebuilding
+        loop {
+            let mut changed = Vec::new();
+            let mut event = events.recv().context("Stopped watching files")?;
+            loop {
+                if let Ok(event) = event {
+                    if !matches!(event.kind, EventKind::Access(_)) {
+                        changed.extend(event.paths);
+                    }
+                }
+                match events.recv_timeout(Duration::from_millis(50)) {
+                    Ok(next) => event = next,
+                    Err(_) => break,
+                }
+            }
+            let affected = graph.affected(&changed);
+            pending = (0..files.len())
+                .filter(|index| affected.contains(&watch_path(&files[*index])))
+                .collect();
+            if !pending.is_empty() {
+                break;
+            }
+        }
+    }
+}
+
+/// Watch the directory holding `path`, unless it is watched already.
+/// Directories are watched rather than files, since editors often save by
+/// replacing the file.
+fn watch_dir(
+    watcher: &mut RecommendedWatcher,
+    watched_dirs: &mut HashSet<PathBuf>,
+    path: &Path,
+) -> Result<()> {
+    let Some(dir) = watch_path(path).parent().map(Path::to_path_buf) else {
+        return Ok(());
+    };
+    if watched_dirs.insert(dir.clone()) {
+        watcher
+            .watch(&dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Failed to watch {}", dir.display()))?;
+    }
+    Ok(())
+}
+
+/// A path as file events name it, or as given if it no longer exists
+fn watch_path(path: &Path) -> PathBuf {
+    path.canonicalize()
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn bundle(
+    entry: &Path,
+    output: Option<&Path>,
+    report_removed: bool,
+    diagnostics_output: &DiagnosticsOutput,
+    config: Arc<CompilerConfig>,
+) -> Result<()> {
+    let file_system = Arc::new(RealFileSystem::new());
+    let bundle = typedlua_core::bundle::bundle(
+        entry,
+        file_system.clone(),
+        config,
+        &mut |path, diagnostic| diagnostics_output.report(path, std::slice::from_ref(diagnostic)),
+    )
+    .with_context(|| format!("Failed to bundle {}", entry.display()))?;
+    if report_removed {
+        for (path, removal) in &bundle.removals {
+            report_removal(path, removal);
+        }
+    }
+
+    let lua = bundle.lua;
+    match output {
+        Some(output) => file_system
+            .write_if_changed(output, &lua)
+            .map(|_| ())
+            .with_context(|| format!("Failed to write {}", output.display())),
+        None => {
+            print!("{}", lua);
+            Ok(())
+        }
+    }
+}
+
+fn bundle_split(
+    entries: &[PathBuf],
+    out_dir: &Path,
+    shared_name: &str,
+    report_removed: bool,
+    diagnostics_output: &DiagnosticsOutput,
+    config: Arc<CompilerConfig>,
+) -> Result<()> {
+    // Bundles are named after their entries, so two entries must not share
+    // a name
+    let mut names = HashSet::new();
+    for entry in entries {
+        let name = file_name(&entry.with_extension("lua"));
+        if name == format!("{}.lua", shared_name) || !names.insert(name.clone()) {
+            anyhow::bail!("Two bundles would be written to {}", name);
+        }
+    }
+
+    let file_system = Arc::new(RealFileSystem::new());
+    let split = typedlua_core::bundle::bundle_split(
+        entries,
+        shared_name,
+        file_system.clone(),
+        config,
+        &mut |path, diagnostic| diagnostics_output.report(path, std::slice::from_ref(diagnostic)),
+    )
+    .context("Failed to bundle")?;
+    if report_removed {
+        for (path, removal) in &split.removals {
+            report_removal(path, removal);
+        }
+    }
+
+    let mut outputs: Vec<(PathBuf, &str)> = split
+        .entries
+        .iter()
+        .map(|(entry, lua)| {
+            let name = file_name(&entry.with_extension("lua"));
+            (out_dir.join(name), lua.as_str())
+        })
+        .collect();
+    if let Some(shared) = &split.shared {
+        outputs.push((out_dir.join(format!("{}.lua", shared_name)), shared));
+    }
+    for (path, lua) in outputs {
+        file_system
+            .write_if_changed(&path, lua)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+fn doctest(files: &[PathBuf], run: bool, config: Arc<CompilerConfig>) -> Result<()> {
+    let (mut total, mut failed) = (0, 0);
+    for file in files {
+        let source = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+
+        // Examples can only be checked against a module that compiles
+        let handler = Arc::new(DiagnosticBus::with_subscribers(vec![Arc::new(
+            terminal_renderer(file, &source),
+        )]));
+        compile_source(&source, config.clone(), handler)
+            .with_context(|| format!("Failed to compile {}", file.display()))?;
+
+        for example in extract_examples(&source) {
+            total += 1;
+            let outcome = check_example(&source, &example, config.clone());
+            let passed = match outcome {
+                Ok(lua) if run && example.run => match run_lua(&lua)? {
+                    None => true,
+                    Some(error) => {
+                        eprintln!(
+                            "{}:{}: error: Doc example failed: {}",
+                            file.display(),
+                            example.span,
+                            error
+                        );
+                        false
+                    }
+                },
+                Ok(_) => true,
+                Err(errors) => {
+                    for error in &errors {
+                        report_diagnostic(file, Some(&source), error);
+                    }
+                    false
+                }
+            };
+            if !passed {
+                failed += 1;
+            }
+        }
+    }
+
+    eprintln!("{} of {} doc example(s) passed", total - failed, total);
+    if failed > 0 {
+        anyhow::bail!("{} doc example(s) failed", failed);
+    }
+    Ok(())
+}
+
+fn fmt(files: &[PathBuf], check: bool, config: Arc<CompilerConfig>) -> Result<()> {
+    let format = |source: &str, path: &Path| {
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let result = format_source(source, &config.format, handler.clone());
+        for diagnostic in handler.get_diagnostics() {
+            report_diagnostic(path, Some(source), &diagnostic);
+        }
+        result.with_context(|| format!("Failed to format {}", path.display()))
+    };
+
+    // Editors pipe the buffer through stdin and read the result back
+    if files.is_empty() || files == [PathBuf::from("-")] {
+        let mut source = String::new();
+        std::io::stdin()
+            .read_to_string(&mut source)
+            .context("Failed to read stdin")?;
+        let formatted = format(&source, Path::new("<stdin>"))?;
+        if check {
+            if formatted != source {
+                anyhow::bail!("<stdin> is not formatted");
+            }
+        } else {
+            print!("{}", formatted);
+        }
+        return Ok(());
+    }
+
+    let file_system = RealFileSystem::new();
+    let mut unformatted = 0;
+    for file in files {
+        let source = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        let formatted = format(&source, file)?;
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{}", file.display());
+            unformatted += 1;
+        } else {
+            file_system
+                .write_if_changed(file, &formatted)
+                .with_context(|| format!("Failed to write {}", file.display()))?;
+        }
+    }
+    if unformatted > 0 {
+        anyhow::bail!("{} file(s) are not formatted", unformatted);
+    }
+    Ok(())
+}
+
+fn fix(files: &[PathBuf], unsafe_fixes: bool, config: Arc<CompilerConfig>) -> Result<()> {
+    let file_system = RealFileSystem::new();
+    let (mut applied, mut skipped) = (0, 0);
+    for file in files {
+        let source = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let fixed = fix_source(&source, config.clone(), handler, unsafe_fixes)
+            .with_context(|| format!("Failed to fix {}", file.display()))?;
+        if fixed.applied > 0 {
+            file_system
+                .write_if_changed(file, &fixed.text)
+                .with_context(|| format!("Failed to write {}", file.display()))?;
+            eprintln!("Fixed {} ({})", file.display(), fixed.applied);
+        }
+        applied += fixed.applied;
+        skipped += fixed.skipped;
+    }
+
+    eprintln!("Applied {} fix(es)", applied);
+    if skipped > 0 {
+        // Fixes overlapping an applied one show up again on the next run
+        eprintln!(
+            "{} fix(es) left to review{}",
+            skipped,
+            if unsafe_fixes {
+                ""
+            } else {
+                "; --unsafe-fixes applies them"
+            }
+        );
+    }
+    Ok(())
+}
+
+fn check_tree_sitter(files: &[PathBuf], grammar_dir: &Path, corpus: Option<&Path>) -> Result<()> {
+    let file_system = RealFileSystem::new();
+    let mut disagreeing = 0;
+    for file in files {
+        let source = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        let tree = tree_sitter_parse(file, grammar_dir)?;
+        let disagreements = tree_sitter::cross_validate(&source, &tree);
+        for disagreement in &disagreements {
+            eprintln!("{}:{}", file.display(), disagreement);
+        }
+        if !disagreements.is_empty() {
+            disagreeing += 1;
+            continue;
+        }
+        if let Some(corpus) = corpus {
+            let name = file.file_stem().unwrap_or_default().to_string_lossy();
+            let path = corpus.join(format!("{}.txt", name));
+            file_system
+                .write_if_changed(&path, &tree_sitter::corpus_entry(&name, &source, &tree))
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+    }
+
+    if disagreeing > 0 {
+        anyhow::bail!(
+            "The grammar disagrees with the parser on {} of {} file(s)",
+            disagreeing,
+            files.len()
+        );
+    }
+    eprintln!(
+        "The grammar agrees with the parser on {} file(s)",
+        files.len()
+    );
+    Ok(())
+}
+
+/// The tree `tree-sitter parse` prints for a file, with the grammar in
+/// `grammar_dir`
+fn tree_sitter_parse(file: &Path, grammar_dir: &Path) -> Result<SyntaxNode> {
+    use std::process::Command;
+
+    let file = std::fs::canonicalize(file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    // Exits with a failure when the tree has errors, which are compared
+    let output = Command::new("tree-sitter")
+        .arg("parse")
+        .arg(&file)
+        .current_dir(grammar_dir)
+        .output()
+        .context("Failed to start the tree-sitter CLI")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.trim().is_empty() {
+        anyhow::bail!(
+            "tree-sitter could not parse {}: {}",
+            file.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    SyntaxNode::parse(&stdout)
+        .map_err(|e| anyhow::anyhow!("Failed to read the tree for {}: {}", file.display(), e))
+}
+
+fn lint(
+    files: &[PathBuf],
+    diagnostics_output: &DiagnosticsOutput,
+    config: Arc<CompilerConfig>,
+) -> Result<()> {
+    let linter = Linter::for_config(&config);
+    for rule in linter.unknown_rules() {
+        eprintln!("warning: Unknown lint rule '{}' in the configuration", rule);
+    }
+
+    let (mut errors, mut warnings) = (0, 0);
+    for file in files {
+        let source = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let result = lint_source(&source, &linter, handler.clone());
+        diagnostics_output.report(file, &handler.get_diagnostics());
+        result.with_context(|| format!("Failed to lint {}", file.display()))?;
+        errors += handler.error_count();
+        warnings += handler.warning_count();
+    }
+
+    eprintln!(
+        "Linted {} file(s): {} error(s), {} warning(s)",
+        files.len(),
+        errors,
+        warnings
+    );
+    if errors > 0 {
+        anyhow::bail!("Lint found {} error(s)", errors);
+    }
+    Ok(())
+}
+
+fn dump(file: &Path, offset: Option<usize>, config: Arc<CompilerConfig>) -> Result<()> {
+    let source = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let handler = Arc::new(DiagnosticBus::with_subscribers(vec![Arc::new(
+        terminal_renderer(file, &source),
+    )]));
+    let dump = dump_types(&source, offset, config, handler)
+        .with_context(|| format!("Failed to parse {}", file.display()))?;
+
+    for binding in &dump.bindings {
+        println!("{}:{}", file.display(), binding);
+    }
+    if let Some(offset) = offset {
+        match &dump.at_offset {
+            Some((span, typ)) => {
+                println!("{}:{}: at offset {}: {}", file.display(), span, offset, typ)
+            }
+            None => println!("{}: nothing typed at offset {}", file.display(), offset),
+        }
+    }
+    Ok(())
+}
+
+fn explain(file: &Path, offset: usize, config: Arc<CompilerConfig>) -> Result<()> {
+    let source = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let handler = Arc::new(DiagnosticBus::with_subscribers(vec![Arc::new(
+        terminal_renderer(file, &source),
+    )]));
+    let explanation = explain_type(&source, offset, config, handler)
+        .with_context(|| format!("Failed to parse {}", file.display()))?;
+
+    match explanation {
+        Some(explanation) => {
+            println!(
+                "{}:{}: {}",
+                file.display(),
+                explanation.span,
+                explanation.typ
+            );
+            for step in &explanation.steps {
+                println!("  {}", step);
+            }
+        }
+        None => println!("{}: nothing typed at offset {}", file.display(), offset),
+    }
+    Ok(())
+}
+
+fn explain_code(code: Option<&str>, config: Arc<CompilerConfig>) -> Result<()> {
+    let linter = Linter::for_config(&config);
+    let Some(code) = code else {
+        for explanation in explain::explanations() {
+            println!("{:<20} {}", explanation.code, explanation.title);
+        }
+        for (rule, _) in linter.rules() {
+            println!("{:<20} {}", rule.name(), rule.description());
+        }
+        return Ok(());
+    };
+
+    if let Some(explanation) = explain::explain(code) {
+        println!("{}: {}\n", explanation.code, explanation.title);
+        println!("{}", explanation.text);
+    } else if let Some((rule, level)) = linter.rules().find(|(rule, _)| rule.name() == code) {
+        let level = match level {
+            StrictLevel::Off => "off",
+            StrictLevel::Warning => "warning",
+            StrictLevel::Error => "error",
+        };
+        println!("{}: lint rule\n", rule.name());
+        println!("{}", rule.description());
+        println!(
+            "\nRuns at level {}; `lint.rules` in the configuration sets it.",
+            level
+        );
+    } else {
+        anyhow::bail!(
+            "Unknown code '{}'; `typedlua explain` lists every code",
+            code
+        );
+    }
+    Ok(())
+}
+
+fn type_diff(
+    file: &Path,
+    types: &[String],
+    offset: Option<usize>,
+    config: Arc<CompilerConfig>,
+) -> Result<()> {
+    let (actual, expected) = match (offset, types) {
+        (Some(offset), [expected]) => (DiffSource::AtOffset(offset), expected),
+        (None, [actual, expected]) => (DiffSource::Written(actual), expected),
+        (Some(_), _) => anyhow::bail!("With --offset, give only the expected type"),
+        (None, _) => anyhow::bail!("Give the type to compare and the type expected of it"),
+    };
+    let source = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let handler = Arc::new(DiagnosticBus::with_subscribers(vec![Arc::new(
+        terminal_renderer(file, &source),
+    )]));
+    let diff = diff_types(&source, actual, expected, config, handler)
+        .with_context(|| format!("Failed to parse {}", file.display()))?;
+
+    match diff {
+        Some(diff) => {
+            print!("{}", diff);
+            if !diff.assignable {
+                anyhow::bail!("`{}` is not assignable to `{}`", diff.source, diff.target);
+            }
+        }
+        None => println!(
+            "{}: nothing typed at offset {}",
+            file.display(),
+            offset.unwrap_or(0)
+        ),
+    }
+    Ok(())
+}
+
+fn why(file: &Path, config: Arc<CompilerConfig>) -> Result<()> {
+    let cache_dir = PathBuf::from(&config.compiler_options.cache_dir);
+    let cache = BuildCache::new(&cache_dir, &config, Arc::new(RealFileSystem::new()));
+    // Lua output is traced back to the cached module compiling to it
+    let out_dir = config.compiler_options.out_dir.as_deref();
+    let module = if file.extension().is_some_and(|extension| extension == "lua") {
+        cache
+            .modules()
+            .into_iter()
+            .find(|module| output_path(module, out_dir) == file)
+            .with_context(|| format!("No cached module compiles to {}", file.display()))?
+    } else {
+        file.to_path_buf()
+    };
+    let source = std::fs::read_to_string(&module)
+        .with_context(|| format!("Failed to read {}", module.display()))?;
+
+    let status = cache.status(&module, &source);
+    if status.reasons.is_empty() {
+        println!("{} is up to date", module.display());
+    } else {
+        println!("{} is rebuilt because", module.display());
+        for reason in &status.reasons {
+            println!("  {}", reason);
+        }
+        if status.reasons.contains(&RebuildReason::NotCached) {
+            return Ok(());
+        }
+    }
+    if status.dependencies.is_empty() {
+        println!("It depends on no other files");
+    } else {
+        println!("It depends on");
+        for dependency in &status.dependencies {
+            println!("  {}", dependency.display());
+        }
+    }
+    Ok(())
+}
+
+fn complete(file: &Path, offset: usize) -> Result<()> {
+    let source = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let resolver = ModuleResolver::new(Arc::new(RealFileSystem::new()));
+    let completions = completion::complete(&source, offset, file, &resolver).unwrap_or_default();
+    for completion in completions {
+        println!("{}", completion.label);
+    }
+    Ok(())
+}
+
+/// Run a chunk of Lua, returning what it wrote to stderr if it failed
+fn run_lua(lua: &str) -> Result<Option<String>> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new("lua")
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to start the lua interpreter")?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(lua.as_bytes())?;
+    let output = child.wait_with_output()?;
+    Ok((!output.status.success())
+        .then(|| String::from_utf8_lossy(&output.stderr).trim().to_string()))
+}
+
+fn rename_symbol(
+    file: &Path,
+    offset: usize,
+    new_name: &str,
+    workspace: &[PathBuf],
+    write: bool,
+    config: Arc<CompilerConfig>,
+) -> Result<()> {
+    let file_system = Arc::new(RealFileSystem::new());
+    // Where `build --emit-declarations` writes the module's declarations
+    let declaration_file =
+        output_path(file, config.compiler_options.out_dir.as_deref()).with_extension("d.tl");
+    let edits = rename::rename(
+        file,
+        offset,
+        new_name,
+        Some(&declaration_file),
+        workspace,
+        file_system.clone(),
+    )
+    .with_context(|| format!("Failed to rename in {}", file.display()))?;
+
+    if !write {
+        for edit in &edits {
+            print!("{}", edit.diff());
+        }
+        return Ok(());
+    }
+    for edit in &edits {
+        file_system
+            .write_file(&edit.path, &edit.after)
+            .with_context(|| format!("Failed to write {}", edit.path.display()))?;
+    }
+    eprintln!("Renamed in {} file(s)", edits.len());
+    Ok(())
+}
+
+/// Runs the chunks `typedlua repl` sends in one Lua state. A chunk comes as
+/// a `<mode> <length>` line and its bytes. The values of an `expression`
+/// chunk are sent back; the table of names a `statements` chunk returns is
+/// kept in scope for later chunks. Replies start with a NUL byte, setting
+/// them apart from what the chunk prints.
+const REPL_DRIVER: &str = r##"
+local scope = setmetatable({}, { __index = _G })
+local function pack(...) return { n = select("#", ...), ... } end
+local function reply(text)
+  io.write("\0", (text:gsub("\n", "\\n")), "\n")
+  io.flush()
+end
+while true do
+  local header = io.read("*l")
+  if not header then break end
+  local mode, size = header:match("^(%a+) (%d+)$")
+  local chunk = io.read(tonumber(size))
+  local f, err
+  if setfenv then
+    f, err = loadstring(chunk, "=repl")
+    if f then setfenv(f, scope) end
+  else
+    f, err = load(chunk, "=repl", "t", scope)
+  end
+  local results = f and pack(pcall(f)) or { n = 2, false, err }
+  if not results[1] then
+    reply("!" .. tostring(results[2]))
+  elseif mode == "expression" then
+    local values = {}
+    for i = 2, results.n do
+      values[#values + 1] = tostring(results[i])
+    end
+    reply("=" .. table.concat(values, ", "))
+  else
+    for name, value in pairs(results[2] or {}) do
+      rawset(scope, name, value)
+    end
+    reply(".")
+  end
+end
+"##;
+
+fn repl(config: Arc<CompilerConfig>) -> Result<()> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::process::{Command, Stdio};
+
+    let mut lua = Command::new("lua")
+        .arg("-e")
+        .arg(REPL_DRIVER)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Failed to start the lua interpreter")?;
+    let mut to_lua = lua.stdin.take().expect("stdin is piped");
+    let mut from_lua = BufReader::new(lua.stdout.take().expect("stdout is piped"));
+
+    let mut session = Session::new(config);
+    let mut input = String::new();
+    loop {
+        print!("{}", if input.is_empty() { "> " } else { ". " });
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        if std::io::stdin().lock().read_line(&mut line)? == 0 {
+            println!();
+            break;
+        }
+        input.push_str(line.trim_end_matches(['\r', '\n']));
+        if input.trim().is_empty() {
+            input.clear();
+            continue;
+        }
+
+        match session.evaluate(&input) {
+            Ok(Evaluation::Incomplete) => {
+                input.push('\n');
+                continue;
+            }
+            Ok(Evaluation::Statements(chunk)) => {
+                run_chunk(&mut to_lua, &mut from_lua, "statements", &chunk)?;
+            }
+            Ok(Evaluation::Expression { lua, typ }) => {
+                if let Some(values) = run_chunk(&mut to_lua, &mut from_lua, "expression", &lua)? {
+                    if values.is_empty() {
+                        println!("{}", typ);
+                    } else {
+                        println!("{}: {}", values, typ);
+                    }
+                }
+            }
+            Err(errors) => {
+                for error in &errors {
+                    report_diagnostic(Path::new("<repl>"), Some(&input), error);
+                }
+            }
+        }
+        input.clear();
+    }
+
+    drop(to_lua);
+    lua.wait()?;
+    Ok(())
+}
+
+/// Send a chunk to the REPL's Lua state and wait for the reply, echoing
+/// what the chunk prints. Returns the values of an expression chunk, or
+/// `None` when it raised an error.
+fn run_chunk(
+    to_lua: &mut impl std::io::Write,
+    from_lua: &mut impl std::io::BufRead,
+    mode: &str,
+    chunk: &str,
+) -> Result<Option<String>> {
+    write!(to_lua, "{} {}\n{}", mode, chunk.len(), chunk)?;
+    to_lua.flush()?;
+    loop {
+        let mut line = String::new();
+        if from_lua.read_line(&mut line)? == 0 {
+            anyhow::bail!("The lua interpreter exited");
+        }
+        let Some(at) = line.find('\0') else {
+            print!("{}", line);
+            continue;
+        };
+        print!("{}", &line[..at]);
+        let reply = line[at + 1..].trim_end_matches('\n');
+        return Ok(match reply.split_at(1) {
+            ("!", error) => {
+                eprintln!("error: {}", error);
+                None
+            }
+            (_, values) => Some(values.to_string()),
+        });
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// The source of a module as named from its map: by file name when the
+/// Lua sits next to it, otherwise by absolute path
+fn map_source(file: &Path, output: &Path) -> String {
+    if file.parent() == output.parent() {
+        return file_name(file);
+    }
+    std::fs::canonicalize(file)
+        .unwrap_or_else(|_| file.to_path_buf())
+        .display()
+        .to_string()
+}
+
+/// Where a module's Lua goes: next to it, or in `outDir` under the same
+/// relative path. A data module `name.data.tl` becomes `name.lua`.
+fn output_path(file: &Path, out_dir: Option<&str>) -> PathBuf {
+    let lua = if is_data_module(file) {
+        file.with_extension("").with_extension("lua")
+    } else {
+        file.with_extension("lua")
+    };
+    match out_dir {
+        Some(dir) => Path::new(dir).join(lua.strip_prefix("/").unwrap_or(&lua)),
+        None => lua,
+    }
 }
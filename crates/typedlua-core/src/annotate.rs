@@ -0,0 +1,166 @@
+//! Writing the checker's inferred types back into source as explicit
+//! annotations (`typedlua annotate`)
+
+use crate::build::parse_module;
+use crate::config::CompilerConfig;
+use crate::diagnostics::DiagnosticHandler;
+use crate::errors::CompilationError;
+use crate::typechecker::{AnnotationTarget, InferredAnnotation, TypeChecker};
+use std::sync::Arc;
+
+/// Text to insert at a byte offset of the original source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub offset: usize,
+    pub text: String,
+}
+
+/// Check a source file and return it with every inferred local and return
+/// type written out. Sources with errors are left alone, since their
+/// inferred types cannot be trusted.
+pub fn annotate_source(
+    source: &str,
+    config: Arc<CompilerConfig>,
+    diagnostic_handler: Arc<dyn DiagnosticHandler>,
+) -> Result<String, CompilationError> {
+    let parsed = parse_module(source, diagnostic_handler.clone())?;
+
+    let mut checker = TypeChecker::new(config, diagnostic_handler);
+    checker.set_directives(&parsed.directives);
+    checker.check_program(&parsed.program)?;
+
+    let edits = annotation_edits(source, checker.inferred_annotations());
+    Ok(apply_edits(source, &edits))
+}
+
+/// Insertions that turn inferred types into written annotations
+pub fn annotation_edits(source: &str, annotations: &[InferredAnnotation]) -> Vec<TextEdit> {
+    // Spans count characters; edits are applied to bytes
+    let byte_offsets: Vec<usize> = source
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(source.len()))
+        .collect();
+    let to_byte = |char_offset: usize| byte_offsets.get(char_offset).copied();
+
+    let mut edits: Vec<TextEdit> = annotations
+        .iter()
+        .filter_map(|annotation| {
+            let text = format!(": {}", annotation.typ);
+            let offset = match &annotation.target {
+                AnnotationTarget::Variable(name) => to_byte(name.span.end)?,
+                // The last parameter's span runs through the closing paren
+                AnnotationTarget::ReturnType(func) => match func.parameters.last() {
+                    Some(param) => to_byte(param.span.end)?,
+                    None => {
+                        let search_from = to_byte(func.name.span.end)?;
+                        search_from + source[search_from..].find(')')? + 1
+                    }
+                },
+            };
+            Some(TextEdit { offset, text })
+        })
+        .collect();
+
+    edits.sort_by_key(|edit| edit.offset);
+    edits
+}
+
+/// Apply insertions sorted by offset
+pub fn apply_edits(source: &str, edits: &[TextEdit]) -> String {
+    let mut result = String::with_capacity(source.len());
+    let mut copied = 0;
+    for edit in edits {
+        result.push_str(&source[copied..edit.offset]);
+        result.push_str(&edit.text);
+        copied = edit.offset;
+    }
+    result.push_str(&source[copied..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::CollectingDiagnosticHandler;
+
+    fn annotate(source: &str) -> String {
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        annotate_source(source, Arc::new(CompilerConfig::default()), handler)
+            .expect("Annotation failed")
+    }
+
+    #[test]
+    fn test_annotates_locals_and_return_types() {
+        let source = r#"
+const name = "lua"
+local count = 1 + 2
+local nothing = nil
+const annotated: number = 3
+
+function greet(who: string, times: number)
+    const message = "hi " .. who
+    return message
+end
+
+function maybe(flag: boolean)
+    if flag then
+        return 1
+    end
+    return nil
+end
+
+function log(message: string)
+    print(message)
+end
+
+function one()
+    return 1
+end
+"#;
+        let expected = r#"
+const name: string = "lua"
+local count: number = 1 + 2
+local nothing = nil
+const annotated: number = 3
+
+function greet(who: string, times: number): string
+    const message: string = "hi " .. who
+    return message
+end
+
+function maybe(flag: boolean): integer?
+    if flag then
+        return 1
+    end
+    return nil
+end
+
+function log(message: string): void
+    print(message)
+end
+
+function one(): integer
+    return 1
+end
+"#;
+        assert_eq!(annotate(source), expected);
+    }
+
+    #[test]
+    fn test_unknown_types_are_left_alone() {
+        let source = "const value = fetch()\nfunction get()\n    return fetch()\nend\n";
+        assert_eq!(annotate(source), source);
+    }
+
+    #[test]
+    fn test_sources_with_type_errors_are_rejected() {
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let result = annotate_source(
+            "const x: number = \"text\"",
+            Arc::new(CompilerConfig::default()),
+            handler,
+        );
+        assert!(matches!(result, Err(CompilationError::TypeErrors(1))));
+    }
+}
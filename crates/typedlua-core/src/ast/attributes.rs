@@ -0,0 +1,142 @@
+//! Attributes the compiler itself reads from a declaration's decorators,
+//! such as `@deprecated("use foo")`, `@inline`, `@no_emit`, `@weak("k")`
+//! and `@schema(version = 3)`.
+//! Other decorators are left to the program.
+
+use super::expression::{AssignmentOp, Expression, ExpressionKind, Literal};
+use super::pattern::Pattern;
+use super::statement::{
+    Decorator, DecoratorExpression, ExportKind, FunctionDeclaration, Statement,
+};
+use crate::span::Span;
+
+/// The decorator named `name`, written bare (`@inline`) or called
+/// (`@deprecated("...")`)
+pub fn find_attribute<'a>(decorators: &'a [Decorator], name: &str) -> Option<&'a Decorator> {
+    decorators.iter().find(|decorator| {
+        let callee = match &decorator.expression {
+            DecoratorExpression::Call { callee, .. } => callee.as_ref(),
+            expression => expression,
+        };
+        matches!(callee, DecoratorExpression::Identifier(ident) if ident.node == name)
+    })
+}
+
+pub fn has_attribute(decorators: &[Decorator], name: &str) -> bool {
+    find_attribute(decorators, name).is_some()
+}
+
+/// Arguments of a called attribute; none for a bare one
+pub fn attribute_arguments(decorator: &Decorator) -> &[Expression] {
+    match &decorator.expression {
+        DecoratorExpression::Call { arguments, .. } => arguments,
+        _ => &[],
+    }
+}
+
+/// The message of a `@deprecated` attribute, empty when it gives none.
+/// A message that is not a string literal gives its span instead.
+pub fn deprecation(decorators: &[Decorator]) -> Option<Result<&str, Span>> {
+    let decorator = find_attribute(decorators, "deprecated")?;
+    Some(match attribute_arguments(decorator) {
+        [] => Ok(""),
+        [argument] => string_argument(argument).ok_or(argument.span),
+        _ => Err(decorator.span),
+    })
+}
+
+/// The mode of a `@weak("k")` attribute: which side of a table the garbage
+/// collector may clear, `"k"`, `"v"` or `"kv"`. A malformed attribute gives
+/// its span instead.
+pub fn weak_mode(decorators: &[Decorator]) -> Option<Result<&str, Span>> {
+    let decorator = find_attribute(decorators, "weak")?;
+    Some(match attribute_arguments(decorator) {
+        [argument] => string_argument(argument)
+            .filter(|mode| matches!(*mode, "k" | "v" | "kv"))
+            .ok_or(decorator.span),
+        _ => Err(decorator.span),
+    })
+}
+
+/// The version of a `@schema(version = 3)` record, which counts up from 1.
+/// A malformed attribute gives its span instead.
+pub fn schema_version(decorators: &[Decorator]) -> Option<Result<i64, Span>> {
+    let decorator = find_attribute(decorators, "schema")?;
+    Some(match attribute_arguments(decorator) {
+        [argument] => match &argument.kind {
+            ExpressionKind::Assignment(name, AssignmentOp::Assign, value)
+                if matches!(&name.kind, ExpressionKind::Identifier(name) if name == "version") =>
+            {
+                integer_argument(value)
+                    .filter(|version| *version >= 1)
+                    .ok_or(decorator.span)
+            }
+            _ => Err(decorator.span),
+        },
+        _ => Err(decorator.span),
+    })
+}
+
+/// The record and version a `@migration(SaveData, 1)` function upgrades
+/// saved data from, to the next version. A malformed attribute gives its
+/// span instead.
+pub fn migration_step(decorators: &[Decorator]) -> Option<Result<(&str, i64), Span>> {
+    let decorator = find_attribute(decorators, "migration")?;
+    Some(match attribute_arguments(decorator) {
+        [schema, from] => match (&schema.kind, integer_argument(from)) {
+            (ExpressionKind::Identifier(schema), Some(from)) if from >= 1 => Ok((schema, from)),
+            _ => Err(decorator.span),
+        },
+        _ => Err(decorator.span),
+    })
+}
+
+/// The expression an `@inline` function stands for: its body must be a
+/// single `return` of one value, and its parameters plain names
+pub fn inline_body(func: &FunctionDeclaration) -> Option<&Expression> {
+    let simple_parameters = func.parameters.iter().all(|param| {
+        !param.is_rest && param.default.is_none() && matches!(param.pattern, Pattern::Identifier(_))
+    });
+    match func.body.statements.as_slice() {
+        [Statement::Return(ret)] if simple_parameters => match ret.values.as_slice() {
+            [value] => Some(value),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The decorators written on a declaration, looking through `export`
+pub fn statement_decorators(statement: &Statement) -> &[Decorator] {
+    match statement {
+        Statement::Variable(decl) => &decl.decorators,
+        Statement::Function(func) => &func.decorators,
+        Statement::Class(class) => &class.decorators,
+        Statement::Interface(interface) => &interface.decorators,
+        Statement::TypeAlias(alias) => &alias.decorators,
+        Statement::Enum(decl) => &decl.decorators,
+        Statement::Declare(decl) => &decl.decorators,
+        Statement::Export(export) => match &export.kind {
+            ExportKind::Declaration(decl) => statement_decorators(decl),
+            _ => &[],
+        },
+        _ => &[],
+    }
+}
+
+fn integer_argument(argument: &Expression) -> Option<i64> {
+    match &argument.kind {
+        ExpressionKind::Literal(Literal::Integer(value)) => Some(*value),
+        ExpressionKind::Literal(Literal::Number(value)) if value.fract() == 0.0 => {
+            Some(*value as i64)
+        }
+        _ => None,
+    }
+}
+
+fn string_argument(argument: &Expression) -> Option<&str> {
+    match &argument.kind {
+        ExpressionKind::Literal(Literal::String(value)) => Some(value),
+        _ => None,
+    }
+}
@@ -2,8 +2,9 @@ use super::{pattern::Pattern, statement::TypeParameter, types::Type, Ident};
 use crate::span::Span;
 
 use super::statement::{Block, Parameter};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Expression {
     pub kind: ExpressionKind,
     pub span: Span,
@@ -15,7 +16,7 @@ impl Expression {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ExpressionKind {
     Identifier(String),
     Literal(Literal),
@@ -23,6 +24,8 @@ pub enum ExpressionKind {
     Unary(UnaryOp, Box<Expression>),
     Assignment(Box<Expression>, AssignmentOp, Box<Expression>),
     Member(Box<Expression>, Ident),
+    /// `object?.member`: nil when the object is nil
+    OptionalMember(Box<Expression>, Ident),
     Index(Box<Expression>, Box<Expression>),
     Call(Box<Expression>, Vec<Argument>),
     MethodCall(Box<Expression>, Ident, Vec<Argument>),
@@ -40,16 +43,47 @@ pub enum ExpressionKind {
     TypeAssertion(Box<Expression>, Type),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Literal {
     Nil,
     Boolean(bool),
     Number(f64),
     Integer(i64),
+    /// A LuaJIT 64-bit integer (`42LL`, `42ULL`); unsigned values keep
+    /// their bits in the `i64`
+    SuffixedInteger(i64, IntegerSuffix),
+    /// A hexadecimal or binary integer past `i64::MAX`. Lua 5.3+ wraps it
+    /// around to the `i64`; earlier versions read it as the `f64`.
+    WrappedInteger(i64, f64),
     String(String),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IntegerSuffix {
+    /// `LL`, an `int64_t` cdata
+    Signed,
+    /// `ULL`, a `uint64_t` cdata
+    Unsigned,
+}
+
+impl IntegerSuffix {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IntegerSuffix::Signed => "LL",
+            IntegerSuffix::Unsigned => "ULL",
+        }
+    }
+}
+
+/// Writes a suffixed integer as it appears in source, such as `42ULL`
+pub fn format_suffixed_integer(value: i64, suffix: IntegerSuffix) -> String {
+    match suffix {
+        IntegerSuffix::Signed => format!("{}LL", value),
+        IntegerSuffix::Unsigned => format!("{}ULL", value as u64),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BinaryOp {
     Add,
     Subtract,
@@ -74,7 +108,7 @@ pub enum BinaryOp {
     ShiftRight,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum UnaryOp {
     Not,
     Negate,
@@ -82,7 +116,7 @@ pub enum UnaryOp {
     BitwiseNot,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AssignmentOp {
     Assign,
     AddAssign,
@@ -93,20 +127,20 @@ pub enum AssignmentOp {
     ConcatenateAssign,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Argument {
     pub value: Expression,
     pub is_spread: bool,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ArrayElement {
     Expression(Expression),
     Spread(Expression),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ObjectProperty {
     Property {
         key: Ident,
@@ -124,7 +158,25 @@ pub enum ObjectProperty {
     },
 }
 
-#[derive(Debug, Clone)]
+impl ArrayElement {
+    pub fn span(&self) -> Span {
+        match self {
+            ArrayElement::Expression(value) | ArrayElement::Spread(value) => value.span,
+        }
+    }
+}
+
+impl ObjectProperty {
+    pub fn span(&self) -> Span {
+        match self {
+            ObjectProperty::Property { span, .. }
+            | ObjectProperty::Computed { span, .. }
+            | ObjectProperty::Spread { span, .. } => *span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FunctionExpression {
     pub type_parameters: Option<Vec<TypeParameter>>,
     pub parameters: Vec<Parameter>,
@@ -133,7 +185,7 @@ pub struct FunctionExpression {
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArrowFunction {
     pub parameters: Vec<Parameter>,
     pub return_type: Option<Type>,
@@ -141,20 +193,20 @@ pub struct ArrowFunction {
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ArrowBody {
     Expression(Box<Expression>),
     Block(Block),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MatchExpression {
     pub value: Box<Expression>,
     pub arms: Vec<MatchArm>,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MatchArm {
     pub pattern: Pattern,
     pub guard: Option<Expression>,
@@ -162,20 +214,22 @@ pub struct MatchArm {
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum MatchArmBody {
     Expression(Expression),
     Block(Block),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TemplateLiteral {
     pub parts: Vec<TemplatePart>,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TemplatePart {
     String(String),
     Expression(Expression),
+    /// `${value:%.2f}`: an expression with a `string.format` specifier
+    Formatted(Expression, String),
 }
@@ -1,12 +1,15 @@
+pub mod attributes;
 pub mod expression;
 pub mod pattern;
 pub mod statement;
 pub mod types;
+pub mod visit;
 
 use crate::span::Span;
+use serde::{Deserialize, Serialize};
 
 /// Wrapper for AST nodes with span information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Spanned<T> {
     pub node: T,
     pub span: Span,
@@ -22,7 +25,7 @@ impl<T> Spanned<T> {
 pub type Ident = Spanned<String>;
 
 /// Top-level program
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Program {
     pub statements: Vec<statement::Statement>,
     pub span: Span,
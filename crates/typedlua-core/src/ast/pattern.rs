@@ -1,35 +1,67 @@
 use super::{expression::Expression, expression::Literal, Ident};
 use crate::span::Span;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Pattern {
     Identifier(Ident),
     Literal(Literal, Span),
     Array(ArrayPattern),
     Object(ObjectPattern),
     Wildcard(Span),
+    /// `a, b, ...rest`: the values of a multiple-return expression, bound
+    /// in order. Only a variable declaration takes one.
+    Multiple(MultiplePattern),
 }
 
-#[derive(Debug, Clone)]
+impl Pattern {
+    pub fn span(&self) -> Span {
+        match self {
+            Pattern::Identifier(name) => name.span,
+            Pattern::Literal(_, span) | Pattern::Wildcard(span) => *span,
+            Pattern::Array(array) => array.span,
+            Pattern::Object(object) => object.span,
+            Pattern::Multiple(multiple) => multiple.span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiplePattern {
+    pub names: Vec<Ident>,
+    pub rest: Option<MultipleRest>,
+    pub span: Span,
+}
+
+/// What a trailing `...` does with the values past the named ones
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MultipleRest {
+    /// `...`: they are dropped
+    Discard(Span),
+    /// `...name`: they are collected in a table
+    Capture(Ident),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArrayPattern {
     pub elements: Vec<ArrayPatternElement>,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ArrayPatternElement {
     Pattern(Pattern),
     Rest(Ident),
     Hole,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ObjectPattern {
     pub properties: Vec<ObjectPatternProperty>,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ObjectPatternProperty {
     pub key: Ident,
     pub value: Option<Pattern>,
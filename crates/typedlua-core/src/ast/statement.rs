@@ -1,7 +1,8 @@
 use super::{expression::Expression, pattern::Pattern, types::Type, Ident};
 use crate::span::Span;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Statement {
     Variable(VariableDeclaration),
     Function(FunctionDeclaration),
@@ -11,19 +12,58 @@ pub enum Statement {
     Enum(EnumDeclaration),
     Import(ImportDeclaration),
     Export(ExportDeclaration),
+    Declare(DeclareDeclaration),
+    Namespace(NamespaceDeclaration),
     If(IfStatement),
     While(WhileStatement),
     For(ForStatement),
     Repeat(RepeatStatement),
+    /// `name: while ... end`, a loop that `break name` and `continue name`
+    /// can target from nested loops
+    Labeled(LabeledLoop),
     Return(ReturnStatement),
-    Break(Span),
-    Continue(Span),
+    Break(LoopJump),
+    Continue(LoopJump),
     Expression(Expression),
     Block(Block),
-}
-
-#[derive(Debug, Clone)]
+    /// `untyped do ... end` (or `trust do ... end`): a block whose type
+    /// errors are suppressed, for fencing off legacy code during migration
+    Untyped(Block),
+    /// A statement with a syntax error. The parser has reported it and
+    /// resumed at the next statement, so the rest of the file still checks.
+    Error(Span),
+}
+
+impl Statement {
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Variable(v) => v.span,
+            Statement::Function(f) => f.span,
+            Statement::Class(c) => c.span,
+            Statement::Interface(i) => i.span,
+            Statement::TypeAlias(t) => t.span,
+            Statement::Enum(e) => e.span,
+            Statement::Import(i) => i.span,
+            Statement::Export(e) => e.span,
+            Statement::Declare(d) => d.span,
+            Statement::Namespace(n) => n.span,
+            Statement::If(i) => i.span,
+            Statement::While(w) => w.span,
+            Statement::For(f) => f.span(),
+            Statement::Repeat(r) => r.span,
+            Statement::Return(r) => r.span,
+            Statement::Labeled(l) => l.span,
+            Statement::Break(j) | Statement::Continue(j) => j.span,
+            Statement::Expression(e) => e.span,
+            Statement::Block(b) | Statement::Untyped(b) => b.span,
+            Statement::Error(span) => *span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VariableDeclaration {
+    pub decorators: Vec<Decorator>,
     pub kind: VariableKind,
     pub pattern: Pattern,
     pub type_annotation: Option<Type>,
@@ -31,14 +71,18 @@ pub struct VariableDeclaration {
     pub span: Span,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum VariableKind {
     Const,
     Local,
+    /// `using name = value`: a constant closed when its block is left,
+    /// through its `__close` metamethod or its `close` method
+    Using,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FunctionDeclaration {
+    pub decorators: Vec<Decorator>,
     pub name: Ident,
     pub type_parameters: Option<Vec<TypeParameter>>,
     pub parameters: Vec<Parameter>,
@@ -47,53 +91,89 @@ pub struct FunctionDeclaration {
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClassDeclaration {
     pub decorators: Vec<Decorator>,
     pub is_abstract: bool,
     pub name: Ident,
     pub type_parameters: Option<Vec<TypeParameter>>,
     pub extends: Option<Type>,
+    /// `extends mixin(A, B)`: classes whose members are copied in, earlier
+    /// mixins taking precedence
+    pub mixins: Vec<Type>,
     pub implements: Vec<Type>,
     pub members: Vec<ClassMember>,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ClassMember {
     Property(PropertyDeclaration),
     Constructor(ConstructorDeclaration),
     Method(MethodDeclaration),
     Getter(GetterDeclaration),
     Setter(SetterDeclaration),
-}
-
-#[derive(Debug, Clone)]
+    StaticBlock(StaticBlock),
+}
+
+impl ClassMember {
+    pub fn span(&self) -> Span {
+        match self {
+            ClassMember::Property(prop) => prop.span,
+            ClassMember::Constructor(constructor) => constructor.span,
+            ClassMember::Method(method) => method.span,
+            ClassMember::Getter(getter) => getter.span,
+            ClassMember::Setter(setter) => setter.span,
+            ClassMember::StaticBlock(block) => block.span,
+        }
+    }
+
+    /// Whether the member belongs to the class table rather than instances
+    pub fn is_static(&self) -> bool {
+        match self {
+            ClassMember::Property(prop) => prop.is_static,
+            ClassMember::Method(method) => method.is_static,
+            ClassMember::Getter(getter) => getter.is_static,
+            ClassMember::Setter(setter) => setter.is_static,
+            ClassMember::Constructor(_) => false,
+            ClassMember::StaticBlock(_) => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PropertyDeclaration {
     pub decorators: Vec<Decorator>,
     pub access: Option<AccessModifier>,
     pub is_static: bool,
     pub is_readonly: bool,
+    /// Declared with `const`: a static, read-only value whose type can be
+    /// named as `Class.NAME`
+    pub is_const: bool,
     pub name: Ident,
     pub type_annotation: Type,
     pub initializer: Option<Expression>,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstructorDeclaration {
     pub decorators: Vec<Decorator>,
     pub parameters: Vec<Parameter>,
-    pub body: Block,
+    /// Missing from the signature-only classes of declaration files
+    pub body: Option<Block>,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MethodDeclaration {
     pub decorators: Vec<Decorator>,
     pub access: Option<AccessModifier>,
     pub is_static: bool,
     pub is_abstract: bool,
+    /// Declared with `override`, claiming to replace a base class or
+    /// interface method
+    pub is_override: bool,
     pub name: Ident,
     pub type_parameters: Option<Vec<TypeParameter>>,
     pub parameters: Vec<Parameter>,
@@ -102,7 +182,20 @@ pub struct MethodDeclaration {
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+impl MethodDeclaration {
+    /// Parameters after an explicitly written leading `self`, which names
+    /// the receiver rather than declaring an argument
+    pub fn parameters_without_self(&self) -> &[Parameter] {
+        match self.parameters.first().map(|p| &p.pattern) {
+            Some(Pattern::Identifier(name)) if name.node == "self" && !self.is_static => {
+                &self.parameters[1..]
+            }
+            _ => &self.parameters,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GetterDeclaration {
     pub decorators: Vec<Decorator>,
     pub access: Option<AccessModifier>,
@@ -113,7 +206,7 @@ pub struct GetterDeclaration {
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SetterDeclaration {
     pub decorators: Vec<Decorator>,
     pub access: Option<AccessModifier>,
@@ -124,15 +217,24 @@ pub struct SetterDeclaration {
     pub span: Span,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// `static do ... end`: statements run once when the class is defined,
+/// after its methods and in order with its static properties
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaticBlock {
+    pub body: Block,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AccessModifier {
     Public,
     Private,
     Protected,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InterfaceDeclaration {
+    pub decorators: Vec<Decorator>,
     pub name: Ident,
     pub type_parameters: Option<Vec<TypeParameter>>,
     pub extends: Vec<Type>,
@@ -140,15 +242,26 @@ pub struct InterfaceDeclaration {
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum InterfaceMember {
     Property(PropertySignature),
     Method(MethodSignature),
     Index(IndexSignature),
 }
 
-#[derive(Debug, Clone)]
+impl InterfaceMember {
+    pub fn span(&self) -> Span {
+        match self {
+            InterfaceMember::Property(prop) => prop.span,
+            InterfaceMember::Method(method) => method.span,
+            InterfaceMember::Index(index) => index.span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PropertySignature {
+    pub decorators: Vec<Decorator>,
     pub is_readonly: bool,
     pub name: Ident,
     pub is_optional: bool,
@@ -156,8 +269,9 @@ pub struct PropertySignature {
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MethodSignature {
+    pub decorators: Vec<Decorator>,
     pub name: Ident,
     pub type_parameters: Option<Vec<TypeParameter>>,
     pub parameters: Vec<Parameter>,
@@ -165,7 +279,7 @@ pub struct MethodSignature {
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexSignature {
     pub key_name: Ident,
     pub key_type: IndexKeyType,
@@ -173,48 +287,57 @@ pub struct IndexSignature {
     pub span: Span,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum IndexKeyType {
     String,
     Number,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TypeAliasDeclaration {
+    pub decorators: Vec<Decorator>,
     pub name: Ident,
     pub type_parameters: Option<Vec<TypeParameter>>,
     pub type_annotation: Type,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnumDeclaration {
+    pub decorators: Vec<Decorator>,
     pub name: Ident,
     pub members: Vec<EnumMember>,
+    /// Members are inlined at each use and no runtime table is emitted
+    pub is_const: bool,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+/// Functions every enum provides besides its members
+pub const ENUM_HELPERS: &[&str] = &["values", "fromName", "ordinal"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnumMember {
     pub name: Ident,
     pub value: Option<EnumValue>,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum EnumValue {
     Number(f64),
     String(String),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImportDeclaration {
     pub clause: ImportClause,
     pub source: String,
+    /// `import lazy ...`: the module is loaded on first use, not at import
+    pub is_lazy: bool,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ImportClause {
     Default(Ident),
     Named(Vec<ImportSpecifier>),
@@ -222,34 +345,77 @@ pub enum ImportClause {
     TypeOnly(Vec<ImportSpecifier>),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImportSpecifier {
     pub imported: Ident,
     pub local: Option<Ident>,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExportDeclaration {
     pub kind: ExportKind,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ExportKind {
     Declaration(Box<Statement>),
     Named(Vec<ExportSpecifier>),
     Default(Expression),
+    /// `export = value`: the module evaluates to this single value
+    Equals(Expression),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExportSpecifier {
     pub local: Ident,
     pub exported: Option<Ident>,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+/// Ambient declaration (`declare function ...`, `declare const ...`) describing
+/// a value that exists at runtime but is implemented outside TypedLua
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeclareDeclaration {
+    pub decorators: Vec<Decorator>,
+    pub kind: DeclareKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DeclareKind {
+    Function(DeclareFunction),
+    Variable(DeclareVariable),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeclareFunction {
+    pub name: Ident,
+    pub type_parameters: Option<Vec<TypeParameter>>,
+    pub parameters: Vec<Parameter>,
+    pub return_type: Type,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeclareVariable {
+    pub kind: VariableKind,
+    pub name: Ident,
+    pub type_annotation: Type,
+    pub span: Span,
+}
+
+/// `namespace Name { ... }`: groups declarations under one table. Only
+/// exported members are visible outside the block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamespaceDeclaration {
+    pub name: Ident,
+    pub body: Block,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IfStatement {
     pub condition: Expression,
     pub then_block: Block,
@@ -258,34 +424,58 @@ pub struct IfStatement {
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ElseIf {
     pub condition: Expression,
     pub block: Block,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WhileStatement {
     pub condition: Expression,
     pub body: Block,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RepeatStatement {
     pub body: Block,
     pub until: Expression,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabeledLoop {
+    pub label: Ident,
+    /// A `while`, `for` or `repeat` statement
+    pub body: Box<Statement>,
+    pub span: Span,
+}
+
+/// `break` or `continue`, optionally naming the enclosing loop it leaves
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoopJump {
+    pub label: Option<Ident>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ForStatement {
     Numeric(ForNumeric),
     Generic(ForGeneric),
 }
 
-#[derive(Debug, Clone)]
+impl ForStatement {
+    pub fn span(&self) -> Span {
+        match self {
+            ForStatement::Numeric(numeric) => numeric.span,
+            ForStatement::Generic(generic) => generic.span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ForNumeric {
     pub variable: Ident,
     pub start: Expression,
@@ -295,7 +485,7 @@ pub struct ForNumeric {
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ForGeneric {
     pub variables: Vec<Ident>,
     pub iterators: Vec<Expression>,
@@ -303,19 +493,19 @@ pub struct ForGeneric {
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReturnStatement {
     pub values: Vec<Expression>,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Block {
     pub statements: Vec<Statement>,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TypeParameter {
     pub name: Ident,
     pub constraint: Option<Box<Type>>,
@@ -323,7 +513,7 @@ pub struct TypeParameter {
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Parameter {
     pub pattern: Pattern,
     pub type_annotation: Option<Type>,
@@ -332,13 +522,13 @@ pub struct Parameter {
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Decorator {
     pub expression: DecoratorExpression,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DecoratorExpression {
     Identifier(Ident),
     Call {
@@ -352,3 +542,14 @@ pub enum DecoratorExpression {
         span: Span,
     },
 }
+
+impl DecoratorExpression {
+    pub fn span(&self) -> Span {
+        match self {
+            DecoratorExpression::Identifier(name) => name.span,
+            DecoratorExpression::Call { span, .. } | DecoratorExpression::Member { span, .. } => {
+                *span
+            }
+        }
+    }
+}
@@ -1,11 +1,13 @@
 use super::{
-    expression::Expression, expression::Literal,
+    expression::format_suffixed_integer, expression::Expression, expression::ExpressionKind,
+    expression::Literal,
     statement::{IndexSignature, MethodSignature, Parameter, PropertySignature, TypeParameter},
     Ident,
 };
 use crate::span::Span;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Type {
     pub kind: TypeKind,
     pub span: Span,
@@ -17,7 +19,7 @@ impl Type {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TypeKind {
     Primitive(PrimitiveType),
     Reference(TypeReference),
@@ -36,9 +38,13 @@ pub enum TypeKind {
     TemplateLiteral(TemplateLiteralType),
     Nullable(Box<Type>),
     Parenthesized(Box<Type>),
+    Asserts(AssertsPredicate),
+    /// `T...`: any number of values of type `T`, last in a list of
+    /// return values
+    Variadic(Box<Type>),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PrimitiveType {
     Nil,
     Boolean,
@@ -52,34 +58,53 @@ pub enum PrimitiveType {
     Coroutine,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TypeReference {
     pub name: Ident,
     pub type_arguments: Option<Vec<Type>>,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ObjectType {
     pub members: Vec<ObjectTypeMember>,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ObjectTypeMember {
     Property(PropertySignature),
     Method(MethodSignature),
     Index(IndexSignature),
 }
 
-#[derive(Debug, Clone)]
+impl ObjectTypeMember {
+    pub fn span(&self) -> Span {
+        match self {
+            ObjectTypeMember::Property(prop) => prop.span,
+            ObjectTypeMember::Method(method) => method.span,
+            ObjectTypeMember::Index(index) => index.span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FunctionType {
     pub parameters: Vec<Parameter>,
     pub return_type: Box<Type>,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+/// Return type of an assertion function: `asserts x` or `asserts x is T`.
+/// A call that returns normally proves the predicate for its argument.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssertsPredicate {
+    pub parameter: Ident,
+    pub type_predicate: Option<Box<Type>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConditionalType {
     pub check_type: Box<Type>,
     pub extends_type: Box<Type>,
@@ -88,7 +113,7 @@ pub struct ConditionalType {
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MappedType {
     pub is_readonly: bool,
     pub type_parameter: Box<TypeParameter>,
@@ -98,14 +123,217 @@ pub struct MappedType {
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TemplateLiteralType {
     pub parts: Vec<TemplateLiteralTypePart>,
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TemplateLiteralTypePart {
     String(String),
     Type(Type),
 }
+
+impl std::fmt::Display for PrimitiveType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            PrimitiveType::Nil => "nil",
+            PrimitiveType::Boolean => "boolean",
+            PrimitiveType::Number => "number",
+            PrimitiveType::Integer => "integer",
+            PrimitiveType::String => "string",
+            PrimitiveType::Unknown => "unknown",
+            PrimitiveType::Never => "never",
+            PrimitiveType::Void => "void",
+            PrimitiveType::Table => "table",
+            PrimitiveType::Coroutine => "coroutine",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Formats a type the way it would be written in source
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            TypeKind::Primitive(primitive) => write!(f, "{}", primitive),
+            TypeKind::Reference(reference) => {
+                write!(f, "{}", reference.name.node)?;
+                if let Some(args) = &reference.type_arguments {
+                    write!(f, "<{}>", join(args, ", "))?;
+                }
+                Ok(())
+            }
+            TypeKind::Union(types) => write!(f, "{}", join_members(types, " | ")),
+            TypeKind::Intersection(types) => write!(f, "{}", join_members(types, " & ")),
+            TypeKind::Object(object) => {
+                if object.members.is_empty() {
+                    return write!(f, "{{}}");
+                }
+                let members: Vec<String> = object
+                    .members
+                    .iter()
+                    .map(|member| match member {
+                        ObjectTypeMember::Property(prop) => format_property(prop),
+                        ObjectTypeMember::Method(method) => format_method(method),
+                        ObjectTypeMember::Index(index) => format_index(index),
+                    })
+                    .collect();
+                write!(f, "{{ {} }}", members.join(", "))
+            }
+            TypeKind::Array(element) => match element.kind {
+                TypeKind::Union(_) | TypeKind::Intersection(_) | TypeKind::Function(_) => {
+                    write!(f, "({})[]", element)
+                }
+                _ => write!(f, "{}[]", element),
+            },
+            TypeKind::Tuple(types) => write!(f, "[{}]", join(types, ", ")),
+            TypeKind::Function(func) => {
+                write!(f, "({}) -> ", format_parameters(&func.parameters))?;
+                match &func.return_type.kind {
+                    // Several return values
+                    TypeKind::Tuple(types) => write!(f, "({})", join(types, ", ")),
+                    _ => write!(f, "{}", func.return_type),
+                }
+            }
+            TypeKind::Literal(literal) => match literal {
+                Literal::Nil => write!(f, "nil"),
+                Literal::Boolean(b) => write!(f, "{}", b),
+                Literal::Number(n) => write!(f, "{}", n),
+                Literal::Integer(i) | Literal::WrappedInteger(i, _) => write!(f, "{}", i),
+                Literal::SuffixedInteger(value, suffix) => {
+                    write!(f, "{}", format_suffixed_integer(*value, *suffix))
+                }
+                Literal::String(s) => write!(f, "\"{}\"", s),
+            },
+            TypeKind::TypeQuery(expr) => match entity_name(expr) {
+                Some(name) => write!(f, "typeof {}", name),
+                None => write!(f, "typeof <expression>"),
+            },
+            TypeKind::KeyOf(inner) => write!(f, "keyof {}", inner),
+            TypeKind::IndexAccess(object, index) => write!(f, "{}[{}]", object, index),
+            TypeKind::Conditional(cond) => write!(
+                f,
+                "{} extends {} ? {} : {}",
+                cond.check_type, cond.extends_type, cond.true_type, cond.false_type
+            ),
+            TypeKind::Mapped(mapped) => write!(
+                f,
+                "{{ {}[{} in {}]{}: {} }}",
+                if mapped.is_readonly { "readonly " } else { "" },
+                mapped.type_parameter.name.node,
+                mapped.in_type,
+                if mapped.is_optional { "?" } else { "" },
+                mapped.value_type
+            ),
+            TypeKind::TemplateLiteral(template) => {
+                write!(f, "`")?;
+                for part in &template.parts {
+                    match part {
+                        TemplateLiteralTypePart::String(s) => write!(f, "{}", s)?,
+                        TemplateLiteralTypePart::Type(t) => write!(f, "${{{}}}", t)?,
+                    }
+                }
+                write!(f, "`")
+            }
+            TypeKind::Nullable(inner) => match inner.kind {
+                TypeKind::Union(_) | TypeKind::Intersection(_) | TypeKind::Function(_) => {
+                    write!(f, "({})?", inner)
+                }
+                _ => write!(f, "{}?", inner),
+            },
+            TypeKind::Parenthesized(inner) => write!(f, "({})", inner),
+            TypeKind::Asserts(predicate) => {
+                write!(f, "asserts {}", predicate.parameter.node)?;
+                if let Some(typ) = &predicate.type_predicate {
+                    write!(f, " is {}", typ)?;
+                }
+                Ok(())
+            }
+            TypeKind::Variadic(inner) => match inner.kind {
+                TypeKind::Union(_) | TypeKind::Intersection(_) | TypeKind::Function(_) => {
+                    write!(f, "({})...", inner)
+                }
+                _ => write!(f, "{}...", inner),
+            },
+        }
+    }
+}
+
+/// The dotted name a `typeof` queries, such as `config.defaults`
+fn entity_name(expr: &Expression) -> Option<String> {
+    match &expr.kind {
+        ExpressionKind::Identifier(name) => Some(name.clone()),
+        ExpressionKind::Member(object, member) => {
+            Some(format!("{}.{}", entity_name(object)?, member.node))
+        }
+        _ => None,
+    }
+}
+
+fn join(types: &[Type], separator: &str) -> String {
+    types
+        .iter()
+        .map(|t| t.to_string())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// Join the members of a union or intersection, parenthesizing function
+/// types so their return types don't absorb the rest
+fn join_members(types: &[Type], separator: &str) -> String {
+    types
+        .iter()
+        .map(|t| match t.kind {
+            TypeKind::Function(_) => format!("({})", t),
+            _ => t.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn format_parameters(parameters: &[Parameter]) -> String {
+    parameters
+        .iter()
+        .map(|param| {
+            let name = match &param.pattern {
+                super::pattern::Pattern::Identifier(ident) => ident.node.clone(),
+                _ => "_".to_string(),
+            };
+            let rest = if param.is_rest { "..." } else { "" };
+            match &param.type_annotation {
+                Some(t) => format!("{}{}: {}", rest, name, t),
+                None => format!("{}{}", rest, name),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn format_property(prop: &PropertySignature) -> String {
+    format!(
+        "{}{}{}: {}",
+        if prop.is_readonly { "readonly " } else { "" },
+        prop.name.node,
+        if prop.is_optional { "?" } else { "" },
+        prop.type_annotation
+    )
+}
+
+fn format_method(method: &MethodSignature) -> String {
+    format!(
+        "{}({}): {}",
+        method.name.node,
+        format_parameters(&method.parameters),
+        method.return_type
+    )
+}
+
+fn format_index(index: &IndexSignature) -> String {
+    let key = match index.key_type {
+        super::statement::IndexKeyType::String => "string",
+        super::statement::IndexKeyType::Number => "number",
+    };
+    format!("[{}: {}]: {}", index.key_name.node, key, index.value_type)
+}
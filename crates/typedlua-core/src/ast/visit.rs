@@ -0,0 +1,644 @@
+//! Walking the AST, for tools built against the crate rather than on
+//! source text: codemods, documentation generators, custom lints.
+//!
+//! Each `visit_*` method walks into the node's children by default.
+//! Override the methods for the nodes of interest, and call the matching
+//! `walk_*` method from an override to keep going below the node.
+//!
+//! ```
+//! use typedlua_core::ast::expression::{Expression, ExpressionKind};
+//! use typedlua_core::ast::visit::Visit;
+//!
+//! /// Collects the names of functions called directly
+//! struct Calls(Vec<String>);
+//!
+//! impl Visit for Calls {
+//!     fn visit_expression(&mut self, expr: &Expression) {
+//!         if let ExpressionKind::Call(callee, _) = &expr.kind {
+//!             if let ExpressionKind::Identifier(name) = &callee.kind {
+//!                 self.0.push(name.clone());
+//!             }
+//!         }
+//!         self.walk_expression(expr);
+//!     }
+//! }
+//! ```
+
+use super::expression::{
+    ArrayElement, ArrowBody, Expression, ExpressionKind, MatchArmBody, ObjectProperty, TemplatePart,
+};
+use super::pattern::{ArrayPatternElement, Pattern};
+use super::statement::{
+    Block, ClassMember, DeclareKind, Decorator, DecoratorExpression, ExportKind, ForStatement,
+    IndexSignature, InterfaceMember, MethodSignature, Parameter, PropertySignature, Statement,
+    TypeParameter,
+};
+use super::types::{ObjectTypeMember, TemplateLiteralTypePart, Type, TypeKind};
+use super::Program;
+
+/// Defines a visitor trait over shared references, or with `mut`, over
+/// mutable ones. The two traits walk the same children in the same order.
+macro_rules! visitor {
+    ($(#[$attr:meta])* $name:ident $(, $mut:ident)?) => {
+        $(#[$attr])*
+        pub trait $name {
+            fn visit_program(&mut self, program: &$($mut)? Program) {
+                self.walk_program(program);
+            }
+
+            fn visit_block(&mut self, block: &$($mut)? Block) {
+                self.walk_block(block);
+            }
+
+            fn visit_statement(&mut self, statement: &$($mut)? Statement) {
+                self.walk_statement(statement);
+            }
+
+            fn visit_class_member(&mut self, member: &$($mut)? ClassMember) {
+                self.walk_class_member(member);
+            }
+
+            fn visit_expression(&mut self, expr: &$($mut)? Expression) {
+                self.walk_expression(expr);
+            }
+
+            fn visit_pattern(&mut self, pattern: &$($mut)? Pattern) {
+                self.walk_pattern(pattern);
+            }
+
+            fn visit_type(&mut self, typ: &$($mut)? Type) {
+                self.walk_type(typ);
+            }
+
+            fn visit_parameter(&mut self, parameter: &$($mut)? Parameter) {
+                self.walk_parameter(parameter);
+            }
+
+            fn visit_type_parameter(&mut self, parameter: &$($mut)? TypeParameter) {
+                self.walk_type_parameter(parameter);
+            }
+
+            fn visit_decorator(&mut self, decorator: &$($mut)? Decorator) {
+                self.walk_decorator_expression(&$($mut)? decorator.expression);
+            }
+
+            fn walk_program(&mut self, program: &$($mut)? Program) {
+                for statement in &$($mut)? program.statements {
+                    self.visit_statement(statement);
+                }
+            }
+
+            fn walk_block(&mut self, block: &$($mut)? Block) {
+                for statement in &$($mut)? block.statements {
+                    self.visit_statement(statement);
+                }
+            }
+
+            fn walk_statement(&mut self, statement: &$($mut)? Statement) {
+                match statement {
+                    Statement::Variable(decl) => {
+                        self.walk_decorators(&$($mut)? decl.decorators);
+                        self.visit_pattern(&$($mut)? decl.pattern);
+                        if let Some(typ) = &$($mut)? decl.type_annotation {
+                            self.visit_type(typ);
+                        }
+                        self.visit_expression(&$($mut)? decl.initializer);
+                    }
+                    Statement::Function(func) => {
+                        self.walk_decorators(&$($mut)? func.decorators);
+                        self.walk_signature(
+                            &$($mut)? func.type_parameters,
+                            &$($mut)? func.parameters,
+                        );
+                        if let Some(typ) = &$($mut)? func.return_type {
+                            self.visit_type(typ);
+                        }
+                        self.visit_block(&$($mut)? func.body);
+                    }
+                    Statement::Class(class) => {
+                        self.walk_decorators(&$($mut)? class.decorators);
+                        self.walk_signature(&$($mut)? class.type_parameters, &$($mut)? []);
+                        if let Some(typ) = &$($mut)? class.extends {
+                            self.visit_type(typ);
+                        }
+                        for typ in (&$($mut)? class.mixins)
+                            .into_iter()
+                            .chain(&$($mut)? class.implements)
+                        {
+                            self.visit_type(typ);
+                        }
+                        for member in &$($mut)? class.members {
+                            self.visit_class_member(member);
+                        }
+                    }
+                    Statement::Interface(interface) => {
+                        self.walk_decorators(&$($mut)? interface.decorators);
+                        self.walk_signature(&$($mut)? interface.type_parameters, &$($mut)? []);
+                        for typ in &$($mut)? interface.extends {
+                            self.visit_type(typ);
+                        }
+                        for member in &$($mut)? interface.members {
+                            match member {
+                                InterfaceMember::Property(prop) => {
+                                    self.walk_property_signature(prop)
+                                }
+                                InterfaceMember::Method(method) => {
+                                    self.walk_method_signature(method)
+                                }
+                                InterfaceMember::Index(index) => {
+                                    self.walk_index_signature(index)
+                                }
+                            }
+                        }
+                    }
+                    Statement::TypeAlias(alias) => {
+                        self.walk_decorators(&$($mut)? alias.decorators);
+                        self.walk_signature(&$($mut)? alias.type_parameters, &$($mut)? []);
+                        self.visit_type(&$($mut)? alias.type_annotation);
+                    }
+                    Statement::Enum(decl) => self.walk_decorators(&$($mut)? decl.decorators),
+                    Statement::Import(_) => {}
+                    Statement::Export(export) => match &$($mut)? export.kind {
+                        ExportKind::Declaration(decl) => self.visit_statement(decl),
+                        ExportKind::Named(_) => {}
+                        ExportKind::Default(expr) | ExportKind::Equals(expr) => {
+                            self.visit_expression(expr)
+                        }
+                    },
+                    Statement::Declare(declare) => {
+                        self.walk_decorators(&$($mut)? declare.decorators);
+                        match &$($mut)? declare.kind {
+                            DeclareKind::Function(func) => {
+                                self.walk_signature(
+                                    &$($mut)? func.type_parameters,
+                                    &$($mut)? func.parameters,
+                                );
+                                self.visit_type(&$($mut)? func.return_type);
+                            }
+                            DeclareKind::Variable(variable) => {
+                                self.visit_type(&$($mut)? variable.type_annotation)
+                            }
+                        }
+                    }
+                    Statement::Namespace(namespace) => self.visit_block(&$($mut)? namespace.body),
+                    Statement::If(if_stmt) => {
+                        self.visit_expression(&$($mut)? if_stmt.condition);
+                        self.visit_block(&$($mut)? if_stmt.then_block);
+                        for else_if in &$($mut)? if_stmt.else_ifs {
+                            self.visit_expression(&$($mut)? else_if.condition);
+                            self.visit_block(&$($mut)? else_if.block);
+                        }
+                        if let Some(block) = &$($mut)? if_stmt.else_block {
+                            self.visit_block(block);
+                        }
+                    }
+                    Statement::While(while_stmt) => {
+                        self.visit_expression(&$($mut)? while_stmt.condition);
+                        self.visit_block(&$($mut)? while_stmt.body);
+                    }
+                    Statement::For(ForStatement::Numeric(numeric)) => {
+                        self.visit_expression(&$($mut)? numeric.start);
+                        self.visit_expression(&$($mut)? numeric.end);
+                        if let Some(step) = &$($mut)? numeric.step {
+                            self.visit_expression(step);
+                        }
+                        self.visit_block(&$($mut)? numeric.body);
+                    }
+                    Statement::For(ForStatement::Generic(generic)) => {
+                        for iterator in &$($mut)? generic.iterators {
+                            self.visit_expression(iterator);
+                        }
+                        self.visit_block(&$($mut)? generic.body);
+                    }
+                    Statement::Repeat(repeat) => {
+                        self.visit_block(&$($mut)? repeat.body);
+                        self.visit_expression(&$($mut)? repeat.until);
+                    }
+                    Statement::Labeled(labeled) => self.visit_statement(&$($mut)? labeled.body),
+                    Statement::Return(ret) => {
+                        for value in &$($mut)? ret.values {
+                            self.visit_expression(value);
+                        }
+                    }
+                    Statement::Break(_) | Statement::Continue(_) | Statement::Error(_) => {}
+                    Statement::Expression(expr) => self.visit_expression(expr),
+                    Statement::Block(block) | Statement::Untyped(block) => self.visit_block(block),
+                }
+            }
+
+            fn walk_class_member(&mut self, member: &$($mut)? ClassMember) {
+                match member {
+                    ClassMember::Property(prop) => {
+                        self.walk_decorators(&$($mut)? prop.decorators);
+                        self.visit_type(&$($mut)? prop.type_annotation);
+                        if let Some(initializer) = &$($mut)? prop.initializer {
+                            self.visit_expression(initializer);
+                        }
+                    }
+                    ClassMember::Constructor(constructor) => {
+                        self.walk_decorators(&$($mut)? constructor.decorators);
+                        self.walk_signature(&$($mut)? None, &$($mut)? constructor.parameters);
+                        if let Some(body) = &$($mut)? constructor.body {
+                            self.visit_block(body);
+                        }
+                    }
+                    ClassMember::Method(method) => {
+                        self.walk_decorators(&$($mut)? method.decorators);
+                        self.walk_signature(
+                            &$($mut)? method.type_parameters,
+                            &$($mut)? method.parameters,
+                        );
+                        if let Some(typ) = &$($mut)? method.return_type {
+                            self.visit_type(typ);
+                        }
+                        if let Some(body) = &$($mut)? method.body {
+                            self.visit_block(body);
+                        }
+                    }
+                    ClassMember::Getter(getter) => {
+                        self.walk_decorators(&$($mut)? getter.decorators);
+                        self.visit_type(&$($mut)? getter.return_type);
+                        self.visit_block(&$($mut)? getter.body);
+                    }
+                    ClassMember::Setter(setter) => {
+                        self.walk_decorators(&$($mut)? setter.decorators);
+                        self.visit_parameter(&$($mut)? setter.parameter);
+                        self.visit_block(&$($mut)? setter.body);
+                    }
+                    ClassMember::StaticBlock(block) => self.visit_block(&$($mut)? block.body),
+                }
+            }
+
+            fn walk_expression(&mut self, expr: &$($mut)? Expression) {
+                match &$($mut)? expr.kind {
+                    ExpressionKind::Identifier(_)
+                    | ExpressionKind::Literal(_)
+                    | ExpressionKind::SelfKeyword
+                    | ExpressionKind::SuperKeyword => {}
+                    ExpressionKind::Binary(_, left, right)
+                    | ExpressionKind::Assignment(left, _, right)
+                    | ExpressionKind::Index(left, right)
+                    | ExpressionKind::Pipe(left, right) => {
+                        self.visit_expression(left);
+                        self.visit_expression(right);
+                    }
+                    ExpressionKind::Unary(_, operand)
+                    | ExpressionKind::Member(operand, _)
+                    | ExpressionKind::OptionalMember(operand, _)
+                    | ExpressionKind::Parenthesized(operand) => self.visit_expression(operand),
+                    ExpressionKind::Call(callee, arguments)
+                    | ExpressionKind::MethodCall(callee, _, arguments) => {
+                        self.visit_expression(callee);
+                        for argument in arguments {
+                            self.visit_expression(&$($mut)? argument.value);
+                        }
+                    }
+                    ExpressionKind::Array(elements) => {
+                        for element in elements {
+                            match element {
+                                ArrayElement::Expression(value) | ArrayElement::Spread(value) => {
+                                    self.visit_expression(value)
+                                }
+                            }
+                        }
+                    }
+                    ExpressionKind::Object(properties) => {
+                        for property in properties {
+                            match property {
+                                ObjectProperty::Property { value, .. }
+                                | ObjectProperty::Spread { value, .. } => {
+                                    self.visit_expression(value)
+                                }
+                                ObjectProperty::Computed { key, value, .. } => {
+                                    self.visit_expression(key);
+                                    self.visit_expression(value);
+                                }
+                            }
+                        }
+                    }
+                    ExpressionKind::Function(func) => {
+                        self.walk_signature(
+                            &$($mut)? func.type_parameters,
+                            &$($mut)? func.parameters,
+                        );
+                        if let Some(typ) = &$($mut)? func.return_type {
+                            self.visit_type(typ);
+                        }
+                        self.visit_block(&$($mut)? func.body);
+                    }
+                    ExpressionKind::Arrow(arrow) => {
+                        self.walk_signature(&$($mut)? None, &$($mut)? arrow.parameters);
+                        if let Some(typ) = &$($mut)? arrow.return_type {
+                            self.visit_type(typ);
+                        }
+                        match &$($mut)? arrow.body {
+                            ArrowBody::Expression(body) => self.visit_expression(body),
+                            ArrowBody::Block(body) => self.visit_block(body),
+                        }
+                    }
+                    ExpressionKind::Conditional(condition, then, otherwise) => {
+                        self.visit_expression(condition);
+                        self.visit_expression(then);
+                        self.visit_expression(otherwise);
+                    }
+                    ExpressionKind::Match(match_expr) => {
+                        self.visit_expression(&$($mut)? match_expr.value);
+                        for arm in &$($mut)? match_expr.arms {
+                            self.visit_pattern(&$($mut)? arm.pattern);
+                            if let Some(guard) = &$($mut)? arm.guard {
+                                self.visit_expression(guard);
+                            }
+                            match &$($mut)? arm.body {
+                                MatchArmBody::Expression(body) => self.visit_expression(body),
+                                MatchArmBody::Block(body) => self.visit_block(body),
+                            }
+                        }
+                    }
+                    ExpressionKind::Template(template) => {
+                        for part in &$($mut)? template.parts {
+                            match part {
+                                TemplatePart::String(_) => {}
+                                TemplatePart::Expression(value)
+                                | TemplatePart::Formatted(value, _) => self.visit_expression(value),
+                            }
+                        }
+                    }
+                    ExpressionKind::TypeAssertion(value, typ) => {
+                        self.visit_expression(value);
+                        self.visit_type(typ);
+                    }
+                }
+            }
+
+            fn walk_pattern(&mut self, pattern: &$($mut)? Pattern) {
+                match pattern {
+                    Pattern::Array(array) => {
+                        for element in &$($mut)? array.elements {
+                            if let ArrayPatternElement::Pattern(pattern) = element {
+                                self.visit_pattern(pattern);
+                            }
+                        }
+                    }
+                    Pattern::Object(object) => {
+                        for property in &$($mut)? object.properties {
+                            if let Some(pattern) = &$($mut)? property.value {
+                                self.visit_pattern(pattern);
+                            }
+                            if let Some(default) = &$($mut)? property.default {
+                                self.visit_expression(default);
+                            }
+                        }
+                    }
+                    Pattern::Identifier(_)
+                    | Pattern::Literal(..)
+                    | Pattern::Wildcard(_)
+                    | Pattern::Multiple(_) => {}
+                }
+            }
+
+            fn walk_type(&mut self, typ: &$($mut)? Type) {
+                match &$($mut)? typ.kind {
+                    TypeKind::Primitive(_) | TypeKind::Literal(_) => {}
+                    TypeKind::Reference(reference) => {
+                        if let Some(arguments) = &$($mut)? reference.type_arguments {
+                            for argument in arguments {
+                                self.visit_type(argument);
+                            }
+                        }
+                    }
+                    TypeKind::Union(types)
+                    | TypeKind::Intersection(types)
+                    | TypeKind::Tuple(types) => {
+                        for typ in types {
+                            self.visit_type(typ);
+                        }
+                    }
+                    TypeKind::Object(object) => {
+                        for member in &$($mut)? object.members {
+                            match member {
+                                ObjectTypeMember::Property(prop) => {
+                                    self.walk_property_signature(prop)
+                                }
+                                ObjectTypeMember::Method(method) => {
+                                    self.walk_method_signature(method)
+                                }
+                                ObjectTypeMember::Index(index) => {
+                                    self.walk_index_signature(index)
+                                }
+                            }
+                        }
+                    }
+                    TypeKind::Array(inner)
+                    | TypeKind::KeyOf(inner)
+                    | TypeKind::Nullable(inner)
+                    | TypeKind::Parenthesized(inner)
+                    | TypeKind::Variadic(inner) => self.visit_type(inner),
+                    TypeKind::Function(func) => {
+                        self.walk_signature(&$($mut)? None, &$($mut)? func.parameters);
+                        self.visit_type(&$($mut)? func.return_type);
+                    }
+                    TypeKind::TypeQuery(expr) => self.visit_expression(expr),
+                    TypeKind::IndexAccess(object, index) => {
+                        self.visit_type(object);
+                        self.visit_type(index);
+                    }
+                    TypeKind::Conditional(conditional) => {
+                        self.visit_type(&$($mut)? conditional.check_type);
+                        self.visit_type(&$($mut)? conditional.extends_type);
+                        self.visit_type(&$($mut)? conditional.true_type);
+                        self.visit_type(&$($mut)? conditional.false_type);
+                    }
+                    TypeKind::Mapped(mapped) => {
+                        self.visit_type_parameter(&$($mut)? mapped.type_parameter);
+                        self.visit_type(&$($mut)? mapped.in_type);
+                        self.visit_type(&$($mut)? mapped.value_type);
+                    }
+                    TypeKind::TemplateLiteral(template) => {
+                        for part in &$($mut)? template.parts {
+                            if let TemplateLiteralTypePart::Type(typ) = part {
+                                self.visit_type(typ);
+                            }
+                        }
+                    }
+                    TypeKind::Asserts(predicate) => {
+                        if let Some(typ) = &$($mut)? predicate.type_predicate {
+                            self.visit_type(typ);
+                        }
+                    }
+                }
+            }
+
+            fn walk_parameter(&mut self, parameter: &$($mut)? Parameter) {
+                self.visit_pattern(&$($mut)? parameter.pattern);
+                if let Some(typ) = &$($mut)? parameter.type_annotation {
+                    self.visit_type(typ);
+                }
+                if let Some(default) = &$($mut)? parameter.default {
+                    self.visit_expression(default);
+                }
+            }
+
+            fn walk_type_parameter(&mut self, parameter: &$($mut)? TypeParameter) {
+                if let Some(constraint) = &$($mut)? parameter.constraint {
+                    self.visit_type(constraint);
+                }
+                if let Some(default) = &$($mut)? parameter.default {
+                    self.visit_type(default);
+                }
+            }
+
+            fn walk_decorator_expression(&mut self, expression: &$($mut)? DecoratorExpression) {
+                match expression {
+                    DecoratorExpression::Identifier(_) => {}
+                    DecoratorExpression::Call { callee, arguments, .. } => {
+                        self.walk_decorator_expression(callee);
+                        for argument in arguments {
+                            self.visit_expression(argument);
+                        }
+                    }
+                    DecoratorExpression::Member { object, .. } => {
+                        self.walk_decorator_expression(object)
+                    }
+                }
+            }
+
+            #[doc(hidden)]
+            fn walk_decorators(&mut self, decorators: &$($mut)? [Decorator]) {
+                for decorator in decorators {
+                    self.visit_decorator(decorator);
+                }
+            }
+
+            #[doc(hidden)]
+            fn walk_signature(
+                &mut self,
+                type_parameters: &$($mut)? Option<Vec<TypeParameter>>,
+                parameters: &$($mut)? [Parameter],
+            ) {
+                if let Some(type_parameters) = type_parameters {
+                    for parameter in type_parameters {
+                        self.visit_type_parameter(parameter);
+                    }
+                }
+                for parameter in parameters {
+                    self.visit_parameter(parameter);
+                }
+            }
+
+            #[doc(hidden)]
+            fn walk_property_signature(&mut self, prop: &$($mut)? PropertySignature) {
+                self.walk_decorators(&$($mut)? prop.decorators);
+                self.visit_type(&$($mut)? prop.type_annotation);
+            }
+
+            #[doc(hidden)]
+            fn walk_method_signature(&mut self, method: &$($mut)? MethodSignature) {
+                self.walk_decorators(&$($mut)? method.decorators);
+                self.walk_signature(&$($mut)? method.type_parameters, &$($mut)? method.parameters);
+                self.visit_type(&$($mut)? method.return_type);
+            }
+
+            #[doc(hidden)]
+            fn walk_index_signature(&mut self, index: &$($mut)? IndexSignature) {
+                self.visit_type(&$($mut)? index.value_type);
+            }
+        }
+    };
+}
+
+visitor!(
+    /// Visits every node of a program in source order
+    Visit
+);
+
+visitor!(
+    /// Visits every node of a program in source order, able to rewrite
+    /// each one in place
+    VisitMut,
+    mut
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::types::TypeReference;
+    use crate::codegen::CodeGenerator;
+    use crate::config::CompilerConfig;
+    use crate::diagnostics::CollectingDiagnosticHandler;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use std::sync::Arc;
+
+    fn parse(source: &str) -> Program {
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let tokens = Lexer::new(source, handler.clone())
+            .tokenize()
+            .expect("Lexing failed");
+        Parser::new(tokens, handler)
+            .parse()
+            .expect("Parsing failed")
+    }
+
+    /// Names read in expressions and named in types, in visiting order
+    #[derive(Default)]
+    struct Names(Vec<String>);
+
+    impl Visit for Names {
+        fn visit_expression(&mut self, expr: &Expression) {
+            if let ExpressionKind::Identifier(name) = &expr.kind {
+                self.0.push(name.clone());
+            }
+            self.walk_expression(expr);
+        }
+
+        fn visit_type(&mut self, typ: &Type) {
+            if let TypeKind::Reference(TypeReference { name, .. }) = &typ.kind {
+                self.0.push(name.node.clone());
+            }
+            self.walk_type(typ);
+        }
+    }
+
+    struct RenameCalls;
+
+    impl VisitMut for RenameCalls {
+        fn visit_expression(&mut self, expr: &mut Expression) {
+            if let ExpressionKind::Identifier(name) = &mut expr.kind {
+                if name == "print" {
+                    *name = "log".to_string();
+                }
+            }
+            self.walk_expression(expr);
+        }
+    }
+
+    #[test]
+    fn test_visitors_reach_nested_nodes() {
+        let source = r#"class Store<T extends Item> {
+    items: T[] = defaults
+
+    get(index: integer = first): T? {
+        return self.items[index]
+    }
+}
+local render = (item: Item): string => `${format(item)}`
+if ready then
+    print(render(current))
+end
+"#;
+        let mut program = parse(source);
+        let mut names = Names::default();
+        names.visit_program(&program);
+        assert_eq!(
+            names.0,
+            vec![
+                "Item", "T", "defaults", "first", "T", "self", "index", "Item", "format", "item",
+                "ready", "print", "render", "current",
+            ]
+        );
+
+        RenameCalls.visit_program(&mut program);
+        let lua = CodeGenerator::new(Arc::new(CompilerConfig::default())).generate(&program);
+        assert!(lua.contains("log(render(current))"));
+    }
+}
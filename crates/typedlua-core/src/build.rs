@@ -0,0 +1,1362 @@
+//! Compiling modules to Lua (`typedlua build`), reusing the output of
+//! unchanged modules from an on-disk cache
+
+use crate::ast::pattern::Pattern;
+use crate::ast::statement::{ExportKind, Statement};
+use crate::ast::Program;
+use crate::codegen::{CodeGenerator, SourceMap};
+use crate::config::CompilerConfig;
+use crate::declarations::module_declarations;
+use crate::diagnostics::{CollectingDiagnosticHandler, Diagnostic, DiagnosticHandler};
+use crate::errors::CompilationError;
+use crate::fs::FileSystem;
+use crate::lexer::{Directive, Lexer, Token};
+use crate::lint::Linter;
+use crate::minify::minify;
+use crate::optimize::eliminate_dead_stores;
+use crate::parser::Parser;
+use crate::plugin::{Plugin, PluginContext};
+use crate::resolve::{is_data_module, ModuleResolver, ResolvedModule};
+use crate::shake::{shake, Removal, UsedExports};
+use crate::span::Span;
+use crate::typechecker::{TypeChecker, TypeCoverage};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// The result of compiling one module
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompiledModule {
+    /// Emitted Lua source
+    pub lua: String,
+    /// Exported values and their types, as written in TypedLua
+    pub exports: Vec<(String, String)>,
+    /// Where each line of `lua` came from, with `sourceMap`
+    #[serde(default)]
+    pub source_map: Option<SourceMap>,
+    /// The module's declaration file, with `emitDeclarations`
+    #[serde(default)]
+    pub declarations: Option<String>,
+    /// Declaration files that typed the module's imports, with the hash of
+    /// their content when it was compiled
+    #[serde(default)]
+    pub declaration_files: Vec<(PathBuf, u64)>,
+    /// What tree shaking left out of the Lua
+    #[serde(default)]
+    pub removals: Vec<Removal>,
+    /// Import paths as written, with the files they resolve to
+    #[serde(default)]
+    pub imports: Vec<(String, Option<PathBuf>)>,
+    /// TypedLua modules whose exports typed the module's imports, with the
+    /// hash of the declarations of those exports when it was compiled
+    #[serde(default)]
+    pub typed_imports: Vec<(PathBuf, u64)>,
+    /// How much of the module the checker could type
+    #[serde(default)]
+    pub coverage: TypeCoverage,
+}
+
+/// Lex, parse, check and generate a module. Sources with errors produce no
+/// output. Imports are not resolved, so leave their names untyped.
+pub fn compile_source(
+    source: &str,
+    config: Arc<CompilerConfig>,
+    diagnostic_handler: Arc<dyn DiagnosticHandler>,
+) -> Result<CompiledModule, CompilationError> {
+    compile(source, None, None, config, diagnostic_handler, None, &[])
+}
+
+/// Compile the module at `path`, typing imports of plain Lua modules from
+/// their declaration files
+pub fn compile_file(
+    path: &Path,
+    source: &str,
+    file_system: Arc<dyn FileSystem>,
+    config: Arc<CompilerConfig>,
+    diagnostic_handler: Arc<dyn DiagnosticHandler>,
+) -> Result<CompiledModule, CompilationError> {
+    compile(
+        source,
+        None,
+        Some(ModuleFile::new(path, file_system)),
+        config,
+        diagnostic_handler,
+        None,
+        &[],
+    )
+}
+
+/// Compile the module at `path` as [`compile_file`] does, running
+/// `plugins` over it once it type checks
+pub fn compile_with_plugins(
+    path: &Path,
+    source: &str,
+    file_system: Arc<dyn FileSystem>,
+    config: Arc<CompilerConfig>,
+    diagnostic_handler: Arc<dyn DiagnosticHandler>,
+    plugins: &[Arc<dyn Plugin>],
+) -> Result<CompiledModule, CompilationError> {
+    compile(
+        source,
+        None,
+        Some(ModuleFile::new(path, file_system)),
+        config,
+        diagnostic_handler,
+        None,
+        plugins,
+    )
+}
+
+impl CompiledModule {
+    /// The files the module was compiled against: those its imports
+    /// resolve to and the declaration files that typed them
+    pub fn dependencies(&self) -> Vec<PathBuf> {
+        let mut dependencies: Vec<PathBuf> = self
+            .imports
+            .iter()
+            .filter_map(|(_, path)| path.clone())
+            .collect();
+        for (path, _) in &self.declaration_files {
+            if !dependencies.contains(path) {
+                dependencies.push(path.clone());
+            }
+        }
+        dependencies
+    }
+}
+
+/// Compile a module of a bundle, tree shaking it down to the exports its
+/// importers use
+pub fn compile_bundled(
+    path: &Path,
+    source: &str,
+    file_system: Arc<dyn FileSystem>,
+    config: Arc<CompilerConfig>,
+    diagnostic_handler: Arc<dyn DiagnosticHandler>,
+    used_exports: UsedExports,
+) -> Result<CompiledModule, CompilationError> {
+    compile(
+        source,
+        None,
+        Some(ModuleFile::new(path, file_system)),
+        config,
+        diagnostic_handler,
+        Some(used_exports),
+        &[],
+    )
+}
+
+/// A module's source as lexed and parsed. The build cache keeps it, so a
+/// module rebuilt from unchanged source, because its configuration or the
+/// exports it imports changed, is not lexed and parsed again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParsedModule {
+    pub tokens: Vec<Token>,
+    pub directives: Vec<Directive>,
+    pub comments: Vec<Span>,
+    pub program: Program,
+}
+
+/// Lex and parse a module. Any syntax error fails it.
+pub fn parse_module(
+    source: &str,
+    diagnostic_handler: Arc<dyn DiagnosticHandler>,
+) -> Result<ParsedModule, CompilationError> {
+    let parsed = parse_source(source, diagnostic_handler.clone())?;
+    if diagnostic_handler.has_errors() {
+        return Err(CompilationError::ParseErrors(
+            diagnostic_handler.error_count(),
+        ));
+    }
+    Ok(parsed)
+}
+
+/// Lex and parse a module, keeping the program the parser recovers from
+/// syntax errors. It fails only where lexing stops, which the lexer reports
+/// with its span.
+pub fn parse_source(
+    source: &str,
+    diagnostic_handler: Arc<dyn DiagnosticHandler>,
+) -> Result<ParsedModule, CompilationError> {
+    let mut lexer = Lexer::new(source, diagnostic_handler.clone());
+    let tokens = lexer
+        .tokenize()
+        .map_err(|_| CompilationError::LexicalErrors(diagnostic_handler.error_count()))?;
+
+    let mut parser = Parser::new(tokens.clone(), diagnostic_handler.clone());
+    let program = parser
+        .parse()
+        .map_err(|_| CompilationError::ParseErrors(1))?;
+    Ok(ParsedModule {
+        tokens,
+        directives: lexer.directives().to_vec(),
+        comments: lexer.comments().to_vec(),
+        program,
+    })
+}
+
+/// The file a module is compiled from, which its imports resolve against
+struct ModuleFile<'a> {
+    path: &'a Path,
+    file_system: Arc<dyn FileSystem>,
+    /// Declarations of the exports of the TypedLua modules it may import
+    module_exports: Option<Arc<HashMap<PathBuf, String>>>,
+}
+
+impl<'a> ModuleFile<'a> {
+    fn new(path: &'a Path, file_system: Arc<dyn FileSystem>) -> Self {
+        ModuleFile {
+            path,
+            file_system,
+            module_exports: None,
+        }
+    }
+}
+
+/// Compile a module, lexing and parsing `source` unless it comes `parsed`
+fn compile(
+    source: &str,
+    parsed: Option<ParsedModule>,
+    file: Option<ModuleFile>,
+    config: Arc<CompilerConfig>,
+    diagnostic_handler: Arc<dyn DiagnosticHandler>,
+    used_exports: Option<UsedExports>,
+    plugins: &[Arc<dyn Plugin>],
+) -> Result<CompiledModule, CompilationError> {
+    let parsed = match parsed {
+        Some(parsed) => parsed,
+        None => parse_module(source, diagnostic_handler.clone())?,
+    };
+    let mut program = parsed.program;
+
+    // The first plugin with its own resolution strategy decides how
+    // imports find modules
+    let resolver = file.as_ref().map(|file| {
+        let file_system = file.file_system.clone();
+        let resolver = match plugins.iter().find_map(|plugin| plugin.resolution()) {
+            Some(strategy) => ModuleResolver::with_strategy(file_system, strategy),
+            None => ModuleResolver::new(file_system),
+        };
+        (file.path, resolver)
+    });
+
+    let mut checker = TypeChecker::new(config.clone(), diagnostic_handler.clone());
+    checker.set_directives(&parsed.directives);
+    if let Some((path, resolver)) = &resolver {
+        checker.set_module_resolver(resolver.clone(), path);
+        if is_data_module(path) {
+            checker.set_data_module();
+        }
+    }
+    let module_exports = file.as_ref().and_then(|file| file.module_exports.clone());
+    if let Some(module_exports) = &module_exports {
+        checker.set_module_exports(module_exports.clone());
+    }
+    checker.check_program(&program)?;
+    if config.lint.on_build {
+        let errors = Linter::for_config(&config).lint(
+            &program,
+            &parsed.directives,
+            &parsed.comments,
+            diagnostic_handler.as_ref(),
+        );
+        if errors > 0 {
+            return Err(CompilationError::LintErrors(errors));
+        }
+    }
+    let declaration_files = match &file {
+        Some(file) => checker
+            .declaration_files()
+            .iter()
+            .map(|path| {
+                let text = file.file_system.read_file(path).unwrap_or_default();
+                (path.clone(), content_hash(&text))
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let imports: Vec<(String, Option<PathBuf>)> = program
+        .statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Import(import) => Some(import.source.clone()),
+            _ => None,
+        })
+        .map(|source| {
+            let path = resolver.as_ref().and_then(|(importer, resolver)| {
+                let module = resolver.resolve(importer, &source)?;
+                Some(module.path().to_path_buf())
+            });
+            (source, path)
+        })
+        .collect();
+    let typed_imports = imports
+        .iter()
+        .filter_map(|(_, path)| {
+            let path = path.as_ref()?;
+            let declarations = module_exports.as_ref()?.get(path)?;
+            Some((path.clone(), content_hash(declarations)))
+        })
+        .collect();
+
+    let exports = exported_names(&program.statements)
+        .into_iter()
+        .filter_map(|(exported, local)| {
+            let symbol = checker.symbols().lookup(&local)?;
+            Some((exported, symbol.typ.to_string()))
+        })
+        .collect();
+    let declarations = config
+        .compiler_options
+        .emit_declarations
+        .then(|| module_declarations(&program, &checker));
+    let used_exports = used_exports.or_else(|| {
+        config
+            .compiler_options
+            .tree_shaking
+            .then_some(UsedExports::All)
+    });
+
+    let context = PluginContext {
+        path: file.as_ref().map(|file| file.path),
+        config: &config,
+        symbols: checker.symbols(),
+        type_environment: checker.type_environment(),
+        diagnostic_handler: diagnostic_handler.as_ref(),
+    };
+    let errors_before = diagnostic_handler.error_count();
+    for plugin in plugins {
+        plugin.transform(&mut program, &context);
+    }
+    let readable = config.compiler_options.readable_output;
+    if config.compiler_options.optimization_level >= 1 && !readable {
+        eliminate_dead_stores(&mut program);
+    }
+
+    let mut generator = CodeGenerator::new(config.clone());
+    if readable {
+        let file = file
+            .as_ref()
+            .and_then(|file| file.path.file_name())
+            .map_or("<input>".into(), |name| name.to_string_lossy());
+        generator.set_readable_source(&file, source, &parsed.comments);
+    }
+    let mut removals = Vec::new();
+    if let Some(used_exports) = used_exports {
+        let tree_shaking = shake(&program.statements, &used_exports);
+        removals = tree_shaking.removals.clone();
+        generator.set_tree_shaking(tree_shaking);
+    }
+    let mut lua = generator.generate(&program);
+    let mut source_map = generator.take_source_map();
+    if !plugins.is_empty() {
+        let generated = lua.clone();
+        for plugin in plugins {
+            plugin.emit(&mut lua, &context);
+        }
+        if lua != generated {
+            source_map = None;
+        }
+    }
+    let plugin_errors = diagnostic_handler.error_count() - errors_before;
+    if plugin_errors > 0 {
+        return Err(CompilationError::PluginErrors(plugin_errors));
+    }
+    if config.compiler_options.minify && !readable {
+        lua = minify(&lua, &config.compiler_options);
+        source_map = None;
+    }
+    Ok(CompiledModule {
+        lua,
+        exports,
+        source_map,
+        declarations,
+        declaration_files,
+        removals,
+        imports,
+        typed_imports,
+        coverage: checker.type_coverage(),
+    })
+}
+
+/// Compile modules on a thread pool, returning each module's result and
+/// diagnostics in input order. The exports of every TypedLua module they
+/// import are checked first, in dependency order, so imports are typed;
+/// the modules themselves then check and generate in parallel. With a
+/// `cache`, modules whose source is unchanged start from their cached AST,
+/// and the ASTs of the rest are cached.
+pub fn compile_modules(
+    modules: &[(&Path, &str)],
+    file_system: Arc<dyn FileSystem>,
+    config: Arc<CompilerConfig>,
+    cache: Option<&BuildCache>,
+) -> Vec<(Result<CompiledModule, CompilationError>, Vec<Diagnostic>)> {
+    let module_exports = match cache {
+        Some(cache) => cache.module_exports(modules),
+        None => Arc::new(module_exports(
+            modules,
+            &file_system,
+            &config,
+            HashMap::new(),
+        )),
+    };
+    modules
+        .par_iter()
+        .map(|(path, source)| {
+            let handler = Arc::new(CollectingDiagnosticHandler::new());
+            let parsed = match cache.and_then(|cache| cache.parsed(path, source)) {
+                Some(parsed) => Ok(parsed),
+                None => parse_module(source, handler.clone()).inspect(|parsed| {
+                    // A module whose AST cannot be cached still compiles
+                    if let Some(cache) = cache {
+                        let _ = cache.store_parsed(path, source, parsed);
+                    }
+                }),
+            };
+            let file = ModuleFile {
+                path,
+                file_system: file_system.clone(),
+                module_exports: Some(module_exports.clone()),
+            };
+            let result = parsed.and_then(|parsed| {
+                compile(
+                    source,
+                    Some(parsed),
+                    Some(file),
+                    config.clone(),
+                    handler.clone(),
+                    None,
+                    &[],
+                )
+            });
+            (result, handler.get_diagnostics())
+        })
+        .collect()
+}
+
+/// The declarations of the exports of every TypedLua module that `modules`
+/// import, directly or through other modules, keyed by path, along with the
+/// `known` ones. Modules not among `modules` are read from the file system.
+/// A module is checked once the modules it imports have been, and modules
+/// whose imports are all done check in parallel; modules in an import cycle
+/// see each other untyped.
+fn module_exports(
+    modules: &[(&Path, &str)],
+    file_system: &Arc<dyn FileSystem>,
+    config: &Arc<CompilerConfig>,
+    known: HashMap<PathBuf, String>,
+) -> HashMap<PathBuf, String> {
+    let resolver = ModuleResolver::new(file_system.clone());
+    let mut sources: HashMap<PathBuf, String> = modules
+        .iter()
+        .map(|(path, source)| (path.to_path_buf(), source.to_string()))
+        .collect();
+
+    // Every module reached and the TypedLua modules it imports
+    let mut graph: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    let mut pending: Vec<PathBuf> = sources.keys().cloned().collect();
+    while let Some(path) = pending.pop() {
+        if graph.contains_key(&path) {
+            continue;
+        }
+        // What a known module imports no longer matters, unless it is
+        // among `modules`
+        let root = modules.iter().any(|(module, _)| *module == path);
+        if known.contains_key(&path) && !root {
+            graph.insert(path, Vec::new());
+            continue;
+        }
+        if !sources.contains_key(&path) {
+            let Ok(source) = file_system.read_file(&path) else {
+                continue;
+            };
+            sources.insert(path.clone(), source);
+        }
+        let imports: Vec<PathBuf> = import_sources(&sources[&path])
+            .iter()
+            .filter_map(|source| match resolver.resolve(&path, source)? {
+                ResolvedModule::TypedLua(imported) => Some(imported),
+                _ => None,
+            })
+            .collect();
+        pending.extend(imports.iter().cloned());
+        graph.insert(path, imports);
+    }
+
+    // Only imported modules need their exports declared
+    let mut remaining: Vec<PathBuf> = graph
+        .values()
+        .flatten()
+        .filter(|path| graph.contains_key(*path) && !known.contains_key(*path))
+        .cloned()
+        .collect();
+    remaining.sort();
+    remaining.dedup();
+
+    let mut exports = known;
+    while !remaining.is_empty() {
+        let (mut ready, waiting): (Vec<PathBuf>, Vec<PathBuf>) =
+            remaining.iter().cloned().partition(|path| {
+                graph[path]
+                    .iter()
+                    .all(|import| exports.contains_key(import) || !remaining.contains(import))
+            });
+        if ready.is_empty() {
+            // An import cycle: check its modules without each other's exports
+            ready = waiting;
+            remaining = Vec::new();
+        } else {
+            remaining = waiting;
+        }
+        let known = Arc::new(exports.clone());
+        let declared: Vec<(PathBuf, String)> = ready
+            .par_iter()
+            .map(|path| {
+                let declarations =
+                    export_declarations(path, &sources[path], &resolver, config, known.clone());
+                (path.clone(), declarations)
+            })
+            .collect();
+        exports.extend(declared);
+    }
+    exports
+}
+
+/// Check a module for the declarations of its exports. Its errors are left
+/// for when the module itself is compiled.
+fn export_declarations(
+    path: &Path,
+    source: &str,
+    resolver: &ModuleResolver,
+    config: &Arc<CompilerConfig>,
+    module_exports: Arc<HashMap<PathBuf, String>>,
+) -> String {
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let mut lexer = Lexer::new(source, handler.clone());
+    let Some(program) = lexer
+        .tokenize()
+        .ok()
+        .and_then(|tokens| Parser::new(tokens, handler.clone()).parse().ok())
+    else {
+        return String::new();
+    };
+    let mut checker = TypeChecker::new(config.clone(), handler);
+    checker.set_directives(lexer.directives());
+    checker.set_module_resolver(resolver.clone(), path);
+    if is_data_module(path) {
+        checker.set_data_module();
+    }
+    checker.set_module_exports(module_exports);
+    let _ = checker.check_program(&program);
+    module_declarations(&program, &checker)
+}
+
+/// The import paths of a module's source, as written. Source that does not
+/// parse imports nothing here and reports its errors when compiled.
+fn import_sources(source: &str) -> Vec<String> {
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let program = Lexer::new(source, handler.clone())
+        .tokenize()
+        .ok()
+        .and_then(|tokens| Parser::new(tokens, handler).parse().ok());
+    program
+        .map(|program| {
+            program
+                .statements
+                .iter()
+                .filter_map(|statement| match statement {
+                    Statement::Import(import) => Some(import.source.clone()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Exported value names paired with the local each one reads
+pub(crate) fn exported_names(statements: &[Statement]) -> Vec<(String, String)> {
+    let mut names = Vec::new();
+    for statement in statements {
+        let Statement::Export(export) = statement else {
+            continue;
+        };
+        match &export.kind {
+            ExportKind::Declaration(decl) => {
+                let name = match decl.as_ref() {
+                    Statement::Function(func) => &func.name,
+                    Statement::Class(class) => &class.name,
+                    Statement::Enum(decl) => &decl.name,
+                    Statement::Variable(decl) => match &decl.pattern {
+                        Pattern::Identifier(name) => name,
+                        _ => continue,
+                    },
+                    _ => continue,
+                };
+                names.push((name.node.clone(), name.node.clone()));
+            }
+            ExportKind::Named(specifiers) => {
+                for specifier in specifiers {
+                    let exported = specifier.exported.as_ref().unwrap_or(&specifier.local);
+                    names.push((exported.node.clone(), specifier.local.node.clone()));
+                }
+            }
+            ExportKind::Default(_) | ExportKind::Equals(_) => {}
+        }
+    }
+    names
+}
+
+/// Compiled modules stored under the cache directory, one file per module.
+/// An entry is reused only while the module's source, the declaration files
+/// typing its imports, the exports of the TypedLua modules it imports, the
+/// configuration and the compiler version are all unchanged. Each module's
+/// AST is kept under `parsed/` for as long as its source is unchanged.
+pub struct BuildCache {
+    dir: PathBuf,
+    config: Arc<CompilerConfig>,
+    config_hash: u64,
+    option_hashes: BTreeMap<String, u64>,
+    file_system: Arc<dyn FileSystem>,
+    /// Declarations of the exports of the modules checked so far, by path
+    exports: Mutex<HashMap<PathBuf, String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CacheEntry {
+    module: PathBuf,
+    compiler_version: String,
+    config_hash: u64,
+    /// The hash of each compiler option and of the lint options, so a
+    /// changed configuration can be narrowed down to the options that
+    /// changed. Entries written before they were recorded have none.
+    #[serde(default)]
+    option_hashes: BTreeMap<String, u64>,
+    source_hash: u64,
+    #[serde(flatten)]
+    compiled: CompiledModule,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ParsedEntry {
+    module: PathBuf,
+    compiler_version: String,
+    source_hash: u64,
+    #[serde(flatten)]
+    parsed: ParsedModule,
+}
+
+/// Why a module's cache entry cannot be reused (`typedlua why`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RebuildReason {
+    /// No entry, or one that could not be read
+    NotCached,
+    /// The entry was written by this other compiler version
+    CompilerVersion(String),
+    /// The configuration changed; names the options that did, when the
+    /// entry recorded them
+    Configuration(Vec<String>),
+    /// The module's source changed
+    Source,
+    /// A declaration file typing one of the module's imports changed or
+    /// is gone
+    DeclarationFile(PathBuf),
+    /// The exports of an imported TypedLua module changed, or it is gone
+    ImportedExports(PathBuf),
+}
+
+impl fmt::Display for RebuildReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RebuildReason::NotCached => write!(f, "it has no cached build"),
+            RebuildReason::CompilerVersion(version) => write!(
+                f,
+                "it was built by typedlua {}, not {}",
+                version,
+                env!("CARGO_PKG_VERSION")
+            ),
+            RebuildReason::Configuration(options) if options.is_empty() => {
+                write!(f, "the configuration changed")
+            }
+            RebuildReason::Configuration(options) => {
+                write!(f, "the configuration changed: {}", options.join(", "))
+            }
+            RebuildReason::Source => write!(f, "its source changed"),
+            RebuildReason::DeclarationFile(path) => {
+                write!(f, "declaration file {} changed", path.display())
+            }
+            RebuildReason::ImportedExports(path) => {
+                write!(f, "the exports of {} changed", path.display())
+            }
+        }
+    }
+}
+
+/// What the cache knows of a module
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheStatus {
+    /// Why the next build compiles the module again; empty when it reuses
+    /// the cached output
+    pub reasons: Vec<RebuildReason>,
+    /// The files the cached build was compiled against
+    pub dependencies: Vec<PathBuf>,
+}
+
+impl BuildCache {
+    pub fn new(dir: &Path, config: &CompilerConfig, file_system: Arc<dyn FileSystem>) -> Self {
+        // Going through a JSON value sorts map keys, so equal
+        // configurations hash equally. Compiler options change what a
+        // module compiles to, lint options what a build reports when it
+        // lints, and formatter options neither.
+        let lint = config.lint.on_build.then_some(&config.lint);
+        let config_value =
+            serde_json::to_value((&config.compiler_options, lint)).unwrap_or_default();
+        BuildCache {
+            dir: dir.to_path_buf(),
+            config: Arc::new(config.clone()),
+            config_hash: content_hash(&config_value.to_string()),
+            option_hashes: option_hashes(&config_value),
+            file_system,
+            exports: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The cached output of a module, if it was compiled from this source
+    /// under the same configuration
+    pub fn load(&self, module: &Path, source: &str) -> Option<CompiledModule> {
+        let entry = self.entry(module)?;
+        self.stale(&entry, source)
+            .is_empty()
+            .then_some(entry.compiled)
+    }
+
+    /// Why the next build would compile `module` from `source` again,
+    /// rather than reuse its cached output, and what it depends on
+    pub fn status(&self, module: &Path, source: &str) -> CacheStatus {
+        let Some(entry) = self.entry(module) else {
+            return CacheStatus {
+                reasons: vec![RebuildReason::NotCached],
+                dependencies: Vec::new(),
+            };
+        };
+        CacheStatus {
+            reasons: self.stale(&entry, source),
+            dependencies: entry.compiled.dependencies(),
+        }
+    }
+
+    /// Every module with an entry, sorted
+    pub fn modules(&self) -> Vec<PathBuf> {
+        let entries = self.file_system.read_dir(&self.dir).unwrap_or_default();
+        let mut modules: Vec<PathBuf> = entries
+            .iter()
+            .filter(|entry| !entry.is_dir)
+            .filter_map(|entry| self.file_system.read_file(&entry.path).ok())
+            .filter_map(|text| serde_json::from_str::<CacheEntry>(&text).ok())
+            .map(|entry| entry.module)
+            .collect();
+        modules.sort();
+        modules
+    }
+
+    fn entry(&self, module: &Path) -> Option<CacheEntry> {
+        let text = self.file_system.read_file(&self.entry_path(module)).ok()?;
+        let entry: CacheEntry = serde_json::from_str(&text).ok()?;
+        (entry.module == module).then_some(entry)
+    }
+
+    /// What changed since `entry` was stored
+    fn stale(&self, entry: &CacheEntry, source: &str) -> Vec<RebuildReason> {
+        let mut reasons = Vec::new();
+        if entry.compiler_version != env!("CARGO_PKG_VERSION") {
+            reasons.push(RebuildReason::CompilerVersion(
+                entry.compiler_version.clone(),
+            ));
+        }
+        if entry.config_hash != self.config_hash {
+            let mut options: Vec<String> = Vec::new();
+            if !entry.option_hashes.is_empty() {
+                for name in entry.option_hashes.keys().chain(self.option_hashes.keys()) {
+                    let changed = entry.option_hashes.get(name) != self.option_hashes.get(name);
+                    if changed && !options.contains(name) {
+                        options.push(name.clone());
+                    }
+                }
+                options.sort();
+            }
+            reasons.push(RebuildReason::Configuration(options));
+        }
+        if entry.source_hash != content_hash(source) {
+            reasons.push(RebuildReason::Source);
+        }
+        for (path, hash) in &entry.compiled.declaration_files {
+            let unchanged = self
+                .file_system
+                .read_file(path)
+                .is_ok_and(|text| content_hash(&text) == *hash);
+            if !unchanged {
+                reasons.push(RebuildReason::DeclarationFile(path.clone()));
+            }
+        }
+        if !entry.compiled.typed_imports.is_empty() {
+            let exports = self.module_exports(&[(&entry.module, source)]);
+            for (path, hash) in &entry.compiled.typed_imports {
+                let unchanged = exports
+                    .get(path)
+                    .is_some_and(|declarations| content_hash(declarations) == *hash);
+                if !unchanged {
+                    reasons.push(RebuildReason::ImportedExports(path.clone()));
+                }
+            }
+        }
+        reasons
+    }
+
+    /// The declarations of the exports of the TypedLua modules `modules`
+    /// import, as [`compile_modules`] types them. Each module is checked
+    /// once per cache.
+    fn module_exports(&self, modules: &[(&Path, &str)]) -> Arc<HashMap<PathBuf, String>> {
+        let mut known = self.exports.lock().unwrap_or_else(PoisonError::into_inner);
+        let exports = module_exports(modules, &self.file_system, &self.config, known.clone());
+        *known = exports.clone();
+        Arc::new(exports)
+    }
+
+    /// The cached AST of a module, if it was parsed from this source
+    pub fn parsed(&self, module: &Path, source: &str) -> Option<ParsedModule> {
+        let text = self.file_system.read_file(&self.parsed_path(module)).ok()?;
+        let entry: ParsedEntry = serde_json::from_str(&text).ok()?;
+        let current = entry.module == module
+            && entry.compiler_version == env!("CARGO_PKG_VERSION")
+            && entry.source_hash == content_hash(source);
+        current.then_some(entry.parsed)
+    }
+
+    pub fn store_parsed(
+        &self,
+        module: &Path,
+        source: &str,
+        parsed: &ParsedModule,
+    ) -> Result<(), CompilationError> {
+        let entry = ParsedEntry {
+            module: module.to_path_buf(),
+            compiler_version: env!("CARGO_PKG_VERSION").to_string(),
+            source_hash: content_hash(source),
+            parsed: parsed.clone(),
+        };
+        let text = serde_json::to_string(&entry)
+            .map_err(|e| CompilationError::CodeGenError(e.to_string()))?;
+        self.file_system
+            .write_if_changed(&self.parsed_path(module), &text)?;
+        Ok(())
+    }
+
+    pub fn store(
+        &self,
+        module: &Path,
+        source: &str,
+        compiled: &CompiledModule,
+    ) -> Result<(), CompilationError> {
+        let entry = CacheEntry {
+            module: module.to_path_buf(),
+            compiler_version: env!("CARGO_PKG_VERSION").to_string(),
+            config_hash: self.config_hash,
+            option_hashes: self.option_hashes.clone(),
+            source_hash: content_hash(source),
+            compiled: compiled.clone(),
+        };
+        let text = serde_json::to_string(&entry)
+            .map_err(|e| CompilationError::CodeGenError(e.to_string()))?;
+        self.file_system
+            .write_if_changed(&self.entry_path(module), &text)?;
+        Ok(())
+    }
+
+    fn entry_path(&self, module: &Path) -> PathBuf {
+        let key = content_hash(&module.to_string_lossy());
+        self.dir.join(format!("{:016x}.json", key))
+    }
+
+    fn parsed_path(&self, module: &Path) -> PathBuf {
+        let key = content_hash(&module.to_string_lossy());
+        self.dir.join("parsed").join(format!("{:016x}.json", key))
+    }
+}
+
+/// What each module of a build depends on, for rebuilding only the modules
+/// a change to some files affects (`typedlua build --watch`). Paths are
+/// compared as given, so callers should normalize them the same way.
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    /// Each module with the files it depends on, in the order modules were
+    /// first added
+    modules: Vec<(PathBuf, Vec<PathBuf>)>,
+}
+
+impl DependencyGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set what `module` depends on, replacing what it depended on before
+    pub fn update(&mut self, module: &Path, dependencies: Vec<PathBuf>) {
+        match self.modules.iter_mut().find(|(path, _)| path == module) {
+            Some((_, existing)) => *existing = dependencies,
+            None => self.modules.push((module.to_path_buf(), dependencies)),
+        }
+    }
+
+    /// The modules that depend on `path` directly, in graph order
+    pub fn dependents(&self, path: &Path) -> Vec<PathBuf> {
+        self.modules
+            .iter()
+            .filter(|(_, dependencies)| dependencies.iter().any(|dependency| dependency == path))
+            .map(|(module, _)| module.clone())
+            .collect()
+    }
+
+    /// The modules to rebuild when `changed` files change: those that are
+    /// changed themselves and those depending on a changed file, directly
+    /// or through other modules of the graph
+    pub fn affected(&self, changed: &[PathBuf]) -> Vec<PathBuf> {
+        let mut dirty: Vec<&Path> = changed.iter().map(PathBuf::as_path).collect();
+        let mut affected: Vec<&Path> = Vec::new();
+        while let Some(path) = dirty.pop() {
+            for (module, dependencies) in &self.modules {
+                let hit =
+                    module == path || dependencies.iter().any(|dependency| dependency == path);
+                if hit && !affected.contains(&module.as_path()) {
+                    affected.push(module);
+                    dirty.push(module);
+                }
+            }
+        }
+        // In graph order, so rebuilds report modules in a stable order
+        self.modules
+            .iter()
+            .filter(|(module, _)| affected.contains(&module.as_path()))
+            .map(|(module, _)| module.clone())
+            .collect()
+    }
+}
+
+/// The hash of each compiler option, by its name in the configuration
+/// file, and of the lint options as `lint`
+fn option_hashes(config: &Value) -> BTreeMap<String, u64> {
+    let mut hashes = BTreeMap::new();
+    if let Some(options) = config.get(0).and_then(Value::as_object) {
+        for (name, value) in options {
+            hashes.insert(name.clone(), content_hash(&value.to_string()));
+        }
+    }
+    if let Some(lint) = config.get(1) {
+        hashes.insert("lint".to_string(), content_hash(&lint.to_string()));
+    }
+    hashes
+}
+
+/// 64-bit FNV-1a, stable across runs and compiler versions
+pub fn content_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::MockFileSystem;
+
+    fn compile(source: &str, config: &Arc<CompilerConfig>) -> CompiledModule {
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        compile_source(source, config.clone(), handler).expect("Compilation failed")
+    }
+
+    #[test]
+    fn test_compile_source_reports_exports() {
+        let config = Arc::new(CompilerConfig::default());
+        let compiled = compile(
+            "export function add(a: number, b: number): number\n    return a + b\nend\nconst limit = 10\nexport { limit as max }\n",
+            &config,
+        );
+        assert!(compiled.lua.contains("local function add(a, b)"));
+        assert_eq!(
+            compiled.exports,
+            vec![
+                (
+                    "add".to_string(),
+                    "(a: number, b: number) -> number".to_string()
+                ),
+                ("max".to_string(), "10".to_string()),
+            ]
+        );
+
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        assert!(compile_source("const x: string = 1", config, handler).is_err());
+        assert!(compiled.source_map.is_none());
+
+        let mut mapped = CompilerConfig::default();
+        mapped.compiler_options.source_map = true;
+        let compiled = compile(
+            "local x = 1\n\nif x > 0 then\n    print(x)\nend\n",
+            &Arc::new(mapped),
+        );
+        assert_eq!(
+            compiled.lua,
+            "local x = 1\nif x > 0 then\n  print(x)\nend\n"
+        );
+        let source_map = compiled.source_map.expect("Source map missing");
+        assert_eq!(source_map.lookup(1), Some((1, 1)));
+        assert_eq!(source_map.lookup(2), Some((3, 1)));
+        assert_eq!(source_map.lookup(3), Some((4, 5)));
+        assert_eq!(source_map.lookup(4), Some((3, 1)));
+    }
+
+    #[test]
+    fn test_parse_module_reports_where_lexing_stops() {
+        let cases = [
+            (
+                "local a = 1\nlocal s = \"open\nprint(s)\n",
+                "TL1004",
+                (22, 23, 2, 11),
+            ),
+            ("local t = `open ${a}", "TL1004", (10, 11, 1, 11)),
+            ("local n = 1__0", "TL1005", (10, 12, 1, 11)),
+        ];
+        for (source, code, (start, end, line, column)) in cases {
+            let handler = Arc::new(CollectingDiagnosticHandler::new());
+            let result = parse_module(source, handler.clone());
+            assert!(
+                matches!(result, Err(CompilationError::LexicalErrors(1))),
+                "{}",
+                source
+            );
+            let diagnostics = handler.get_diagnostics();
+            assert_eq!(diagnostics.len(), 1, "{}", source);
+            assert_eq!(diagnostics[0].code.as_deref(), Some(code));
+            assert_eq!(diagnostics[0].span, Span::new(start, end, line, column));
+        }
+    }
+
+    #[test]
+    fn test_tree_shaking_drops_unreferenced_declarations() {
+        let source = "enum Mode { Fast, Slow }\nfunction unused(): void end\nexport function mode(): Mode\n  return Mode.Fast\nend\n";
+        let compiled = compile(source, &Arc::new(CompilerConfig::default()));
+        assert!(compiled.lua.contains("local function unused()"));
+        assert!(compiled.removals.is_empty());
+
+        let mut config = CompilerConfig::default();
+        config.compiler_options.tree_shaking = true;
+        let compiled = compile(source, &Arc::new(config));
+        assert_eq!(
+            compiled.lua,
+            "local Mode = {\n  Fast = 1,\n}\nlocal function mode()\n  return Mode.Fast\nend\nreturn {\n  mode = mode,\n}\n"
+        );
+        let removed: Vec<(&str, usize)> = compiled
+            .removals
+            .iter()
+            .map(|removal| (removal.name.as_str(), removal.line))
+            .collect();
+        assert_eq!(removed, vec![("Mode.Slow", 1), ("unused", 2)]);
+    }
+
+    #[test]
+    fn test_readable_output_keeps_names_and_doc_comments() {
+        let source = "const greeting = \"hello\"\n\n/**\n * Greets someone\n */\nexport function greet(name: string): string\n    // plain comments stay behind\n    local message = greeting\n    message = message .. \" \" .. name\n    return message\nend\n";
+        let mut config = CompilerConfig::default();
+        config.compiler_options.readable_output = true;
+        let compiled = compile_file(
+            Path::new("src/main.tl"),
+            source,
+            Arc::new(MockFileSystem::new()),
+            Arc::new(config),
+            Arc::new(CollectingDiagnosticHandler::new()),
+        )
+        .expect("Compilation failed");
+        assert_eq!(
+            compiled.lua,
+            "-- from main.tl:1\nlocal greeting = \"hello\"\n-- from main.tl:6\n--- Greets someone\nlocal function greet(name)\n  -- from main.tl:8\n  local message = greeting\n  message = (message .. \" \") .. name\n  return message\nend\nreturn {\n  greet = greet,\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_compile_modules_keeps_input_order() {
+        let config = Arc::new(CompilerConfig::default());
+        let sources: Vec<String> = (0..20)
+            .map(|i| match i {
+                7 => "const x: string = 7".to_string(),
+                _ => format!("export const value{} = {}", i, i),
+            })
+            .collect();
+        let path = Path::new("src/main.tl");
+        let modules: Vec<(&Path, &str)> = sources.iter().map(|s| (path, s.as_str())).collect();
+
+        let outputs = compile_modules(&modules, Arc::new(MockFileSystem::new()), config, None);
+        assert_eq!(outputs.len(), 20);
+        for (i, (result, diagnostics)) in outputs.iter().enumerate() {
+            if i == 7 {
+                assert!(result.is_err());
+                assert_eq!(diagnostics.len(), 1);
+            } else {
+                let compiled = result.as_ref().expect("Compilation failed");
+                assert_eq!(compiled.exports[0].0, format!("value{}", i));
+                assert!(diagnostics.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_compile_modules_types_imports_of_typedlua_modules() {
+        // `label` is typed through `base`, which is read from disk rather
+        // than compiled alongside
+        let mut file_system = MockFileSystem::new();
+        let files = [
+            ("src/base.tl", "export const base: string = \"x\"\n"),
+            (
+                "src/label.tl",
+                "import { base } from \"./base\"\nexport function label()\n  return base\nend\n",
+            ),
+            (
+                "src/main.tl",
+                "import { label } from \"./label\"\nconst n: number = label()\n",
+            ),
+        ];
+        for (path, source) in files {
+            file_system.add_file(path, source);
+        }
+        let modules: Vec<(&Path, &str)> = files[1..]
+            .iter()
+            .map(|(path, source)| (Path::new(*path), *source))
+            .collect();
+
+        let outputs = compile_modules(
+            &modules,
+            Arc::new(file_system),
+            Arc::new(CompilerConfig::default()),
+            None,
+        );
+        assert!(outputs[0].0.is_ok());
+        let messages: Vec<&str> = outputs[1]
+            .1
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec!["Type mismatch: expected number, found string"]
+        );
+    }
+
+    #[test]
+    fn test_cache_reuses_unchanged_modules() {
+        let file_system: Arc<dyn FileSystem> = Arc::new(MockFileSystem::new());
+        let config = Arc::new(CompilerConfig::default());
+        let cache = BuildCache::new(Path::new("cache"), &config, file_system.clone());
+        let module = Path::new("src/main.tl");
+        let source = "const x = 1";
+        assert!(cache.load(module, source).is_none());
+
+        let compiled = compile(source, &config);
+        cache.store(module, source, &compiled).unwrap();
+        assert_eq!(cache.load(module, source), Some(compiled));
+        assert!(cache.load(module, "const x = 2").is_none());
+        assert!(cache.load(Path::new("src/other.tl"), source).is_none());
+
+        // Another configuration compiles differently, so misses
+        let mut other = CompilerConfig::default();
+        other.compiler_options.inline_constants = false;
+        let cache = BuildCache::new(Path::new("cache"), &other, file_system);
+        assert!(cache.load(module, source).is_none());
+    }
+
+    #[test]
+    fn test_cache_keys_on_the_exports_of_imported_modules() {
+        let mut file_system = MockFileSystem::new();
+        file_system.add_file(
+            "src/util.tl",
+            "export function name(): string\n  return \"a\"\nend\n",
+        );
+        let file_system: Arc<dyn FileSystem> = Arc::new(file_system);
+        let config = Arc::new(CompilerConfig::default());
+        let module = Path::new("src/main.tl");
+        let source = "import { name } from \"./util\"\nprint(name())\n";
+
+        let cache = BuildCache::new(Path::new("cache"), &config, file_system.clone());
+        let (result, _) = compile_modules(
+            &[(module, source)],
+            file_system.clone(),
+            config.clone(),
+            Some(&cache),
+        )
+        .remove(0);
+        let compiled = result.expect("Compilation failed");
+        cache.store(module, source, &compiled).unwrap();
+        assert!(cache.status(module, source).reasons.is_empty());
+
+        // A new body keeps the exports, so the importer is still reused
+        file_system
+            .write_file(
+                Path::new("src/util.tl"),
+                "export function name(): string\n  return \"b\"\nend\n",
+            )
+            .unwrap();
+        let cache = BuildCache::new(Path::new("cache"), &config, file_system.clone());
+        assert!(cache.status(module, source).reasons.is_empty());
+
+        file_system
+            .write_file(
+                Path::new("src/util.tl"),
+                "export function name(): number\n  return 1\nend\n",
+            )
+            .unwrap();
+        let cache = BuildCache::new(Path::new("cache"), &config, file_system.clone());
+        let reasons = cache.status(module, source).reasons;
+        assert_eq!(
+            reasons,
+            vec![RebuildReason::ImportedExports(PathBuf::from("src/util.tl"))]
+        );
+        assert_eq!(reasons[0].to_string(), "the exports of src/util.tl changed");
+    }
+
+    #[test]
+    fn test_cache_keeps_the_ast_of_unchanged_sources() {
+        let file_system: Arc<dyn FileSystem> = Arc::new(MockFileSystem::new());
+        let config = Arc::new(CompilerConfig::default());
+        let cache = BuildCache::new(Path::new("cache"), &config, file_system.clone());
+        let module = Path::new("src/main.tl");
+        let source = "// @lint-ignore unused-local\nlocal x = 1\n";
+        assert!(cache.parsed(module, source).is_none());
+
+        let modules = [(module, source)];
+        let outputs = compile_modules(&modules, file_system.clone(), config.clone(), Some(&cache));
+        assert!(outputs[0].0.is_ok());
+        let parsed = cache.parsed(module, source).expect("AST not cached");
+        assert_eq!(parsed.program.statements.len(), 1);
+        assert_eq!(parsed.directives[0].name, "lint-ignore");
+        assert_eq!(parsed.comments.len(), 1);
+        assert!(cache.parsed(module, "local x = 2\n").is_none());
+
+        // Compiling from the cached AST gives the same output
+        let again = compile_modules(&modules, file_system, config, Some(&cache));
+        assert_eq!(again[0].0.as_ref().ok(), outputs[0].0.as_ref().ok());
+    }
+
+    #[test]
+    fn test_cache_misses_when_declaration_files_change() {
+        let mut file_system = MockFileSystem::new();
+        file_system.add_file("src/socket.lua", "return {}");
+        file_system.add_file("src/socket.d.tl", "export declare const VERSION: string");
+        let file_system: Arc<dyn FileSystem> = Arc::new(file_system);
+        let config = Arc::new(CompilerConfig::default());
+        let cache = BuildCache::new(Path::new("cache"), &config, file_system.clone());
+        let module = Path::new("src/main.tl");
+        let source = "import { VERSION } from \"./socket\"\nconst version: string = VERSION";
+
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let compiled = compile_file(module, source, file_system.clone(), config, handler)
+            .expect("Compilation failed");
+        assert_eq!(compiled.declaration_files.len(), 1);
+        cache.store(module, source, &compiled).unwrap();
+        assert_eq!(cache.load(module, source), Some(compiled));
+
+        file_system
+            .write_file(
+                Path::new("src/socket.d.tl"),
+                "export declare const VERSION: number",
+            )
+            .unwrap();
+        assert!(cache.load(module, source).is_none());
+    }
+
+    #[test]
+    fn test_cache_explains_rebuilds() {
+        let mut file_system = MockFileSystem::new();
+        file_system.add_file("src/socket.lua", "return {}");
+        file_system.add_file("src/socket.d.tl", "export declare const VERSION: string");
+        let file_system: Arc<dyn FileSystem> = Arc::new(file_system);
+        let config = Arc::new(CompilerConfig::default());
+        let cache = BuildCache::new(Path::new("cache"), &config, file_system.clone());
+        let module = Path::new("src/main.tl");
+        let source = "import { VERSION } from \"./socket\"\nconst version: string = VERSION";
+        assert_eq!(
+            cache.status(module, source).reasons,
+            vec![RebuildReason::NotCached]
+        );
+
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let compiled = compile_file(module, source, file_system.clone(), config, handler)
+            .expect("Compilation failed");
+        cache.store(module, source, &compiled).unwrap();
+        let status = cache.status(module, source);
+        assert!(status.reasons.is_empty());
+        assert_eq!(status.dependencies, vec![PathBuf::from("src/socket.d.tl")]);
+        assert_eq!(cache.modules(), vec![module.to_path_buf()]);
+
+        let mut other = CompilerConfig::default();
+        other.compiler_options.inline_constants = false;
+        let cache = BuildCache::new(Path::new("cache"), &other, file_system.clone());
+        file_system
+            .write_file(
+                Path::new("src/socket.d.tl"),
+                "export declare const VERSION: number",
+            )
+            .unwrap();
+        let reasons = cache.status(module, "const x = 1").reasons;
+        assert_eq!(
+            reasons,
+            vec![
+                RebuildReason::Configuration(vec!["inlineConstants".to_string()]),
+                RebuildReason::Source,
+                RebuildReason::DeclarationFile(PathBuf::from("src/socket.d.tl")),
+            ]
+        );
+        assert_eq!(
+            reasons[0].to_string(),
+            "the configuration changed: inlineConstants"
+        );
+    }
+
+    #[test]
+    fn test_dependency_graph_finds_transitive_dependents() {
+        let mut file_system = MockFileSystem::new();
+        file_system.add_file("src/socket.lua", "return {}");
+        file_system.add_file("src/socket.d.tl", "export declare const VERSION: string");
+        file_system.add_file(
+            "src/util.tl",
+            "import { VERSION } from \"./socket\"\nexport const v = VERSION",
+        );
+        file_system.add_file("src/main.tl", "import { v } from \"./util\"\nprint(v)");
+        file_system.add_file("src/other.tl", "print(1)");
+        let file_system: Arc<dyn FileSystem> = Arc::new(file_system);
+        let config = Arc::new(CompilerConfig::default());
+
+        let mut graph = DependencyGraph::new();
+        for path in ["src/main.tl", "src/util.tl", "src/other.tl"] {
+            let path = Path::new(path);
+            let source = file_system.read_file(path).unwrap();
+            let handler = Arc::new(CollectingDiagnosticHandler::new());
+            let compiled =
+                compile_file(path, &source, file_system.clone(), config.clone(), handler)
+                    .expect("Compilation failed");
+            graph.update(path, compiled.dependencies());
+        }
+
+        let affected = |changed: &str| graph.affected(&[PathBuf::from(changed)]);
+        assert_eq!(
+            affected("src/socket.d.tl"),
+            vec![PathBuf::from("src/main.tl"), PathBuf::from("src/util.tl")]
+        );
+        assert_eq!(affected("src/main.tl"), vec![PathBuf::from("src/main.tl")]);
+        assert_eq!(
+            affected("src/other.tl"),
+            vec![PathBuf::from("src/other.tl")]
+        );
+        assert!(affected("README.md").is_empty());
+    }
+}
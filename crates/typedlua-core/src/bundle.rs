@@ -0,0 +1,567 @@
+//! Bundling a program and every module it imports into one self-contained
+//! Lua file (`typedlua bundle`), for hosts such as LÖVE where the program
+//! cannot set `package.path`. Programs with several entry points get one
+//! bundle each, with the modules they share in a shared chunk.
+
+use crate::ast::statement::{ImportClause, Statement};
+use crate::build::compile_bundled;
+use crate::codegen::quote_string;
+use crate::config::CompilerConfig;
+use crate::diagnostics::{CollectingDiagnosticHandler, Diagnostic, DiagnosticHandler};
+use crate::errors::CompilationError;
+use crate::fs::FileSystem;
+use crate::lexer::Lexer;
+use crate::minify::minify;
+use crate::parser::Parser;
+use crate::resolve::{ModuleResolver, ResolvedModule};
+use crate::shake::{Removal, UsedExports};
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// Loads bundled modules on first use, as `require` would, and hands each
+/// module a `require` that finds the modules it imports in the bundle
+const LOADER: &str = "local __modules, __loaded, __loading = {}, {}, {}
+local function __load(id)
+  local value = __loaded[id]
+  if value == nil then
+    if __loading[id] then
+      error(\"loop loading module '\" .. id .. \"'\", 2)
+    end
+    __loading[id] = true
+    value = __modules[id](id)
+    if value == nil then
+      value = true
+    end
+    __loaded[id] = value
+  end
+  return value
+end
+local function __requirer(imports)
+  return function(name)
+    local id = imports[name]
+    if id == nil then
+      return require(name)
+    end
+    return __load(id)
+  end
+end
+";
+
+/// Returned by a shared chunk, so the bundles requiring it register their
+/// modules with its loader
+const SHARED_EXPORTS: &str =
+    "return { modules = __modules, load = __load, requirer = __requirer }\n";
+
+/// A module of the bundle, found by following imports from the entries
+struct BundledModule {
+    path: PathBuf,
+    id: String,
+    source: String,
+    /// Import paths as written in the module, with the modules they name
+    imports: Vec<(String, usize)>,
+    /// The exports its importers use, deciding what tree shaking keeps
+    used_exports: UsedExports,
+}
+
+/// A bundle's Lua, with what tree shaking left out of each module
+#[derive(Debug)]
+pub struct Bundle {
+    pub lua: String,
+    pub removals: Vec<(PathBuf, Removal)>,
+}
+
+/// Bundles for several entry modules, with the modules more than one of
+/// them imports split out into a shared chunk
+#[derive(Debug)]
+pub struct SplitBundle {
+    /// The shared chunk, or `None` when the entries share no module
+    pub shared: Option<String>,
+    /// Each entry's bundle, in the order the entries were given
+    pub entries: Vec<(PathBuf, String)>,
+    pub removals: Vec<(PathBuf, Removal)>,
+}
+
+/// Compile `entry` and the modules it imports, directly or not, into one
+/// Lua file that returns what the entry module returns. Each module is tree
+/// shaken down to the exports its importers use. Plain Lua modules are
+/// copied in as they are. Imports that resolve to no file, such as C
+/// modules, are left to the host's `require`. Each module's diagnostics
+/// are passed to `report` with its path; the first module with errors
+/// stops the bundle. With the `minify` option the whole file is minified.
+pub fn bundle(
+    entry: &Path,
+    file_system: Arc<dyn FileSystem>,
+    config: Arc<CompilerConfig>,
+    report: &mut dyn FnMut(&Path, &Diagnostic),
+) -> Result<Bundle, CompilationError> {
+    let entry = normalize(entry);
+    let modules = module_graph(std::slice::from_ref(&entry), &file_system)?;
+    let mut removals = Vec::new();
+    let compiled = compile_modules(&modules, &file_system, &config, report, &mut removals)?;
+
+    let mut lua = LOADER.to_string();
+    for (module, code) in modules.iter().zip(&compiled) {
+        push_module(&mut lua, module, code, &modules);
+    }
+    lua.push_str(&format!(
+        "return __load({})\n",
+        quote_string(&module_id(&entry))
+    ));
+    Ok(Bundle {
+        lua: finish(lua, &config),
+        removals,
+    })
+}
+
+/// Bundle several entry modules, such as a game's client, server and tool
+/// scripts, as `bundle` does each one. Modules more than one entry imports,
+/// directly or not, are compiled once into a shared chunk holding the
+/// loader, so no library is bundled twice. Each entry's bundle requires the
+/// chunk by `shared_name` before loading its own modules. Tree shaking
+/// keeps the exports any entry uses.
+pub fn bundle_split(
+    entries: &[PathBuf],
+    shared_name: &str,
+    file_system: Arc<dyn FileSystem>,
+    config: Arc<CompilerConfig>,
+    report: &mut dyn FnMut(&Path, &Diagnostic),
+) -> Result<SplitBundle, CompilationError> {
+    let entries: Vec<PathBuf> = entries.iter().map(|entry| normalize(entry)).collect();
+    let modules = module_graph(&entries, &file_system)?;
+    let mut removals = Vec::new();
+    let compiled = compile_modules(&modules, &file_system, &config, report, &mut removals)?;
+
+    let reached: Vec<Vec<bool>> = entries
+        .iter()
+        .map(|entry| {
+            let index = modules
+                .iter()
+                .position(|module| module.path == *entry)
+                .expect("entries are in the graph");
+            reachable(&modules, index)
+        })
+        .collect();
+    let shared: Vec<bool> = (0..modules.len())
+        .map(|index| reached.iter().filter(|reach| reach[index]).count() > 1)
+        .collect();
+
+    let shared_chunk = shared.contains(&true).then(|| {
+        let mut lua = LOADER.to_string();
+        for (index, module) in modules.iter().enumerate() {
+            if shared[index] {
+                push_module(&mut lua, module, &compiled[index], &modules);
+            }
+        }
+        lua.push_str(SHARED_EXPORTS);
+        finish(lua, &config)
+    });
+
+    let mut bundles = Vec::new();
+    for (entry, reach) in entries.iter().zip(&reached) {
+        let mut lua = match shared_chunk {
+            Some(_) => format!(
+                "local __shared = require({})\nlocal __modules, __load, __requirer = __shared.modules, __shared.load, __shared.requirer\n",
+                quote_string(shared_name)
+            ),
+            None => LOADER.to_string(),
+        };
+        for (index, module) in modules.iter().enumerate() {
+            if reach[index] && !shared[index] {
+                push_module(&mut lua, module, &compiled[index], &modules);
+            }
+        }
+        lua.push_str(&format!(
+            "return __load({})\n",
+            quote_string(&module_id(entry))
+        ));
+        bundles.push((entry.clone(), finish(lua, &config)));
+    }
+    Ok(SplitBundle {
+        shared: shared_chunk,
+        entries: bundles,
+        removals,
+    })
+}
+
+/// The Lua of every module, in graph order. TypedLua modules are compiled
+/// and tree shaken; plain Lua modules are kept as they are.
+fn compile_modules(
+    modules: &[BundledModule],
+    file_system: &Arc<dyn FileSystem>,
+    config: &Arc<CompilerConfig>,
+    report: &mut dyn FnMut(&Path, &Diagnostic),
+    removals: &mut Vec<(PathBuf, Removal)>,
+) -> Result<Vec<String>, CompilationError> {
+    let mut compiled = Vec::new();
+    for module in modules {
+        if !is_typed_lua(&module.path) {
+            compiled.push(module.source.clone());
+            continue;
+        }
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let result = compile_bundled(
+            &module.path,
+            &module.source,
+            file_system.clone(),
+            config.clone(),
+            handler.clone(),
+            module.used_exports.clone(),
+        );
+        for diagnostic in handler.get_diagnostics() {
+            report(&module.path, &diagnostic);
+        }
+        let module_lua = result?;
+        removals.extend(
+            module_lua
+                .removals
+                .into_iter()
+                .map(|removal| (module.path.clone(), removal)),
+        );
+        compiled.push(module_lua.lua);
+    }
+    Ok(compiled)
+}
+
+/// Append a module, wrapped in a function the loader calls on first use
+fn push_module(bundle: &mut String, module: &BundledModule, lua: &str, modules: &[BundledModule]) {
+    bundle.push_str(&format!(
+        "__modules[{}] = function(...)\n",
+        quote_string(&module.id)
+    ));
+    if !module.imports.is_empty() {
+        let imports: Vec<String> = module
+            .imports
+            .iter()
+            .map(|(import, index)| {
+                format!(
+                    "[{}] = {}",
+                    quote_string(import),
+                    quote_string(&modules[*index].id)
+                )
+            })
+            .collect();
+        bundle.push_str(&format!(
+            "local require = __requirer({{ {} }})\n",
+            imports.join(", ")
+        ));
+    }
+    // Left unindented, so long strings keep their content
+    bundle.push_str(lua);
+    if !lua.ends_with('\n') {
+        bundle.push('\n');
+    }
+    bundle.push_str("end\n");
+}
+
+fn finish(lua: String, config: &CompilerConfig) -> String {
+    let options = &config.compiler_options;
+    if options.minify && !options.readable_output {
+        // Modules are minified as they compile; this shrinks the loader
+        // and any plain Lua modules
+        minify(&lua, &config.compiler_options)
+    } else {
+        lua
+    }
+}
+
+/// Which modules `from` imports, directly or not, itself included
+fn reachable(modules: &[BundledModule], from: usize) -> Vec<bool> {
+    let mut reached = vec![false; modules.len()];
+    let mut pending = vec![from];
+    while let Some(index) = pending.pop() {
+        if !std::mem::replace(&mut reached[index], true) {
+            pending.extend(modules[index].imports.iter().map(|(_, target)| *target));
+        }
+    }
+    reached
+}
+
+/// Every module reachable from the entries, entries first, with the
+/// exports their importers use. Imports are read before any module is
+/// compiled, so a module's exports are known to be unused before it is
+/// emitted.
+fn module_graph(
+    entries: &[PathBuf],
+    file_system: &Arc<dyn FileSystem>,
+) -> Result<Vec<BundledModule>, CompilationError> {
+    let resolver = ModuleResolver::new(file_system.clone());
+    let mut indexes = HashMap::new();
+    let mut modules = Vec::new();
+    for entry in entries {
+        if indexes.contains_key(entry) {
+            continue;
+        }
+        indexes.insert(entry.clone(), modules.len());
+        modules.push(BundledModule {
+            id: module_id(entry),
+            source: file_system.read_file(entry)?,
+            path: entry.clone(),
+            imports: Vec::new(),
+            used_exports: UsedExports::All,
+        });
+    }
+
+    let mut next = 0;
+    while next < modules.len() {
+        let path = modules[next].path.clone();
+        if is_typed_lua(&path) {
+            for (import, names) in runtime_imports(&modules[next].source) {
+                let Some(target) = runtime_file(&resolver, &path, &import) else {
+                    continue;
+                };
+                let target = normalize(&target);
+                let index = match indexes.get(&target) {
+                    Some(index) => *index,
+                    None => {
+                        indexes.insert(target.clone(), modules.len());
+                        modules.push(BundledModule {
+                            id: module_id(&target),
+                            source: file_system.read_file(&target)?,
+                            path: target,
+                            imports: Vec::new(),
+                            used_exports: UsedExports::Only(HashSet::new()),
+                        });
+                        modules.len() - 1
+                    }
+                };
+                modules[index].used_exports.add(names);
+                modules[next].imports.push((import, index));
+            }
+        }
+        next += 1;
+    }
+    Ok(modules)
+}
+
+/// The imports of a module that load it at runtime, with the names each
+/// one uses, or `None` for the whole module. A module that does not parse
+/// imports nothing here; compiling it reports why.
+fn runtime_imports(source: &str) -> Vec<(String, Option<Vec<String>>)> {
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let program = Lexer::new(source, handler.clone())
+        .tokenize()
+        .ok()
+        .and_then(|tokens| Parser::new(tokens, handler).parse().ok());
+    let Some(program) = program else {
+        return Vec::new();
+    };
+    program
+        .statements
+        .iter()
+        .filter_map(|statement| {
+            let Statement::Import(import) = statement else {
+                return None;
+            };
+            let names = match &import.clause {
+                ImportClause::TypeOnly(_) => return None,
+                ImportClause::Named(specifiers) => Some(
+                    specifiers
+                        .iter()
+                        .map(|specifier| specifier.imported.node.clone())
+                        .collect(),
+                ),
+                ImportClause::Default(_) | ImportClause::Namespace(_) => None,
+            };
+            Some((import.source.clone(), names))
+        })
+        .collect()
+}
+
+/// The file that runs when `import` is required from `importer`. A
+/// declaration file only types a module, so the `.lua` next to it runs.
+fn runtime_file(resolver: &ModuleResolver, importer: &Path, import: &str) -> Option<PathBuf> {
+    match resolver.resolve(importer, import)? {
+        ResolvedModule::TypedLua(path) | ResolvedModule::Lua(path) => Some(path),
+        ResolvedModule::Declaration(path) => {
+            let lua = PathBuf::from(path.to_string_lossy().replace(".d.tl", ".lua"));
+            resolver.file_system().exists(&lua).then_some(lua)
+        }
+    }
+}
+
+fn is_typed_lua(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "tl")
+}
+
+/// A module's name in the bundle: its path without the extension, with
+/// `/` separators on every platform
+fn module_id(path: &Path) -> String {
+    let path = path.with_extension("");
+    let parts: Vec<String> = path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    parts.join("/")
+}
+
+/// Collapse `.` and `..`, so a module reached by two paths is bundled once
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::MockFileSystem;
+
+    fn bundle_files(files: &[(&str, &str)], entry: &str) -> Result<Bundle, Vec<String>> {
+        let mut file_system = MockFileSystem::new();
+        for (path, content) in files {
+            file_system.add_file(*path, *content);
+        }
+        let mut errors = Vec::new();
+        let result = bundle(
+            Path::new(entry),
+            Arc::new(file_system),
+            Arc::new(CompilerConfig::default()),
+            &mut |path, diagnostic| {
+                errors.push(format!("{}: {}", path.display(), diagnostic.message))
+            },
+        );
+        result.map_err(|_| errors)
+    }
+
+    #[test]
+    fn test_bundle_wraps_each_imported_module_once() {
+        let lua = bundle_files(
+            &[
+                (
+                    "src/main.tl",
+                    "import { greet } from \"./greet\"\nimport json from \"./lib/json\"\nimport socket from \"socket\"\nprint(greet(json.encode({})))",
+                ),
+                (
+                    "src/greet.tl",
+                    "import json from \"./lib/json\"\nexport function greet(name: string): string\n  return \"hi \" .. name\nend",
+                ),
+                ("src/lib/json.lua", "return { encode = function() return \"{}\" end }"),
+                ("src/lib/json.d.tl", "export declare function encode(value: unknown): string"),
+            ],
+            "src/main.tl",
+        )
+        .expect("Bundling failed")
+        .lua;
+
+        assert!(lua.starts_with(LOADER), "{}", lua);
+        assert!(lua.contains(
+            "__modules[\"src/main\"] = function(...)\nlocal require = __requirer({ [\"./greet\"] = \"src/greet\", [\"./lib/json\"] = \"src/lib/json\" })\nlocal _greet = require(\"./greet\")\n"
+        ), "{}", lua);
+        assert!(lua.contains(
+            "__modules[\"src/greet\"] = function(...)\nlocal require = __requirer({ [\"./lib/json\"] = \"src/lib/json\" })\n"
+        ), "{}", lua);
+        assert!(lua.contains(
+            "__modules[\"src/lib/json\"] = function(...)\nreturn { encode = function() return \"{}\" end }\nend\n"
+        ), "{}", lua);
+        assert_eq!(lua.matches("__modules[\"src/lib/json\"] =").count(), 1);
+        assert!(lua.ends_with("return __load(\"src/main\")\n"));
+    }
+
+    #[test]
+    fn test_split_bundle_shares_common_modules() {
+        let mut file_system = MockFileSystem::new();
+        let files = [
+            ("client.tl", "import { clamp } from \"./lib/math\"\nimport { draw } from \"./ui\"\ndraw(clamp(2))"),
+            ("server.tl", "import { clamp, lerp } from \"./lib/math\"\nprint(lerp(clamp(1), 2))"),
+            ("ui.tl", "export function draw(n: number): nil\n  print(n)\nend"),
+            ("lib/math.tl", "export function clamp(n: number): number\n  return n\nend\nexport function lerp(a: number, b: number): number\n  return a + b\nend\nexport function unused(): number\n  return 0\nend"),
+        ];
+        for (path, content) in files {
+            file_system.add_file(path, content);
+        }
+        let split = bundle_split(
+            &[PathBuf::from("client.tl"), PathBuf::from("server.tl")],
+            "shared",
+            Arc::new(file_system),
+            Arc::new(CompilerConfig::default()),
+            &mut |_, _| {},
+        )
+        .expect("Bundling failed");
+
+        let shared = split.shared.expect("No shared chunk");
+        assert!(shared.starts_with(LOADER), "{}", shared);
+        assert!(shared.contains("__modules[\"lib/math\"] = function(...)\n"));
+        assert!(
+            shared.contains("clamp = clamp,\n  lerp = lerp,\n"),
+            "{}",
+            shared
+        );
+        assert!(shared.ends_with(SHARED_EXPORTS));
+
+        let (path, client) = &split.entries[0];
+        assert_eq!(path, Path::new("client.tl"));
+        assert!(
+            client.starts_with("local __shared = require(\"shared\")\n"),
+            "{}",
+            client
+        );
+        assert!(client.contains("__modules[\"ui\"] = function(...)\n"));
+        assert!(!client.contains("__modules[\"lib/math\"]"));
+        assert!(client.ends_with("return __load(\"client\")\n"));
+        let (_, server) = &split.entries[1];
+        assert!(!server.contains("__modules[\"ui\"]"), "{}", server);
+        assert!(server.contains("__modules[\"server\"] = function(...)\nlocal require = __requirer({ [\"./lib/math\"] = \"lib/math\" })\n"));
+    }
+
+    #[test]
+    fn test_bundle_stops_at_a_module_with_errors() {
+        let errors = bundle_files(
+            &[
+                ("main.tl", "import broken from \"./broken\""),
+                ("broken.tl", "const x: string = 1"),
+            ],
+            "main.tl",
+        );
+        assert_eq!(errors.unwrap_err().len(), 1);
+        assert!(bundle_files(&[], "missing.tl").is_err());
+    }
+
+    #[test]
+    fn test_bundle_shakes_exports_no_module_imports() {
+        let bundle = bundle_files(
+            &[
+                (
+                    "main.tl",
+                    "import { add } from \"./math\"\nexport function run(): number\n  return add(1, 2)\nend",
+                ),
+                (
+                    "math.tl",
+                    "function check(n: number): number\n  return n\nend\nexport function add(a: number, b: number): number\n  return a + b\nend\nexport function sub(a: number, b: number): number\n  return check(a - b)\nend",
+                ),
+            ],
+            "main.tl",
+        )
+        .expect("Bundling failed");
+        assert!(bundle.lua.contains(
+            "__modules[\"math\"] = function(...)\nlocal function add(a, b)\n  return a + b\nend\nreturn {\n  add = add,\n}\nend\n"
+        ), "{}", bundle.lua);
+        let removed: Vec<String> = bundle
+            .removals
+            .iter()
+            .map(|(path, removal)| {
+                format!("{} {} {}", path.display(), removal.name, removal.reason)
+            })
+            .collect();
+        assert_eq!(
+            removed,
+            vec![
+                "math.tl check only referenced by removed code",
+                "math.tl sub exported but never imported",
+            ]
+        );
+    }
+}
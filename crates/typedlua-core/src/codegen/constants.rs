@@ -0,0 +1,77 @@
+use super::expression::format_literal;
+use super::pattern::collect_bound_names;
+use super::CodeGenerator;
+use crate::ast::expression::{Expression, Literal};
+use crate::ast::pattern::Pattern;
+use crate::ast::statement::{VariableDeclaration, VariableKind};
+use crate::constant;
+
+impl CodeGenerator {
+    fn inlines_constants(&self) -> bool {
+        let options = &self.config.compiler_options;
+        options.inline_constants && !options.readable_output
+    }
+
+    pub(super) fn enter_constant_scope(&mut self) {
+        self.constants.push(Default::default());
+    }
+
+    pub(super) fn exit_constant_scope(&mut self) {
+        self.constants.pop();
+    }
+
+    /// A new binding hides any constant of the same name
+    pub(super) fn shadow_constant(&mut self, name: &str) {
+        if let Some(scope) = self.constants.last_mut() {
+            scope.insert(name.to_string(), None);
+        }
+    }
+
+    pub(super) fn shadow_pattern_constants(&mut self, pattern: &Pattern) {
+        let mut names = Vec::new();
+        collect_bound_names(pattern, &mut names);
+        for name in names {
+            self.shadow_constant(&name);
+        }
+    }
+
+    fn constant_value(&self, name: &str) -> Option<Literal> {
+        self.constants
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .cloned()
+            .flatten()
+    }
+
+    /// The folded value of a `const` initialized from literals and other
+    /// constants, which is then inlined wherever the name is read
+    pub(super) fn fold_constant(&mut self, decl: &VariableDeclaration) -> Option<String> {
+        let Pattern::Identifier(name) = &decl.pattern else {
+            return None;
+        };
+        if decl.kind != VariableKind::Const || !self.inlines_constants() {
+            return None;
+        }
+        let value = constant::evaluate(&decl.initializer, &|name| self.constant_value(name))?;
+        let code = format_literal(&value, self.target());
+        if let Some(scope) = self.constants.last_mut() {
+            scope.insert(name.node.clone(), Some(value));
+        }
+        Some(code)
+    }
+
+    /// The literal for a read of a folded constant. Negative numbers are
+    /// parenthesized so they cannot merge with a preceding `-` into a comment.
+    pub(super) fn inlined_constant(&self, expr: &Expression) -> Option<String> {
+        let crate::ast::expression::ExpressionKind::Identifier(name) = &expr.kind else {
+            return None;
+        };
+        let code = format_literal(&self.constant_value(name)?, self.target());
+        if code.starts_with('-') {
+            Some(format!("({})", code))
+        } else {
+            Some(code)
+        }
+    }
+}
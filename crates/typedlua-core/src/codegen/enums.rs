@@ -0,0 +1,87 @@
+use super::CodeGenerator;
+use crate::ast::expression::{Expression, ExpressionKind};
+use crate::ast::statement::ENUM_HELPERS;
+
+impl CodeGenerator {
+    /// `Enum.values()`, `Enum.fromName(name)` and `Enum.ordinal(value)`
+    /// expand in place to lookups over the declared members, so the enum's
+    /// table holds nothing but its members
+    pub(super) fn enum_helper_call(&self, callee: &Expression, arguments: &str) -> Option<String> {
+        let (helper, members) = self.enum_helper(callee)?;
+        let argument = if arguments.is_empty() {
+            "nil"
+        } else {
+            arguments
+        };
+        Some(expand_helper(helper, members, argument))
+    }
+
+    /// A helper read without being called becomes a function doing the same
+    pub(super) fn enum_helper_value(&self, expr: &Expression) -> Option<String> {
+        let (helper, members) = self.enum_helper(expr)?;
+        Some(match helper {
+            "values" => format!(
+                "function() return {} end",
+                expand_helper(helper, members, "")
+            ),
+            _ => format!(
+                "function(value) return {} end",
+                expand_helper(helper, members, "value")
+            ),
+        })
+    }
+
+    pub(super) fn is_enum_helper_call(&self, expr: &Expression) -> bool {
+        match &expr.kind {
+            ExpressionKind::Call(callee, _) => self.enum_helper(callee).is_some(),
+            _ => false,
+        }
+    }
+
+    /// The helper an `Enum.name` access refers to, with the enum's members.
+    /// A member declared with the same name wins.
+    fn enum_helper(&self, expr: &Expression) -> Option<(&'static str, &[(String, String)])> {
+        let ExpressionKind::Member(object, member) = &expr.kind else {
+            return None;
+        };
+        let ExpressionKind::Identifier(name) = &object.kind else {
+            return None;
+        };
+        let members = self.enums.get(name)?;
+        let helper = ENUM_HELPERS
+            .iter()
+            .copied()
+            .find(|helper| *helper == member.node)?;
+        if members.iter().any(|(name, _)| name == helper) {
+            return None;
+        }
+        Some((helper, members))
+    }
+}
+
+/// The lookup table a helper reads, indexed by the argument: member values
+/// in order, values by member name, or positions by value
+fn expand_helper(helper: &str, members: &[(String, String)], argument: &str) -> String {
+    let entries: Vec<String> = match helper {
+        "values" => members.iter().map(|(_, value)| value.clone()).collect(),
+        "fromName" => members
+            .iter()
+            .map(|(name, value)| format!("{} = {}", name, value))
+            .collect(),
+        _ => members
+            .iter()
+            .enumerate()
+            .map(|(i, (_, value))| format!("[{}] = {}", value, i + 1))
+            .collect(),
+    };
+    let table = if entries.is_empty() {
+        "{}".to_string()
+    } else {
+        format!("{{ {} }}", entries.join(", "))
+    };
+    if helper == "values" {
+        table
+    } else {
+        format!("({})[{}]", table, argument)
+    }
+}
@@ -0,0 +1,777 @@
+use super::helpers::RuntimeHelper;
+use super::random::RANDOM;
+use super::CodeGenerator;
+use crate::ast::expression::*;
+use crate::ast::statement::Block;
+use crate::ast::Ident;
+use crate::config::LuaVersion;
+use crate::features::LuaFeature;
+
+// Lua operator precedence, lowest to highest
+const PREC_OR: u8 = 1;
+const PREC_AND: u8 = 2;
+const PREC_COMPARE: u8 = 3;
+const PREC_BOR: u8 = 4;
+const PREC_BXOR: u8 = 5;
+const PREC_BAND: u8 = 6;
+const PREC_SHIFT: u8 = 7;
+const PREC_CONCAT: u8 = 8;
+const PREC_ADD: u8 = 9;
+const PREC_MUL: u8 = 10;
+const PREC_UNARY: u8 = 11;
+const PREC_POW: u8 = 12;
+const PREC_ATOM: u8 = 13;
+
+impl CodeGenerator {
+    pub(super) fn generate_expression(&mut self, expr: &Expression) -> String {
+        match &expr.kind {
+            ExpressionKind::Identifier(name) if self.inline_arguments.contains_key(name) => {
+                self.inline_arguments[name].clone()
+            }
+            ExpressionKind::Identifier(name) if name == RANDOM && !self.random_declared => {
+                self.use_helper(RuntimeHelper::Random);
+                name.clone()
+            }
+            ExpressionKind::Identifier(name) => match self.lazy_imports.get(name) {
+                Some(loaded) => loaded.clone(),
+                None => self.inlined_constant(expr).unwrap_or_else(|| name.clone()),
+            },
+            // Only LuaJIT has 64-bit integer cdata literals
+            ExpressionKind::Literal(Literal::SuffixedInteger(value, suffix))
+                if self.target().supports(LuaFeature::Int64Literals) =>
+            {
+                format_suffixed_integer(*value, *suffix)
+            }
+            ExpressionKind::Literal(literal) => {
+                let code = format_literal(literal, self.target());
+                self.pool_constant(expr, code)
+            }
+            ExpressionKind::SelfKeyword => "self".to_string(),
+            ExpressionKind::SuperKeyword => self.super_reference(),
+            ExpressionKind::Binary(op, left, right) => {
+                if let Some(operator) = self.checked_operator(*op) {
+                    let left = self.generate_expression(left);
+                    let right = self.generate_expression(right);
+                    return self.checked_arithmetic(operator, &left, &right);
+                }
+                if let Some(call) = self.lowered_binary(*op, left, right) {
+                    return call;
+                }
+                let prec = binary_precedence(*op);
+                let right_assoc = matches!(op, BinaryOp::Concatenate | BinaryOp::Power);
+                let left_code = self.generate_operand(left, prec, right_assoc);
+                let right_code = self.generate_operand(right, prec, !right_assoc);
+                format!("{} {} {}", left_code, binary_operator(*op), right_code)
+            }
+            ExpressionKind::Unary(op, operand) => {
+                if let Some(call) = self.lowered_unary(*op, operand) {
+                    return call;
+                }
+                let operand = self.generate_operand(operand, PREC_UNARY, false);
+                match op {
+                    UnaryOp::Not => format!("not {}", operand),
+                    UnaryOp::Negate => format!("-{}", operand),
+                    UnaryOp::Length => format!("#{}", operand),
+                    UnaryOp::BitwiseNot => format!("~{}", operand),
+                }
+            }
+            ExpressionKind::Assignment(target, op, value) => {
+                // Assignments are statements in Lua; in expression position
+                // perform the assignment and yield the assigned value
+                let assignment = self.generate_assignment(target, *op, value);
+                let target = self.generate_expression(target);
+                format!("(function() {} return {} end)()", assignment, target)
+            }
+            ExpressionKind::Member(..) | ExpressionKind::OptionalMember(..)
+                if is_optional_chain(expr) =>
+            {
+                self.generate_optional_chain(expr)
+            }
+            ExpressionKind::OptionalMember(..) => unreachable!("optional member is a chain"),
+            ExpressionKind::Member(object, member) => {
+                if let Some(helper) = self.enum_helper_value(expr) {
+                    return helper;
+                }
+                if let Some(value) = self.const_enum_member(object, member) {
+                    return value;
+                }
+                if is_super(object) {
+                    return format!("{}.{}", self.super_reference(), member.node);
+                }
+                format!("{}.{}", self.generate_prefix(object), member.node)
+            }
+            ExpressionKind::Index(object, index) => {
+                let object = self.generate_prefix(object);
+                let index = self.generate_expression(index);
+                format!("{}[{}]", object, index)
+            }
+            ExpressionKind::Call(callee, args) => self.generate_call(callee, args),
+            ExpressionKind::MethodCall(object, method, args) => {
+                let args = self.generate_arguments(args);
+                if is_super(object) {
+                    let args = if args.is_empty() {
+                        "self".to_string()
+                    } else {
+                        format!("self, {}", args)
+                    };
+                    return format!("{}.{}({})", self.super_reference(), method.node, args);
+                }
+                format!("{}:{}({})", self.generate_prefix(object), method.node, args)
+            }
+            ExpressionKind::Array(elements) => {
+                let code = self.generate_array(elements);
+                self.pool_constant(expr, code)
+            }
+            ExpressionKind::Object(properties) => {
+                let code = self.generate_object(properties);
+                self.pool_constant(expr, code)
+            }
+            ExpressionKind::Function(func) => {
+                let generics = self.enter_luau_generics(func.type_parameters.as_deref());
+                let params = self.generate_parameter_names(&func.parameters);
+                let returns = self.luau_return_annotation(func.return_type.as_ref());
+                let body = self.capture(|gen| {
+                    gen.dedent();
+                    gen.generate_function_body(&func.parameters, &func.body);
+                    gen.indent();
+                });
+                self.exit_luau_generics();
+                format!(
+                    "function{}({}){}\n{}{}end",
+                    generics,
+                    params,
+                    returns,
+                    body,
+                    self.indentation()
+                )
+            }
+            ExpressionKind::Arrow(arrow) => {
+                let signature = format!(
+                    "({}){}",
+                    self.generate_parameter_names(&arrow.parameters),
+                    self.luau_return_annotation(arrow.return_type.as_ref())
+                );
+                match &arrow.body {
+                    ArrowBody::Expression(body) if is_simple_parameter_list(arrow) => {
+                        self.enter_constant_scope();
+                        for param in &arrow.parameters {
+                            self.shadow_pattern_constants(&param.pattern);
+                        }
+                        let body = self.generate_expression(body);
+                        self.exit_constant_scope();
+                        format!("function{} return {} end", signature, body)
+                    }
+                    ArrowBody::Expression(body) => {
+                        let block = Block {
+                            statements: vec![crate::ast::statement::Statement::Return(
+                                crate::ast::statement::ReturnStatement {
+                                    values: vec![(**body).clone()],
+                                    span: body.span,
+                                },
+                            )],
+                            span: body.span,
+                        };
+                        self.generate_function_literal(&signature, &arrow.parameters, &block)
+                    }
+                    ArrowBody::Block(block) => {
+                        self.generate_function_literal(&signature, &arrow.parameters, block)
+                    }
+                }
+            }
+            ExpressionKind::Conditional(condition, then_expr, else_expr) => {
+                let condition = self.generate_expression(condition);
+                let then_code = self.generate_operand(then_expr, PREC_AND, true);
+                let else_code = self.generate_operand(else_expr, PREC_OR, true);
+                if is_always_truthy(then_expr) {
+                    // `c and a or b` is only safe when `a` can never be falsy
+                    format!("{} and {} or {}", condition, then_code, else_code)
+                } else {
+                    format!(
+                        "(function() if {} then return {} else return {} end end)()",
+                        condition, then_code, else_code
+                    )
+                }
+            }
+            ExpressionKind::Pipe(value, function) => self.generate_pipe(value, function),
+            ExpressionKind::Match(match_expr) => self.generate_match(match_expr),
+            ExpressionKind::Parenthesized(inner) => {
+                format!("({})", self.generate_expression(inner))
+            }
+            ExpressionKind::Template(template) => self.generate_template(template),
+            ExpressionKind::TypeAssertion(inner, _) => self.generate_expression(inner),
+        }
+    }
+
+    /// Generate a sub-expression, parenthesizing it when its precedence is
+    /// lower than the surrounding operator requires
+    fn generate_operand(&mut self, expr: &Expression, parent: u8, wrap_equal: bool) -> String {
+        let code = self.generate_expression(expr);
+        let prec = expression_precedence(expr);
+        if prec < parent || (wrap_equal && prec == parent) {
+            format!("({})", code)
+        } else {
+            code
+        }
+    }
+
+    /// Generate an expression that must be a Lua prefix expression, such as
+    /// the object of a call or member access
+    fn generate_prefix(&mut self, expr: &Expression) -> String {
+        let code = self.generate_expression(expr);
+        if is_optional_chain(expr)
+            || self.is_const_enum_member(expr)
+            || self.is_enum_helper_call(expr)
+            || self.inlined_constant(expr).is_some()
+        {
+            return format!("({})", code);
+        }
+        match &expr.kind {
+            ExpressionKind::Identifier(_)
+            | ExpressionKind::SelfKeyword
+            | ExpressionKind::Member(..)
+            | ExpressionKind::Index(..)
+            | ExpressionKind::Call(..)
+            | ExpressionKind::MethodCall(..)
+            | ExpressionKind::Parenthesized(_) => code,
+            ExpressionKind::TypeAssertion(inner, _) => {
+                if matches!(inner.kind, ExpressionKind::Identifier(_)) {
+                    code
+                } else {
+                    format!("({})", code)
+                }
+            }
+            _ => format!("({})", code),
+        }
+    }
+
+    /// The inlined literal for a `const enum` member access
+    fn const_enum_member(&self, object: &Expression, member: &Ident) -> Option<String> {
+        match &object.kind {
+            ExpressionKind::Identifier(name) => self
+                .const_enums
+                .get(name.as_str())?
+                .get(member.node.as_str())
+                .cloned(),
+            _ => None,
+        }
+    }
+
+    fn is_const_enum_member(&self, expr: &Expression) -> bool {
+        match &expr.kind {
+            ExpressionKind::Member(object, member) => {
+                self.const_enum_member(object, member).is_some()
+            }
+            _ => false,
+        }
+    }
+
+    /// `a?.b?.c` tests each optional link inline (`a and a.b and a.b.c`).
+    /// Deep chains use the shared safe-access helper when `nilSafeHelpers`
+    /// is on, as do chains whose root must only be evaluated once.
+    fn generate_optional_chain(&mut self, expr: &Expression) -> String {
+        let mut links = Vec::new();
+        let mut root = expr;
+        loop {
+            match &root.kind {
+                ExpressionKind::Member(object, member) => {
+                    links.push((member.node.as_str(), false));
+                    root = object;
+                }
+                ExpressionKind::OptionalMember(object, member) => {
+                    links.push((member.node.as_str(), true));
+                    root = object;
+                }
+                _ => break,
+            }
+        }
+        links.reverse();
+
+        let root_code = self.generate_prefix(root);
+        let deep = links.len() > 1 && self.config.compiler_options.nil_safe_helpers;
+        if deep || !is_name_path(root) {
+            let keys: Vec<&str> = links.iter().map(|(name, _)| *name).collect();
+            return self.safe_get(root_code, &keys);
+        }
+
+        let mut checks = Vec::new();
+        let mut path = root_code;
+        for (name, optional) in links {
+            if optional {
+                checks.push(path.clone());
+            }
+            path = format!("{}.{}", path, name);
+        }
+        checks.push(path);
+        checks.join(" and ")
+    }
+
+    fn super_reference(&self) -> String {
+        self.class_base
+            .clone()
+            .unwrap_or_else(|| "super".to_string())
+    }
+
+    pub(super) fn generate_assignment(
+        &mut self,
+        target: &Expression,
+        op: AssignmentOp,
+        value: &Expression,
+    ) -> String {
+        let target_code = self.generate_expression(target);
+        let binary = match op {
+            AssignmentOp::Assign => None,
+            AssignmentOp::AddAssign => Some(BinaryOp::Add),
+            AssignmentOp::SubtractAssign => Some(BinaryOp::Subtract),
+            AssignmentOp::MultiplyAssign => Some(BinaryOp::Multiply),
+            AssignmentOp::DivideAssign => Some(BinaryOp::Divide),
+            AssignmentOp::ModuloAssign => Some(BinaryOp::Modulo),
+            AssignmentOp::ConcatenateAssign => Some(BinaryOp::Concatenate),
+        };
+
+        match binary {
+            None => format!("{} = {}", target_code, self.generate_expression(value)),
+            Some(op) => {
+                if let Some(operator) = self.checked_operator(op) {
+                    let value = self.generate_expression(value);
+                    let value = self.checked_arithmetic(operator, &target_code, &value);
+                    return format!("{} = {}", target_code, value);
+                }
+                let prec = binary_precedence(op);
+                let right_assoc = op == BinaryOp::Concatenate;
+                let value = self.generate_operand(value, prec, !right_assoc);
+                format!(
+                    "{} = {} {} {}",
+                    target_code,
+                    target_code,
+                    binary_operator(op),
+                    value
+                )
+            }
+        }
+    }
+
+    fn generate_call(&mut self, callee: &Expression, args: &[Argument]) -> String {
+        if let Some(call) = self.inline_call(callee, args) {
+            return call;
+        }
+        let arguments = self.generate_arguments(args);
+
+        // `super(...)` runs the base class initializer on this instance
+        if is_super(callee) {
+            let args = if arguments.is_empty() {
+                "self".to_string()
+            } else {
+                format!("self, {}", arguments)
+            };
+            return format!("{}._init({})", self.super_reference(), args);
+        }
+
+        // `super.method(...)` calls the base implementation with this instance
+        if let ExpressionKind::Member(object, method) = &callee.kind {
+            if is_super(object) {
+                let args = if arguments.is_empty() {
+                    "self".to_string()
+                } else {
+                    format!("self, {}", arguments)
+                };
+                return format!("{}.{}({})", self.super_reference(), method.node, args);
+            }
+        }
+
+        if let Some(call) = self.enum_helper_call(callee, &arguments) {
+            return call;
+        }
+        format!("{}({})", self.generate_prefix(callee), arguments)
+    }
+
+    fn generate_arguments(&mut self, args: &[Argument]) -> String {
+        let args: Vec<String> = args
+            .iter()
+            .map(|arg| {
+                let value = self.generate_expression(&arg.value);
+                if arg.is_spread {
+                    format!("{}({})", self.unpack_function(), value)
+                } else {
+                    value
+                }
+            })
+            .collect();
+        args.join(", ")
+    }
+
+    pub(super) fn unpack_function(&self) -> &'static str {
+        if self.target().supports(LuaFeature::TableUnpack) {
+            "table.unpack"
+        } else {
+            "unpack"
+        }
+    }
+
+    /// A function expression with the given parenthesized parameters and
+    /// return annotation
+    fn generate_function_literal(
+        &mut self,
+        signature: &str,
+        parameters: &[crate::ast::statement::Parameter],
+        body: &Block,
+    ) -> String {
+        let body = self.capture(|gen| {
+            gen.dedent();
+            gen.generate_function_body(parameters, body);
+            gen.indent();
+        });
+        format!("function{}\n{}{}end", signature, body, self.indentation())
+    }
+
+    fn generate_array(&mut self, elements: &[ArrayElement]) -> String {
+        let has_spread = elements
+            .iter()
+            .any(|e| matches!(e, ArrayElement::Spread(_)));
+
+        if !has_spread {
+            let items: Vec<String> = elements
+                .iter()
+                .map(|e| match e {
+                    ArrayElement::Expression(e) | ArrayElement::Spread(e) => {
+                        self.generate_expression(e)
+                    }
+                })
+                .collect();
+            return format!("{{{}}}", items.join(", "));
+        }
+
+        // Spreads are copied element by element into a fresh table
+        let body = self.capture(|gen| {
+            gen.write_line("local result = {}");
+            for element in elements {
+                match element {
+                    ArrayElement::Expression(e) => {
+                        let value = gen.generate_expression(e);
+                        gen.write_line(&format!("result[#result + 1] = {}", value));
+                    }
+                    ArrayElement::Spread(e) => {
+                        let value = gen.generate_expression(e);
+                        gen.write_line(&format!(
+                            "for _, v in ipairs({}) do result[#result + 1] = v end",
+                            value
+                        ));
+                    }
+                }
+            }
+            gen.write_line("return result");
+        });
+        format!("(function()\n{}{}end)()", body, self.indentation())
+    }
+
+    fn generate_object(&mut self, properties: &[ObjectProperty]) -> String {
+        let has_spread = properties
+            .iter()
+            .any(|p| matches!(p, ObjectProperty::Spread { .. }));
+
+        if !has_spread {
+            let fields: Vec<String> = properties.iter().map(|p| self.generate_field(p)).collect();
+            return format!("{{{}}}", fields.join(", "));
+        }
+
+        // Later properties override fields copied from earlier spreads
+        let body = self.capture(|gen| {
+            gen.write_line("local result = {}");
+            for property in properties {
+                match property {
+                    ObjectProperty::Spread { value, .. } => {
+                        let value = gen.generate_expression(value);
+                        gen.write_line(&format!(
+                            "for k, v in pairs({}) do result[k] = v end",
+                            value
+                        ));
+                    }
+                    ObjectProperty::Property { key, value, .. } => {
+                        let value = gen.generate_expression(value);
+                        gen.write_line(&format!("result.{} = {}", key.node, value));
+                    }
+                    ObjectProperty::Computed { key, value, .. } => {
+                        let key = gen.generate_expression(key);
+                        let value = gen.generate_expression(value);
+                        gen.write_line(&format!("result[{}] = {}", key, value));
+                    }
+                }
+            }
+            gen.write_line("return result");
+        });
+        format!("(function()\n{}{}end)()", body, self.indentation())
+    }
+
+    fn generate_field(&mut self, property: &ObjectProperty) -> String {
+        match property {
+            ObjectProperty::Property { key, value, .. } => {
+                format!("{} = {}", key.node, self.generate_expression(value))
+            }
+            ObjectProperty::Computed { key, value, .. } => {
+                let key = self.generate_expression(key);
+                format!("[{}] = {}", key, self.generate_expression(value))
+            }
+            ObjectProperty::Spread { value, .. } => self.generate_expression(value),
+        }
+    }
+
+    /// `x |> f` is `f(x)`; `x |> f(a)` inserts `x` as the first argument
+    fn generate_pipe(&mut self, value: &Expression, function: &Expression) -> String {
+        let value = self.generate_expression(value);
+        match &function.kind {
+            ExpressionKind::Call(callee, args) => {
+                let callee = self.generate_prefix(callee);
+                let args = self.generate_arguments(args);
+                if args.is_empty() {
+                    format!("{}({})", callee, value)
+                } else {
+                    format!("{}({}, {})", callee, value, args)
+                }
+            }
+            ExpressionKind::MethodCall(object, method, args) => {
+                let object = self.generate_prefix(object);
+                let args = self.generate_arguments(args);
+                if args.is_empty() {
+                    format!("{}:{}({})", object, method.node, value)
+                } else {
+                    format!("{}:{}({}, {})", object, method.node, value, args)
+                }
+            }
+            _ => format!("{}({})", self.generate_prefix(function), value),
+        }
+    }
+
+    fn generate_template(&mut self, template: &TemplateLiteral) -> String {
+        if template
+            .parts
+            .iter()
+            .any(|part| matches!(part, TemplatePart::Formatted(..)))
+        {
+            return self.generate_formatted_template(template);
+        }
+        let parts: Vec<String> = template
+            .parts
+            .iter()
+            .filter(|part| !matches!(part, TemplatePart::String(s) if s.is_empty()))
+            .map(|part| match part {
+                TemplatePart::String(s) => quote_string(s),
+                TemplatePart::Expression(e) => {
+                    format!("tostring({})", self.generate_expression(e))
+                }
+                TemplatePart::Formatted(..) => unreachable!("handled by string.format"),
+            })
+            .collect();
+
+        if parts.is_empty() {
+            "\"\"".to_string()
+        } else {
+            parts.join(" .. ")
+        }
+    }
+
+    /// A template with a format specifier becomes one `string.format` call;
+    /// plain interpolations format through `%s`
+    fn generate_formatted_template(&mut self, template: &TemplateLiteral) -> String {
+        let mut format = String::new();
+        let mut arguments = Vec::new();
+        for part in &template.parts {
+            match part {
+                TemplatePart::String(s) => format.push_str(&s.replace('%', "%%")),
+                TemplatePart::Expression(e) => {
+                    format.push_str("%s");
+                    arguments.push(format!("tostring({})", self.generate_expression(e)));
+                }
+                TemplatePart::Formatted(e, specifier) => {
+                    format.push_str(specifier);
+                    arguments.push(self.generate_expression(e));
+                }
+            }
+        }
+        format!(
+            "string.format({}, {})",
+            quote_string(&format),
+            arguments.join(", ")
+        )
+    }
+}
+
+/// Whether a member access contains a `?.` link anywhere along its chain
+fn is_optional_chain(expr: &Expression) -> bool {
+    match &expr.kind {
+        ExpressionKind::OptionalMember(..) => true,
+        ExpressionKind::Member(object, _) => is_optional_chain(object),
+        _ => false,
+    }
+}
+
+/// Names and plain field paths, which are cheap and safe to repeat
+fn is_name_path(expr: &Expression) -> bool {
+    match &expr.kind {
+        ExpressionKind::Identifier(_) | ExpressionKind::SelfKeyword => true,
+        ExpressionKind::Member(object, _) => is_name_path(object),
+        _ => false,
+    }
+}
+
+fn is_super(expr: &Expression) -> bool {
+    match &expr.kind {
+        ExpressionKind::SuperKeyword => true,
+        ExpressionKind::Identifier(name) => name == "super",
+        _ => false,
+    }
+}
+
+fn is_simple_parameter_list(arrow: &ArrowFunction) -> bool {
+    arrow.parameters.iter().all(|p| {
+        !p.is_rest
+            && p.default.is_none()
+            && matches!(p.pattern, crate::ast::pattern::Pattern::Identifier(_))
+    })
+}
+
+/// Expressions that can never evaluate to `nil` or `false`
+fn is_always_truthy(expr: &Expression) -> bool {
+    match &expr.kind {
+        ExpressionKind::Literal(Literal::Nil)
+        | ExpressionKind::Literal(Literal::Boolean(false)) => false,
+        ExpressionKind::Literal(_)
+        | ExpressionKind::Array(_)
+        | ExpressionKind::Object(_)
+        | ExpressionKind::Function(_)
+        | ExpressionKind::Arrow(_)
+        | ExpressionKind::Template(_) => true,
+        ExpressionKind::Binary(BinaryOp::Concatenate, ..) => true,
+        ExpressionKind::Parenthesized(inner) => is_always_truthy(inner),
+        _ => false,
+    }
+}
+
+fn binary_precedence(op: BinaryOp) -> u8 {
+    match op {
+        BinaryOp::Or => PREC_OR,
+        BinaryOp::And => PREC_AND,
+        BinaryOp::Equal
+        | BinaryOp::NotEqual
+        | BinaryOp::LessThan
+        | BinaryOp::LessThanOrEqual
+        | BinaryOp::GreaterThan
+        | BinaryOp::GreaterThanOrEqual => PREC_COMPARE,
+        BinaryOp::BitwiseOr => PREC_BOR,
+        BinaryOp::BitwiseXor => PREC_BXOR,
+        BinaryOp::BitwiseAnd => PREC_BAND,
+        BinaryOp::ShiftLeft | BinaryOp::ShiftRight => PREC_SHIFT,
+        BinaryOp::Concatenate => PREC_CONCAT,
+        BinaryOp::Add | BinaryOp::Subtract => PREC_ADD,
+        BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::IntegerDivide | BinaryOp::Modulo => {
+            PREC_MUL
+        }
+        BinaryOp::Power => PREC_POW,
+    }
+}
+
+fn expression_precedence(expr: &Expression) -> u8 {
+    match &expr.kind {
+        ExpressionKind::Binary(op, ..) => binary_precedence(*op),
+        ExpressionKind::Unary(..) => PREC_UNARY,
+        ExpressionKind::Member(..) | ExpressionKind::OptionalMember(..)
+            if is_optional_chain(expr) =>
+        {
+            PREC_AND
+        }
+        ExpressionKind::Conditional(_, then_expr, _) if is_always_truthy(then_expr) => PREC_OR,
+        ExpressionKind::TypeAssertion(inner, _) => expression_precedence(inner),
+        _ => PREC_ATOM,
+    }
+}
+
+fn binary_operator(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "+",
+        BinaryOp::Subtract => "-",
+        BinaryOp::Multiply => "*",
+        BinaryOp::Divide => "/",
+        BinaryOp::Modulo => "%",
+        BinaryOp::IntegerDivide => "//",
+        BinaryOp::Power => "^",
+        BinaryOp::Equal => "==",
+        BinaryOp::NotEqual => "~=",
+        BinaryOp::LessThan => "<",
+        BinaryOp::LessThanOrEqual => "<=",
+        BinaryOp::GreaterThan => ">",
+        BinaryOp::GreaterThanOrEqual => ">=",
+        BinaryOp::And => "and",
+        BinaryOp::Or => "or",
+        BinaryOp::Concatenate => "..",
+        BinaryOp::BitwiseAnd => "&",
+        BinaryOp::BitwiseOr => "|",
+        BinaryOp::BitwiseXor => "~",
+        BinaryOp::ShiftLeft => "<<",
+        BinaryOp::ShiftRight => ">>",
+    }
+}
+
+/// Lua source for a literal. Integers too large for an `i64` are wrapped
+/// only on targets with an integer subtype, as their own Lua would read them.
+pub(super) fn format_literal(literal: &Literal, target: LuaVersion) -> String {
+    match literal {
+        Literal::Nil => "nil".to_string(),
+        Literal::Boolean(b) => b.to_string(),
+        Literal::Number(n) => format_float(*n),
+        // Without LuaJIT's cdata or an integer subtype, a `ULL` value past
+        // `i64::MAX` is read as a float
+        Literal::SuffixedInteger(i, IntegerSuffix::Unsigned)
+            if *i < 0 && !target.supports(LuaFeature::IntegerSubtype) =>
+        {
+            format_float(*i as u64 as f64)
+        }
+        Literal::Integer(i) | Literal::SuffixedInteger(i, _) => i.to_string(),
+        Literal::WrappedInteger(i, _) if target.supports(LuaFeature::IntegerSubtype) => {
+            i.to_string()
+        }
+        Literal::WrappedInteger(_, n) => format_float(*n),
+        Literal::String(s) => quote_string(s),
+    }
+}
+
+/// A float literal keeps its fractional part, so Lua 5.3+ does not read a
+/// whole-valued float back as an integer
+fn format_float(n: f64) -> String {
+    let text = format_number(n);
+    if n.is_finite() && n.fract() == 0.0 {
+        format!("{}.0", text)
+    } else {
+        text
+    }
+}
+
+pub(super) fn format_number(n: f64) -> String {
+    if n.is_nan() {
+        "(0 / 0)".to_string()
+    } else if n.is_infinite() {
+        if n > 0.0 {
+            "math.huge".to_string()
+        } else {
+            "-math.huge".to_string()
+        }
+    } else {
+        n.to_string()
+    }
+}
+
+pub(crate) fn quote_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '\0' => quoted.push_str("\\0"),
+            c if c.is_control() => quoted.push_str(&format!("\\{}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
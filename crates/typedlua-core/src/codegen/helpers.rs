@@ -0,0 +1,257 @@
+use super::expression::quote_string;
+use super::random::{random_source, RANDOM};
+use super::CodeGenerator;
+use crate::ast::types::{Type, TypeKind};
+use crate::config::LuaVersion;
+
+/// Support functions emitted once at the top of modules that use them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum RuntimeHelper {
+    /// Walks a chain of keys, returning nil as soon as a link is nil
+    SafeGet,
+    /// Copies members of mixin classes into a class table without
+    /// overwriting members it already has
+    Mixin,
+    /// Wraps a table in a proxy that reads through to it (freezing nested
+    /// tables too) and raises on writes
+    Freeze,
+    /// Runs the rest of a block after a `using` in a protected call, then
+    /// closes the value and rethrows any error
+    Using,
+    /// Performs `+`, `-` or `*` and raises if the result left the exact
+    /// integer range: wrapped around for integers, past 2^53 for floats
+    CheckedArithmetic,
+    /// The runtime's seeded `Random` class, a PCG32 generator that gives
+    /// the same sequence on every target
+    Random,
+}
+
+impl RuntimeHelper {
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            RuntimeHelper::SafeGet => "__safe_get",
+            RuntimeHelper::Mixin => "__mixin",
+            RuntimeHelper::Freeze => "__freeze",
+            RuntimeHelper::Using => "__using",
+            RuntimeHelper::CheckedArithmetic => "__checked_arith",
+            RuntimeHelper::Random => RANDOM,
+        }
+    }
+
+    fn source(self, target: LuaVersion) -> String {
+        let source = match self {
+            RuntimeHelper::SafeGet => {
+                "local function __safe_get(value, ...)
+  for i = 1, select(\"#\", ...) do
+    if value == nil then
+      return nil
+    end
+    value = value[select(i, ...)]
+  end
+  return value
+end
+"
+            }
+            RuntimeHelper::Mixin => {
+                "local function __mixin(target, ...)
+  for i = 1, select(\"#\", ...) do
+    for key, value in pairs((select(i, ...))) do
+      if rawget(target, key) == nil then
+        target[key] = value
+      end
+    end
+  end
+  return target
+end
+"
+            }
+            RuntimeHelper::Freeze => {
+                "local function __freeze(value)
+  if type(value) ~= \"table\" then
+    return value
+  end
+  return setmetatable({}, {
+    __index = function(_, key)
+      return __freeze(value[key])
+    end,
+    __newindex = function(_, key)
+      error(\"attempt to modify read-only field '\" .. tostring(key) .. \"'\", 2)
+    end,
+    __len = function()
+      return #value
+    end,
+    __pairs = function()
+      return function(_, key)
+        local next_key, next_value = next(value, key)
+        return next_key, __freeze(next_value)
+      end, nil, nil
+    end,
+  })
+end
+"
+            }
+            RuntimeHelper::Using => {
+                "local function __using(resource, body)
+  local function finish(ok, returned, ...)
+    if resource then
+      local meta = getmetatable(resource)
+      if type(meta) == \"table\" and meta.__close then
+        meta.__close(resource, (not ok) and returned or nil)
+      else
+        resource:close()
+      end
+    end
+    if not ok then
+      error(returned, 0)
+    end
+    if returned then
+      return { n = select(\"#\", ...), ... }
+    end
+  end
+  return finish(pcall(body))
+end
+"
+            }
+            RuntimeHelper::CheckedArithmetic => {
+                "local function __checked_arith(operator, a, b)
+  local result, approx
+  if operator == \"+\" then
+    result, approx = a + b, (a + 0.0) + b
+  elseif operator == \"-\" then
+    result, approx = a - b, (a + 0.0) - b
+  else
+    result, approx = a * b, (a + 0.0) * b
+  end
+  local overflow
+  if math.type and math.type(result) == \"integer\" then
+    -- Near the limits, a wrapped result has the wrong sign
+    overflow = math.abs(approx) >= 2 ^ 64
+      or (math.abs(approx) >= 2 ^ 62 and (result >= 0) ~= (approx >= 0))
+  else
+    overflow = math.abs(result) > 2 ^ 53 and a % 1 == 0 and b % 1 == 0
+  end
+  if overflow then
+    error(\"integer overflow: \" .. tostring(a) .. \" \" .. operator .. \" \" .. tostring(b), 2)
+  end
+  return result
+end
+"
+            }
+            RuntimeHelper::Random => return random_source(target),
+        };
+        source.to_string()
+    }
+}
+
+impl CodeGenerator {
+    pub(super) fn use_helper(&mut self, helper: RuntimeHelper) {
+        self.helpers.insert(helper);
+    }
+
+    /// Definitions of every helper used by the module, in a stable order,
+    /// then the specialized ones in order of first use
+    pub(super) fn helper_prelude(&mut self) -> String {
+        let helpers = std::mem::take(&mut self.helpers);
+        let safe_get_paths = std::mem::take(&mut self.safe_get_paths);
+        let target = self.target();
+        helpers
+            .iter()
+            .map(|helper| helper.source(target))
+            .chain(
+                safe_get_paths
+                    .iter()
+                    .enumerate()
+                    .map(|(i, keys)| specialized_safe_get(&safe_get_name(i), keys)),
+            )
+            .collect()
+    }
+
+    /// At `optimizationLevel` 2 and above, helpers are specialized to each
+    /// use instead of taking their arguments through `...`
+    pub(super) fn specializes_helpers(&self) -> bool {
+        self.config.compiler_options.optimization_level >= 2
+    }
+
+    /// A call walking `keys` from `root`, returning nil at the first nil
+    /// link
+    pub(super) fn safe_get(&mut self, root: String, keys: &[&str]) -> String {
+        if !self.specializes_helpers() {
+            self.use_helper(RuntimeHelper::SafeGet);
+            let keys: Vec<String> = keys.iter().map(|key| quote_string(key)).collect();
+            return format!(
+                "{}({}, {})",
+                RuntimeHelper::SafeGet.name(),
+                root,
+                keys.join(", ")
+            );
+        }
+        let keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+        let index = match self.safe_get_paths.iter().position(|path| *path == keys) {
+            Some(index) => index,
+            None => {
+                self.safe_get_paths.push(keys);
+                self.safe_get_paths.len() - 1
+            }
+        };
+        format!("{}({})", safe_get_name(index), root)
+    }
+
+    /// Copy the members of mixin classes into `class`, keeping members it
+    /// already has
+    pub(super) fn generate_mixins(&mut self, class: &str, mixins: &[String]) {
+        if !self.specializes_helpers() {
+            self.use_helper(RuntimeHelper::Mixin);
+            self.write_line(&format!(
+                "{}({}, {})",
+                RuntimeHelper::Mixin.name(),
+                class,
+                mixins.join(", ")
+            ));
+            return;
+        }
+        for mixin in mixins {
+            self.write_line(&format!(
+                "for key, value in pairs({}) do if rawget({}, key) == nil then {}[key] = value end end",
+                mixin, class, class
+            ));
+        }
+    }
+
+    /// Wrap a value in `__freeze` when it is declared read-only and
+    /// `freezeReadonly` is enabled
+    pub(super) fn freeze_if_readonly(&mut self, typ: &Type, value: String) -> String {
+        if !self.config.compiler_options.freeze_readonly || !is_readonly_type(typ) {
+            return value;
+        }
+        self.use_helper(RuntimeHelper::Freeze);
+        format!("{}({})", RuntimeHelper::Freeze.name(), value)
+    }
+}
+
+fn safe_get_name(index: usize) -> String {
+    format!("{}{}", RuntimeHelper::SafeGet.name(), index + 1)
+}
+
+/// `__safe_get` with its keys unrolled
+fn specialized_safe_get(name: &str, keys: &[String]) -> String {
+    let mut source = format!("local function {}(value)\n", name);
+    for (i, key) in keys.iter().enumerate() {
+        source.push_str("  if value == nil then\n    return nil\n  end\n");
+        if i + 1 == keys.len() {
+            source.push_str(&format!("  return value.{}\n", key));
+        } else {
+            source.push_str(&format!("  value = value.{}\n", key));
+        }
+    }
+    source + "end\n"
+}
+
+fn is_readonly_type(typ: &Type) -> bool {
+    match &typ.kind {
+        TypeKind::Reference(reference) => {
+            matches!(reference.name.node.as_str(), "Readonly" | "ReadonlyTable")
+        }
+        TypeKind::Parenthesized(inner) => is_readonly_type(inner),
+        _ => false,
+    }
+}
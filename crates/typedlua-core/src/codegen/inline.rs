@@ -0,0 +1,75 @@
+use super::CodeGenerator;
+use crate::ast::attributes::{has_attribute, inline_body};
+use crate::ast::expression::{Argument, Expression, ExpressionKind};
+use crate::ast::pattern::Pattern;
+use crate::ast::statement::FunctionDeclaration;
+
+impl CodeGenerator {
+    /// Remember an `@inline` function so calls to it can be replaced by its
+    /// body. The function itself is still emitted for other uses.
+    pub(super) fn register_inline(&mut self, func: &FunctionDeclaration) {
+        if !has_attribute(&func.decorators, "inline") {
+            self.inline_functions.remove(&func.name.node);
+            return;
+        }
+        let Some(body) = inline_body(func) else {
+            return;
+        };
+        let parameters = func
+            .parameters
+            .iter()
+            .filter_map(|param| match &param.pattern {
+                Pattern::Identifier(name) => Some(name.node.clone()),
+                _ => None,
+            })
+            .collect();
+        self.inline_functions
+            .insert(func.name.node.clone(), (parameters, body.clone()));
+    }
+
+    /// A call to an `@inline` function, expanded to its returned expression
+    /// with the arguments in place of the parameters. Only arguments that
+    /// are names or literals are substituted, so none is evaluated twice or
+    /// dropped.
+    pub(super) fn inline_call(&mut self, callee: &Expression, args: &[Argument]) -> Option<String> {
+        let ExpressionKind::Identifier(name) = &callee.kind else {
+            return None;
+        };
+        let (parameters, body) = self.inline_functions.get(name)?;
+        let simple = args.iter().all(|arg| {
+            !arg.is_spread
+                && matches!(
+                    arg.value.kind,
+                    ExpressionKind::Identifier(_) | ExpressionKind::Literal(_)
+                )
+        });
+        if parameters.len() != args.len() || !simple {
+            return None;
+        }
+        let (parameters, body) = (parameters.clone(), body.clone());
+
+        let mut bindings = Vec::new();
+        for (parameter, arg) in parameters.into_iter().zip(args) {
+            bindings.push((parameter, self.generate_expression(&arg.value)));
+        }
+        // A recursive call inside the body stays a call
+        let entry = self.inline_functions.remove(name);
+        let saved = std::mem::replace(&mut self.inline_arguments, bindings.into_iter().collect());
+        let code = self.generate_expression(&body);
+        self.inline_arguments = saved;
+        if let Some(entry) = entry {
+            self.inline_functions.insert(name.clone(), entry);
+        }
+        Some(format!("({})", code))
+    }
+
+    pub(super) fn is_inline_call(&self, expr: &Expression) -> bool {
+        match &expr.kind {
+            ExpressionKind::Call(callee, _) => matches!(
+                &callee.kind,
+                ExpressionKind::Identifier(name) if self.inline_functions.contains_key(name)
+            ),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use super::CodeGenerator;
+use crate::ast::statement::*;
+use crate::features::LuaFeature;
+
+/// A labeled `break` or `continue` that leaves a block: its label, whether
+/// it continues, and how many loops inside the block it crosses
+type LabeledJump<'a> = (&'a str, bool, usize);
+
+impl CodeGenerator {
+    /// Whether the target can jump straight to a label. Lua 5.1 and Luau
+    /// have no `goto`, so labeled jumps set a flag and break loop by loop.
+    fn has_goto(&self) -> bool {
+        self.target().supports(LuaFeature::Goto)
+    }
+
+    /// Whether the target has a native `continue`, as Luau does
+    fn has_continue(&self) -> bool {
+        self.target().supports(LuaFeature::Continue)
+    }
+
+    pub(super) fn generate_loop(&mut self, statement: &Statement, label: Option<&str>) {
+        match statement {
+            Statement::While(while_stmt) => {
+                let condition = self.generate_expression(&while_stmt.condition);
+                self.write_line(&format!("while {} do", condition));
+                self.generate_loop_body(&while_stmt.body, label);
+                self.write_line("end");
+            }
+            Statement::For(ForStatement::Numeric(numeric)) => {
+                let start = self.generate_expression(&numeric.start);
+                let end = self.generate_expression(&numeric.end);
+                let header = match &numeric.step {
+                    Some(step) => {
+                        let step = self.generate_expression(step);
+                        format!(
+                            "for {} = {}, {}, {} do",
+                            numeric.variable.node, start, end, step
+                        )
+                    }
+                    None => format!("for {} = {}, {} do", numeric.variable.node, start, end),
+                };
+                self.write_line(&header);
+                self.enter_constant_scope();
+                self.shadow_constant(&numeric.variable.node);
+                self.generate_loop_body(&numeric.body, label);
+                self.exit_constant_scope();
+                self.write_line("end");
+            }
+            Statement::For(ForStatement::Generic(generic)) => {
+                let variables: Vec<&str> =
+                    generic.variables.iter().map(|v| v.node.as_str()).collect();
+                let iterators: Vec<String> = generic
+                    .iterators
+                    .iter()
+                    .map(|e| self.generate_expression(e))
+                    .collect();
+                self.write_line(&format!(
+                    "for {} in {} do",
+                    variables.join(", "),
+                    iterators.join(", ")
+                ));
+                self.enter_constant_scope();
+                for variable in &generic.variables {
+                    self.shadow_constant(&variable.node);
+                }
+                self.generate_loop_body(&generic.body, label);
+                self.exit_constant_scope();
+                self.write_line("end");
+            }
+            Statement::Repeat(repeat) => {
+                self.write_line("repeat");
+                self.generate_loop_body(&repeat.body, label);
+                let until = self.generate_expression(&repeat.until);
+                self.write_line(&format!("until {}", until));
+            }
+            _ => return,
+        }
+        self.generate_escape_checks(statement, label);
+    }
+
+    /// A labeled loop. A `break` from a nested loop jumps to a label after
+    /// the loop, kept in its own block so sibling loops can reuse the name,
+    /// or sets a flag declared ahead of the loop.
+    pub(super) fn generate_labeled_loop(&mut self, labeled: &LabeledLoop) {
+        let label = labeled.label.node.as_str();
+        let breaks_out = loop_body(&labeled.body).is_some_and(|body| {
+            labeled_jumps(&body.statements)
+                .iter()
+                .any(|&(target, is_continue, depth)| target == label && !is_continue && depth > 0)
+        });
+
+        if !breaks_out {
+            self.generate_loop(&labeled.body, Some(label));
+        } else if self.has_goto() {
+            self.write_line("do");
+            self.indent();
+            self.generate_loop(&labeled.body, Some(label));
+            self.write_line(&format!("::{}_break::", label));
+            self.dedent();
+            self.write_line("end");
+        } else {
+            self.write_line(&format!("local {}_break = false", label));
+            self.generate_loop(&labeled.body, Some(label));
+        }
+    }
+
+    /// Emit a loop body, adding the target's lowering for `continue`
+    fn generate_loop_body(&mut self, body: &Block, label: Option<&str>) {
+        let jumps = labeled_jumps(&body.statements);
+        let nested = |continues: bool| {
+            jumps.iter().any(|&(target, is_continue, depth)| {
+                Some(target) == label && is_continue == continues && depth > 0
+            })
+        };
+        let (breaks_out, continues_out) = (nested(false), nested(true));
+        let continues = targets_loop(&body.statements, label, true);
+        let wrapped = !self.has_continue() && !self.has_goto() && (continues || continues_out);
+
+        self.indent();
+        self.loop_labels.push(label.map(str::to_string));
+        self.wrapped_loops.push(wrapped);
+        if continues_out && !self.has_goto() {
+            self.write_line(&format!(
+                "local {}_continue = false",
+                label.unwrap_or_default()
+            ));
+        }
+        if self.has_continue() || !(continues || continues_out) {
+            self.generate_block(body);
+        } else if wrapped {
+            // `continue` breaks out of a one-shot `repeat`, so `break` and
+            // jumps to outer loops set a flag and leave the loop after it
+            let breaks = targets_loop(&body.statements, label, false);
+            if breaks {
+                self.write_line("local break_ = false");
+            }
+            self.write_line("repeat");
+            self.generate_indented_block(body);
+            self.write_line("until true");
+            if breaks {
+                self.write_line("if break_ then break end");
+            }
+            if breaks_out {
+                let label = label.unwrap_or_default();
+                self.write_line(&format!("if {}_break then break end", label));
+            }
+            for (target, is_continue) in escaping_jumps(body, label) {
+                self.write_line(&format!(
+                    "if {} then break end",
+                    jump_name(target, is_continue)
+                ));
+            }
+        } else {
+            self.generate_block(body);
+            if continues {
+                self.write_line("::continue::");
+            }
+            if continues_out {
+                self.write_line(&format!("::{}_continue::", label.unwrap_or_default()));
+            }
+        }
+        self.wrapped_loops.pop();
+        self.loop_labels.pop();
+        self.dedent();
+    }
+
+    /// `break` or `continue`. A labeled jump to the innermost loop is the
+    /// same as an unlabeled one.
+    pub(super) fn generate_loop_jump(&mut self, jump: &LoopJump, is_continue: bool) {
+        let innermost = self.loop_labels.last().cloned().flatten();
+        match &jump.label {
+            Some(label) if innermost.as_ref() != Some(&label.node) => {
+                let name = jump_name(&label.node, is_continue);
+                if self.has_goto() {
+                    self.write_line(&format!("goto {}", name));
+                } else {
+                    self.write_line(&format!("{} = true", name));
+                    self.write_line("break");
+                }
+            }
+            _ if !is_continue => {
+                if self.wrapped_loops.last() == Some(&true) {
+                    self.write_line("break_ = true");
+                }
+                self.write_line("break");
+            }
+            _ if self.has_continue() => self.write_line("continue"),
+            // Without goto, loop bodies containing `continue` are wrapped
+            // in a one-shot `repeat`
+            _ if !self.has_goto() => self.write_line("break"),
+            _ => self.write_line("goto continue"),
+        }
+    }
+
+    /// Without `goto`, a labeled jump leaves one loop at a time: after each
+    /// loop it crosses, its flag is checked to keep going
+    fn generate_escape_checks(&mut self, statement: &Statement, label: Option<&str>) {
+        let Some(body) = loop_body(statement) else {
+            return;
+        };
+        if self.has_goto() {
+            return;
+        }
+
+        for (target, is_continue) in escaping_jumps(body, label) {
+            let reached = self
+                .loop_labels
+                .last()
+                .is_some_and(|l| l.as_deref() == Some(target));
+            let jump = if is_continue && reached && self.has_continue() {
+                "continue"
+            } else {
+                "break"
+            };
+            self.write_line(&format!(
+                "if {} then {} end",
+                jump_name(target, is_continue),
+                jump
+            ));
+        }
+    }
+}
+
+/// Name of the `goto` label or flag a labeled jump uses
+fn jump_name(label: &str, is_continue: bool) -> String {
+    let kind = if is_continue { "continue" } else { "break" };
+    format!("{}_{}", label, kind)
+}
+
+/// Labeled jumps from a loop body to outer loops, each once
+fn escaping_jumps<'a>(body: &'a Block, label: Option<&str>) -> Vec<(&'a str, bool)> {
+    let mut escaping: Vec<(&str, bool)> = Vec::new();
+    for (target, is_continue, _) in labeled_jumps(&body.statements) {
+        if Some(target) != label && !escaping.contains(&(target, is_continue)) {
+            escaping.push((target, is_continue));
+        }
+    }
+    escaping
+}
+
+fn loop_body(statement: &Statement) -> Option<&Block> {
+    match statement {
+        Statement::While(while_stmt) => Some(&while_stmt.body),
+        Statement::For(ForStatement::Numeric(numeric)) => Some(&numeric.body),
+        Statement::For(ForStatement::Generic(generic)) => Some(&generic.body),
+        Statement::Repeat(repeat) => Some(&repeat.body),
+        _ => None,
+    }
+}
+
+/// Whether a loop body contains a `continue`, or a `break`, that targets
+/// this loop without crossing another loop
+fn targets_loop(statements: &[Statement], label: Option<&str>, continues: bool) -> bool {
+    statements.iter().any(|statement| match statement {
+        Statement::Break(jump) | Statement::Continue(jump) => {
+            matches!(statement, Statement::Continue(_)) == continues
+                && (jump.label.is_none() || jump.label.as_ref().map(|l| l.node.as_str()) == label)
+        }
+        Statement::If(if_stmt) => {
+            targets_loop(&if_stmt.then_block.statements, label, continues)
+                || if_stmt
+                    .else_ifs
+                    .iter()
+                    .any(|e| targets_loop(&e.block.statements, label, continues))
+                || if_stmt
+                    .else_block
+                    .as_ref()
+                    .is_some_and(|b| targets_loop(&b.statements, label, continues))
+        }
+        Statement::Block(block) | Statement::Untyped(block) => {
+            targets_loop(&block.statements, label, continues)
+        }
+        _ => false,
+    })
+}
+
+/// Labeled jumps that leave a block, in source order
+fn labeled_jumps(statements: &[Statement]) -> Vec<LabeledJump<'_>> {
+    let mut jumps = Vec::new();
+    collect_labeled_jumps(statements, 0, &mut jumps);
+    jumps
+}
+
+fn collect_labeled_jumps<'a>(
+    statements: &'a [Statement],
+    depth: usize,
+    jumps: &mut Vec<LabeledJump<'a>>,
+) {
+    for statement in statements {
+        match statement {
+            Statement::Break(jump) | Statement::Continue(jump) => {
+                if let Some(label) = &jump.label {
+                    let is_continue = matches!(statement, Statement::Continue(_));
+                    jumps.push((label.node.as_str(), is_continue, depth));
+                }
+            }
+            Statement::If(if_stmt) => {
+                collect_labeled_jumps(&if_stmt.then_block.statements, depth, jumps);
+                for else_if in &if_stmt.else_ifs {
+                    collect_labeled_jumps(&else_if.block.statements, depth, jumps);
+                }
+                if let Some(block) = &if_stmt.else_block {
+                    collect_labeled_jumps(&block.statements, depth, jumps);
+                }
+            }
+            Statement::Block(block) | Statement::Untyped(block) => {
+                collect_labeled_jumps(&block.statements, depth, jumps)
+            }
+            Statement::Labeled(labeled) => {
+                // Jumps to this loop's own label end inside it
+                let mut inner = Vec::new();
+                collect_labeled_jumps(
+                    std::slice::from_ref(labeled.body.as_ref()),
+                    depth,
+                    &mut inner,
+                );
+                jumps.extend(
+                    inner
+                        .into_iter()
+                        .filter(|(target, _, _)| *target != labeled.label.node),
+                );
+            }
+            statement => {
+                if let Some(body) = loop_body(statement) {
+                    collect_labeled_jumps(&body.statements, depth + 1, jumps);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,326 @@
+use super::expression::quote_string;
+use super::CodeGenerator;
+use crate::ast::expression::Literal;
+use crate::ast::statement::{
+    IndexKeyType, IndexSignature, InterfaceDeclaration, InterfaceMember, MethodSignature,
+    Parameter, PropertySignature, Statement, TypeParameter,
+};
+use crate::ast::types::{ObjectTypeMember, PrimitiveType, Type, TypeKind};
+use crate::config::LuaVersion;
+
+impl CodeGenerator {
+    /// Whether types are kept as Luau annotations rather than erased
+    pub(super) fn emits_luau_types(&self) -> bool {
+        self.target() == LuaVersion::Luau && self.config.compiler_options.luau_types
+    }
+
+    /// `--!strict`, so Luau's checker holds the module to its annotations
+    pub(super) fn luau_header(&self) -> &'static str {
+        if self.emits_luau_types() {
+            "--!strict\n"
+        } else {
+            ""
+        }
+    }
+
+    /// Remember a type alias or interface the module declares. References
+    /// to any other name, such as classes and imported types, have no Luau
+    /// type and become `any`.
+    pub(super) fn register_luau_type(&mut self, statement: &Statement) {
+        let name = match statement {
+            Statement::TypeAlias(alias) => &alias.name,
+            Statement::Interface(interface) => &interface.name,
+            _ => return,
+        };
+        self.luau_type_names.insert(name.node.clone());
+    }
+
+    /// `type Name = ...` for a type alias or interface, `export type` when
+    /// the module exports it
+    pub(super) fn generate_luau_type_declaration(&mut self, statement: &Statement, exported: bool) {
+        if !self.emits_luau_types() {
+            return;
+        }
+        let (name, type_parameters, definition) = match statement {
+            Statement::TypeAlias(alias) => {
+                let generics = self.enter_luau_generics(alias.type_parameters.as_deref());
+                let definition = self.luau_type(&alias.type_annotation);
+                (&alias.name, generics, definition)
+            }
+            Statement::Interface(interface) => {
+                let generics = self.enter_luau_generics(interface.type_parameters.as_deref());
+                let definition = self.luau_interface(interface);
+                (&interface.name, generics, definition)
+            }
+            _ => return,
+        };
+        self.exit_luau_generics();
+        let export = if exported { "export " } else { "" };
+        self.write_line(&format!(
+            "{}type {}{} = {}",
+            export, name.node, type_parameters, definition
+        ));
+    }
+
+    /// Bring a declaration's type parameters into scope, returning them as
+    /// Luau writes them. Pair with `exit_luau_generics`.
+    pub(super) fn enter_luau_generics(&mut self, parameters: Option<&[TypeParameter]>) -> String {
+        let names: Vec<String> = parameters
+            .unwrap_or_default()
+            .iter()
+            .map(|param| param.name.node.clone())
+            .collect();
+        let written = if names.is_empty() || !self.emits_luau_types() {
+            String::new()
+        } else {
+            format!("<{}>", names.join(", "))
+        };
+        self.luau_generics.push(names);
+        written
+    }
+
+    pub(super) fn exit_luau_generics(&mut self) {
+        self.luau_generics.pop();
+    }
+
+    /// `: T` after a name, when the name is annotated
+    pub(super) fn luau_annotation(&self, typ: Option<&Type>) -> String {
+        match typ {
+            Some(typ) if self.emits_luau_types() => format!(": {}", self.luau_type(typ)),
+            _ => String::new(),
+        }
+    }
+
+    /// `: R` after a parameter list, with tuples and `void` as type packs
+    pub(super) fn luau_return_annotation(&self, typ: Option<&Type>) -> String {
+        match typ {
+            Some(typ) if self.emits_luau_types() => format!(": {}", self.luau_return_type(typ)),
+            _ => String::new(),
+        }
+    }
+
+    /// A parameter as Luau declares it. A rest parameter is `...: T` with
+    /// the element type, and one with a default may be omitted.
+    pub(super) fn luau_parameter(&self, name: String, param: &Parameter) -> String {
+        let Some(typ) = param
+            .type_annotation
+            .as_ref()
+            .filter(|_| self.emits_luau_types())
+        else {
+            return name;
+        };
+        if param.is_rest {
+            let element = match &typ.kind {
+                TypeKind::Array(element) => self.luau_type(element),
+                _ => "any".to_string(),
+            };
+            return format!("{}: {}", name, element);
+        }
+        let mut written = self.luau_type(typ);
+        if param.default.is_some() {
+            written = optional(written, typ);
+        }
+        format!("{}: {}", name, written)
+    }
+
+    fn luau_return_type(&self, typ: &Type) -> String {
+        match &typ.kind {
+            TypeKind::Primitive(PrimitiveType::Void) => "()".to_string(),
+            TypeKind::Tuple(types) => {
+                let types: Vec<String> = types.iter().map(|t| self.luau_pack_member(t)).collect();
+                format!("({})", types.join(", "))
+            }
+            TypeKind::Variadic(_) => format!("({})", self.luau_pack_member(typ)),
+            _ => self.luau_type(typ),
+        }
+    }
+
+    fn luau_pack_member(&self, typ: &Type) -> String {
+        match &typ.kind {
+            TypeKind::Variadic(element) => format!("...{}", self.luau_type(element)),
+            _ => self.luau_type(typ),
+        }
+    }
+
+    /// A TypedLua type in Luau's syntax. Types Luau cannot express, such
+    /// as conditional and mapped types, become `any`.
+    pub(super) fn luau_type(&self, typ: &Type) -> String {
+        match &typ.kind {
+            TypeKind::Primitive(primitive) => match primitive {
+                PrimitiveType::Nil => "nil",
+                PrimitiveType::Boolean => "boolean",
+                PrimitiveType::Number | PrimitiveType::Integer => "number",
+                PrimitiveType::String => "string",
+                PrimitiveType::Unknown | PrimitiveType::Void => "any",
+                PrimitiveType::Never => "never",
+                PrimitiveType::Table => "{ [any]: any }",
+                PrimitiveType::Coroutine => "thread",
+            }
+            .to_string(),
+            TypeKind::Reference(reference) => {
+                let name = reference.name.node.as_str();
+                let arguments = reference.type_arguments.as_deref().unwrap_or_default();
+                if self
+                    .luau_generics
+                    .iter()
+                    .flatten()
+                    .any(|param| param == name)
+                {
+                    return name.to_string();
+                }
+                if self.luau_type_names.contains(name) {
+                    if arguments.is_empty() {
+                        return name.to_string();
+                    }
+                    let arguments: Vec<String> =
+                        arguments.iter().map(|t| self.luau_type(t)).collect();
+                    return format!("{}<{}>", name, arguments.join(", "));
+                }
+                match (name, arguments) {
+                    ("Record", [key, value]) if is_key_primitive(key) => {
+                        format!("{{ [{}]: {} }}", self.luau_type(key), self.luau_type(value))
+                    }
+                    ("Readonly" | "Required", [inner]) => self.luau_type(inner),
+                    _ => "any".to_string(),
+                }
+            }
+            TypeKind::Union(types) => self.luau_types(types, " | "),
+            TypeKind::Intersection(types) => self.luau_types(types, " & "),
+            TypeKind::Object(object) => {
+                let fields: Vec<String> = object
+                    .members
+                    .iter()
+                    .map(|member| match member {
+                        ObjectTypeMember::Property(property) => self.luau_property(property),
+                        ObjectTypeMember::Method(method) => self.luau_method(method),
+                        ObjectTypeMember::Index(index) => self.luau_index(index),
+                    })
+                    .collect();
+                luau_table(&fields)
+            }
+            TypeKind::Array(element) => format!("{{ {} }}", self.luau_type(element)),
+            TypeKind::Function(function) => {
+                format!(
+                    "({}) -> {}",
+                    self.luau_parameter_types(&function.parameters),
+                    self.luau_return_type(&function.return_type)
+                )
+            }
+            TypeKind::Literal(literal) => match literal {
+                Literal::Nil => "nil".to_string(),
+                Literal::Boolean(value) => value.to_string(),
+                Literal::String(value) => quote_string(value),
+                _ => "number".to_string(),
+            },
+            TypeKind::Nullable(inner) => optional(self.luau_type(inner), inner),
+            TypeKind::Parenthesized(inner) => format!("({})", self.luau_type(inner)),
+            TypeKind::Variadic(element) => format!("{{ {} }}", self.luau_type(element)),
+            TypeKind::Asserts(_) => "()".to_string(),
+            TypeKind::TemplateLiteral(_) => "string".to_string(),
+            TypeKind::Tuple(_)
+            | TypeKind::TypeQuery(_)
+            | TypeKind::KeyOf(_)
+            | TypeKind::IndexAccess(..)
+            | TypeKind::Conditional(_)
+            | TypeKind::Mapped(_) => "any".to_string(),
+        }
+    }
+
+    fn luau_types(&self, types: &[Type], separator: &str) -> String {
+        let types: Vec<String> = types
+            .iter()
+            .map(|typ| match &typ.kind {
+                TypeKind::Function(_) => format!("({})", self.luau_type(typ)),
+                _ => self.luau_type(typ),
+            })
+            .collect();
+        types.join(separator)
+    }
+
+    /// An interface as a table type, intersected with what it extends
+    fn luau_interface(&self, interface: &InterfaceDeclaration) -> String {
+        let fields: Vec<String> = interface
+            .members
+            .iter()
+            .map(|member| match member {
+                InterfaceMember::Property(property) => self.luau_property(property),
+                InterfaceMember::Method(method) => self.luau_method(method),
+                InterfaceMember::Index(index) => self.luau_index(index),
+            })
+            .collect();
+        let mut parts: Vec<String> = interface
+            .extends
+            .iter()
+            .map(|base| self.luau_type(base))
+            .collect();
+        parts.push(luau_table(&fields));
+        parts.join(" & ")
+    }
+
+    fn luau_property(&self, property: &PropertySignature) -> String {
+        let mut typ = self.luau_type(&property.type_annotation);
+        if property.is_optional {
+            typ = optional(typ, &property.type_annotation);
+        }
+        format!("{}: {}", property.name.node, typ)
+    }
+
+    fn luau_method(&self, method: &MethodSignature) -> String {
+        format!(
+            "{}: ({}) -> {}",
+            method.name.node,
+            self.luau_parameter_types(&method.parameters),
+            self.luau_return_type(&method.return_type)
+        )
+    }
+
+    fn luau_index(&self, index: &IndexSignature) -> String {
+        let key = match index.key_type {
+            IndexKeyType::String => "string",
+            IndexKeyType::Number => "number",
+        };
+        format!("[{}]: {}", key, self.luau_type(&index.value_type))
+    }
+
+    fn luau_parameter_types(&self, parameters: &[Parameter]) -> String {
+        let types: Vec<String> = parameters
+            .iter()
+            .map(|param| match (&param.type_annotation, param.is_rest) {
+                (Some(typ), true) => match &typ.kind {
+                    TypeKind::Array(element) => format!("...{}", self.luau_type(element)),
+                    _ => "...any".to_string(),
+                },
+                (None, true) => "...any".to_string(),
+                (Some(typ), false) => self.luau_type(typ),
+                (None, false) => "any".to_string(),
+            })
+            .collect();
+        types.join(", ")
+    }
+}
+
+/// `T?`, parenthesizing unions and functions so the `?` covers them
+fn optional(written: String, typ: &Type) -> String {
+    match &typ.kind {
+        TypeKind::Union(_) | TypeKind::Intersection(_) | TypeKind::Function(_) => {
+            format!("({})?", written)
+        }
+        TypeKind::Nullable(_) => written,
+        _ => format!("{}?", written),
+    }
+}
+
+fn luau_table(fields: &[String]) -> String {
+    if fields.is_empty() {
+        "{}".to_string()
+    } else {
+        format!("{{ {} }}", fields.join(", "))
+    }
+}
+
+fn is_key_primitive(typ: &Type) -> bool {
+    matches!(
+        typ.kind,
+        TypeKind::Primitive(PrimitiveType::String | PrimitiveType::Number | PrimitiveType::Integer)
+    )
+}
@@ -0,0 +1,808 @@
+mod constants;
+mod enums;
+mod expression;
+mod helpers;
+mod inline;
+mod loops;
+mod luau;
+mod modules;
+mod operators;
+mod overflow;
+mod pattern;
+mod pool;
+mod random;
+mod readable;
+mod schemas;
+mod source_map;
+mod using;
+
+#[cfg(test)]
+mod tests;
+
+use crate::ast::attributes::{has_attribute, statement_decorators, weak_mode};
+use crate::ast::expression::{Expression, Literal};
+use crate::ast::pattern::Pattern;
+use crate::ast::statement::*;
+use crate::ast::Program;
+use crate::config::{ClassTemplate, CompilerConfig, EnumTemplate, LuaVersion};
+use crate::shake::TreeShaking;
+pub(crate) use expression::quote_string;
+use helpers::RuntimeHelper;
+pub(crate) use pattern::collect_bound_names;
+use pool::LiteralPool;
+use readable::ReadableSource;
+pub use source_map::SourceMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+
+const INDENT: &str = "  ";
+
+/// Lowers a checked TypedLua program to Lua source
+pub struct CodeGenerator {
+    config: Arc<CompilerConfig>,
+    output: String,
+    indent_level: usize,
+    temp_counter: usize,
+    /// Named exports as (exported name, local name)
+    exports: Vec<(String, String)>,
+    default_export: Option<String>,
+    /// Value of an `export =` assignment; replaces the export table entirely
+    module_value: Option<String>,
+    /// Base class of the class whose members are being emitted, for `super`
+    class_base: Option<String>,
+    /// Name the module registers itself under in global-registration style
+    module_name: Option<String>,
+    /// Enclosing namespace blocks, outermost first
+    namespace_path: Vec<String>,
+    /// Qualified names of namespace tables already created
+    namespaces: HashSet<String>,
+    /// Runtime helpers referenced by the generated code
+    helpers: BTreeSet<RuntimeHelper>,
+    /// Key paths of the `__safe_get` specializations, numbered in order
+    safe_get_paths: Vec<Vec<String>>,
+    /// Member literals of `const enum`s, which are inlined at each use
+    const_enums: HashMap<String, HashMap<String, String>>,
+    /// Member names and values of every enum in declaration order, for
+    /// expanding `values`, `fromName` and `ordinal`
+    enums: HashMap<String, Vec<(String, String)>>,
+    /// Lazily imported names and the loader expression each one reads through
+    lazy_imports: HashMap<String, String>,
+    /// `@inline` functions: their parameter names and returned expression
+    inline_functions: HashMap<String, (Vec<String>, Expression)>,
+    /// Code of the arguments standing in for parameters while an `@inline`
+    /// function's body is expanded
+    inline_arguments: HashMap<String, String>,
+    /// Labels of the enclosing loops, innermost last
+    loop_labels: Vec<Option<String>>,
+    /// Whether each enclosing loop's body is wrapped in a one-shot `repeat`
+    /// for `continue`, so `break` leaves through a flag; innermost last
+    wrapped_loops: Vec<bool>,
+    /// How many `using` closures enclose the code being generated within
+    /// the current function, whose returns also report that they returned
+    using_scopes: usize,
+    /// Whether `+ - *` are being generated inside a `@checked` function
+    checking_overflow: bool,
+    /// Type aliases and interfaces the module declares, which Luau
+    /// annotations may refer to
+    luau_type_names: HashSet<String>,
+    /// Type parameters in scope, innermost declaration last
+    luau_generics: Vec<Vec<String>>,
+    /// Whether the module declares its own `Random`, so references to it
+    /// need not pull in the runtime's generator
+    random_declared: bool,
+    /// Declarations, enum members and exports left out by tree shaking
+    tree_shaking: TreeShaking,
+    /// Folded `const` values by scope, innermost last. `None` marks a name
+    /// bound to something else, hiding an outer constant.
+    constants: Vec<HashMap<String, Option<Literal>>>,
+    /// Repeated constants hoisted to module-level locals by `poolLiterals`
+    pool: LiteralPool,
+    /// `@schema` records whose tables have been defined
+    schemas: HashSet<String>,
+    /// `@migration` functions defined above their record, by record name
+    pending_migrations: HashMap<String, Vec<(i64, String)>>,
+    /// Source line and column of the statement being generated
+    source_position: Option<(usize, usize)>,
+    /// Map of the last generated module, with `sourceMap`
+    source_map: Option<SourceMap>,
+    /// The source being compiled, with `readableOutput`
+    readable: Option<ReadableSource>,
+}
+
+impl CodeGenerator {
+    pub fn new(config: Arc<CompilerConfig>) -> Self {
+        CodeGenerator {
+            config,
+            output: String::new(),
+            indent_level: 0,
+            temp_counter: 0,
+            exports: Vec::new(),
+            default_export: None,
+            module_value: None,
+            class_base: None,
+            module_name: None,
+            namespace_path: Vec::new(),
+            namespaces: HashSet::new(),
+            helpers: BTreeSet::new(),
+            safe_get_paths: Vec::new(),
+            const_enums: HashMap::new(),
+            enums: HashMap::new(),
+            lazy_imports: HashMap::new(),
+            inline_functions: HashMap::new(),
+            inline_arguments: HashMap::new(),
+            loop_labels: Vec::new(),
+            wrapped_loops: Vec::new(),
+            using_scopes: 0,
+            checking_overflow: false,
+            luau_type_names: HashSet::new(),
+            luau_generics: Vec::new(),
+            random_declared: false,
+            tree_shaking: TreeShaking::default(),
+            constants: Vec::new(),
+            pool: LiteralPool::default(),
+            schemas: HashSet::new(),
+            pending_migrations: HashMap::new(),
+            source_position: None,
+            source_map: None,
+            readable: None,
+        }
+    }
+
+    /// Set the name a global-registration style module registers under.
+    /// Without one, the name passed to `require` is used.
+    pub fn set_module_name(&mut self, name: &str) {
+        self.module_name = Some(name.to_string());
+    }
+
+    /// Leave out what tree shaking found nothing refers to
+    pub fn set_tree_shaking(&mut self, tree_shaking: TreeShaking) {
+        self.tree_shaking = tree_shaking;
+    }
+
+    /// Generate the Lua source for a whole module
+    pub fn generate(&mut self, program: &Program) -> String {
+        self.pool = LiteralPool::default();
+        if self.pools_literals() {
+            self.begin_pool_count();
+            self.generate_module(program);
+            self.end_pool_count();
+        }
+        let body = self.generate_module(program);
+        let code =
+            self.luau_header().to_string() + &self.helper_prelude() + &self.pool_prelude() + &body;
+        if !self.maps_sources() {
+            return code;
+        }
+        let (code, source_map) = source_map::strip_markers(&code);
+        self.source_map = Some(source_map);
+        code
+    }
+
+    /// The source map of the module generated last, with `sourceMap`
+    pub fn take_source_map(&mut self) -> Option<SourceMap> {
+        self.source_map.take()
+    }
+
+    fn generate_module(&mut self, program: &Program) -> String {
+        self.output.clear();
+        self.indent_level = 0;
+        self.temp_counter = 0;
+        self.exports.clear();
+        self.default_export = None;
+        self.module_value = None;
+        self.helpers.clear();
+        self.safe_get_paths.clear();
+        self.namespaces.clear();
+        self.const_enums.clear();
+        self.enums.clear();
+        self.lazy_imports.clear();
+        self.inline_functions.clear();
+        self.schemas.clear();
+        self.pending_migrations.clear();
+        self.constants.clear();
+        self.luau_type_names.clear();
+        self.random_declared = false;
+        self.enter_constant_scope();
+
+        // Enums and types may be referenced above their declaration
+        for statement in &program.statements {
+            let statement = match statement {
+                Statement::Export(ExportDeclaration {
+                    kind: ExportKind::Declaration(decl),
+                    ..
+                }) => decl.as_ref(),
+                statement => statement,
+            };
+            if let Statement::Enum(decl) = statement {
+                self.register_enum(decl);
+            }
+            self.random_declared |= random::declares_random(statement);
+            self.register_luau_type(statement);
+        }
+
+        self.generate_module_header();
+        self.generate_statements(&program.statements);
+        self.generate_module_footer();
+        std::mem::take(&mut self.output)
+    }
+
+    fn target(&self) -> LuaVersion {
+        self.config.compiler_options.target
+    }
+
+    // Output helpers
+
+    fn write_line(&mut self, line: &str) {
+        for _ in 0..self.indent_level {
+            self.output.push_str(INDENT);
+        }
+        if let Some(marker) = self.source_marker() {
+            self.output.push_str(&marker);
+        }
+        self.output.push_str(line);
+        self.output.push('\n');
+    }
+
+    fn indent(&mut self) {
+        self.indent_level += 1;
+    }
+
+    fn dedent(&mut self) {
+        self.indent_level = self.indent_level.saturating_sub(1);
+    }
+
+    /// Run an emitter one level deeper and return what it wrote instead of
+    /// appending it to the output
+    fn capture(&mut self, emit: impl FnOnce(&mut Self)) -> String {
+        let saved = std::mem::take(&mut self.output);
+        self.indent();
+        emit(self);
+        self.dedent();
+        std::mem::replace(&mut self.output, saved)
+    }
+
+    fn indentation(&self) -> String {
+        INDENT.repeat(self.indent_level)
+    }
+
+    fn fresh_temp(&mut self, base: &str) -> String {
+        self.temp_counter += 1;
+        if self.temp_counter == 1 {
+            format!("_{}", base)
+        } else {
+            format!("_{}{}", base, self.temp_counter)
+        }
+    }
+
+    // Statements
+
+    fn generate_statement(&mut self, statement: &Statement) {
+        // `@no_emit` declarations exist for the checker only
+        if has_attribute(statement_decorators(statement), "no_emit") || self.is_shaken(statement) {
+            return;
+        }
+        let outer = self.enter_source(statement.span());
+        let notes = self.write_source_notes(statement.span());
+        match statement {
+            Statement::Variable(decl) => self.generate_variable(decl),
+            Statement::Function(func) => {
+                let generics = self.enter_luau_generics(func.type_parameters.as_deref());
+                let params = self.generate_parameter_names(&func.parameters);
+                let returns = self.luau_return_annotation(func.return_type.as_ref());
+                self.shadow_constant(&func.name.node);
+                self.write_line(&format!(
+                    "local function {}{}({}){}",
+                    func.name.node, generics, params, returns
+                ));
+                let checked = self.checks_overflow(func);
+                let outer = std::mem::replace(&mut self.checking_overflow, checked);
+                self.generate_function_body(&func.parameters, &func.body);
+                self.checking_overflow = outer;
+                self.exit_luau_generics();
+                self.write_line("end");
+                self.register_inline(func);
+                self.register_migration(func);
+            }
+            Statement::Class(class) => self.generate_class(class),
+            Statement::Enum(decl) => self.generate_enum(decl),
+            // Types only exist at compile time, apart from `@schema` records
+            // and the annotations kept for Luau
+            Statement::Interface(interface) => {
+                self.generate_luau_type_declaration(statement, false);
+                self.generate_schema(interface);
+            }
+            Statement::TypeAlias(_) => self.generate_luau_type_declaration(statement, false),
+            Statement::Declare(_) => {}
+            Statement::Error(_) => {}
+            Statement::Import(import) => self.generate_import(import),
+            Statement::Export(export) => self.generate_export(export),
+            Statement::Namespace(namespace) => self.generate_namespace(namespace),
+            Statement::If(if_stmt) => {
+                let condition = self.generate_expression(&if_stmt.condition);
+                self.write_line(&format!("if {} then", condition));
+                self.generate_indented_block(&if_stmt.then_block);
+                for else_if in &if_stmt.else_ifs {
+                    let condition = self.generate_expression(&else_if.condition);
+                    self.write_line(&format!("elseif {} then", condition));
+                    self.generate_indented_block(&else_if.block);
+                }
+                if let Some(else_block) = &if_stmt.else_block {
+                    self.write_line("else");
+                    self.generate_indented_block(else_block);
+                }
+                self.write_line("end");
+            }
+            Statement::While(_) | Statement::For(_) | Statement::Repeat(_) => {
+                self.generate_loop(statement, None)
+            }
+            Statement::Labeled(labeled) => self.generate_labeled_loop(labeled),
+            Statement::Return(ret) => {
+                let values: Vec<String> = ret
+                    .values
+                    .iter()
+                    .map(|v| self.generate_expression(v))
+                    .collect();
+                let line = self.return_statement(values);
+                self.write_line(&line);
+            }
+            Statement::Break(jump) => self.generate_loop_jump(jump, false),
+            Statement::Continue(jump) => self.generate_loop_jump(jump, true),
+            Statement::Expression(expr) => self.generate_expression_statement(expr),
+            Statement::Block(block) | Statement::Untyped(block) => {
+                self.write_line("do");
+                self.generate_indented_block(block);
+                self.write_line("end");
+            }
+        }
+        self.end_source_notes(statement.span(), notes);
+        self.source_position = outer;
+    }
+
+    fn is_shaken(&self, statement: &Statement) -> bool {
+        self.tree_shaking.statements.contains(&statement.span())
+    }
+
+    fn generate_block(&mut self, block: &Block) {
+        self.enter_constant_scope();
+        self.generate_statements(&block.statements);
+        self.exit_constant_scope();
+    }
+
+    fn generate_indented_block(&mut self, block: &Block) {
+        self.indent();
+        self.generate_block(block);
+        self.dedent();
+    }
+
+    fn generate_expression_statement(&mut self, expr: &Expression) {
+        use crate::ast::expression::ExpressionKind;
+
+        match &expr.kind {
+            ExpressionKind::Assignment(target, op, value) => {
+                let line = self.generate_assignment(target, *op, value);
+                self.write_line(&line);
+            }
+            ExpressionKind::Call(..) | ExpressionKind::MethodCall(..)
+                if !self.is_inline_call(expr) =>
+            {
+                let call = self.generate_expression(expr);
+                self.write_line(&call);
+            }
+            ExpressionKind::Pipe(..) => {
+                let call = self.generate_expression(expr);
+                self.write_line(&call);
+            }
+            // Lua only allows calls and assignments as statements
+            _ => {
+                let value = self.generate_expression(expr);
+                self.write_line(&format!("local _ = {}", value));
+            }
+        }
+    }
+
+    fn generate_variable(&mut self, decl: &VariableDeclaration) {
+        let annotation = self.luau_annotation(decl.type_annotation.as_ref());
+        if let (Some(value), Pattern::Identifier(name)) = (self.fold_constant(decl), &decl.pattern)
+        {
+            self.write_line(&format!("local {}{} = {}", name.node, annotation, value));
+            return;
+        }
+        let value = self.generate_expression(&decl.initializer);
+        match &decl.pattern {
+            Pattern::Identifier(name) => {
+                let value = match weak_mode(&decl.decorators) {
+                    Some(Ok(mode)) => {
+                        format!("setmetatable({}, {{ __mode = \"{}\" }})", value, mode)
+                    }
+                    _ => value,
+                };
+                let value = match &decl.type_annotation {
+                    Some(typ) => self.freeze_if_readonly(typ, value),
+                    None => value,
+                };
+                self.shadow_constant(&name.node);
+                let attribute = match decl.kind {
+                    VariableKind::Using if self.has_close_attribute() => " <close>",
+                    _ => "",
+                };
+                self.write_line(&format!(
+                    "local {}{}{} = {}",
+                    name.node, annotation, attribute, value
+                ));
+            }
+            pattern => self.generate_destructuring(pattern, &value),
+        }
+    }
+
+    /// Parameter list for a function header. Rest parameters become `...`
+    /// and destructured parameters get placeholder names.
+    fn generate_parameter_names(&mut self, parameters: &[Parameter]) -> String {
+        let names: Vec<String> = parameters
+            .iter()
+            .enumerate()
+            .map(|(i, param)| {
+                let name = if param.is_rest {
+                    "...".to_string()
+                } else {
+                    parameter_name(param, i)
+                };
+                self.luau_parameter(name, param)
+            })
+            .collect();
+        names.join(", ")
+    }
+
+    /// Emit a function body preceded by the parameter prologue: rest
+    /// collection, defaults and destructuring
+    fn generate_function_body(&mut self, parameters: &[Parameter], body: &Block) {
+        let using_scopes = std::mem::take(&mut self.using_scopes);
+        self.indent();
+        self.enter_constant_scope();
+        for (i, param) in parameters.iter().enumerate() {
+            let name = parameter_name(param, i);
+            self.shadow_constant(&name);
+            if param.is_rest {
+                self.write_line(&format!("local {} = {{...}}", name));
+            }
+            if let Some(default) = &param.default {
+                let default = self.generate_expression(default);
+                self.write_line(&format!(
+                    "if {} == nil then {} = {} end",
+                    name, name, default
+                ));
+            }
+            if let Some(typ) = &param.type_annotation {
+                let frozen = self.freeze_if_readonly(typ, name.clone());
+                if frozen != name {
+                    self.write_line(&format!("{} = {}", name, frozen));
+                }
+            }
+            if !matches!(param.pattern, Pattern::Identifier(_)) {
+                self.generate_destructuring(&param.pattern, &name);
+            }
+        }
+        self.generate_block(body);
+        self.exit_constant_scope();
+        self.dedent();
+        self.using_scopes = using_scopes;
+    }
+
+    fn generate_class(&mut self, class: &ClassDeclaration) {
+        let name = class.name.node.clone();
+        let base = class.extends.as_ref().and_then(|t| match &t.kind {
+            crate::ast::types::TypeKind::Reference(r) => Some(r.name.node.clone()),
+            _ => None,
+        });
+        let closures = self.uses_closure_classes();
+
+        match &base {
+            Some(base) => self.write_line(&format!(
+                "local {} = setmetatable({{}}, {{__index = {}}})",
+                name, base
+            )),
+            None => self.write_line(&format!("local {} = {{}}", name)),
+        }
+        let mixins = mixin_names(class);
+        if !mixins.is_empty() {
+            self.generate_mixins(&name, &mixins);
+        }
+        if !closures {
+            self.write_line(&format!("{}.__index = {}", name, name));
+            if let Some(Ok(mode)) = weak_mode(&class.decorators) {
+                self.write_line(&format!("{}.__mode = \"{}\"", name, mode));
+            }
+        }
+
+        let saved_base = std::mem::replace(&mut self.class_base, base.clone());
+
+        if closures {
+            self.generate_method_binder(class, &name, base.as_deref());
+        }
+        self.generate_constructor(class, &name, base.as_deref());
+
+        for member in &class.members {
+            // Closure classes bind instance members in `_bind` instead
+            if closures && !member.is_static() {
+                continue;
+            }
+            self.generate_class_method(&name, member);
+        }
+        self.generate_static_initialization(class, &name);
+
+        self.class_base = saved_base;
+    }
+
+    /// Static properties and `static do` blocks run in declaration order
+    /// once every method is defined, so they may call static methods
+    fn generate_static_initialization(&mut self, class: &ClassDeclaration, name: &str) {
+        let statics: Vec<&ClassMember> = class
+            .members
+            .iter()
+            .filter(|member| match member {
+                ClassMember::Property(prop) => prop.is_static && prop.initializer.is_some(),
+                ClassMember::StaticBlock(_) => true,
+                _ => false,
+            })
+            .collect();
+        if statics.is_empty() {
+            return;
+        }
+
+        self.write_line("");
+        for member in statics {
+            match member {
+                ClassMember::Property(prop) => {
+                    if let Some(init) = &prop.initializer {
+                        let value = self.generate_expression(init);
+                        self.write_line(&format!("{}.{} = {}", name, prop.name.node, value));
+                    }
+                }
+                ClassMember::StaticBlock(block) => {
+                    self.write_line("do");
+                    self.generate_indented_block(&block.body);
+                    self.write_line("end");
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn uses_closure_classes(&self) -> bool {
+        self.config.compiler_options.class_template == ClassTemplate::Closure
+    }
+
+    /// Emit a method, getter or setter. Instance members of closure classes
+    /// are bound to `self` and ignore the receiver passed by `obj:m()`.
+    fn generate_class_method(&mut self, class_name: &str, member: &ClassMember) {
+        let (is_static, name, parameters, body) = match member {
+            ClassMember::Method(method) => match &method.body {
+                Some(body) => (
+                    method.is_static,
+                    method.name.node.clone(),
+                    method.parameters_without_self(),
+                    body,
+                ),
+                None => return,
+            },
+            ClassMember::Getter(getter) => (
+                getter.is_static,
+                format!("get_{}", getter.name.node),
+                &[][..],
+                &getter.body,
+            ),
+            ClassMember::Setter(setter) => (
+                setter.is_static,
+                format!("set_{}", setter.name.node),
+                std::slice::from_ref(&setter.parameter),
+                &setter.body,
+            ),
+            ClassMember::Property(_)
+            | ClassMember::Constructor(_)
+            | ClassMember::StaticBlock(_) => return,
+        };
+
+        let params = self.generate_parameter_names(parameters);
+        let bound = !is_static && self.uses_closure_classes();
+        // Members bound inside `_bind` are not separated by blank lines
+        if !bound {
+            self.write_line("");
+        }
+        let notes = self.write_source_notes(member.span());
+        let header = if is_static {
+            format!("function {}.{}({})", class_name, name, params)
+        } else if bound {
+            let params = if params.is_empty() {
+                "_".to_string()
+            } else {
+                format!("_, {}", params)
+            };
+            format!("function self.{}({})", name, params)
+        } else {
+            format!("function {}:{}({})", class_name, name, params)
+        };
+        self.write_line(&header);
+        self.generate_function_body(parameters, body);
+        self.write_line("end");
+        self.end_source_notes(member.span(), notes);
+    }
+
+    /// Closure classes attach instance methods in `_bind(self)`, after those
+    /// of the base class and mixins so overrides win. `super` inside them
+    /// reads the base's bound methods, snapshotted before overriding.
+    fn generate_method_binder(&mut self, class: &ClassDeclaration, name: &str, base: Option<&str>) {
+        self.write_line("");
+        self.write_line(&format!("function {}._bind(self)", name));
+        self.indent();
+        if let Some(base) = base {
+            self.write_line(&format!("{}._bind(self)", base));
+            self.write_line("local super = {}");
+            self.write_line("for key, value in pairs(self) do");
+            self.write_line("  super[key] = value");
+            self.write_line("end");
+        }
+        for mixin in mixin_names(class) {
+            self.write_line(&format!("{}._bind(self)", mixin));
+        }
+
+        let saved_base = self.class_base.take();
+        self.class_base = base.map(|_| "super".to_string());
+        for member in &class.members {
+            if !member.is_static() {
+                self.generate_class_method(name, member);
+            }
+        }
+        self.class_base = saved_base;
+        self.dedent();
+        self.write_line("end");
+    }
+
+    fn generate_constructor(&mut self, class: &ClassDeclaration, name: &str, base: Option<&str>) {
+        let constructor = class.members.iter().find_map(|member| match member {
+            ClassMember::Constructor(ctor) => Some(ctor),
+            _ => None,
+        });
+
+        let mixins = mixin_names(class);
+        let params = match constructor {
+            Some(ctor) => self.generate_parameter_names(&ctor.parameters),
+            None if base.is_some() || !mixins.is_empty() => "...".to_string(),
+            None => String::new(),
+        };
+        let init_params = if params.is_empty() {
+            "self".to_string()
+        } else {
+            format!("self, {}", params)
+        };
+
+        self.write_line("");
+        self.write_line(&format!("function {}._init({})", name, init_params));
+        self.indent();
+        if let (None, Some(base)) = (constructor, base) {
+            self.write_line(&format!("{}._init(self, ...)", base));
+        }
+        // Without a constructor of its own, a class initializes each mixin
+        if constructor.is_none() {
+            for mixin in &mixins {
+                self.write_line(&format!("{}._init(self, ...)", mixin));
+            }
+        }
+        for member in &class.members {
+            if let ClassMember::Property(prop) = member {
+                if let (false, Some(init)) = (prop.is_static, &prop.initializer) {
+                    let value = self.generate_expression(init);
+                    self.write_line(&format!("self.{} = {}", prop.name.node, value));
+                }
+            }
+        }
+        self.dedent();
+        if let Some((ctor, Some(body))) = constructor.map(|ctor| (ctor, &ctor.body)) {
+            self.generate_function_body(&ctor.parameters, body);
+        }
+        self.write_line("end");
+
+        self.write_line("");
+        self.write_line(&format!("function {}.new(...)", name));
+        self.indent();
+        if self.uses_closure_classes() {
+            match weak_mode(&class.decorators) {
+                Some(Ok(mode)) => self.write_line(&format!(
+                    "local self = setmetatable({{}}, {{ __mode = \"{}\" }})",
+                    mode
+                )),
+                _ => self.write_line("local self = {}"),
+            }
+            self.write_line(&format!("{}._bind(self)", name));
+        } else {
+            self.write_line(&format!("local self = setmetatable({{}}, {})", name));
+        }
+        self.write_line(&format!("{}._init(self, ...)", name));
+        self.write_line("return self");
+        self.dedent();
+        self.write_line("end");
+    }
+
+    fn generate_enum(&mut self, decl: &EnumDeclaration) {
+        self.register_enum(decl);
+        if decl.is_const {
+            return;
+        }
+        let protected = self.config.compiler_options.enum_template == EnumTemplate::Protected;
+        if protected {
+            self.use_helper(RuntimeHelper::Freeze);
+            self.write_line(&format!(
+                "local {} = {}({{",
+                decl.name.node,
+                RuntimeHelper::Freeze.name()
+            ));
+        } else {
+            self.write_line(&format!("local {} = {{", decl.name.node));
+        }
+        self.indent();
+        let removed = self
+            .tree_shaking
+            .enum_members
+            .get(&decl.span)
+            .cloned()
+            .unwrap_or_default();
+        for (name, value) in enum_member_values(decl) {
+            if removed.contains(&name) {
+                continue;
+            }
+            self.write_line(&format!("{} = {},", name, value));
+        }
+        self.dedent();
+        self.write_line(if protected { "})" } else { "}" });
+    }
+
+    fn register_enum(&mut self, decl: &EnumDeclaration) {
+        let members = enum_member_values(decl);
+        if decl.is_const {
+            self.const_enums
+                .insert(decl.name.node.clone(), members.iter().cloned().collect());
+        }
+        self.enums.insert(decl.name.node.clone(), members);
+    }
+}
+
+/// Runtime names of the classes a class mixes in
+fn mixin_names(class: &ClassDeclaration) -> Vec<String> {
+    class
+        .mixins
+        .iter()
+        .filter_map(|mixin| match &mixin.kind {
+            crate::ast::types::TypeKind::Reference(r) => Some(r.name.node.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Each enum member with its value as a Lua literal
+fn enum_member_values(decl: &EnumDeclaration) -> Vec<(String, String)> {
+    // Auto-increment starts at 1, following Lua's array convention
+    let mut next = 1.0;
+    decl.members
+        .iter()
+        .map(|member| {
+            let value = match &member.value {
+                Some(EnumValue::Number(n)) => {
+                    next = n + 1.0;
+                    expression::format_number(*n)
+                }
+                Some(EnumValue::String(s)) => expression::quote_string(s),
+                None => {
+                    let value = expression::format_number(next);
+                    next += 1.0;
+                    value
+                }
+            };
+            (member.name.node.clone(), value)
+        })
+        .collect()
+}
+
+fn parameter_name(param: &Parameter, index: usize) -> String {
+    match &param.pattern {
+        Pattern::Identifier(name) => name.node.clone(),
+        _ => format!("_arg{}", index + 1),
+    }
+}
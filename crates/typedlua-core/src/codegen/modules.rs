@@ -0,0 +1,326 @@
+use super::expression::quote_string;
+use super::pattern::collect_bound_names;
+use super::CodeGenerator;
+use crate::ast::attributes::has_attribute;
+use crate::ast::statement::*;
+use crate::config::ModuleStyle;
+
+impl CodeGenerator {
+    /// Style used for an imported module: configured per import path, or
+    /// the project's own style otherwise
+    fn import_style(&self, source: &str) -> ModuleStyle {
+        let options = &self.config.compiler_options;
+        options
+            .module_conventions
+            .get(source)
+            .copied()
+            .unwrap_or(options.module_style)
+    }
+
+    /// Code evaluating to the value of an imported module. Modules that
+    /// register a global return nothing useful from `require`, so the shim
+    /// requires them for effect and reads the global instead.
+    fn import_value(&mut self, source: &str) -> String {
+        let require = format!("require({})", quote_string(source));
+        match self.import_style(source) {
+            ModuleStyle::Return | ModuleStyle::Module => require,
+            ModuleStyle::Global => {
+                self.write_line(&require);
+                format!("_G[{}]", quote_string(&module_variable_name(source)))
+            }
+        }
+    }
+
+    pub(super) fn generate_import(&mut self, import: &ImportDeclaration) {
+        if import.is_lazy || self.is_lazy_import(&import.source) {
+            self.generate_lazy_import(import);
+            return;
+        }
+
+        match &import.clause {
+            ImportClause::Default(name) | ImportClause::Namespace(name) => {
+                let value = self.import_value(&import.source);
+                self.write_line(&format!("local {} = {}", name.node, value));
+            }
+            ImportClause::Named(specifiers) => {
+                let value = self.import_value(&import.source);
+                let module = format!("_{}", module_variable_name(&import.source));
+                self.write_line(&format!("local {} = {}", module, value));
+                for specifier in specifiers {
+                    let local = specifier.local.as_ref().unwrap_or(&specifier.imported);
+                    self.write_line(&format!(
+                        "local {} = {}.{}",
+                        local.node, module, specifier.imported.node
+                    ));
+                }
+            }
+            ImportClause::TypeOnly(_) => {}
+        }
+    }
+
+    fn is_lazy_import(&self, source: &str) -> bool {
+        self.config
+            .compiler_options
+            .lazy_imports
+            .iter()
+            .any(|pattern| glob_match(pattern, source))
+    }
+
+    /// Emit a loader that requires the module on its first call and caches
+    /// it. Uses of the imported names are rewritten to go through it.
+    fn generate_lazy_import(&mut self, import: &ImportDeclaration) {
+        if matches!(import.clause, ImportClause::TypeOnly(_)) {
+            return;
+        }
+
+        let base = module_variable_name(&import.source);
+        let module = format!("_{}", base);
+        let loader = format!("_load_{}", base);
+        self.write_line(&format!("local {}", module));
+        self.write_line(&format!("local function {}()", loader));
+        self.indent();
+        self.write_line(&format!("if {} == nil then", module));
+        self.indent();
+        let value = self.import_value(&import.source);
+        self.write_line(&format!("{} = {}", module, value));
+        self.dedent();
+        self.write_line("end");
+        self.write_line(&format!("return {}", module));
+        self.dedent();
+        self.write_line("end");
+
+        match &import.clause {
+            ImportClause::Default(name) | ImportClause::Namespace(name) => {
+                self.lazy_imports
+                    .insert(name.node.clone(), format!("{}()", loader));
+            }
+            ImportClause::Named(specifiers) => {
+                for specifier in specifiers {
+                    let local = specifier.local.as_ref().unwrap_or(&specifier.imported);
+                    self.lazy_imports.insert(
+                        local.node.clone(),
+                        format!("{}().{}", loader, specifier.imported.node),
+                    );
+                }
+            }
+            ImportClause::TypeOnly(_) => {}
+        }
+    }
+
+    pub(super) fn generate_export(&mut self, export: &ExportDeclaration) {
+        if let Some(namespace) = self.namespace_path.last().cloned() {
+            self.generate_namespace_export(&namespace, export);
+            return;
+        }
+
+        match &export.kind {
+            ExportKind::Declaration(decl) => {
+                if self.is_shaken(decl) {
+                    return;
+                }
+                match decl.as_ref() {
+                    Statement::Interface(interface) => {
+                        self.generate_luau_type_declaration(decl, true);
+                        self.generate_schema(interface);
+                    }
+                    Statement::TypeAlias(_) => self.generate_luau_type_declaration(decl, true),
+                    _ => self.generate_statement(decl),
+                }
+                for name in declared_value_names(decl) {
+                    self.exports.push((name.clone(), name));
+                }
+            }
+            ExportKind::Named(specifiers) => {
+                for specifier in specifiers {
+                    let exported = specifier.exported.as_ref().unwrap_or(&specifier.local);
+                    if self.tree_shaking.exports.contains(&exported.node) {
+                        continue;
+                    }
+                    self.exports
+                        .push((exported.node.clone(), specifier.local.node.clone()));
+                }
+            }
+            ExportKind::Default(expr) => {
+                self.default_export = Some(self.generate_expression(expr));
+            }
+            ExportKind::Equals(expr) => {
+                self.module_value = Some(self.generate_expression(expr));
+            }
+        }
+    }
+
+    /// Exports inside a namespace become fields of the namespace table
+    fn generate_namespace_export(&mut self, namespace: &str, export: &ExportDeclaration) {
+        match &export.kind {
+            ExportKind::Declaration(decl) => {
+                self.generate_statement(decl);
+                for name in declared_value_names(decl) {
+                    self.write_line(&format!("{}.{} = {}", namespace, name, name));
+                }
+            }
+            ExportKind::Named(specifiers) => {
+                for specifier in specifiers {
+                    let exported = specifier.exported.as_ref().unwrap_or(&specifier.local);
+                    self.write_line(&format!(
+                        "{}.{} = {}",
+                        namespace, exported.node, specifier.local.node
+                    ));
+                }
+            }
+            ExportKind::Default(expr) => {
+                let value = self.generate_expression(expr);
+                self.write_line(&format!("{}.default = {}", namespace, value));
+            }
+            // Rejected by the checker; a namespace is always a table
+            ExportKind::Equals(_) => {}
+        }
+    }
+
+    /// A namespace is a table filled in by a `do` block, so members that are
+    /// not exported stay local to the block. Repeated blocks for the same
+    /// namespace add to the same table.
+    pub(super) fn generate_namespace(&mut self, namespace: &NamespaceDeclaration) {
+        let name = &namespace.name.node;
+        let parent = self.namespace_path.last().cloned();
+        self.namespace_path.push(name.clone());
+
+        let first = self.namespaces.insert(self.namespace_path.join("."));
+        match (first, parent) {
+            (true, _) => self.write_line(&format!("local {} = {{}}", name)),
+            // An enclosing block may have ended, so reopen through the parent
+            (false, Some(parent)) => {
+                self.write_line(&format!("local {} = {}.{} or {{}}", name, parent, name))
+            }
+            (false, None) => {}
+        }
+
+        self.write_line("do");
+        self.generate_indented_block(&namespace.body);
+        self.write_line("end");
+        self.namespace_path.pop();
+    }
+
+    /// Header emitted before any statement of the module
+    pub(super) fn generate_module_header(&mut self) {
+        if self.config.compiler_options.module_style == ModuleStyle::Module {
+            self.write_line("module(..., package.seeall)");
+        }
+    }
+
+    /// Hand the module's exports to the host in the configured style
+    pub(super) fn generate_module_footer(&mut self) {
+        match self.config.compiler_options.module_style {
+            ModuleStyle::Return => {
+                if let Some(value) = self.module_export_value() {
+                    self.write_line(&format!("return {}", value));
+                }
+            }
+            ModuleStyle::Global => {
+                if let Some(value) = self.module_export_value() {
+                    let global = match &self.module_name {
+                        Some(name) => format!("_G[{}]", quote_string(name)),
+                        None => "_G[(...)]".to_string(),
+                    };
+                    self.write_line(&format!("{} = {}", global, value));
+                    self.write_line(&format!("return {}", global));
+                }
+            }
+            ModuleStyle::Module => {
+                // `module()` already created the table; exports become its fields
+                if let Some(value) = self.module_value.take() {
+                    self.write_line(&format!("package.loaded[_NAME] = {}", value));
+                    return;
+                }
+                let exports = std::mem::take(&mut self.exports);
+                for (exported, local) in &exports {
+                    self.write_line(&format!("_M.{} = {}", exported, local));
+                }
+                if let Some(default) = self.default_export.take() {
+                    if exports.is_empty() {
+                        self.write_line(&format!("package.loaded[_NAME] = {}", default));
+                    } else {
+                        self.write_line(&format!("_M.default = {}", default));
+                    }
+                }
+            }
+        }
+    }
+
+    /// The value a module evaluates to: the `export =` value, the default
+    /// export alone, or a table of named exports
+    fn module_export_value(&mut self) -> Option<String> {
+        if let Some(value) = self.module_value.take() {
+            return Some(value);
+        }
+
+        if self.exports.is_empty() {
+            return self.default_export.take();
+        }
+
+        let exports = std::mem::take(&mut self.exports);
+        let fields = self.capture(|gen| {
+            for (exported, local) in &exports {
+                gen.write_line(&format!("{} = {},", exported, local));
+            }
+            if let Some(default) = gen.default_export.take() {
+                gen.write_line(&format!("default = {},", default));
+            }
+        });
+        Some(format!("{{\n{}{}}}", fields, self.indentation()))
+    }
+}
+
+/// Local variable name for a required module, e.g. `./utils/string` -> `string`
+pub(super) fn module_variable_name(source: &str) -> String {
+    let base = source
+        .rsplit(['/', '.'])
+        .find(|s| !s.is_empty())
+        .unwrap_or("module");
+    base.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Match an import path against a glob pattern: `*` and `?` stay within one
+/// path segment, `**` spans any number of segments
+fn glob_match(pattern: &str, path: &str) -> bool {
+    fn matches(pattern: &[char], path: &[char]) -> bool {
+        match pattern {
+            [] => path.is_empty(),
+            ['*', '*', rest @ ..] => {
+                let rest = rest.strip_prefix(&['/']).unwrap_or(rest);
+                (0..=path.len()).any(|i| matches(rest, &path[i..]))
+            }
+            ['*', rest @ ..] => (0..=path.len())
+                .take_while(|&i| i == 0 || path[i - 1] != '/')
+                .any(|i| matches(rest, &path[i..])),
+            ['?', rest @ ..] => {
+                matches!(path.first(), Some(c) if *c != '/') && matches(rest, &path[1..])
+            }
+            [c, rest @ ..] => path.first() == Some(c) && matches(rest, &path[1..]),
+        }
+    }
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    matches(&pattern, &path)
+}
+
+/// Runtime names introduced by an exported declaration
+pub(super) fn declared_value_names(statement: &Statement) -> Vec<String> {
+    match statement {
+        Statement::Variable(decl) => {
+            let mut names = Vec::new();
+            collect_bound_names(&decl.pattern, &mut names);
+            names
+        }
+        Statement::Function(func) => vec![func.name.node.clone()],
+        Statement::Class(class) => vec![class.name.node.clone()],
+        Statement::Enum(decl) if !decl.is_const => vec![decl.name.node.clone()],
+        Statement::Namespace(namespace) => vec![namespace.name.node.clone()],
+        Statement::Interface(interface) if has_attribute(&interface.decorators, "schema") => {
+            vec![interface.name.node.clone()]
+        }
+        _ => Vec::new(),
+    }
+}
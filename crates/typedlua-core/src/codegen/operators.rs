@@ -0,0 +1,54 @@
+use super::CodeGenerator;
+use crate::ast::expression::{BinaryOp, Expression, UnaryOp};
+use crate::config::LuaVersion;
+use crate::features::LuaFeature;
+
+impl CodeGenerator {
+    /// A bitwise operator the target lacks, as a call to its bit library
+    pub(super) fn lowered_binary(
+        &mut self,
+        op: BinaryOp,
+        left: &Expression,
+        right: &Expression,
+    ) -> Option<String> {
+        let function = match op {
+            BinaryOp::BitwiseAnd => "band",
+            BinaryOp::BitwiseOr => "bor",
+            BinaryOp::BitwiseXor => "bxor",
+            BinaryOp::ShiftLeft => "lshift",
+            BinaryOp::ShiftRight => "rshift",
+            _ => return None,
+        };
+        let library = self.bit_library()?;
+        let left = self.generate_expression(left);
+        let right = self.generate_expression(right);
+        Some(format!("{}.{}({}, {})", library, function, left, right))
+    }
+
+    /// `~operand` through the bit library on targets without the operator
+    pub(super) fn lowered_unary(&mut self, op: UnaryOp, operand: &Expression) -> Option<String> {
+        if op != UnaryOp::BitwiseNot {
+            return None;
+        }
+        let library = self.bit_library()?;
+        Some(format!(
+            "{}.bnot({})",
+            library,
+            self.generate_expression(operand)
+        ))
+    }
+
+    /// The library bitwise operators compile to, where they are not native.
+    /// Its functions work on 32-bit integers, unlike Lua 5.3's operators.
+    fn bit_library(&self) -> Option<&'static str> {
+        let target = self.target();
+        if target.supports(LuaFeature::BitwiseOperators) || !target.supports(LuaFeature::BitLibrary)
+        {
+            return None;
+        }
+        Some(match target {
+            LuaVersion::LuaJIT => "bit",
+            _ => "bit32",
+        })
+    }
+}
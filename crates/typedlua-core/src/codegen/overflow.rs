@@ -0,0 +1,43 @@
+use super::helpers::RuntimeHelper;
+use super::CodeGenerator;
+use crate::ast::attributes::has_attribute;
+use crate::ast::expression::BinaryOp;
+use crate::ast::statement::FunctionDeclaration;
+use crate::config::IntegerOverflow;
+
+impl CodeGenerator {
+    /// Whether the body of `func` checks its arithmetic for overflow: it is
+    /// `@checked`, or nested in a function that is, and `integerOverflow`
+    /// is `checked`
+    pub(super) fn checks_overflow(&self, func: &FunctionDeclaration) -> bool {
+        self.checking_overflow
+            || (self.config.compiler_options.integer_overflow == IntegerOverflow::Checked
+                && has_attribute(&func.decorators, "checked"))
+    }
+
+    /// The operator of `op` when it needs an overflow check, which is
+    /// `+ - *` inside a checked function
+    pub(super) fn checked_operator(&self, op: BinaryOp) -> Option<&'static str> {
+        if !self.checking_overflow {
+            return None;
+        }
+        match op {
+            BinaryOp::Add => Some("+"),
+            BinaryOp::Subtract => Some("-"),
+            BinaryOp::Multiply => Some("*"),
+            _ => None,
+        }
+    }
+
+    /// `left operator right` through `__checked_arith`
+    pub(super) fn checked_arithmetic(&mut self, operator: &str, left: &str, right: &str) -> String {
+        self.use_helper(RuntimeHelper::CheckedArithmetic);
+        format!(
+            "{}(\"{}\", {}, {})",
+            RuntimeHelper::CheckedArithmetic.name(),
+            operator,
+            left,
+            right
+        )
+    }
+}
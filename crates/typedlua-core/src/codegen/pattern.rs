@@ -0,0 +1,207 @@
+use super::expression::format_literal;
+use super::CodeGenerator;
+use crate::ast::expression::{MatchArmBody, MatchExpression};
+use crate::ast::pattern::{ArrayPatternElement, MultipleRest, Pattern};
+use crate::ast::statement::Statement;
+use crate::config::LuaVersion;
+
+impl CodeGenerator {
+    /// Bind every name in a destructuring pattern from an already generated
+    /// value. Nested patterns read through a temporary.
+    pub(super) fn generate_destructuring(&mut self, pattern: &Pattern, value: &str) {
+        self.shadow_pattern_constants(pattern);
+        match pattern {
+            Pattern::Identifier(name) => {
+                self.write_line(&format!("local {} = {}", name.node, value));
+            }
+            Pattern::Literal(..) | Pattern::Wildcard(_) => {}
+            Pattern::Multiple(multiple) => {
+                let names: Vec<&str> = multiple.names.iter().map(|n| n.node.as_str()).collect();
+                let line = match &multiple.rest {
+                    Some(MultipleRest::Capture(rest)) if names.is_empty() => {
+                        format!("local {} = {{ {} }}", rest.node, value)
+                    }
+                    // The extra values are only reachable as `...` of a function
+                    Some(MultipleRest::Capture(rest)) => {
+                        let names = names.join(", ");
+                        format!(
+                            "local {}, {} = (function({}, ...) return {}, {{ ... }} end)({})",
+                            names, rest.node, names, names, value
+                        )
+                    }
+                    _ => format!("local {} = {}", names.join(", "), value),
+                };
+                self.write_line(&line);
+            }
+            Pattern::Array(array) => {
+                let temp = self.temp_for(value);
+                let mut index = 0;
+                for element in &array.elements {
+                    match element {
+                        ArrayPatternElement::Pattern(pattern) => {
+                            index += 1;
+                            self.generate_destructuring(pattern, &format!("{}[{}]", temp, index));
+                        }
+                        ArrayPatternElement::Rest(name) => {
+                            self.write_line(&format!("local {} = {{}}", name.node));
+                            self.write_line(&format!(
+                                "for i = {}, #{} do {}[#{} + 1] = {}[i] end",
+                                index + 1,
+                                temp,
+                                name.node,
+                                name.node,
+                                temp
+                            ));
+                        }
+                        ArrayPatternElement::Hole => index += 1,
+                    }
+                }
+            }
+            Pattern::Object(object) => {
+                let temp = self.temp_for(value);
+                for property in &object.properties {
+                    let field = format!("{}.{}", temp, property.key.node);
+                    match &property.value {
+                        Some(pattern) if property.default.is_none() => {
+                            self.generate_destructuring(pattern, &field);
+                        }
+                        Some(pattern) => {
+                            let default =
+                                self.generate_expression(property.default.as_ref().unwrap());
+                            let field_temp = self.fresh_temp("tmp");
+                            self.write_line(&format!("local {} = {}", field_temp, field));
+                            self.write_line(&format!(
+                                "if {} == nil then {} = {} end",
+                                field_temp, field_temp, default
+                            ));
+                            self.generate_destructuring(pattern, &field_temp);
+                        }
+                        None => {
+                            let name = &property.key.node;
+                            self.write_line(&format!("local {} = {}", name, field));
+                            if let Some(default) = &property.default {
+                                let default = self.generate_expression(default);
+                                self.write_line(&format!(
+                                    "if {} == nil then {} = {} end",
+                                    name, name, default
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Plain names can be indexed directly; anything else is evaluated once
+    fn temp_for(&mut self, value: &str) -> String {
+        if is_name(value) {
+            return value.to_string();
+        }
+        let temp = self.fresh_temp("tmp");
+        self.write_line(&format!("local {} = {}", temp, value));
+        temp
+    }
+
+    /// `match` becomes an immediately invoked function that tests each arm
+    /// in order and returns from the first one that matches. Every arm
+    /// returns, so a failed guard falls through to the next arm.
+    pub(super) fn generate_match(&mut self, match_expr: &MatchExpression) -> String {
+        let value = self.generate_expression(&match_expr.value);
+        let subject = "_m";
+
+        let body = self.capture(|gen| {
+            for arm in &match_expr.arms {
+                let condition = pattern_condition(&arm.pattern, subject, gen.target());
+                gen.write_line(&format!("if {} then", condition));
+                gen.indent();
+                gen.generate_destructuring(&arm.pattern, subject);
+                match &arm.guard {
+                    Some(guard) => {
+                        // Guards may refer to bound names, so they are tested after binding
+                        let guard = gen.generate_expression(guard);
+                        gen.write_line(&format!("if {} then", guard));
+                        gen.indent();
+                        gen.generate_arm_body(&arm.body);
+                        gen.dedent();
+                        gen.write_line("end");
+                    }
+                    None => gen.generate_arm_body(&arm.body),
+                }
+                gen.dedent();
+                gen.write_line("end");
+            }
+        });
+
+        format!(
+            "(function({})\n{}{}end)({})",
+            subject,
+            body,
+            self.indentation(),
+            value
+        )
+    }
+
+    fn generate_arm_body(&mut self, body: &MatchArmBody) {
+        match body {
+            MatchArmBody::Expression(expr) => {
+                let value = self.generate_expression(expr);
+                self.write_line(&format!("return {}", value));
+            }
+            MatchArmBody::Block(block) => {
+                self.generate_block(block);
+                if !matches!(block.statements.last(), Some(Statement::Return(_))) {
+                    self.write_line("return nil");
+                }
+            }
+        }
+    }
+}
+
+/// Names introduced by a pattern, in binding order
+pub(crate) fn collect_bound_names(pattern: &Pattern, names: &mut Vec<String>) {
+    match pattern {
+        Pattern::Identifier(name) => names.push(name.node.clone()),
+        Pattern::Literal(..) | Pattern::Wildcard(_) => {}
+        Pattern::Multiple(multiple) => {
+            names.extend(multiple.names.iter().map(|name| name.node.clone()));
+            if let Some(MultipleRest::Capture(rest)) = &multiple.rest {
+                names.push(rest.node.clone());
+            }
+        }
+        Pattern::Array(array) => {
+            for element in &array.elements {
+                match element {
+                    ArrayPatternElement::Pattern(pattern) => collect_bound_names(pattern, names),
+                    ArrayPatternElement::Rest(name) => names.push(name.node.clone()),
+                    ArrayPatternElement::Hole => {}
+                }
+            }
+        }
+        Pattern::Object(object) => {
+            for property in &object.properties {
+                match &property.value {
+                    Some(pattern) => collect_bound_names(pattern, names),
+                    None => names.push(property.key.node.clone()),
+                }
+            }
+        }
+    }
+}
+
+/// Lua condition testing whether a value matches a pattern's shape
+fn pattern_condition(pattern: &Pattern, value: &str, target: LuaVersion) -> String {
+    match pattern {
+        Pattern::Identifier(_) | Pattern::Wildcard(_) | Pattern::Multiple(_) => "true".to_string(),
+        Pattern::Literal(literal, _) => {
+            format!("{} == {}", value, format_literal(literal, target))
+        }
+        Pattern::Array(_) | Pattern::Object(_) => format!("type({}) == \"table\"", value),
+    }
+}
+
+fn is_name(text: &str) -> bool {
+    let mut chars = text.chars();
+    matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
@@ -0,0 +1,151 @@
+use super::expression::{format_literal, quote_string};
+use super::CodeGenerator;
+use crate::ast::expression::{ArrayElement, Expression, ExpressionKind, Literal, ObjectProperty};
+use crate::config::LuaVersion;
+use std::collections::HashMap;
+
+/// Shortest string literal, in characters, worth pooling
+const MIN_POOLED_STRING: usize = 16;
+
+/// Lua allows 200 locals per function; the module keeps the rest
+const MAX_POOLED: usize = 100;
+
+/// Repeated string literals and constant tables hoisted into module-level
+/// locals by `poolLiterals`. A first generation pass counts each constant;
+/// the second replaces the chosen ones by their local.
+#[derive(Default)]
+pub(super) struct LiteralPool {
+    /// Order of first use and number of uses of each constant in the
+    /// counting pass
+    counts: HashMap<String, (usize, usize)>,
+    /// Local name of each constant chosen for pooling
+    names: HashMap<String, String>,
+    /// Pooled locals and their values, in the order they are defined.
+    /// Tables come after the constants they contain.
+    definitions: Vec<(String, String)>,
+    counting: bool,
+}
+
+impl LiteralPool {
+    fn count(&mut self, key: String) {
+        let first_use = self.counts.len();
+        self.counts.entry(key).or_insert((first_use, 0)).1 += 1;
+    }
+
+    /// Pool the repeated constants that save the most code, numbered in
+    /// order of first use
+    fn choose(&mut self) {
+        let mut repeated: Vec<(&String, usize, usize)> = self
+            .counts
+            .iter()
+            .filter(|(_, (_, count))| *count > 1)
+            .map(|(key, (first_use, count))| (key, *first_use, *count))
+            .collect();
+        repeated.sort_by_key(|(key, first_use, count)| {
+            (std::cmp::Reverse((count - 1) * key.len()), *first_use)
+        });
+        repeated.truncate(MAX_POOLED);
+        repeated.sort_by_key(|(_, first_use, _)| *first_use);
+        self.names = repeated
+            .into_iter()
+            .enumerate()
+            .map(|(i, (key, _, _))| (key.clone(), format!("__pool{}", i + 1)))
+            .collect();
+    }
+}
+
+impl CodeGenerator {
+    pub(super) fn pools_literals(&self) -> bool {
+        let options = &self.config.compiler_options;
+        options.pool_literals && !options.readable_output
+    }
+
+    /// Start the pass that counts constants instead of replacing them
+    pub(super) fn begin_pool_count(&mut self) {
+        self.pool = LiteralPool {
+            counting: true,
+            ..Default::default()
+        };
+    }
+
+    pub(super) fn end_pool_count(&mut self) {
+        self.pool.counting = false;
+        self.pool.choose();
+    }
+
+    /// The generated code of a constant, or the local it is pooled in
+    pub(super) fn pool_constant(&mut self, expr: &Expression, code: String) -> String {
+        if !self.pools_literals() {
+            return code;
+        }
+        let Some(key) = constant_key(expr, self.target()) else {
+            return code;
+        };
+        if self.pool.counting {
+            self.pool.count(key);
+            return code;
+        }
+        let Some(name) = self.pool.names.get(&key).cloned() else {
+            return code;
+        };
+        if !self
+            .pool
+            .definitions
+            .iter()
+            .any(|(defined, _)| *defined == name)
+        {
+            self.pool.definitions.push((name.clone(), code));
+        }
+        name
+    }
+
+    /// Definitions of the pooled locals, placed above the module body
+    pub(super) fn pool_prelude(&mut self) -> String {
+        std::mem::take(&mut self.pool.definitions)
+            .into_iter()
+            .map(|(name, value)| format!("local {} = {}\n", name, value))
+            .collect()
+    }
+}
+
+/// A canonical spelling of a long string literal or a non-empty table built
+/// only from literals and such tables. Other expressions are not pooled.
+fn constant_key(expr: &Expression, target: LuaVersion) -> Option<String> {
+    match &expr.kind {
+        ExpressionKind::Literal(Literal::String(s)) if s.chars().count() >= MIN_POOLED_STRING => {
+            Some(quote_string(s))
+        }
+        ExpressionKind::Array(elements) if !elements.is_empty() => {
+            let items = elements
+                .iter()
+                .map(|element| match element {
+                    ArrayElement::Expression(e) => constant_value(e, target),
+                    ArrayElement::Spread(_) => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            Some(format!("{{{}}}", items.join(", ")))
+        }
+        ExpressionKind::Object(properties) if !properties.is_empty() => {
+            let fields = properties
+                .iter()
+                .map(|property| match property {
+                    ObjectProperty::Property { key, value, .. } => {
+                        Some(format!("{} = {}", key.node, constant_value(value, target)?))
+                    }
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            Some(format!("{{{}}}", fields.join(", ")))
+        }
+        _ => None,
+    }
+}
+
+/// Any literal, or a constant table, as spelled in the key of the table
+/// holding it
+fn constant_value(expr: &Expression, target: LuaVersion) -> Option<String> {
+    match &expr.kind {
+        ExpressionKind::Literal(literal) => Some(format_literal(literal, target)),
+        _ => constant_key(expr, target),
+    }
+}
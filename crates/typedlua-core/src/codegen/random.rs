@@ -0,0 +1,116 @@
+use super::modules::declared_value_names;
+use crate::ast::statement::{ImportClause, Statement};
+use crate::config::LuaVersion;
+use crate::features::LuaFeature;
+
+/// The name the runtime's seeded generator is declared under
+pub(super) const RANDOM: &str = "Random";
+
+/// Whether a top-level statement declares its own `Random`, which shadows
+/// the runtime's
+pub(super) fn declares_random(statement: &Statement) -> bool {
+    match statement {
+        Statement::Import(import) => match &import.clause {
+            ImportClause::Default(name) | ImportClause::Namespace(name) => name.node == RANDOM,
+            ImportClause::Named(specifiers) => specifiers.iter().any(|specifier| {
+                specifier.local.as_ref().unwrap_or(&specifier.imported).node == RANDOM
+            }),
+            ImportClause::TypeOnly(_) => false,
+        },
+        statement => declared_value_names(statement)
+            .iter()
+            .any(|name| name == RANDOM),
+    }
+}
+
+/// A PCG32 generator: 64 bits of state, kept as two 32-bit halves so every
+/// target computes it exactly with doubles, and a permuted 32-bit output.
+/// Only the xor differs between targets.
+pub(super) fn random_source(target: LuaVersion) -> String {
+    let bxor = if target.supports(LuaFeature::BitwiseOperators) {
+        "function(a, b)
+    return a ~ b
+  end"
+    } else if target == LuaVersion::LuaJIT {
+        "function(a, b)
+    return bit.bxor(a, b) % 4294967296
+  end"
+    } else if target.supports(LuaFeature::BitLibrary) {
+        "bit32.bxor"
+    } else {
+        "function(a, b)
+    local result, bit = 0, 1
+    while a > 0 or b > 0 do
+      if a % 2 ~= b % 2 then
+        result = result + bit
+      end
+      a, b, bit = math.floor(a / 2), math.floor(b / 2), bit * 2
+    end
+    return result
+  end"
+    };
+    format!(
+        "local Random = {{}}
+Random.__index = Random
+do
+  local bxor = {}
+  -- The low and high halves of a * b, for a and b below 2^32
+  local function multiply(a, b)
+    local a1, a0 = math.floor(a / 65536), a % 65536
+    local b1, b0 = math.floor(b / 65536), b % 65536
+    local middle = a1 * b0 + a0 * b1
+    local low = a0 * b0 + (middle % 65536) * 65536
+    return low % 4294967296, a1 * b1 + math.floor(middle / 65536) + math.floor(low / 4294967296)
+  end
+  -- state = state * 0x5851F42D4C957F2D + 0x14057B7EF767814F
+  local function step(rng)
+    local low, high = multiply(rng.low, 0x4C957F2D)
+    high = high + multiply(rng.high, 0x4C957F2D) + multiply(rng.low, 0x5851F42D)
+    low = low + 0xF767814F
+    rng.low = low % 4294967296
+    rng.high = (high + 0x14057B7E + math.floor(low / 4294967296)) % 4294967296
+  end
+  function Random.new(seed)
+    local rng = setmetatable({{ high = 0, low = 0 }}, Random)
+    step(rng)
+    local low = rng.low + seed % 4294967296
+    rng.low = low % 4294967296
+    rng.high = (rng.high + math.floor(seed / 4294967296) + math.floor(low / 4294967296)) % 4294967296
+    step(rng)
+    return rng
+  end
+  function Random:next()
+    local high, low = self.high, self.low
+    step(self)
+    -- ((state >> 18) ~ state) >> 27, rotated right by state >> 59
+    local xhigh = bxor(math.floor(high / 262144), high)
+    local xlow = bxor(math.floor(low / 262144) + (high % 262144) * 16384, low)
+    local value = math.floor(xlow / 134217728) + (xhigh % 134217728) * 32
+    local rotation = math.floor(high / 134217728)
+    if rotation == 0 then
+      return value
+    end
+    local shift = 2 ^ rotation
+    return math.floor(math.floor(value / shift) + (value % shift) * 2 ^ (32 - rotation))
+  end
+  function Random:nextFloat()
+    return self:next() / 4294967296
+  end
+  function Random:nextInt(min, max)
+    local range = max - min + 1
+    if range < 1 or range > 4294967296 then
+      error(\"invalid range [\" .. tostring(min) .. \", \" .. tostring(max) .. \"]\", 2)
+    end
+    -- Reject the few low values that would make some results likelier
+    local threshold = (4294967296 - range) % range
+    local value = self:next()
+    while value < threshold do
+      value = self:next()
+    end
+    return min + value % range
+  end
+end
+",
+        bxor
+    )
+}
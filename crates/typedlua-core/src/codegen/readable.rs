@@ -0,0 +1,141 @@
+//! Readable output (`readableOutput`), for people reviewing the emitted Lua
+//! rather than running it fast: doc comments are carried over as `---`
+//! comments and each block of statements is marked with the line it came
+//! from, for output vendored into engines that cannot use source maps.
+
+use super::CodeGenerator;
+use crate::span::Span;
+use std::ops::Range;
+
+/// The module being generated, as written
+pub(super) struct ReadableSource {
+    /// The name the `-- from` markers give the file
+    file: String,
+    /// Spans count characters
+    chars: Vec<char>,
+    comments: Vec<Span>,
+    /// The line after the statement emitted last. A statement starting
+    /// anywhere else starts a new block.
+    next_line: usize,
+    /// The line of the statement noted last, so statements nested on its
+    /// line are not noted again
+    noted_line: usize,
+}
+
+impl CodeGenerator {
+    /// Generate readable output for `source`, named `file` by the markers,
+    /// whose comments the lexer found at `comments`
+    pub fn set_readable_source(&mut self, file: &str, source: &str, comments: &[Span]) {
+        self.readable = Some(ReadableSource {
+            file: file.to_string(),
+            chars: source.chars().collect(),
+            comments: comments.to_vec(),
+            next_line: 0,
+            noted_line: 0,
+        });
+    }
+
+    /// Write what precedes the statement or class member at `span`: the
+    /// line it came from if it starts a block, then its doc comments.
+    /// Returns where the notes were written in the output.
+    pub(super) fn write_source_notes(&mut self, span: Span) -> Option<Range<usize>> {
+        let readable = self.readable.as_mut()?;
+        if span.line == 0 || span.line == readable.noted_line {
+            return None;
+        }
+        readable.noted_line = span.line;
+        let mut lines = doc_comment_lines(readable, span);
+        if span.line != readable.next_line {
+            lines.insert(0, format!("-- from {}:{}", readable.file, span.line));
+        }
+        let start = self.output.len();
+        for line in lines {
+            self.write_line(&line);
+        }
+        Some(start..self.output.len())
+    }
+
+    /// Finish the statement or class member at `span`, taking back its
+    /// notes if it emitted nothing, as type declarations do
+    pub(super) fn end_source_notes(&mut self, span: Span, notes: Option<Range<usize>>) {
+        let Some(readable) = self.readable.as_mut() else {
+            return;
+        };
+        let end = span.end.min(readable.chars.len());
+        let start = span.start.min(end);
+        let lines = readable.chars[start..end]
+            .iter()
+            .filter(|c| **c == '\n')
+            .count();
+        readable.next_line = readable.next_line.max(span.line + lines + 1);
+        if let Some(notes) = notes {
+            if self.output.len() == notes.end {
+                self.output.truncate(notes.start);
+            }
+        }
+    }
+}
+
+/// The doc comments written directly above `span`, as Lua comment lines.
+/// Decorators and plain comments may sit between them and the statement.
+fn doc_comment_lines(readable: &ReadableSource, span: Span) -> Vec<String> {
+    let mut attached = Vec::new();
+    let mut end = span.start.min(readable.chars.len());
+    for comment in readable.comments.iter().rev() {
+        if comment.end > end {
+            continue;
+        }
+        let gap: String = readable.chars[comment.end..end].iter().collect();
+        let separated = gap
+            .lines()
+            .map(str::trim)
+            .any(|line| !line.is_empty() && !line.starts_with('@'));
+        if separated {
+            break;
+        }
+        let text: String = readable.chars[comment.start..comment.end].iter().collect();
+        attached.push(text);
+        end = comment.start;
+    }
+    attached
+        .iter()
+        .rev()
+        .flat_map(|text| lua_doc_comment(text))
+        .collect()
+}
+
+/// `/// text` and `/** text */` as `--- text` lines. Other comments are
+/// not documentation and are left out.
+fn lua_doc_comment(text: &str) -> Vec<String> {
+    if let Some(line) = text.strip_prefix("///") {
+        return vec![format!("---{}", line.trim_end())];
+    }
+    let Some(body) = text
+        .strip_prefix("/**")
+        .and_then(|body| body.strip_suffix("*/"))
+    else {
+        return Vec::new();
+    };
+    let lines: Vec<&str> = body
+        .lines()
+        .map(|line| {
+            let line = line.trim();
+            line.strip_prefix('*').map_or(line, str::trim_start)
+        })
+        .collect();
+    let first = lines.iter().position(|line| !line.is_empty());
+    let last = lines.iter().rposition(|line| !line.is_empty());
+    let (Some(first), Some(last)) = (first, last) else {
+        return Vec::new();
+    };
+    lines[first..=last]
+        .iter()
+        .map(|line| {
+            if line.is_empty() {
+                "---".to_string()
+            } else {
+                format!("--- {}", line)
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,84 @@
+use super::CodeGenerator;
+use crate::ast::attributes::{migration_step, schema_version};
+use crate::ast::statement::{FunctionDeclaration, InterfaceDeclaration, InterfaceMember};
+
+impl CodeGenerator {
+    /// The table of a `@schema` record: its version, the migrations
+    /// registered so far, and `serialize` and `deserialize`. Saved data is
+    /// `{ version = N, data = { ... } }`, holding the record's declared
+    /// properties; older versions are migrated one step at a time on load.
+    pub(super) fn generate_schema(&mut self, interface: &InterfaceDeclaration) {
+        let Some(Ok(version)) = schema_version(&interface.decorators) else {
+            return;
+        };
+        let name = interface.name.node.clone();
+        let fields: Vec<String> = interface
+            .members
+            .iter()
+            .filter_map(|member| match member {
+                InterfaceMember::Property(prop) => {
+                    Some(format!("{} = value.{}", prop.name.node, prop.name.node))
+                }
+                _ => None,
+            })
+            .collect();
+
+        self.write_line(&format!(
+            "local {} = {{ version = {}, migrations = {{}} }}",
+            name, version
+        ));
+        self.write_line(&format!("function {}.serialize(value)", name));
+        self.indent();
+        self.write_line(&format!(
+            "return {{ version = {}, data = {{ {} }} }}",
+            version,
+            fields.join(", ")
+        ));
+        self.dedent();
+        self.write_line("end");
+
+        self.write_line(&format!("function {}.deserialize(saved)", name));
+        self.indent();
+        self.write_line(&format!("if saved.version > {} then", version));
+        self.indent();
+        self.write_line(&format!(
+            "error(\"{}: saved version \" .. saved.version .. \" is newer than {}\")",
+            name, version
+        ));
+        self.dedent();
+        self.write_line("end");
+        self.write_line("local data = saved.data");
+        self.write_line(&format!("for version = saved.version, {} do", version - 1));
+        self.indent();
+        self.write_line(&format!("data = {}.migrations[version](data)", name));
+        self.dedent();
+        self.write_line("end");
+        self.write_line("return data");
+        self.dedent();
+        self.write_line("end");
+
+        for (from, function) in self.pending_migrations.remove(&name).unwrap_or_default() {
+            self.write_line(&format!("{}.migrations[{}] = {}", name, from, function));
+        }
+        self.schemas.insert(name);
+    }
+
+    /// Register a `@migration` function with its record, or hold it until
+    /// the record's table is defined
+    pub(super) fn register_migration(&mut self, func: &FunctionDeclaration) {
+        let Some(Ok((schema, from))) = migration_step(&func.decorators) else {
+            return;
+        };
+        if self.schemas.contains(schema) {
+            self.write_line(&format!(
+                "{}.migrations[{}] = {}",
+                schema, from, func.name.node
+            ));
+        } else {
+            self.pending_migrations
+                .entry(schema.to_string())
+                .or_default()
+                .push((from, func.name.node.clone()));
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use super::CodeGenerator;
+use crate::span::Span;
+use serde::{Deserialize, Serialize};
+
+/// Opens and closes a `line:column` marker at the start of generated code.
+/// Control characters are always escaped in emitted strings, so markers
+/// can be stripped once the module is assembled, wherever capturing and
+/// preludes have moved the lines they start.
+const MARKER: char = '\u{1}';
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The source position each line of a generated module came from
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SourceMap {
+    /// Line and column, both 1-based, per output line
+    lines: Vec<Option<(usize, usize)>>,
+}
+
+impl SourceMap {
+    /// The source line and column that output `line` (1-based) was
+    /// generated from
+    pub fn lookup(&self, line: usize) -> Option<(usize, usize)> {
+        self.lines.get(line.checked_sub(1)?).copied().flatten()
+    }
+
+    /// The map as Source Map v3 JSON for the Lua file `file`, generated
+    /// from `source`
+    pub fn to_json(&self, file: &str, source: &str) -> String {
+        serde_json::json!({
+            "version": 3,
+            "file": file,
+            "sources": [source],
+            "names": [],
+            "mappings": self.mappings(),
+        })
+        .to_string()
+    }
+
+    /// One segment per mapped line, at its first column. Source lines and
+    /// columns are relative to the previous segment, and 0-based.
+    fn mappings(&self) -> String {
+        let mut mappings = String::new();
+        let (mut previous_line, mut previous_column) = (0, 0);
+        for (i, position) in self.lines.iter().enumerate() {
+            if i > 0 {
+                mappings.push(';');
+            }
+            let Some((line, column)) = position else {
+                continue;
+            };
+            let (line, column) = (*line as i64 - 1, *column as i64 - 1);
+            for value in [0, 0, line - previous_line, column - previous_column] {
+                encode_vlq(value, &mut mappings);
+            }
+            (previous_line, previous_column) = (line, column);
+        }
+        mappings
+    }
+}
+
+fn encode_vlq(value: i64, out: &mut String) {
+    let mut rest = if value < 0 {
+        ((-value) << 1) | 1
+    } else {
+        value << 1
+    };
+    loop {
+        let mut digit = rest & 0b11111;
+        rest >>= 5;
+        if rest > 0 {
+            digit |= 0b100000;
+        }
+        out.push(BASE64[digit as usize] as char);
+        if rest == 0 {
+            break;
+        }
+    }
+}
+
+impl CodeGenerator {
+    pub(super) fn maps_sources(&self) -> bool {
+        self.config.compiler_options.source_map
+    }
+
+    /// Attribute the lines written from now on to `span`, returning the
+    /// position to restore afterwards
+    pub(super) fn enter_source(&mut self, span: Span) -> Option<(usize, usize)> {
+        let outer = self.source_position;
+        if span.line > 0 {
+            self.source_position = Some((span.line, span.column));
+        }
+        outer
+    }
+
+    /// The marker starting a line written at the current source position
+    pub(super) fn source_marker(&self) -> Option<String> {
+        let (line, column) = self.source_position.filter(|_| self.maps_sources())?;
+        Some(format!("{}{}:{}{}", MARKER, line, column, MARKER))
+    }
+}
+
+/// Remove the markers from an assembled module, mapping each line to the
+/// first marker on it
+pub(super) fn strip_markers(code: &str) -> (String, SourceMap) {
+    let mut stripped = String::with_capacity(code.len());
+    let mut map = SourceMap::default();
+    for line in code.split_inclusive('\n') {
+        let mut position = None;
+        let mut parts = line.split(MARKER);
+        stripped.push_str(parts.next().unwrap_or_default());
+        while let (Some(marker), Some(text)) = (parts.next(), parts.next()) {
+            if position.is_none() {
+                position = marker
+                    .split_once(':')
+                    .and_then(|(line, column)| Some((line.parse().ok()?, column.parse().ok()?)));
+            }
+            stripped.push_str(text);
+        }
+        map.lines.push(position);
+    }
+    (stripped, map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vlq_encoding() {
+        let encode = |value| {
+            let mut out = String::new();
+            encode_vlq(value, &mut out);
+            out
+        };
+        assert_eq!(encode(0), "A");
+        assert_eq!(encode(1), "C");
+        assert_eq!(encode(-1), "D");
+        assert_eq!(encode(15), "e");
+        assert_eq!(encode(16), "gB");
+        assert_eq!(encode(-17), "jB");
+    }
+
+    #[test]
+    fn test_strip_markers() {
+        let code =
+            "local helper = 1\n\u{1}2:1\u{1}local x = function()\n  \u{1}3:3\u{1}return 1\nend\n";
+        let (code, map) = strip_markers(code);
+        assert_eq!(
+            code,
+            "local helper = 1\nlocal x = function()\n  return 1\nend\n"
+        );
+        assert_eq!(map.lookup(1), None);
+        assert_eq!(map.lookup(2), Some((2, 1)));
+        assert_eq!(map.lookup(3), Some((3, 3)));
+        assert_eq!(map.lookup(4), None);
+        assert_eq!(map.mappings(), ";AACA;AACE;");
+    }
+}
@@ -0,0 +1,1135 @@
+use super::*;
+use crate::config::{IntegerOverflow, ModuleStyle};
+use crate::diagnostics::{CollectingDiagnosticHandler, DiagnosticHandler};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+fn generate_with(source: &str, config: CompilerConfig) -> String {
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let mut lexer = Lexer::new(source, handler.clone());
+    let tokens = lexer.tokenize().expect("Lexing failed");
+    let mut parser = Parser::new(tokens, handler.clone());
+    let program = parser.parse().expect("Parse failed");
+    assert!(
+        !handler.has_errors(),
+        "Unexpected parse errors: {:?}",
+        handler.get_diagnostics()
+    );
+
+    CodeGenerator::new(Arc::new(config)).generate(&program)
+}
+
+fn generate_for(source: &str, target: LuaVersion) -> String {
+    let mut config = CompilerConfig::default();
+    config.compiler_options.target = target;
+    generate_with(source, config)
+}
+
+fn generate(source: &str) -> String {
+    generate_for(source, LuaVersion::Lua54)
+}
+
+/// What a chunk prints when run by the first of `interpreters` installed,
+/// or `None` when none is, so the generated code is only compared as text
+fn run_lua(lua: &str, interpreters: &[&str]) -> Option<String> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut child = interpreters.iter().find_map(|interpreter| {
+        Command::new(interpreter)
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .ok()
+    })?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(lua.as_bytes())
+        .expect("the interpreter reads its chunk");
+    let output = child.wait_with_output().expect("the interpreter runs");
+    assert!(
+        output.status.success(),
+        "{}\n{}",
+        lua,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[test]
+fn test_export_equals_returns_single_value() {
+    let source = r#"
+        function createLogger(name: string)
+            return name
+        end
+
+        export = createLogger
+    "#;
+    assert_eq!(
+        generate(source),
+        "local function createLogger(name)\n  return name\nend\nreturn createLogger\n"
+    );
+}
+
+#[test]
+fn test_default_import_binds_module_value() {
+    let lua = generate("import createLogger from \"./logger\"\nconst log = createLogger(\"app\")");
+    assert_eq!(
+        lua,
+        "local createLogger = require(\"./logger\")\nlocal log = createLogger(\"app\")\n"
+    );
+}
+
+#[test]
+fn test_named_imports_and_exports() {
+    let source = r#"
+        import { createUser, User as U } from "./user"
+        export const version = 2
+        export { createUser }
+    "#;
+    assert_eq!(
+        generate(source),
+        "local _user = require(\"./user\")\n\
+         local createUser = _user.createUser\n\
+         local U = _user.User\n\
+         local version = 2\n\
+         return {\n  version = version,\n  createUser = createUser,\n}\n"
+    );
+}
+
+#[test]
+fn test_export_default_only() {
+    assert_eq!(generate("export default 42"), "return 42\n");
+}
+
+#[test]
+fn test_class_lowering() {
+    let source = r#"
+        class Counter {
+            count: number = 0
+
+            increment(): void {
+                self.count = self.count + 1
+            }
+
+            static create(): Counter {
+                return Counter.new()
+            }
+        }
+    "#;
+    let lua = generate(source);
+    assert!(lua.starts_with("local Counter = {}\nCounter.__index = Counter\n"));
+    assert!(lua.contains("function Counter._init(self)\n  self.count = 0\nend\n"));
+    assert!(lua.contains(
+        "function Counter.new(...)\n  local self = setmetatable({}, Counter)\n  Counter._init(self, ...)\n  return self\nend\n"
+    ));
+    assert!(lua.contains("function Counter:increment()\n  self.count = self.count + 1\nend\n"));
+    assert!(lua.contains("function Counter.create()\n  return Counter.new()\nend\n"));
+}
+
+#[test]
+fn test_static_initialization_follows_methods() {
+    let source = r#"
+        class Registry {
+            const LIMIT = 3
+            static entries: {string} = Registry.empty()
+
+            static do
+                Registry.entries[1] = "first"
+            end
+
+            static empty(): {string} {
+                return {}
+            }
+        }
+    "#;
+    let lua = generate(source);
+    assert!(lua.ends_with(
+        "function Registry.empty()\n  return {}\nend\n\n\
+         Registry.LIMIT = 3\n\
+         Registry.entries = Registry.empty()\n\
+         do\n  Registry.entries[1] = \"first\"\nend\n"
+    ));
+}
+
+#[test]
+fn test_enum_auto_increments_from_one() {
+    let lua = generate("enum Color { Red, Green = 5, Blue }");
+    assert_eq!(
+        lua,
+        "local Color = {\n  Red = 1,\n  Green = 5,\n  Blue = 6,\n}\n"
+    );
+}
+
+#[test]
+fn test_expression_precedence() {
+    let lua = generate("local x = (1 + 2) * 3\nlocal y = 1 + 2 * 3\nlocal z = 10 - (4 - 1)");
+    assert_eq!(
+        lua,
+        "local x = (1 + 2) * 3\nlocal y = 1 + 2 * 3\nlocal z = 10 - (4 - 1)\n"
+    );
+}
+
+#[test]
+fn test_continue_lowering_depends_on_target() {
+    let source = "while true do\n  continue\nend";
+    assert_eq!(
+        generate_for(source, LuaVersion::Lua54),
+        "while true do\n  goto continue\n  ::continue::\nend\n"
+    );
+    assert_eq!(
+        generate_for(source, LuaVersion::Lua51),
+        "while true do\n  repeat\n    break\n  until true\nend\n"
+    );
+}
+
+fn generate_in_style(source: &str, style: ModuleStyle) -> String {
+    let mut config = CompilerConfig::default();
+    config.compiler_options.module_style = style;
+    generate_with(source, config)
+}
+
+#[test]
+fn test_module_style_legacy_module() {
+    let source = "export function greet() end\nexport const version = 1";
+    assert_eq!(
+        generate_in_style(source, ModuleStyle::Module),
+        "module(..., package.seeall)\n\
+         local function greet()\n\
+         end\n\
+         local version = 1\n\
+         _M.greet = greet\n\
+         _M.version = version\n"
+    );
+    assert_eq!(
+        generate_in_style("export = 42", ModuleStyle::Module),
+        "module(..., package.seeall)\npackage.loaded[_NAME] = 42\n"
+    );
+}
+
+#[test]
+fn test_module_style_global_registration() {
+    let source = "export const version = 1";
+    assert_eq!(
+        generate_in_style(source, ModuleStyle::Global),
+        "local version = 1\n_G[(...)] = {\n  version = version,\n}\nreturn _G[(...)]\n"
+    );
+}
+
+#[test]
+fn test_import_shim_for_global_registration_module() {
+    let mut config = CompilerConfig::default();
+    config
+        .compiler_options
+        .module_conventions
+        .insert("./vendor/json".to_string(), ModuleStyle::Global);
+
+    let source = "import json from \"./vendor/json\"\nimport { encode } from \"./vendor/json\"";
+    assert_eq!(
+        generate_with(source, config),
+        "require(\"./vendor/json\")\n\
+         local json = _G[\"json\"]\n\
+         require(\"./vendor/json\")\n\
+         local _json = _G[\"json\"]\n\
+         local encode = _json.encode\n"
+    );
+}
+
+#[test]
+fn test_optional_chain_inline() {
+    assert_eq!(
+        generate("const city = user?.address?.city"),
+        "local city = user and user.address and user.address.city\n"
+    );
+    assert_eq!(
+        generate("const n = #(user?.name)\nconst first = user?.names[1]"),
+        "local n = #(user and user.name)\nlocal first = (user and user.names)[1]\n"
+    );
+}
+
+#[test]
+fn test_optional_chain_uses_helper() {
+    let mut config = CompilerConfig::default();
+    config.compiler_options.nil_safe_helpers = true;
+    let lua = generate_with(
+        "const city = user?.address?.city\nconst name = user?.name",
+        config,
+    );
+    assert!(lua.starts_with("local function __safe_get(value, ...)\n"));
+    assert!(lua.ends_with(
+        "local city = __safe_get(user, \"address\", \"city\")\nlocal name = user and user.name\n"
+    ));
+
+    // Roots with side effects are evaluated once, even without the option
+    let lua = generate("const id = load()?.id");
+    assert!(lua.ends_with("local id = __safe_get(load(), \"id\")\n"));
+}
+
+#[test]
+fn test_namespace_emits_nested_tables() {
+    let source = r#"
+        export namespace Geometry {
+            function square(n: number): number
+                return n * n
+            end
+
+            export function area(n: number): number
+                return square(n)
+            end
+
+            export namespace Units {
+                export const meter = 1
+            }
+        }
+
+        namespace Geometry {
+            export const origin = 0
+        }
+    "#;
+    assert_eq!(
+        generate(source),
+        "local Geometry = {}\n\
+         do\n\
+        \x20 local function square(n)\n\
+        \x20   return n * n\n\
+        \x20 end\n\
+        \x20 local function area(n)\n\
+        \x20   return square(n)\n\
+        \x20 end\n\
+        \x20 Geometry.area = area\n\
+        \x20 local Units = {}\n\
+        \x20 do\n\
+        \x20   local meter = 1\n\
+        \x20   Units.meter = meter\n\
+        \x20 end\n\
+        \x20 Geometry.Units = Units\n\
+         end\n\
+         do\n\
+        \x20 local origin = 0\n\
+        \x20 Geometry.origin = origin\n\
+         end\n\
+         return {\n\
+        \x20 Geometry = Geometry,\n\
+         }\n"
+    );
+}
+
+#[test]
+fn test_string_enum_values() {
+    let lua = generate("enum Color { Red = \"red\", Green = \"green\", Count = 3, Extra }");
+    assert_eq!(
+        lua,
+        "local Color = {\n  Red = \"red\",\n  Green = \"green\",\n  Count = 3,\n  Extra = 4,\n}\n"
+    );
+}
+
+#[test]
+fn test_enum_helpers_expand_at_each_use() {
+    let source = r#"
+enum Color { Red, Green = 5, Blue }
+const enum Size { Small = "s", Large = "l" }
+const all = Color.values()
+const first = Color.values()[1]
+const green = Color.fromName(name)
+const before = Color.ordinal(a) < Color.ordinal(b)
+const sizes = Size.values
+"#;
+    assert_eq!(
+        generate(source),
+        "local Color = {\n  Red = 1,\n  Green = 5,\n  Blue = 6,\n}\n\
+         local all = { 1, 5, 6 }\n\
+         local first = ({ 1, 5, 6 })[1]\n\
+         local green = ({ Red = 1, Green = 5, Blue = 6 })[name]\n\
+         local before = ({ [1] = 1, [5] = 2, [6] = 3 })[a] < ({ [1] = 1, [5] = 2, [6] = 3 })[b]\n\
+         local sizes = function() return { \"s\", \"l\" } end\n"
+    );
+}
+
+#[test]
+fn test_const_enum_members_are_inlined() {
+    let source = r#"
+function paint(): string
+    return Color.Red
+end
+const enum Color { Red = "red", Green = 2 }
+const label = Color.Green .. "!"
+const upper = Color.Red::upper()
+"#;
+    assert_eq!(
+        generate(source),
+        "local function paint()\n  return \"red\"\nend\nlocal label = 2 .. \"!\"\nlocal upper = (\"red\"):upper()\n"
+    );
+}
+
+#[test]
+fn test_type_only_imports_are_erased() {
+    let source = "import type { Point } from \"./geometry\"\nconst p: Point = { x = 1, y = 2 }";
+    assert_eq!(generate(source), "local p = {x = 1, y = 2}\n");
+}
+
+#[test]
+fn test_explicit_self_parameter_is_the_receiver() {
+    let source = "class Vec2 {\n  x: number\n  __add(self, other: Vec2): number {\n    return self.x + other.x\n  }\n}";
+    let lua = generate(source);
+    assert!(lua.contains("function Vec2:__add(other)\n"), "{}", lua);
+}
+
+#[test]
+fn test_lazy_import_defers_require_to_first_use() {
+    let source =
+        "import lazy { render as draw } from \"./heavy\"\nfunction frame()\n  draw(1)\nend";
+    assert_eq!(
+        generate(source),
+        "local _heavy\n\
+         local function _load_heavy()\n\
+         \x20 if _heavy == nil then\n\
+         \x20   _heavy = require(\"./heavy\")\n\
+         \x20 end\n\
+         \x20 return _heavy\n\
+         end\n\
+         local function frame()\n\
+         \x20 _load_heavy().render(1)\n\
+         end\n"
+    );
+}
+
+#[test]
+fn test_lazy_imports_from_config_globs() {
+    let mut config = CompilerConfig::default();
+    config.compiler_options.lazy_imports = vec!["./assets/**".to_string()];
+    let source =
+        "import Atlas from \"./assets/atlas\"\nimport lazy from \"./lazy\"\nconst w = Atlas.width";
+    assert_eq!(
+        generate_with(source, config),
+        "local _atlas\n\
+         local function _load_atlas()\n\
+         \x20 if _atlas == nil then\n\
+         \x20   _atlas = require(\"./assets/atlas\")\n\
+         \x20 end\n\
+         \x20 return _atlas\n\
+         end\n\
+         local lazy = require(\"./lazy\")\n\
+         local w = _load_atlas().width\n"
+    );
+}
+
+#[test]
+fn test_mixins_are_copied_into_the_class() {
+    let source = "class Player extends mixin(Entity, Serializable) {\n  greet(): string {\n    return \"hi\"\n  }\n}";
+    let lua = generate(source);
+    assert!(
+        lua.starts_with("local function __mixin(target, ...)\n"),
+        "{}",
+        lua
+    );
+    assert!(
+        lua.contains(
+            "local Player = {}\n\
+             __mixin(Player, Entity, Serializable)\n\
+             Player.__index = Player\n\
+             \n\
+             function Player._init(self, ...)\n\
+             \x20 Entity._init(self, ...)\n\
+             \x20 Serializable._init(self, ...)\n\
+             end\n"
+        ),
+        "{}",
+        lua
+    );
+}
+
+#[test]
+fn test_freeze_readonly_wraps_readonly_values() {
+    let source = "function apply(config: Readonly<Config>)\n  return config\nend\nconst defaults: ReadonlyTable = {}\nconst open: Config = {}";
+    assert!(!generate(source).contains("__freeze"));
+
+    let mut config = CompilerConfig::default();
+    config.compiler_options.freeze_readonly = true;
+    let lua = generate_with(source, config);
+    assert!(
+        lua.starts_with("local function __freeze(value)\n"),
+        "{}",
+        lua
+    );
+    assert!(
+        lua.contains(
+            "local function apply(config)\n\
+             \x20 config = __freeze(config)\n\
+             \x20 return config\n\
+             end\n\
+             local defaults = __freeze({})\n\
+             local open = {}\n"
+        ),
+        "{}",
+        lua
+    );
+}
+
+#[test]
+fn test_closure_class_template_binds_methods_per_instance() {
+    let source = "class Dog extends Animal {\n  speak(times: number): string {\n    return super::speak(times)\n  }\n  static create(): Dog {\n    return Dog.new()\n  }\n}";
+    let mut config = CompilerConfig::default();
+    config.compiler_options.class_template = crate::config::ClassTemplate::Closure;
+    let lua = generate_with(source, config);
+    assert_eq!(
+        lua,
+        "local Dog = setmetatable({}, {__index = Animal})\n\
+         \n\
+         function Dog._bind(self)\n\
+         \x20 Animal._bind(self)\n\
+         \x20 local super = {}\n\
+         \x20 for key, value in pairs(self) do\n\
+         \x20   super[key] = value\n\
+         \x20 end\n\
+         \x20 function self.speak(_, times)\n\
+         \x20   return super.speak(self, times)\n\
+         \x20 end\n\
+         end\n\
+         \n\
+         function Dog._init(self, ...)\n\
+         \x20 Animal._init(self, ...)\n\
+         end\n\
+         \n\
+         function Dog.new(...)\n\
+         \x20 local self = {}\n\
+         \x20 Dog._bind(self)\n\
+         \x20 Dog._init(self, ...)\n\
+         \x20 return self\n\
+         end\n\
+         \n\
+         function Dog.create()\n\
+         \x20 return Dog.new()\n\
+         end\n"
+    );
+}
+
+#[test]
+fn test_protected_enum_template() {
+    let source = "enum Color {\n  Red,\n  Green\n}";
+    let mut config = CompilerConfig::default();
+    config.compiler_options.enum_template = crate::config::EnumTemplate::Protected;
+    let lua = generate_with(source, config);
+    assert!(
+        lua.starts_with("local function __freeze(value)\n"),
+        "{}",
+        lua
+    );
+    assert!(
+        lua.ends_with("local Color = __freeze({\n  Red = 1,\n  Green = 2,\n})\n"),
+        "{}",
+        lua
+    );
+}
+
+#[test]
+fn test_pipeline_stages_nest_left_to_right() {
+    let lua = generate("const saved = input |> parse |> save(3)");
+    assert_eq!(lua, "local saved = save(parse(input), 3)\n");
+}
+
+#[test]
+fn test_number_literals_keep_their_subtype() {
+    assert_eq!(
+        generate("local a = 2.0\nlocal b = 0xFF\nlocal c = 1.5\nlocal d = 10"),
+        "local a = 2.0\nlocal b = 255\nlocal c = 1.5\nlocal d = 10\n"
+    );
+}
+
+#[test]
+fn test_numeric_literal_spellings_are_normalized_per_target() {
+    let source = "local a = 1_000_000\nlocal b = 0b1010_0101\nlocal c = 42LL\nlocal d = 0xFFFFFFFFFFFFFFFFULL";
+    assert_eq!(
+        generate_for(source, LuaVersion::LuaJIT),
+        "local a = 1000000\nlocal b = 165\nlocal c = 42LL\nlocal d = 18446744073709551615ULL\n"
+    );
+    assert_eq!(
+        generate_for(source, LuaVersion::Lua54),
+        "local a = 1000000\nlocal b = 165\nlocal c = 42\nlocal d = -1\n"
+    );
+}
+
+#[test]
+fn test_radix_integers_past_64_bits_wrap_only_with_an_integer_subtype() {
+    let source = "local a = 0x7FFFFFFFFFFFFFFF\nlocal b = 0xFFFFFFFFFFFFFFFF\nlocal c = 0x1FFFFFFFFFFFFFFFF\nlocal d = 0xFFFFFFFFFFFFFFFFULL";
+    assert_eq!(
+        generate_for(source, LuaVersion::Lua54),
+        "local a = 9223372036854775807\nlocal b = -1\nlocal c = -1\nlocal d = -1\n"
+    );
+    assert_eq!(
+        generate_for(source, LuaVersion::Lua51),
+        "local a = 9223372036854775807\nlocal b = 18446744073709552000.0\nlocal c = 36893488147419103000.0\nlocal d = 18446744073709552000.0\n"
+    );
+}
+
+#[test]
+fn test_labeled_loops_lower_to_goto_or_flags() {
+    let source = r#"
+outer: for i = 1, 3 do
+    for j = 1, 3 do
+        if j == i then
+            continue outer
+        end
+        if j > 2 then
+            break outer
+        end
+    end
+    print(i)
+end
+"#;
+    assert_eq!(
+        generate_for(source, LuaVersion::Lua54),
+        "do\n  for i = 1, 3 do\n    for j = 1, 3 do\n      if j == i then\n        goto outer_continue\n      end\n      if j > 2 then\n        goto outer_break\n      end\n    end\n    print(i)\n    ::outer_continue::\n  end\n  ::outer_break::\nend\n"
+    );
+    assert_eq!(
+        generate_for(source, LuaVersion::Lua51),
+        "local outer_break = false\nfor i = 1, 3 do\n  local outer_continue = false\n  repeat\n    for j = 1, 3 do\n      if j == i then\n        outer_continue = true\n        break\n      end\n      if j > 2 then\n        outer_break = true\n        break\n      end\n    end\n    if outer_continue then break end\n    if outer_break then break end\n    print(i)\n  until true\n  if outer_break then break end\nend\n"
+    );
+    assert_eq!(
+        generate_for(source, LuaVersion::Luau),
+        "--!strict\nlocal outer_break = false\nfor i = 1, 3 do\n  local outer_continue = false\n  for j = 1, 3 do\n    if j == i then\n      outer_continue = true\n      break\n    end\n    if j > 2 then\n      outer_break = true\n      break\n    end\n  end\n  if outer_continue then continue end\n  if outer_break then break end\n  print(i)\nend\n"
+    );
+}
+
+#[test]
+fn test_breaks_leave_loops_wrapped_for_continue() {
+    let source = r#"
+for i = 1, 3 do
+    if i == 1 then
+        continue
+    end
+    if i == 2 then
+        break
+    end
+    print(i)
+end
+print("done")
+"#;
+    let lua = generate_for(source, LuaVersion::Lua51);
+    assert_eq!(
+        lua,
+        "for i = 1, 3 do\n  local break_ = false\n  repeat\n    if i == 1 then\n      break\n    end\n    if i == 2 then\n      break_ = true\n      break\n    end\n    print(i)\n  until true\n  if break_ then break end\nend\nprint(\"done\")\n"
+    );
+    if let Some(output) = run_lua(&lua, &["lua5.1", "luajit"]) {
+        assert_eq!(output, "done\n");
+    }
+
+    let source = r#"
+outer: for i = 1, 3 do
+    for j = 1, 3 do
+        if j == 1 then
+            continue
+        end
+        print(i, j)
+        if i == 1 then
+            continue outer
+        end
+        if i == 2 then
+            break outer
+        end
+    end
+    print(i)
+end
+print("done")
+"#;
+    let lua = generate_for(source, LuaVersion::Lua51);
+    assert_eq!(
+        lua,
+        "local outer_break = false\nfor i = 1, 3 do\n  local outer_continue = false\n  repeat\n    for j = 1, 3 do\n      repeat\n        if j == 1 then\n          break\n        end\n        print(i, j)\n        if i == 1 then\n          outer_continue = true\n          break\n        end\n        if i == 2 then\n          outer_break = true\n          break\n        end\n      until true\n      if outer_continue then break end\n      if outer_break then break end\n    end\n    if outer_continue then break end\n    if outer_break then break end\n    print(i)\n  until true\n  if outer_break then break end\nend\nprint(\"done\")\n"
+    );
+    if let Some(output) = run_lua(&lua, &["lua5.1", "luajit"]) {
+        assert_eq!(output, "1\t2\n2\t2\ndone\n");
+    }
+}
+
+#[test]
+fn test_const_expressions_are_folded_and_inlined() {
+    let source = r#"
+const SECONDS = 60 * 60 * 24
+const OFFSET = -SECONDS
+const NAME = "v" .. 2
+function shift(time: number): number
+    return time - OFFSET
+end
+function days(SECONDS: number): number
+    return SECONDS / 86400
+end
+const label = NAME::upper()
+const timeout = SECONDS * clock()
+"#;
+    assert_eq!(
+        generate(source),
+        "local SECONDS = 86400\nlocal OFFSET = -86400\nlocal NAME = \"v2\"\nlocal function shift(time)\n  return time - (-86400)\nend\nlocal function days(SECONDS)\n  return SECONDS / 86400\nend\nlocal label = (\"v2\"):upper()\nlocal timeout = 86400 * clock()\n"
+    );
+
+    let mut config = CompilerConfig::default();
+    config.compiler_options.inline_constants = false;
+    assert_eq!(
+        generate_with("const SECONDS = 60 * 60\nconst later = SECONDS + 1", config),
+        "local SECONDS = 60 * 60\nlocal later = SECONDS + 1\n"
+    );
+}
+
+#[test]
+fn test_macros_expand_hygienically_with_source_text() {
+    let source = r#"
+macro assertEq(actual, expected)
+    local value = actual
+    if value ~= expected then
+        error(`${stringify(actual)} ~= ${stringify(expected)} on line ${lineof(actual)}`)
+    end
+end
+local value = 2
+assertEq(value * 2, t.size)
+assertEq(value, 2)
+"#;
+    assert_eq!(
+        generate(source),
+        "local value = 2\ndo\n  local value__1 = value * 2\n  if value__1 ~= (t.size) then\n    error(tostring(\"value * 2\") .. \" ~= \" .. tostring(\"t.size\") .. \" on line \" .. tostring(9))\n  end\nend\ndo\n  local value__2 = value\n  if value__2 ~= 2 then\n    error(tostring(\"value\") .. \" ~= \" .. tostring(\"2\") .. \" on line \" .. tostring(10))\n  end\nend\n"
+    );
+}
+
+#[test]
+fn test_macro_arguments_are_assigned_and_evaluated_once() {
+    let source = r#"
+macro swap(a, b)
+    local tmp = a
+    a = b
+    b = tmp
+end
+macro twice(value)
+    print(value + value, value.n)
+end
+local tmp = 1
+local other = 2
+swap(tmp, other)
+print(tmp, other)
+local calls = 0
+function next(): { n: number }
+    calls = calls + 1
+    return { n = calls }
+end
+twice(next().n)
+twice(calls * 2)
+print(calls)
+"#;
+    let lua = generate(source);
+    assert!(
+        lua.contains("do\n  local tmp__1 = tmp\n  tmp = other\n  other = tmp__1\nend\n"),
+        "{}",
+        lua
+    );
+    assert!(
+        lua.contains(
+            "do\n  local value__2 = next().n\n  print(value__2 + value__2, value__2.n)\nend\n"
+        ),
+        "{}",
+        lua
+    );
+    assert!(
+        lua.contains("do\n  local value__3 = calls * 2\n"),
+        "{}",
+        lua
+    );
+    if let Some(output) = run_lua(&lua, &["lua5.4", "lua"]) {
+        assert_eq!(output, "2\t1\n2\tnil\n4\tnil\n1\n");
+    }
+
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let source = "macro swap(a, b)\n    local tmp = a\n    a = b\n    b = tmp\nend\n\
+                  swap(f(), other)\nlocal r = swap(x, y)\n";
+    let tokens = Lexer::new(source, handler.clone()).tokenize().unwrap();
+    let _ = Parser::new(tokens, handler.clone()).parse();
+    let messages: Vec<String> = handler
+        .get_diagnostics()
+        .into_iter()
+        .map(|diagnostic| diagnostic.message)
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Macro 'swap' assigns to 'a', so its argument must be a name",
+            "Macro 'swap' is used as an expression, but expands to statements",
+        ]
+    );
+}
+
+#[test]
+fn test_untyped_block_is_a_plain_block() {
+    assert_eq!(
+        generate("untyped do\n    local x: string = 1\nend"),
+        "do\n  local x = 1\nend\n"
+    );
+}
+
+#[test]
+fn test_weak_tables_and_classes_set_mode() {
+    let source =
+        "@weak(\"k\")\nlocal cache: { [key: string]: Node } = {}\n@weak(\"v\")\nclass Handle {\n}";
+    let lua = generate(source);
+    assert!(
+        lua.starts_with("local cache = setmetatable({}, { __mode = \"k\" })\n"),
+        "{}",
+        lua
+    );
+    assert!(
+        lua.contains("Handle.__index = Handle\nHandle.__mode = \"v\"\n"),
+        "{}",
+        lua
+    );
+
+    let mut config = CompilerConfig::default();
+    config.compiler_options.class_template = crate::config::ClassTemplate::Closure;
+    let lua = generate_with("@weak(\"kv\")\nclass Handle {\n}", config);
+    assert!(
+        lua.contains("local self = setmetatable({}, { __mode = \"kv\" })\n"),
+        "{}",
+        lua
+    );
+}
+
+#[test]
+fn test_inline_and_no_emit_attributes() {
+    let source = r#"@no_emit
+function trace(message: string)
+end
+@inline
+function square(x: number): number
+  return x * x
+end
+local a = square(3)
+local b = square(a) + 1
+local c = square(a + 1)
+square(2)"#;
+    let lua = generate(source);
+    assert!(!lua.contains("trace"), "{}", lua);
+    assert!(lua.contains("local function square(x)\n"), "{}", lua);
+    assert!(lua.contains("local a = (3 * 3)\n"), "{}", lua);
+    assert!(lua.contains("local b = (a * a) + 1\n"), "{}", lua);
+    assert!(lua.contains("local c = square(a + 1)\n"), "{}", lua);
+    assert!(lua.contains("local _ = (2 * 2)\n"), "{}", lua);
+}
+
+#[test]
+fn test_template_format_specifiers_use_string_format() {
+    let lua = generate("const line = `${name}: ${score:%.2f} (100%)`");
+    assert!(
+        lua.contains("local line = string.format(\"%s: %.2f (100%%)\", tostring(name), score)\n"),
+        "{}",
+        lua
+    );
+    let lua = generate("const line = `${name}!`");
+    assert!(
+        lua.contains("local line = tostring(name) .. \"!\"\n"),
+        "{}",
+        lua
+    );
+}
+
+#[test]
+fn test_multiple_values_capture_the_rest() {
+    let lua = generate("local ok, err = pcall(f)");
+    assert!(lua.contains("local ok, err = pcall(f)\n"), "{}", lua);
+    let lua = generate("local ok, ... = pcall(f)");
+    assert!(lua.contains("local ok = pcall(f)\n"), "{}", lua);
+    let lua = generate("local ...results = pcall(f)");
+    assert!(lua.contains("local results = { pcall(f) }\n"), "{}", lua);
+    let lua = generate("local ok, ...results = pcall(f)");
+    assert!(
+        lua.contains("local ok, results = (function(ok, ...) return ok, { ... } end)(pcall(f))\n"),
+        "{}",
+        lua
+    );
+}
+
+#[test]
+fn test_pool_literals_hoists_repeated_constants() {
+    let mut config = CompilerConfig::default();
+    config.compiler_options.pool_literals = true;
+    let source = r#"
+        say("Welcome back, traveller")
+        say("Welcome back, traveller")
+        say("short", "short")
+        const a = { mood = "calm", lines = [1, 2] }
+        const b = { mood = "calm", lines = [1, 2] }
+        const c = [1, 2]
+    "#;
+    let lua = generate_with(source, config);
+    assert!(
+        lua.starts_with(
+            "local __pool1 = \"Welcome back, traveller\"\nlocal __pool2 = {1, 2}\nlocal __pool3 = {mood = \"calm\", lines = __pool2}\n"
+        ),
+        "{}",
+        lua
+    );
+    assert!(lua.contains("say(__pool1)\nsay(__pool1)\n"), "{}", lua);
+    assert!(lua.contains("say(\"short\", \"short\")\n"), "{}", lua);
+    assert!(
+        lua.contains("local a = __pool3\nlocal b = __pool3\nlocal c = __pool2\n"),
+        "{}",
+        lua
+    );
+
+    let lua = generate(source);
+    assert!(!lua.contains("__pool"), "{}", lua);
+}
+
+#[test]
+fn test_optimization_level_specializes_helpers() {
+    let mut config = CompilerConfig::default();
+    config.compiler_options.optimization_level = 2;
+    let source = "const id = load()?.id\nconst again = load()?.id\nconst city = find()?.address.city\nclass Player extends mixin(Entity, Serializable) {}";
+    let lua = generate_with(source, config);
+    assert!(
+        lua.starts_with(
+            "local function __safe_get1(value)\n\
+             \x20 if value == nil then\n\
+             \x20   return nil\n\
+             \x20 end\n\
+             \x20 return value.id\n\
+             end\n\
+             local function __safe_get2(value)\n\
+             \x20 if value == nil then\n\
+             \x20   return nil\n\
+             \x20 end\n\
+             \x20 value = value.address\n\
+             \x20 if value == nil then\n\
+             \x20   return nil\n\
+             \x20 end\n\
+             \x20 return value.city\n\
+             end\n"
+        ),
+        "{}",
+        lua
+    );
+    assert!(
+        lua.contains(
+            "local id = __safe_get1(load())\nlocal again = __safe_get1(load())\nlocal city = __safe_get2(find())\n"
+        ),
+        "{}",
+        lua
+    );
+    assert!(
+        lua.contains(
+            "for key, value in pairs(Entity) do if rawget(Player, key) == nil then Player[key] = value end end\n"
+        ),
+        "{}",
+        lua
+    );
+    assert!(!lua.contains("__mixin"), "{}", lua);
+}
+
+#[test]
+fn test_schema_records_generate_versioned_serializers() {
+    let source = r#"@migration(SaveData, 1)
+function addGold(data: table): table
+  return data
+end
+@schema(version = 3)
+export interface SaveData {
+  name: string
+  gold: number
+}
+@migration(SaveData, 2)
+function renameHero(data: table): table
+  return data
+end"#;
+    let lua = generate(source);
+    assert!(
+        lua.contains(
+            "local SaveData = { version = 3, migrations = {} }\nfunction SaveData.serialize(value)\n  return { version = 3, data = { name = value.name, gold = value.gold } }\nend\n"
+        ),
+        "{}",
+        lua
+    );
+    assert!(
+        lua.contains("  for version = saved.version, 2 do\n    data = SaveData.migrations[version](data)\n  end\n"),
+        "{}",
+        lua
+    );
+    assert!(
+        lua.contains("end\nSaveData.migrations[1] = addGold\n"),
+        "{}",
+        lua
+    );
+    assert!(
+        lua.contains("end\nSaveData.migrations[2] = renameHero\n"),
+        "{}",
+        lua
+    );
+    assert!(lua.contains("SaveData = SaveData"), "{}", lua);
+}
+
+#[test]
+fn test_using_lowering_per_target() {
+    let source = r#"function firstLine(path: string): string
+  using file = open(path)
+  const line = file.read()
+  if line == nil then
+    return ""
+  end
+  return line
+end"#;
+    let lua = generate_for(source, LuaVersion::Lua54);
+    assert!(
+        lua.contains("  local file <close> = open(path)\n"),
+        "{}",
+        lua
+    );
+    assert!(!lua.contains("__using"), "{}", lua);
+
+    let lua = generate_for(source, LuaVersion::Lua51);
+    assert!(
+        lua.contains("local function __using(resource, body)"),
+        "{}",
+        lua
+    );
+    assert!(
+        lua.contains(
+            "  local file = open(path)\n  local file_result = __using(file, function()\n    local line = file.read()\n    if line == nil then\n      return true, \"\"\n    end\n    return true, line\n  end)\n  if file_result then return unpack(file_result, 1, file_result.n) end\n"
+        ),
+        "{}",
+        lua
+    );
+}
+
+#[test]
+fn test_checked_functions_check_integer_overflow() {
+    let source = r#"@checked
+function step(position: integer, velocity: integer): integer
+  local next = position + velocity * 2
+  next = next - 1
+  return next % 2
+end
+function free(a: integer): integer
+  return a + 1
+end"#;
+    assert!(!generate(source).contains("__checked_arith"));
+
+    let mut config = CompilerConfig::default();
+    config.compiler_options.integer_overflow = IntegerOverflow::Checked;
+    let lua = generate_with(source, config);
+    assert!(
+        lua.starts_with("local function __checked_arith(operator, a, b)\n"),
+        "{}",
+        lua
+    );
+    assert!(
+        lua.contains(
+            "  local next = __checked_arith(\"+\", position, __checked_arith(\"*\", velocity, 2))\n  next = __checked_arith(\"-\", next, 1)\n  return next % 2\n"
+        ),
+        "{}",
+        lua
+    );
+    assert!(lua.contains("  return a + 1\n"), "{}", lua);
+}
+
+#[test]
+fn test_operators_lower_to_the_target() {
+    let source = "const mask = (flags & 255) << 2\nconst inverted = ~mask";
+
+    let lua = generate_for(source, LuaVersion::Lua53);
+    assert!(lua.contains("local mask = (flags & 255) << 2\n"), "{}", lua);
+    assert!(lua.contains("local inverted = ~mask\n"), "{}", lua);
+
+    let lua = generate_for(source, LuaVersion::Lua52);
+    assert!(
+        lua.contains("local mask = bit32.lshift((bit32.band(flags, 255)), 2)\n"),
+        "{}",
+        lua
+    );
+    assert!(
+        lua.contains("local inverted = bit32.bnot(mask)\n"),
+        "{}",
+        lua
+    );
+
+    let lua = generate_for(source, LuaVersion::LuaJIT);
+    assert!(
+        lua.contains("local mask = bit.lshift((bit.band(flags, 255)), 2)\n"),
+        "{}",
+        lua
+    );
+}
+
+#[test]
+fn test_luau_target_keeps_type_annotations() {
+    let source = r#"export interface Point {
+  x: number
+  y: number
+  label?: string
+}
+type Pair<T> = [T, T]
+type Handler = (event: string, ...args: number[]) -> boolean
+function distance(a: Point, b: Point): number
+  const dx: number = a.x - b.x
+  return dx
+end
+function first<T>(items: T[], fallback: T = nil): (T, integer)
+  return items[1], 1
+end
+const log = (message: string): void => print(message)
+class Player {}
+local owner: Player | nil = nil"#;
+    let lua = generate_for(source, LuaVersion::Luau);
+    assert!(
+        lua.starts_with(
+            "--!strict\nexport type Point = { x: number, y: number, label: string? }\ntype Pair<T> = any\ntype Handler = (string, ...number) -> boolean\nlocal function distance(a: Point, b: Point): number\n  local dx: number = a.x - b.x\n"
+        ),
+        "{}",
+        lua
+    );
+    assert!(
+        lua.contains("local function first<T>(items: { T }, fallback: T?): (T, number)\n"),
+        "{}",
+        lua
+    );
+    assert!(
+        lua.contains("local log = function(message: string): () return print(message) end\n"),
+        "{}",
+        lua
+    );
+    assert!(lua.contains("local owner: any | nil = nil\n"), "{}", lua);
+
+    let mut config = CompilerConfig::default();
+    config.compiler_options.target = LuaVersion::Luau;
+    config.compiler_options.luau_types = false;
+    let lua = generate_with(source, config);
+    assert!(
+        lua.starts_with("local function distance(a, b)\n"),
+        "{}",
+        lua
+    );
+}
+
+#[test]
+fn test_random_is_emitted_when_used() {
+    let source = "const rng = Random.new(42)\nconst roll = rng::nextInt(1, 6)";
+    let lua = generate(source);
+    assert!(
+        lua.starts_with("local Random = {}\nRandom.__index = Random\ndo\n"),
+        "{}",
+        lua
+    );
+    assert!(lua.contains("  local bxor = function(a, b)\n    return a ~ b\n  end\n"));
+    assert!(lua.ends_with("end\nlocal rng = Random.new(42)\nlocal roll = rng:nextInt(1, 6)\n"));
+
+    let lua = generate_for(source, LuaVersion::Lua52);
+    assert!(lua.contains("  local bxor = bit32.bxor\n"), "{}", lua);
+    let lua = generate_for(source, LuaVersion::LuaJIT);
+    assert!(
+        lua.contains("return bit.bxor(a, b) % 4294967296\n"),
+        "{}",
+        lua
+    );
+    let lua = generate_for(source, LuaVersion::Lua51);
+    assert!(lua.contains("if a % 2 ~= b % 2 then\n"), "{}", lua);
+
+    // Modules that don't use it, or declare their own, don't get it
+    assert!(!generate("const x = 1").contains("Random"));
+    let lua = generate("class Random {}\nconst rng = Random.new()");
+    assert!(!lua.contains("Random.__index = Random\ndo\n"), "{}", lua);
+}
@@ -0,0 +1,98 @@
+use super::helpers::RuntimeHelper;
+use super::CodeGenerator;
+use crate::ast::pattern::Pattern;
+use crate::ast::statement::*;
+use crate::features::LuaFeature;
+
+impl CodeGenerator {
+    /// Lua 5.4 closes `using` values itself, as `<close>` variables
+    pub(super) fn has_close_attribute(&self) -> bool {
+        self.target().supports(LuaFeature::LocalAttributes)
+    }
+
+    /// The statements of a block. On targets without `<close>`, those after
+    /// a `using` run in a closure passed to `__using`, which closes the
+    /// value however the closure exits.
+    pub(super) fn generate_statements(&mut self, statements: &[Statement]) {
+        for (i, statement) in statements.iter().enumerate() {
+            self.generate_statement(statement);
+            if let Some(name) = self.lowered_using(statement) {
+                self.generate_using_scope(name, &statements[i + 1..]);
+                return;
+            }
+        }
+    }
+
+    /// `return values`, which inside a `using` closure first reports that
+    /// the closure returned rather than finished its block
+    pub(super) fn return_statement(&self, mut values: Vec<String>) -> String {
+        if self.using_scopes > 0 {
+            values.insert(0, "true".to_string());
+        }
+        if values.is_empty() {
+            "return".to_string()
+        } else {
+            format!("return {}", values.join(", "))
+        }
+    }
+
+    fn lowered_using<'a>(&self, statement: &'a Statement) -> Option<&'a str> {
+        match statement {
+            Statement::Variable(VariableDeclaration {
+                kind: VariableKind::Using,
+                pattern: Pattern::Identifier(name),
+                ..
+            }) if !self.has_close_attribute() => Some(&name.node),
+            _ => None,
+        }
+    }
+
+    fn generate_using_scope(&mut self, name: &str, rest: &[Statement]) {
+        self.use_helper(RuntimeHelper::Using);
+        let call = format!("{}({}, function()", RuntimeHelper::Using.name(), name);
+        let result = format!("{}_result", name);
+        let returns = contains_return(rest);
+        if returns {
+            self.write_line(&format!("local {} = {}", result, call));
+        } else {
+            self.write_line(&call);
+        }
+        self.indent();
+        self.using_scopes += 1;
+        self.generate_statements(rest);
+        self.using_scopes -= 1;
+        self.dedent();
+        self.write_line("end)");
+        if returns {
+            let values = format!("{}({}, 1, {}.n)", self.unpack_function(), result, result);
+            let line = self.return_statement(vec![values]);
+            self.write_line(&format!("if {} then {} end", result, line));
+        }
+    }
+}
+
+/// Whether a `return` among the statements returns from the enclosing
+/// function, rather than from a function nested in them
+fn contains_return(statements: &[Statement]) -> bool {
+    statements.iter().any(|statement| match statement {
+        Statement::Return(_) => true,
+        Statement::If(if_stmt) => {
+            contains_return(&if_stmt.then_block.statements)
+                || if_stmt
+                    .else_ifs
+                    .iter()
+                    .any(|e| contains_return(&e.block.statements))
+                || if_stmt
+                    .else_block
+                    .as_ref()
+                    .is_some_and(|b| contains_return(&b.statements))
+        }
+        Statement::Block(block) | Statement::Untyped(block) => contains_return(&block.statements),
+        Statement::While(while_stmt) => contains_return(&while_stmt.body.statements),
+        Statement::For(ForStatement::Numeric(numeric)) => contains_return(&numeric.body.statements),
+        Statement::For(ForStatement::Generic(generic)) => contains_return(&generic.body.statements),
+        Statement::Repeat(repeat) => contains_return(&repeat.body.statements),
+        Statement::Labeled(labeled) => contains_return(std::slice::from_ref(&labeled.body)),
+        _ => false,
+    })
+}
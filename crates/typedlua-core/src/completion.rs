@@ -0,0 +1,116 @@
+//! Completions for editors. Inside the path string of an import,
+//! `import { User } from "./mo|"`, the modules the resolver would find are
+//! offered.
+
+use crate::resolve::ModuleResolver;
+use std::path::Path;
+
+/// What a completion inserts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    Module,
+    Directory,
+}
+
+/// A candidate for the text being typed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    /// The text replacing the last path segment typed; directories end
+    /// with `/` so completion can continue into them
+    pub label: String,
+    pub kind: CompletionKind,
+}
+
+/// Complete at `offset`, a byte offset into `source`, the module `importer`.
+/// `None` outside an import path, where names are the caller's to offer.
+pub fn complete(
+    source: &str,
+    offset: usize,
+    importer: &Path,
+    resolver: &ModuleResolver,
+) -> Option<Vec<Completion>> {
+    let typed = source.get(..offset).and_then(import_path_prefix)?;
+    let (directory, partial) = match typed.rfind('/') {
+        Some(slash) => typed.split_at(slash + 1),
+        None => ("", typed),
+    };
+
+    let completions = resolver
+        .entries(importer, directory)
+        .into_iter()
+        .filter(|entry| entry.name.starts_with(partial))
+        .map(|entry| match entry.is_directory {
+            true => Completion {
+                label: format!("{}/", entry.name),
+                kind: CompletionKind::Directory,
+            },
+            false => Completion {
+                label: entry.name,
+                kind: CompletionKind::Module,
+            },
+        })
+        .collect();
+    Some(completions)
+}
+
+/// The part of an import path typed so far, when `before` ends inside the
+/// string after `from`. The string may not be closed yet, so this reads
+/// the text rather than tokens.
+fn import_path_prefix(before: &str) -> Option<&str> {
+    let line = before.rsplit('\n').next().unwrap_or_default();
+    let quote = line.rfind(['"', '\''])?;
+    let keyword = line[..quote].trim_end();
+    let is_from = keyword.strip_suffix("from").is_some_and(|rest| {
+        !rest
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_')
+    });
+    is_from.then_some(&line[quote + 1..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::MockFileSystem;
+    use std::sync::Arc;
+
+    fn labels(source: &str, resolver: &ModuleResolver) -> Vec<String> {
+        let offset = source.find('|').expect("No cursor in source");
+        let source = source.replace('|', "");
+        complete(&source, offset, Path::new("src/main.tl"), resolver)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|completion| completion.label)
+            .collect()
+    }
+
+    #[test]
+    fn test_completes_modules_inside_import_paths() {
+        let mut file_system = MockFileSystem::new();
+        file_system.add_file("src/main.tl", "");
+        file_system.add_file("src/user.tl", "");
+        file_system.add_file("src/user.d.tl", "");
+        file_system.add_file("src/utils.lua", "");
+        file_system.add_file("src/levels.data.tl", "");
+        file_system.add_file("src/README.md", "");
+        file_system.add_file("src/models/account.tl", "");
+        file_system.add_file("src/models/session.d.tl", "");
+        let resolver = ModuleResolver::new(Arc::new(file_system));
+
+        assert_eq!(
+            labels("import { User } from \"./|\"", &resolver),
+            vec!["levels", "models/", "user", "utils"]
+        );
+        assert_eq!(
+            labels("import { User } from './u|", &resolver),
+            vec!["user", "utils"]
+        );
+        assert_eq!(
+            labels("export { Account } from \"./models/|\"", &resolver),
+            vec!["account", "session"]
+        );
+        assert!(labels("local path = \"./|\"", &resolver).is_empty());
+        assert!(labels("import { User } from \"./user\" -- |", &resolver).is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -12,6 +13,50 @@ pub enum LuaVersion {
     #[serde(rename = "5.4")]
     #[default]
     Lua54,
+    #[serde(rename = "luajit")]
+    LuaJIT,
+    #[serde(rename = "luau")]
+    Luau,
+}
+
+impl LuaVersion {
+    /// Every target, oldest first
+    pub const ALL: [LuaVersion; 6] = [
+        LuaVersion::Lua51,
+        LuaVersion::Lua52,
+        LuaVersion::Lua53,
+        LuaVersion::Lua54,
+        LuaVersion::LuaJIT,
+        LuaVersion::Luau,
+    ];
+
+    /// Parse a target name as written in `@target(...)` guards.
+    /// Accepts both `lua51` and `5.1` spellings.
+    pub fn from_target_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "lua51" | "5.1" => Some(LuaVersion::Lua51),
+            "lua52" | "5.2" => Some(LuaVersion::Lua52),
+            "lua53" | "5.3" => Some(LuaVersion::Lua53),
+            "lua54" | "5.4" => Some(LuaVersion::Lua54),
+            "luajit" => Some(LuaVersion::LuaJIT),
+            "luau" => Some(LuaVersion::Luau),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for LuaVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            LuaVersion::Lua51 => "Lua 5.1",
+            LuaVersion::Lua52 => "Lua 5.2",
+            LuaVersion::Lua53 => "Lua 5.3",
+            LuaVersion::Lua54 => "Lua 5.4",
+            LuaVersion::LuaJIT => "LuaJIT",
+            LuaVersion::Luau => "Luau",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -25,6 +70,63 @@ pub enum StrictLevel {
     Error,
 }
 
+/// How a compiled module hands its exports to the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ModuleStyle {
+    /// `return { ... }` from the chunk, for use with `require`
+    #[serde(rename = "return")]
+    #[default]
+    Return,
+    /// Lua 5.1 `module(..., package.seeall)` with exports as module fields
+    #[serde(rename = "module")]
+    Module,
+    /// Register the module table as a global named after the module
+    #[serde(rename = "global")]
+    Global,
+}
+
+/// How classes are lowered to Lua
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ClassTemplate {
+    /// Methods live on the class table and instances reach them through
+    /// `__index`
+    #[serde(rename = "metatable")]
+    #[default]
+    Metatable,
+    /// Each instance is a plain table holding its methods as closures,
+    /// trading memory for metatable-free lookups
+    #[serde(rename = "closure")]
+    Closure,
+}
+
+/// How enums are lowered to Lua
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum EnumTemplate {
+    /// A plain table of members
+    #[serde(rename = "table")]
+    #[default]
+    Table,
+    /// A proxy that raises when a member is assigned at runtime
+    #[serde(rename = "protected")]
+    Protected,
+}
+
+/// What integer arithmetic does when it leaves the exact integer range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum IntegerOverflow {
+    /// The target's own semantics: integers wrap around past 2^63 on Lua
+    /// 5.3 and 5.4, and lose precision past 2^53 on targets where every
+    /// number is a float
+    #[serde(rename = "native")]
+    #[default]
+    Native,
+    /// Raise an error when `+`, `-` or `*` in a `@checked` function
+    /// overflows, for debug builds of simulations that must compute the
+    /// same results on every machine
+    #[serde(rename = "checked")]
+    Checked,
+}
+
 /// Compiler options that control type checking and code generation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -33,6 +135,13 @@ pub struct CompilerOptions {
     #[serde(default = "default_true")]
     pub strict_null_checks: bool,
 
+    /// Reject `nil` wherever a type does not include it, including reading
+    /// members of, indexing, calling or operating on possibly-nil values,
+    /// and passing or returning them where nil is not declared. Implies
+    /// `strictNullChecks` (default: false)
+    #[serde(default)]
+    pub strict_nil: bool,
+
     /// Naming convention enforcement (default: error)
     #[serde(default)]
     pub strict_naming: StrictLevel,
@@ -45,10 +154,43 @@ pub struct CompilerOptions {
     #[serde(default)]
     pub no_explicit_unknown: bool,
 
+    /// Require object literals annotated with an interface to provide every
+    /// required property; `Partial<T>` opts out (default: false)
+    #[serde(default)]
+    pub strict_initialization: bool,
+
+    /// Reject calls passing more or fewer arguments than the function
+    /// takes. Turn off for code relying on Lua dropping extra arguments and
+    /// filling missing ones with nil (default: true)
+    #[serde(default = "default_true")]
+    pub strict_arity: bool,
+
+    /// Require `override` on every method that replaces a base class or
+    /// interface method (default: false)
+    #[serde(default)]
+    pub no_implicit_override: bool,
+
+    /// Warn where a value read from one of the `taintSources` can reach
+    /// `load`, `os.execute`, `io.popen` or another function running it as
+    /// code or as a shell command (default: false)
+    #[serde(default)]
+    pub taint_analysis: bool,
+
+    /// Calls producing untrusted values for `taintAnalysis`: function paths
+    /// such as `io.read`, or `::name` for any method call named `name`
+    /// (default: io.read, os.getenv, ::read, ::receive)
+    #[serde(default = "default_taint_sources")]
+    pub taint_sources: Vec<String>,
+
     /// Target Lua version (default: 5.4)
     #[serde(default)]
     pub target: LuaVersion,
 
+    /// Host platform the output runs on, such as `roblox` or `sandbox`.
+    /// Declarations marked `@unavailableOn(...)` for it are rejected.
+    #[serde(default)]
+    pub platform: Option<String>,
+
     /// Enable OOP features (default: true)
     #[serde(default = "default_true")]
     pub enable_oop: bool,
@@ -73,10 +215,21 @@ pub struct CompilerOptions {
     #[serde(default)]
     pub out_file: Option<String>,
 
-    /// Generate source maps (default: false)
+    /// Write a Source Map v3 file (`.lua.map`) next to each compiled module,
+    /// mapping its lines back to the TypedLua source (default: false)
     #[serde(default)]
     pub source_map: bool,
 
+    /// Write a declaration file (`.d.tl`) of the exported types and
+    /// signatures next to each compiled module (default: false)
+    #[serde(default)]
+    pub emit_declarations: bool,
+
+    /// Directory of the build cache, which lets `typedlua build` skip
+    /// modules unchanged since the last build (default: .typedlua-cache)
+    #[serde(default = "default_cache_dir")]
+    pub cache_dir: String,
+
     /// Don't emit output files (type check only, default: false)
     #[serde(default)]
     pub no_emit: bool,
@@ -84,20 +237,127 @@ pub struct CompilerOptions {
     /// Pretty-print diagnostics (default: true)
     #[serde(default = "default_true")]
     pub pretty: bool,
+
+    /// How emitted modules export their values (default: return)
+    #[serde(default)]
+    pub module_style: ModuleStyle,
+
+    /// Lowering used for classes (default: metatable)
+    #[serde(default)]
+    pub class_template: ClassTemplate,
+
+    /// Lowering used for enums (default: table)
+    #[serde(default)]
+    pub enum_template: EnumTemplate,
+
+    /// Emit deep optional member chains (`a?.b?.c`) as calls to a shared
+    /// safe-access helper instead of inline `and` chains (default: false)
+    #[serde(default)]
+    pub nil_safe_helpers: bool,
+
+    /// Convention used by imported modules that differ from `moduleStyle`,
+    /// keyed by import path. Imports of these get a compatibility shim.
+    #[serde(default)]
+    pub module_conventions: HashMap<String, ModuleStyle>,
+
+    /// Import paths (glob patterns) whose `require` is deferred until the
+    /// imported value is first used, as if imported with `import lazy`
+    #[serde(default)]
+    pub lazy_imports: Vec<String>,
+
+    /// Wrap values typed `Readonly<T>` in proxies that raise on writes,
+    /// for debug builds (default: false)
+    #[serde(default)]
+    pub freeze_readonly: bool,
+
+    /// How integer overflow behaves in `@checked` functions (default:
+    /// native)
+    #[serde(default)]
+    pub integer_overflow: IntegerOverflow,
+
+    /// Fold `const` declarations initialized from literals and other such
+    /// constants, and inline their values at each use (default: true)
+    #[serde(default = "default_true")]
+    pub inline_constants: bool,
+
+    /// Hoist string literals of 16 or more characters and constant tables
+    /// that occur more than once in a module into shared module-level
+    /// locals. Pooled tables are shared by all their uses, so only enable
+    /// this for modules that never modify them (default: false)
+    #[serde(default)]
+    pub pool_literals: bool,
+
+    /// When targeting Luau, keep type annotations as Luau's own and mark
+    /// modules `--!strict`, rather than erasing types (default: true)
+    #[serde(default = "default_true")]
+    pub luau_types: bool,
+
+    /// Leave unexported functions, types and enum members that nothing
+    /// refers to out of the emitted Lua. Bundles always do (default: false)
+    #[serde(default)]
+    pub tree_shaking: bool,
+
+    /// Minify the emitted Lua: strip whitespace and comments, shorten the
+    /// names of locals and join concatenated string literals. Minified
+    /// modules have no source maps (default: false)
+    #[serde(default)]
+    pub minify: bool,
+
+    /// How far code generation optimizes, from 0 to 2. Level 2 specializes
+    /// runtime helpers to each use, unrolling their argument loops, which
+    /// helps targets without a JIT (default: 1)
+    #[serde(default = "default_optimization_level")]
+    pub optimization_level: u8,
+
+    /// Emit Lua for people to read rather than to run fast: locals keep
+    /// their names, doc comments are carried over and each block of
+    /// statements is marked with the line it came from, as in
+    /// `-- from main.tl:42`. Overrides `minify`, `poolLiterals`,
+    /// `inlineConstants` and dead store elimination (default: false)
+    #[serde(default)]
+    pub readable_output: bool,
+
+    /// Reject `// @tl-ignore` comments instead of letting them silence
+    /// diagnostics, for CI builds that must see every problem (default:
+    /// false)
+    #[serde(default)]
+    pub no_suppressions: bool,
 }
 
 fn default_true() -> bool {
     true
 }
 
+fn default_optimization_level() -> u8 {
+    1
+}
+
+fn default_taint_sources() -> Vec<String> {
+    ["io.read", "os.getenv", "::read", "::receive"]
+        .iter()
+        .map(|source| source.to_string())
+        .collect()
+}
+
+fn default_cache_dir() -> String {
+    ".typedlua-cache".to_string()
+}
+
 impl Default for CompilerOptions {
     fn default() -> Self {
         Self {
             strict_null_checks: true,
+            strict_nil: false,
             strict_naming: StrictLevel::Error,
             no_implicit_unknown: false,
             no_explicit_unknown: false,
+            strict_initialization: false,
+            strict_arity: true,
+            no_implicit_override: false,
+            taint_analysis: false,
+            taint_sources: default_taint_sources(),
             target: LuaVersion::Lua54,
+            platform: None,
             enable_oop: true,
             enable_fp: true,
             enable_decorators: true,
@@ -105,12 +365,75 @@ impl Default for CompilerOptions {
             out_dir: None,
             out_file: None,
             source_map: false,
+            emit_declarations: false,
+            cache_dir: default_cache_dir(),
             no_emit: false,
             pretty: true,
+            module_style: ModuleStyle::Return,
+            class_template: ClassTemplate::Metatable,
+            enum_template: EnumTemplate::Table,
+            nil_safe_helpers: false,
+            module_conventions: HashMap::new(),
+            lazy_imports: Vec::new(),
+            freeze_readonly: false,
+            integer_overflow: IntegerOverflow::Native,
+            inline_constants: true,
+            pool_literals: false,
+            luau_types: true,
+            tree_shaking: false,
+            minify: false,
+            optimization_level: 1,
+            readable_output: false,
+            no_suppressions: false,
         }
     }
 }
 
+/// How `typedlua fmt` lays out source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormatOptions {
+    /// Spaces per level of indentation (default: 4)
+    #[serde(default = "default_indent_width")]
+    pub indent_width: usize,
+
+    /// Lines longer than this are broken at the commas of their outermost
+    /// bracketed list (default: 100)
+    #[serde(default = "default_line_width")]
+    pub line_width: usize,
+}
+
+fn default_indent_width() -> usize {
+    4
+}
+
+fn default_line_width() -> usize {
+    100
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent_width: default_indent_width(),
+            line_width: default_line_width(),
+        }
+    }
+}
+
+/// Which lint rules `typedlua lint` runs, and how severely
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LintOptions {
+    /// Also run the lint rules when building (default: false)
+    #[serde(default)]
+    pub on_build: bool,
+
+    /// Severity of a rule by name, overriding its default; `"off"`
+    /// disables it
+    #[serde(default)]
+    pub rules: HashMap<String, StrictLevel>,
+}
+
 /// Main compiler configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -119,6 +442,14 @@ pub struct CompilerConfig {
     #[serde(default)]
     pub compiler_options: CompilerOptions,
 
+    /// Formatter options
+    #[serde(default)]
+    pub format: FormatOptions,
+
+    /// Lint options
+    #[serde(default)]
+    pub lint: LintOptions,
+
     /// Files to include (glob patterns)
     #[serde(default)]
     pub include: Vec<String>,
@@ -136,6 +467,8 @@ impl Default for CompilerConfig {
     fn default() -> Self {
         Self {
             compiler_options: CompilerOptions::default(),
+            format: FormatOptions::default(),
+            lint: LintOptions::default(),
             include: vec!["**/*.tl".to_string()],
             exclude: default_exclude(),
         }
@@ -167,6 +500,9 @@ impl CompilerConfig {
         if let Some(strict_null_checks) = overrides.strict_null_checks {
             self.compiler_options.strict_null_checks = strict_null_checks;
         }
+        if let Some(strict_nil) = overrides.strict_nil {
+            self.compiler_options.strict_nil = strict_nil;
+        }
         if let Some(strict_naming) = overrides.strict_naming {
             self.compiler_options.strict_naming = strict_naming;
         }
@@ -176,6 +512,15 @@ impl CompilerConfig {
         if let Some(no_explicit_unknown) = overrides.no_explicit_unknown {
             self.compiler_options.no_explicit_unknown = no_explicit_unknown;
         }
+        if let Some(strict_initialization) = overrides.strict_initialization {
+            self.compiler_options.strict_initialization = strict_initialization;
+        }
+        if let Some(strict_arity) = overrides.strict_arity {
+            self.compiler_options.strict_arity = strict_arity;
+        }
+        if let Some(no_implicit_override) = overrides.no_implicit_override {
+            self.compiler_options.no_implicit_override = no_implicit_override;
+        }
         if let Some(target) = overrides.target {
             self.compiler_options.target = target;
         }
@@ -200,12 +545,30 @@ impl CompilerConfig {
         if let Some(source_map) = overrides.source_map {
             self.compiler_options.source_map = source_map;
         }
+        if let Some(emit_declarations) = overrides.emit_declarations {
+            self.compiler_options.emit_declarations = emit_declarations;
+        }
         if let Some(no_emit) = overrides.no_emit {
             self.compiler_options.no_emit = no_emit;
         }
         if let Some(pretty) = overrides.pretty {
             self.compiler_options.pretty = pretty;
         }
+        if let Some(module_style) = overrides.module_style {
+            self.compiler_options.module_style = module_style;
+        }
+        if let Some(class_template) = overrides.class_template {
+            self.compiler_options.class_template = class_template;
+        }
+        if let Some(enum_template) = overrides.enum_template {
+            self.compiler_options.enum_template = enum_template;
+        }
+        if let Some(nil_safe_helpers) = overrides.nil_safe_helpers {
+            self.compiler_options.nil_safe_helpers = nil_safe_helpers;
+        }
+        if let Some(ref platform) = overrides.platform {
+            self.compiler_options.platform = Some(platform.clone());
+        }
     }
 }
 
@@ -214,9 +577,13 @@ impl CompilerConfig {
 #[derive(Debug, Default, Clone)]
 pub struct CliOverrides {
     pub strict_null_checks: Option<bool>,
+    pub strict_nil: Option<bool>,
     pub strict_naming: Option<StrictLevel>,
     pub no_implicit_unknown: Option<bool>,
     pub no_explicit_unknown: Option<bool>,
+    pub strict_initialization: Option<bool>,
+    pub strict_arity: Option<bool>,
+    pub no_implicit_override: Option<bool>,
     pub target: Option<LuaVersion>,
     pub enable_oop: Option<bool>,
     pub enable_fp: Option<bool>,
@@ -225,8 +592,14 @@ pub struct CliOverrides {
     pub out_dir: Option<String>,
     pub out_file: Option<String>,
     pub source_map: Option<bool>,
+    pub emit_declarations: Option<bool>,
     pub no_emit: Option<bool>,
     pub pretty: Option<bool>,
+    pub module_style: Option<ModuleStyle>,
+    pub class_template: Option<ClassTemplate>,
+    pub enum_template: Option<EnumTemplate>,
+    pub nil_safe_helpers: Option<bool>,
+    pub platform: Option<String>,
 }
 
 #[cfg(test)]
@@ -258,6 +631,46 @@ compilerOptions:
         let config: CompilerConfig = serde_yaml::from_str(yaml).unwrap();
         assert_eq!(config.compiler_options.target, LuaVersion::Lua53);
         assert!(!config.compiler_options.enable_oop);
+        assert!(!config.compiler_options.freeze_readonly);
+    }
+
+    #[test]
+    fn test_target_names() {
+        assert_eq!(LuaVersion::from_target_name("lua51"), Some(LuaVersion::Lua51));
+        assert_eq!(LuaVersion::from_target_name("5.3"), Some(LuaVersion::Lua53));
+        assert_eq!(LuaVersion::from_target_name("Luau"), Some(LuaVersion::Luau));
+        assert_eq!(LuaVersion::from_target_name("lua99"), None);
+
+        let config: CompilerConfig =
+            serde_yaml::from_str("compilerOptions:\n  target: luajit\n  platform: roblox\n")
+                .unwrap();
+        assert_eq!(config.compiler_options.target, LuaVersion::LuaJIT);
+        assert_eq!(config.compiler_options.platform.as_deref(), Some("roblox"));
+    }
+
+    #[test]
+    fn test_module_style_options() {
+        let yaml = r#"
+compilerOptions:
+  moduleStyle: global
+  moduleConventions:
+    "./legacy/json": module
+  lazyImports:
+    - "./assets/**"
+"#;
+        let mut config: CompilerConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.compiler_options.module_style, ModuleStyle::Global);
+        assert_eq!(
+            config.compiler_options.module_conventions.get("./legacy/json"),
+            Some(&ModuleStyle::Module)
+        );
+        assert_eq!(config.compiler_options.lazy_imports, vec!["./assets/**"]);
+
+        config.merge(&CliOverrides {
+            module_style: Some(ModuleStyle::Return),
+            ..Default::default()
+        });
+        assert_eq!(config.compiler_options.module_style, ModuleStyle::Return);
     }
 
     #[test]
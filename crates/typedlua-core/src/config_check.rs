@@ -0,0 +1,280 @@
+use crate::config::CompilerConfig;
+use crate::diagnostics::Diagnostic;
+use crate::span::Span;
+use serde_yaml::{Mapping, Value};
+
+/// Parse and validate the contents of a configuration file, reporting every
+/// problem with a span into the file: syntax errors, unknown keys, values
+/// of the wrong type and options that contradict each other.
+///
+/// The configuration is returned unless an error prevented reading it.
+pub fn check_config(source: &str) -> (Option<CompilerConfig>, Vec<Diagnostic>) {
+    let mut diagnostics = Vec::new();
+
+    let value: Value = match serde_yaml::from_str(source) {
+        Ok(value) => value,
+        Err(err) => {
+            diagnostics.push(yaml_error(source, &err));
+            return (None, diagnostics);
+        }
+    };
+    // An empty file is the default configuration
+    if value.is_null() {
+        return (Some(CompilerConfig::default()), diagnostics);
+    }
+
+    let known = serde_yaml::to_value(CompilerConfig::default()).expect("config serializes");
+    check_keys(source, &value, &known, 0, &mut diagnostics);
+
+    let config = match serde_yaml::from_value::<CompilerConfig>(value.clone()) {
+        Ok(config) => config,
+        Err(err) => {
+            // Values carry no positions, so re-read the text to locate the error
+            let err = serde_yaml::from_str::<CompilerConfig>(source)
+                .err()
+                .unwrap_or(err);
+            diagnostics.push(yaml_error(source, &err));
+            return (None, diagnostics);
+        }
+    };
+
+    check_conflicts(source, &value, &mut diagnostics);
+    (Some(config), diagnostics)
+}
+
+/// Report keys of `value` that the schema in `known` does not have
+fn check_keys(
+    source: &str,
+    value: &Value,
+    known: &Value,
+    indent: usize,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let (Value::Mapping(mapping), Value::Mapping(known)) = (value, known) else {
+        return;
+    };
+
+    for (key, nested) in mapping {
+        let Some(key) = key.as_str() else {
+            continue;
+        };
+        match known.get(key) {
+            // Only nested option groups have a fixed set of keys
+            Some(schema @ Value::Mapping(group)) if !group.is_empty() => {
+                check_keys(source, nested, schema, indent + 1, diagnostics);
+            }
+            Some(_) => {}
+            None => {
+                let mut message = format!("Unknown configuration option '{}'", key);
+                if let Some(suggestion) = suggest_key(key, known) {
+                    message.push_str(&format!("; did you mean '{}'?", suggestion));
+                }
+                let span = key_span(source, key, indent);
+                diagnostics.push(Diagnostic::error(span, message).with_code("TL5002"));
+            }
+        }
+    }
+}
+
+/// A known key that differs from `key` only in case or separators, such
+/// as `strict_nil` for `strictNil`
+fn suggest_key<'a>(key: &str, known: &'a Mapping) -> Option<&'a str> {
+    let normalize = |name: &str| name.replace(['_', '-'], "").to_ascii_lowercase();
+    let wanted = normalize(key);
+    known
+        .keys()
+        .filter_map(Value::as_str)
+        .find(|candidate| normalize(candidate) == wanted)
+}
+
+/// Report options that cannot be used together
+fn check_conflicts(source: &str, value: &Value, diagnostics: &mut Vec<Diagnostic>) {
+    let Some(options) = value.get("compilerOptions") else {
+        return;
+    };
+    let is_set = |key: &str| options.get(key).is_some_and(|v| !v.is_null());
+    let is_true = |key: &str| options.get(key).and_then(Value::as_bool) == Some(true);
+    let span = |key: &str| key_span(source, key, 1);
+
+    if is_set("outFile") && is_set("outDir") {
+        diagnostics.push(
+            Diagnostic::error(
+                span("outFile"),
+                "'outFile' bundles all output into one file and cannot be combined with 'outDir'",
+            )
+            .with_code("TL5003"),
+        );
+    }
+    if is_true("strictNil")
+        && options.get("strictNullChecks").and_then(Value::as_bool) == Some(false)
+    {
+        diagnostics.push(
+            Diagnostic::error(
+                span("strictNil"),
+                "'strictNil' implies 'strictNullChecks', which is disabled",
+            )
+            .with_code("TL5003"),
+        );
+    }
+    if is_true("noEmit") {
+        for key in ["outDir", "outFile", "sourceMap"] {
+            if is_set(key) && options.get(key) != Some(&Value::Bool(false)) {
+                diagnostics.push(
+                    Diagnostic::warning(
+                        span(key),
+                        format!("'{}' has no effect with 'noEmit'", key),
+                    )
+                    .with_code("TL5004"),
+                );
+            }
+        }
+    }
+    if is_true("readableOutput") {
+        for key in ["minify", "poolLiterals", "inlineConstants"] {
+            if is_true(key) {
+                diagnostics.push(
+                    Diagnostic::warning(
+                        span(key),
+                        format!("'{}' has no effect with 'readableOutput'", key),
+                    )
+                    .with_code("TL5004"),
+                );
+            }
+        }
+    }
+}
+
+fn yaml_error(source: &str, err: &serde_yaml::Error) -> Diagnostic {
+    let mut message = err.to_string();
+    // The position is carried by the span instead
+    if let Some(index) = message.find(" at line ") {
+        message.truncate(index);
+    }
+    let span = match err.location() {
+        Some(location) => Span::new(
+            location.index(),
+            location.index(),
+            location.line(),
+            location.column(),
+        ),
+        None => Span::new(0, source.len().min(1), 1, 1),
+    };
+    Diagnostic::error(span, message).with_code("TL5001")
+}
+
+/// The span of the first `key:` written at the given nesting depth, or the
+/// start of the file if it cannot be found
+fn key_span(source: &str, key: &str, depth: usize) -> Span {
+    let mut offset = 0;
+    for (index, line) in source.split_inclusive('\n').enumerate() {
+        let indent = line.len() - line.trim_start().len();
+        let rest = line.trim_start();
+        let name = rest
+            .strip_prefix('"')
+            .and_then(|r| r.strip_prefix(key))
+            .and_then(|r| r.strip_prefix('"'))
+            .or_else(|| rest.strip_prefix(key));
+        let at_depth = (indent == 0) == (depth == 0);
+        if let Some(after) = name.filter(|_| at_depth) {
+            if after.trim_start().starts_with(':') {
+                let start = offset + indent;
+                let len = rest.len() - after.len();
+                return Span::new(start, start + len, index + 1, indent + 1);
+            }
+        }
+        offset += line.len();
+    }
+    Span::new(0, 0, 1, 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LuaVersion;
+    use crate::diagnostics::DiagnosticLevel;
+
+    fn messages(source: &str) -> Vec<(usize, String)> {
+        check_config(source)
+            .1
+            .into_iter()
+            .map(|d| (d.span.line, d.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_config() {
+        let (config, diagnostics) = check_config("compilerOptions:\n  target: \"5.1\"\n");
+        assert!(diagnostics.is_empty());
+        assert_eq!(config.unwrap().compiler_options.target, LuaVersion::Lua51);
+
+        let (config, diagnostics) = check_config("");
+        assert!(diagnostics.is_empty());
+        assert!(config.is_some());
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let source =
+            "include:\n  - src/**\ncompilerOptions:\n  strict_nil: true\n  fancy: 1\nextra: no\n";
+        assert_eq!(
+            messages(source),
+            vec![
+                (
+                    4,
+                    "Unknown configuration option 'strict_nil'; did you mean 'strictNil'?"
+                        .to_string()
+                ),
+                (5, "Unknown configuration option 'fancy'".to_string()),
+                (6, "Unknown configuration option 'extra'".to_string()),
+            ]
+        );
+        // Unknown keys alone do not stop the file from being read
+        assert!(check_config(source).0.is_some());
+    }
+
+    #[test]
+    fn test_wrong_types() {
+        let (config, diagnostics) =
+            check_config("compilerOptions:\n  enableOop: maybe\n  target: \"5.9\"\n");
+        assert!(config.is_none());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].span.line, 2);
+        assert!(diagnostics[0].message.contains("enableOop"));
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        let (config, diagnostics) = check_config("compilerOptions:\n  target: [\n");
+        assert!(config.is_none());
+        assert_eq!(diagnostics[0].level, DiagnosticLevel::Error);
+        assert!(diagnostics[0].span.line > 1);
+    }
+
+    #[test]
+    fn test_conflicting_options() {
+        let source = "compilerOptions:\n  outDir: dist\n  outFile: out.lua\n  strictNullChecks: false\n  strictNil: true\n";
+        assert_eq!(
+            messages(source),
+            vec![
+                (
+                    3,
+                    "'outFile' bundles all output into one file and cannot be combined with 'outDir'"
+                        .to_string()
+                ),
+                (
+                    5,
+                    "'strictNil' implies 'strictNullChecks', which is disabled".to_string()
+                ),
+            ]
+        );
+
+        let (_, diagnostics) =
+            check_config("compilerOptions:\n  noEmit: true\n  sourceMap: true\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].level, DiagnosticLevel::Warning);
+        assert_eq!(
+            diagnostics[0].message,
+            "'sourceMap' has no effect with 'noEmit'"
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use crate::ast::expression::{BinaryOp, Expression, ExpressionKind, Literal, UnaryOp};
+
+/// Evaluate an expression built only from literals and named constants, as
+/// Lua would at runtime. `lookup` gives the value of a constant in scope.
+///
+/// Anything whose result depends on the target is left alone: bitwise
+/// operators, integers that overflow, and floats converted to strings.
+pub fn evaluate(expr: &Expression, lookup: &dyn Fn(&str) -> Option<Literal>) -> Option<Literal> {
+    match &expr.kind {
+        // LuaJIT's suffixed integers are cdata, not numbers, and what an
+        // integer past 64 bits reads as depends on the target
+        ExpressionKind::Literal(Literal::SuffixedInteger(..) | Literal::WrappedInteger(..)) => None,
+        ExpressionKind::Literal(literal) => Some(literal.clone()),
+        ExpressionKind::Identifier(name) => lookup(name),
+        ExpressionKind::Parenthesized(inner) => evaluate(inner, lookup),
+        ExpressionKind::Unary(op, operand) => unary(*op, evaluate(operand, lookup)?),
+        ExpressionKind::Binary(BinaryOp::And, left, right) => {
+            let left = evaluate(left, lookup)?;
+            if is_truthy(&left) {
+                evaluate(right, lookup)
+            } else {
+                Some(left)
+            }
+        }
+        ExpressionKind::Binary(BinaryOp::Or, left, right) => {
+            let left = evaluate(left, lookup)?;
+            if is_truthy(&left) {
+                Some(left)
+            } else {
+                evaluate(right, lookup)
+            }
+        }
+        ExpressionKind::Binary(op, left, right) => {
+            binary(*op, evaluate(left, lookup)?, evaluate(right, lookup)?)
+        }
+        _ => None,
+    }
+}
+
+fn is_truthy(value: &Literal) -> bool {
+    !matches!(value, Literal::Nil | Literal::Boolean(false))
+}
+
+fn unary(op: UnaryOp, operand: Literal) -> Option<Literal> {
+    match (op, operand) {
+        (UnaryOp::Not, operand) => Some(Literal::Boolean(!is_truthy(&operand))),
+        (UnaryOp::Negate, Literal::Integer(i)) => i.checked_neg().map(Literal::Integer),
+        (UnaryOp::Negate, Literal::Number(n)) => Some(Literal::Number(-n)),
+        (UnaryOp::Length, Literal::String(s)) => Some(Literal::Integer(s.len() as i64)),
+        _ => None,
+    }
+}
+
+fn binary(op: BinaryOp, left: Literal, right: Literal) -> Option<Literal> {
+    use Literal::{Boolean, Integer, Number};
+
+    match op {
+        BinaryOp::Concatenate => Some(Literal::String(format!(
+            "{}{}",
+            concat_operand(&left)?,
+            concat_operand(&right)?
+        ))),
+        BinaryOp::Equal => Some(Boolean(literal_equals(&left, &right))),
+        BinaryOp::NotEqual => Some(Boolean(!literal_equals(&left, &right))),
+        BinaryOp::LessThan
+        | BinaryOp::LessThanOrEqual
+        | BinaryOp::GreaterThan
+        | BinaryOp::GreaterThanOrEqual => {
+            let ordering = match (&left, &right) {
+                (Literal::String(a), Literal::String(b)) => a.partial_cmp(b),
+                _ => as_float(&left)?.partial_cmp(&as_float(&right)?),
+            }?;
+            Some(Boolean(match op {
+                BinaryOp::LessThan => ordering.is_lt(),
+                BinaryOp::LessThanOrEqual => ordering.is_le(),
+                BinaryOp::GreaterThan => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            }))
+        }
+        // Integer arithmetic stays integral unless it would overflow
+        _ => match (left, right) {
+            (Integer(a), Integer(b)) => match op {
+                BinaryOp::Add => a.checked_add(b).map(Integer),
+                BinaryOp::Subtract => a.checked_sub(b).map(Integer),
+                BinaryOp::Multiply => a.checked_mul(b).map(Integer),
+                BinaryOp::Modulo if b != 0 => floor_div(a, b)?
+                    .checked_mul(b)
+                    .and_then(|product| a.checked_sub(product))
+                    .map(Integer),
+                BinaryOp::Divide => Some(Number(a as f64 / b as f64)),
+                BinaryOp::Power => Some(Number((a as f64).powf(b as f64))),
+                _ => None,
+            },
+            (left, right) => {
+                let (a, b) = (as_float(&left)?, as_float(&right)?);
+                match op {
+                    BinaryOp::Add => Some(Number(a + b)),
+                    BinaryOp::Subtract => Some(Number(a - b)),
+                    BinaryOp::Multiply => Some(Number(a * b)),
+                    BinaryOp::Divide => Some(Number(a / b)),
+                    BinaryOp::Power => Some(Number(a.powf(b))),
+                    BinaryOp::Modulo if b != 0.0 => Some(Number(a - (a / b).floor() * b)),
+                    _ => None,
+                }
+            }
+        },
+    }
+}
+
+/// Integer division rounding towards negative infinity, which Lua's `%`
+/// is defined by
+fn floor_div(a: i64, b: i64) -> Option<i64> {
+    let quotient = a.checked_div(b)?;
+    if (a % b != 0) && ((a < 0) != (b < 0)) {
+        Some(quotient - 1)
+    } else {
+        Some(quotient)
+    }
+}
+
+fn as_float(value: &Literal) -> Option<f64> {
+    match value {
+        Literal::Integer(i) => Some(*i as f64),
+        Literal::Number(n) => Some(*n),
+        _ => None,
+    }
+}
+
+/// Floats are formatted differently by each Lua version, so only strings
+/// and integers are concatenated
+fn concat_operand(value: &Literal) -> Option<String> {
+    match value {
+        Literal::String(s) => Some(s.clone()),
+        Literal::Integer(i) => Some(i.to_string()),
+        _ => None,
+    }
+}
+
+fn literal_equals(left: &Literal, right: &Literal) -> bool {
+    match (left, right) {
+        (Literal::Nil, Literal::Nil) => true,
+        (Literal::Boolean(a), Literal::Boolean(b)) => a == b,
+        (Literal::String(a), Literal::String(b)) => a == b,
+        _ => match (as_float(left), as_float(right)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::CollectingDiagnosticHandler;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use std::sync::Arc;
+
+    fn fold(source: &str) -> Option<Literal> {
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let tokens = Lexer::new(&format!("const x = {}", source), handler.clone())
+            .tokenize()
+            .expect("Lexing failed");
+        let program = Parser::new(tokens, handler).parse().expect("Parse failed");
+        let crate::ast::statement::Statement::Variable(decl) = &program.statements[0] else {
+            panic!("Expected variable declaration");
+        };
+        let lookup = |name: &str| (name == "SIZE").then_some(Literal::Integer(16));
+        evaluate(&decl.initializer, &lookup)
+    }
+
+    #[test]
+    fn test_folds_arithmetic_like_lua() {
+        assert!(matches!(
+            fold("60 * 60 * 24"),
+            Some(Literal::Integer(86400))
+        ));
+        assert!(matches!(fold("SIZE * 2 + 1"), Some(Literal::Integer(33))));
+        assert!(matches!(fold("-7 % 3"), Some(Literal::Integer(2))));
+        assert!(matches!(fold("7 / 2"), Some(Literal::Number(n)) if n == 3.5));
+        assert!(matches!(fold("2 ^ 10"), Some(Literal::Number(n)) if n == 1024.0));
+        assert!(matches!(fold("1.5 + 1"), Some(Literal::Number(n)) if n == 2.5));
+        assert!(matches!(fold("#\"abc\""), Some(Literal::Integer(3))));
+        assert!(
+            matches!(fold("SIZE > 8 and \"big\" or \"small\""), Some(Literal::String(s)) if s == "big")
+        );
+    }
+
+    #[test]
+    fn test_folds_string_concatenation() {
+        assert!(
+            matches!(fold("\"v\" .. SIZE .. \".0\""), Some(Literal::String(s)) if s == "v16.0")
+        );
+        // A float's text depends on the Lua version
+        assert!(fold("\"v\" .. 1.5").is_none());
+    }
+
+    #[test]
+    fn test_leaves_runtime_values_alone() {
+        assert!(fold("other + 1").is_none());
+        assert!(fold("SIZE % 0").is_none());
+        assert!(fold("9223372036854775807 + 1").is_none());
+        assert!(fold("SIZE & 1").is_none());
+        assert!(fold("load()").is_none());
+    }
+}
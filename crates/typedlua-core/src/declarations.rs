@@ -0,0 +1,544 @@
+//! Writing a module's exported types and signatures as a declaration file
+//! (`.d.tl`) with `emitDeclarations`, so other projects can type-check
+//! against compiled Lua without its TypedLua source
+
+use crate::ast::attributes::schema_version;
+use crate::ast::pattern::Pattern;
+use crate::ast::statement::*;
+use crate::ast::types::{Type, TypeKind};
+use crate::ast::{Ident, Program};
+use crate::typechecker::{AnnotationTarget, TypeChecker};
+
+/// The declaration file of a checked module. Exported functions, values,
+/// classes, interfaces, type aliases and enums are written without their
+/// bodies; unexported interfaces and type aliases are kept so the exported
+/// signatures still resolve. Default exports, `export =` and namespaces are
+/// left out.
+pub fn module_declarations(program: &Program, checker: &TypeChecker) -> String {
+    let mut writer = DeclarationWriter {
+        checker,
+        out: String::new(),
+    };
+    let locals: Vec<&Statement> = program.statements.iter().collect();
+    for statement in &program.statements {
+        match statement {
+            Statement::Interface(_) | Statement::TypeAlias(_) => {
+                writer.declaration(statement, false)
+            }
+            Statement::Export(export) => match &export.kind {
+                ExportKind::Declaration(decl) => writer.declaration(decl, true),
+                ExportKind::Named(specifiers) => {
+                    for specifier in specifiers {
+                        writer.named_export(specifier, &locals);
+                    }
+                }
+                ExportKind::Default(_) | ExportKind::Equals(_) => {}
+            },
+            _ => {}
+        }
+    }
+    writer.out
+}
+
+struct DeclarationWriter<'a> {
+    checker: &'a TypeChecker,
+    out: String,
+}
+
+impl DeclarationWriter<'_> {
+    fn declaration(&mut self, statement: &Statement, exported: bool) {
+        let export = if exported { "export " } else { "" };
+        match statement {
+            Statement::Function(func) => {
+                let return_type = match &func.return_type {
+                    Some(typ) => typ.to_string(),
+                    None => self.inferred_return(&func.name),
+                };
+                self.line(format!(
+                    "{}declare function {}{}({}): {}",
+                    export,
+                    func.name.node,
+                    type_parameters(&func.type_parameters),
+                    parameters(&func.parameters),
+                    return_type
+                ));
+            }
+            Statement::Variable(decl) => {
+                let Pattern::Identifier(name) = &decl.pattern else {
+                    return;
+                };
+                let typ = match &decl.type_annotation {
+                    Some(typ) => typ.to_string(),
+                    None => self.symbol_type(name),
+                };
+                self.line(format!(
+                    "{}declare {} {}: {}",
+                    export,
+                    variable_kind(decl.kind),
+                    name.node,
+                    typ
+                ));
+            }
+            Statement::Declare(decl) => self.line(format!("{}{}", export, declare(decl))),
+            Statement::Interface(interface) => {
+                // Loading the declaration declares a record's table again
+                if let Some(Ok(version)) = schema_version(&interface.decorators) {
+                    self.line(format!("@schema(version = {})", version));
+                }
+                let mut header = format!(
+                    "{}interface {}{}",
+                    export,
+                    interface.name.node,
+                    type_parameters(&interface.type_parameters)
+                );
+                if !interface.extends.is_empty() {
+                    header.push_str(&format!(" extends {}", join(&interface.extends)));
+                }
+                let members = interface.members.iter().map(interface_member).collect();
+                self.block(header, members);
+            }
+            Statement::TypeAlias(alias) => self.line(format!(
+                "{}type {}{} = {}",
+                export,
+                alias.name.node,
+                type_parameters(&alias.type_parameters),
+                alias.type_annotation
+            )),
+            Statement::Enum(decl) => {
+                let header = format!(
+                    "{}{}enum {}",
+                    export,
+                    if decl.is_const { "const " } else { "" },
+                    decl.name.node
+                );
+                let members = decl
+                    .members
+                    .iter()
+                    .map(|member| match &member.value {
+                        Some(EnumValue::Number(n)) => format!("{} = {},", member.name.node, n),
+                        Some(EnumValue::String(s)) => {
+                            format!("{} = {},", member.name.node, quote(s))
+                        }
+                        None => format!("{},", member.name.node),
+                    })
+                    .collect();
+                self.block(header, members);
+            }
+            Statement::Class(class) => self.class(class, export),
+            _ => {}
+        }
+    }
+
+    /// `export { name }` declares the local it reads, under the exported
+    /// name
+    fn named_export(&mut self, specifier: &ExportSpecifier, locals: &[&Statement]) {
+        let exported = specifier.exported.as_ref().unwrap_or(&specifier.local);
+        let local = locals
+            .iter()
+            .find(|statement| declared_name(statement) == Some(&specifier.local.node));
+        match local {
+            Some(Statement::Interface(_) | Statement::TypeAlias(_)) => {
+                if exported.node != specifier.local.node {
+                    self.line(format!(
+                        "export type {} = {}",
+                        exported.node, specifier.local.node
+                    ));
+                } else {
+                    self.line(format!("export {{ {} }}", exported.node));
+                }
+            }
+            Some(statement) if exported.node == specifier.local.node => {
+                self.declaration(statement, true)
+            }
+            _ => {
+                let typ = self.symbol_type(&specifier.local);
+                self.line(format!("export declare const {}: {}", exported.node, typ));
+            }
+        }
+    }
+
+    fn class(&mut self, class: &ClassDeclaration, export: &str) {
+        let mut header = format!(
+            "{}{}class {}{}",
+            export,
+            if class.is_abstract { "abstract " } else { "" },
+            class.name.node,
+            type_parameters(&class.type_parameters)
+        );
+        if let Some(extends) = &class.extends {
+            header.push_str(&format!(" extends {}", extends));
+        } else if !class.mixins.is_empty() {
+            header.push_str(&format!(" extends mixin({})", join(&class.mixins)));
+        }
+        if !class.implements.is_empty() {
+            header.push_str(&format!(" implements {}", join(&class.implements)));
+        }
+
+        let mut members = Vec::new();
+        for member in &class.members {
+            match member {
+                ClassMember::Property(prop) if prop.is_const && !is_private(prop.access) => members
+                    .push(format!(
+                        "{}const {}: {}",
+                        modifiers(prop.access, false, false),
+                        prop.name.node,
+                        prop.type_annotation
+                    )),
+                ClassMember::Property(prop) if !is_private(prop.access) => members.push(format!(
+                    "{}{}{}: {}",
+                    modifiers(prop.access, prop.is_static, false),
+                    if prop.is_readonly { "readonly " } else { "" },
+                    prop.name.node,
+                    prop.type_annotation
+                )),
+                ClassMember::Constructor(ctor) => {
+                    members.push(format!("constructor({})", parameters(&ctor.parameters)))
+                }
+                ClassMember::Method(method) if !is_private(method.access) => {
+                    let mut signature = format!(
+                        "{}{}{}{}({})",
+                        modifiers(method.access, method.is_static, method.is_abstract),
+                        if method.is_override { "override " } else { "" },
+                        method.name.node,
+                        type_parameters(&method.type_parameters),
+                        parameters(&method.parameters)
+                    );
+                    if let Some(return_type) = &method.return_type {
+                        signature.push_str(&format!(": {}", return_type));
+                    }
+                    members.push(signature);
+                }
+                // Accessors have no bodiless form, so become properties:
+                // read-only unless the class also has a setter
+                ClassMember::Getter(getter) if !is_private(getter.access) => {
+                    let has_setter = class.members.iter().any(|other| {
+                        matches!(other, ClassMember::Setter(setter)
+                            if setter.name.node == getter.name.node)
+                    });
+                    members.push(format!(
+                        "{}{}{}: {}",
+                        modifiers(getter.access, getter.is_static, false),
+                        if has_setter { "" } else { "readonly " },
+                        getter.name.node,
+                        getter.return_type
+                    ));
+                }
+                ClassMember::Setter(setter) if !is_private(setter.access) => {
+                    let has_getter = class.members.iter().any(|other| {
+                        matches!(other, ClassMember::Getter(getter)
+                            if getter.name.node == setter.name.node)
+                    });
+                    if !has_getter {
+                        let typ = setter
+                            .parameter
+                            .type_annotation
+                            .as_ref()
+                            .map_or("unknown".to_string(), |typ| typ.to_string());
+                        members.push(format!(
+                            "{}{}: {}",
+                            modifiers(setter.access, setter.is_static, false),
+                            setter.name.node,
+                            typ
+                        ));
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.block(header, members);
+    }
+
+    /// The checked type of a value, or `unknown` if it has none
+    fn symbol_type(&self, name: &Ident) -> String {
+        self.checker
+            .symbols()
+            .lookup(&name.node)
+            .map_or("unknown".to_string(), |symbol| symbol.typ.to_string())
+    }
+
+    /// The return type the checker inferred for an unannotated function
+    fn inferred_return(&self, name: &Ident) -> String {
+        self.checker
+            .inferred_annotations()
+            .iter()
+            .find_map(|annotation| match &annotation.target {
+                AnnotationTarget::ReturnType(func) if func.name.span == name.span => {
+                    Some(annotation.typ.to_string())
+                }
+                _ => None,
+            })
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+
+    fn line(&mut self, line: String) {
+        self.out.push_str(&line);
+        self.out.push('\n');
+    }
+
+    fn block(&mut self, header: String, members: Vec<String>) {
+        self.line(format!("{} {{", header));
+        for member in members {
+            self.line(format!("    {}", member));
+        }
+        self.line("}".to_string());
+    }
+}
+
+fn declared_name(statement: &Statement) -> Option<&String> {
+    let name = match statement {
+        Statement::Function(func) => &func.name,
+        Statement::Class(class) => &class.name,
+        Statement::Interface(interface) => &interface.name,
+        Statement::TypeAlias(alias) => &alias.name,
+        Statement::Enum(decl) => &decl.name,
+        Statement::Variable(decl) => match &decl.pattern {
+            Pattern::Identifier(name) => name,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(&name.node)
+}
+
+fn declare(decl: &DeclareDeclaration) -> String {
+    match &decl.kind {
+        DeclareKind::Function(func) => format!(
+            "declare function {}{}({}): {}",
+            func.name.node,
+            type_parameters(&func.type_parameters),
+            parameters(&func.parameters),
+            func.return_type
+        ),
+        DeclareKind::Variable(var) => format!(
+            "declare {} {}: {}",
+            variable_kind(var.kind),
+            var.name.node,
+            var.type_annotation
+        ),
+    }
+}
+
+fn interface_member(member: &InterfaceMember) -> String {
+    match member {
+        InterfaceMember::Property(prop) => format!(
+            "{}{}{}: {}",
+            if prop.is_readonly { "readonly " } else { "" },
+            prop.name.node,
+            if prop.is_optional { "?" } else { "" },
+            prop.type_annotation
+        ),
+        InterfaceMember::Method(method) => format!(
+            "{}{}({}): {}",
+            method.name.node,
+            type_parameters(&method.type_parameters),
+            parameters(&method.parameters),
+            method.return_type
+        ),
+        InterfaceMember::Index(index) => format!(
+            "[{}: {}]: {}",
+            index.key_name.node,
+            match index.key_type {
+                IndexKeyType::String => "string",
+                IndexKeyType::Number => "number",
+            },
+            index.value_type
+        ),
+    }
+}
+
+fn type_parameters(params: &Option<Vec<TypeParameter>>) -> String {
+    let Some(params) = params else {
+        return String::new();
+    };
+    let params: Vec<String> = params
+        .iter()
+        .map(|param| {
+            let mut text = param.name.node.clone();
+            if let Some(constraint) = &param.constraint {
+                text.push_str(&format!(" extends {}", constraint));
+            }
+            if let Some(default) = &param.default {
+                text.push_str(&format!(" = {}", default));
+            }
+            text
+        })
+        .collect();
+    format!("<{}>", params.join(", "))
+}
+
+/// Parameters without their defaults; one with a default may be left out,
+/// so it is written as optional
+fn parameters(params: &[Parameter]) -> String {
+    params
+        .iter()
+        .map(|param| {
+            let name = match &param.pattern {
+                Pattern::Identifier(ident) => ident.node.as_str(),
+                _ => "_",
+            };
+            let rest = if param.is_rest { "..." } else { "" };
+            match (&param.type_annotation, &param.default) {
+                (Some(typ), Some(_)) if !matches!(typ.kind, TypeKind::Nullable(_)) => {
+                    format!("{}{}?: {}", rest, name, typ)
+                }
+                (Some(typ), _) => format!("{}{}: {}", rest, name, typ),
+                (None, _) => format!("{}{}: unknown", rest, name),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn modifiers(access: Option<AccessModifier>, is_static: bool, is_abstract: bool) -> String {
+    let mut text = String::new();
+    if access == Some(AccessModifier::Protected) {
+        text.push_str("protected ");
+    }
+    if is_static {
+        text.push_str("static ");
+    }
+    if is_abstract {
+        text.push_str("abstract ");
+    }
+    text
+}
+
+fn is_private(access: Option<AccessModifier>) -> bool {
+    access == Some(AccessModifier::Private)
+}
+
+fn variable_kind(kind: VariableKind) -> &'static str {
+    match kind {
+        VariableKind::Const | VariableKind::Using => "const",
+        VariableKind::Local => "local",
+    }
+}
+
+fn join(types: &[Type]) -> String {
+    types
+        .iter()
+        .map(|t| t.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CompilerConfig;
+    use crate::diagnostics::{CollectingDiagnosticHandler, DiagnosticHandler};
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use std::sync::Arc;
+
+    fn parse(source: &str, handler: Arc<CollectingDiagnosticHandler>) -> Program {
+        let mut lexer = Lexer::new(source, handler.clone());
+        let tokens = lexer.tokenize().expect("Lexing failed");
+        let mut parser = Parser::new(tokens, handler);
+        parser.parse().expect("Parsing failed")
+    }
+
+    fn declarations(source: &str) -> String {
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let program = parse(source, handler.clone());
+        let mut checker = TypeChecker::new(Arc::new(CompilerConfig::default()), handler);
+        checker.check_program(&program).expect("Checking failed");
+        module_declarations(&program, &checker)
+    }
+
+    const MODULE: &str = r#"interface Point {
+    x: number
+    y: number
+}
+type Pair<T> = [T, T]
+
+export function distance(a: Point, b: Point, scale: number = 1): number
+    return (a.x - b.x) * scale
+end
+
+export function origin()
+    return { x = 0, y = 0 }
+end
+
+export const limit = 10
+local secret = "hidden"
+
+export enum Color { Red = 1, Green = "green" }
+
+export class Counter implements Readable {
+    private count: number = 0
+    readonly step: number = 1
+
+    constructor(start: number) {
+        self.count = start
+    }
+
+    increment(): void {
+        self.count = self.count + self.step
+    }
+
+    private reset(): void {
+        self.count = 0
+    }
+
+    get value(): number {
+        return self.count
+    }
+}
+
+export interface Readable {
+    readonly value: number
+}
+
+function helper(): string
+    return secret
+end
+export { helper as describe }
+"#;
+
+    #[test]
+    fn test_module_declarations() {
+        assert_eq!(
+            declarations(MODULE),
+            r#"interface Point {
+    x: number
+    y: number
+}
+type Pair<T> = [T, T]
+export declare function distance(a: Point, b: Point, scale?: number): number
+export declare function origin(): { x: number, y: number }
+export declare const limit: 10
+export enum Color {
+    Red = 1,
+    Green = "green",
+}
+export class Counter implements Readable {
+    readonly step: number
+    constructor(start: number)
+    increment(): void
+    readonly value: number
+}
+export interface Readable {
+    readonly value: number
+}
+export declare const describe: () -> string
+"#
+        );
+    }
+
+    #[test]
+    fn test_declarations_load_as_declaration_file() {
+        let text = declarations(MODULE);
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let program = parse(&text, handler.clone());
+        let mut checker = TypeChecker::new(Arc::new(CompilerConfig::default()), handler.clone());
+        checker.load_declarations(&program);
+        assert!(!handler.has_errors(), "{:?}", handler.get_diagnostics());
+        assert!(checker.symbols().lookup("distance").is_some());
+        assert!(checker.symbols().lookup("Counter").is_some());
+    }
+}
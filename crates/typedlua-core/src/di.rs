@@ -1,14 +1,22 @@
+use crate::build::{compile_with_plugins, CompiledModule};
 use crate::config::CompilerConfig;
-use crate::diagnostics::{ConsoleDiagnosticHandler, DiagnosticHandler};
+use crate::diagnostics::{
+    DiagnosticBus, DiagnosticHandler, DiagnosticSubscriber, TerminalRenderer,
+};
+use crate::errors::CompilationError;
 use crate::fs::{FileSystem, RealFileSystem};
+use crate::plugin::Plugin;
+use std::path::Path;
 use std::sync::Arc;
 
 /// Dependency injection container
 /// Manages all shared dependencies and creates instances with proper wiring
 pub struct Container {
     config: Arc<CompilerConfig>,
+    diagnostic_bus: Arc<DiagnosticBus>,
     diagnostic_handler: Arc<dyn DiagnosticHandler>,
     file_system: Arc<dyn FileSystem>,
+    plugins: Vec<Arc<dyn Plugin>>,
 }
 
 impl Container {
@@ -16,34 +24,69 @@ impl Container {
     pub fn new(config: CompilerConfig) -> Self {
         let config = Arc::new(config);
 
-        let diagnostic_handler = Arc::new(ConsoleDiagnosticHandler::new(
-            config.compiler_options.pretty,
-        ));
+        let renderer = Arc::new(TerminalRenderer::new(config.compiler_options.pretty));
+        let diagnostic_bus = Arc::new(DiagnosticBus::with_subscribers(vec![renderer]));
 
         let file_system = Arc::new(RealFileSystem::new());
 
         Container {
             config,
-            diagnostic_handler,
+            diagnostic_handler: diagnostic_bus.clone(),
+            diagnostic_bus,
             file_system,
+            plugins: Vec::new(),
         }
     }
 
     /// Create a container with custom dependencies (for testing)
+    /// Diagnostics go only to the given subscribers
     pub fn with_dependencies(
         config: CompilerConfig,
-        diagnostic_handler: Arc<dyn DiagnosticHandler>,
+        subscribers: Vec<Arc<dyn DiagnosticSubscriber>>,
         file_system: Arc<dyn FileSystem>,
     ) -> Self {
         let config = Arc::new(config);
+        let diagnostic_bus = Arc::new(DiagnosticBus::with_subscribers(subscribers));
 
         Container {
             config,
-            diagnostic_handler,
+            diagnostic_handler: diagnostic_bus.clone(),
+            diagnostic_bus,
             file_system,
+            plugins: Vec::new(),
         }
     }
 
+    /// Deliver diagnostics to another sink, such as a JSON writer or a
+    /// language server, alongside those already registered
+    pub fn subscribe(&self, subscriber: Arc<dyn DiagnosticSubscriber>) {
+        self.diagnostic_bus.subscribe(subscriber);
+    }
+
+    /// Run a plugin over every module the container compiles, after the
+    /// plugins already registered
+    pub fn register_plugin(&mut self, plugin: Arc<dyn Plugin>) {
+        self.plugins.push(plugin);
+    }
+
+    /// Get the registered plugins
+    pub fn plugins(&self) -> &[Arc<dyn Plugin>] {
+        &self.plugins
+    }
+
+    /// Compile the module at `path` with the registered plugins
+    pub fn compile_file(&self, path: &Path) -> Result<CompiledModule, CompilationError> {
+        let source = self.file_system.read_file(path)?;
+        compile_with_plugins(
+            path,
+            &source,
+            self.file_system.clone(),
+            self.config.clone(),
+            self.diagnostic_handler.clone(),
+            &self.plugins,
+        )
+    }
+
     /// Get the configuration
     pub fn config(&self) -> &Arc<CompilerConfig> {
         &self.config
@@ -78,10 +121,43 @@ impl Container {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::diagnostics::CollectingDiagnosticHandler;
+    use crate::ast::expression::ExpressionKind;
+    use crate::ast::statement::Statement;
+    use crate::ast::Program;
+    use crate::diagnostics::{CollectingDiagnosticHandler, JsonDiagnosticWriter};
     use crate::fs::MockFileSystem;
+    use crate::plugin::PluginContext;
     use crate::span::Span;
 
+    /// Drops `assert(...)` statements and stamps the output with the module
+    struct ReleasePlugin;
+
+    impl Plugin for ReleasePlugin {
+        fn name(&self) -> &'static str {
+            "release"
+        }
+
+        fn transform(&self, program: &mut Program, context: &PluginContext) {
+            if context.symbols.lookup("main").is_none() {
+                context
+                    .diagnostic_handler
+                    .error(program.span, "Module has no main function");
+            }
+            program.statements.retain(|statement| {
+                let Statement::Expression(expr) = statement else {
+                    return true;
+                };
+                !matches!(&expr.kind, ExpressionKind::Call(callee, _)
+                    if matches!(&callee.kind, ExpressionKind::Identifier(name) if name == "assert"))
+            });
+        }
+
+        fn emit(&self, lua: &mut String, context: &PluginContext) {
+            let path = context.path.unwrap().display();
+            lua.insert_str(0, &format!("-- {}\n", path));
+        }
+    }
+
     #[test]
     fn test_container_creation() {
         let config = CompilerConfig::default();
@@ -97,7 +173,7 @@ mod tests {
         let diagnostics = Arc::new(CollectingDiagnosticHandler::new());
         let fs = Arc::new(MockFileSystem::new());
 
-        let container = Container::with_dependencies(config, diagnostics.clone(), fs);
+        let container = Container::with_dependencies(config, vec![diagnostics.clone()], fs);
 
         // Report an error
         container
@@ -106,6 +182,57 @@ mod tests {
 
         assert!(container.has_errors());
         assert_eq!(container.error_count(), 1);
+        assert_eq!(diagnostics.error_count(), 1);
+    }
+
+    #[test]
+    fn test_container_subscribe() {
+        let config = CompilerConfig::default();
+        let fs = Arc::new(MockFileSystem::new());
+        let container = Container::with_dependencies(config, Vec::new(), fs);
+
+        let writer = Arc::new(JsonDiagnosticWriter::new(Vec::new()));
+        container.subscribe(writer.clone());
+        container
+            .diagnostic_handler()
+            .warning(Span::dummy(), "Test warning");
+
+        drop(container);
+        let output = Arc::try_unwrap(writer).ok().unwrap().into_inner();
+        assert!(String::from_utf8(output)
+            .unwrap()
+            .contains("\"Test warning\""));
+    }
+
+    #[test]
+    fn test_container_runs_plugins() {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            "main.tl",
+            "function main(n: number)\n    assert(n > 0)\n    print(n)\nend\nassert(true)\n",
+        );
+        fs.add_file("lib.tl", "local x = 1\n");
+        let diagnostics = Arc::new(CollectingDiagnosticHandler::new());
+        let mut container = Container::with_dependencies(
+            CompilerConfig::default(),
+            vec![diagnostics.clone()],
+            Arc::new(fs),
+        );
+        container.register_plugin(Arc::new(ReleasePlugin));
+        assert_eq!(container.plugins().len(), 1);
+
+        let compiled = container
+            .compile_file(Path::new("main.tl"))
+            .expect("Compilation failed");
+        // Only top-level statements are rewritten
+        assert_eq!(
+            compiled.lua,
+            "-- main.tl\nlocal function main(n)\n  assert(n > 0)\n  print(n)\nend\n"
+        );
+
+        let result = container.compile_file(Path::new("lib.tl"));
+        assert!(matches!(result, Err(CompilationError::PluginErrors(1))));
+        assert_eq!(diagnostics.error_count(), 1);
     }
 
     #[test]
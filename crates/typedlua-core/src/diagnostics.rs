@@ -1,48 +1,200 @@
 use crate::span::Span;
-use std::sync::Mutex;
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
 
 /// Diagnostic severity level
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum DiagnosticLevel {
     Error,
     Warning,
     Info,
 }
 
+impl DiagnosticLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiagnosticLevel::Error => "error",
+            DiagnosticLevel::Warning => "warning",
+            DiagnosticLevel::Info => "info",
+        }
+    }
+}
+
 /// A diagnostic message with location and severity
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct Diagnostic {
     pub level: DiagnosticLevel,
     pub span: Span,
     pub message: String,
+    /// A stable name for the kind of problem, such as a lint rule's name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// Other places the message refers to
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub related: Vec<RelatedSpan>,
+    /// Context that does not belong to one place, shown after the message
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+    /// Changes that would resolve the problem, for tools to offer
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fixes: Vec<SuggestedFix>,
 }
 
 impl Diagnostic {
-    pub fn error(span: Span, message: impl Into<String>) -> Self {
+    pub fn new(level: DiagnosticLevel, span: Span, message: impl Into<String>) -> Self {
         Self {
-            level: DiagnosticLevel::Error,
+            level,
             span,
             message: message.into(),
+            code: None,
+            related: Vec::new(),
+            notes: Vec::new(),
+            fixes: Vec::new(),
         }
     }
 
+    pub fn error(span: Span, message: impl Into<String>) -> Self {
+        Self::new(DiagnosticLevel::Error, span, message)
+    }
+
     pub fn warning(span: Span, message: impl Into<String>) -> Self {
+        Self::new(DiagnosticLevel::Warning, span, message)
+    }
+
+    pub fn info(span: Span, message: impl Into<String>) -> Self {
+        Self::new(DiagnosticLevel::Info, span, message)
+    }
+
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    pub fn with_related(mut self, related: Vec<RelatedSpan>) -> Self {
+        self.related.extend(related);
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_fixes(mut self, fixes: Vec<SuggestedFix>) -> Self {
+        self.fixes.extend(fixes);
+        self
+    }
+
+    /// The warning for a suppression comment, such as `// @tl-ignore`,
+    /// that silenced nothing, or nothing under some of the codes or rules
+    /// it `named`. None when it silenced something under each.
+    pub fn unused_suppression(
+        span: Span,
+        directive: &str,
+        named: &[String],
+        used: &[String],
+    ) -> Option<Self> {
+        let message = if named.is_empty() {
+            if !used.is_empty() {
+                return None;
+            }
+            format!("This '@{}' silences nothing", directive)
+        } else {
+            let unused: Vec<&str> = named
+                .iter()
+                .filter(|name| !used.contains(name))
+                .map(String::as_str)
+                .collect();
+            if unused.is_empty() {
+                return None;
+            }
+            format!(
+                "This '@{}' silences nothing under {}",
+                directive,
+                unused.join(", ")
+            )
+        };
+        Some(Self::warning(span, message).with_code("TL3015"))
+    }
+}
+
+/// Another place a diagnostic refers to, such as a declaration it clashes
+/// with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RelatedSpan {
+    /// The file the span is in, when it is not the diagnostic's own
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    pub span: Span,
+    pub message: String,
+}
+
+impl RelatedSpan {
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
         Self {
-            level: DiagnosticLevel::Warning,
+            file: None,
             span,
             message: message.into(),
         }
     }
 
-    pub fn info(span: Span, message: impl Into<String>) -> Self {
+    /// Place the span in another file
+    pub fn in_file(mut self, file: &Path) -> Self {
+        self.file = Some(file.to_path_buf());
+        self
+    }
+}
+
+/// Edits to the source that would resolve a diagnostic
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SuggestedFix {
+    /// What the fix does, such as "Rename to '_count'"
+    pub message: String,
+    pub replacements: Vec<Replacement>,
+    pub applicability: Applicability,
+}
+
+impl SuggestedFix {
+    /// A fix replacing the text at one span
+    pub fn replace(
+        message: impl Into<String>,
+        span: Span,
+        text: impl Into<String>,
+        applicability: Applicability,
+    ) -> Self {
         Self {
-            level: DiagnosticLevel::Info,
-            span,
             message: message.into(),
+            replacements: vec![Replacement {
+                span,
+                text: text.into(),
+            }],
+            applicability,
         }
     }
 }
 
+/// How sure a fix is to be what was meant, which decides whether
+/// `typedlua fix` applies it unasked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Applicability {
+    /// Cannot change what the program does, such as widening a type,
+    /// which the emitted Lua never sees
+    MachineApplicable,
+    /// Likely, but a guess at the intent that should be reviewed
+    MaybeIncorrect,
+}
+
+/// Text to put in place of a span of the source; an empty span inserts it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Replacement {
+    pub span: Span,
+    pub text: String,
+}
+
 /// Trait for handling diagnostics
 /// This allows for dependency injection and testing with mock handlers
 pub trait DiagnosticHandler: Send + Sync {
@@ -66,68 +218,422 @@ pub trait DiagnosticHandler: Send + Sync {
     fn get_diagnostics(&self) -> Vec<Diagnostic>;
 }
 
-/// Console-based diagnostic handler that prints to stderr
-pub struct ConsoleDiagnosticHandler {
+/// A sink that receives every diagnostic as it is reported. Embedders
+/// register subscribers to route diagnostics to a terminal, a JSON stream,
+/// an editor, or a test, without parsing rendered text.
+pub trait DiagnosticSubscriber: Send + Sync {
+    fn notify(&self, diagnostic: &Diagnostic);
+}
+
+/// Diagnostic handler that records diagnostics and fans each one out to
+/// its subscribers
+#[derive(Default)]
+pub struct DiagnosticBus {
     diagnostics: Mutex<Vec<Diagnostic>>,
-    pretty: bool,
+    subscribers: RwLock<Vec<Arc<dyn DiagnosticSubscriber>>>,
 }
 
-impl ConsoleDiagnosticHandler {
-    pub fn new(pretty: bool) -> Self {
+impl DiagnosticBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a bus that delivers to the given subscribers
+    pub fn with_subscribers(subscribers: Vec<Arc<dyn DiagnosticSubscriber>>) -> Self {
         Self {
             diagnostics: Mutex::new(Vec::new()),
-            pretty,
+            subscribers: RwLock::new(subscribers),
         }
     }
+
+    /// Deliver every diagnostic reported from now on to `subscriber` too
+    pub fn subscribe(&self, subscriber: Arc<dyn DiagnosticSubscriber>) {
+        self.subscribers.write().unwrap().push(subscriber);
+    }
 }
 
-impl DiagnosticHandler for ConsoleDiagnosticHandler {
+impl DiagnosticHandler for DiagnosticBus {
     fn report(&self, diagnostic: Diagnostic) {
-        let level_str = match diagnostic.level {
-            DiagnosticLevel::Error => "error",
-            DiagnosticLevel::Warning => "warning",
-            DiagnosticLevel::Info => "info",
+        for subscriber in self.subscribers.read().unwrap().iter() {
+            subscriber.notify(&diagnostic);
+        }
+        self.diagnostics.lock().unwrap().push(diagnostic);
+    }
+
+    fn has_errors(&self) -> bool {
+        self.error_count() > 0
+    }
+
+    fn error_count(&self) -> usize {
+        count_level(&self.diagnostics.lock().unwrap(), DiagnosticLevel::Error)
+    }
+
+    fn warning_count(&self) -> usize {
+        count_level(&self.diagnostics.lock().unwrap(), DiagnosticLevel::Warning)
+    }
+
+    fn get_diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics.lock().unwrap().clone()
+    }
+}
+
+fn count_level(diagnostics: &[Diagnostic], level: DiagnosticLevel) -> usize {
+    diagnostics.iter().filter(|d| d.level == level).count()
+}
+
+const BOLD: &str = "1";
+const GUTTER: &str = "1;34";
+
+/// Subscriber that prints diagnostics to stderr. Given the source they
+/// are reported against, each diagnostic comes with a frame of the lines
+/// it points at: its own span underlined in the color of its level, and
+/// its related spans labeled below, each line shown once.
+pub struct TerminalRenderer {
+    /// Whether to color the output
+    pretty: bool,
+    source: Option<(PathBuf, String)>,
+}
+
+impl TerminalRenderer {
+    pub fn new(pretty: bool) -> Self {
+        Self {
+            pretty,
+            source: None,
+        }
+    }
+
+    /// Frame diagnostics with the lines of `source`, the file at `path`
+    pub fn with_source(mut self, path: &Path, source: &str) -> Self {
+        self.source = Some((path.to_path_buf(), source.to_string()));
+        self
+    }
+
+    /// The diagnostic as printed, ending in a newline
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let mut level = diagnostic.level.as_str().to_string();
+        if let Some(code) = &diagnostic.code {
+            level.push_str(&format!("[{}]", code));
+        }
+        let color = match diagnostic.level {
+            DiagnosticLevel::Error => "1;31",
+            DiagnosticLevel::Warning => "1;33",
+            DiagnosticLevel::Info => "1;36",
         };
 
-        if self.pretty {
-            eprintln!(
-                "\x1b[1m{}\x1b[0m at {}: {}",
-                level_str, diagnostic.span, diagnostic.message
+        let Some((path, source)) = &self.source else {
+            let mut output = format!(
+                "{} at {}: {}\n",
+                self.paint(&level, BOLD),
+                diagnostic.span,
+                diagnostic.message
             );
+            self.write_footer(&mut output, diagnostic, 1);
+            return output;
+        };
+
+        let lines: Vec<&str> = source.lines().collect();
+        // Offsets count characters, so columns are found from where each
+        // line starts
+        let mut line_starts = vec![0];
+        for (offset, c) in source.chars().enumerate() {
+            if c == '\n' {
+                line_starts.push(offset + 1);
+            }
+        }
+        let label = |span: Span, mark: char, color, text| {
+            let length = lines
+                .get(span.line.wrapping_sub(1))
+                .map_or(0, |text| text.chars().count());
+            let start = span.column.saturating_sub(1).min(length);
+            let end_line = line_starts.partition_point(|start| *start <= span.end);
+            let end = match line_starts.get(span.line.wrapping_sub(1)) {
+                Some(line_start) if end_line == span.line => span.end - line_start,
+                _ => length,
+            };
+            Label {
+                line: span.line,
+                start,
+                end: end.min(length).max(start + 1),
+                mark,
+                color,
+                text,
+            }
+        };
+
+        // Each line is framed once, with every label on it, in the order
+        // the lines are first pointed at
+        let (here, elsewhere): (Vec<&RelatedSpan>, Vec<&RelatedSpan>) = diagnostic
+            .related
+            .iter()
+            .partition(|related| related.file.as_ref().is_none_or(|file| file == path));
+        let mut frames: Vec<(usize, Vec<Label>)> = Vec::new();
+        let labels = std::iter::once(label(diagnostic.span, '^', color, None)).chain(
+            here.iter()
+                .map(|related| label(related.span, '-', GUTTER, Some(&related.message))),
+        );
+        for label in labels {
+            match frames.iter_mut().find(|(line, _)| *line == label.line) {
+                Some((_, on_line)) => on_line.push(label),
+                None => frames.push((label.line, vec![label])),
+            }
+        }
+        frames.retain(|(line, _)| (1..=lines.len()).contains(line));
+        // Only lines of this file are numbered in the gutter
+        let width = frames
+            .iter()
+            .map(|(line, _)| *line)
+            .max()
+            .unwrap_or_default()
+            .to_string()
+            .len();
+
+        let mut output = format!(
+            "{}: {}\n",
+            self.paint(&level, color),
+            self.paint(&diagnostic.message, BOLD)
+        );
+        output.push_str(&format!(
+            "{}{} {}:{}\n",
+            " ".repeat(width),
+            self.paint("-->", GUTTER),
+            path.display(),
+            diagnostic.span
+        ));
+        output.push_str(&format!("{}\n", self.gutter(width, "")));
+        for (line, labels) in &frames {
+            self.write_frame(&mut output, lines[line - 1], *line, width, labels);
+        }
+        for related in elsewhere {
+            let file = related.file.as_deref().unwrap_or(path);
+            output.push_str(&format!(
+                "{}{} {}:{}: {}\n",
+                " ".repeat(width),
+                self.paint(":::", GUTTER),
+                file.display(),
+                related.span,
+                related.message
+            ));
+        }
+        self.write_footer(&mut output, diagnostic, width);
+        output
+    }
+
+    /// A source line and, below it, the spans of the labels on it marked,
+    /// the diagnostic's own over any it overlaps. Spans running past the
+    /// line are marked to its end. The last label's text follows the
+    /// marks; the others' go on lines of their own below, right to left.
+    fn write_frame(
+        &self,
+        output: &mut String,
+        text: &str,
+        line: usize,
+        width: usize,
+        labels: &[Label],
+    ) {
+        output.push_str(&format!(
+            "{} {}\n",
+            self.gutter(width, &line.to_string()),
+            text
+        ));
+
+        let chars: Vec<char> = text.chars().collect();
+        // Tabs stay tabs so the marks line up under them
+        let padding = |columns: std::ops::Range<usize>| -> String {
+            columns
+                .map(|column| match chars.get(column) {
+                    Some('\t') => '\t',
+                    _ => ' ',
+                })
+                .collect()
+        };
+        let columns = labels
+            .iter()
+            .map(|label| label.end)
+            .max()
+            .unwrap_or_default();
+        let mut owners: Vec<Option<&Label>> = vec![None; columns];
+        for label in labels {
+            for owner in &mut owners[label.start..label.end] {
+                owner.get_or_insert(label);
+            }
+        }
+
+        let mut marks = String::new();
+        let mut column = 0;
+        while column < owners.len() {
+            let owner = owners[column];
+            let run = owners[column..]
+                .iter()
+                .take_while(|other| match (other, owner) {
+                    (Some(other), Some(owner)) => std::ptr::eq(*other, owner),
+                    (None, None) => true,
+                    _ => false,
+                })
+                .count();
+            match owner {
+                Some(label) => {
+                    marks.push_str(&self.paint(&label.mark.to_string().repeat(run), label.color))
+                }
+                None => marks.push_str(&padding(column..column + run)),
+            }
+            column += run;
+        }
+        let mut labeled: Vec<&Label> = labels.iter().filter(|label| label.text.is_some()).collect();
+        labeled.sort_by_key(|label| label.start);
+        // The text of the label starting furthest right follows the marks
+        let rightmost = labels.iter().map(|label| label.start).max();
+        if let Some(label) = labeled
+            .last()
+            .filter(|label| Some(label.start) == rightmost)
+        {
+            let text = format!(" {}", label.text.unwrap_or_default());
+            marks.push_str(&self.paint(&text, label.color));
+            labeled.pop();
+        }
+        output.push_str(&format!("{} {}\n", self.gutter(width, ""), marks));
+        for label in labeled.iter().rev() {
+            output.push_str(&format!(
+                "{} {}{}\n",
+                self.gutter(width, ""),
+                padding(0..label.start),
+                self.paint(label.text.unwrap_or_default(), label.color)
+            ));
+        }
+    }
+
+    /// The notes, then the fixes as help
+    fn write_footer(&self, output: &mut String, diagnostic: &Diagnostic, width: usize) {
+        let notes = diagnostic.notes.iter().map(|note| ("note", note));
+        let help = diagnostic.fixes.iter().map(|fix| ("help", &fix.message));
+        for (kind, text) in notes.chain(help) {
+            output.push_str(&format!(
+                "{} {} {}: {}\n",
+                " ".repeat(width),
+                self.paint("=", GUTTER),
+                self.paint(kind, BOLD),
+                text
+            ));
+        }
+    }
+
+    fn gutter(&self, width: usize, line: &str) -> String {
+        self.paint(&format!("{:>width$} |", line, width = width), GUTTER)
+    }
+
+    fn paint(&self, text: &str, style: &str) -> String {
+        if self.pretty {
+            format!("\x1b[{}m{}\x1b[0m", style, text)
         } else {
-            eprintln!(
-                "{} at {}: {}",
-                level_str, diagnostic.span, diagnostic.message
-            );
+            text.to_string()
         }
+    }
+}
+
+/// A span marked in a frame, with `^` for the diagnostic's own and `-` for
+/// related ones. Its columns count characters from 0, `end` exclusive.
+struct Label<'a> {
+    line: usize,
+    start: usize,
+    end: usize,
+    mark: char,
+    color: &'a str,
+    text: Option<&'a str>,
+}
+
+impl DiagnosticSubscriber for TerminalRenderer {
+    fn notify(&self, diagnostic: &Diagnostic) {
+        eprint!("{}", self.render(diagnostic));
+    }
+}
+
+/// Subscriber that writes each diagnostic as one line of JSON
+pub struct JsonDiagnosticWriter<W: Write + Send> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write + Send> JsonDiagnosticWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner().unwrap()
+    }
+}
+
+impl<W: Write + Send> DiagnosticSubscriber for JsonDiagnosticWriter<W> {
+    fn notify(&self, diagnostic: &Diagnostic) {
+        let mut writer = self.writer.lock().unwrap();
+        // A closed stream must not abort compilation
+        let _ = serde_json::to_writer(&mut *writer, diagnostic);
+        let _ = writeln!(writer);
+    }
+}
+
+/// One diagnostic as written by `JsonDiagnosticHandler`
+#[derive(Serialize)]
+struct DiagnosticRecord<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<&'a Path>,
+    #[serde(flatten)]
+    diagnostic: &'a Diagnostic,
+}
+
+/// Diagnostic handler that records diagnostics and writes each one as a
+/// line of JSON as it is reported, for editors and CI tools. Records carry
+/// the file the handler reports for, when it is given one.
+pub struct JsonDiagnosticHandler<W: Write + Send> {
+    file: Option<PathBuf>,
+    writer: Mutex<W>,
+    diagnostics: Mutex<Vec<Diagnostic>>,
+}
+
+impl<W: Write + Send> JsonDiagnosticHandler<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            file: None,
+            writer: Mutex::new(writer),
+            diagnostics: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Name `file` in every record written
+    pub fn with_file(mut self, file: &Path) -> Self {
+        self.file = Some(file.to_path_buf());
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner().unwrap()
+    }
+}
 
+impl<W: Write + Send> DiagnosticHandler for JsonDiagnosticHandler<W> {
+    fn report(&self, diagnostic: Diagnostic) {
+        let record = DiagnosticRecord {
+            file: self.file.as_deref(),
+            diagnostic: &diagnostic,
+        };
+        let mut writer = self.writer.lock().unwrap();
+        // A closed stream must not abort compilation
+        let _ = serde_json::to_writer(&mut *writer, &record);
+        let _ = writeln!(writer);
+        drop(writer);
         self.diagnostics.lock().unwrap().push(diagnostic);
     }
 
     fn has_errors(&self) -> bool {
-        self.diagnostics
-            .lock()
-            .unwrap()
-            .iter()
-            .any(|d| d.level == DiagnosticLevel::Error)
+        self.error_count() > 0
     }
 
     fn error_count(&self) -> usize {
-        self.diagnostics
-            .lock()
-            .unwrap()
-            .iter()
-            .filter(|d| d.level == DiagnosticLevel::Error)
-            .count()
+        count_level(&self.diagnostics.lock().unwrap(), DiagnosticLevel::Error)
     }
 
     fn warning_count(&self) -> usize {
-        self.diagnostics
-            .lock()
-            .unwrap()
-            .iter()
-            .filter(|d| d.level == DiagnosticLevel::Warning)
-            .count()
+        count_level(&self.diagnostics.lock().unwrap(), DiagnosticLevel::Warning)
     }
 
     fn get_diagnostics(&self) -> Vec<Diagnostic> {
@@ -135,8 +641,27 @@ impl DiagnosticHandler for ConsoleDiagnosticHandler {
     }
 }
 
+/// Subscriber that hands each diagnostic to a callback, such as a language
+/// server publishing them to the editor
+pub struct CallbackSubscriber<F: Fn(&Diagnostic) + Send + Sync> {
+    callback: F,
+}
+
+impl<F: Fn(&Diagnostic) + Send + Sync> CallbackSubscriber<F> {
+    pub fn new(callback: F) -> Self {
+        Self { callback }
+    }
+}
+
+impl<F: Fn(&Diagnostic) + Send + Sync> DiagnosticSubscriber for CallbackSubscriber<F> {
+    fn notify(&self, diagnostic: &Diagnostic) {
+        (self.callback)(diagnostic);
+    }
+}
+
 /// Collecting diagnostic handler for testing
-/// Collects all diagnostics without printing
+/// Collects all diagnostics without printing. It can also be subscribed to
+/// a `DiagnosticBus` to capture what the bus delivers.
 pub struct CollectingDiagnosticHandler {
     diagnostics: Mutex<Vec<Diagnostic>>,
 }
@@ -191,6 +716,12 @@ impl DiagnosticHandler for CollectingDiagnosticHandler {
     }
 }
 
+impl DiagnosticSubscriber for CollectingDiagnosticHandler {
+    fn notify(&self, diagnostic: &Diagnostic) {
+        self.report(diagnostic.clone());
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -230,4 +761,137 @@ mod tests {
         assert!(!handler.has_errors());
         assert_eq!(handler.error_count(), 0);
     }
+
+    #[test]
+    fn test_bus_delivers_to_every_subscriber() {
+        let collector = Arc::new(CollectingDiagnosticHandler::new());
+        let published = Arc::new(Mutex::new(Vec::new()));
+        let sink = published.clone();
+        let bus = DiagnosticBus::with_subscribers(vec![collector.clone()]);
+        bus.subscribe(Arc::new(CallbackSubscriber::new(move |d: &Diagnostic| {
+            sink.lock().unwrap().push(d.message.clone());
+        })));
+
+        let span = Span::new(0, 5, 1, 1);
+        bus.error(span, "Error 1");
+        bus.warning(span, "Warning 1");
+
+        assert_eq!(bus.error_count(), 1);
+        assert_eq!(bus.warning_count(), 1);
+        assert_eq!(collector.get_diagnostics(), bus.get_diagnostics());
+        assert_eq!(*published.lock().unwrap(), vec!["Error 1", "Warning 1"]);
+    }
+
+    #[test]
+    fn test_terminal_renderer_frames_spans() {
+        let source = "local count = 0\nlocal total: string = count\n";
+        let renderer = TerminalRenderer::new(false).with_source(Path::new("src/main.tl"), source);
+        let diagnostic = Diagnostic::error(
+            Span::new(38, 43, 2, 23),
+            "Type 'number' is not assignable to type 'string'",
+        )
+        .with_code("TL2001")
+        .with_related(vec![
+            RelatedSpan::new(Span::new(6, 11, 1, 7), "'count' is declared here"),
+            RelatedSpan::new(Span::new(0, 0, 1, 1), "'count' is exported here")
+                .in_file(Path::new("src/util.tl")),
+        ])
+        .with_note("'total' is annotated as 'string'");
+
+        assert_eq!(
+            renderer.render(&diagnostic),
+            "error[TL2001]: Type 'number' is not assignable to type 'string'\n \
+             --> src/main.tl:2:23\n  \
+             |\n\
+             2 | local total: string = count\n  \
+             |                       ^^^^^\n\
+             1 | local count = 0\n  \
+             |       ----- 'count' is declared here\n \
+             ::: src/util.tl:1:1: 'count' is exported here\n  \
+             = note: 'total' is annotated as 'string'\n"
+        );
+        assert_eq!(
+            TerminalRenderer::new(false).render(&diagnostic),
+            "error[TL2001] at 2:23: Type 'number' is not assignable to type 'string'\n  \
+             = note: 'total' is annotated as 'string'\n"
+        );
+    }
+
+    #[test]
+    fn test_terminal_renderer_frames_each_line_once() {
+        let source = "local naïve = naïve + 1\n";
+        let renderer = TerminalRenderer::new(false).with_source(Path::new("main.tl"), source);
+        let diagnostic = Diagnostic::error(Span::new(14, 19, 1, 15), "Cannot read 'naïve' here")
+            .with_related(vec![
+                RelatedSpan::new(Span::new(6, 11, 1, 7), "'naïve' is declared here"),
+                RelatedSpan::new(Span::new(0, 5, 1200, 1), "'naïve' is exported here")
+                    .in_file(Path::new("lib.tl")),
+            ]);
+
+        // One frame for the line, marked as wide as the name, with the
+        // gutter sized to this file's lines
+        assert_eq!(
+            renderer.render(&diagnostic),
+            "error: Cannot read 'naïve' here\n \
+             --> main.tl:1:15\n  \
+             |\n\
+             1 | local naïve = naïve + 1\n  \
+             |       -----   ^^^^^\n  \
+             |       'naïve' is declared here\n \
+             ::: lib.tl:1200:1: 'naïve' is exported here\n"
+        );
+
+        let diagnostic =
+            Diagnostic::error(Span::new(6, 11, 1, 7), "'naïve' is never read").with_related(vec![
+                RelatedSpan::new(Span::new(14, 23, 1, 15), "shadowed here"),
+            ]);
+        assert_eq!(
+            renderer.render(&diagnostic),
+            "error: 'naïve' is never read\n \
+             --> main.tl:1:7\n  \
+             |\n\
+             1 | local naïve = naïve + 1\n  \
+             |       ^^^^^   --------- shadowed here\n"
+        );
+    }
+
+    #[test]
+    fn test_json_writer() {
+        let writer = JsonDiagnosticWriter::new(Vec::new());
+        writer.notify(&Diagnostic::warning(Span::new(4, 9, 2, 3), "Unused"));
+
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(
+            output,
+            "{\"level\":\"warning\",\"span\":{\"start\":4,\"end\":9,\"line\":2,\"column\":3},\"message\":\"Unused\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_json_handler_writes_records() {
+        let handler = JsonDiagnosticHandler::new(Vec::new()).with_file(Path::new("src/main.tl"));
+        let span = Span::new(6, 11, 1, 7);
+        let fix = SuggestedFix::replace(
+            "Rename to '_count'",
+            Span::new(6, 6, 1, 7),
+            "_",
+            Applicability::MaybeIncorrect,
+        );
+        handler.report(
+            Diagnostic::warning(span, "'count' is declared but never read")
+                .with_code("unused-local")
+                .with_fixes(vec![fix]),
+        );
+        assert_eq!(handler.warning_count(), 1);
+
+        let output = String::from_utf8(handler.into_inner()).unwrap();
+        let record: serde_json::Value = serde_json::from_str(output.trim_end()).unwrap();
+        assert_eq!(record["file"], "src/main.tl");
+        assert_eq!(record["code"], "unused-local");
+        assert_eq!(record["level"], "warning");
+        assert_eq!(record["span"]["line"], 1);
+        assert_eq!(record["fixes"][0]["replacements"][0]["text"], "_");
+        assert_eq!(record["fixes"][0]["applicability"], "maybeIncorrect");
+        assert!(record.get("related").is_none());
+    }
 }
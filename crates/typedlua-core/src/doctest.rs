@@ -0,0 +1,186 @@
+//! Checking the code examples in doc comments (`typedlua doctest`), so
+//! they keep compiling as the code they describe changes
+
+use crate::build::compile_source;
+use crate::config::CompilerConfig;
+use crate::diagnostics::{
+    CollectingDiagnosticHandler, Diagnostic, DiagnosticHandler, DiagnosticLevel,
+};
+use crate::span::Span;
+use std::sync::Arc;
+
+/// A fenced code example in a `///` doc comment
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocExample {
+    pub code: String,
+    /// The whole doc comment holding the example
+    pub span: Span,
+    /// False for examples fenced as `no_run`, which are only checked
+    pub run: bool,
+}
+
+/// The examples in a module's doc comments. Fences without a language, or
+/// marked `typedlua` or `tl`, hold examples; `ignore` and other languages
+/// are skipped.
+pub fn extract_examples(source: &str) -> Vec<DocExample> {
+    let mut examples = Vec::new();
+    // Examples of the doc comment being read, and its span so far
+    let mut comment: Option<Span> = None;
+    let mut pending: Vec<(String, bool)> = Vec::new();
+    // Inside a fence, the example's code and whether it runs, or `None`
+    // for a skipped fence
+    let mut fence: Option<Option<(String, bool)>> = None;
+    let mut offset = 0;
+
+    // A blank line after the source ends the last comment
+    for (index, line) in source.split('\n').chain([""]).enumerate() {
+        let length = line.chars().count();
+        let trimmed = line.trim_start();
+        match trimmed.strip_prefix("///") {
+            Some(text) => {
+                let column = length - trimmed.chars().count() + 1;
+                let end = offset + length;
+                comment = Some(match comment {
+                    Some(span) => Span::new(span.start, end, span.line, span.column),
+                    None => Span::new(offset + column - 1, end, index + 1, column),
+                });
+                let text = text.strip_prefix(' ').unwrap_or(text);
+                match (&mut fence, text.trim().strip_prefix("```")) {
+                    (Some(example), Some(_)) => {
+                        pending.extend(example.take());
+                        fence = None;
+                    }
+                    (Some(Some((code, _))), None) => {
+                        code.push_str(text);
+                        code.push('\n');
+                    }
+                    (Some(None), None) => {}
+                    (None, Some(info)) => {
+                        fence = Some(fence_runs(info).map(|run| (String::new(), run)));
+                    }
+                    (None, None) => {}
+                }
+            }
+            None => {
+                // An unclosed fence ends with its comment
+                if let Some(example) = fence.take() {
+                    pending.extend(example);
+                }
+                if let Some(span) = comment.take() {
+                    examples.extend(pending.drain(..).map(|(code, run)| DocExample {
+                        code,
+                        span,
+                        run,
+                    }));
+                }
+            }
+        }
+        offset += length + 1;
+    }
+    examples
+}
+
+/// Whether a fence with this info string holds an example that runs, one
+/// that is only checked, or no example at all
+fn fence_runs(info: &str) -> Option<bool> {
+    let mut words = info
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|word| !word.is_empty())
+        .peekable();
+    if let Some(&language) = words.peek() {
+        if matches!(language, "typedlua" | "tl") {
+            words.next();
+        }
+    }
+    let mut run = true;
+    for word in words {
+        match word {
+            "no_run" => run = false,
+            _ => return None,
+        }
+    }
+    Some(run)
+}
+
+/// Check an example as code at the end of its module, so the module's
+/// declarations and exports are in scope. Returns the Lua of the module
+/// followed by the example, or the example's errors reported at its doc
+/// comment. The module itself is expected to compile.
+pub fn check_example(
+    module: &str,
+    example: &DocExample,
+    config: Arc<CompilerConfig>,
+) -> Result<String, Vec<Diagnostic>> {
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let source = format!("{}\n{}", module, example.code);
+    let result = compile_source(&source, config, handler.clone());
+
+    let module_lines = module.split('\n').count();
+    let mut errors: Vec<Diagnostic> = handler
+        .get_diagnostics()
+        .into_iter()
+        .filter(|d| d.level == DiagnosticLevel::Error)
+        .map(|d| {
+            let line = d.span.line.saturating_sub(module_lines);
+            let error = Diagnostic::error(
+                example.span,
+                format!("Doc example line {}: {}", line, d.message),
+            );
+            match d.code {
+                Some(code) => error.with_code(code),
+                None => error,
+            }
+        })
+        .collect();
+    match result {
+        Ok(compiled) if errors.is_empty() => Ok(compiled.lua),
+        Ok(_) => Err(errors),
+        Err(error) => {
+            if errors.is_empty() {
+                errors.push(Diagnostic::error(
+                    example.span,
+                    format!("Doc example: {}", error),
+                ));
+            }
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODULE: &str = "/// Adds two numbers\n///\n/// ```\n/// const sum: number = add(1, 2)\n/// ```\n///\n/// ```lua\n/// print(add)\n/// ```\nexport function add(a: number, b: number): number\n    return a + b\nend\n\n/// ```typedlua no_run\n/// const s: string = add(1, 2)\n/// ```\n/// ```ignore\n/// not checked\n/// ```\nexport const name = \"math\"\n";
+
+    #[test]
+    fn test_extract_examples() {
+        let examples = extract_examples(MODULE);
+        assert_eq!(examples.len(), 2);
+
+        assert_eq!(examples[0].code, "const sum: number = add(1, 2)\n");
+        assert!(examples[0].run);
+        assert_eq!(examples[0].span.line, 1);
+        assert_eq!(examples[0].span.start, 0);
+        let first_comment = MODULE.find("\nexport function").unwrap();
+        assert_eq!(examples[0].span.end, first_comment);
+
+        assert_eq!(examples[1].code, "const s: string = add(1, 2)\n");
+        assert!(!examples[1].run);
+        assert_eq!(examples[1].span.line, 14);
+    }
+
+    #[test]
+    fn test_check_example() {
+        let config = Arc::new(CompilerConfig::default());
+        let examples = extract_examples(MODULE);
+
+        let lua = check_example(MODULE, &examples[0], config.clone()).expect("Example failed");
+        assert!(lua.contains("local sum = add(1, 2)"));
+
+        let errors = check_example(MODULE, &examples[1], config).expect_err("Example passed");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].span, examples[1].span);
+        assert!(errors[0].message.starts_with("Doc example line 1: "));
+    }
+}
@@ -14,6 +14,12 @@ pub enum CompilationError {
     #[error("Type checking failed with {0} errors")]
     TypeErrors(usize),
 
+    #[error("Linting failed with {0} errors")]
+    LintErrors(usize),
+
+    #[error("Plugins failed with {0} errors")]
+    PluginErrors(usize),
+
     #[error("Code generation failed: {0}")]
     CodeGenError(String),
 
@@ -36,6 +42,24 @@ pub enum ResolutionError {
     AmbiguousResolution(String),
 }
 
+#[derive(Debug, Error)]
+pub enum RenameError {
+    #[error("No name to rename at offset {0}")]
+    NoName(usize),
+
+    #[error("'{0}' is not a valid name")]
+    InvalidName(String),
+
+    #[error("'{name}' is already declared in {path}")]
+    NameTaken { name: String, path: String },
+
+    #[error("{0} does not parse")]
+    ParseFailed(String),
+
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
 #[derive(Debug, Error)]
 pub enum LexerError {
     #[error("Unexpected character: {0}")]
@@ -54,6 +78,18 @@ pub enum LexerError {
     InvalidEscape(String),
 }
 
+impl LexerError {
+    /// The stable code of the error, which `typedlua explain` describes
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexerError::UnexpectedCharacter(_) => "TL1001",
+            LexerError::UnterminatedComment => "TL1002",
+            LexerError::UnterminatedString => "TL1004",
+            LexerError::InvalidNumber(_) | LexerError::InvalidEscape(_) => "TL1005",
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum ParserError {
     #[error("Unexpected token: expected {expected}, found {found}")]
@@ -88,4 +124,350 @@ pub enum TypeCheckError {
 
     #[error("Invalid operation: {0}")]
     InvalidOperation(String),
+
+    #[error("Cannot instantiate abstract class '{0}'")]
+    AbstractInstantiation(String),
+
+    #[error("Abstract method '{0}' cannot have an implementation")]
+    AbstractMethodWithBody(String),
+
+    #[error("Abstract method '{method}' can only appear in an abstract class, but '{class}' is not abstract")]
+    AbstractMethodInConcreteClass { method: String, class: String },
+
+    #[error("Method '{0}' is missing an implementation")]
+    MissingMethodBody(String),
+
+    #[error("Non-abstract class '{class}' does not implement inherited abstract method '{method}' from '{base}'")]
+    MissingAbstractImplementation {
+        class: String,
+        method: String,
+        base: String,
+    },
+
+    #[error("'{name}' is not available on platform '{platform}'")]
+    UnavailableOnPlatform { name: String, platform: String },
+
+    #[error("'{name}' is not available on target {target}; it requires {available}")]
+    UnavailableOnTarget {
+        name: String,
+        target: String,
+        available: String,
+    },
+
+    #[error("Property '{property}' does not exist on type '{type_name}'")]
+    UnknownProperty { property: String, type_name: String },
+
+    #[error("Type '{type_name}' cannot be used with 'using': it has no {expected}")]
+    NotClosable { type_name: String, expected: String },
+
+    #[error("'{statement}' cannot leave the scope of a 'using' declaration on target {target}")]
+    UsingScopeEscape { statement: String, target: String },
+
+    #[error("Property '{property}' of type '{property_type}' is not assignable to string index type '{index_type}'")]
+    IndexSignatureConflict {
+        property: String,
+        property_type: String,
+        index_type: String,
+    },
+
+    #[error("Number index type '{number_type}' is not assignable to string index type '{string_type}'")]
+    IndexSignatureMismatch {
+        number_type: String,
+        string_type: String,
+    },
+
+    #[error("All declarations of '{0}' must have identical type parameters")]
+    MergedTypeParameterMismatch(String),
+
+    #[error("Subsequent declarations of property '{property}' must have the same type: expected '{expected}', found '{actual}'")]
+    MergedPropertyMismatch {
+        property: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("A module using 'export =' cannot have other value exports")]
+    ExportEqualsConflict,
+
+    #[error("'export =' is only allowed at the top level of a module")]
+    ExportEqualsInNamespace,
+
+    #[error("'{0}' cannot be used as a value because it was imported using 'import type'")]
+    TypeOnlyImportUsedAsValue(String),
+
+    #[error("Cannot import '{0}': it is plain Lua without a declaration file (.d.tl)")]
+    UntypedLuaImport(String),
+
+    #[error("Declaration file {path} could not be loaded: {message}")]
+    InvalidDeclarationFile { path: String, message: String },
+
+    #[error("Module '{module}' does not export '{name}'")]
+    NotExported { module: String, name: String },
+
+    #[error("The 'self' of type '{actual}' is not assignable to the method's 'self' of type '{expected}'")]
+    SelfTypeMismatch { expected: String, actual: String },
+
+    #[error("Object literal is missing required properties of '{type_name}': {properties}")]
+    MissingProperties { type_name: String, properties: String },
+
+    #[error("Pipeline stage '{stage}' expects '{expected}', but the previous stage produces '{actual}'")]
+    PipelineStageMismatch {
+        stage: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("Pipeline stage '{stage}' is not a function; it has type '{actual}'")]
+    PipelineStageNotCallable { stage: String, actual: String },
+
+    #[error("'{0}' is possibly nil")]
+    PossiblyNil(String),
+
+    #[error("Cannot find parameter '{0}' named by the assertion signature")]
+    UnknownAssertedParameter(String),
+
+    #[error("Cannot assign to '{0}' because it is a read-only property")]
+    ReadonlyProperty(String),
+
+    #[error("Index signature of read-only type '{0}' only permits reading")]
+    ReadonlyIndex(String),
+
+    #[error("Operator '{operator}' cannot be applied to types '{left}' and '{right}'")]
+    OperatorNotSupported {
+        operator: String,
+        left: String,
+        right: String,
+    },
+
+    #[error("Operator '{operator}' cannot be applied to type '{operand}'")]
+    UnaryOperatorNotSupported { operator: String, operand: String },
+
+    #[error("Operand of '{operator}' has type '{operand}', which may have no integer representation at runtime")]
+    FloatToIntegerConversion { operator: String, operand: String },
+
+    #[error("Integer '{0}' by zero raises an error at runtime")]
+    IntegerDivisionByZero(String),
+
+    #[error("Cannot find an enclosing loop labeled '{0}'")]
+    UnknownLoopLabel(String),
+
+    #[error("Label '{0}' is already used by an enclosing loop")]
+    DuplicateLoopLabel(String),
+
+    #[error("Method '{0}' is marked 'override' but does not override a base class or interface method")]
+    NothingToOverride(String),
+
+    #[error("Method '{method}' overrides a method of '{base}' and must be marked 'override'")]
+    MissingOverride { method: String, base: String },
+
+    #[error("Method '{method}' is not compatible with the method it overrides in '{base}': expected {expected}, found {actual}")]
+    OverrideSignatureMismatch {
+        method: String,
+        base: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("'{member}' is an instance member of '{class}' and cannot be accessed through the class")]
+    InstanceMemberThroughClass { member: String, class: String },
+
+    #[error("'{member}' is a static member of '{class}'; access it as '{class}.{member}'")]
+    StaticMemberThroughInstance { member: String, class: String },
+
+    #[error("Class constant '{0}' must be initialized")]
+    UninitializedClassConstant(String),
+
+    #[error("'{0}' is not a class and cannot be used as a mixin")]
+    InvalidMixin(String),
+
+    #[error("Mixins '{first}' and '{second}' both provide '{member}' with different types; declare it in the class to resolve the conflict")]
+    MixinMemberConflict {
+        member: String,
+        first: String,
+        second: String,
+    },
+
+    #[error("'#' counts the array part, but '{0}' has only named fields")]
+    LengthWithoutArrayPart(String),
+
+    #[error("ipairs only visits the array part, but '{0}' has only named fields; use pairs")]
+    IpairsWithoutArrayPart(String),
+
+    #[error("Type '{argument}' does not satisfy the constraint '{constraint}' of type parameter '{parameter}'")]
+    ConstraintNotSatisfied {
+        argument: String,
+        constraint: String,
+        parameter: String,
+    },
+
+    #[error("Generic type '{name}' expects {expected} type argument(s), got {actual}")]
+    TypeArgumentCount {
+        name: String,
+        expected: String,
+        actual: usize,
+    },
+
+    #[error("Type parameter '{0}' has no default but follows one that does")]
+    RequiredTypeParameterAfterDefault(String),
+
+    #[error("Enum member '{member}' conflicts with the generated '{enum_name}.{member}'")]
+    ReservedEnumMember { enum_name: String, member: String },
+
+    #[error("@weak expects \"k\", \"v\" or \"kv\"")]
+    InvalidWeakMode,
+
+    #[error("@deprecated expects at most one message string")]
+    InvalidDeprecation,
+
+    #[error("@schema expects 'version = N', with N a positive integer")]
+    InvalidSchema,
+
+    #[error("@migration expects a @schema record and the version it migrates from")]
+    InvalidMigration,
+
+    #[error("'{0}' is not a @schema record")]
+    UnknownSchema(String),
+
+    #[error("Migration '{0}' must take the saved data as its only parameter")]
+    MigrationParameters(String),
+
+    #[error("'{schema}' is at version {version}, so has no version {from} to migrate from")]
+    MigrationOutOfRange {
+        schema: String,
+        version: i64,
+        from: i64,
+    },
+
+    #[error("'{schema}' already has a migration from version {from}")]
+    DuplicateMigration { schema: String, from: i64 },
+
+    #[error("'{schema}' is at version {version} but has no migration from version {from}")]
+    MissingMigration {
+        schema: String,
+        version: i64,
+        from: i64,
+    },
+
+    #[error("Expected {expected} argument(s), got {actual}")]
+    ArgumentCount { expected: String, actual: usize },
+
+    #[error("No overload of '{callee}' matches arguments ({arguments})")]
+    NoMatchingOverload { callee: String, arguments: String },
+
+    #[error("Expression produces {values} value(s), but {names} names are bound")]
+    TooManyValueNames { values: usize, names: usize },
+
+    #[error("Invalid format specifier '{0}'")]
+    InvalidFormatSpecifier(String),
+
+    #[error("Format specifier '{specifier}' expects {expected}, found {found}")]
+    FormatSpecifierMismatch {
+        specifier: String,
+        expected: String,
+        found: String,
+    },
+
+    #[error("Data modules hold only types and constant tables, not {0}")]
+    NotData(String),
+
+    #[error("Data '{0}' needs a declared type to be validated against")]
+    UntypedData(String),
+
+    #[error("{path}: expected {expected}, found {found}")]
+    DataMismatch {
+        path: String,
+        expected: String,
+        found: String,
+    },
+
+    #[error("{path}: not a field of {type_name}")]
+    UnknownDataField { path: String, type_name: String },
+
+    #[error("{path}: missing field '{field}'")]
+    MissingDataField { path: String, field: String },
+
+    #[error("'@{0}' is not allowed: noSuppressions is set")]
+    SuppressionForbidden(String),
+}
+
+impl TypeCheckError {
+    /// The stable code of the error, which `typedlua explain` describes
+    pub fn code(&self) -> &'static str {
+        match self {
+            TypeCheckError::TypeMismatch { .. } => "TL2001",
+            TypeCheckError::UndefinedVariable { .. } => "TL2002",
+            TypeCheckError::UndefinedType { .. } => "TL2003",
+            TypeCheckError::ConstReassignment { .. } => "TL2004",
+            TypeCheckError::DuplicateDeclaration { .. } => "TL2005",
+            TypeCheckError::InvalidOperation { .. } => "TL2006",
+            TypeCheckError::AbstractInstantiation { .. } => "TL2007",
+            TypeCheckError::AbstractMethodWithBody { .. } => "TL2008",
+            TypeCheckError::AbstractMethodInConcreteClass { .. } => "TL2009",
+            TypeCheckError::MissingMethodBody { .. } => "TL2010",
+            TypeCheckError::MissingAbstractImplementation { .. } => "TL2011",
+            TypeCheckError::UnavailableOnPlatform { .. } => "TL2012",
+            TypeCheckError::UnavailableOnTarget { .. } => "TL2013",
+            TypeCheckError::UnknownProperty { .. } => "TL2014",
+            TypeCheckError::NotClosable { .. } => "TL2015",
+            TypeCheckError::UsingScopeEscape { .. } => "TL2016",
+            TypeCheckError::IndexSignatureConflict { .. } => "TL2017",
+            TypeCheckError::IndexSignatureMismatch { .. } => "TL2018",
+            TypeCheckError::MergedTypeParameterMismatch { .. } => "TL2019",
+            TypeCheckError::MergedPropertyMismatch { .. } => "TL2020",
+            TypeCheckError::ExportEqualsConflict => "TL2021",
+            TypeCheckError::ExportEqualsInNamespace => "TL2022",
+            TypeCheckError::TypeOnlyImportUsedAsValue { .. } => "TL2023",
+            TypeCheckError::UntypedLuaImport { .. } => "TL2024",
+            TypeCheckError::InvalidDeclarationFile { .. } => "TL2025",
+            TypeCheckError::NotExported { .. } => "TL2026",
+            TypeCheckError::SelfTypeMismatch { .. } => "TL2027",
+            TypeCheckError::MissingProperties { .. } => "TL2028",
+            TypeCheckError::PipelineStageMismatch { .. } => "TL2029",
+            TypeCheckError::PipelineStageNotCallable { .. } => "TL2030",
+            TypeCheckError::PossiblyNil { .. } => "TL2031",
+            TypeCheckError::UnknownAssertedParameter { .. } => "TL2032",
+            TypeCheckError::ReadonlyProperty { .. } => "TL2033",
+            TypeCheckError::ReadonlyIndex { .. } => "TL2034",
+            TypeCheckError::OperatorNotSupported { .. } => "TL2035",
+            TypeCheckError::UnaryOperatorNotSupported { .. } => "TL2036",
+            TypeCheckError::FloatToIntegerConversion { .. } => "TL2037",
+            TypeCheckError::IntegerDivisionByZero { .. } => "TL2038",
+            TypeCheckError::UnknownLoopLabel { .. } => "TL2039",
+            TypeCheckError::DuplicateLoopLabel { .. } => "TL2040",
+            TypeCheckError::NothingToOverride { .. } => "TL2041",
+            TypeCheckError::MissingOverride { .. } => "TL2042",
+            TypeCheckError::OverrideSignatureMismatch { .. } => "TL2043",
+            TypeCheckError::InstanceMemberThroughClass { .. } => "TL2044",
+            TypeCheckError::StaticMemberThroughInstance { .. } => "TL2045",
+            TypeCheckError::UninitializedClassConstant { .. } => "TL2046",
+            TypeCheckError::InvalidMixin { .. } => "TL2047",
+            TypeCheckError::MixinMemberConflict { .. } => "TL2048",
+            TypeCheckError::LengthWithoutArrayPart { .. } => "TL2049",
+            TypeCheckError::IpairsWithoutArrayPart { .. } => "TL2050",
+            TypeCheckError::ConstraintNotSatisfied { .. } => "TL2051",
+            TypeCheckError::TypeArgumentCount { .. } => "TL2052",
+            TypeCheckError::RequiredTypeParameterAfterDefault { .. } => "TL2053",
+            TypeCheckError::ReservedEnumMember { .. } => "TL2054",
+            TypeCheckError::InvalidWeakMode => "TL2055",
+            TypeCheckError::InvalidDeprecation => "TL2056",
+            TypeCheckError::InvalidSchema => "TL2057",
+            TypeCheckError::InvalidMigration => "TL2058",
+            TypeCheckError::UnknownSchema { .. } => "TL2059",
+            TypeCheckError::MigrationParameters { .. } => "TL2060",
+            TypeCheckError::MigrationOutOfRange { .. } => "TL2061",
+            TypeCheckError::DuplicateMigration { .. } => "TL2062",
+            TypeCheckError::MissingMigration { .. } => "TL2063",
+            TypeCheckError::ArgumentCount { .. } => "TL2064",
+            TypeCheckError::NoMatchingOverload { .. } => "TL2065",
+            TypeCheckError::TooManyValueNames { .. } => "TL2066",
+            TypeCheckError::InvalidFormatSpecifier { .. } => "TL2067",
+            TypeCheckError::FormatSpecifierMismatch { .. } => "TL2068",
+            TypeCheckError::NotData { .. } => "TL2069",
+            TypeCheckError::UntypedData { .. } => "TL2070",
+            TypeCheckError::DataMismatch { .. } => "TL2071",
+            TypeCheckError::UnknownDataField { .. } => "TL2072",
+            TypeCheckError::MissingDataField { .. } => "TL2073",
+            TypeCheckError::SuppressionForbidden(_) => "TL2074",
+        }
+    }
 }
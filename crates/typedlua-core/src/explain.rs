@@ -0,0 +1,58 @@
+//! Extended explanations of diagnostic codes (`typedlua explain TL2001`):
+//! what each code means, an example and the usual fixes, so diagnostic
+//! messages themselves can stay short
+
+const EXPLANATIONS: &str = include_str!("explain/codes.md");
+
+/// The explanation of one diagnostic code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Explanation {
+    pub code: &'static str,
+    pub title: &'static str,
+    /// Markdown: a description, usually an example and common fixes
+    pub text: &'static str,
+}
+
+/// Every explained code, in order
+pub fn explanations() -> impl Iterator<Item = Explanation> {
+    EXPLANATIONS.split("\n## ").skip(1).filter_map(|section| {
+        let (heading, text) = section.split_once('\n')?;
+        let (code, title) = heading.split_once(": ")?;
+        Some(Explanation {
+            code,
+            title,
+            text: text.trim(),
+        })
+    })
+}
+
+/// The explanation of a code, matched case-insensitively, so `tl2001`
+/// finds `TL2001`
+pub fn explain(code: &str) -> Option<Explanation> {
+    explanations().find(|explanation| explanation.code.eq_ignore_ascii_case(code.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::TypeCheckError;
+
+    #[test]
+    fn test_explain_codes() {
+        let explanation = explain("tl2031").expect("TL2031 is explained");
+        assert_eq!(explanation.code, "TL2031");
+        assert_eq!(explanation.title, "Possibly nil");
+        assert!(explanation.text.contains("```lua"));
+        assert!(explain("TL9999").is_none());
+
+        let error = TypeCheckError::PossiblyNil("user".to_string());
+        assert_eq!(error.code(), "TL2031");
+
+        let codes: Vec<&str> = explanations().map(|explanation| explanation.code).collect();
+        let mut sorted = codes.clone();
+        sorted.sort_unstable();
+        sorted.dedup();
+        assert_eq!(codes, sorted, "codes are listed once, in order");
+        assert!(codes.contains(&"TL2073"));
+    }
+}
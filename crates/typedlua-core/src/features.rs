@@ -0,0 +1,76 @@
+//! The language features each Lua target provides. The checker and code
+//! generator ask the target whether it supports a feature rather than
+//! matching on versions, so a new target only needs a column here.
+
+use crate::config::LuaVersion;
+
+/// A language feature that only some Lua targets provide
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LuaFeature {
+    /// Numbers split into integer and float subtypes
+    IntegerSubtype,
+    /// 64-bit integer literals with `LL`/`ULL` suffixes
+    Int64Literals,
+    /// `goto` and `::label::`
+    Goto,
+    /// A native `continue` statement
+    Continue,
+    /// The `&`, `|`, `~`, `<<`, `>>` operators
+    BitwiseOperators,
+    /// A library of bitwise functions the operators can compile to:
+    /// `bit32`, or `bit` on LuaJIT
+    BitLibrary,
+    /// The `__close` metamethod
+    CloseMetamethod,
+    /// `<const>` and `<close>` local attributes
+    LocalAttributes,
+    /// `table.unpack`, rather than the global `unpack`
+    TableUnpack,
+}
+
+impl LuaVersion {
+    /// Whether code for this target may use `feature`
+    pub fn supports(self, feature: LuaFeature) -> bool {
+        use LuaVersion::*;
+        match feature {
+            LuaFeature::IntegerSubtype => matches!(self, Lua53 | Lua54),
+            LuaFeature::Int64Literals => matches!(self, LuaJIT),
+            LuaFeature::Goto => matches!(self, Lua52 | Lua53 | Lua54 | LuaJIT),
+            LuaFeature::Continue => matches!(self, Luau),
+            LuaFeature::BitwiseOperators => matches!(self, Lua53 | Lua54),
+            LuaFeature::BitLibrary => matches!(self, Lua52 | LuaJIT | Luau),
+            LuaFeature::CloseMetamethod => matches!(self, Lua54),
+            LuaFeature::LocalAttributes => matches!(self, Lua54),
+            LuaFeature::TableUnpack => matches!(self, Lua52 | Lua53 | Lua54 | Luau),
+        }
+    }
+
+    /// The targets that support `feature`
+    pub fn supporting(feature: LuaFeature) -> Vec<LuaVersion> {
+        LuaVersion::ALL
+            .into_iter()
+            .filter(|target| target.supports(feature))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feature_matrix() {
+        assert!(LuaVersion::Lua54.supports(LuaFeature::LocalAttributes));
+        assert!(!LuaVersion::Lua53.supports(LuaFeature::CloseMetamethod));
+        assert!(!LuaVersion::Lua51.supports(LuaFeature::Goto));
+        assert!(LuaVersion::LuaJIT.supports(LuaFeature::Goto));
+        assert_eq!(
+            LuaVersion::supporting(LuaFeature::BitLibrary),
+            vec![LuaVersion::Lua52, LuaVersion::LuaJIT, LuaVersion::Luau]
+        );
+        assert_eq!(
+            LuaVersion::supporting(LuaFeature::Continue),
+            vec![LuaVersion::Luau]
+        );
+    }
+}
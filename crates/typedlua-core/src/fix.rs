@@ -0,0 +1,142 @@
+//! Applying the fixes diagnostics suggest (`typedlua fix`)
+
+use crate::build::parse_source;
+use crate::config::CompilerConfig;
+use crate::diagnostics::{Applicability, DiagnosticHandler, SuggestedFix};
+use crate::errors::CompilationError;
+use crate::span::Span;
+use crate::typechecker::TypeChecker;
+use std::sync::Arc;
+
+/// A source with fixes applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedSource {
+    pub text: String,
+    /// How many fixes were applied
+    pub applied: usize,
+    /// Fixes left out because they may be incorrect or overlap one applied
+    pub skipped: usize,
+}
+
+/// Check a source file and apply the fixes its errors suggest: those that
+/// are machine-applicable, and those that may be incorrect too when
+/// `maybe_incorrect` is set. A file with syntax errors only gets the fixes
+/// for those, since it is not type checked. Diagnostics go to
+/// `diagnostic_handler`, which must keep them.
+pub fn fix_source(
+    source: &str,
+    config: Arc<CompilerConfig>,
+    diagnostic_handler: Arc<dyn DiagnosticHandler>,
+    maybe_incorrect: bool,
+) -> Result<FixedSource, CompilationError> {
+    let parsed = parse_source(source, diagnostic_handler.clone())?;
+    if !diagnostic_handler.has_errors() {
+        let mut checker = TypeChecker::new(config, diagnostic_handler.clone());
+        checker.set_directives(&parsed.directives);
+        // The errors are what the fixes come from
+        let _ = checker.check_program(&parsed.program);
+    }
+
+    let diagnostics = diagnostic_handler.get_diagnostics();
+    let fixes: Vec<&SuggestedFix> = diagnostics
+        .iter()
+        .flat_map(|diagnostic| &diagnostic.fixes)
+        .collect();
+    let chosen: Vec<&SuggestedFix> = fixes
+        .iter()
+        .copied()
+        .filter(|fix| maybe_incorrect || fix.applicability == Applicability::MachineApplicable)
+        .collect();
+    let (text, applied) = apply_fixes(source, &chosen);
+    Ok(FixedSource {
+        text,
+        applied,
+        skipped: fixes.len() - applied,
+    })
+}
+
+/// Apply fixes in order, leaving out any that touch text an earlier one
+/// replaced. Returns the new source and how many fixes were applied.
+pub fn apply_fixes(source: &str, fixes: &[&SuggestedFix]) -> (String, usize) {
+    let mut accepted: Vec<(Span, &str)> = Vec::new();
+    let mut applied = 0;
+    for fix in fixes {
+        let overlaps = fix.replacements.iter().any(|replacement| {
+            accepted
+                .iter()
+                .any(|(span, _)| touches(replacement.span, *span))
+        });
+        if overlaps {
+            continue;
+        }
+        accepted.extend(
+            fix.replacements
+                .iter()
+                .map(|replacement| (replacement.span, replacement.text.as_str())),
+        );
+        applied += 1;
+    }
+
+    // Spans count characters
+    accepted.sort_by_key(|(span, _)| span.start);
+    let chars: Vec<char> = source.chars().collect();
+    let mut text = String::with_capacity(source.len());
+    let mut at = 0;
+    for (span, replacement) in accepted {
+        text.extend(&chars[at..span.start.min(chars.len())]);
+        text.push_str(replacement);
+        at = span.end.min(chars.len());
+    }
+    text.extend(&chars[at..]);
+    (text, applied)
+}
+
+/// Whether two replacements change the same text, or insert at the same
+/// place, where their order would be a guess
+fn touches(a: Span, b: Span) -> bool {
+    a.start == b.start || (a.start < b.end && b.start < a.end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::CollectingDiagnosticHandler;
+
+    fn fix(source: &str, maybe_incorrect: bool) -> FixedSource {
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        fix_source(
+            source,
+            Arc::new(CompilerConfig::default()),
+            handler,
+            maybe_incorrect,
+        )
+        .expect("Fixing failed")
+    }
+
+    #[test]
+    fn test_fixes_missing_end_misspelled_fields_and_nil() {
+        let source = "local function f()\n  return 1\n";
+        assert_eq!(fix(source, false).text, source);
+        let fixed = fix(source, true);
+        assert_eq!(fixed.text, "local function f()\n  return 1\nend\n");
+        assert_eq!(fixed.applied, 1);
+
+        let source = r#"
+interface Point { x: number, y: number, label: string }
+const p: Point = { x = 1, y = 2, label = "origin" }
+const name = p.lable
+declare function find(key: string): string | nil
+declare function checker(key: string): ((n: number) -> boolean) | nil
+const found: string = find("a")
+const check: (n: number) -> boolean = checker("b")
+"#;
+        let fixed = fix(source, false);
+        assert!(fixed.text.contains("p.lable"));
+        assert!(fixed.text.contains("const found: string | nil ="));
+        assert!(fixed
+            .text
+            .contains("const check: ((n: number) -> boolean) | nil ="));
+        assert_eq!((fixed.applied, fixed.skipped), (2, 1));
+        assert!(fix(source, true).text.contains("const name = p.label\n"));
+    }
+}
@@ -0,0 +1,642 @@
+//! Formatting TypedLua source (`typedlua fmt`). Lines are re-indented from
+//! the nesting of blocks and brackets and re-spaced token by token, keeping
+//! the author's line breaks, comments and blank lines. Lines longer than
+//! the configured width are broken at the commas of a bracketed list.
+
+use crate::ast::expression::{Expression, ExpressionKind};
+use crate::ast::types::{Type, TypeKind};
+use crate::ast::visit::Visit;
+use crate::build::parse_module;
+use crate::config::FormatOptions;
+use crate::diagnostics::DiagnosticHandler;
+use crate::errors::CompilationError;
+use crate::lexer::TokenKind;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// A token or comment of the source, with its text as written
+struct Item {
+    kind: ItemKind,
+    text: String,
+    line: usize,
+    /// The line the item ends on, past the first for multi-line strings
+    /// and block comments
+    end_line: usize,
+    /// Whether whitespace separated it from the item before
+    space_before: bool,
+    /// The operator the parser read the token as, if any
+    operator: Option<Operator>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    /// Between the operands of a binary expression, or the members of a
+    /// union or intersection type
+    Binary,
+    /// `-`, `#` or `~` before its operand
+    Unary,
+}
+
+/// Where the parser read operators: the gaps between the operands of
+/// binary expressions and between the members of union and intersection
+/// types, and where unary expressions start. The same tokens read
+/// otherwise, such as the angle brackets of type arguments, are not
+/// operators.
+#[derive(Default)]
+struct Operators {
+    /// Start and end offsets; gaps never overlap
+    gaps: Vec<(usize, usize)>,
+    unary: HashSet<usize>,
+}
+
+impl Operators {
+    fn of(&self, kind: &TokenKind, start: usize) -> Option<Operator> {
+        use TokenKind::*;
+        match kind {
+            Minus | Hash | Tilde if self.unary.contains(&start) => Some(Operator::Unary),
+            Plus | Minus | Star | Slash | Percent | Caret | Ampersand | Pipe | Tilde | LessThan
+            | LessEqual | GreaterThan | GreaterEqual | EqualEqual | BangEqual | TildeEqual
+            | DotDot => {
+                let before = self.gaps.partition_point(|(gap, _)| *gap <= start);
+                let inside = before > 0 && start < self.gaps[before - 1].1;
+                inside.then_some(Operator::Binary)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Visit for Operators {
+    fn visit_expression(&mut self, expr: &Expression) {
+        match &expr.kind {
+            ExpressionKind::Binary(_, left, right) => {
+                self.gaps.push((left.span.end, right.span.start))
+            }
+            ExpressionKind::Unary(_, _) => {
+                self.unary.insert(expr.span.start);
+            }
+            _ => {}
+        }
+        self.walk_expression(expr);
+    }
+
+    fn visit_type(&mut self, typ: &Type) {
+        if let TypeKind::Union(members) | TypeKind::Intersection(members) = &typ.kind {
+            for pair in members.windows(2) {
+                self.gaps.push((pair[0].span.end, pair[1].span.start));
+            }
+        }
+        self.walk_type(typ);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ItemKind {
+    Token(TokenKind),
+    Comment,
+}
+
+/// A block or bracket open at the current line
+struct Frame {
+    /// The indentation of the line that opened it, which its closer gets
+    open_indent: usize,
+    /// The indentation of the lines inside it
+    indent: usize,
+}
+
+/// A line of output: its indentation and its items, spaced
+struct Line<'a> {
+    indent: usize,
+    items: Vec<(&'a Item, bool)>,
+    blank_before: bool,
+}
+
+/// Format a module. Sources that do not parse are left alone and their
+/// errors reported, since where their blocks end cannot be known.
+pub fn format_source(
+    source: &str,
+    options: &FormatOptions,
+    diagnostic_handler: Arc<dyn DiagnosticHandler>,
+) -> Result<String, CompilationError> {
+    let parsed = parse_module(source, diagnostic_handler)?;
+
+    let mut operators = Operators::default();
+    operators.visit_program(&parsed.program);
+    operators.gaps.sort_unstable();
+
+    // Spans count characters
+    let chars: Vec<char> = source.chars().collect();
+    let mut items: Vec<(usize, Item)> = Vec::new();
+    let tokens = parsed
+        .tokens
+        .into_iter()
+        .filter(|token| token.kind != TokenKind::Eof)
+        .map(|token| (token.span, ItemKind::Token(token.kind)));
+    let comments = parsed
+        .comments
+        .iter()
+        .map(|span| (*span, ItemKind::Comment));
+    for (span, kind) in tokens.chain(comments) {
+        let text: String = chars[span.start..span.end].iter().collect();
+        let space_before = span.start > 0 && chars[span.start - 1].is_whitespace();
+        let operator = match &kind {
+            ItemKind::Token(token) => operators.of(token, span.start),
+            ItemKind::Comment => None,
+        };
+        items.push((
+            span.start,
+            Item {
+                end_line: span.line + text.matches('\n').count(),
+                kind,
+                text,
+                line: span.line,
+                space_before,
+                operator,
+            },
+        ));
+    }
+    items.sort_by_key(|(start, _)| *start);
+    let items: Vec<Item> = items.into_iter().map(|(_, item)| item).collect();
+
+    let lines = layout(&items);
+    let mut output = String::new();
+    for line in &lines {
+        for line in break_line(line, options) {
+            if line.blank_before && !output.is_empty() {
+                output.push('\n');
+            }
+            render(&line, options, &mut output);
+        }
+    }
+    Ok(output)
+}
+
+/// Group items into lines, indent each line, and space its items
+fn layout(items: &[Item]) -> Vec<Line<'_>> {
+    let mut lines: Vec<Line> = Vec::new();
+    let mut frames: Vec<Frame> = Vec::new();
+    // The last token of the previous line with code, and whether that line
+    // opened a block or bracket
+    let mut previous: Option<(&TokenKind, bool)> = None;
+
+    let mut start = 0;
+    while start < items.len() {
+        let mut end = start + 1;
+        while end < items.len() && items[end].line <= items[end - 1].end_line {
+            end += 1;
+        }
+        let line_items = &items[start..end];
+        let blank_before = start > 0 && items[start].line > items[start - 1].end_line + 1;
+
+        let mut indent = None;
+        let mut frames_before = frames.len();
+        let mut index = 0;
+        // Closers leading the line put it back at the indentation of the
+        // line that opened them
+        while let Some(ItemKind::Token(kind)) = line_items.get(index).map(|item| &item.kind) {
+            if !closes(kind) {
+                break;
+            }
+            if let Some(frame) = frames.pop() {
+                indent = Some(frame.open_indent);
+            }
+            frames_before = frames.len();
+            index += 1;
+            if reopens(kind) {
+                break;
+            }
+        }
+        let indent = indent.unwrap_or_else(|| {
+            let base = frames.last().map_or(0, |frame| frame.indent);
+            let first = line_items.iter().find_map(|item| match &item.kind {
+                ItemKind::Token(kind) => Some(kind),
+                ItemKind::Comment => None,
+            });
+            let continues = matches!(previous, Some((kind, false)) if ends_incomplete(kind))
+                || first.is_some_and(continues_expression);
+            base + usize::from(continues)
+        });
+
+        // Open and close the rest of the line's blocks and brackets
+        for (position, item) in line_items.iter().enumerate() {
+            let ItemKind::Token(kind) = &item.kind else {
+                continue;
+            };
+            let leading = position < index;
+            if closes(kind) && !leading {
+                frames.pop();
+            }
+            if reopens(kind) || opens(line_items, position, items, start) {
+                frames.push(Frame {
+                    open_indent: indent,
+                    indent: indent + 1,
+                });
+            }
+        }
+
+        let last = line_items.iter().rev().find_map(|item| match &item.kind {
+            ItemKind::Token(kind) => Some(kind),
+            ItemKind::Comment => None,
+        });
+        if let Some(last) = last {
+            previous = Some((last, frames.len() > frames_before));
+        }
+        let mut spaced = Vec::new();
+        // `?` with no `:` right after starts a conditional, whose `:` is
+        // spaced; other colons, of annotations, are not
+        let mut conditionals = 0;
+        for (position, item) in line_items.iter().enumerate() {
+            let next = line_items.get(position + 1).map(|item| &item.kind);
+            let space = match &item.kind {
+                ItemKind::Token(TokenKind::Colon) if conditionals > 0 => {
+                    conditionals -= 1;
+                    true
+                }
+                ItemKind::Token(TokenKind::Colon) => false,
+                _ => position > 0 && space_between(&line_items[position - 1], item),
+            };
+            if item.kind == ItemKind::Token(TokenKind::Question)
+                && next != Some(&ItemKind::Token(TokenKind::Colon))
+            {
+                conditionals += 1;
+            }
+            spaced.push((item, space));
+        }
+        lines.push(Line {
+            indent,
+            items: spaced,
+            blank_before,
+        });
+        start = end;
+    }
+    lines
+}
+
+/// Whether the token at `position` of a line opens a block or bracket.
+/// `function` has no body after `declare`, and `macro` only opens one at
+/// the start of a statement.
+fn opens(line: &[Item], position: usize, items: &[Item], line_start: usize) -> bool {
+    let ItemKind::Token(kind) = &line[position].kind else {
+        return false;
+    };
+    let token_before = items[..line_start + position]
+        .iter()
+        .rev()
+        .find_map(|item| match &item.kind {
+            ItemKind::Token(kind) => Some(kind),
+            ItemKind::Comment => None,
+        });
+    match kind {
+        TokenKind::Function => {
+            !matches!(token_before, Some(TokenKind::Identifier(name)) if name == "declare")
+        }
+        TokenKind::Then
+        | TokenKind::Do
+        | TokenKind::Repeat
+        | TokenKind::LeftParen
+        | TokenKind::LeftBracket
+        | TokenKind::LeftBrace => true,
+        TokenKind::Identifier(name) if name == "macro" => {
+            let next = |offset: usize| match line.get(position + offset).map(|item| &item.kind) {
+                Some(ItemKind::Token(kind)) => Some(kind),
+                _ => None,
+            };
+            position == 0
+                && matches!(next(1), Some(TokenKind::Identifier(_)))
+                && matches!(next(2), Some(TokenKind::LeftParen))
+        }
+        _ => false,
+    }
+}
+
+fn closes(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::End
+            | TokenKind::Until
+            | TokenKind::Else
+            | TokenKind::Elseif
+            | TokenKind::RightParen
+            | TokenKind::RightBracket
+            | TokenKind::RightBrace
+    )
+}
+
+/// `else` closes the block before it and opens its own; `elseif` opens
+/// its own at its `then`
+fn reopens(kind: &TokenKind) -> bool {
+    matches!(kind, TokenKind::Else)
+}
+
+/// A token ending a line whose expression goes on on the next
+fn ends_incomplete(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Equal
+            | TokenKind::And
+            | TokenKind::Or
+            | TokenKind::DotDot
+            | TokenKind::Plus
+            | TokenKind::Minus
+            | TokenKind::Star
+            | TokenKind::Slash
+            | TokenKind::Percent
+            | TokenKind::Caret
+            | TokenKind::EqualEqual
+            | TokenKind::BangEqual
+            | TokenKind::TildeEqual
+            | TokenKind::LessEqual
+            | TokenKind::GreaterEqual
+            | TokenKind::PipeOp
+            | TokenKind::Arrow
+            | TokenKind::FatArrow
+    )
+}
+
+/// A token starting a line that goes on with the expression before, such
+/// as `and` or the `::` of a method chain
+fn continues_expression(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::And
+            | TokenKind::Or
+            | TokenKind::DotDot
+            | TokenKind::Plus
+            | TokenKind::Star
+            | TokenKind::Slash
+            | TokenKind::Percent
+            | TokenKind::Caret
+            | TokenKind::PipeOp
+            | TokenKind::Dot
+            | TokenKind::ColonColon
+            | TokenKind::QuestionDot
+    )
+}
+
+/// Whether a space goes between two items of a line. Binary operators are
+/// spaced and unary ones are not, as the parser read them; punctuation and
+/// the angle brackets of type parameters and arguments follow fixed rules;
+/// otherwise the source decides, with runs of whitespace collapsed to one
+/// space.
+fn space_between(before: &Item, after: &Item) -> bool {
+    use TokenKind::*;
+    let (ItemKind::Token(left), ItemKind::Token(right)) = (&before.kind, &after.kind) else {
+        // A comment after code is set off by one space
+        return matches!(after.kind, ItemKind::Comment) || after.space_before;
+    };
+    let words = |text: &str, first: bool| {
+        let c = if first {
+            text.chars().next()
+        } else {
+            text.chars().last()
+        };
+        c.is_some_and(|c| c.is_alphanumeric() || c == '_')
+    };
+    if words(&before.text, false) && words(&after.text, true) {
+        return true;
+    }
+    match (before.operator, after.operator) {
+        // The two angle brackets of a shift stay together
+        (Some(Operator::Binary), Some(Operator::Binary)) => return false,
+        (Some(Operator::Binary), _) | (_, Some(Operator::Binary)) => return true,
+        (Some(Operator::Unary), _) => return false,
+        _ => {}
+    }
+    match (left, right) {
+        (_, Comma | Semicolon | RightParen | RightBracket) => false,
+        (LeftParen | LeftBracket, _) => false,
+        (Dot | QuestionDot | ColonColon, _) | (_, Dot | QuestionDot | ColonColon) => false,
+        (LeftBrace, RightBrace) => false,
+        (LeftBrace, _) | (_, RightBrace) => true,
+        (Identifier(_) | RightParen | RightBracket | GreaterThan | Function, LeftParen) => false,
+        (Identifier(_) | RightParen | RightBracket | GreaterThan, LeftBracket) => false,
+        (LessThan, _) | (_, LessThan | GreaterThan) => false,
+        (Comma | Colon, _) => true,
+        (
+            Equal | EqualEqual | BangEqual | TildeEqual | LessEqual | GreaterEqual | DotDot | Arrow
+            | FatArrow | PipeOp,
+            _,
+        )
+        | (
+            _,
+            Equal | EqualEqual | BangEqual | TildeEqual | LessEqual | GreaterEqual | DotDot | Arrow
+            | FatArrow | PipeOp,
+        ) => true,
+        _ => after.space_before,
+    }
+}
+
+/// Break a line past the width at the commas of its first bracketed list
+/// that opens and closes on it, one element per line, then break those
+/// lines in turn
+fn break_line<'a>(line: &Line<'a>, options: &FormatOptions) -> Vec<Line<'a>> {
+    let unbroken = || {
+        vec![Line {
+            indent: line.indent,
+            items: line.items.clone(),
+            blank_before: line.blank_before,
+        }]
+    };
+    let width = line.indent * options.indent_width
+        + line
+            .items
+            .iter()
+            .map(|(item, space)| item.text.chars().count() + usize::from(*space))
+            .sum::<usize>();
+    let multiline = line.items.iter().any(|(item, _)| item.end_line > item.line);
+    if width <= options.line_width || multiline {
+        return unbroken();
+    }
+
+    let Some((open, close, commas)) = first_list(&line.items) else {
+        return unbroken();
+    };
+    let mut lines = vec![Line {
+        indent: line.indent,
+        items: line.items[..=open].to_vec(),
+        blank_before: line.blank_before,
+    }];
+    let mut element_start = open + 1;
+    for end in commas.into_iter().chain(std::iter::once(close - 1)) {
+        if element_start > end {
+            continue;
+        }
+        let mut items = line.items[element_start..=end].to_vec();
+        items[0].1 = false;
+        let element = Line {
+            indent: line.indent + 1,
+            items,
+            blank_before: false,
+        };
+        lines.extend(break_line(&element, options));
+        element_start = end + 1;
+    }
+    let mut tail = line.items[close..].to_vec();
+    tail[0].1 = false;
+    lines.push(Line {
+        indent: line.indent,
+        items: tail,
+        blank_before: false,
+    });
+    lines
+}
+
+/// The first bracket of a line that closes on the same line with commas
+/// directly inside it: the positions of the bracket, its closer and the
+/// commas
+fn first_list(items: &[(&Item, bool)]) -> Option<(usize, usize, Vec<usize>)> {
+    for (open, (item, _)) in items.iter().enumerate() {
+        let ItemKind::Token(TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::LeftBrace) =
+            item.kind
+        else {
+            continue;
+        };
+        let mut depth = 0;
+        let mut commas = Vec::new();
+        for (position, (item, _)) in items.iter().enumerate().skip(open) {
+            match &item.kind {
+                ItemKind::Token(
+                    TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::LeftBrace,
+                ) => depth += 1,
+                ItemKind::Token(
+                    TokenKind::RightParen | TokenKind::RightBracket | TokenKind::RightBrace,
+                ) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        if !commas.is_empty() {
+                            return Some((open, position, commas));
+                        }
+                        break;
+                    }
+                }
+                ItemKind::Token(TokenKind::Comma) if depth == 1 => commas.push(position),
+                // A line comment runs to the end of the line, so nothing
+                // after it can move to a line of its own
+                ItemKind::Comment if item.text.starts_with("//") => return None,
+                _ => {}
+            }
+        }
+    }
+    None
+}
+
+fn render(line: &Line, options: &FormatOptions, output: &mut String) {
+    output.push_str(&" ".repeat(line.indent * options.indent_width));
+    for (item, space) in &line.items {
+        if *space {
+            output.push(' ');
+        }
+        output.push_str(&item.text);
+    }
+    output.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::CollectingDiagnosticHandler;
+
+    fn format(source: &str, options: &FormatOptions) -> String {
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        format_source(source, options, handler).expect("Formatting failed")
+    }
+
+    #[test]
+    fn test_format_indents_and_spaces_code() {
+        let source = r#"
+// Totals
+function total(items:Item[],  scale :number): number
+local sum=0
+  for _, item in ipairs(items) do
+      if item.count>0 then sum = sum+item.count*scale // counted
+      elseif item.free then
+  sum=sum..""
+      else
+  /* skipped */
+      end
+   end
+
+
+  return sum
+end
+const point = {x=1,y=2}
+class Point {
+x: number
+  move(dx: number): void {
+self.x = self.x + dx
+  }
+}
+local done = items::map((item) => item.done)
+    |> count
+"#;
+        let expected = r#"// Totals
+function total(items: Item[], scale: number): number
+    local sum = 0
+    for _, item in ipairs(items) do
+        if item.count > 0 then sum = sum + item.count * scale // counted
+        elseif item.free then
+            sum = sum .. ""
+        else
+            /* skipped */
+        end
+    end
+
+    return sum
+end
+const point = { x = 1, y = 2 }
+class Point {
+    x: number
+    move(dx: number): void {
+        self.x = self.x + dx
+    }
+}
+local done = items::map((item) => item.done)
+    |> count
+"#;
+        let options = FormatOptions::default();
+        assert_eq!(format(source, &options), expected);
+        assert_eq!(format(expected, &options), expected);
+    }
+
+    #[test]
+    fn test_format_spaces_operators_as_the_parser_reads_them() {
+        let source = r#"
+local a = -x-1
+local b = # items*2^ - n
+local c = ~ mask&flags|bits ~ 1
+local d = a<<2 >>1
+local e = a<b and b>c or a<=b
+type Pair<K,V> = Map< K, Array<V> > | nil&Named
+function first < T > (items: Array<T>): T? return items[1] end
+local g = not - x
+"#;
+        let expected = r#"local a = -x - 1
+local b = #items * 2 ^ -n
+local c = ~mask & flags | bits ~ 1
+local d = a << 2 >> 1
+local e = a < b and b > c or a <= b
+type Pair<K, V> = Map<K, Array<V>> | nil & Named
+function first<T>(items: Array<T>): T? return items[1] end
+local g = not -x
+"#;
+        let options = FormatOptions::default();
+        assert_eq!(format(source, &options), expected);
+        assert_eq!(format(expected, &options), expected);
+    }
+
+    #[test]
+    fn test_format_breaks_long_lines_at_commas() {
+        let options = FormatOptions {
+            indent_width: 2,
+            line_width: 30,
+        };
+        let source = "print(greet(\"first\"), [alpha, beta, gamma], 3)\n";
+        let expected = "print(\n  greet(\"first\"),\n  [alpha, beta, gamma],\n  3\n)\n";
+        assert_eq!(format(source, &options), expected);
+        assert_eq!(format(expected, &options), expected);
+
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        assert!(format_source("if x then", &options, handler.clone()).is_err());
+        assert!(handler.has_errors());
+    }
+}
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 /// File system abstraction for dependency injection
 pub trait FileSystem: Send + Sync {
@@ -7,6 +8,27 @@ pub trait FileSystem: Send + Sync {
     fn write_file(&self, path: &Path, content: &str) -> Result<(), std::io::Error>;
     fn exists(&self, path: &Path) -> bool;
     fn resolve_path(&self, base: &Path, relative: &str) -> PathBuf;
+
+    /// Files and subdirectories directly inside a directory
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, std::io::Error>;
+
+    /// Write only when the content differs from what is already on disk, so
+    /// unchanged outputs keep their modification times and file watchers
+    /// downstream are not triggered. Returns whether the file was written.
+    fn write_if_changed(&self, path: &Path, content: &str) -> Result<bool, std::io::Error> {
+        if self.exists(path) && self.read_file(path).is_ok_and(|current| current == content) {
+            return Ok(false);
+        }
+        self.write_file(path, content)?;
+        Ok(true)
+    }
+}
+
+/// An entry of a directory listing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub path: PathBuf,
+    pub is_dir: bool,
 }
 
 /// Real file system implementation
@@ -43,22 +65,51 @@ impl FileSystem for RealFileSystem {
     fn resolve_path(&self, base: &Path, relative: &str) -> PathBuf {
         base.join(relative)
     }
+
+    /// Entries are joined onto `path` as given, and an empty path lists the
+    /// current directory
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, std::io::Error> {
+        let dir = if path.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            path
+        };
+        std::fs::read_dir(dir)?
+            .map(|entry| {
+                let entry = entry?;
+                Ok(DirEntry {
+                    path: path.join(entry.file_name()),
+                    is_dir: entry.file_type()?.is_dir(),
+                })
+            })
+            .collect()
+    }
 }
 
 /// Mock file system for testing
 pub struct MockFileSystem {
-    files: HashMap<PathBuf, String>,
+    files: Mutex<HashMap<PathBuf, String>>,
+    writes: Mutex<Vec<PathBuf>>,
 }
 
 impl MockFileSystem {
     pub fn new() -> Self {
         Self {
-            files: HashMap::new(),
+            files: Mutex::new(HashMap::new()),
+            writes: Mutex::new(Vec::new()),
         }
     }
 
     pub fn add_file(&mut self, path: impl Into<PathBuf>, content: impl Into<String>) {
-        self.files.insert(path.into(), content.into());
+        self.files
+            .get_mut()
+            .unwrap()
+            .insert(path.into(), content.into());
+    }
+
+    /// Paths written so far, in order
+    pub fn writes(&self) -> Vec<PathBuf> {
+        self.writes.lock().unwrap().clone()
     }
 }
 
@@ -70,26 +121,63 @@ impl Default for MockFileSystem {
 
 impl FileSystem for MockFileSystem {
     fn read_file(&self, path: &Path) -> Result<String, std::io::Error> {
-        self.files.get(path).cloned().ok_or_else(|| {
-            std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                format!("File not found: {}", path.display()),
-            )
-        })
+        self.files
+            .lock()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("File not found: {}", path.display()),
+                )
+            })
     }
 
-    fn write_file(&self, _path: &Path, _content: &str) -> Result<(), std::io::Error> {
-        // Mock implementation - could track writes if needed
+    fn write_file(&self, path: &Path, content: &str) -> Result<(), std::io::Error> {
+        self.writes.lock().unwrap().push(path.to_path_buf());
+        self.files
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), content.to_string());
         Ok(())
     }
 
     fn exists(&self, path: &Path) -> bool {
-        self.files.contains_key(path)
+        self.files.lock().unwrap().contains_key(path)
     }
 
     fn resolve_path(&self, base: &Path, relative: &str) -> PathBuf {
         base.join(relative)
     }
+
+    /// Directories exist only as prefixes of the files added
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, std::io::Error> {
+        let mut entries: Vec<DirEntry> = Vec::new();
+        for file in self.files.lock().unwrap().keys() {
+            let Ok(rest) = file.strip_prefix(path) else {
+                continue;
+            };
+            let mut components = rest.components();
+            let Some(first) = components.next() else {
+                continue;
+            };
+            let entry = DirEntry {
+                path: path.join(first),
+                is_dir: components.next().is_some(),
+            };
+            if !entries.contains(&entry) {
+                entries.push(entry);
+            }
+        }
+        if entries.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Directory not found: {}", path.display()),
+            ));
+        }
+        Ok(entries)
+    }
 }
 
 #[cfg(test)]
@@ -121,6 +209,34 @@ mod tests {
         assert!(!fs.exists(Path::new("/other.txt")));
     }
 
+    #[test]
+    fn test_write_if_changed_skips_identical_content() {
+        let mut fs = MockFileSystem::new();
+        fs.add_file("/out/main.lua", "return 1\n");
+
+        assert!(!fs
+            .write_if_changed(Path::new("/out/main.lua"), "return 1\n")
+            .unwrap());
+        assert!(fs
+            .write_if_changed(Path::new("/out/main.lua"), "return 2\n")
+            .unwrap());
+        assert!(fs
+            .write_if_changed(Path::new("/out/new.lua"), "return 3\n")
+            .unwrap());
+
+        assert_eq!(
+            fs.writes(),
+            vec![
+                PathBuf::from("/out/main.lua"),
+                PathBuf::from("/out/new.lua")
+            ]
+        );
+        assert_eq!(
+            fs.read_file(Path::new("/out/main.lua")).unwrap(),
+            "return 2\n"
+        );
+    }
+
     #[test]
     fn test_resolve_path() {
         let fs = RealFileSystem::new();
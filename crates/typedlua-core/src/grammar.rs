@@ -0,0 +1,271 @@
+//! The syntax the parser accepts, as EBNF rules (`typedlua grammar`), for
+//! the authors of TextMate, tree-sitter and other editor grammars to diff
+//! between releases instead of reading parser changes
+//!
+//! The rules in `grammar/typedlua.ebnf` are maintained by hand, not derived
+//! from the parser. Tests read the examples in their comments and a corpus
+//! of programs with both, and fail where the two disagree.
+
+pub mod tree_sitter;
+
+use crate::lexer::KEYWORDS;
+use serde::Serialize;
+use serde_json::json;
+
+const RULES: &str = include_str!("grammar/typedlua.ebnf");
+
+/// The version of the syntax the rules describe, bumped whenever they
+/// change, so editor grammars can name the syntax they cover
+pub const SYNTAX_VERSION: u32 = 1;
+
+/// Generated rules are wrapped before this column
+const WIDTH: usize = 100;
+
+/// One production of the grammar
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Rule {
+    pub name: String,
+    /// The heading the rule is listed under
+    pub section: String,
+    /// What the notation does not say, such as which words are contextual
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+    /// The right-hand side in W3C EBNF notation, one line per line of the
+    /// source, each starting with `|` after the first
+    pub definition: String,
+}
+
+/// Every rule: those in `grammar/typedlua.ebnf`, then `Keyword` and
+/// `ContextualKeyword`, generated from the lexer's reserved words and the
+/// other words the rules use
+pub fn rules() -> Vec<Rule> {
+    let mut rules = Vec::new();
+    let mut section = String::new();
+    for block in RULES.split("\n\n").map(str::trim) {
+        let (doc, body) = match block
+            .strip_prefix("/*")
+            .and_then(|rest| rest.split_once("*/"))
+        {
+            Some((doc, body)) => (Some(doc.trim().to_string()), body.trim()),
+            None => (None, block),
+        };
+        let Some((name, definition)) = body.split_once("::=") else {
+            section = doc.unwrap_or_default();
+            continue;
+        };
+        let definition: Vec<&str> = definition.lines().map(str::trim).collect();
+        rules.push(Rule {
+            name: name.trim().to_string(),
+            section: section.clone(),
+            doc,
+            definition: definition.join("\n"),
+        });
+    }
+
+    let mut keywords: Vec<&str> = KEYWORDS.to_vec();
+    keywords.sort_unstable();
+    let mut contextual: Vec<String> = Vec::new();
+    for rule in &rules {
+        for term in terms(&rule.definition) {
+            let word = match term {
+                Term::Literal(word) if word.chars().all(|c| c.is_alphanumeric() || c == '_') => {
+                    word
+                }
+                _ => continue,
+            };
+            let named = word.chars().any(char::is_alphabetic);
+            if named && !KEYWORDS.contains(&word) && !contextual.iter().any(|c| c == word) {
+                contextual.push(word.to_string());
+            }
+        }
+    }
+    contextual.sort_unstable();
+    rules.push(Rule {
+        name: "Keyword".to_string(),
+        section: "Keywords".to_string(),
+        doc: Some("Reserved words, which are never identifiers".to_string()),
+        definition: wrap_alternatives("Keyword", &keywords),
+    });
+    rules.push(Rule {
+        name: "ContextualKeyword".to_string(),
+        section: "Keywords".to_string(),
+        doc: Some(
+            "Words meaning something only where the rules use them, identifiers elsewhere"
+                .to_string(),
+        ),
+        definition: wrap_alternatives("ContextualKeyword", &contextual),
+    });
+    rules
+}
+
+/// The grammar in W3C EBNF, which railroad diagram generators read,
+/// headed by the syntax version
+pub fn to_ebnf() -> String {
+    let mut out = format!(
+        "/* TypedLua syntax version {}, as typedlua {} parses it */\n",
+        SYNTAX_VERSION,
+        env!("CARGO_PKG_VERSION")
+    );
+    let mut section = None;
+    for rule in rules() {
+        if section != Some(rule.section.clone()) {
+            out.push_str(&format!("\n/* {} */\n", rule.section));
+            section = Some(rule.section.clone());
+        }
+        out.push('\n');
+        if let Some(doc) = &rule.doc {
+            out.push_str(&format!("/* {} */\n", doc));
+        }
+        let indent = " ".repeat(rule.name.len() + 3);
+        for (i, line) in rule.definition.lines().enumerate() {
+            if i == 0 {
+                out.push_str(&format!("{} ::= {}\n", rule.name, line));
+            } else {
+                out.push_str(&format!("{}{}\n", indent, line));
+            }
+        }
+    }
+    out
+}
+
+/// The grammar as JSON: the syntax version, every rule, and the reserved
+/// and contextual keywords
+pub fn to_json() -> String {
+    let rules = rules();
+    let words = |name: &str| -> Vec<String> {
+        let rule = rules.iter().find(|rule| rule.name == name);
+        rule.map_or(Vec::new(), |rule| {
+            terms(&rule.definition)
+                .into_iter()
+                .filter_map(|term| match term {
+                    Term::Literal(word) => Some(word.to_string()),
+                    Term::Reference(_) => None,
+                })
+                .collect()
+        })
+    };
+    let grammar = json!({
+        "syntaxVersion": SYNTAX_VERSION,
+        "compilerVersion": env!("CARGO_PKG_VERSION"),
+        "keywords": words("Keyword"),
+        "contextualKeywords": words("ContextualKeyword"),
+        "rules": rules,
+    });
+    serde_json::to_string_pretty(&grammar).expect("grammar values serialize")
+}
+
+/// A literal or rule name in a definition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Term<'a> {
+    Literal(&'a str),
+    Reference(&'a str),
+}
+
+/// The literals and rule names of a definition, in order. Character
+/// classes are neither.
+fn terms(definition: &str) -> Vec<Term<'_>> {
+    let mut terms = Vec::new();
+    let mut rest = definition;
+    while let Some(c) = rest.chars().next() {
+        let end = match c {
+            '\'' | '"' => {
+                let end = rest[1..].find(c).map_or(rest.len(), |i| i + 2);
+                terms.push(Term::Literal(&rest[1..end - 1]));
+                end
+            }
+            '[' => rest.find(']').map_or(rest.len(), |i| i + 1),
+            c if c.is_ascii_alphabetic() => {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric())
+                    .unwrap_or(rest.len());
+                terms.push(Term::Reference(&rest[..end]));
+                end
+            }
+            c => c.len_utf8(),
+        };
+        rest = &rest[end..];
+    }
+    terms
+}
+
+/// `'a' | 'b' | ...`, broken before `|` to stay within `WIDTH` once
+/// written after `name ::= `
+fn wrap_alternatives(name: &str, words: &[impl AsRef<str>]) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in words {
+        let alternative = format!("'{}'", word.as_ref());
+        if line.is_empty() {
+            line = alternative;
+        } else if name.len() + 5 + line.len() + 3 + alternative.len() > WIDTH {
+            lines.push(line);
+            line = format!("| {}", alternative);
+        } else {
+            line = format!("{} | {}", line, alternative);
+        }
+    }
+    lines.push(line);
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build::content_hash;
+
+    #[test]
+    fn test_grammar_covers_the_lexer() {
+        // Changing the rules changes the syntax version
+        assert_eq!(
+            content_hash(RULES),
+            0xcff7_6ec8_7af1_7fc7,
+            "the grammar changed: bump SYNTAX_VERSION and update this hash"
+        );
+
+        let rules = rules();
+        let names: Vec<&str> = rules.iter().map(|rule| rule.name.as_str()).collect();
+        for rule in &rules {
+            for term in terms(&rule.definition) {
+                let literal = match term {
+                    Term::Reference(name) => {
+                        assert!(
+                            names.contains(&name),
+                            "{} uses undefined {}",
+                            rule.name,
+                            name
+                        );
+                        continue;
+                    }
+                    Term::Literal(literal) => literal,
+                };
+                assert!(!literal.is_empty(), "{} has an empty literal", rule.name);
+            }
+        }
+        // Every reserved word is used somewhere, so new keywords need rules
+        let literals: Vec<Term> = rules
+            .iter()
+            .filter(|rule| rule.section != "Keywords")
+            .flat_map(|rule| terms(&rule.definition))
+            .collect();
+        for keyword in KEYWORDS {
+            assert!(
+                literals.contains(&Term::Literal(keyword)),
+                "no rule uses '{}'",
+                keyword
+            );
+        }
+
+        let keyword = rules.iter().find(|rule| rule.name == "ContextualKeyword");
+        let contextual = terms(&keyword.unwrap().definition);
+        assert!(contextual.contains(&Term::Literal("declare")));
+        assert!(!contextual.contains(&Term::Literal("const")));
+
+        let ebnf = to_ebnf();
+        assert!(ebnf.starts_with("/* TypedLua syntax version 1,"));
+        assert!(ebnf.contains("\nStatement ::= Decorator+"));
+        assert!(ebnf.contains("\n            | DeclareDeclaration\n"));
+        let json: serde_json::Value = serde_json::from_str(&to_json()).unwrap();
+        assert_eq!(json["syntaxVersion"], SYNTAX_VERSION);
+        assert_eq!(json["keywords"][0], "abstract");
+    }
+}
@@ -0,0 +1,892 @@
+//! A tree-sitter grammar generated from the syntax rules, and checks of a
+//! tree-sitter grammar's parses against the real parser, so editor
+//! highlighting built on one stays true to what the compiler accepts
+//!
+//! A grammar agrees with the parser on a file when every node it builds
+//! starts and ends where a token or comment does, and it finds a syntax
+//! error on the line the parser reports its first one, or finds none when
+//! the parser does not. Files it agrees on can be published as tree-sitter
+//! corpus tests, pinning the grammar's trees.
+
+use super::{rules, SYNTAX_VERSION};
+use crate::diagnostics::{CollectingDiagnosticHandler, DiagnosticHandler, DiagnosticLevel};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+use std::sync::Arc;
+
+/// The rules of the Tokens section, written as tree-sitter tokens. Template
+/// strings are one token, interpolations included.
+const TOKENS: &[(&str, &str)] = &[
+    ("Identifier", r"/[A-Za-z_][A-Za-z0-9_]*/"),
+    (
+        "Number",
+        r"token(choice(/[0-9][0-9_]*(\.[0-9_]+)?([eE][+-]?[0-9]+)?/, /(0[xX][0-9A-Fa-f_]+|0[bB][01_]+|[0-9][0-9_]*)([uU]?[lL][lL])?/))",
+    ),
+    (
+        "String",
+        r#"token(choice(/"([^"\\]|\\.)*"/, /'([^'\\]|\\.)*'/))"#,
+    ),
+    ("TemplateString", r"token(/`([^`\\]|\\.)*`/)"),
+];
+
+/// Comments, which may appear between any two tokens
+const COMMENT: &str = r"token(choice(seq('//', /.*/), seq('/*', /[^*]*\*+([^/*][^*]*\*+)*/, '/')))";
+
+/// A tree-sitter `grammar.js` for the syntax, with a node for every rule
+/// named in snake case. Tree-sitter rejects rules other than the first
+/// that match nothing, so those match something, and their uses are
+/// optional instead. Conflicts `tree-sitter generate` reports are left to
+/// a grammar extending this one to declare.
+pub fn grammar_js() -> String {
+    let mut definitions: Vec<(String, Expr)> = rules()
+        .into_iter()
+        .filter(|rule| rule.section != "Tokens" && rule.section != "Keywords")
+        .map(|rule| (rule.name, Expr::parse(&rule.definition)))
+        .collect();
+    let nullable = nullable_rules(&definitions);
+    for (index, (name, definition)) in definitions.iter_mut().enumerate() {
+        *definition = definition.optional_uses(&nullable);
+        if index > 0 && nullable.contains(name.as_str()) {
+            *definition = definition.non_empty();
+        }
+    }
+
+    let mut out = format!(
+        "// Generated by `typedlua tree-sitter generate` from TypedLua syntax version {}.\n\
+         // Regenerate it rather than editing it.\n\n\
+         module.exports = grammar({{\n  name: 'typedlua',\n\n  word: $ => $.identifier,\n\n  \
+         extras: $ => [/\\s/, $.comment],\n\n  rules: {{\n",
+        SYNTAX_VERSION
+    );
+    for (name, definition) in &definitions {
+        out.push_str(&format!(
+            "    {}: $ => {},\n\n",
+            snake_case(name),
+            definition.to_js()
+        ));
+    }
+    for (name, token) in TOKENS {
+        out.push_str(&format!("    {}: $ => {},\n\n", snake_case(name), token));
+    }
+    out.push_str(&format!("    comment: $ => {},\n  }},\n}});\n", COMMENT));
+    out
+}
+
+/// A rule's right-hand side
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Literal(String),
+    Rule(String),
+    Seq(Vec<Expr>),
+    Choice(Vec<Expr>),
+    Optional(Box<Expr>),
+    Repeat(Box<Expr>),
+    Repeat1(Box<Expr>),
+}
+
+impl Expr {
+    /// Read a definition outside the Tokens section, which uses no
+    /// character classes or exclusions
+    fn parse(definition: &str) -> Expr {
+        let chars: Vec<char> = definition.chars().collect();
+        let mut at = 0;
+        Self::choice(&chars, &mut at)
+    }
+
+    fn choice(chars: &[char], at: &mut usize) -> Expr {
+        let mut alternatives = vec![Self::seq(chars, at)];
+        while skip_space(chars, at) == Some('|') {
+            *at += 1;
+            alternatives.push(Self::seq(chars, at));
+        }
+        match alternatives.len() {
+            1 => alternatives.remove(0),
+            _ => Expr::Choice(alternatives),
+        }
+    }
+
+    fn seq(chars: &[char], at: &mut usize) -> Expr {
+        let mut items = Vec::new();
+        while let Some(c) = skip_space(chars, at) {
+            let item = match c {
+                '|' | ')' => break,
+                '(' => {
+                    *at += 1;
+                    let inner = Self::choice(chars, at);
+                    *at += 1;
+                    inner
+                }
+                '\'' | '"' => {
+                    let end = chars[*at + 1..]
+                        .iter()
+                        .position(|d| *d == c)
+                        .map_or(chars.len(), |i| *at + 1 + i);
+                    let literal = chars[*at + 1..end].iter().collect();
+                    *at = end + 1;
+                    Expr::Literal(literal)
+                }
+                _ => {
+                    let start = *at;
+                    while *at < chars.len() && chars[*at].is_ascii_alphanumeric() {
+                        *at += 1;
+                    }
+                    *at = (*at).max(start + 1);
+                    Expr::Rule(chars[start..*at].iter().collect())
+                }
+            };
+            let mut item = item;
+            while let Some(suffix) = chars.get(*at) {
+                item = match suffix {
+                    '?' => Expr::Optional(Box::new(item)),
+                    '*' => Expr::Repeat(Box::new(item)),
+                    '+' => Expr::Repeat1(Box::new(item)),
+                    _ => break,
+                };
+                *at += 1;
+            }
+            items.push(item);
+        }
+        match items.len() {
+            1 => items.remove(0),
+            _ => Expr::Seq(items),
+        }
+    }
+
+    fn nullable(&self, rules: &BTreeSet<String>) -> bool {
+        match self {
+            Expr::Literal(_) => false,
+            Expr::Rule(name) => rules.contains(name.as_str()),
+            Expr::Seq(items) => items.iter().all(|item| item.nullable(rules)),
+            Expr::Choice(items) => items.iter().any(|item| item.nullable(rules)),
+            Expr::Optional(_) | Expr::Repeat(_) => true,
+            Expr::Repeat1(inner) => inner.nullable(rules),
+        }
+    }
+
+    /// Make every use of a rule in `nullable` optional, as those rules
+    /// are about to stop matching nothing
+    fn optional_uses(&self, nullable: &BTreeSet<String>) -> Expr {
+        let map = |items: &[Expr]| items.iter().map(|i| i.optional_uses(nullable)).collect();
+        match self {
+            Expr::Rule(name) if nullable.contains(name.as_str()) => {
+                Expr::Optional(Box::new(self.clone()))
+            }
+            Expr::Literal(_) | Expr::Rule(_) => self.clone(),
+            Expr::Seq(items) => Expr::Seq(map(items)),
+            Expr::Choice(items) => Expr::Choice(map(items)),
+            Expr::Optional(inner) => Expr::Optional(Box::new(inner.optional_uses(nullable))),
+            Expr::Repeat(inner) => Expr::Repeat(Box::new(inner.optional_uses(nullable))),
+            Expr::Repeat1(inner) => Expr::Repeat1(Box::new(inner.optional_uses(nullable))),
+        }
+    }
+
+    /// The same language without the empty string. Uses of rules are
+    /// already optional, so no rule is nullable any more.
+    fn non_empty(&self) -> Expr {
+        let none = BTreeSet::new();
+        if !self.nullable(&none) {
+            return self.clone();
+        }
+        match self {
+            Expr::Optional(inner) => inner.non_empty(),
+            Expr::Repeat(inner) | Expr::Repeat1(inner) => {
+                Expr::Repeat1(Box::new(inner.non_empty()))
+            }
+            Expr::Choice(items) => {
+                Expr::Choice(items.iter().map(|item| item.non_empty()).collect())
+            }
+            // Every item may match nothing: the first to match something
+            // is followed by the rest as they were
+            Expr::Seq(items) => Expr::Choice(
+                (0..items.len())
+                    .map(|first| {
+                        let mut rest = vec![items[first].non_empty()];
+                        rest.extend(items[first + 1..].iter().cloned());
+                        match rest.len() {
+                            1 => rest.remove(0),
+                            _ => Expr::Seq(rest),
+                        }
+                    })
+                    .collect(),
+            ),
+            Expr::Literal(_) | Expr::Rule(_) => self.clone(),
+        }
+    }
+
+    fn to_js(&self) -> String {
+        let list = |items: &[Expr]| {
+            let items: Vec<String> = items.iter().map(Expr::to_js).collect();
+            items.join(", ")
+        };
+        match self {
+            Expr::Literal(text) => serde_json::to_string(text).expect("strings serialize"),
+            Expr::Rule(name) => format!("$.{}", snake_case(name)),
+            Expr::Seq(items) => format!("seq({})", list(items)),
+            Expr::Choice(items) => format!("choice({})", list(items)),
+            Expr::Optional(inner) => format!("optional({})", inner.to_js()),
+            Expr::Repeat(inner) => format!("repeat({})", inner.to_js()),
+            Expr::Repeat1(inner) => format!("repeat1({})", inner.to_js()),
+        }
+    }
+}
+
+fn skip_space(chars: &[char], at: &mut usize) -> Option<char> {
+    while chars.get(*at).is_some_and(|c| c.is_whitespace()) {
+        *at += 1;
+    }
+    chars.get(*at).copied()
+}
+
+/// The rules that can match nothing
+fn nullable_rules(definitions: &[(String, Expr)]) -> BTreeSet<String> {
+    let mut nullable = BTreeSet::new();
+    loop {
+        let before = nullable.len();
+        for (name, definition) in definitions {
+            if definition.nullable(&nullable) {
+                nullable.insert(name.clone());
+            }
+        }
+        if nullable.len() == before {
+            return nullable;
+        }
+    }
+}
+
+/// `VariableDeclaration` as `variable_declaration`
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// A position as tree-sitter gives it: a row and a byte column, from 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Point {
+    pub row: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.row + 1, self.column + 1)
+    }
+}
+
+/// A node of the tree `tree-sitter parse` prints
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxNode {
+    /// The node's type, `ERROR`, or `MISSING` for a token tree-sitter
+    /// inserted to recover
+    pub kind: String,
+    /// The field of its parent the node is in
+    pub field: Option<String>,
+    /// What a `MISSING` node stands for
+    pub missing: Option<String>,
+    pub start: Point,
+    pub end: Point,
+    pub children: Vec<SyntaxNode>,
+}
+
+impl SyntaxNode {
+    /// Read the S-expression `tree-sitter parse` prints, with each node's
+    /// range, such as `(program [0, 0] - [1, 0] (block ...))`
+    pub fn parse(output: &str) -> Result<SyntaxNode, String> {
+        let chars: Vec<char> = output.chars().collect();
+        let mut at = 0;
+        while chars.get(at).is_some_and(|c| *c != '(') {
+            at += 1;
+        }
+        read_node(&chars, &mut at, None)
+    }
+
+    fn is_error(&self) -> bool {
+        self.kind == "ERROR" || self.kind == "MISSING"
+    }
+
+    /// The tree as a corpus test expects it: fields and node types,
+    /// without ranges
+    fn write_expected(&self, depth: usize, out: &mut String) {
+        out.push_str(&"  ".repeat(depth));
+        if let Some(field) = &self.field {
+            out.push_str(&format!("{}: ", field));
+        }
+        out.push('(');
+        out.push_str(&self.kind);
+        if let Some(missing) = &self.missing {
+            out.push_str(&format!(" {}", missing));
+        }
+        for child in &self.children {
+            out.push('\n');
+            child.write_expected(depth + 1, out);
+        }
+        out.push(')');
+    }
+}
+
+fn read_node(chars: &[char], at: &mut usize, field: Option<String>) -> Result<SyntaxNode, String> {
+    let expect = |at: &mut usize, c: char| {
+        if skip_space(chars, at) == Some(c) {
+            *at += 1;
+            Ok(())
+        } else {
+            Err(format!("expected '{}' at character {}", c, at))
+        }
+    };
+    let word = |at: &mut usize| {
+        skip_space(chars, at);
+        let start = *at;
+        if chars.get(*at) == Some(&'"') {
+            *at += 1;
+            while chars.get(*at).is_some_and(|c| *c != '"') {
+                *at += if chars[*at] == '\\' { 2 } else { 1 };
+            }
+            *at += 1;
+        } else {
+            while chars
+                .get(*at)
+                .is_some_and(|c| !c.is_whitespace() && !"()[]".contains(*c))
+            {
+                *at += 1;
+            }
+        }
+        chars[start..(*at).min(chars.len())]
+            .iter()
+            .collect::<String>()
+    };
+    let number = |at: &mut usize| {
+        skip_space(chars, at);
+        let start = *at;
+        while chars.get(*at).is_some_and(char::is_ascii_digit) {
+            *at += 1;
+        }
+        let digits: String = chars[start..*at].iter().collect();
+        digits
+            .parse::<usize>()
+            .map_err(|_| format!("expected a number at character {}", start))
+    };
+    let point = |at: &mut usize| -> Result<Point, String> {
+        expect(at, '[')?;
+        let row = number(at)?;
+        expect(at, ',')?;
+        let column = number(at)?;
+        expect(at, ']')?;
+        Ok(Point { row, column })
+    };
+
+    expect(at, '(')?;
+    let kind = word(at);
+    let missing = match (kind.as_str(), skip_space(chars, at)) {
+        ("MISSING", Some(c)) if c != '[' => Some(word(at)),
+        _ => None,
+    };
+    let start = point(at)?;
+    expect(at, '-')?;
+    let end = point(at)?;
+    let mut children = Vec::new();
+    loop {
+        match skip_space(chars, at) {
+            Some(')') => {
+                *at += 1;
+                break;
+            }
+            Some('(') => children.push(read_node(chars, at, None)?),
+            Some(_) => {
+                let name = word(at);
+                let field = name.strip_suffix(':').map(str::to_string);
+                if field.is_none() {
+                    return Err(format!("unexpected '{}' in the tree", name));
+                }
+                children.push(read_node(chars, at, field)?);
+            }
+            None => return Err("the tree ends early".to_string()),
+        }
+    }
+    Ok(SyntaxNode {
+        kind,
+        field,
+        missing,
+        start,
+        end,
+        children,
+    })
+}
+
+/// Where a tree-sitter grammar and the parser see a file differently
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Disagreement {
+    /// A node starts or ends inside a token, or in the space between two
+    TokenBoundary {
+        kind: String,
+        start: Point,
+        end: Point,
+    },
+    /// Only the parser finds a syntax error
+    ErrorOnlyInParser(Point),
+    /// Only the grammar finds a syntax error
+    ErrorOnlyInGrammar(Point),
+    /// Both find errors, but the first ones are on different lines
+    ErrorPosition { parser: Point, grammar: Point },
+}
+
+impl fmt::Display for Disagreement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Disagreement::TokenBoundary { kind, start, end } => write!(
+                f,
+                "{}: '{}' ending at {} does not start and end where tokens do",
+                start, kind, end
+            ),
+            Disagreement::ErrorOnlyInParser(at) => {
+                write!(f, "{}: the parser finds an error the grammar does not", at)
+            }
+            Disagreement::ErrorOnlyInGrammar(at) => {
+                write!(f, "{}: the grammar finds an error the parser does not", at)
+            }
+            Disagreement::ErrorPosition { parser, grammar } => write!(
+                f,
+                "{}: the parser's first error is here, the grammar's at {}",
+                parser, grammar
+            ),
+        }
+    }
+}
+
+/// Compare a grammar's parse of `source` with the parser's
+pub fn cross_validate(source: &str, tree: &SyntaxNode) -> Vec<Disagreement> {
+    // Spans count characters; tree-sitter columns count bytes
+    let mut points = Vec::with_capacity(source.len() + 1);
+    let mut point = Point { row: 0, column: 0 };
+    for c in source.chars() {
+        points.push(point);
+        if c == '\n' {
+            point = Point {
+                row: point.row + 1,
+                column: 0,
+            };
+        } else {
+            point.column += c.len_utf8();
+        }
+    }
+    points.push(point);
+    let at = |offset: usize| points[offset.min(points.len() - 1)];
+
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let mut lexer = Lexer::new(source, handler.clone());
+    let mut starts = HashSet::new();
+    let mut ends = HashSet::new();
+    let lexed = lexer.tokenize();
+    let lexed = lexed.is_ok_and(|tokens| {
+        for span in tokens
+            .iter()
+            .map(|token| token.span)
+            .chain(lexer.comments().iter().copied())
+        {
+            starts.insert(at(span.start));
+            ends.insert(at(span.end));
+        }
+        let _ = Parser::new(tokens, handler.clone()).parse();
+        true
+    });
+    let parser_error = handler
+        .get_diagnostics()
+        .iter()
+        .filter(|diagnostic| diagnostic.level == DiagnosticLevel::Error)
+        .map(|diagnostic| at(diagnostic.span.start))
+        .min()
+        .or((!lexed).then_some(Point { row: 0, column: 0 }));
+
+    let mut disagreements = Vec::new();
+    let mut grammar_errors = Vec::new();
+    let mut pending: Vec<&SyntaxNode> = tree.children.iter().rev().collect();
+    while let Some(node) = pending.pop() {
+        if node.is_error() {
+            grammar_errors.push(node.start);
+            continue;
+        }
+        let inside =
+            node.start != node.end && (!starts.contains(&node.start) || !ends.contains(&node.end));
+        if lexed && inside {
+            disagreements.push(Disagreement::TokenBoundary {
+                kind: node.kind.clone(),
+                start: node.start,
+                end: node.end,
+            });
+        }
+        pending.extend(node.children.iter().rev());
+    }
+
+    match (parser_error, grammar_errors.iter().min()) {
+        (Some(parser), Some(grammar)) if parser.row != grammar.row => {
+            disagreements.push(Disagreement::ErrorPosition {
+                parser,
+                grammar: *grammar,
+            })
+        }
+        (Some(parser), None) => disagreements.push(Disagreement::ErrorOnlyInParser(parser)),
+        (None, Some(grammar)) => disagreements.push(Disagreement::ErrorOnlyInGrammar(*grammar)),
+        _ => {}
+    }
+    disagreements
+}
+
+/// A tree-sitter corpus test named `name`, expecting `tree` for `source`
+pub fn corpus_entry(name: &str, source: &str, tree: &SyntaxNode) -> String {
+    let rule = "=".repeat(name.chars().count().max(3));
+    let mut expected = String::new();
+    tree.write_expected(0, &mut expected);
+    format!(
+        "{}\n{}\n{}\n\n{}\n\n---\n\n{}\n",
+        rule,
+        name,
+        rule,
+        source.trim_end(),
+        expected
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::{Token, TokenKind};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_generated_grammar_and_cross_validation() {
+        let grammar = grammar_js();
+        assert!(grammar.contains("    program: $ => optional($.block),\n"));
+        assert!(grammar.contains("    block: $ => repeat1(choice($.statement, "));
+        assert!(grammar.contains("\"(\", optional($.parameters), \")\""));
+        // Every rule used is defined, and every token rule is written out
+        let defined: Vec<String> = grammar
+            .lines()
+            .filter_map(|line| {
+                line.trim()
+                    .split_once(": $ =>")
+                    .map(|(name, _)| name.to_string())
+            })
+            .collect();
+        for used in grammar.split("$.").skip(1) {
+            let name: String = used
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+                .collect();
+            assert!(defined.contains(&name), "{} is used but not defined", name);
+        }
+        for rule in rules().iter().filter(|rule| rule.section == "Tokens") {
+            assert!(
+                TOKENS.iter().any(|(name, _)| *name == rule.name),
+                "{}",
+                rule.name
+            );
+        }
+
+        let source = "local x = 1 // one\n";
+        let agreeing = SyntaxNode::parse(
+            "(program [0, 0] - [1, 0]\n  (block [0, 0] - [0, 11]\n    (statement [0, 0] - [0, 11]\n      \
+             (variable_declaration [0, 0] - [0, 11]\n        (variable_target [0, 6] - [0, 7]\n          \
+             (pattern [0, 6] - [0, 7] (identifier [0, 6] - [0, 7])))\n        \
+             value: (expression [0, 10] - [0, 11] (number [0, 10] - [0, 11])))))\n  \
+             (comment [0, 12] - [0, 18]))",
+        )
+        .unwrap();
+        assert!(cross_validate(source, &agreeing).is_empty());
+        let entry = corpus_entry("declaration", source, &agreeing);
+        assert!(entry
+            .starts_with("===========\ndeclaration\n===========\n\nlocal x = 1 // one\n\n---\n"));
+        assert!(
+            entry.contains("\n        value: (expression\n          (number)))))\n  (comment))\n")
+        );
+
+        let split =
+            SyntaxNode::parse("(program [0, 0] - [1, 0] (identifier [0, 6] - [0, 8]))").unwrap();
+        assert_eq!(
+            cross_validate("local x = 1\n", &split)[0],
+            Disagreement::TokenBoundary {
+                kind: "identifier".to_string(),
+                start: Point { row: 0, column: 6 },
+                end: Point { row: 0, column: 8 },
+            }
+        );
+        assert!(cross_validate("local xy = 1\n", &split).is_empty());
+
+        let source = "function f()\n  return 1\n";
+        let missing = SyntaxNode::parse(
+            "(program [0, 0] - [2, 0] (function_declaration [0, 0] - [2, 0] \
+             name: (identifier [0, 9] - [0, 10]) (MISSING \"end\" [2, 0] - [2, 0])))",
+        )
+        .unwrap();
+        assert!(cross_validate(source, &missing).is_empty());
+        assert_eq!(
+            missing.children[0].children[1].missing.as_deref(),
+            Some("\"end\"")
+        );
+        let early = SyntaxNode::parse("(program [0, 0] - [2, 0] (ERROR [0, 0] - [0, 8]))").unwrap();
+        assert_eq!(
+            cross_validate(source, &early),
+            vec![Disagreement::ErrorPosition {
+                parser: Point { row: 2, column: 0 },
+                grammar: Point { row: 0, column: 0 },
+            }]
+        );
+    }
+
+    /// Programs using each kind of statement, declaration, expression and
+    /// type, besides the standard library's declarations
+    const CORPUS: &[&str] = &[
+        "local a, b, ...rest = f()\nconst c: number = 1\n\
+         local { x, y: [first, , ...others] } = p\n",
+        "function add<T extends number>(a: T, b?: T, ...more: T[]): T\n\
+         return a + (b or 0)\nend\n",
+        "if a then f() elseif b then g() else h() end\nwhile x do x = x - 1 end\n\
+         repeat x = x + 1 until x > 10\n",
+        "for i = 1, 10, 2 do print(i) end\nfor k, v in pairs(t) do continue end\n\
+         outer: for i = 1, 3 do\n  while true do\n    break outer\n  end\nend\n",
+        "interface Shape extends Named, Sized {\n  readonly name: string\n  area?: number,\n  \
+         scale<T>(by: T): Shape;\n  [key: string]: unknown\n}\n",
+        "type Pair<K, V = K> = { first: K, second: V } | [K, V] | nil\n\
+         type Handler = (event: string, ...args: unknown[]) -> (boolean, string...)\n\
+         type Keys = keyof Shape\ntype Listof = {string}\n\
+         type Guard = (x: unknown) -> asserts x is string\n",
+        "const enum Color { Red = 1, Green = \"g\", Blue = 3, }\nenum Empty {}\n",
+        "declare function print(...values: unknown[]): void\ndeclare const VERSION: string\n\
+         declare local debug: boolean\n",
+        "import { a, b as c } from \"./m\"\nimport type { T } from \"./types\"\n\
+         import * as all from \"./all\"\nimport lazy heavy from \"./heavy\"\n\
+         export function f() end\nexport { a as d }\nexport default 1\n",
+        "@sealed\nabstract class Animal<T> extends mixin(Walker, Swimmer) implements Named {\n  \
+         private static count: number = 0\n  readonly name: string\n  const legs = 4\n  \
+         constructor(name: string) { self.name = name }\n  \
+         get label(): string { return self.name }\n  \
+         set label(value: string) { self.name = value }\n  abstract speak(): string\n  \
+         @memo\n  public override move<U>(to: U): void { }\n  static do count = 1 end\n}\n",
+        "namespace Geometry { const origin = [0, 0] }\nuntyped do raw() end\ntrust do raw() end\n\
+         using file = open(\"x\")\n",
+        "macro twice(x) x() x() end\ntwice(tick)\n",
+        "local r = a |> f |> g\nlocal s = #t + -n * 2 ^ 3 ^ 2 // 1 % 3 .. \"x\"\n\
+         local b = a < b and c <= d or e ~= f and not g == h and i != j\n\
+         local bits = a | b ~ c & d << 2 >> 1 + ~e\nlocal c = ok ? 1 : 2\n",
+        "local o = obj?.field.other[1](a, ...rest)::method(b)\n\
+         local t = { x = 1, [k] = v, ...base, }\nlocal l = [1, ...more, 3,]\n\
+         local fn = function<T>(x: T): T return x end\nlocal arrow = (a: number, b) => a + b\n\
+         local single = x => { return x }\nlocal msg = `hello ${name} ${n:%d}`\n",
+        "local kind = match value {\n  0 => \"zero\",\n  [a, b] when a > b => \"pair\"\n  \
+         { x } => { return \"point\" }\n  _ => \"other\"\n}\n",
+        "return 1, 2;\n;\n",
+    ];
+
+    /// Programs both should find an error in, on the same line
+    const ERRORS: &[&str] = &[
+        "local x = 1\nlocal = 2\n",
+        "function f()\n  return 1\n",
+        "if x then\nelse\nelse\nend\n",
+        "local t = { x = 1\nlocal y = 2\n",
+    ];
+
+    #[test]
+    fn test_rules_read_the_examples_and_corpus_as_the_parser_does() {
+        let check = |source: &str| {
+            let disagreements = cross_validate(source, &parse_with_rules(source));
+            let found: Vec<String> = disagreements.iter().map(ToString::to_string).collect();
+            assert!(found.is_empty(), "{}\n---\n{}", source, found.join("\n"));
+        };
+
+        // The examples in the rules' comments, types written as aliases
+        for rule in rules() {
+            let doc = rule.doc.unwrap_or_default();
+            for example in doc.split('`').skip(1).step_by(2) {
+                match rule.section.as_str() {
+                    "Types, loosest binding first" => check(&format!("type T = {}\n", example)),
+                    _ => check(example),
+                }
+            }
+        }
+        for source in CORPUS {
+            assert!(parse_with_rules(source).children.is_empty(), "{}", source);
+            check(source);
+        }
+        for source in ERRORS {
+            assert!(!parse_with_rules(source).children.is_empty(), "{}", source);
+            check(source);
+        }
+        for declarations in [
+            include_str!("../typechecker/stdlib/globals.d.tl"),
+            include_str!("../typechecker/stdlib/string.d.tl"),
+            include_str!("../typechecker/stdlib/table.d.tl"),
+            include_str!("../typechecker/stdlib/math.d.tl"),
+            include_str!("../typechecker/stdlib/io.d.tl"),
+            include_str!("../typechecker/stdlib/os.d.tl"),
+            include_str!("../typechecker/stdlib/coroutine.d.tl"),
+            include_str!("../typechecker/stdlib/debug.d.tl"),
+            include_str!("../typechecker/stdlib/random.d.tl"),
+        ] {
+            assert!(parse_with_rules(declarations).children.is_empty());
+            check(declarations);
+        }
+    }
+
+    /// Read `source` with the rules themselves, as a grammar built from
+    /// them would: a `program`, with an `ERROR` from the furthest token no
+    /// reading of the rules gets past when none reads it all
+    fn parse_with_rules(source: &str) -> SyntaxNode {
+        let definitions: HashMap<String, Expr> = rules()
+            .into_iter()
+            .filter(|rule| rule.section != "Tokens" && rule.section != "Keywords")
+            .map(|rule| (rule.name, Expr::parse(&rule.definition)))
+            .collect();
+        let chars: Vec<char> = source.chars().collect();
+        let point = |offset: usize| {
+            let before = &chars[..offset];
+            let row = before.iter().filter(|c| **c == '\n').count();
+            let line = before.iter().rev().take_while(|c| **c != '\n');
+            let column = line.map(|c| c.len_utf8()).sum();
+            Point { row, column }
+        };
+
+        let mut program = SyntaxNode {
+            kind: "program".to_string(),
+            field: None,
+            missing: None,
+            start: point(0),
+            end: point(chars.len()),
+            children: Vec::new(),
+        };
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let error_from = match Lexer::new(source, handler).tokenize() {
+            Ok(mut tokens) => {
+                tokens.retain(|token| token.kind != TokenKind::Eof);
+                let texts: Vec<String> = tokens
+                    .iter()
+                    .map(|token| chars[token.span.start..token.span.end].iter().collect())
+                    .collect();
+                let mut reader = RuleReader {
+                    definitions: &definitions,
+                    tokens: &tokens,
+                    texts: &texts,
+                    memo: HashMap::new(),
+                    furthest: 0,
+                };
+                let ends = reader.read(&Expr::Rule("Program".to_string()), 0);
+                (!ends.contains(&tokens.len())).then(|| {
+                    let furthest = tokens.get(reader.furthest);
+                    furthest.map_or(program.end, |token| point(token.span.start))
+                })
+            }
+            Err(_) => Some(program.start),
+        };
+        if let Some(start) = error_from {
+            program.children.push(SyntaxNode {
+                kind: "ERROR".to_string(),
+                start,
+                ..program.clone()
+            });
+        }
+        program
+    }
+
+    /// Every way the rules can read tokens, as the positions each reading
+    /// of a rule from a token can end at
+    struct RuleReader<'a> {
+        definitions: &'a HashMap<String, Expr>,
+        tokens: &'a [Token],
+        texts: &'a [String],
+        memo: HashMap<(String, usize), BTreeSet<usize>>,
+        /// The furthest token a reading failed at
+        furthest: usize,
+    }
+
+    impl RuleReader<'_> {
+        fn read(&mut self, expr: &Expr, from: usize) -> BTreeSet<usize> {
+            match expr {
+                Expr::Literal(text) => {
+                    let matched = self.tokens.get(from).is_some_and(|token| {
+                        let literal = !matches!(
+                            token.kind,
+                            TokenKind::Number(_)
+                                | TokenKind::String(_)
+                                | TokenKind::TemplateString(_)
+                        );
+                        literal && self.texts[from] == *text
+                    });
+                    self.token(from, matched)
+                }
+                Expr::Rule(name) if TOKENS.iter().any(|(token, _)| token == name) => {
+                    let matched = self.tokens.get(from).is_some_and(|token| {
+                        matches!(
+                            (name.as_str(), &token.kind),
+                            ("Identifier", TokenKind::Identifier(_))
+                                | ("Number", TokenKind::Number(_))
+                                | ("String", TokenKind::String(_))
+                                | ("TemplateString", TokenKind::TemplateString(_))
+                        )
+                    });
+                    self.token(from, matched)
+                }
+                Expr::Rule(name) => {
+                    let key = (name.clone(), from);
+                    if let Some(ends) = self.memo.get(&key) {
+                        return ends.clone();
+                    }
+                    // The rules are not left recursive, so this is never read
+                    self.memo.insert(key.clone(), BTreeSet::new());
+                    let definitions = self.definitions;
+                    let ends = self.read(&definitions[name], from);
+                    self.memo.insert(key, ends.clone());
+                    ends
+                }
+                Expr::Seq(items) => items.iter().fold(BTreeSet::from([from]), |starts, item| {
+                    starts
+                        .into_iter()
+                        .flat_map(|start| self.read(item, start))
+                        .collect()
+                }),
+                Expr::Choice(items) => items
+                    .iter()
+                    .flat_map(|item| self.read(item, from))
+                    .collect(),
+                Expr::Optional(inner) => {
+                    let mut ends = self.read(inner, from);
+                    ends.insert(from);
+                    ends
+                }
+                Expr::Repeat(inner) => self.repeat(inner, BTreeSet::from([from])),
+                Expr::Repeat1(inner) => {
+                    let once = self.read(inner, from);
+                    self.repeat(inner, once)
+                }
+            }
+        }
+
+        /// `ends`, and wherever further readings of `inner` from them end
+        fn repeat(&mut self, inner: &Expr, mut ends: BTreeSet<usize>) -> BTreeSet<usize> {
+            let mut pending: Vec<usize> = ends.iter().copied().collect();
+            while let Some(start) = pending.pop() {
+                for end in self.read(inner, start) {
+                    if ends.insert(end) {
+                        pending.push(end);
+                    }
+                }
+            }
+            ends
+        }
+
+        fn token(&mut self, from: usize, matched: bool) -> BTreeSet<usize> {
+            if matched {
+                return BTreeSet::from([from + 1]);
+            }
+            self.furthest = self.furthest.max(from);
+            BTreeSet::new()
+        }
+    }
+}
@@ -0,0 +1,451 @@
+//! Printing the types the checker resolved for a module (`typedlua
+//! dump-types`), and how it arrived at one of them (`typedlua
+//! explain-type`), for debugging inference without an editor attached
+
+use crate::ast::statement::{ExportKind, ImportClause, Statement};
+use crate::build::{exported_names, parse_module};
+use crate::codegen::collect_bound_names;
+use crate::config::CompilerConfig;
+use crate::diagnostics::DiagnosticHandler;
+use crate::errors::CompilationError;
+use crate::parser;
+use crate::span::Span;
+use crate::typechecker::{NameTrace, NarrowingCause, SymbolKind, TypeChecker, TypeDiff};
+use std::fmt;
+use std::sync::Arc;
+
+/// A top-level name of a module and the type the checker gave it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    pub name: String,
+    /// `const`, `local`, `function`, `class`, `enum` or `namespace`
+    pub kind: &'static str,
+    pub typ: String,
+    /// The names the module exports it as
+    pub exports: Vec<String>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{} ", self.line, self.column)?;
+        if !self.exports.is_empty() {
+            write!(f, "export ")?;
+        }
+        write!(f, "{} {}: {}", self.kind, self.name, self.typ)?;
+        let renamed: Vec<&str> = self
+            .exports
+            .iter()
+            .filter(|name| **name != self.name)
+            .map(String::as_str)
+            .collect();
+        if !renamed.is_empty() {
+            write!(f, " (exported as {})", renamed.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// What `dump_types` found
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDump {
+    /// Top-level bindings in source order
+    pub bindings: Vec<Binding>,
+    /// The innermost expression or declared name at the requested offset,
+    /// and its type
+    pub at_offset: Option<(Span, String)>,
+}
+
+/// Check a module and list the resolved type of each top-level binding,
+/// and with `offset`, a byte offset into the source, the type found there.
+/// Type errors are reported but do not stop the dump, since inference is
+/// most often inspected while it is going wrong.
+pub fn dump_types(
+    source: &str,
+    offset: Option<usize>,
+    config: Arc<CompilerConfig>,
+    diagnostic_handler: Arc<dyn DiagnosticHandler>,
+) -> Result<TypeDump, CompilationError> {
+    let parsed = parse_module(source, diagnostic_handler.clone())?;
+    let mut checker = TypeChecker::new(config, diagnostic_handler);
+    checker.set_directives(&parsed.directives);
+    // Spans count characters
+    if let Some(offset) = offset.and_then(|offset| source.get(..offset)) {
+        checker.probe_offset(offset.chars().count());
+    }
+    let _ = checker.check_program(&parsed.program);
+
+    let exports = exported_names(&parsed.program.statements);
+    let mut names = Vec::new();
+    for statement in &parsed.program.statements {
+        top_level_names(statement, &mut names);
+    }
+    let mut bindings: Vec<Binding> = Vec::new();
+    for name in names {
+        if bindings.iter().any(|binding| binding.name == name) {
+            continue;
+        }
+        let Some(symbol) = checker.symbols().lookup(&name) else {
+            continue;
+        };
+        bindings.push(Binding {
+            kind: kind_label(symbol.kind),
+            typ: symbol.typ.to_string(),
+            exports: exports
+                .iter()
+                .filter(|(_, local)| *local == name)
+                .map(|(exported, _)| exported.clone())
+                .collect(),
+            line: symbol.span.line,
+            column: symbol.span.column,
+            name,
+        });
+    }
+    Ok(TypeDump {
+        bindings,
+        at_offset: checker
+            .probed_type()
+            .map(|(span, typ)| (*span, typ.to_string())),
+    })
+}
+
+/// How the checker arrived at the type of the innermost expression or name
+/// at an offset
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeExplanation {
+    pub span: Span,
+    pub typ: String,
+    /// The declaration and narrowings behind the type, the types of the
+    /// expressions enclosing it, and why any mismatch reported there
+    /// failed, one line each
+    pub steps: Vec<String>,
+}
+
+/// Check a module and explain the type at `offset`, a byte offset into the
+/// source. `None` when nothing there has a type.
+pub fn explain_type(
+    source: &str,
+    offset: usize,
+    config: Arc<CompilerConfig>,
+    diagnostic_handler: Arc<dyn DiagnosticHandler>,
+) -> Result<Option<TypeExplanation>, CompilationError> {
+    let parsed = parse_module(source, diagnostic_handler.clone())?;
+    let mut checker = TypeChecker::new(config, diagnostic_handler);
+    checker.set_directives(&parsed.directives);
+    let Some(offset) = source.get(..offset).map(|text| text.chars().count()) else {
+        return Ok(None);
+    };
+    checker.explain_offset(offset);
+    let _ = checker.check_program(&parsed.program);
+
+    let (Some((span, typ)), Some(trace)) = (checker.probed_type(), checker.type_trace()) else {
+        return Ok(None);
+    };
+    let text = |span: Span| excerpt(source, span);
+    let mut steps = Vec::new();
+    if let Some(name) = &trace.name {
+        steps.extend(name_steps(name, &text));
+    }
+    let enclosing = trace.expressions.iter().filter(|(other, _)| other != span);
+    for (span, typ) in enclosing {
+        steps.push(format!("`{}` at {} is `{}`", text(*span), span, typ));
+    }
+    for (span, chain) in &trace.mismatches {
+        steps.push(format!("mismatch at {}:", span));
+        steps.extend(
+            chain
+                .iter()
+                .enumerate()
+                .map(|(depth, reason)| format!("{}{}", "  ".repeat(depth + 1), reason)),
+        );
+    }
+    Ok(Some(TypeExplanation {
+        span: *span,
+        typ: typ.to_string(),
+        steps,
+    }))
+}
+
+/// The type compared by `diff_types`
+#[derive(Debug, Clone, Copy)]
+pub enum DiffSource<'a> {
+    /// A type written as in an annotation, such as an interface name
+    Written(&'a str),
+    /// The type of the innermost expression or name at a byte offset
+    AtOffset(usize),
+}
+
+/// Check a module and compare a type with the `expected` type written as
+/// in an annotation, both resolved in the module's scope. `None` when
+/// nothing at the offset has a type.
+pub fn diff_types(
+    source: &str,
+    actual: DiffSource,
+    expected: &str,
+    config: Arc<CompilerConfig>,
+    diagnostic_handler: Arc<dyn DiagnosticHandler>,
+) -> Result<Option<TypeDiff>, CompilationError> {
+    let parsed = parse_module(source, diagnostic_handler.clone())?;
+    let written = |text: &str| {
+        parser::parse_type(text, diagnostic_handler.clone())
+            .map_err(|_| CompilationError::ParseErrors(1))
+    };
+    let expected = written(expected)?;
+    let written_actual = match actual {
+        DiffSource::Written(text) => Some(written(text)?),
+        DiffSource::AtOffset(_) => None,
+    };
+
+    let mut checker = TypeChecker::new(config, diagnostic_handler);
+    checker.set_directives(&parsed.directives);
+    if let DiffSource::AtOffset(offset) = actual {
+        let Some(offset) = source.get(..offset).map(|text| text.chars().count()) else {
+            return Ok(None);
+        };
+        checker.probe_offset(offset);
+    }
+    // The module's own errors are reported, but its types are still compared
+    let _ = checker.check_program(&parsed.program);
+
+    let actual = match written_actual {
+        Some(actual) => actual,
+        None => match checker.probed_type() {
+            Some((_, typ)) => typ.clone(),
+            None => return Ok(None),
+        },
+    };
+    Ok(Some(checker.diff_types(&actual, &expected)))
+}
+
+fn name_steps(name: &NameTrace, text: &impl Fn(Span) -> String) -> Vec<String> {
+    let origin = if name.inferred {
+        "inferred from its initializer"
+    } else {
+        "as declared"
+    };
+    let mut steps = vec![format!(
+        "`{}` is a {} declared at {}: `{}`, {}",
+        name.name,
+        kind_label(name.kind),
+        name.declared_at,
+        name.declared,
+        origin
+    )];
+    if let Some((typ, cause)) = &name.narrowed {
+        steps.push(match cause {
+            Some(NarrowingCause::Condition { span, truthy }) => format!(
+                "narrowed to `{}` where `{}` at {} is {}",
+                typ,
+                text(*span),
+                span,
+                if *truthy { "true" } else { "false" }
+            ),
+            Some(NarrowingCause::Assertion(span)) => {
+                format!("narrowed to `{}` after `{}` at {}", typ, text(*span), span)
+            }
+            Some(NarrowingCause::Assignment(span)) => format!(
+                "narrowed to `{}` by assigning `{}` at {}",
+                typ,
+                text(*span),
+                span
+            ),
+            None => format!("narrowed to `{}` by control flow", typ),
+        });
+    }
+    steps
+}
+
+/// The source a span covers, on one line and cut short when long
+fn excerpt(source: &str, span: Span) -> String {
+    const LIMIT: usize = 40;
+    let text: String = source
+        .chars()
+        .skip(span.start)
+        .take(span.len())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if text.chars().count() > LIMIT {
+        format!("{}...", text.chars().take(LIMIT).collect::<String>())
+    } else {
+        text
+    }
+}
+
+/// The names a top-level statement binds
+pub(crate) fn top_level_names(statement: &Statement, names: &mut Vec<String>) {
+    match statement {
+        Statement::Variable(decl) => collect_bound_names(&decl.pattern, names),
+        Statement::Function(func) => names.push(func.name.node.clone()),
+        Statement::Class(class) => names.push(class.name.node.clone()),
+        Statement::Enum(decl) => names.push(decl.name.node.clone()),
+        Statement::Namespace(namespace) => names.push(namespace.name.node.clone()),
+        Statement::Import(import) => match &import.clause {
+            ImportClause::Default(name) | ImportClause::Namespace(name) => {
+                names.push(name.node.clone())
+            }
+            ImportClause::Named(specifiers) => names.extend(
+                specifiers
+                    .iter()
+                    .map(|specifier| specifier.local.as_ref().unwrap_or(&specifier.imported))
+                    .map(|name| name.node.clone()),
+            ),
+            ImportClause::TypeOnly(_) => {}
+        },
+        Statement::Export(export) => {
+            if let ExportKind::Declaration(decl) = &export.kind {
+                top_level_names(decl, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn kind_label(kind: SymbolKind) -> &'static str {
+    match kind {
+        SymbolKind::Const => "const",
+        SymbolKind::Variable => "local",
+        SymbolKind::Function => "function",
+        SymbolKind::Class => "class",
+        SymbolKind::Namespace => "namespace",
+        SymbolKind::Enum => "enum",
+        SymbolKind::Parameter => "parameter",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::CollectingDiagnosticHandler;
+
+    fn dump(source: &str, offset: Option<usize>) -> TypeDump {
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        dump_types(source, offset, Arc::new(CompilerConfig::default()), handler)
+            .expect("Dump failed")
+    }
+
+    #[test]
+    fn test_dump_types_lists_top_level_bindings() {
+        let source = r#"const name = "lua"
+local count = 1 + 2
+function greet(who: string): string
+    local message = "hi " .. who
+    return message
+end
+export { greet as hello }
+export const total: number = count
+"#;
+        let lines: Vec<String> = dump(source, None)
+            .bindings
+            .iter()
+            .map(|binding| binding.to_string())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "1:7 const name: \"lua\"",
+                "2:7 local count: number",
+                "3:10 export function greet: (who: string) -> string (exported as hello)",
+                "8:14 export const total: number",
+            ]
+        );
+
+        let offset = source.find("message\n").unwrap();
+        let (span, typ) = dump(source, Some(offset)).at_offset.unwrap();
+        assert_eq!((span.line, typ.as_str()), (5, "string"));
+    }
+
+    #[test]
+    fn test_explain_type_traces_narrowing_and_mismatches() {
+        let source = r#"function greet(name: string?, count: integer): string
+    if name ~= nil then
+        return name .. "!"
+    end
+    local point: { x: number } = { x = "left" }
+    return "nobody"
+end
+"#;
+        let explain = |offset: usize| {
+            let handler = Arc::new(CollectingDiagnosticHandler::new());
+            explain_type(source, offset, Arc::new(CompilerConfig::default()), handler)
+                .expect("Explain failed")
+                .expect("Nothing typed at offset")
+        };
+
+        let explanation = explain(source.find("name ..").unwrap());
+        assert_eq!(
+            (explanation.span.line, explanation.typ.as_str()),
+            (3, "string")
+        );
+        assert_eq!(
+            explanation.steps,
+            vec![
+                "`name` is a parameter declared at 1:16: `string?`, as declared",
+                "narrowed to `string` where `name ~= nil` at 2:8 is true",
+                "`name .. \"!\"` at 3:16 is `string`",
+            ]
+        );
+
+        let explanation = explain(source.find("\"left\"").unwrap());
+        assert_eq!(
+            explanation.steps,
+            vec![
+                "`{ x = \"left\" }` at 5:34 is `{ x: string }`",
+                "mismatch at 5:34:",
+                "  `{ x: string }` is not assignable to `{ x: number }`",
+                "    property `x`: `string` is not assignable to `number`",
+                "      `string` and `number` are different types",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_types_lists_differing_members() {
+        let source = r#"interface Shape {
+    name: string
+    area(): number
+    color?: string
+}
+interface Square {
+    name: integer
+    side: number
+}
+local sq: Square = { name = 1, side = 2 }
+"#;
+        let diff = |actual: DiffSource, expected: &str| {
+            let handler = Arc::new(CollectingDiagnosticHandler::new());
+            diff_types(
+                source,
+                actual,
+                expected,
+                Arc::new(CompilerConfig::default()),
+                handler,
+            )
+            .expect("Diff failed")
+            .expect("Nothing typed at offset")
+        };
+
+        let shown = diff(DiffSource::Written("Square"), "Shape").to_string();
+        assert_eq!(
+            shown.lines().collect::<Vec<_>>(),
+            vec![
+                "`Square` -> `Shape`: not assignable",
+                "  ~ name: integer, expected string",
+                "      `integer` and `string` are different types",
+                "  - area: () -> number (missing)",
+                "  ? color: string? (optional, missing)",
+                "  + side: number (only in `Square`)",
+            ]
+        );
+
+        let offset = source.rfind("sq").unwrap();
+        let diff = diff(
+            DiffSource::AtOffset(offset),
+            "{ name: number, side: number }",
+        );
+        assert!(diff.assignable);
+        assert_eq!(diff.entries.len(), 2);
+    }
+}
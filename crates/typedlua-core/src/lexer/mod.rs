@@ -1,10 +1,11 @@
 mod token;
 
-pub use token::{TemplatePart, Token, TokenKind};
+pub use token::{tokens_to_source, TemplatePart, Token, TokenKind, KEYWORDS};
 
-use crate::diagnostics::DiagnosticHandler;
+use crate::diagnostics::{Diagnostic, DiagnosticHandler};
 use crate::errors::LexerError;
 use crate::span::Span;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
 /// Lexer for TypedLua source code
@@ -14,6 +15,21 @@ pub struct Lexer {
     line: usize,
     column: usize,
     diagnostic_handler: Arc<dyn DiagnosticHandler>,
+    directives: Vec<Directive>,
+    comments: Vec<Span>,
+}
+
+/// A `// @name` comment, such as `// @varargs-ok`, relaxing a check for
+/// the code on its line and the line after
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Directive {
+    pub name: String,
+    /// Words after the name, such as the rules of `// @lint-ignore
+    /// unused-local`
+    pub arguments: Vec<String>,
+    pub line: usize,
+    /// The comment
+    pub span: Span,
 }
 
 impl Lexer {
@@ -24,10 +40,25 @@ impl Lexer {
             line: 1,
             column: 1,
             diagnostic_handler,
+            directives: Vec::new(),
+            comments: Vec::new(),
         }
     }
 
-    /// Tokenize the entire source
+    /// The `// @name` directives met while tokenizing
+    pub fn directives(&self) -> &[Directive] {
+        &self.directives
+    }
+
+    /// The spans of the `//` and `/* */` comments met while tokenizing, in
+    /// source order. Tokens leave comments out, so tools that rewrite
+    /// source, such as the formatter, read them from here.
+    pub fn comments(&self) -> &[Span] {
+        &self.comments
+    }
+
+    /// Tokenize the entire source. Lexing stops at a string or number it
+    /// cannot read, after reporting it to the diagnostic handler.
     pub fn tokenize(&mut self) -> Result<Vec<Token>, LexerError> {
         let mut tokens = Vec::new();
 
@@ -42,11 +73,30 @@ impl Lexer {
                 continue;
             }
 
-            let token = self.next_token()?;
-            tokens.push(token);
+            let (start, line, column) = (self.position, self.line, self.column);
+            match self.next_token() {
+                Ok(token) => tokens.push(token),
+                Err(error) => {
+                    // An unterminated string runs to the end of the file,
+                    // so only its opening quote is marked. Otherwise the
+                    // character lexing stopped at is included.
+                    let end = match error {
+                        LexerError::UnterminatedString => start + 1,
+                        _ => (self.position + 1).min(self.source.len()),
+                    };
+                    let span = Span::new(start, end, line, column);
+                    self.diagnostic_handler.report(
+                        Diagnostic::error(span, error.to_string()).with_code(error.code()),
+                    );
+                    return Err(error);
+                }
+            }
         }
 
-        tokens.push(Token::eof(self.position));
+        tokens.push(Token::new(
+            TokenKind::Eof,
+            Span::new(self.position, self.position, self.line, self.column),
+        ));
         Ok(tokens)
     }
 
@@ -121,7 +171,13 @@ impl Lexer {
             }
             '?' => {
                 self.advance();
-                TokenKind::Question
+                // `?.5` is a conditional followed by a number, not `?.`
+                if self.current() == '.' && !self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    self.advance();
+                    TokenKind::QuestionDot
+                } else {
+                    TokenKind::Question
+                }
             }
             '@' => {
                 self.advance();
@@ -232,9 +288,10 @@ impl Lexer {
 
             _ => {
                 self.advance();
-                self.diagnostic_handler.error(
-                    Span::new(start, self.position, start_line, start_column),
-                    &format!("Unexpected character: '{}'", ch),
+                let span = Span::new(start, self.position, start_line, start_column);
+                self.diagnostic_handler.report(
+                    Diagnostic::error(span, format!("Unexpected character: '{}'", ch))
+                        .with_code("TL1001"),
                 );
                 TokenKind::Unknown(ch)
             }
@@ -260,39 +317,26 @@ impl Lexer {
     fn read_number(&mut self) -> Result<TokenKind, LexerError> {
         let mut number = String::new();
 
-        // Handle hex numbers (0x...)
-        if self.current() == '0' && self.peek() == Some('x') {
-            number.push(self.current());
-            self.advance();
-            number.push(self.current());
-            self.advance();
-
-            while !self.is_at_end() && self.current().is_ascii_hexdigit() {
-                number.push(self.current());
-                self.advance();
-            }
-            return Ok(TokenKind::Number(number));
-        }
-
-        // Handle binary numbers (0b...)
-        if self.current() == '0' && self.peek() == Some('b') {
+        // Handle hex (0x...) and binary (0b...) integers
+        let radix_digit: Option<fn(char) -> bool> = match (self.current(), self.peek()) {
+            ('0', Some('x' | 'X')) => Some(|c| c.is_ascii_hexdigit()),
+            ('0', Some('b' | 'B')) => Some(|c| matches!(c, '0' | '1')),
+            _ => None,
+        };
+        if let Some(is_digit) = radix_digit {
             number.push(self.current());
             self.advance();
             number.push(self.current());
             self.advance();
 
-            while !self.is_at_end() && matches!(self.current(), '0' | '1') {
-                number.push(self.current());
-                self.advance();
-            }
+            self.read_digits(&mut number, is_digit)?;
+            self.read_integer_suffix(&mut number);
             return Ok(TokenKind::Number(number));
         }
 
         // Regular decimal number
-        while !self.is_at_end() && self.current().is_ascii_digit() {
-            number.push(self.current());
-            self.advance();
-        }
+        self.read_digits(&mut number, |c| c.is_ascii_digit())?;
+        let mut is_float = false;
 
         // Handle decimal point
         if !self.is_at_end()
@@ -301,11 +345,8 @@ impl Lexer {
         {
             number.push(self.current());
             self.advance();
-
-            while !self.is_at_end() && self.current().is_ascii_digit() {
-                number.push(self.current());
-                self.advance();
-            }
+            self.read_digits(&mut number, |c| c.is_ascii_digit())?;
+            is_float = true;
         }
 
         // Handle scientific notation (e.g., 1e10, 2.5e-3)
@@ -318,15 +359,65 @@ impl Lexer {
                 self.advance();
             }
 
-            while !self.is_at_end() && self.current().is_ascii_digit() {
-                number.push(self.current());
-                self.advance();
-            }
+            self.read_digits(&mut number, |c| c.is_ascii_digit())?;
+            is_float = true;
         }
 
+        if !is_float {
+            self.read_integer_suffix(&mut number);
+        }
         Ok(TokenKind::Number(number))
     }
 
+    /// Read digits with optional `_` separators (`1_000_000`). A separator
+    /// must sit between two digits.
+    fn read_digits(
+        &mut self,
+        number: &mut String,
+        is_digit: fn(char) -> bool,
+    ) -> Result<(), LexerError> {
+        let mut after_digit = false;
+        while !self.is_at_end() {
+            let ch = self.current();
+            if ch == '_' {
+                if !after_digit || !self.peek().is_some_and(is_digit) {
+                    number.push(ch);
+                    return Err(LexerError::InvalidNumber(number.clone()));
+                }
+                after_digit = false;
+            } else if is_digit(ch) {
+                after_digit = true;
+            } else {
+                break;
+            }
+            number.push(ch);
+            self.advance();
+        }
+        Ok(())
+    }
+
+    /// LuaJIT's `LL` and `ULL` suffixes, in either case, marking 64-bit
+    /// integer literals
+    fn read_integer_suffix(&mut self, number: &mut String) {
+        let ahead: String = self.source[self.position..].iter().take(3).collect();
+        let ahead = ahead.to_ascii_uppercase();
+        let length = if ahead.starts_with("ULL") {
+            3
+        } else if ahead.starts_with("LL") {
+            2
+        } else {
+            return;
+        };
+        let next = self.source.get(self.position + length);
+        if next.is_some_and(|c| c.is_alphanumeric() || *c == '_') {
+            return;
+        }
+        for _ in 0..length {
+            number.push(self.current());
+            self.advance();
+        }
+    }
+
     fn read_string(&mut self, quote: char) -> Result<TokenKind, LexerError> {
         self.advance(); // Skip opening quote
 
@@ -387,8 +478,10 @@ impl Lexer {
                 self.advance(); // Skip $
                 self.advance(); // Skip {
 
-                // Read expression tokens until }
+                // Read expression tokens until }, or until a `:%` format
+                // specifier, which runs to the }
                 let mut expr_tokens = Vec::new();
+                let mut format = None;
                 let mut brace_depth = 1;
 
                 while !self.is_at_end() && brace_depth > 0 {
@@ -402,6 +495,16 @@ impl Lexer {
                     }
 
                     self.skip_whitespace();
+                    if brace_depth == 1 && self.current() == ':' && self.peek() == Some('%') {
+                        self.advance(); // Skip :
+                        let mut specifier = String::new();
+                        while !self.is_at_end() && self.current() != '}' {
+                            specifier.push(self.current());
+                            self.advance();
+                        }
+                        format = Some(specifier.trim_end().to_string());
+                        continue;
+                    }
                     if !self.is_at_end() && self.current() != '}' {
                         let token = self.next_token()?;
                         expr_tokens.push(token);
@@ -413,7 +516,10 @@ impl Lexer {
                 }
 
                 self.advance(); // Skip }
-                parts.push(TemplatePart::Expression(expr_tokens));
+                parts.push(match format {
+                    Some(format) => TemplatePart::Formatted(expr_tokens, format),
+                    None => TemplatePart::Expression(expr_tokens),
+                });
             } else if self.current() == '\\' {
                 self.advance();
                 if !self.is_at_end() {
@@ -452,11 +558,28 @@ impl Lexer {
     }
 
     fn try_skip_comment(&mut self) -> bool {
+        let (start, line, column) = (self.position, self.line, self.column);
         // Single-line comment: //
         if self.current() == '/' && self.peek() == Some('/') {
             while !self.is_at_end() && self.current() != '\n' {
                 self.advance();
             }
+            let span = Span::new(start, self.position, line, column);
+            self.comments.push(span);
+            let start = start + 2;
+            let text: String = self.source[start..self.position].iter().collect();
+            if let Some(text) = text.trim().strip_prefix('@') {
+                let mut words = text.split_whitespace();
+                let name = words.next().unwrap_or_default();
+                if !name.is_empty() {
+                    self.directives.push(Directive {
+                        name: name.to_string(),
+                        arguments: words.map(str::to_string).collect(),
+                        line: self.line,
+                        span,
+                    });
+                }
+            }
             return true;
         }
 
@@ -469,16 +592,17 @@ impl Lexer {
                 if self.current() == '*' && self.peek() == Some('/') {
                     self.advance(); // Skip *
                     self.advance(); // Skip /
+                    self.comments
+                        .push(Span::new(start, self.position, line, column));
                     return true;
                 }
                 self.advance();
             }
 
             // Unterminated comment
-            self.diagnostic_handler.error(
-                Span::new(self.position, self.position, self.line, self.column),
-                "Unterminated comment",
-            );
+            let span = Span::new(self.position, self.position, self.line, self.column);
+            self.diagnostic_handler
+                .report(Diagnostic::error(span, "Unterminated comment").with_code("TL1002"));
             return true;
         }
 
@@ -561,6 +685,27 @@ mod tests {
         assert!(matches!(&tokens[5].kind, TokenKind::Number(n) if n == "2.5e-3"));
     }
 
+    #[test]
+    fn test_number_separators_and_suffixes() {
+        let tokens = lex("1_000_000 0XFF_FF 0b1010_0101 1_0.5_0 42LL 0x10ull 7LLx");
+
+        assert!(matches!(&tokens[0].kind, TokenKind::Number(n) if n == "1_000_000"));
+        assert!(matches!(&tokens[1].kind, TokenKind::Number(n) if n == "0XFF_FF"));
+        assert!(matches!(&tokens[2].kind, TokenKind::Number(n) if n == "0b1010_0101"));
+        assert!(matches!(&tokens[3].kind, TokenKind::Number(n) if n == "1_0.5_0"));
+        assert!(matches!(&tokens[4].kind, TokenKind::Number(n) if n == "42LL"));
+        assert!(matches!(&tokens[5].kind, TokenKind::Number(n) if n == "0x10ull"));
+        // A suffix must end the literal
+        assert!(matches!(&tokens[6].kind, TokenKind::Number(n) if n == "7"));
+        assert!(matches!(&tokens[7].kind, TokenKind::Identifier(i) if i == "LLx"));
+
+        for source in ["1__0", "1_", "0x_1", "1_.5"] {
+            let handler = Arc::new(CollectingDiagnosticHandler::new());
+            let mut lexer = Lexer::new(source, handler);
+            assert!(lexer.tokenize().is_err(), "{} should not lex", source);
+        }
+    }
+
     #[test]
     fn test_strings() {
         let tokens = lex(r#""hello" 'world' "escape\n\t\"" "#);
@@ -581,6 +726,14 @@ mod tests {
             assert!(matches!(&parts[1], TemplatePart::Expression(_)));
             assert!(matches!(&parts[2], TemplatePart::String(s) if s == " world"));
         }
+
+        let tokens = lex("`${score:%.2f} ${a:b()}`");
+        if let TokenKind::TemplateString(parts) = &tokens[0].kind {
+            assert!(
+                matches!(&parts[0], TemplatePart::Formatted(tokens, f) if tokens.len() == 1 && f == "%.2f")
+            );
+            assert!(matches!(&parts[2], TemplatePart::Expression(tokens) if tokens.len() == 5));
+        }
     }
 
     #[test]
@@ -618,6 +771,13 @@ mod tests {
         assert_eq!(tokens[2].kind, TokenKind::PipeOp);
     }
 
+    #[test]
+    fn test_optional_member_operator() {
+        let tokens = lex("a?.b c ?.5");
+        assert_eq!(tokens[1].kind, TokenKind::QuestionDot);
+        assert_eq!(tokens[4].kind, TokenKind::Question);
+    }
+
     #[test]
     fn test_dots() {
         let tokens = lex(". .. ...");
@@ -649,6 +809,28 @@ mod tests {
         assert_eq!(tokens[4].kind, TokenKind::Local);
     }
 
+    #[test]
+    fn test_directive_comments() {
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let mut lexer = Lexer::new(
+            "f(1) // @varargs-ok\n// not a directive\n//@inline",
+            handler,
+        );
+        lexer.tokenize().unwrap();
+        let directives: Vec<(&str, usize)> = lexer
+            .directives()
+            .iter()
+            .map(|directive| (directive.name.as_str(), directive.line))
+            .collect();
+        assert_eq!(directives, vec![("varargs-ok", 1), ("inline", 3)]);
+        let comments: Vec<(usize, usize, usize)> = lexer
+            .comments()
+            .iter()
+            .map(|span| (span.start, span.end, span.line))
+            .collect();
+        assert_eq!(comments, vec![(5, 19, 1), (20, 38, 2), (39, 48, 3)]);
+    }
+
     #[test]
     fn test_multi_line_comment() {
         let tokens = lex("const x = 5 /* this is\n a multi-line\n comment */ local y = 10");
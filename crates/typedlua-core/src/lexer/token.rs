@@ -1,7 +1,9 @@
 use crate::span::Span;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 
 /// Token kind representing different types of lexical elements
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TokenKind {
     // Keywords
     Const,
@@ -45,6 +47,7 @@ pub enum TokenKind {
     Static,
     Abstract,
     Readonly,
+    Override,
 
     // Identifiers and Literals
     Identifier(String),
@@ -78,6 +81,7 @@ pub enum TokenKind {
     FatArrow,     // =>
     PipeOp,       // |>
     Question,     // ?
+    QuestionDot,  // ?.
     Colon,        // :
     ColonColon,   // ::
     Bang,         // !
@@ -98,15 +102,64 @@ pub enum TokenKind {
     Unknown(char),
 }
 
+/// Every reserved word, as `TokenKind::from_keyword` reads them. Contextual
+/// keywords such as `declare` lex as identifiers and are not listed.
+pub const KEYWORDS: &[&str] = &[
+    "const",
+    "local",
+    "function",
+    "return",
+    "if",
+    "elseif",
+    "else",
+    "then",
+    "end",
+    "while",
+    "do",
+    "for",
+    "in",
+    "break",
+    "continue",
+    "repeat",
+    "until",
+    "and",
+    "or",
+    "not",
+    "true",
+    "false",
+    "nil",
+    "interface",
+    "type",
+    "enum",
+    "export",
+    "import",
+    "from",
+    "as",
+    "match",
+    "when",
+    "class",
+    "extends",
+    "implements",
+    "public",
+    "private",
+    "protected",
+    "static",
+    "abstract",
+    "readonly",
+    "override",
+];
+
 /// Part of a template literal
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TemplatePart {
     String(String),
     Expression(Vec<Token>),
+    /// Tokens of an expression followed by a `:%...` format specifier
+    Formatted(Vec<Token>, String),
 }
 
 /// A token with its kind and location
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Token {
     pub kind: TokenKind,
     pub span: Span,
@@ -171,6 +224,7 @@ impl TokenKind {
                 | TokenKind::Static
                 | TokenKind::Abstract
                 | TokenKind::Readonly
+                | TokenKind::Override
         )
     }
 
@@ -218,11 +272,152 @@ impl TokenKind {
             "static" => Some(TokenKind::Static),
             "abstract" => Some(TokenKind::Abstract),
             "readonly" => Some(TokenKind::Readonly),
+            "override" => Some(TokenKind::Override),
             _ => None,
         }
     }
 }
 
+/// Writes a token as it could appear in source. Strings are requoted, so
+/// escapes may be spelled differently than in the original.
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            TokenKind::Const => "const",
+            TokenKind::Local => "local",
+            TokenKind::Function => "function",
+            TokenKind::Return => "return",
+            TokenKind::If => "if",
+            TokenKind::Elseif => "elseif",
+            TokenKind::Else => "else",
+            TokenKind::Then => "then",
+            TokenKind::End => "end",
+            TokenKind::While => "while",
+            TokenKind::Do => "do",
+            TokenKind::For => "for",
+            TokenKind::In => "in",
+            TokenKind::Break => "break",
+            TokenKind::Continue => "continue",
+            TokenKind::Repeat => "repeat",
+            TokenKind::Until => "until",
+            TokenKind::And => "and",
+            TokenKind::Or => "or",
+            TokenKind::Not => "not",
+            TokenKind::True => "true",
+            TokenKind::False => "false",
+            TokenKind::Nil => "nil",
+            TokenKind::Interface => "interface",
+            TokenKind::Type => "type",
+            TokenKind::Enum => "enum",
+            TokenKind::Export => "export",
+            TokenKind::Import => "import",
+            TokenKind::From => "from",
+            TokenKind::As => "as",
+            TokenKind::Match => "match",
+            TokenKind::When => "when",
+            TokenKind::Class => "class",
+            TokenKind::Extends => "extends",
+            TokenKind::Implements => "implements",
+            TokenKind::Public => "public",
+            TokenKind::Private => "private",
+            TokenKind::Protected => "protected",
+            TokenKind::Static => "static",
+            TokenKind::Abstract => "abstract",
+            TokenKind::Readonly => "readonly",
+            TokenKind::Override => "override",
+            TokenKind::Identifier(name) => name,
+            TokenKind::Number(number) => number,
+            TokenKind::String(string) => return write!(f, "\"{}\"", escape_string(string)),
+            TokenKind::TemplateString(parts) => {
+                write!(f, "`")?;
+                for part in parts {
+                    match part {
+                        TemplatePart::String(text) => write!(f, "{}", text)?,
+                        TemplatePart::Expression(tokens) => {
+                            write!(f, "${{{}}}", tokens_to_source(tokens))?
+                        }
+                        TemplatePart::Formatted(tokens, format) => {
+                            write!(f, "${{{}:{}}}", tokens_to_source(tokens), format)?
+                        }
+                    }
+                }
+                return write!(f, "`");
+            }
+            TokenKind::Plus => "+",
+            TokenKind::Minus => "-",
+            TokenKind::Star => "*",
+            TokenKind::Slash => "/",
+            TokenKind::Percent => "%",
+            TokenKind::Caret => "^",
+            TokenKind::Hash => "#",
+            TokenKind::Ampersand => "&",
+            TokenKind::Pipe => "|",
+            TokenKind::Tilde => "~",
+            TokenKind::LessThan => "<",
+            TokenKind::LessEqual => "<=",
+            TokenKind::GreaterThan => ">",
+            TokenKind::GreaterEqual => ">=",
+            TokenKind::Equal => "=",
+            TokenKind::EqualEqual => "==",
+            TokenKind::BangEqual => "!=",
+            TokenKind::TildeEqual => "~=",
+            TokenKind::Dot => ".",
+            TokenKind::DotDot => "..",
+            TokenKind::DotDotDot => "...",
+            TokenKind::Arrow => "->",
+            TokenKind::FatArrow => "=>",
+            TokenKind::PipeOp => "|>",
+            TokenKind::Question => "?",
+            TokenKind::QuestionDot => "?.",
+            TokenKind::Colon => ":",
+            TokenKind::ColonColon => "::",
+            TokenKind::Bang => "!",
+            TokenKind::At => "@",
+            TokenKind::LeftParen => "(",
+            TokenKind::RightParen => ")",
+            TokenKind::LeftBrace => "{",
+            TokenKind::RightBrace => "}",
+            TokenKind::LeftBracket => "[",
+            TokenKind::RightBracket => "]",
+            TokenKind::Comma => ",",
+            TokenKind::Semicolon => ";",
+            TokenKind::Eof => "",
+            TokenKind::Unknown(ch) => return write!(f, "{}", ch),
+        };
+        write!(f, "{}", text)
+    }
+}
+
+fn escape_string(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+    for ch in string.chars() {
+        match ch {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Source text for a run of tokens. Tokens that were separated in the
+/// original are separated by a single space.
+pub fn tokens_to_source(tokens: &[Token]) -> String {
+    let mut text = String::new();
+    let mut previous_end = None;
+    for token in tokens {
+        if previous_end.is_some_and(|end| end < token.span.start) {
+            text.push(' ');
+        }
+        text.push_str(&token.kind.to_string());
+        previous_end = Some(token.span.end);
+    }
+    text
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -247,5 +442,23 @@ mod tests {
             Some(TokenKind::Interface)
         );
         assert_eq!(TokenKind::from_keyword("notakeyword"), None);
+        for keyword in KEYWORDS {
+            let kind = TokenKind::from_keyword(keyword).expect("listed keywords are keywords");
+            assert!(kind.is_keyword());
+            assert_eq!(kind.to_string(), *keyword);
+        }
+    }
+
+    #[test]
+    fn test_tokens_to_source() {
+        let span = |start, end| Span::new(start, end, 1, start + 1);
+        let tokens = vec![
+            Token::new(TokenKind::Identifier("t".to_string()), span(0, 1)),
+            Token::new(TokenKind::Dot, span(1, 2)),
+            Token::new(TokenKind::Identifier("n".to_string()), span(2, 3)),
+            Token::new(TokenKind::DotDot, span(4, 6)),
+            Token::new(TokenKind::String("a\"b".to_string()), span(7, 13)),
+        ];
+        assert_eq!(tokens_to_source(&tokens), "t.n .. \"a\\\"b\"");
     }
 }
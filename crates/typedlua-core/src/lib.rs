@@ -1,14 +1,42 @@
+pub mod annotate;
+#[allow(clippy::large_enum_variant)]
 pub mod ast;
+pub mod build;
+pub mod bundle;
+pub mod codegen;
+pub mod completion;
 pub mod config;
+pub mod config_check;
+pub mod constant;
+pub mod declarations;
 pub mod di;
 pub mod diagnostics;
+pub mod doctest;
 pub mod errors;
+pub mod explain;
+pub mod features;
+pub mod fix;
+pub mod format;
 pub mod fs;
+pub mod grammar;
+pub mod inspect;
 pub mod lexer;
+pub mod lint;
+pub mod minify;
+pub mod optimize;
 pub mod parser;
+pub mod plugin;
+pub mod rename;
+pub mod repl;
+pub mod report;
+pub mod resolve;
+pub mod sarif;
+pub mod shake;
 pub mod span;
+pub mod typechecker;
 
 pub use ast::{Program, Spanned};
+pub use codegen::CodeGenerator;
 pub use config::{CliOverrides, CompilerConfig};
 pub use di::Container;
 pub use diagnostics::{Diagnostic, DiagnosticHandler, DiagnosticLevel};
@@ -16,3 +44,4 @@ pub use errors::CompilationError;
 pub use lexer::{Lexer, Token, TokenKind};
 pub use parser::Parser;
 pub use span::Span;
+pub use typechecker::TypeChecker;
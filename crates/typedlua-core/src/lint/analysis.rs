@@ -0,0 +1,663 @@
+use crate::ast::expression::*;
+use crate::ast::pattern::{ArrayPatternElement, MultipleRest, Pattern};
+use crate::ast::statement::*;
+use crate::ast::types::{ObjectTypeMember, TemplateLiteralTypePart, Type, TypeKind};
+use crate::ast::{Ident, Program};
+use crate::span::Span;
+
+/// How a name came to be bound
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingKind {
+    Local,
+    Const,
+    /// `using name = value`, read when its block closes it
+    Using,
+    Function,
+    /// A parameter, or a name bound by a `match` arm pattern
+    Parameter,
+    LoopVariable,
+    Import,
+    /// A class, enum, namespace or ambient declaration
+    Declaration,
+}
+
+/// A name bound in the program, with what the rest of it did with the name
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub name: String,
+    pub span: Span,
+    pub kind: BindingKind,
+    /// How many times the name is read. Assigning to it is not a read.
+    pub reads: usize,
+    pub exported: bool,
+    /// The declaration of the same name in an enclosing scope it hides
+    pub shadows: Option<Span>,
+    /// The code the name is visible in, such as the function it is a
+    /// parameter of, or `None` at the top level of the module
+    pub scope: Option<Span>,
+}
+
+/// A use of a bound name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reference {
+    pub span: Span,
+    /// Index of the binding in `Analysis::bindings`
+    pub binding: usize,
+}
+
+/// The names and nodes of a program, collected in one pass for the lint
+/// rules and the language server to share
+#[derive(Debug, Default)]
+pub struct Analysis<'a> {
+    /// Every binding, in the order the declarations appear
+    pub bindings: Vec<Binding>,
+    /// Assignments to names no enclosing scope declares, which make globals
+    pub implicit_globals: Vec<Ident>,
+    /// Reads of and assignments to bound names, in the order walked
+    pub references: Vec<Reference>,
+    /// Names used in types, such as `User` in `user: User` or the whole of
+    /// `models.User`. Type names are not scoped the way values are, so
+    /// these are not matched to bindings.
+    pub type_references: Vec<Ident>,
+    /// Every statement, each before the statements nested in it
+    pub statements: Vec<&'a Statement>,
+    /// Every expression, each before the expressions nested in it
+    pub expressions: Vec<&'a Expression>,
+}
+
+impl<'a> Analysis<'a> {
+    pub fn new(program: &'a Program) -> Self {
+        let mut walker = Walker {
+            analysis: Analysis::default(),
+            scopes: vec![(None, Vec::new())],
+        };
+        walker.statements(&program.statements);
+        walker.analysis
+    }
+}
+
+struct Walker<'a> {
+    analysis: Analysis<'a>,
+    /// The code each open scope covers, and indices into the bindings of
+    /// the names it declares
+    scopes: Vec<(Option<Span>, Vec<usize>)>,
+}
+
+impl<'a> Walker<'a> {
+    fn lookup(&self, name: &str) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|(_, scope)| scope.iter().rev())
+            .copied()
+            .find(|&index| self.analysis.bindings[index].name == name)
+    }
+
+    fn declare(&mut self, name: &Ident, kind: BindingKind, exported: bool) {
+        let scope = self.scopes.len() - 1;
+        // Only names of enclosing scopes are shadowed; a redeclaration in
+        // the same scope is the type checker's concern
+        let shadows = self.scopes[..scope]
+            .iter()
+            .rev()
+            .flat_map(|(_, scope)| scope.iter().rev())
+            .map(|&index| &self.analysis.bindings[index])
+            .find(|binding| binding.name == name.node)
+            .map(|binding| binding.span);
+        self.analysis.bindings.push(Binding {
+            name: name.node.clone(),
+            span: name.span,
+            kind,
+            reads: 0,
+            exported,
+            shadows,
+            scope: self.scopes[scope].0,
+        });
+        let index = self.analysis.bindings.len() - 1;
+        self.scopes[scope].1.push(index);
+    }
+
+    fn read(&mut self, name: &str, span: Span) {
+        if let Some(index) = self.lookup(name) {
+            self.analysis.bindings[index].reads += 1;
+            self.analysis.references.push(Reference {
+                span,
+                binding: index,
+            });
+        }
+    }
+
+    fn scoped(&mut self, span: Span, walk: impl FnOnce(&mut Self)) {
+        self.scopes.push((Some(span), Vec::new()));
+        walk(self);
+        self.scopes.pop();
+    }
+
+    fn statements(&mut self, statements: &'a [Statement]) {
+        // Functions, classes and other declarations can be referred to from
+        // function bodies above them
+        for statement in statements {
+            self.hoist(statement, false);
+        }
+        for statement in statements {
+            self.statement(statement, false);
+        }
+    }
+
+    fn block(&mut self, block: &'a Block) {
+        self.scoped(block.span, |walker| walker.statements(&block.statements));
+    }
+
+    fn hoist(&mut self, statement: &Statement, exported: bool) {
+        match statement {
+            Statement::Function(func) => self.declare(&func.name, BindingKind::Function, exported),
+            Statement::Class(class) => {
+                self.declare(&class.name, BindingKind::Declaration, exported)
+            }
+            Statement::Enum(decl) => self.declare(&decl.name, BindingKind::Declaration, exported),
+            Statement::Namespace(namespace) => {
+                self.declare(&namespace.name, BindingKind::Declaration, exported)
+            }
+            Statement::Declare(decl) => {
+                let name = match &decl.kind {
+                    DeclareKind::Function(func) => &func.name,
+                    DeclareKind::Variable(var) => &var.name,
+                };
+                self.declare(name, BindingKind::Declaration, exported);
+            }
+            Statement::Export(export) => {
+                if let ExportKind::Declaration(decl) = &export.kind {
+                    self.hoist(decl, true);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn declare_pattern(&mut self, pattern: &'a Pattern, kind: BindingKind, exported: bool) {
+        match pattern {
+            Pattern::Identifier(name) => self.declare(name, kind, exported),
+            Pattern::Array(array) => {
+                for element in &array.elements {
+                    match element {
+                        ArrayPatternElement::Pattern(inner) => {
+                            self.declare_pattern(inner, kind, exported)
+                        }
+                        ArrayPatternElement::Rest(name) => self.declare(name, kind, exported),
+                        ArrayPatternElement::Hole => {}
+                    }
+                }
+            }
+            Pattern::Object(object) => {
+                for property in &object.properties {
+                    if let Some(default) = &property.default {
+                        self.expression(default);
+                    }
+                    match &property.value {
+                        Some(inner) => self.declare_pattern(inner, kind, exported),
+                        None => self.declare(&property.key, kind, exported),
+                    }
+                }
+            }
+            Pattern::Multiple(multiple) => {
+                for name in &multiple.names {
+                    self.declare(name, kind, exported);
+                }
+                if let Some(MultipleRest::Capture(name)) = &multiple.rest {
+                    self.declare(name, kind, exported);
+                }
+            }
+            Pattern::Literal(..) | Pattern::Wildcard(_) => {}
+        }
+    }
+
+    fn parameters(&mut self, parameters: &'a [Parameter]) {
+        for param in parameters {
+            if let Some(default) = &param.default {
+                self.expression(default);
+            }
+            self.declare_pattern(&param.pattern, BindingKind::Parameter, false);
+        }
+        self.parameter_types(parameters);
+    }
+
+    fn parameter_types(&mut self, parameters: &'a [Parameter]) {
+        for param in parameters {
+            self.optional_type(param.type_annotation.as_ref());
+        }
+    }
+
+    fn type_parameters(&mut self, type_parameters: Option<&'a Vec<TypeParameter>>) {
+        for param in type_parameters.into_iter().flatten() {
+            self.optional_type(param.constraint.as_deref());
+            self.optional_type(param.default.as_deref());
+        }
+    }
+
+    fn signature(
+        &mut self,
+        type_parameters: Option<&'a Vec<TypeParameter>>,
+        parameters: &'a [Parameter],
+        return_type: Option<&'a Type>,
+    ) {
+        self.type_parameters(type_parameters);
+        self.parameter_types(parameters);
+        self.optional_type(return_type);
+    }
+
+    fn optional_type(&mut self, typ: Option<&'a Type>) {
+        if let Some(typ) = typ {
+            self.typ(typ);
+        }
+    }
+
+    fn typ(&mut self, typ: &'a Type) {
+        match &typ.kind {
+            TypeKind::Reference(reference) => {
+                self.analysis.type_references.push(reference.name.clone());
+                for argument in reference.type_arguments.iter().flatten() {
+                    self.typ(argument);
+                }
+            }
+            TypeKind::Union(types) | TypeKind::Intersection(types) | TypeKind::Tuple(types) => {
+                for inner in types {
+                    self.typ(inner);
+                }
+            }
+            TypeKind::Object(object) => {
+                for member in &object.members {
+                    match member {
+                        ObjectTypeMember::Property(prop) => self.typ(&prop.type_annotation),
+                        ObjectTypeMember::Method(method) => self.signature(
+                            method.type_parameters.as_ref(),
+                            &method.parameters,
+                            Some(&method.return_type),
+                        ),
+                        ObjectTypeMember::Index(index) => self.typ(&index.value_type),
+                    }
+                }
+            }
+            TypeKind::Array(inner)
+            | TypeKind::KeyOf(inner)
+            | TypeKind::Nullable(inner)
+            | TypeKind::Parenthesized(inner)
+            | TypeKind::Variadic(inner) => self.typ(inner),
+            TypeKind::Function(func) => {
+                self.signature(None, &func.parameters, Some(&func.return_type))
+            }
+            TypeKind::IndexAccess(object, index) => {
+                self.typ(object);
+                self.typ(index);
+            }
+            TypeKind::Conditional(conditional) => {
+                self.typ(&conditional.check_type);
+                self.typ(&conditional.extends_type);
+                self.typ(&conditional.true_type);
+                self.typ(&conditional.false_type);
+            }
+            TypeKind::Mapped(mapped) => {
+                self.optional_type(mapped.type_parameter.constraint.as_deref());
+                self.typ(&mapped.in_type);
+                self.typ(&mapped.value_type);
+            }
+            TypeKind::TemplateLiteral(template) => {
+                for part in &template.parts {
+                    if let TemplateLiteralTypePart::Type(inner) = part {
+                        self.typ(inner);
+                    }
+                }
+            }
+            TypeKind::Asserts(asserts) => self.optional_type(asserts.type_predicate.as_deref()),
+            // `typeof value` reads the value
+            TypeKind::TypeQuery(expr) => self.expression(expr),
+            TypeKind::Primitive(_) | TypeKind::Literal(_) => {}
+        }
+    }
+
+    fn decorators(&mut self, decorators: &'a [Decorator]) {
+        for decorator in decorators {
+            self.decorator_expression(&decorator.expression);
+        }
+    }
+
+    fn decorator_expression(&mut self, expression: &'a DecoratorExpression) {
+        match expression {
+            DecoratorExpression::Identifier(name) => self.read(&name.node, name.span),
+            DecoratorExpression::Call {
+                callee, arguments, ..
+            } => {
+                self.decorator_expression(callee);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            }
+            DecoratorExpression::Member { object, .. } => self.decorator_expression(object),
+        }
+    }
+
+    fn statement(&mut self, statement: &'a Statement, exported: bool) {
+        self.analysis.statements.push(statement);
+        match statement {
+            Statement::Variable(decl) => {
+                self.decorators(&decl.decorators);
+                self.optional_type(decl.type_annotation.as_ref());
+                self.expression(&decl.initializer);
+                let kind = match decl.kind {
+                    VariableKind::Const => BindingKind::Const,
+                    VariableKind::Local => BindingKind::Local,
+                    VariableKind::Using => BindingKind::Using,
+                };
+                self.declare_pattern(&decl.pattern, kind, exported);
+            }
+            Statement::Function(func) => {
+                self.decorators(&func.decorators);
+                self.type_parameters(func.type_parameters.as_ref());
+                self.optional_type(func.return_type.as_ref());
+                self.scoped(func.span, |walker| {
+                    walker.parameters(&func.parameters);
+                    walker.block(&func.body);
+                });
+            }
+            Statement::Class(class) => self.class(class),
+            Statement::Import(import) => match &import.clause {
+                ImportClause::Default(name) | ImportClause::Namespace(name) => {
+                    self.declare(name, BindingKind::Import, false)
+                }
+                ImportClause::Named(specifiers) => {
+                    for specifier in specifiers {
+                        let name = specifier.local.as_ref().unwrap_or(&specifier.imported);
+                        self.declare(name, BindingKind::Import, false);
+                    }
+                }
+                ImportClause::TypeOnly(_) => {}
+            },
+            Statement::Export(export) => match &export.kind {
+                ExportKind::Declaration(decl) => self.statement(decl, true),
+                ExportKind::Named(specifiers) => {
+                    for specifier in specifiers {
+                        if let Some(index) = self.lookup(&specifier.local.node) {
+                            self.analysis.bindings[index].exported = true;
+                            self.analysis.references.push(Reference {
+                                span: specifier.local.span,
+                                binding: index,
+                            });
+                        }
+                    }
+                }
+                ExportKind::Default(expr) | ExportKind::Equals(expr) => self.expression(expr),
+            },
+            Statement::Declare(decl) => {
+                self.decorators(&decl.decorators);
+                match &decl.kind {
+                    DeclareKind::Function(func) => self.signature(
+                        func.type_parameters.as_ref(),
+                        &func.parameters,
+                        Some(&func.return_type),
+                    ),
+                    DeclareKind::Variable(var) => self.typ(&var.type_annotation),
+                }
+            }
+            Statement::Interface(interface) => {
+                self.decorators(&interface.decorators);
+                self.type_parameters(interface.type_parameters.as_ref());
+                for base in &interface.extends {
+                    self.typ(base);
+                }
+                for member in &interface.members {
+                    match member {
+                        InterfaceMember::Property(prop) => self.typ(&prop.type_annotation),
+                        InterfaceMember::Method(method) => self.signature(
+                            method.type_parameters.as_ref(),
+                            &method.parameters,
+                            Some(&method.return_type),
+                        ),
+                        InterfaceMember::Index(index) => self.typ(&index.value_type),
+                    }
+                }
+            }
+            Statement::TypeAlias(alias) => {
+                self.decorators(&alias.decorators);
+                self.type_parameters(alias.type_parameters.as_ref());
+                self.typ(&alias.type_annotation);
+            }
+            Statement::Namespace(namespace) => self.block(&namespace.body),
+            Statement::If(if_stmt) => {
+                self.expression(&if_stmt.condition);
+                self.block(&if_stmt.then_block);
+                for else_if in &if_stmt.else_ifs {
+                    self.expression(&else_if.condition);
+                    self.block(&else_if.block);
+                }
+                if let Some(else_block) = &if_stmt.else_block {
+                    self.block(else_block);
+                }
+            }
+            Statement::While(while_stmt) => {
+                self.expression(&while_stmt.condition);
+                self.block(&while_stmt.body);
+            }
+            Statement::For(ForStatement::Numeric(for_num)) => {
+                self.expression(&for_num.start);
+                self.expression(&for_num.end);
+                if let Some(step) = &for_num.step {
+                    self.expression(step);
+                }
+                self.scoped(for_num.span, |walker| {
+                    walker.declare(&for_num.variable, BindingKind::LoopVariable, false);
+                    walker.block(&for_num.body);
+                });
+            }
+            Statement::For(ForStatement::Generic(for_gen)) => {
+                for iterator in &for_gen.iterators {
+                    self.expression(iterator);
+                }
+                self.scoped(for_gen.span, |walker| {
+                    for variable in &for_gen.variables {
+                        walker.declare(variable, BindingKind::LoopVariable, false);
+                    }
+                    walker.block(&for_gen.body);
+                });
+            }
+            // The condition of `until` sees the body's locals
+            Statement::Repeat(repeat) => self.scoped(repeat.span, |walker| {
+                walker.statements(&repeat.body.statements);
+                walker.expression(&repeat.until);
+            }),
+            Statement::Labeled(labeled) => self.statement(&labeled.body, false),
+            Statement::Return(ret) => {
+                for value in &ret.values {
+                    self.expression(value);
+                }
+            }
+            Statement::Expression(expr) => self.expression(expr),
+            Statement::Block(block) | Statement::Untyped(block) => self.block(block),
+            Statement::Enum(_)
+            | Statement::Break(_)
+            | Statement::Continue(_)
+            | Statement::Error(_) => {}
+        }
+    }
+
+    fn class(&mut self, class: &'a ClassDeclaration) {
+        self.decorators(&class.decorators);
+        self.type_parameters(class.type_parameters.as_ref());
+        // Base classes and mixins are read at runtime
+        for heritage in class.extends.iter().chain(&class.mixins) {
+            if let TypeKind::Reference(base) = &heritage.kind {
+                let root = base.name.node.split('.').next().unwrap_or_default();
+                self.read(root, base.name.span);
+            }
+        }
+        for heritage in class
+            .extends
+            .iter()
+            .chain(&class.mixins)
+            .chain(&class.implements)
+        {
+            self.typ(heritage);
+        }
+        for member in &class.members {
+            match member {
+                ClassMember::Property(prop) => {
+                    self.decorators(&prop.decorators);
+                    self.typ(&prop.type_annotation);
+                    if let Some(initializer) = &prop.initializer {
+                        self.expression(initializer);
+                    }
+                }
+                ClassMember::Constructor(ctor) => {
+                    self.decorators(&ctor.decorators);
+                    self.member_body(ctor.span, &ctor.parameters, ctor.body.as_ref());
+                }
+                ClassMember::Method(method) => {
+                    self.decorators(&method.decorators);
+                    self.type_parameters(method.type_parameters.as_ref());
+                    self.optional_type(method.return_type.as_ref());
+                    self.member_body(method.span, &method.parameters, method.body.as_ref());
+                }
+                ClassMember::Getter(getter) => {
+                    self.decorators(&getter.decorators);
+                    self.typ(&getter.return_type);
+                    self.member_body(getter.span, &[], Some(&getter.body));
+                }
+                ClassMember::Setter(setter) => {
+                    self.decorators(&setter.decorators);
+                    self.member_body(
+                        setter.span,
+                        std::slice::from_ref(&setter.parameter),
+                        Some(&setter.body),
+                    );
+                }
+                ClassMember::StaticBlock(block) => {
+                    self.member_body(block.span, &[], Some(&block.body))
+                }
+            }
+        }
+    }
+
+    fn member_body(&mut self, span: Span, parameters: &'a [Parameter], body: Option<&'a Block>) {
+        self.scoped(span, |walker| {
+            walker.parameters(parameters);
+            if let Some(body) = body {
+                walker.block(body);
+            }
+        });
+    }
+
+    fn expression(&mut self, expr: &'a Expression) {
+        self.analysis.expressions.push(expr);
+        match &expr.kind {
+            ExpressionKind::Identifier(name) => self.read(name, expr.span),
+            ExpressionKind::Literal(_)
+            | ExpressionKind::SelfKeyword
+            | ExpressionKind::SuperKeyword => {}
+            ExpressionKind::Assignment(target, _, value) => {
+                self.expression(value);
+                self.assignment_target(target);
+            }
+            ExpressionKind::Binary(_, left, right)
+            | ExpressionKind::Index(left, right)
+            | ExpressionKind::Pipe(left, right) => {
+                self.expression(left);
+                self.expression(right);
+            }
+            ExpressionKind::Unary(_, inner)
+            | ExpressionKind::Member(inner, _)
+            | ExpressionKind::OptionalMember(inner, _)
+            | ExpressionKind::Parenthesized(inner) => self.expression(inner),
+            ExpressionKind::TypeAssertion(inner, typ) => {
+                self.expression(inner);
+                self.typ(typ);
+            }
+            ExpressionKind::Call(callee, args) | ExpressionKind::MethodCall(callee, _, args) => {
+                self.expression(callee);
+                for arg in args {
+                    self.expression(&arg.value);
+                }
+            }
+            ExpressionKind::Array(elements) => {
+                for element in elements {
+                    match element {
+                        ArrayElement::Expression(value) | ArrayElement::Spread(value) => {
+                            self.expression(value)
+                        }
+                    }
+                }
+            }
+            ExpressionKind::Object(properties) => {
+                for property in properties {
+                    match property {
+                        ObjectProperty::Property { value, .. }
+                        | ObjectProperty::Spread { value, .. } => self.expression(value),
+                        ObjectProperty::Computed { key, value, .. } => {
+                            self.expression(key);
+                            self.expression(value);
+                        }
+                    }
+                }
+            }
+            ExpressionKind::Function(func) => self.scoped(expr.span, |walker| {
+                walker.type_parameters(func.type_parameters.as_ref());
+                walker.optional_type(func.return_type.as_ref());
+                walker.parameters(&func.parameters);
+                walker.block(&func.body);
+            }),
+            ExpressionKind::Arrow(arrow) => self.scoped(expr.span, |walker| {
+                walker.optional_type(arrow.return_type.as_ref());
+                walker.parameters(&arrow.parameters);
+                match &arrow.body {
+                    ArrowBody::Expression(body) => walker.expression(body),
+                    ArrowBody::Block(body) => walker.block(body),
+                }
+            }),
+            ExpressionKind::Conditional(condition, then, otherwise) => {
+                self.expression(condition);
+                self.expression(then);
+                self.expression(otherwise);
+            }
+            ExpressionKind::Match(match_expr) => {
+                self.expression(&match_expr.value);
+                for arm in &match_expr.arms {
+                    self.scoped(arm.span, |walker| {
+                        walker.declare_pattern(&arm.pattern, BindingKind::Parameter, false);
+                        if let Some(guard) = &arm.guard {
+                            walker.expression(guard);
+                        }
+                        match &arm.body {
+                            MatchArmBody::Expression(body) => walker.expression(body),
+                            MatchArmBody::Block(body) => walker.block(body),
+                        }
+                    });
+                }
+            }
+            ExpressionKind::Template(template) => {
+                for part in &template.parts {
+                    if let TemplatePart::Expression(inner) | TemplatePart::Formatted(inner, _) =
+                        part
+                    {
+                        self.expression(inner);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Assigning to a name writes it without reading it; assigning to a
+    /// member or index reads the table it is stored in
+    fn assignment_target(&mut self, target: &'a Expression) {
+        let ExpressionKind::Identifier(name) = &target.kind else {
+            return self.expression(target);
+        };
+        self.analysis.expressions.push(target);
+        match self.lookup(name) {
+            Some(binding) => self.analysis.references.push(Reference {
+                span: target.span,
+                binding,
+            }),
+            None => self
+                .analysis
+                .implicit_globals
+                .push(Ident::new(name.clone(), target.span)),
+        }
+    }
+}
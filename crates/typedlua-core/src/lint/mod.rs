@@ -0,0 +1,469 @@
+//! Lint rules: checks for code that type checks but is likely a mistake,
+//! such as locals never read or assignments that make globals (`typedlua
+//! lint`, or `lint.onBuild` to run them with every build)
+
+mod analysis;
+mod rules;
+
+pub use analysis::{Analysis, Binding, BindingKind, Reference};
+
+use crate::ast::Program;
+use crate::build::parse_module;
+use crate::config::{CompilerConfig, LintOptions, StrictLevel};
+use crate::diagnostics::{
+    Diagnostic, DiagnosticHandler, DiagnosticLevel, RelatedSpan, SuggestedFix,
+};
+use crate::errors::{CompilationError, TypeCheckError};
+use crate::lexer::Directive;
+use crate::span::Span;
+use std::sync::Arc;
+
+/// Something a lint rule found
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub span: Span,
+    pub message: String,
+    /// Other places the message refers to
+    pub related: Vec<RelatedSpan>,
+    /// Edits that would resolve the finding
+    pub fixes: Vec<SuggestedFix>,
+}
+
+impl Lint {
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
+        Lint {
+            span,
+            message: message.into(),
+            related: Vec::new(),
+            fixes: Vec::new(),
+        }
+    }
+}
+
+/// What a lint rule sees of a module
+pub struct LintContext<'a> {
+    pub program: &'a Program,
+    pub analysis: &'a Analysis<'a>,
+    /// Spans of the module's comments, in source order
+    pub comments: &'a [Span],
+}
+
+impl LintContext<'_> {
+    /// Whether a comment lies between the character offsets `start` and `end`
+    pub fn has_comment_between(&self, start: usize, end: usize) -> bool {
+        self.comments
+            .iter()
+            .any(|comment| comment.start >= start && comment.end <= end)
+    }
+}
+
+/// A check over a whole module. Configuration and `// @lint-ignore`
+/// comments refer to a rule by its kebab-case name.
+pub trait LintRule: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// One sentence on what the rule flags, for reports listing the rules
+    fn description(&self) -> &'static str {
+        ""
+    }
+
+    /// Severity when the configuration does not set one
+    fn default_level(&self) -> StrictLevel {
+        StrictLevel::Warning
+    }
+
+    fn check(&self, context: &LintContext, lints: &mut Vec<Lint>);
+}
+
+/// Runs lint rules over modules at the severities the configuration sets
+pub struct Linter {
+    rules: Vec<Box<dyn LintRule>>,
+    options: LintOptions,
+    /// Whether `@lint-ignore` comments are errors rather than silencing
+    /// anything (`noSuppressions`)
+    no_suppressions: bool,
+}
+
+impl Linter {
+    /// A linter running the built-in rules
+    pub fn new(options: &LintOptions) -> Self {
+        Linter {
+            rules: rules::builtin_rules(),
+            options: options.clone(),
+            no_suppressions: false,
+        }
+    }
+
+    /// A linter running the built-in rules as a project's configuration
+    /// sets them, honoring its `noSuppressions`
+    pub fn for_config(config: &CompilerConfig) -> Self {
+        Linter {
+            no_suppressions: config.compiler_options.no_suppressions,
+            ..Linter::new(&config.lint)
+        }
+    }
+
+    /// Run another rule along with the built-in ones
+    pub fn add_rule(&mut self, rule: Box<dyn LintRule>) {
+        self.rules.push(rule);
+    }
+
+    /// Names of the rules the configuration sets a severity for that no
+    /// rule answers to, sorted
+    pub fn unknown_rules(&self) -> Vec<&str> {
+        let mut unknown: Vec<&str> = self
+            .options
+            .rules
+            .keys()
+            .map(String::as_str)
+            .filter(|name| !self.rules.iter().any(|rule| rule.name() == *name))
+            .collect();
+        unknown.sort_unstable();
+        unknown
+    }
+
+    /// Every rule, with the severity the configuration runs it at
+    pub fn rules(&self) -> impl Iterator<Item = (&dyn LintRule, StrictLevel)> {
+        self.rules
+            .iter()
+            .map(|rule| (rule.as_ref(), self.level(rule.as_ref())))
+    }
+
+    fn level(&self, rule: &dyn LintRule) -> StrictLevel {
+        self.options
+            .rules
+            .get(rule.name())
+            .copied()
+            .unwrap_or_else(|| rule.default_level())
+    }
+
+    /// Run every enabled rule over a parsed module and report what they
+    /// find. `// @lint-ignore name...` silences the named rules, or every
+    /// rule when it names none, on its line and the next;
+    /// `// @lint-ignore-file name...` silences them in the whole module.
+    /// Comments that silence nothing are warned about, and under
+    /// `noSuppressions` every such comment is an error instead. Returns
+    /// how many findings were reported as errors.
+    pub fn lint(
+        &self,
+        program: &Program,
+        directives: &[Directive],
+        comments: &[Span],
+        diagnostic_handler: &dyn DiagnosticHandler,
+    ) -> usize {
+        let analysis = Analysis::new(program);
+        let context = LintContext {
+            program,
+            analysis: &analysis,
+            comments,
+        };
+        let mut found = Vec::new();
+        let mut suppressions = Suppressions::new(directives);
+        if self.no_suppressions {
+            for suppression in std::mem::take(&mut suppressions.0) {
+                let directive = suppression.directive;
+                let error = TypeCheckError::SuppressionForbidden(directive.name.clone());
+                found.push(
+                    Diagnostic::error(directive.span, error.to_string()).with_code(error.code()),
+                );
+            }
+        }
+
+        for rule in &self.rules {
+            let level = self.level(rule.as_ref());
+            if level == StrictLevel::Off {
+                continue;
+            }
+            let mut lints = Vec::new();
+            rule.check(&context, &mut lints);
+            for lint in lints {
+                if !suppressions.silence(rule.name(), lint.span.line) {
+                    let message = format!("{} ({})", lint.message, rule.name());
+                    let diagnostic = if level == StrictLevel::Error {
+                        Diagnostic::error(lint.span, message)
+                    } else {
+                        Diagnostic::warning(lint.span, message)
+                    };
+                    found.push(
+                        diagnostic
+                            .with_code(rule.name())
+                            .with_related(lint.related)
+                            .with_fixes(lint.fixes),
+                    );
+                }
+            }
+        }
+
+        found.extend(suppressions.unused());
+
+        found.sort_by_key(|diagnostic| diagnostic.span.start);
+        let errors = found
+            .iter()
+            .filter(|diagnostic| diagnostic.level == DiagnosticLevel::Error)
+            .count();
+        for diagnostic in found {
+            diagnostic_handler.report(diagnostic);
+        }
+        errors
+    }
+}
+
+/// Lex, parse and lint a module without type checking it
+pub fn lint_source(
+    source: &str,
+    linter: &Linter,
+    diagnostic_handler: Arc<dyn DiagnosticHandler>,
+) -> Result<(), CompilationError> {
+    let parsed = parse_module(source, diagnostic_handler.clone())?;
+
+    let _ = linter.lint(
+        &parsed.program,
+        &parsed.directives,
+        &parsed.comments,
+        diagnostic_handler.as_ref(),
+    );
+    Ok(())
+}
+
+/// The `@lint-ignore` and `@lint-ignore-file` comments of a module
+struct Suppressions(Vec<Suppression>);
+
+struct Suppression {
+    /// Its arguments are the rules it silences; none names every rule
+    directive: Directive,
+    /// The rules something was silenced under
+    used: Vec<String>,
+}
+
+impl Suppression {
+    fn covers(&self, rule: &str, line: usize) -> bool {
+        let directive = &self.directive;
+        let lines = directive.name == "lint-ignore-file"
+            || line == directive.line
+            || line == directive.line + 1;
+        let rules = &directive.arguments;
+        lines && (rules.is_empty() || rules.iter().any(|name| name == rule))
+    }
+}
+
+impl Suppressions {
+    fn new(directives: &[Directive]) -> Self {
+        Suppressions(
+            directives
+                .iter()
+                .filter(|directive| {
+                    matches!(directive.name.as_str(), "lint-ignore" | "lint-ignore-file")
+                })
+                .map(|directive| Suppression {
+                    directive: directive.clone(),
+                    used: Vec::new(),
+                })
+                .collect(),
+        )
+    }
+
+    /// Whether a suppression silences a finding, noting that it did
+    fn silence(&mut self, rule: &str, line: usize) -> bool {
+        match self.0.iter_mut().find(|s| s.covers(rule, line)) {
+            Some(suppression) => {
+                if !suppression.used.iter().any(|used| used == rule) {
+                    suppression.used.push(rule.to_string());
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Warnings for the suppressions, or the rules they name, that
+    /// silenced nothing
+    fn unused(self) -> Vec<Diagnostic> {
+        self.0
+            .into_iter()
+            .filter_map(|suppression| {
+                let directive = suppression.directive;
+                Diagnostic::unused_suppression(
+                    directive.span,
+                    &directive.name,
+                    &directive.arguments,
+                    &suppression.used,
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::{CollectingDiagnosticHandler, DiagnosticLevel};
+    use std::collections::HashMap;
+
+    fn lint(source: &str, rules: &[(&str, StrictLevel)]) -> Vec<(DiagnosticLevel, usize, String)> {
+        let options = LintOptions {
+            on_build: false,
+            rules: rules
+                .iter()
+                .map(|(name, level)| (name.to_string(), *level))
+                .collect::<HashMap<_, _>>(),
+        };
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        lint_source(source, &Linter::new(&options), handler.clone()).expect("Lint failed");
+        handler
+            .get_diagnostics()
+            .into_iter()
+            .map(|diagnostic| (diagnostic.level, diagnostic.span.line, diagnostic.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_builtin_rules() {
+        let source = r#"local unused = 1
+local _ignored = 2
+export const limit = 10
+local count = 0
+function helper(value: number): number
+    if value == nil then
+        return 0
+    end
+    local count = value
+    total = count
+    return count
+end
+print(helper(limit))
+while limit > 0 do
+end
+for i = 1, 3 do
+    // nothing yet
+end
+"#;
+        let messages: Vec<(usize, String)> = lint(source, &[])
+            .into_iter()
+            .map(|(_, line, message)| (line, message))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (1, "'unused' is declared but never read (unused-local)".to_string()),
+                (4, "'count' is declared but never read (unused-local)".to_string()),
+                (
+                    6,
+                    "Comparison with nil; test the value itself unless false must be told apart from nil (nil-comparison)".to_string()
+                ),
+                (9, "'count' shadows the declaration at 4:7 (shadowing)".to_string()),
+                (
+                    10,
+                    "Assignment to undeclared 'total' creates a global; declare it with 'local' first (implicit-global)".to_string()
+                ),
+                (
+                    14,
+                    "Empty loop body; remove the loop or add a comment saying why it is empty (empty-block)".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_findings_carry_codes_related_spans_and_fixes() {
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let linter = Linter::new(&LintOptions::default());
+        let source = "local count = 0\nprint(count)\nif count > 0 then\n    local count = 1\nend\n";
+        lint_source(source, &linter, handler.clone()).expect("Lint failed");
+
+        let diagnostics = handler.get_diagnostics();
+        let codes: Vec<_> = diagnostics.iter().map(|d| d.code.as_deref()).collect();
+        assert_eq!(codes, vec![Some("unused-local"), Some("shadowing")]);
+
+        let fix = &diagnostics[0].fixes[0];
+        assert_eq!(fix.message, "Rename to '_count'");
+        assert_eq!(fix.replacements[0].span.start, 57);
+        assert_eq!(fix.replacements[0].text, "_");
+        assert_eq!(diagnostics[1].related[0].span.line, 1);
+    }
+
+    #[test]
+    fn test_rule_levels_and_suppressions() {
+        let source = r#"// @lint-ignore-file nil-comparison
+local a = 1 // @lint-ignore unused-local
+// @lint-ignore
+local b = 2
+local c = 3
+if c == nil then print(c) end
+"#;
+        assert_eq!(
+            lint(source, &[("implicit-global", StrictLevel::Off)]),
+            Vec::new()
+        );
+        assert_eq!(
+            lint(
+                "local unused = 1\nglobalName = 2\n",
+                &[
+                    ("unused-local", StrictLevel::Error),
+                    ("implicit-global", StrictLevel::Off)
+                ]
+            ),
+            vec![(
+                DiagnosticLevel::Error,
+                1,
+                "'unused' is declared but never read (unused-local)".to_string()
+            )]
+        );
+
+        let options = LintOptions {
+            on_build: false,
+            rules: HashMap::from([("unused-locals".to_string(), StrictLevel::Off)]),
+        };
+        assert_eq!(Linter::new(&options).unknown_rules(), vec!["unused-locals"]);
+    }
+
+    #[test]
+    fn test_unused_and_forbidden_suppressions() {
+        let source = r#"// @lint-ignore-file shadowing
+// @lint-ignore unused-local nil-comparison
+local a = 1
+// @lint-ignore
+local b = 2
+print(b)
+"#;
+        assert_eq!(
+            lint(source, &[]),
+            vec![
+                (
+                    DiagnosticLevel::Warning,
+                    1,
+                    "This '@lint-ignore-file' silences nothing under shadowing".to_string()
+                ),
+                (
+                    DiagnosticLevel::Warning,
+                    2,
+                    "This '@lint-ignore' silences nothing under nil-comparison".to_string()
+                ),
+                (
+                    DiagnosticLevel::Warning,
+                    4,
+                    "This '@lint-ignore' silences nothing".to_string()
+                ),
+            ]
+        );
+
+        let mut config = CompilerConfig::default();
+        config.compiler_options.no_suppressions = true;
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        lint_source(source, &Linter::for_config(&config), handler.clone()).expect("Lint failed");
+        let found: Vec<(usize, Option<String>)> = handler
+            .get_diagnostics()
+            .into_iter()
+            .map(|diagnostic| (diagnostic.span.line, diagnostic.code))
+            .collect();
+        let forbidden = Some("TL2074".to_string());
+        assert_eq!(
+            found,
+            vec![
+                (1, forbidden.clone()),
+                (2, forbidden.clone()),
+                (3, Some("unused-local".to_string())),
+                (4, forbidden),
+            ]
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use super::{BindingKind, Lint, LintContext, LintRule};
+use crate::ast::expression::{BinaryOp, ExpressionKind, Literal};
+use crate::ast::statement::{Block, ForStatement, Statement};
+use crate::diagnostics::{Applicability, RelatedSpan, SuggestedFix};
+use crate::span::Span;
+use std::collections::HashSet;
+
+/// The rules every linter starts with
+pub(super) fn builtin_rules() -> Vec<Box<dyn LintRule>> {
+    vec![
+        Box::new(UnusedLocal),
+        Box::new(Shadowing),
+        Box::new(ImplicitGlobal),
+        Box::new(NilComparison),
+        Box::new(EmptyBlock),
+    ]
+}
+
+/// Locals, constants and functions that are declared and never read.
+/// Names starting with `_` are taken as deliberately unused.
+struct UnusedLocal;
+
+impl LintRule for UnusedLocal {
+    fn name(&self) -> &'static str {
+        "unused-local"
+    }
+
+    fn description(&self) -> &'static str {
+        "Locals, constants and functions that are declared and never read"
+    }
+
+    fn check(&self, context: &LintContext, lints: &mut Vec<Lint>) {
+        for binding in &context.analysis.bindings {
+            if binding.reads > 0 || binding.exported || binding.name.starts_with('_') {
+                continue;
+            }
+            let message = match binding.kind {
+                BindingKind::Local | BindingKind::Const => {
+                    format!("'{}' is declared but never read", binding.name)
+                }
+                BindingKind::Function => format!("Function '{}' is never used", binding.name),
+                _ => continue,
+            };
+            // Names starting with `_` are not reported
+            // Writes elsewhere still use the old name
+            let rename = SuggestedFix::replace(
+                format!("Rename to '_{}'", binding.name),
+                Span::new(
+                    binding.span.start,
+                    binding.span.start,
+                    binding.span.line,
+                    binding.span.column,
+                ),
+                "_",
+                Applicability::MaybeIncorrect,
+            );
+            let mut lint = Lint::new(binding.span, message);
+            lint.fixes.push(rename);
+            lints.push(lint);
+        }
+    }
+}
+
+/// Declarations hiding a name of an enclosing scope
+struct Shadowing;
+
+impl LintRule for Shadowing {
+    fn name(&self) -> &'static str {
+        "shadowing"
+    }
+
+    fn description(&self) -> &'static str {
+        "Declarations hiding a name of an enclosing scope"
+    }
+
+    fn check(&self, context: &LintContext, lints: &mut Vec<Lint>) {
+        for binding in &context.analysis.bindings {
+            let Some(shadowed) = binding.shadows else {
+                continue;
+            };
+            if binding.name.starts_with('_') || binding.name == "self" {
+                continue;
+            }
+            let mut lint = Lint::new(
+                binding.span,
+                format!("'{}' shadows the declaration at {}", binding.name, shadowed),
+            );
+            lint.related.push(RelatedSpan::new(
+                shadowed,
+                format!("'{}' is declared here", binding.name),
+            ));
+            lints.push(lint);
+        }
+    }
+}
+
+/// Assignments to undeclared names, which Lua turns into globals
+struct ImplicitGlobal;
+
+impl LintRule for ImplicitGlobal {
+    fn name(&self) -> &'static str {
+        "implicit-global"
+    }
+
+    fn description(&self) -> &'static str {
+        "Assignments to undeclared names, which Lua turns into globals"
+    }
+
+    fn check(&self, context: &LintContext, lints: &mut Vec<Lint>) {
+        let mut reported = HashSet::new();
+        for name in &context.analysis.implicit_globals {
+            if !reported.insert(&name.node) {
+                continue;
+            }
+            lints.push(Lint::new(
+                name.span,
+                format!(
+                    "Assignment to undeclared '{}' creates a global; declare it with 'local' first",
+                    name.node
+                ),
+            ));
+        }
+    }
+}
+
+/// `value == nil` and `value ~= nil`, which read as `not value` and `value`
+struct NilComparison;
+
+impl LintRule for NilComparison {
+    fn name(&self) -> &'static str {
+        "nil-comparison"
+    }
+
+    fn description(&self) -> &'static str {
+        "Comparisons with nil, which read as a test of the value itself"
+    }
+
+    fn check(&self, context: &LintContext, lints: &mut Vec<Lint>) {
+        for expr in &context.analysis.expressions {
+            let ExpressionKind::Binary(BinaryOp::Equal | BinaryOp::NotEqual, left, right) =
+                &expr.kind
+            else {
+                continue;
+            };
+            let is_nil = |operand: &crate::ast::expression::Expression| {
+                matches!(operand.kind, ExpressionKind::Literal(Literal::Nil))
+            };
+            if is_nil(left) || is_nil(right) {
+                lints.push(Lint::new(
+                    expr.span,
+                    "Comparison with nil; test the value itself unless false must be told apart from nil",
+                ));
+            }
+        }
+    }
+}
+
+/// Branches and loop bodies with no statements. A comment in the block
+/// marks it as empty on purpose.
+struct EmptyBlock;
+
+impl LintRule for EmptyBlock {
+    fn name(&self) -> &'static str {
+        "empty-block"
+    }
+
+    fn description(&self) -> &'static str {
+        "Branches and loop bodies with no statements"
+    }
+
+    fn check(&self, context: &LintContext, lints: &mut Vec<Lint>) {
+        // An empty block's span is the keyword ending it, so a comment
+        // inside lies between what precedes the block and that span
+        let mut check = |block: &Block, after: usize, span: Span, what: &str| {
+            if block.statements.is_empty() && !context.has_comment_between(after, block.span.start)
+            {
+                lints.push(Lint::new(span, what));
+            }
+        };
+        const LOOP: &str =
+            "Empty loop body; remove the loop or add a comment saying why it is empty";
+
+        for statement in &context.analysis.statements {
+            match statement {
+                Statement::If(if_stmt) => {
+                    let mut after = if_stmt.condition.span.end;
+                    check(
+                        &if_stmt.then_block,
+                        after,
+                        if_stmt.span,
+                        "Empty 'then' block; invert the condition or add a comment saying why it is empty",
+                    );
+                    after = if_stmt.then_block.span.end;
+                    for else_if in &if_stmt.else_ifs {
+                        check(
+                            &else_if.block,
+                            else_if.condition.span.end,
+                            else_if.span,
+                            "Empty 'elseif' block; add a comment saying why it is empty",
+                        );
+                        after = else_if.block.span.end;
+                    }
+                    if let Some(else_block) = &if_stmt.else_block {
+                        check(
+                            else_block,
+                            after,
+                            if_stmt.span,
+                            "Empty 'else' block; remove it or add a comment saying why it is empty",
+                        );
+                    }
+                }
+                Statement::While(while_stmt) => check(
+                    &while_stmt.body,
+                    while_stmt.condition.span.end,
+                    while_stmt.span,
+                    LOOP,
+                ),
+                Statement::For(ForStatement::Numeric(for_num)) => {
+                    let last = for_num.step.as_ref().unwrap_or(&for_num.end);
+                    check(&for_num.body, last.span.end, for_num.span, LOOP);
+                }
+                Statement::For(ForStatement::Generic(for_gen)) => {
+                    let after = for_gen
+                        .iterators
+                        .last()
+                        .map_or(for_gen.span.start, |iterator| iterator.span.end);
+                    check(&for_gen.body, after, for_gen.span, LOOP);
+                }
+                Statement::Repeat(repeat) => {
+                    check(&repeat.body, repeat.span.start, repeat.span, LOOP)
+                }
+                _ => {}
+            }
+        }
+    }
+}
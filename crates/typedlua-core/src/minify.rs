@@ -0,0 +1,741 @@
+//! Minifying emitted Lua (`minify`): dropping whitespace and comments,
+//! shortening the names of locals, and folding concatenations of string
+//! literals. The pass works on Lua tokens, so it also takes plain Lua
+//! modules copied into a bundle.
+
+use crate::codegen::quote_string;
+use crate::config::{CompilerOptions, LuaVersion};
+use crate::features::LuaFeature;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Name,
+    Keyword,
+    Number,
+    String,
+    Symbol,
+    Comment,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+}
+
+impl Token {
+    fn is(&self, kind: TokenKind, text: &str) -> bool {
+        self.kind == kind && self.text == text
+    }
+
+    fn is_symbol(&self, text: &str) -> bool {
+        self.is(TokenKind::Symbol, text)
+    }
+
+    fn is_keyword(&self, text: &str) -> bool {
+        self.is(TokenKind::Keyword, text)
+    }
+
+    /// Whether an expression can end with this token, so that a name after
+    /// it starts a new statement
+    fn ends_expression(&self) -> bool {
+        match self.kind {
+            TokenKind::Name | TokenKind::Number | TokenKind::String => true,
+            TokenKind::Keyword => matches!(self.text.as_str(), "true" | "false" | "nil" | "end"),
+            TokenKind::Symbol => matches!(self.text.as_str(), ")" | "]" | "}" | "..."),
+            TokenKind::Comment => false,
+        }
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "if", "in", "local",
+    "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+const SYMBOLS: &[&str] = &[
+    "...", "..", "==", "~=", "<=", ">=", "<<", ">>", "//", "::", "+", "-", "*", "/", "%", "^", "#",
+    "&", "~", "|", "<", ">", "=", "(", ")", "{", "}", "[", "]", ";", ":", ",", ".",
+];
+
+/// Minify Lua emitted for `options.target`. Local names are only shortened
+/// when the output carries no Luau type annotations, whose names the pass
+/// cannot tell apart from values. Source that does not lex as Lua is
+/// returned unchanged.
+pub fn minify(lua: &str, options: &CompilerOptions) -> String {
+    let target = options.target;
+    let Some(mut tokens) = tokenize(lua, target) else {
+        return lua.to_string();
+    };
+    fold_strings(&mut tokens);
+    if !(target == LuaVersion::Luau && options.luau_types) {
+        if let Some(names) = Renamer::new(&tokens).rename() {
+            for (token, name) in tokens.iter_mut().zip(names) {
+                if let Some(name) = name {
+                    token.text = name;
+                }
+            }
+        }
+    }
+
+    let mut minified = String::with_capacity(lua.len() / 2);
+    let mut previous: Option<&Token> = None;
+    for token in &tokens {
+        if token.kind == TokenKind::Comment {
+            // `--!strict` and other Luau directives lead the module
+            if token.text.starts_with("--!") && previous.is_none() {
+                minified.push_str(&token.text);
+                minified.push('\n');
+            }
+            continue;
+        }
+        if let Some(previous) = previous {
+            if needs_space(previous, token, target) {
+                minified.push(' ');
+            }
+        }
+        minified.push_str(&token.text);
+        previous = Some(token);
+    }
+    minified.push('\n');
+    minified
+}
+
+/// Whether two tokens written together would read as something else, such
+/// as `local x` becoming `localx` or `a - -b` becoming a comment
+fn needs_space(left: &Token, right: &Token, target: LuaVersion) -> bool {
+    let joined = format!("{}{}", left.text, right.text);
+    match tokenize(&joined, target) {
+        Some(tokens) => tokens.len() != 2 || tokens[0] != *left || tokens[1] != *right,
+        None => true,
+    }
+}
+
+fn tokenize(source: &str, target: LuaVersion) -> Option<Vec<Token>> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let start = i;
+        let kind = if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        } else if source[i..].starts_with("--") {
+            i += 2;
+            match long_bracket(bytes, i) {
+                Some(level) => i = long_bracket_end(source, i, level)?,
+                None => {
+                    while i < bytes.len() && bytes[i] != b'\n' {
+                        i += 1;
+                    }
+                }
+            }
+            TokenKind::Comment
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            let word = &source[start..i];
+            let goto = word == "goto" && target.supports(LuaFeature::Goto);
+            if KEYWORDS.contains(&word) || goto {
+                TokenKind::Keyword
+            } else {
+                TokenKind::Name
+            }
+        } else if c.is_ascii_digit()
+            || (c == b'.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit))
+        {
+            i = number_end(bytes, i);
+            TokenKind::Number
+        } else if c == b'"' || c == b'\'' || (c == b'`' && target == LuaVersion::Luau) {
+            i += 1;
+            loop {
+                match bytes.get(i)? {
+                    b'\\' => i += 2,
+                    b'\n' => return None,
+                    &quote if quote == c => break,
+                    _ => i += 1,
+                }
+            }
+            i += 1;
+            TokenKind::String
+        } else if let Some(level) = long_bracket(bytes, i) {
+            i = long_bracket_end(source, i, level)?;
+            TokenKind::String
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| source[i..].starts_with(**symbol))?;
+            i += symbol.len();
+            TokenKind::Symbol
+        };
+        tokens.push(Token {
+            kind,
+            text: source.get(start..i)?.to_string(),
+        });
+    }
+    Some(tokens)
+}
+
+/// Where a numeral starting at `i` ends, taking exponents with signs and
+/// the suffixes LuaJIT allows
+fn number_end(bytes: &[u8], mut i: usize) -> usize {
+    let hex = bytes[i] == b'0' && matches!(bytes.get(i + 1), Some(b'x' | b'X'));
+    let exponent: &[u8] = if hex { b"Pp" } else { b"Ee" };
+    if hex {
+        i += 2;
+    }
+    while i < bytes.len() {
+        let c = bytes[i];
+        if exponent.contains(&c) && matches!(bytes.get(i + 1), Some(b'+' | b'-')) {
+            i += 2;
+        } else if c.is_ascii_alphanumeric() || c == b'.' || c == b'_' {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    i
+}
+
+/// The level of a long bracket `[==[` opening at `i`
+fn long_bracket(bytes: &[u8], i: usize) -> Option<usize> {
+    if bytes.get(i) != Some(&b'[') {
+        return None;
+    }
+    let level = bytes[i + 1..].iter().take_while(|c| **c == b'=').count();
+    (bytes.get(i + 1 + level) == Some(&b'[')).then_some(level)
+}
+
+/// Where the long bracket of `level` opening at `i` closes
+fn long_bracket_end(source: &str, i: usize, level: usize) -> Option<usize> {
+    let close = format!("]{}]", "=".repeat(level));
+    let body = i + level + 2;
+    Some(body + source.get(body..)?.find(&close)? + close.len())
+}
+
+/// Join `"a" .. "b"` into `"ab"`. The concatenation operator is right
+/// associative and binds looser than arithmetic, so a pair is only joined
+/// when neither string belongs to a tighter operator or a call, and the
+/// right one is not concatenated further, where a `__concat` metamethod
+/// could see it.
+fn fold_strings(tokens: &mut Vec<Token>) {
+    const TIGHTER: &[&str] = &["+", "-", "*", "/", "//", "%", "^", "#", "~"];
+    let mut i = tokens.len();
+    while i >= 3 {
+        let right = i - 1;
+        let left = i - 3;
+        let foldable = tokens[left].kind == TokenKind::String
+            && tokens[left + 1].is_symbol("..")
+            && tokens[right].kind == TokenKind::String
+            && tokens.get(i).is_none_or(|next| {
+                !(next.kind == TokenKind::Symbol
+                    && (TIGHTER.contains(&next.text.as_str()) || next.text == ".."))
+            })
+            && left.checked_sub(1).is_none_or(|previous| {
+                let previous = &tokens[previous];
+                !(previous.ends_expression()
+                    || previous.is_keyword("not")
+                    || previous.kind == TokenKind::Symbol
+                        && TIGHTER.contains(&previous.text.as_str()))
+            });
+        let folded = foldable
+            .then(|| Some(string_value(&tokens[left].text)? + &string_value(&tokens[right].text)?))
+            .flatten();
+        match folded {
+            Some(value) => {
+                tokens.splice(
+                    left..i,
+                    [Token {
+                        kind: TokenKind::String,
+                        text: quote_string(&value),
+                    }],
+                );
+                // The joined string may be the right side of another pair
+                i = left + 1;
+            }
+            None => i -= 1,
+        }
+    }
+}
+
+/// The value of a quoted string literal. Long strings, and strings holding
+/// control characters other than newlines and tabs, give `None` and are
+/// left alone.
+fn string_value(literal: &str) -> Option<String> {
+    let quote = literal.chars().next()?;
+    if quote != '"' && quote != '\'' {
+        return None;
+    }
+    let body = literal.get(1..literal.len() - 1)?.as_bytes();
+    let mut value = Vec::with_capacity(body.len());
+    let mut i = 0;
+    while i < body.len() {
+        if body[i] != b'\\' {
+            value.push(body[i]);
+            i += 1;
+            continue;
+        }
+        let escaped = *body.get(i + 1)?;
+        i += 2;
+        let byte = match escaped {
+            b'n' => b'\n',
+            b't' => b'\t',
+            b'r' => b'\r',
+            b'\\' | b'"' | b'\'' => escaped,
+            b'\n' => b'\n',
+            b'0'..=b'9' => {
+                let digits = body[i - 1..]
+                    .iter()
+                    .take(3)
+                    .take_while(|c| c.is_ascii_digit())
+                    .count();
+                let code: u32 = std::str::from_utf8(&body[i - 1..i - 1 + digits])
+                    .ok()?
+                    .parse()
+                    .ok()?;
+                i += digits - 1;
+                u8::try_from(code).ok()?
+            }
+            _ => return None,
+        };
+        value.push(byte);
+    }
+    let value = String::from_utf8(value).ok()?;
+    let plain = value
+        .chars()
+        .all(|c| !c.is_control() || matches!(c, '\n' | '\t'));
+    plain.then_some(value)
+}
+
+/// What a frame of the scope walk was opened by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frame {
+    /// `(`, `[` or `{`
+    Bracket(u8),
+    /// A `do`, `then` or `else` block
+    Block,
+    /// A function body, from its parameter list to `end`
+    Function,
+    /// A `repeat` block, whose locals its `until` condition can see
+    Repeat,
+}
+
+/// Locals declared but not in scope until their statement ends, as in
+/// `local x = x + 1`, or a `repeat` block closing after its condition
+#[derive(Debug)]
+enum Pending {
+    Locals(usize, Vec<(String, usize)>),
+    CloseRepeat(usize),
+}
+
+/// Finds the declaration each name refers to and gives every local a
+/// short name. Each local takes the lowest slot no visible local holds,
+/// and slots map to names no global of the module uses, so a renamed local
+/// can neither capture nor shadow another name.
+struct Renamer<'a> {
+    tokens: &'a [Token],
+    frames: Vec<Frame>,
+    /// The locals in scope, innermost scope last, with their declarations
+    scopes: Vec<Vec<(String, usize)>>,
+    pending: Vec<Pending>,
+    /// Loop variables declared by each `for` waiting for its `do`
+    pending_for: Vec<(usize, Vec<(String, usize)>)>,
+    /// The slot of each declaration, or `None` for one kept as written
+    slots: Vec<Option<usize>>,
+    /// Slots taken by the locals in scope and pending
+    live: usize,
+    /// The declaration each name token refers to
+    resolved: Vec<Option<usize>>,
+    globals: HashSet<String>,
+    /// Whether the next `(` opens a parameter list
+    awaiting_parameters: bool,
+}
+
+impl<'a> Renamer<'a> {
+    fn new(tokens: &'a [Token]) -> Self {
+        Renamer {
+            tokens,
+            frames: Vec::new(),
+            scopes: vec![Vec::new()],
+            pending: Vec::new(),
+            pending_for: Vec::new(),
+            slots: Vec::new(),
+            live: 0,
+            resolved: vec![None; tokens.len()],
+            globals: HashSet::new(),
+            awaiting_parameters: false,
+        }
+    }
+
+    /// The new text of each token, or `None` where the walk could not
+    /// follow the source's structure
+    fn rename(mut self) -> Option<Vec<Option<String>>> {
+        let tokens = self.tokens;
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            if token.kind == TokenKind::Comment {
+                i += 1;
+                continue;
+            }
+            self.end_statements(i)?;
+            i = self.visit(i)?;
+        }
+
+        let mut names: Vec<String> = Vec::new();
+        let mut candidates = short_names();
+        let renamed = self
+            .resolved
+            .iter()
+            .map(|declaration| {
+                let slot = self.slots[(*declaration)?]?;
+                while names.len() <= slot {
+                    let name = candidates
+                        .by_ref()
+                        .find(|name| !self.globals.contains(name))?;
+                    names.push(name);
+                }
+                Some(names[slot].clone())
+            })
+            .collect();
+        Some(renamed)
+    }
+
+    /// Bring pending locals into scope, and close `repeat` blocks, whose
+    /// statements end at token `i`
+    fn end_statements(&mut self, i: usize) -> Option<()> {
+        let token = &self.tokens[i];
+        let previous_ends = self.previous(i).is_some_and(Token::ends_expression);
+        let starts = match token.kind {
+            TokenKind::Keyword => match token.text.as_str() {
+                "function" => previous_ends,
+                "and" | "or" | "not" | "in" | "true" | "false" | "nil" => false,
+                _ => true,
+            },
+            TokenKind::Name | TokenKind::Number => previous_ends,
+            TokenKind::Symbol => matches!(token.text.as_str(), ";" | "::"),
+            TokenKind::String | TokenKind::Comment => false,
+        };
+        if !starts {
+            return Some(());
+        }
+        while let Some(pending) = self.pending.last() {
+            match pending {
+                Pending::Locals(depth, _) if *depth == self.frames.len() => {
+                    let Some(Pending::Locals(_, locals)) = self.pending.pop() else {
+                        unreachable!()
+                    };
+                    self.scopes.last_mut()?.extend(locals);
+                }
+                Pending::CloseRepeat(depth) if *depth == self.frames.len() => {
+                    self.pending.pop();
+                    self.close(Frame::Repeat)?;
+                }
+                _ => break,
+            }
+        }
+        Some(())
+    }
+
+    /// Walk the token at `i`, returning the index of the next one to walk
+    fn visit(&mut self, i: usize) -> Option<usize> {
+        let token = &self.tokens[i];
+        match (token.kind, token.text.as_str()) {
+            (TokenKind::Keyword, "local") => return self.visit_local(i),
+            (TokenKind::Keyword, "for") => return self.visit_for(i),
+            (TokenKind::Keyword, "function") => self.awaiting_parameters = true,
+            (TokenKind::Keyword, "do") => {
+                self.open(Frame::Block);
+                if let Some((depth, _)) = self.pending_for.last() {
+                    if *depth == self.frames.len() - 1 {
+                        let (_, locals) = self.pending_for.pop()?;
+                        self.scopes.last_mut()?.extend(locals);
+                    }
+                }
+            }
+            (TokenKind::Keyword, "then") => self.open(Frame::Block),
+            (TokenKind::Keyword, "repeat") => self.open(Frame::Repeat),
+            (TokenKind::Keyword, "until") => {
+                if self.frames.last() != Some(&Frame::Repeat) {
+                    return None;
+                }
+                self.pending.push(Pending::CloseRepeat(self.frames.len()));
+            }
+            (TokenKind::Keyword, "elseif") => self.close(Frame::Block)?,
+            (TokenKind::Keyword, "else") => {
+                self.close(Frame::Block)?;
+                self.open(Frame::Block);
+            }
+            (TokenKind::Keyword, "end") => match self.frames.last()? {
+                Frame::Block => self.close(Frame::Block)?,
+                Frame::Function => self.close(Frame::Function)?,
+                _ => return None,
+            },
+            (TokenKind::Symbol, "(") if self.awaiting_parameters => {
+                self.awaiting_parameters = false;
+                self.open(Frame::Function);
+                let mut j = i + 1;
+                loop {
+                    let token = self.tokens.get(j)?;
+                    match token.kind {
+                        TokenKind::Name => {
+                            self.declare(j, true);
+                        }
+                        TokenKind::Symbol if token.text == ")" => return Some(j + 1),
+                        TokenKind::Symbol if matches!(token.text.as_str(), "," | "...") => {}
+                        _ => return None,
+                    }
+                    j += 1;
+                }
+            }
+            (TokenKind::Symbol, "(" | "[" | "{") => {
+                self.frames.push(Frame::Bracket(token.text.as_bytes()[0]));
+            }
+            (TokenKind::Symbol, ")") => self.close_bracket(b'(')?,
+            (TokenKind::Symbol, "]") => self.close_bracket(b'[')?,
+            (TokenKind::Symbol, "}") => self.close_bracket(b'{')?,
+            // Names inside interpolated strings cannot be followed
+            (TokenKind::String, text) if text.starts_with('`') && text.contains('{') => {
+                return None
+            }
+            (TokenKind::Name, name) if self.is_variable(i) => match self.lookup(name) {
+                Some(declaration) => self.resolved[i] = Some(declaration),
+                None => {
+                    self.globals.insert(name.to_string());
+                }
+            },
+            _ => {}
+        }
+        Some(i + 1)
+    }
+
+    /// `local function f` puts `f` in scope at once; `local a, b = ...`
+    /// only once the statement ends
+    fn visit_local(&mut self, i: usize) -> Option<usize> {
+        if self.tokens.get(i + 1)?.is_keyword("function") {
+            self.declare(i + 2, true);
+            self.awaiting_parameters = true;
+            return Some(i + 3);
+        }
+        let mut locals = Vec::new();
+        let mut j = i + 1;
+        loop {
+            if self.tokens.get(j)?.kind != TokenKind::Name {
+                return None;
+            }
+            locals.push(self.declare(j, false));
+            j += 1;
+            // `<const>` and `<close>`
+            if self.tokens.get(j).is_some_and(|token| token.is_symbol("<")) {
+                j += 3;
+            }
+            if !self.tokens.get(j).is_some_and(|token| token.is_symbol(",")) {
+                break;
+            }
+            j += 1;
+        }
+        if self.tokens.get(j).is_some_and(|token| token.is_symbol("=")) {
+            self.pending
+                .push(Pending::Locals(self.frames.len(), locals));
+        } else {
+            self.scopes.last_mut()?.extend(locals);
+        }
+        Some(j)
+    }
+
+    /// Loop variables come into scope at the loop's `do`
+    fn visit_for(&mut self, i: usize) -> Option<usize> {
+        let mut locals = Vec::new();
+        let mut j = i + 1;
+        loop {
+            if self.tokens.get(j)?.kind != TokenKind::Name {
+                return None;
+            }
+            locals.push(self.declare(j, false));
+            j += 1;
+            if !self.tokens.get(j)?.is_symbol(",") {
+                break;
+            }
+            j += 1;
+        }
+        self.pending_for.push((self.frames.len(), locals));
+        Some(j)
+    }
+
+    /// Give the name at `i` a slot, in scope at once or left for the caller
+    /// to bring into scope
+    fn declare(&mut self, i: usize, in_scope: bool) -> (String, usize) {
+        let name = self.tokens[i].text.clone();
+        let declaration = self.slots.len();
+        // `self` is declared implicitly by methods, so it keeps its name
+        if name == "self" {
+            self.slots.push(None);
+        } else {
+            self.slots.push(Some(self.live));
+            self.live += 1;
+        }
+        self.resolved[i] = Some(declaration);
+        if in_scope {
+            if let Some(scope) = self.scopes.last_mut() {
+                scope.push((name.clone(), declaration));
+            }
+        }
+        (name, declaration)
+    }
+
+    fn lookup(&self, name: &str) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|(local, _)| local == name)
+            .map(|(_, declaration)| *declaration)
+    }
+
+    /// Whether the name at `i` reads or writes a variable, rather than
+    /// naming a field, a table key or a label
+    fn is_variable(&self, i: usize) -> bool {
+        let previous = self.previous(i);
+        let after_access = previous.is_some_and(|previous| {
+            previous.is_symbol(".")
+                || previous.is_symbol(":")
+                || previous.is_symbol("::")
+                || previous.is_keyword("goto")
+        });
+        let table_key = self.frames.last() == Some(&Frame::Bracket(b'{'))
+            && previous.is_some_and(|previous| {
+                previous.is_symbol("{") || previous.is_symbol(",") || previous.is_symbol(";")
+            })
+            && self
+                .tokens
+                .get(i + 1)
+                .is_some_and(|next| next.is_symbol("="));
+        !after_access && !table_key
+    }
+
+    fn previous(&self, i: usize) -> Option<&Token> {
+        self.tokens[..i]
+            .iter()
+            .rev()
+            .find(|token| token.kind != TokenKind::Comment)
+    }
+
+    fn open(&mut self, frame: Frame) {
+        self.frames.push(frame);
+        self.scopes.push(Vec::new());
+    }
+
+    /// Close the innermost frame, which must be `frame`, bringing what is
+    /// pending inside it into scope first so its slots are freed with it
+    fn close(&mut self, frame: Frame) -> Option<()> {
+        if self.frames.last() != Some(&frame) {
+            return None;
+        }
+        while let Some(Pending::Locals(depth, _)) = self.pending.last() {
+            if *depth < self.frames.len() {
+                break;
+            }
+            let Some(Pending::Locals(_, locals)) = self.pending.pop() else {
+                unreachable!()
+            };
+            self.scopes.last_mut()?.extend(locals);
+        }
+        self.frames.pop();
+        let scope = self.scopes.pop()?;
+        let freed = scope
+            .iter()
+            .filter(|(_, declaration)| self.slots[*declaration].is_some())
+            .count();
+        self.live -= freed;
+        Some(())
+    }
+
+    fn close_bracket(&mut self, open: u8) -> Option<()> {
+        match self.frames.pop()? {
+            Frame::Bracket(bracket) if bracket == open => Some(()),
+            _ => None,
+        }
+    }
+}
+
+/// `a`, `b`, ..., `_`, `aa`, `ab`, ... skipping keywords and `self`
+fn short_names() -> impl Iterator<Item = String> {
+    const FIRST: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_";
+    const REST: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_0123456789";
+    (0usize..)
+        .map(|mut n| {
+            let mut name = vec![FIRST[n % FIRST.len()]];
+            n /= FIRST.len();
+            while n > 0 {
+                n -= 1;
+                name.push(REST[n % REST.len()]);
+                n /= REST.len();
+            }
+            String::from_utf8(name).expect("names are ASCII")
+        })
+        .filter(|name| {
+            !KEYWORDS.contains(&name.as_str()) && !matches!(name.as_str(), "goto" | "self")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minify_for(lua: &str, target: LuaVersion) -> String {
+        let options = CompilerOptions {
+            target,
+            ..CompilerOptions::default()
+        };
+        minify(lua, &options)
+    }
+
+    #[test]
+    fn test_minify_strips_whitespace_and_comments() {
+        let lua = "-- header\nlocal x = 1 -- one\n--[[ long\ncomment ]]\nprint(x - -x, 1 .. 2, t[ [[s]] ])\n";
+        assert_eq!(
+            minify_for(lua, LuaVersion::Lua54),
+            "local a=1 print(a- -a,1 ..2,t[ [[s]]])\n"
+        );
+        assert_eq!(
+            minify_for("--!strict\nlocal x = 1\n", LuaVersion::Luau),
+            "--!strict\nlocal x=1\n"
+        );
+    }
+
+    #[test]
+    fn test_minify_renames_locals_by_scope() {
+        let lua = r#"
+local count = 0
+local function bump(step, ...)
+  local count = count + step
+  return count, { count = count }, self, print
+end
+for index, value in ipairs(list) do
+  local index = index + 1
+  obj:send(value.count, index)
+end
+repeat
+  local done = count
+until done
+goto skip
+::skip::
+"#;
+        assert_eq!(
+            minify_for(lua, LuaVersion::Lua54),
+            "local a=0 local function b(c,...)local d=a+c return d,{count=d},self,print end for c,d in ipairs(list)do local e=c+1 obj:send(d.count,e)end repeat local c=a until c goto skip::skip::\n"
+        );
+    }
+
+    #[test]
+    fn test_minify_folds_string_literals() {
+        let lua = r#"print("a" .. "b" .. 'c', x .. "d" .. "e", "f" .. "g" + 1, f "h" .. "i", "j" .. "k" .. y)"#;
+        assert_eq!(
+            minify_for(lua, LuaVersion::Lua54),
+            "print(\"abc\",x..\"de\",\"f\"..\"g\"+1,f\"h\"..\"i\",\"j\"..\"k\"..y)\n"
+        );
+    }
+}
@@ -0,0 +1,383 @@
+//! Dead-store elimination (`optimizationLevel` 1 and up): assignments to
+//! locals whose values are never read are removed, and a `local x = nil`
+//! followed by `x = value` becomes `local x = value`
+//!
+//! The analysis stays within one statement list: a store is dead when the
+//! list overwrites it before reading it, or when the list is a function's
+//! body and ends without reading it. Any other statement between the two,
+//! such as a loop or an `if`, counts as reading every local, and locals
+//! that a nested function mentions are left alone.
+
+use crate::ast::expression::{
+    ArrayElement, ArrowBody, AssignmentOp, Expression, ExpressionKind, ObjectProperty,
+};
+use crate::ast::pattern::Pattern;
+use crate::ast::statement::{Block, ClassMember, ForStatement, Parameter, Statement, VariableKind};
+use crate::ast::visit::{Visit, VisitMut};
+use crate::ast::Program;
+use crate::codegen::collect_bound_names;
+use std::collections::{HashMap, HashSet};
+
+/// Remove the dead stores of a module and of every function in it
+pub fn eliminate_dead_stores(program: &mut Program) {
+    // The module's locals outlive its last statement when it runs as part
+    // of a longer session, so stores at the end of it are kept
+    let captured = captured_names(&program.statements);
+    optimize_statements(&mut program.statements, HashSet::new(), &captured, false);
+    FunctionBodies.visit_program(program);
+}
+
+/// Finds every function, including methods, and optimizes its body
+struct FunctionBodies;
+
+impl VisitMut for FunctionBodies {
+    fn visit_statement(&mut self, statement: &mut Statement) {
+        if let Statement::Function(func) = statement {
+            optimize_function(&func.parameters, &mut func.body);
+        }
+        self.walk_statement(statement);
+    }
+
+    fn visit_class_member(&mut self, member: &mut ClassMember) {
+        match member {
+            ClassMember::Constructor(constructor) => {
+                if let Some(body) = &mut constructor.body {
+                    optimize_function(&constructor.parameters, body);
+                }
+            }
+            ClassMember::Method(method) => {
+                if let Some(body) = &mut method.body {
+                    optimize_function(&method.parameters, body);
+                }
+            }
+            ClassMember::Getter(getter) => optimize_function(&[], &mut getter.body),
+            ClassMember::Setter(setter) => {
+                optimize_function(std::slice::from_ref(&setter.parameter), &mut setter.body)
+            }
+            ClassMember::Property(_) | ClassMember::StaticBlock(_) => {}
+        }
+        self.walk_class_member(member);
+    }
+
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        match &mut expr.kind {
+            ExpressionKind::Function(func) => optimize_function(&func.parameters, &mut func.body),
+            ExpressionKind::Arrow(arrow) => {
+                if let ArrowBody::Block(body) = &mut arrow.body {
+                    optimize_function(&arrow.parameters, body);
+                }
+            }
+            _ => {}
+        }
+        self.walk_expression(expr);
+    }
+}
+
+fn optimize_function(parameters: &[Parameter], body: &mut Block) {
+    let mut declared = Vec::new();
+    for parameter in parameters {
+        collect_bound_names(&parameter.pattern, &mut declared);
+    }
+    let captured = captured_names(&body.statements);
+    optimize_statements(
+        &mut body.statements,
+        declared.into_iter().collect(),
+        &captured,
+        true,
+    );
+}
+
+/// Optimize a statement list and the blocks nested in it. `declared`
+/// holds the locals in scope at its start; `ends_function` whether
+/// control leaves the function when the list ends.
+fn optimize_statements(
+    statements: &mut Vec<Statement>,
+    mut declared: HashSet<String>,
+    captured: &HashSet<String>,
+    mut ends_function: bool,
+) {
+    collapse_declarations(statements);
+
+    // The last store to each local that nothing has read yet
+    let mut pending: HashMap<String, usize> = HashMap::new();
+    let mut dead = Vec::new();
+    for (index, statement) in statements.iter_mut().enumerate() {
+        match statement {
+            Statement::Variable(decl) => {
+                let reads = mentions(|names| {
+                    names.visit_pattern(&decl.pattern);
+                    names.visit_expression(&decl.initializer);
+                });
+                for name in reads {
+                    pending.remove(&name);
+                }
+                // The rest of the block may run in a closure that closes
+                // the value
+                if decl.kind == VariableKind::Using {
+                    pending.clear();
+                    ends_function = false;
+                }
+                let mut names = Vec::new();
+                collect_bound_names(&decl.pattern, &mut names);
+                for name in names {
+                    pending.remove(&name);
+                    declared.insert(name);
+                }
+            }
+            Statement::Expression(expr) => match store(expr) {
+                Some((name, value)) => {
+                    for read in mentions(|names| names.visit_expression(value)) {
+                        pending.remove(&read);
+                    }
+                    if declared.contains(name) && !captured.contains(name) {
+                        if let Some(previous) = pending.remove(name) {
+                            dead.push(previous);
+                        }
+                        if is_pure(value, &declared) {
+                            pending.insert(name.to_string(), index);
+                        }
+                    }
+                }
+                None => {
+                    for read in mentions(|names| names.visit_expression(expr)) {
+                        pending.remove(&read);
+                    }
+                }
+            },
+            _ => {
+                pending.clear();
+                optimize_nested(statement, &declared, captured);
+            }
+        }
+    }
+    if ends_function {
+        dead.extend(pending.into_values());
+    }
+
+    dead.sort_unstable();
+    for index in dead.into_iter().rev() {
+        statements.remove(index);
+    }
+}
+
+/// Optimize the blocks of a statement that are not function bodies
+fn optimize_nested(
+    statement: &mut Statement,
+    declared: &HashSet<String>,
+    captured: &HashSet<String>,
+) {
+    let block = |block: &mut Block, variables: Vec<String>| {
+        let mut declared = declared.clone();
+        declared.extend(variables);
+        optimize_statements(&mut block.statements, declared, captured, false);
+    };
+    match statement {
+        Statement::If(if_stmt) => {
+            block(&mut if_stmt.then_block, Vec::new());
+            for else_if in &mut if_stmt.else_ifs {
+                block(&mut else_if.block, Vec::new());
+            }
+            if let Some(else_block) = &mut if_stmt.else_block {
+                block(else_block, Vec::new());
+            }
+        }
+        Statement::While(while_stmt) => block(&mut while_stmt.body, Vec::new()),
+        Statement::For(ForStatement::Numeric(numeric)) => {
+            block(&mut numeric.body, vec![numeric.variable.node.clone()])
+        }
+        Statement::For(ForStatement::Generic(generic)) => {
+            let variables = generic.variables.iter().map(|v| v.node.clone()).collect();
+            block(&mut generic.body, variables)
+        }
+        Statement::Repeat(repeat) => block(&mut repeat.body, Vec::new()),
+        Statement::Labeled(labeled) => optimize_nested(&mut labeled.body, declared, captured),
+        Statement::Block(inner) | Statement::Untyped(inner) => block(inner, Vec::new()),
+        _ => {}
+    }
+}
+
+/// Fold `local x = <pure>` followed by `x = value` into `local x = value`,
+/// unless the value mentions `x`, which would then name an outer variable
+fn collapse_declarations(statements: &mut Vec<Statement>) {
+    let mut index = 0;
+    while index + 1 < statements.len() {
+        let collapses = match (&statements[index], &statements[index + 1]) {
+            (Statement::Variable(decl), Statement::Expression(expr)) => {
+                match (&decl.pattern, store(expr)) {
+                    (Pattern::Identifier(local), Some((name, value))) => {
+                        decl.kind == VariableKind::Local
+                            && decl.decorators.is_empty()
+                            && local.node == name
+                            && is_pure(&decl.initializer, &HashSet::new())
+                            && !mentions(|names| names.visit_expression(value)).contains(name)
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        };
+        if collapses {
+            let Statement::Expression(Expression {
+                kind: ExpressionKind::Assignment(_, _, value),
+                ..
+            }) = statements.remove(index + 1)
+            else {
+                unreachable!("only assignments collapse into declarations");
+            };
+            if let Statement::Variable(decl) = &mut statements[index] {
+                decl.initializer = *value;
+            }
+        } else {
+            index += 1;
+        }
+    }
+}
+
+/// The local and value of a plain `name = value` statement
+fn store(expr: &Expression) -> Option<(&str, &Expression)> {
+    match &expr.kind {
+        ExpressionKind::Assignment(target, AssignmentOp::Assign, value) => match &target.kind {
+            ExpressionKind::Identifier(name) => Some((name, value)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether evaluating an expression can be skipped: it calls nothing,
+/// reads no global and triggers no metamethod
+fn is_pure(expr: &Expression, declared: &HashSet<String>) -> bool {
+    match &expr.kind {
+        ExpressionKind::Literal(_)
+        | ExpressionKind::SelfKeyword
+        | ExpressionKind::Function(_)
+        | ExpressionKind::Arrow(_) => true,
+        ExpressionKind::Identifier(name) => declared.contains(name),
+        ExpressionKind::Parenthesized(inner) => is_pure(inner, declared),
+        ExpressionKind::Array(elements) => elements.iter().all(|element| match element {
+            ArrayElement::Expression(value) => is_pure(value, declared),
+            ArrayElement::Spread(_) => false,
+        }),
+        ExpressionKind::Object(properties) => properties.iter().all(|property| match property {
+            ObjectProperty::Property { value, .. } => is_pure(value, declared),
+            ObjectProperty::Computed { key, value, .. } => {
+                is_pure(key, declared) && is_pure(value, declared)
+            }
+            ObjectProperty::Spread { .. } => false,
+        }),
+        _ => false,
+    }
+}
+
+/// Names a node mentions as expressions, including in nested functions
+fn mentions(visit: impl FnOnce(&mut Mentions)) -> HashSet<String> {
+    let mut mentions = Mentions::default();
+    visit(&mut mentions);
+    mentions.names
+}
+
+#[derive(Default)]
+struct Mentions {
+    /// How many functions deep the walk is
+    depth: usize,
+    names: HashSet<String>,
+    /// Only names mentioned inside nested functions are collected
+    nested_only: bool,
+}
+
+impl Visit for Mentions {
+    fn visit_statement(&mut self, statement: &Statement) {
+        if matches!(statement, Statement::Function(_) | Statement::Class(_)) {
+            self.depth += 1;
+            self.walk_statement(statement);
+            self.depth -= 1;
+        } else {
+            self.walk_statement(statement);
+        }
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        match &expr.kind {
+            ExpressionKind::Identifier(name) if !self.nested_only || self.depth > 0 => {
+                self.names.insert(name.clone());
+            }
+            ExpressionKind::Function(_) | ExpressionKind::Arrow(_) => {
+                self.depth += 1;
+                self.walk_expression(expr);
+                self.depth -= 1;
+                return;
+            }
+            _ => {}
+        }
+        self.walk_expression(expr);
+    }
+}
+
+/// Names mentioned inside the functions and classes nested in a body,
+/// which may read a local after any of its stores
+fn captured_names(statements: &[Statement]) -> HashSet<String> {
+    let mut mentions = Mentions {
+        nested_only: true,
+        ..Mentions::default()
+    };
+    for statement in statements {
+        mentions.visit_statement(statement);
+    }
+    mentions.names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::CodeGenerator;
+    use crate::config::CompilerConfig;
+    use crate::diagnostics::CollectingDiagnosticHandler;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use std::sync::Arc;
+
+    fn optimize(source: &str) -> String {
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let tokens = Lexer::new(source, handler.clone())
+            .tokenize()
+            .expect("Lexing failed");
+        let mut program = Parser::new(tokens, handler)
+            .parse()
+            .expect("Parsing failed");
+        eliminate_dead_stores(&mut program);
+        CodeGenerator::new(Arc::new(CompilerConfig::default())).generate(&program)
+    }
+
+    #[test]
+    fn test_dead_stores_are_removed() {
+        let source = r#"function load(path: string): string
+    local text = nil
+    text = read(path)
+    local size = 0
+    print(size)
+    size = 10
+    size = #text
+    print(size)
+    local status = "ok"
+    status = "done"
+    local handler = nil
+    handler = () => handler
+    if size > 0 then
+        size = 1
+    end
+    return text
+end
+"#;
+        let lua = optimize(source);
+        assert!(lua.contains("local text = read(path)\n"), "{}", lua);
+        assert!(!lua.contains("text = nil"), "{}", lua);
+        assert!(lua.contains("local size = 0\n"), "{}", lua);
+        assert!(!lua.contains("size = 10"), "{}", lua);
+        assert!(lua.contains("size = #text\n"), "{}", lua);
+        assert!(lua.contains("local status = \"done\"\n"), "{}", lua);
+        // The arrow reads the local it is assigned to, so the two stay
+        // apart, and the store in the `if` is not the function's last
+        assert!(lua.contains("local handler = nil\n"), "{}", lua);
+        assert!(lua.contains("size = 1\n"), "{}", lua);
+    }
+}
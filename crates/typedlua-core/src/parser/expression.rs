@@ -1,6 +1,6 @@
-use super::{Parser, ParserError, PatternParser, StatementParser, TypeParser};
+use super::{number_literal, Parser, ParserError, PatternParser, StatementParser, TypeParser};
 use crate::ast::expression::*;
-use crate::lexer::TokenKind;
+use crate::lexer::{Token, TokenKind};
 
 pub trait ExpressionParser {
     fn parse_expression(&mut self) -> Result<Expression, ParserError>;
@@ -29,7 +29,7 @@ impl Parser {
         let expr = self.parse_conditional()?;
 
         if let Some(op) = self.match_assignment_op() {
-            let right = self.parse_assignment()?;
+            let right = self.nested(|parser| parser.parse_assignment())?;
             let span = expr.span.combine(&right.span);
             return Ok(Expression {
                 kind: ExpressionKind::Assignment(Box::new(expr), op, Box::new(right)),
@@ -101,7 +101,7 @@ impl Parser {
     }
 
     fn parse_conditional(&mut self) -> Result<Expression, ParserError> {
-        let expr = self.parse_logical_or()?;
+        let expr = self.parse_pipe()?;
 
         if self.match_token(&[TokenKind::Question]) {
             let then_expr = self.parse_expression()?;
@@ -121,6 +121,23 @@ impl Parser {
         Ok(expr)
     }
 
+    /// `x |> f |> g` binds looser than every binary operator and threads
+    /// left to right, so it parses as `(x |> f) |> g`
+    fn parse_pipe(&mut self) -> Result<Expression, ParserError> {
+        let mut expr = self.parse_logical_or()?;
+
+        while self.match_token(&[TokenKind::PipeOp]) {
+            let right = self.parse_logical_or()?;
+            let span = expr.span.combine(&right.span);
+            expr = Expression {
+                kind: ExpressionKind::Pipe(Box::new(expr), Box::new(right)),
+                span,
+            };
+        }
+
+        Ok(expr)
+    }
+
     fn parse_logical_or(&mut self) -> Result<Expression, ParserError> {
         let mut expr = self.parse_logical_and()?;
 
@@ -298,7 +315,7 @@ impl Parser {
         let expr = self.parse_unary()?;
 
         if self.match_token(&[TokenKind::Caret]) {
-            let right = self.parse_power()?; // Right associative
+            let right = self.nested(|parser| parser.parse_power())?; // Right associative
             let span = expr.span.combine(&right.span);
             return Ok(Expression {
                 kind: ExpressionKind::Binary(BinaryOp::Power, Box::new(expr), Box::new(right)),
@@ -310,17 +327,20 @@ impl Parser {
     }
 
     fn parse_unary(&mut self) -> Result<Expression, ParserError> {
-        if let Some(op) = self.match_unary_op() {
-            let expr = self.parse_unary()?;
-            let start_span = self.current_span();
-            let span = start_span.combine(&expr.span);
-            return Ok(Expression {
-                kind: ExpressionKind::Unary(op, Box::new(expr)),
-                span,
-            });
-        }
+        // Every nested operand comes through here
+        self.nested(|parser| {
+            let start_span = parser.current_span();
+            if let Some(op) = parser.match_unary_op() {
+                let expr = parser.parse_unary()?;
+                let span = start_span.combine(&expr.span);
+                return Ok(Expression {
+                    kind: ExpressionKind::Unary(op, Box::new(expr)),
+                    span,
+                });
+            }
 
-        self.parse_postfix()
+            parser.parse_postfix()
+        })
     }
 
     fn parse_postfix(&mut self) -> Result<Expression, ParserError> {
@@ -337,6 +357,15 @@ impl Parser {
                         span,
                     };
                 }
+                TokenKind::QuestionDot => {
+                    self.advance();
+                    let member = self.parse_identifier()?;
+                    let span = expr.span.combine(&member.span);
+                    expr = Expression {
+                        kind: ExpressionKind::OptionalMember(Box::new(expr), member),
+                        span,
+                    };
+                }
                 TokenKind::LeftBracket => {
                     self.advance();
                     let index = self.parse_expression()?;
@@ -371,15 +400,6 @@ impl Parser {
                         span,
                     };
                 }
-                TokenKind::PipeOp => {
-                    self.advance();
-                    let right = self.parse_unary()?;
-                    let span = expr.span.combine(&right.span);
-                    expr = Expression {
-                        kind: ExpressionKind::Pipe(Box::new(expr), Box::new(right)),
-                        span,
-                    };
-                }
                 _ => break,
             }
         }
@@ -413,13 +433,13 @@ impl Parser {
                 })
             }
             TokenKind::Number(s) => {
-                let num = s.parse::<f64>().map_err(|_| ParserError {
+                let literal = number_literal(s).ok_or_else(|| ParserError {
                     message: "Invalid number literal".to_string(),
                     span: start_span,
                 })?;
                 self.advance();
                 Ok(Expression {
-                    kind: ExpressionKind::Literal(Literal::Number(num)),
+                    kind: ExpressionKind::Literal(literal),
                     span: start_span,
                 })
             }
@@ -432,6 +452,16 @@ impl Parser {
                 })
             }
             TokenKind::Identifier(name) => {
+                // Statements can invoke macros; their bodies have no value
+                if self.check_macro_invocation() {
+                    return Err(ParserError {
+                        message: format!(
+                            "Macro '{}' is used as an expression, but expands to statements",
+                            name
+                        ),
+                        span: start_span,
+                    });
+                }
                 let id = name.clone();
                 self.advance();
                 Ok(Expression {
@@ -555,8 +585,7 @@ impl Parser {
         };
 
         let body = self.parse_block()?;
-        self.consume(TokenKind::End, "Expected 'end' after function body")?;
-        let end_span = self.current_span();
+        let end_span = self.consume(TokenKind::End, "Expected 'end' after function body")?.span;
 
         Ok(Expression {
             kind: ExpressionKind::Function(FunctionExpression {
@@ -646,13 +675,15 @@ impl Parser {
                     ast_parts.push(crate::ast::expression::TemplatePart::String(s));
                 }
                 crate::lexer::TemplatePart::Expression(tokens) => {
-                    // Parse the expression tokens
-                    // We need to create a temporary parser for these tokens
-                    let handler = self.diagnostic_handler.clone();
-                    let mut temp_parser = Parser::new(tokens, handler);
-                    let expr = temp_parser.parse_expression()?;
+                    let expr = self.parse_interpolation(tokens)?;
                     ast_parts.push(crate::ast::expression::TemplatePart::Expression(expr));
                 }
+                crate::lexer::TemplatePart::Formatted(tokens, format) => {
+                    let expr = self.parse_interpolation(tokens)?;
+                    ast_parts.push(crate::ast::expression::TemplatePart::Formatted(
+                        expr, format,
+                    ));
+                }
             }
         }
 
@@ -665,6 +696,24 @@ impl Parser {
         })
     }
 
+    /// Parse the tokens of one `${...}` with a parser of their own, which
+    /// must use all of them
+    fn parse_interpolation(&mut self, tokens: Vec<Token>) -> Result<Expression, ParserError> {
+        let mut parser = Parser::new(tokens, self.diagnostic_handler.clone());
+        parser.nesting = self.nesting;
+        let expr = parser.parse_expression()?;
+        if !parser.is_at_end() {
+            return Err(ParserError {
+                message: format!(
+                    "Unexpected '{}' in template interpolation",
+                    parser.current().kind
+                ),
+                span: parser.current_span(),
+            });
+        }
+        Ok(expr)
+    }
+
     fn parse_argument_list(&mut self) -> Result<Vec<Argument>, ParserError> {
         let mut arguments = Vec::new();
 
@@ -737,9 +786,23 @@ impl Parser {
         op
     }
 
+    /// `<<` and `>>` are lexed as two adjacent angle brackets, so that
+    /// nested generic arguments like `Map<string, Array<T>>` still close
     fn match_shift_op(&mut self) -> Option<BinaryOp> {
-        // TODO: Add shift operators when we have << and >> tokens
-        None
+        let op = match &self.current().kind {
+            TokenKind::LessThan => BinaryOp::ShiftLeft,
+            TokenKind::GreaterThan => BinaryOp::ShiftRight,
+            _ => return None,
+        };
+        let next = self.peek(1)?;
+        if std::mem::discriminant(&next.kind) != std::mem::discriminant(&self.current().kind)
+            || next.span.start != self.current().span.end
+        {
+            return None;
+        }
+        self.advance();
+        self.advance();
+        Some(op)
     }
 
     fn match_additive_op(&mut self) -> Option<BinaryOp> {
@@ -0,0 +1,465 @@
+use super::{ExpressionParser, Parser, ParserError, StatementParser};
+use crate::ast::statement::{Block, Statement};
+use crate::lexer::{tokens_to_source, TemplatePart, Token, TokenKind};
+use crate::span::Span;
+use std::collections::HashMap;
+
+/// Expansions may invoke other macros, but not without bound
+const MAX_EXPANSION_DEPTH: usize = 32;
+
+/// A `macro name(params) ... end` definition. The body is kept as tokens
+/// and reparsed at every invocation with the arguments spliced in.
+#[derive(Debug, Clone)]
+pub(super) struct MacroDefinition {
+    parameters: Vec<String>,
+    body: Vec<Token>,
+}
+
+pub(super) type Macros = HashMap<String, MacroDefinition>;
+
+impl Parser {
+    /// `macro` is contextual: only a keyword when a name and `(` follow it
+    /// at the start of a statement
+    pub(super) fn check_macro(&self) -> bool {
+        matches!(&self.current().kind, TokenKind::Identifier(s) if s == "macro")
+            && matches!(
+                self.peek(1).map(|t| &t.kind),
+                Some(TokenKind::Identifier(_))
+            )
+            && matches!(self.peek(2).map(|t| &t.kind), Some(TokenKind::LeftParen))
+    }
+
+    pub(super) fn check_macro_invocation(&self) -> bool {
+        matches!(&self.current().kind, TokenKind::Identifier(name) if self.macros.contains_key(name))
+            && matches!(self.peek(1).map(|t| &t.kind), Some(TokenKind::LeftParen))
+    }
+
+    /// Register a macro for the rest of the file. Definitions leave nothing
+    /// in the AST.
+    pub(super) fn parse_macro_definition(&mut self) -> Result<(), ParserError> {
+        self.advance(); // macro
+        let name = self.parse_identifier()?;
+        self.consume(TokenKind::LeftParen, "Expected '(' after macro name")?;
+
+        let mut parameters = Vec::new();
+        if !self.check(&TokenKind::RightParen) {
+            loop {
+                parameters.push(self.parse_identifier()?.node);
+                if !self.match_token(&[TokenKind::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenKind::RightParen, "Expected ')' after macro parameters")?;
+
+        // Parse the body once so syntax errors point at the definition
+        let body_start = self.position;
+        self.parse_block()?;
+        let body = self.tokens[body_start..self.position].to_vec();
+        self.consume(TokenKind::End, "Expected 'end' after macro body")?;
+
+        self.macros
+            .insert(name.node, MacroDefinition { parameters, body });
+        Ok(())
+    }
+
+    /// A macro invocation statement, replaced by the macro's body in its own
+    /// block. Arguments are substituted as written, parenthesized where they
+    /// are operands, and the body's locals are renamed so they cannot
+    /// capture names the arguments use. An argument the body uses more than
+    /// once is evaluated once, into a local, unless it is a name or a
+    /// literal. A parameter the body assigns to takes only names. Inside
+    /// the body, `stringify(param)` is the argument's source text and
+    /// `lineof(param)` the line it starts on.
+    pub(super) fn parse_macro_invocation(&mut self) -> Result<Statement, ParserError> {
+        let start_span = self.current_span();
+        let name = self.parse_identifier()?;
+        self.consume(TokenKind::LeftParen, "Expected '(' after macro name")?;
+
+        let mut arguments: Vec<Vec<Token>> = Vec::new();
+        if !self.check(&TokenKind::RightParen) {
+            loop {
+                let argument_start = self.position;
+                self.parse_expression()?;
+                arguments.push(self.tokens[argument_start..self.position].to_vec());
+                if !self.match_token(&[TokenKind::Comma]) {
+                    break;
+                }
+            }
+        }
+        let end_span = self.current_span();
+        self.consume(TokenKind::RightParen, "Expected ')' after macro arguments")?;
+        let span = start_span.combine(&end_span);
+
+        let definition = self.macros[&name.node].clone();
+        if arguments.len() != definition.parameters.len() {
+            return Err(ParserError {
+                message: format!(
+                    "Macro '{}' expects {} argument(s), got {}",
+                    name.node,
+                    definition.parameters.len(),
+                    arguments.len()
+                ),
+                span,
+            });
+        }
+        if self.macro_depth >= MAX_EXPANSION_DEPTH {
+            return Err(ParserError {
+                message: format!("Expansion of macro '{}' is nested too deeply", name.node),
+                span,
+            });
+        }
+
+        for (parameter, argument) in definition.parameters.iter().zip(&arguments) {
+            let is_name = matches!(argument.as_slice(), [token] if matches!(token.kind, TokenKind::Identifier(_)));
+            if !is_name && assigns(&definition.body, parameter) {
+                return Err(ParserError {
+                    message: format!(
+                        "Macro '{}' assigns to '{}', so its argument must be a name",
+                        name.node, parameter
+                    ),
+                    span: argument.first().map_or(span, |token| token.span),
+                });
+            }
+        }
+
+        self.expansions += 1;
+        let mut tokens = expand(&definition, &arguments, self.expansions);
+        tokens.push(Token::eof(span.end));
+
+        let mut parser = Parser::new(tokens, self.diagnostic_handler.clone());
+        parser.macros = self.macros.clone();
+        parser.macro_depth = self.macro_depth + 1;
+        parser.expansions = self.expansions;
+        let block = parser.parse_block()?;
+        if !parser.is_at_end() {
+            return Err(ParserError {
+                message: format!("Unexpected token in expansion of macro '{}'", name.node),
+                span: parser.current_span(),
+            });
+        }
+        self.expansions = parser.expansions;
+
+        Ok(Statement::Block(Block {
+            statements: block.statements,
+            span,
+        }))
+    }
+}
+
+/// The body of a macro with its parameters replaced by the arguments,
+/// after locals holding the arguments it evaluates once, in order
+fn expand(definition: &MacroDefinition, arguments: &[Vec<Token>], expansion: usize) -> Vec<Token> {
+    let body = &definition.body;
+    let mut tokens = Vec::new();
+    let mut bound = HashMap::new();
+    for (parameter, argument) in definition.parameters.iter().zip(arguments) {
+        if is_simple(argument) || uses(body, parameter) < 2 {
+            continue;
+        }
+        // `local a__1 = f();`, the `;` keeping a parenthesized statement
+        // after it from reading as a call
+        let name = format!("{}__{}", parameter, expansion);
+        let span = argument
+            .first()
+            .map_or_else(Span::dummy, |token| token.span);
+        tokens.push(Token::new(TokenKind::Local, span));
+        tokens.push(Token::new(TokenKind::Identifier(name.clone()), span));
+        tokens.push(Token::new(TokenKind::Equal, span));
+        tokens.extend(argument.iter().cloned());
+        tokens.push(Token::new(TokenKind::Semicolon, span));
+        bound.insert(parameter.as_str(), name);
+    }
+    let arguments: HashMap<&str, &[Token]> = definition
+        .parameters
+        .iter()
+        .map(String::as_str)
+        .zip(arguments.iter().map(Vec::as_slice))
+        .collect();
+    let substitutions = Substitutions {
+        arguments: &arguments,
+        bound: &bound,
+        locals: &declared_locals(body),
+        expansion,
+    };
+    tokens.extend(substitutions.apply(body));
+    tokens
+}
+
+/// What replaces the names of a macro body
+struct Substitutions<'a> {
+    arguments: &'a HashMap<&'a str, &'a [Token]>,
+    /// The locals holding arguments evaluated once, by parameter
+    bound: &'a HashMap<&'a str, String>,
+    locals: &'a [&'a str],
+    expansion: usize,
+}
+
+impl Substitutions<'_> {
+    fn apply(&self, body: &[Token]) -> Vec<Token> {
+        let mut tokens = Vec::with_capacity(body.len());
+        let mut index = 0;
+
+        while index < body.len() {
+            let token = &body[index];
+            let name = match &token.kind {
+                TokenKind::Identifier(name) if !is_member_name(body, index) => name.as_str(),
+                TokenKind::TemplateString(parts) => {
+                    let parts = parts
+                        .iter()
+                        .map(|part| match part {
+                            TemplatePart::Expression(inner) => {
+                                TemplatePart::Expression(self.apply(inner))
+                            }
+                            TemplatePart::Formatted(inner, format) => {
+                                TemplatePart::Formatted(self.apply(inner), format.clone())
+                            }
+                            part => part.clone(),
+                        })
+                        .collect();
+                    tokens.push(Token::new(TokenKind::TemplateString(parts), token.span));
+                    index += 1;
+                    continue;
+                }
+                _ => {
+                    tokens.push(token.clone());
+                    index += 1;
+                    continue;
+                }
+            };
+
+            if let Some(argument) = intrinsic_argument(body, index, self.arguments) {
+                let kind = if name == "stringify" {
+                    TokenKind::String(tokens_to_source(argument))
+                } else {
+                    TokenKind::Number(argument[0].span.line.to_string())
+                };
+                tokens.push(Token::new(kind, token.span.combine(&body[index + 3].span)));
+                index += 4;
+                continue;
+            }
+
+            if let Some(local) = self.bound.get(name) {
+                tokens.push(Token::new(TokenKind::Identifier(local.clone()), token.span));
+            } else if let Some(argument) = self.arguments.get(name) {
+                if argument.len() > 1 && is_operand(body, index) {
+                    // A statement can start here, so end the one before it
+                    // rather than call what it ends with
+                    if tokens
+                        .last()
+                        .is_some_and(|token| ends_expression(&token.kind))
+                    {
+                        tokens.push(Token::new(TokenKind::Semicolon, token.span));
+                    }
+                    let first = argument.first().map_or(token.span, |t| t.span);
+                    let last = argument.last().map_or(token.span, |t| t.span);
+                    tokens.push(Token::new(TokenKind::LeftParen, first));
+                    tokens.extend(argument.iter().cloned());
+                    tokens.push(Token::new(TokenKind::RightParen, last));
+                } else {
+                    tokens.extend(argument.iter().cloned());
+                }
+            } else if self.locals.contains(&name) {
+                let renamed = format!("{}__{}", name, self.expansion);
+                tokens.push(Token::new(TokenKind::Identifier(renamed), token.span));
+            } else {
+                tokens.push(token.clone());
+            }
+            index += 1;
+        }
+
+        tokens
+    }
+}
+
+/// The argument of `stringify(param)` or `lineof(param)` starting at `index`
+fn intrinsic_argument<'a>(
+    body: &[Token],
+    index: usize,
+    arguments: &HashMap<&str, &'a [Token]>,
+) -> Option<&'a [Token]> {
+    let [intrinsic, open, param, close] = body.get(index..index + 4)? else {
+        return None;
+    };
+    let is_intrinsic =
+        matches!(&intrinsic.kind, TokenKind::Identifier(n) if n == "stringify" || n == "lineof");
+    match (&open.kind, &param.kind, &close.kind) {
+        (TokenKind::LeftParen, TokenKind::Identifier(param), TokenKind::RightParen)
+            if is_intrinsic =>
+        {
+            arguments.get(param.as_str()).copied()
+        }
+        _ => None,
+    }
+}
+
+/// Names a macro body declares with `local`, `const`, `local function` or
+/// as `for` loop variables
+fn declared_locals(body: &[Token]) -> Vec<&str> {
+    let mut locals = Vec::new();
+    for (index, token) in body.iter().enumerate() {
+        let declared = match token.kind {
+            TokenKind::Local | TokenKind::Const => match body.get(index + 1).map(|t| &t.kind) {
+                Some(TokenKind::Function) => body.get(index + 2..index + 3),
+                _ => body.get(index + 1..index + 2),
+            },
+            TokenKind::For => {
+                let end = body[index + 1..]
+                    .iter()
+                    .position(|t| matches!(t.kind, TokenKind::Equal | TokenKind::In))
+                    .map_or(index + 1, |offset| index + 1 + offset);
+                body.get(index + 1..end)
+            }
+            _ => None,
+        };
+        for token in declared.unwrap_or_default() {
+            if let TokenKind::Identifier(name) = &token.kind {
+                if !locals.contains(&name.as_str()) {
+                    locals.push(name.as_str());
+                }
+            }
+        }
+    }
+    locals
+}
+
+/// A name or literal, which reads the same however often it is evaluated
+fn is_simple(argument: &[Token]) -> bool {
+    matches!(
+        argument,
+        [Token {
+            kind: TokenKind::Identifier(_)
+                | TokenKind::Number(_)
+                | TokenKind::String(_)
+                | TokenKind::True
+                | TokenKind::False
+                | TokenKind::Nil,
+            ..
+        }]
+    )
+}
+
+/// The places a body reads or writes `parameter`, besides `stringify` and
+/// `lineof`, which only look at the argument's source
+fn uses(body: &[Token], parameter: &str) -> usize {
+    body.iter()
+        .enumerate()
+        .map(|(index, token)| match &token.kind {
+            TokenKind::Identifier(name) => {
+                let in_intrinsic = index >= 2
+                    && matches!(&body[index - 2].kind, TokenKind::Identifier(n) if n == "stringify" || n == "lineof")
+                    && body[index - 1].kind == TokenKind::LeftParen
+                    && body.get(index + 1).map(|t| &t.kind) == Some(&TokenKind::RightParen);
+                usize::from(name == parameter && !is_member_name(body, index) && !in_intrinsic)
+            }
+            TokenKind::TemplateString(parts) => parts
+                .iter()
+                .map(|part| match part {
+                    TemplatePart::Expression(inner) | TemplatePart::Formatted(inner, _) => {
+                        uses(inner, parameter)
+                    }
+                    TemplatePart::String(_) => 0,
+                })
+                .sum(),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Whether the body assigns to `parameter`, as in `a = b` or `a, b = b, a`
+fn assigns(body: &[Token], parameter: &str) -> bool {
+    body.iter().enumerate().any(|(index, token)| {
+        if !matches!(&token.kind, TokenKind::Identifier(name) if name == parameter)
+            || is_member_name(body, index)
+        {
+            return false;
+        }
+        let mut next = index + 1;
+        while matches!(body.get(next).map(|t| &t.kind), Some(TokenKind::Comma))
+            && matches!(
+                body.get(next + 1).map(|t| &t.kind),
+                Some(TokenKind::Identifier(_))
+            )
+        {
+            next += 2;
+        }
+        matches!(body.get(next).map(|t| &t.kind), Some(TokenKind::Equal))
+    })
+}
+
+/// Whether the name at `index` is the operand of an operator or of a
+/// member access, index or call, where an argument of several tokens
+/// needs parentheses to keep its precedence
+fn is_operand(body: &[Token], index: usize) -> bool {
+    let before = index.checked_sub(1).map(|before| &body[before].kind);
+    let after = body.get(index + 1).map(|token| &token.kind);
+    before.is_some_and(is_operator)
+        || after.is_some_and(|kind| {
+            is_operator(kind) && !matches!(kind, TokenKind::Not | TokenKind::Hash)
+                || matches!(
+                    kind,
+                    TokenKind::Dot
+                        | TokenKind::QuestionDot
+                        | TokenKind::ColonColon
+                        | TokenKind::LeftBracket
+                        | TokenKind::LeftParen
+                )
+        })
+}
+
+fn is_operator(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Plus
+            | TokenKind::Minus
+            | TokenKind::Star
+            | TokenKind::Slash
+            | TokenKind::Percent
+            | TokenKind::Caret
+            | TokenKind::Hash
+            | TokenKind::Ampersand
+            | TokenKind::Pipe
+            | TokenKind::Tilde
+            | TokenKind::LessThan
+            | TokenKind::LessEqual
+            | TokenKind::GreaterThan
+            | TokenKind::GreaterEqual
+            | TokenKind::EqualEqual
+            | TokenKind::BangEqual
+            | TokenKind::TildeEqual
+            | TokenKind::DotDot
+            | TokenKind::PipeOp
+            | TokenKind::Question
+            | TokenKind::And
+            | TokenKind::Or
+            | TokenKind::Not
+    )
+}
+
+/// Whether an expression can end with the token, so that a `(` after it
+/// would call it
+fn ends_expression(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Identifier(_)
+            | TokenKind::Number(_)
+            | TokenKind::String(_)
+            | TokenKind::TemplateString(_)
+            | TokenKind::True
+            | TokenKind::False
+            | TokenKind::Nil
+            | TokenKind::RightParen
+            | TokenKind::RightBracket
+            | TokenKind::RightBrace
+            | TokenKind::End
+    )
+}
+
+/// Whether the identifier at `index` names a member, as in `t.name`
+fn is_member_name(tokens: &[Token], index: usize) -> bool {
+    index > 0
+        && matches!(
+            tokens[index - 1].kind,
+            TokenKind::Dot | TokenKind::ColonColon | TokenKind::QuestionDot
+        )
+}
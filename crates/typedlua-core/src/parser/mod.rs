@@ -1,4 +1,5 @@
 mod expression;
+mod macros;
 mod pattern;
 mod statement;
 mod types;
@@ -6,10 +7,14 @@ mod types;
 #[cfg(test)]
 mod tests;
 
+use crate::ast::expression::{Expression, IntegerSuffix, Literal};
+use crate::ast::statement::Statement;
+use crate::ast::types::Type;
 use crate::ast::Program;
-use crate::diagnostics::{Diagnostic, DiagnosticHandler, DiagnosticLevel};
-use crate::lexer::{Token, TokenKind};
+use crate::diagnostics::{Applicability, Diagnostic, DiagnosticHandler, SuggestedFix};
+use crate::lexer::{Lexer, Token, TokenKind};
 use crate::span::Span;
+use macros::Macros;
 use std::sync::Arc;
 
 pub use expression::ExpressionParser;
@@ -17,12 +22,90 @@ pub use pattern::PatternParser;
 pub use statement::StatementParser;
 pub use types::TypeParser;
 
+/// Deepest nesting of expressions, types, patterns or blocks the parser
+/// accepts. Deeper input is an error rather than a stack overflow.
+const MAX_NESTING: usize = 64;
+
+/// Close the innermost open block at the end of the input. Where the
+/// block was meant to end is a guess, so the fix is only offered.
+fn missing_end_fix(eof: Span) -> SuggestedFix {
+    let text = if eof.column == 1 { "end\n" } else { "\nend" };
+    SuggestedFix::replace("Insert 'end'", eof, text, Applicability::MaybeIncorrect)
+}
+
 #[derive(Debug, Clone)]
 pub struct ParserError {
     pub message: String,
     pub span: Span,
 }
 
+/// Interpret a numeric token. Integer spellings (`42`, `0xFF`, `0b101`)
+/// become integer literals; a fraction or exponent makes a float, as does a
+/// decimal integer too large to represent. Hexadecimal and binary integers
+/// past `i64::MAX` keep both the value they wrap around to and their float
+/// value, since which one Lua reads depends on the target. `_` separators are
+/// dropped and LuaJIT's `LL`/`ULL` suffixes are kept.
+fn number_literal(text: &str) -> Option<Literal> {
+    let text = text.replace('_', "");
+    let upper = text.to_ascii_uppercase();
+    let suffix = if upper.ends_with("ULL") {
+        Some(IntegerSuffix::Unsigned)
+    } else if upper.ends_with("LL") {
+        Some(IntegerSuffix::Signed)
+    } else {
+        None
+    };
+    if let Some(suffix) = suffix {
+        let digits = &text[..text.len() - suffix.as_str().len()];
+        let value = match radix_integer(digits) {
+            Some((value, _)) => value,
+            None if suffix == IntegerSuffix::Unsigned => digits.parse::<u64>().ok()? as i64,
+            None => digits.parse::<i64>().ok()?,
+        };
+        return Some(Literal::SuffixedInteger(value, suffix));
+    }
+
+    if let Some((value, float)) = radix_integer(&text) {
+        return Some(match float {
+            Some(float) => Literal::WrappedInteger(value, float),
+            None => Literal::Integer(value),
+        });
+    }
+    if text.bytes().all(|b| b.is_ascii_digit()) {
+        if let Ok(value) = text.parse::<i64>() {
+            return Some(Literal::Integer(value));
+        }
+    }
+    text.parse::<f64>().ok().map(Literal::Number)
+}
+
+/// A hexadecimal or binary integer, wrapped around to 64 bits as Lua 5.3+
+/// does, with its float value when it does not fit in an `i64`
+fn radix_integer(text: &str) -> Option<(i64, Option<f64>)> {
+    let radix = match text.get(..2) {
+        Some("0x" | "0X") => 16,
+        Some("0b" | "0B") => 2,
+        _ => return None,
+    };
+    let digits = &text[2..];
+    if digits.is_empty() {
+        return None;
+    }
+    let mut wrapped: u64 = 0;
+    let mut exact = Some(0u64);
+    let mut float = 0.0;
+    for c in digits.chars() {
+        let digit = c.to_digit(radix)?;
+        wrapped = wrapped.wrapping_mul(radix as u64).wrapping_add(digit as u64);
+        exact = exact
+            .and_then(|value| value.checked_mul(radix as u64))
+            .and_then(|value| value.checked_add(digit as u64));
+        float = float * radix as f64 + digit as f64;
+    }
+    let fits = matches!(exact, Some(value) if value <= i64::MAX as u64);
+    Some((wrapped as i64, (!fits).then_some(float)))
+}
+
 impl std::fmt::Display for ParserError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} at line {}", self.message, self.span.line)
@@ -31,35 +114,105 @@ impl std::fmt::Display for ParserError {
 
 impl std::error::Error for ParserError {}
 
+/// Parse source holding exactly one expression, for tools such as a REPL
+/// that have no surrounding file
+pub fn parse_expression(
+    source: &str,
+    diagnostic_handler: Arc<dyn DiagnosticHandler>,
+) -> Result<Expression, ParserError> {
+    parse_fragment(source, diagnostic_handler, "expression", |parser| {
+        parser.parse_expression()
+    })
+}
+
+/// Parse source holding exactly one type
+pub fn parse_type(
+    source: &str,
+    diagnostic_handler: Arc<dyn DiagnosticHandler>,
+) -> Result<Type, ParserError> {
+    parse_fragment(source, diagnostic_handler, "type", |parser| {
+        parser.parse_type()
+    })
+}
+
+/// Parse a list of statements. Like a whole file, statements with syntax
+/// errors are reported and kept as `Statement::Error`.
+pub fn parse_statements(
+    source: &str,
+    diagnostic_handler: Arc<dyn DiagnosticHandler>,
+) -> Result<Vec<Statement>, ParserError> {
+    parse_fragment(source, diagnostic_handler, "statements", |parser| {
+        Ok(parser.parse_statements(|_| false))
+    })
+}
+
+/// Lex `source` and parse all of it with `parse`. Lexical errors and
+/// leftover tokens are errors.
+fn parse_fragment<T>(
+    source: &str,
+    diagnostic_handler: Arc<dyn DiagnosticHandler>,
+    what: &str,
+    parse: impl FnOnce(&mut Parser) -> Result<T, ParserError>,
+) -> Result<T, ParserError> {
+    let tokens = Lexer::new(source, diagnostic_handler.clone())
+        .tokenize()
+        .map_err(|e| ParserError {
+            message: e.to_string(),
+            span: Span::dummy(),
+        })?;
+    let mut parser = Parser::new(tokens, diagnostic_handler);
+    let parsed = parse(&mut parser)?;
+    if !parser.is_at_end() {
+        return Err(ParserError {
+            message: format!("Unexpected '{}' after {}", parser.current().kind, what),
+            span: parser.current_span(),
+        });
+    }
+    Ok(parsed)
+}
+
 pub struct Parser {
     tokens: Vec<Token>,
     position: usize,
     diagnostic_handler: Arc<dyn DiagnosticHandler>,
+    macros: Macros,
+    macro_depth: usize,
+    expansions: usize,
+    nesting: usize,
+    /// Where the input ended while a block still needed its `end`
+    missing_end: Option<Span>,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>, diagnostic_handler: Arc<dyn DiagnosticHandler>) -> Self {
+    pub fn new(mut tokens: Vec<Token>, diagnostic_handler: Arc<dyn DiagnosticHandler>) -> Self {
+        // Token lists cut from a larger one, such as a template's
+        // interpolations, end without an Eof of their own
+        if !matches!(tokens.last(), Some(token) if token.kind == TokenKind::Eof) {
+            let eof = match tokens.last() {
+                Some(last) => {
+                    let span = last.span;
+                    let column = span.column + span.len();
+                    Token::new(TokenKind::Eof, Span::new(span.end, span.end, span.line, column))
+                }
+                None => Token::eof(0),
+            };
+            tokens.push(eof);
+        }
         Parser {
             tokens,
             position: 0,
             diagnostic_handler,
+            macros: Macros::new(),
+            macro_depth: 0,
+            expansions: 0,
+            nesting: 0,
+            missing_end: None,
         }
     }
 
     pub fn parse(&mut self) -> Result<Program, ParserError> {
         let start_span = self.current_span();
-        let mut statements = Vec::new();
-
-        while !self.is_at_end() {
-            match self.parse_statement() {
-                Ok(stmt) => statements.push(stmt),
-                Err(e) => {
-                    self.report_error(&e.message, e.span);
-                    // Error recovery: skip to next statement
-                    self.synchronize();
-                }
-            }
-        }
+        let statements = self.parse_statements(|_| false);
 
         let end_span = if !statements.is_empty() {
             statements.last().unwrap().span()
@@ -83,6 +236,14 @@ impl Parser {
         self.tokens.get(self.position + offset)
     }
 
+    /// Whether the current token is on a later line than the previous one
+    fn starts_new_line(&self) -> bool {
+        match self.position.checked_sub(1).and_then(|i| self.tokens.get(i)) {
+            Some(previous) => self.current().span.line > previous.span.line,
+            None => false,
+        }
+    }
+
     fn is_at_end(&self) -> bool {
         matches!(self.current().kind, TokenKind::Eof)
     }
@@ -91,7 +252,7 @@ impl Parser {
         if !self.is_at_end() {
             self.position += 1;
         }
-        &self.tokens[self.position - 1]
+        &self.tokens[self.position.saturating_sub(1)]
     }
 
     fn check(&self, kind: &TokenKind) -> bool {
@@ -116,6 +277,9 @@ impl Parser {
             return Ok(self.advance());
         }
 
+        if kind == TokenKind::End && self.is_at_end() {
+            self.missing_end = Some(self.current_span());
+        }
         Err(ParserError {
             message: message.to_string(),
             span: self.current_span(),
@@ -126,28 +290,77 @@ impl Parser {
         self.current().span
     }
 
-    // Error reporting
-    fn report_error(&self, message: &str, span: Span) {
-        self.diagnostic_handler.report(Diagnostic {
-            level: DiagnosticLevel::Error,
-            span,
-            message: message.to_string(),
-        });
+    /// Run a parser that may recurse, failing once nesting reaches
+    /// `MAX_NESTING`
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParserError>,
+    ) -> Result<T, ParserError> {
+        if self.nesting >= MAX_NESTING {
+            return Err(ParserError {
+                message: "Nesting is too deep".to_string(),
+                span: self.current_span(),
+            });
+        }
+        self.nesting += 1;
+        let result = parse(self);
+        self.nesting -= 1;
+        result
+    }
+
+    /// Parse statements up to the end of input or a token `at_end` accepts.
+    /// A statement with a syntax error is reported and kept as
+    /// `Statement::Error`, and parsing resumes at the next statement
+    /// boundary, so one file can report several errors.
+    fn parse_statements(&mut self, at_end: impl Fn(&TokenKind) -> bool) -> Vec<Statement> {
+        let mut statements = Vec::new();
+
+        while !self.is_at_end() && !at_end(&self.current().kind) {
+            if self.match_token(&[TokenKind::Semicolon]) {
+                continue;
+            }
+            let start = self.position;
+            let start_span = self.current_span();
+            let result = if self.check_macro() {
+                self.parse_macro_definition().map(|_| None)
+            } else {
+                self.parse_statement().map(Some)
+            };
+            match result {
+                Ok(statement) => statements.extend(statement),
+                Err(e) => {
+                    let mut error = Diagnostic::error(e.span, &e.message).with_code("TL1003");
+                    if self.missing_end.take() == Some(e.span) {
+                        error = error.with_fixes(vec![missing_end_fix(e.span)]);
+                    }
+                    self.diagnostic_handler.report(error);
+                    self.synchronize(start);
+                    let end_span = self.tokens[self.position.max(1) - 1].span;
+                    statements.push(Statement::Error(start_span.combine(&end_span)));
+                }
+            }
+        }
+        statements
     }
 
-    // Error recovery: skip to next statement boundary
-    fn synchronize(&mut self) {
-        self.advance();
+    // Error recovery: skip to the next statement boundary, having consumed
+    // at least one token since the failed statement started
+    fn synchronize(&mut self, start: usize) {
+        if self.position == start {
+            self.advance();
+        }
 
         while !self.is_at_end() {
-            // Check if we're at a statement boundary
             match &self.current().kind {
+                // Statement keywords start the next statement, and block
+                // terminators close the enclosing one
                 TokenKind::Function
                 | TokenKind::Local
                 | TokenKind::Const
                 | TokenKind::If
                 | TokenKind::While
                 | TokenKind::For
+                | TokenKind::Repeat
                 | TokenKind::Return
                 | TokenKind::Break
                 | TokenKind::Continue
@@ -156,7 +369,15 @@ impl Parser {
                 | TokenKind::Enum
                 | TokenKind::Class
                 | TokenKind::Import
-                | TokenKind::Export => return,
+                | TokenKind::Export
+                | TokenKind::End
+                | TokenKind::Else
+                | TokenKind::Elseif
+                | TokenKind::Until => return,
+                TokenKind::Semicolon => {
+                    self.advance();
+                    return;
+                }
                 _ => {}
             }
 
@@ -164,35 +385,3 @@ impl Parser {
         }
     }
 }
-
-// Helper trait to get span from any statement/expression
-trait Spannable {
-    fn span(&self) -> Span;
-}
-
-impl Spannable for crate::ast::statement::Statement {
-    fn span(&self) -> Span {
-        use crate::ast::statement::Statement::*;
-        match self {
-            Variable(v) => v.span,
-            Function(f) => f.span,
-            Class(c) => c.span,
-            Interface(i) => i.span,
-            TypeAlias(t) => t.span,
-            Enum(e) => e.span,
-            Import(i) => i.span,
-            Export(e) => e.span,
-            If(i) => i.span,
-            While(w) => w.span,
-            For(f) => match f {
-                crate::ast::statement::ForStatement::Numeric(n) => n.span,
-                crate::ast::statement::ForStatement::Generic(g) => g.span,
-            },
-            Repeat(r) => r.span,
-            Return(r) => r.span,
-            Break(s) | Continue(s) => *s,
-            Expression(e) => e.span,
-            Block(b) => b.span,
-        }
-    }
-}
@@ -1,4 +1,4 @@
-use super::{ExpressionParser, Parser, ParserError};
+use super::{number_literal, ExpressionParser, Parser, ParserError};
 use crate::ast::expression::Literal;
 use crate::ast::pattern::*;
 use crate::ast::Spanned;
@@ -10,6 +10,12 @@ pub trait PatternParser {
 
 impl PatternParser for Parser {
     fn parse_pattern(&mut self) -> Result<Pattern, ParserError> {
+        self.nested(|parser| parser.parse_pattern_unchecked())
+    }
+}
+
+impl Parser {
+    fn parse_pattern_unchecked(&mut self) -> Result<Pattern, ParserError> {
         let start_span = self.current_span();
 
         match &self.current().kind.clone() {
@@ -23,12 +29,12 @@ impl PatternParser for Parser {
                 Ok(Pattern::Identifier(Spanned::new(id, start_span)))
             }
             TokenKind::Number(s) => {
-                let num = s.parse::<f64>().map_err(|_| ParserError {
+                let literal = number_literal(s).ok_or_else(|| ParserError {
                     message: "Invalid number in pattern".to_string(),
                     span: start_span,
                 })?;
                 self.advance();
-                Ok(Pattern::Literal(Literal::Number(num), start_span))
+                Ok(Pattern::Literal(literal, start_span))
             }
             TokenKind::String(s) => {
                 let string = s.clone();
@@ -55,9 +61,7 @@ impl PatternParser for Parser {
             }),
         }
     }
-}
 
-impl Parser {
     fn parse_array_pattern(&mut self) -> Result<Pattern, ParserError> {
         let start_span = self.current_span();
         self.consume(TokenKind::LeftBracket, "Expected '['")?;
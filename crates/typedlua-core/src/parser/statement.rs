@@ -1,5 +1,8 @@
 use super::{ExpressionParser, Parser, ParserError, PatternParser, TypeParser};
+use crate::ast::expression::ExpressionKind;
+use crate::ast::pattern::{MultiplePattern, MultipleRest, Pattern};
 use crate::ast::statement::*;
+use crate::ast::types::{Type, TypeKind};
 use crate::ast::Ident;
 use crate::ast::Spanned;
 use crate::lexer::TokenKind;
@@ -12,12 +15,57 @@ pub trait StatementParser {
 
 impl StatementParser for Parser {
     fn parse_statement(&mut self) -> Result<Statement, ParserError> {
-        // Check for decorators first
+        // Check for decorators first; they lead a declaration, which keeps
+        // them as its attributes
         if self.check(&TokenKind::At) {
-            return self.parse_class_declaration();
+            let checkpoint = self.position;
+            let start_span = self.current_span();
+            let decorators = self.parse_decorators()?;
+            if self.check_declare() {
+                return self.parse_declare_declaration(decorators, start_span);
+            }
+            if matches!(self.current().kind, TokenKind::Class | TokenKind::Abstract) {
+                self.position = checkpoint;
+                return self.parse_class_declaration();
+            }
+            let mut statement = self.parse_statement()?;
+            match declaration_decorators(&mut statement) {
+                Some(target) => *target = decorators,
+                None => {
+                    return Err(ParserError {
+                        message: "Decorators must precede a declaration".to_string(),
+                        span: start_span,
+                    })
+                }
+            }
+            return Ok(statement);
+        }
+
+        if self.check_declare() {
+            let start_span = self.current_span();
+            return self.parse_declare_declaration(Vec::new(), start_span);
+        }
+
+        if self.check_namespace() {
+            return self.parse_namespace_declaration();
+        }
+
+        if self.check_untyped() {
+            return self.parse_untyped_block();
+        }
+
+        if self.check_using() {
+            return self.parse_using_declaration();
+        }
+
+        if self.check_macro_invocation() {
+            return self.parse_macro_invocation();
         }
 
         match &self.current().kind {
+            TokenKind::Const if matches!(self.peek(1).map(|t| &t.kind), Some(TokenKind::Enum)) => {
+                self.parse_enum_declaration()
+            }
             TokenKind::Const | TokenKind::Local => self.parse_variable_declaration(),
             TokenKind::Function => self.parse_function_declaration(),
             TokenKind::If => self.parse_if_statement(),
@@ -25,16 +73,17 @@ impl StatementParser for Parser {
             TokenKind::For => self.parse_for_statement(),
             TokenKind::Repeat => self.parse_repeat_statement(),
             TokenKind::Return => self.parse_return_statement(),
-            TokenKind::Break => {
-                let span = self.current_span();
-                self.advance();
-                Ok(Statement::Break(span))
-            }
-            TokenKind::Continue => {
-                let span = self.current_span();
-                self.advance();
-                Ok(Statement::Continue(span))
+            TokenKind::Identifier(_)
+                if matches!(self.peek(1).map(|t| &t.kind), Some(TokenKind::Colon))
+                    && matches!(
+                        self.peek(2).map(|t| &t.kind),
+                        Some(TokenKind::While | TokenKind::For | TokenKind::Repeat)
+                    ) =>
+            {
+                self.parse_labeled_loop()
             }
+            TokenKind::Break => Ok(Statement::Break(self.parse_loop_jump())),
+            TokenKind::Continue => Ok(Statement::Continue(self.parse_loop_jump())),
             TokenKind::Interface => self.parse_interface_declaration(),
             TokenKind::Type => self.parse_type_alias_declaration(),
             TokenKind::Enum => self.parse_enum_declaration(),
@@ -51,22 +100,21 @@ impl StatementParser for Parser {
 
     fn parse_block(&mut self) -> Result<Block, ParserError> {
         let start_span = self.current_span();
-        let mut statements = Vec::new();
 
         // Blocks in Lua don't require braces in many contexts
         // We'll parse until we hit an end marker
-        while !self.is_at_end()
-            && !matches!(
-                &self.current().kind,
-                TokenKind::End
-                    | TokenKind::Else
-                    | TokenKind::Elseif
-                    | TokenKind::Until
-                    | TokenKind::RightBrace
-            )
-        {
-            statements.push(self.parse_statement()?);
-        }
+        let statements = self.nested(|parser| {
+            Ok(parser.parse_statements(|kind| {
+                matches!(
+                    kind,
+                    TokenKind::End
+                        | TokenKind::Else
+                        | TokenKind::Elseif
+                        | TokenKind::Until
+                        | TokenKind::RightBrace
+                )
+            }))
+        })?;
 
         let end_span = if !statements.is_empty() {
             statements.last().unwrap().span()
@@ -92,7 +140,16 @@ impl Parser {
         };
         self.advance();
 
-        let pattern = self.parse_pattern()?;
+        let mut pattern = if self.check(&TokenKind::DotDotDot) {
+            self.parse_multiple_pattern(Vec::new())?
+        } else {
+            self.parse_pattern()?
+        };
+        if let Pattern::Identifier(first) = &pattern {
+            if self.check(&TokenKind::Comma) {
+                pattern = self.parse_multiple_pattern(vec![first.clone()])?;
+            }
+        }
 
         let type_annotation = if self.match_token(&[TokenKind::Colon]) {
             Some(self.parse_type()?)
@@ -109,6 +166,7 @@ impl Parser {
         let end_span = initializer.span;
 
         Ok(Statement::Variable(VariableDeclaration {
+            decorators: Vec::new(),
             kind,
             pattern,
             type_annotation,
@@ -117,6 +175,41 @@ impl Parser {
         }))
     }
 
+    /// The rest of `local a, b, ...rest = f()` after the names already read
+    fn parse_multiple_pattern(&mut self, mut names: Vec<Ident>) -> Result<Pattern, ParserError> {
+        let start_span = names.first().map_or(self.current_span(), |name| name.span);
+        let mut rest = None;
+        let mut end_span = start_span;
+        while names.is_empty() || self.match_token(&[TokenKind::Comma]) {
+            end_span = self.current_span();
+            if self.match_token(&[TokenKind::DotDotDot]) {
+                rest = Some(match &self.current().kind {
+                    TokenKind::Identifier(_) => {
+                        let name = self.parse_identifier()?;
+                        end_span = name.span;
+                        MultipleRest::Capture(name)
+                    }
+                    _ if names.is_empty() => {
+                        return Err(ParserError {
+                            message: "Expected a name after '...'".to_string(),
+                            span: self.current_span(),
+                        })
+                    }
+                    _ => MultipleRest::Discard(end_span),
+                });
+                break;
+            }
+            let name = self.parse_identifier()?;
+            end_span = name.span;
+            names.push(name);
+        }
+        Ok(Pattern::Multiple(MultiplePattern {
+            names,
+            rest,
+            span: start_span.combine(&end_span),
+        }))
+    }
+
     fn parse_function_declaration(&mut self) -> Result<Statement, ParserError> {
         let start_span = self.current_span();
         self.consume(TokenKind::Function, "Expected 'function'")?;
@@ -140,10 +233,10 @@ impl Parser {
         };
 
         let body = self.parse_block()?;
-        self.consume(TokenKind::End, "Expected 'end' after function body")?;
-        let end_span = self.current_span();
+        let end_span = self.consume(TokenKind::End, "Expected 'end' after function body")?.span;
 
         Ok(Statement::Function(FunctionDeclaration {
+            decorators: Vec::new(),
             name,
             type_parameters,
             parameters,
@@ -183,8 +276,7 @@ impl Parser {
             None
         };
 
-        self.consume(TokenKind::End, "Expected 'end' after if statement")?;
-        let end_span = self.current_span();
+        let end_span = self.consume(TokenKind::End, "Expected 'end' after if statement")?.span;
 
         Ok(Statement::If(IfStatement {
             condition,
@@ -203,8 +295,7 @@ impl Parser {
         self.consume(TokenKind::Do, "Expected 'do' after while condition")?;
 
         let body = self.parse_block()?;
-        self.consume(TokenKind::End, "Expected 'end' after while body")?;
-        let end_span = self.current_span();
+        let end_span = self.consume(TokenKind::End, "Expected 'end' after while body")?.span;
 
         Ok(Statement::While(WhileStatement {
             condition,
@@ -230,6 +321,40 @@ impl Parser {
         }))
     }
 
+    fn parse_labeled_loop(&mut self) -> Result<Statement, ParserError> {
+        let label = self.parse_identifier()?;
+        self.consume(TokenKind::Colon, "Expected ':' after loop label")?;
+
+        let body = self.parse_statement()?;
+        let span = label.span.combine(&body.span());
+        Ok(Statement::Labeled(LabeledLoop {
+            label,
+            body: Box::new(body),
+            span,
+        }))
+    }
+
+    /// `break` or `continue`, with the label of the loop it targets when an
+    /// identifier follows on the same line
+    fn parse_loop_jump(&mut self) -> LoopJump {
+        let keyword = self.current_span();
+        self.advance();
+
+        let label = match &self.current().kind {
+            TokenKind::Identifier(name) if self.current_span().line == keyword.line => {
+                let label = Spanned::new(name.clone(), self.current_span());
+                self.advance();
+                Some(label)
+            }
+            _ => None,
+        };
+        let span = match &label {
+            Some(label) => keyword.combine(&label.span),
+            None => keyword,
+        };
+        LoopJump { label, span }
+    }
+
     fn parse_for_statement(&mut self) -> Result<Statement, ParserError> {
         let start_span = self.current_span();
         self.consume(TokenKind::For, "Expected 'for'")?;
@@ -253,8 +378,7 @@ impl Parser {
 
             self.consume(TokenKind::Do, "Expected 'do' after for range")?;
             let body = self.parse_block()?;
-            self.consume(TokenKind::End, "Expected 'end' after for body")?;
-            let end_span = self.current_span();
+            let end_span = self.consume(TokenKind::End, "Expected 'end' after for body")?.span;
 
             Ok(Statement::For(ForStatement::Numeric(ForNumeric {
                 variable: first_var,
@@ -281,8 +405,7 @@ impl Parser {
 
             self.consume(TokenKind::Do, "Expected 'do' after for iterators")?;
             let body = self.parse_block()?;
-            self.consume(TokenKind::End, "Expected 'end' after for body")?;
-            let end_span = self.current_span();
+            let end_span = self.consume(TokenKind::End, "Expected 'end' after for body")?.span;
 
             Ok(Statement::For(ForStatement::Generic(ForGeneric {
                 variables,
@@ -302,7 +425,12 @@ impl Parser {
         // Return can have no values
         if !matches!(
             &self.current().kind,
-            TokenKind::End | TokenKind::Else | TokenKind::Elseif | TokenKind::Until | TokenKind::Eof
+            TokenKind::End
+                | TokenKind::Else
+                | TokenKind::Elseif
+                | TokenKind::Until
+                | TokenKind::RightBrace
+                | TokenKind::Eof
         ) {
             values.push(self.parse_expression()?);
 
@@ -347,10 +475,12 @@ impl Parser {
 
         let members = self.parse_interface_members()?;
 
-        self.consume(TokenKind::RightBrace, "Expected '}' after interface body")?;
-        let end_span = self.current_span();
+        let end_span = self
+            .consume(TokenKind::RightBrace, "Expected '}' after interface body")?
+            .span;
 
         Ok(Statement::Interface(InterfaceDeclaration {
+            decorators: Vec::new(),
             name,
             type_parameters,
             extends,
@@ -367,6 +497,7 @@ impl Parser {
             if self.check(&TokenKind::LeftBracket) {
                 members.push(InterfaceMember::Index(self.parse_index_signature()?));
             } else {
+                let decorators = self.parse_decorators()?;
                 let is_readonly = self.match_token(&[TokenKind::Readonly]);
                 let name = self.parse_identifier()?;
 
@@ -388,6 +519,7 @@ impl Parser {
                     let span = name.span.combine(&return_type.span);
 
                     members.push(InterfaceMember::Method(MethodSignature {
+                        decorators,
                         name,
                         type_parameters,
                         parameters,
@@ -402,6 +534,7 @@ impl Parser {
                     let span = name.span.combine(&type_annotation.span);
 
                     members.push(InterfaceMember::Property(PropertySignature {
+                        decorators,
                         is_readonly,
                         name,
                         is_optional,
@@ -477,6 +610,7 @@ impl Parser {
         let end_span = type_annotation.span;
 
         Ok(Statement::TypeAlias(TypeAliasDeclaration {
+            decorators: Vec::new(),
             name,
             type_parameters,
             type_annotation,
@@ -486,6 +620,7 @@ impl Parser {
 
     fn parse_enum_declaration(&mut self) -> Result<Statement, ParserError> {
         let start_span = self.current_span();
+        let is_const = self.match_token(&[TokenKind::Const]);
         self.consume(TokenKind::Enum, "Expected 'enum'")?;
 
         let name = self.parse_identifier()?;
@@ -493,6 +628,8 @@ impl Parser {
         self.consume(TokenKind::LeftBrace, "Expected '{' after enum name")?;
 
         let mut members = Vec::new();
+        // Members after a string value have nothing to auto-increment from
+        let mut after_string = false;
         while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
             let member_start = self.current_span();
             let member_name = self.parse_identifier()?;
@@ -505,11 +642,13 @@ impl Parser {
                             span: self.current_span(),
                         })?;
                         self.advance();
+                        after_string = false;
                         Some(EnumValue::Number(val))
                     }
                     TokenKind::String(s) => {
                         let val = s.clone();
                         self.advance();
+                        after_string = true;
                         Some(EnumValue::String(val))
                     }
                     _ => {
@@ -519,6 +658,11 @@ impl Parser {
                         })
                     }
                 }
+            } else if after_string {
+                return Err(ParserError {
+                    message: "Enum member must have a value after a string member".to_string(),
+                    span: member_name.span,
+                });
             } else {
                 None
             };
@@ -536,12 +680,13 @@ impl Parser {
             }
         }
 
-        self.consume(TokenKind::RightBrace, "Expected '}' after enum body")?;
-        let end_span = self.current_span();
+        let end_span = self.consume(TokenKind::RightBrace, "Expected '}' after enum body")?.span;
 
         Ok(Statement::Enum(EnumDeclaration {
+            decorators: Vec::new(),
             name,
             members,
+            is_const,
             span: start_span.combine(&end_span),
         }))
     }
@@ -550,8 +695,24 @@ impl Parser {
         let start_span = self.current_span();
         self.consume(TokenKind::Import, "Expected 'import'")?;
 
+        // `lazy` is contextual: `import lazy from "..."` imports a default named lazy
+        let is_lazy = matches!(&self.current().kind, TokenKind::Identifier(s) if s == "lazy")
+            && matches!(
+                self.peek(1).map(|t| &t.kind),
+                Some(TokenKind::LeftBrace | TokenKind::Star | TokenKind::Identifier(_))
+            );
+        if is_lazy {
+            self.advance();
+        }
+
         // Parse import clause
-        let clause = if self.match_token(&[TokenKind::Star]) {
+        let clause = if self.match_token(&[TokenKind::Type]) {
+            // import type { A, B as C } from "source": erased at emit
+            self.consume(TokenKind::LeftBrace, "Expected '{' after 'import type'")?;
+            let specifiers = self.parse_import_specifiers()?;
+            self.consume(TokenKind::RightBrace, "Expected '}'")?;
+            ImportClause::TypeOnly(specifiers)
+        } else if self.match_token(&[TokenKind::Star]) {
             // import * as name from "source"
             self.consume(TokenKind::As, "Expected 'as' after '*'")?;
             let name = self.parse_identifier()?;
@@ -589,6 +750,7 @@ impl Parser {
         Ok(Statement::Import(ImportDeclaration {
             clause,
             source,
+            is_lazy,
             span: start_span.combine(&end_span),
         }))
     }
@@ -640,6 +802,10 @@ impl Parser {
             // export default expression
             let expr = self.parse_expression()?;
             ExportKind::Default(expr)
+        } else if self.match_token(&[TokenKind::Equal]) {
+            // export = expression
+            let expr = self.parse_expression()?;
+            ExportKind::Equals(expr)
         } else if self.check(&TokenKind::LeftBrace) {
             // export { a, b as c }
             self.consume(TokenKind::LeftBrace, "Expected '{'")?;
@@ -691,6 +857,167 @@ impl Parser {
         Ok(specifiers)
     }
 
+    /// `declare` is contextual: only a keyword when it leads a declaration
+    fn check_declare(&self) -> bool {
+        matches!(&self.current().kind, TokenKind::Identifier(s) if s == "declare")
+            && matches!(
+                self.peek(1).map(|t| &t.kind),
+                Some(TokenKind::Function | TokenKind::Const | TokenKind::Local)
+            )
+    }
+
+    /// `mixin` is contextual: only a keyword when `(` follows it in a class
+    /// heritage clause
+    fn check_mixin(&self) -> bool {
+        matches!(&self.current().kind, TokenKind::Identifier(s) if s == "mixin")
+            && matches!(self.peek(1).map(|t| &t.kind), Some(TokenKind::LeftParen))
+    }
+
+    /// `using` is contextual: only a keyword when a name and `=` or `:`
+    /// follow
+    fn check_using(&self) -> bool {
+        matches!(&self.current().kind, TokenKind::Identifier(s) if s == "using")
+            && matches!(self.peek(1).map(|t| &t.kind), Some(TokenKind::Identifier(_)))
+            && matches!(
+                self.peek(2).map(|t| &t.kind),
+                Some(TokenKind::Equal | TokenKind::Colon)
+            )
+    }
+
+    fn parse_using_declaration(&mut self) -> Result<Statement, ParserError> {
+        let start_span = self.current_span();
+        self.advance(); // using
+
+        let name = self.parse_identifier()?;
+        let type_annotation = if self.match_token(&[TokenKind::Colon]) {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        self.consume(TokenKind::Equal, "Expected '=' in using declaration")?;
+        let initializer = self.parse_expression()?;
+        let end_span = initializer.span;
+
+        Ok(Statement::Variable(VariableDeclaration {
+            decorators: Vec::new(),
+            kind: VariableKind::Using,
+            pattern: Pattern::Identifier(name),
+            type_annotation,
+            initializer,
+            span: start_span.combine(&end_span),
+        }))
+    }
+
+    /// `namespace` is contextual: only a keyword when a name and `{` follow
+    /// `untyped` and `trust` are contextual: only keywords when `do` follows
+    fn check_untyped(&self) -> bool {
+        matches!(&self.current().kind, TokenKind::Identifier(s) if s == "untyped" || s == "trust")
+            && matches!(self.peek(1).map(|t| &t.kind), Some(TokenKind::Do))
+    }
+
+    fn parse_untyped_block(&mut self) -> Result<Statement, ParserError> {
+        let start_span = self.current_span();
+        self.advance(); // untyped
+        self.advance(); // do
+
+        let body = self.parse_block()?;
+        let end_span = self.current_span();
+        self.consume(TokenKind::End, "Expected 'end' after untyped block")?;
+
+        Ok(Statement::Untyped(Block {
+            statements: body.statements,
+            span: start_span.combine(&end_span),
+        }))
+    }
+
+    fn check_namespace(&self) -> bool {
+        matches!(&self.current().kind, TokenKind::Identifier(s) if s == "namespace")
+            && matches!(self.peek(1).map(|t| &t.kind), Some(TokenKind::Identifier(_)))
+            && matches!(self.peek(2).map(|t| &t.kind), Some(TokenKind::LeftBrace))
+    }
+
+    fn parse_namespace_declaration(&mut self) -> Result<Statement, ParserError> {
+        let start_span = self.current_span();
+        self.advance(); // namespace
+
+        let name = self.parse_identifier()?;
+        self.consume(TokenKind::LeftBrace, "Expected '{' after namespace name")?;
+        let body = self.parse_block()?;
+        let end_span = self.current_span();
+        self.consume(TokenKind::RightBrace, "Expected '}' after namespace body")?;
+
+        Ok(Statement::Namespace(NamespaceDeclaration {
+            name,
+            body,
+            span: start_span.combine(&end_span),
+        }))
+    }
+
+    fn parse_declare_declaration(
+        &mut self,
+        decorators: Vec<Decorator>,
+        start_span: Span,
+    ) -> Result<Statement, ParserError> {
+        self.advance(); // 'declare'
+
+        let kind = if self.match_token(&[TokenKind::Function]) {
+            let name = self.parse_identifier()?;
+
+            let type_parameters = if self.match_token(&[TokenKind::LessThan]) {
+                Some(self.parse_type_parameters()?)
+            } else {
+                None
+            };
+
+            self.consume(TokenKind::LeftParen, "Expected '(' after function name")?;
+            let parameters = self.parse_parameter_list()?;
+            self.consume(TokenKind::RightParen, "Expected ')' after parameters")?;
+            self.consume(
+                TokenKind::Colon,
+                "Expected ':' and return type in declared function",
+            )?;
+            let return_type = self.parse_type()?;
+            let span = name.span.combine(&return_type.span);
+
+            DeclareKind::Function(DeclareFunction {
+                name,
+                type_parameters,
+                parameters,
+                return_type,
+                span,
+            })
+        } else {
+            let kind = if self.match_token(&[TokenKind::Const]) {
+                VariableKind::Const
+            } else {
+                self.consume(TokenKind::Local, "Expected 'const' or 'local'")?;
+                VariableKind::Local
+            };
+            let name = self.parse_identifier()?;
+            self.consume(TokenKind::Colon, "Expected ':' and type in declared variable")?;
+            let type_annotation = self.parse_type()?;
+            let span = name.span.combine(&type_annotation.span);
+
+            DeclareKind::Variable(DeclareVariable {
+                kind,
+                name,
+                type_annotation,
+                span,
+            })
+        };
+
+        let end_span = match &kind {
+            DeclareKind::Function(func) => func.span,
+            DeclareKind::Variable(var) => var.span,
+        };
+
+        Ok(Statement::Declare(DeclareDeclaration {
+            decorators,
+            kind,
+            span: start_span.combine(&end_span),
+        }))
+    }
+
     fn parse_class_declaration(&mut self) -> Result<Statement, ParserError> {
         let start_span = self.current_span();
 
@@ -709,11 +1036,21 @@ impl Parser {
             None
         };
 
-        let extends = if self.match_token(&[TokenKind::Extends]) {
-            Some(self.parse_type()?)
-        } else {
-            None
-        };
+        let mut extends = None;
+        let mut mixins = Vec::new();
+        if self.match_token(&[TokenKind::Extends]) {
+            if self.check_mixin() {
+                self.advance(); // mixin
+                self.consume(TokenKind::LeftParen, "Expected '(' after 'mixin'")?;
+                mixins.push(self.parse_type()?);
+                while self.match_token(&[TokenKind::Comma]) {
+                    mixins.push(self.parse_type()?);
+                }
+                self.consume(TokenKind::RightParen, "Expected ')' after mixins")?;
+            } else {
+                extends = Some(self.parse_type()?);
+            }
+        }
 
         let mut implements = Vec::new();
         if self.match_token(&[TokenKind::Implements]) {
@@ -725,10 +1062,9 @@ impl Parser {
 
         self.consume(TokenKind::LeftBrace, "Expected '{' after class header")?;
 
-        let members = Vec::new(); // TODO: Implement class member parsing
+        let members = self.parse_class_members()?;
 
-        self.consume(TokenKind::RightBrace, "Expected '}' after class body")?;
-        let end_span = self.current_span();
+        let end_span = self.consume(TokenKind::RightBrace, "Expected '}' after class body")?.span;
 
         Ok(Statement::Class(ClassDeclaration {
             decorators,
@@ -736,12 +1072,266 @@ impl Parser {
             name,
             type_parameters,
             extends,
+            mixins,
             implements,
             members,
             span: start_span.combine(&end_span),
         }))
     }
 
+    fn parse_class_members(&mut self) -> Result<Vec<ClassMember>, ParserError> {
+        let mut members = Vec::new();
+
+        while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
+            members.push(self.parse_class_member()?);
+
+            // Optional comma or semicolon
+            self.match_token(&[TokenKind::Comma, TokenKind::Semicolon]);
+        }
+
+        Ok(members)
+    }
+
+    fn parse_class_member(&mut self) -> Result<ClassMember, ParserError> {
+        let start_span = self.current_span();
+        let decorators = self.parse_decorators()?;
+
+        // Modifiers may appear in any order
+        let mut access = None;
+        let mut is_static = false;
+        let mut is_abstract = false;
+        let mut is_readonly = false;
+        let mut is_override = false;
+        let mut is_const = false;
+
+        loop {
+            match &self.current().kind {
+                TokenKind::Public => access = Some(AccessModifier::Public),
+                TokenKind::Private => access = Some(AccessModifier::Private),
+                TokenKind::Protected => access = Some(AccessModifier::Protected),
+                TokenKind::Static => is_static = true,
+                TokenKind::Abstract => is_abstract = true,
+                TokenKind::Readonly => is_readonly = true,
+                TokenKind::Override => is_override = true,
+                TokenKind::Const => is_const = true,
+                _ => break,
+            }
+            self.advance();
+        }
+
+        // static do ... end
+        if is_static && self.check(&TokenKind::Do) {
+            self.advance();
+            let body = self.parse_block()?;
+            let end_span = self.current_span();
+            self.consume(TokenKind::End, "Expected 'end' after static block")?;
+
+            return Ok(ClassMember::StaticBlock(StaticBlock {
+                body,
+                span: start_span.combine(&end_span),
+            }));
+        }
+
+        // constructor(params) { ... }
+        if matches!(&self.current().kind, TokenKind::Identifier(s) if s == "constructor") {
+            self.advance();
+            self.consume(TokenKind::LeftParen, "Expected '(' after 'constructor'")?;
+            let parameters = self.parse_parameter_list()?;
+            let mut end_span = self.current_span();
+            self.consume(TokenKind::RightParen, "Expected ')' after parameters")?;
+            // Declaration files leave the body out; the type checker
+            // reports it anywhere else
+            let body = if self.check(&TokenKind::LeftBrace) {
+                let block = self.parse_braced_block("constructor")?;
+                end_span = block.span;
+                Some(block)
+            } else {
+                None
+            };
+
+            return Ok(ClassMember::Constructor(ConstructorDeclaration {
+                decorators,
+                parameters,
+                body,
+                span: start_span.combine(&end_span),
+            }));
+        }
+
+        // get name(): T { ... } / set name(value: T) { ... }
+        let accessor = match (&self.current().kind, self.peek(1).map(|t| &t.kind)) {
+            (TokenKind::Identifier(s), Some(TokenKind::Identifier(_)))
+                if s == "get" || s == "set" =>
+            {
+                Some(s == "get")
+            }
+            _ => None,
+        };
+
+        if let Some(is_getter) = accessor {
+            self.advance();
+            let name = self.parse_identifier()?;
+            self.consume(TokenKind::LeftParen, "Expected '(' after accessor name")?;
+
+            if is_getter {
+                self.consume(TokenKind::RightParen, "Getter cannot have parameters")?;
+                self.consume(TokenKind::Colon, "Expected ':' after getter parameters")?;
+                let return_type = self.parse_type()?;
+                let body = self.parse_braced_block("getter")?;
+                let end_span = body.span;
+
+                return Ok(ClassMember::Getter(GetterDeclaration {
+                    decorators,
+                    access,
+                    is_static,
+                    name,
+                    return_type,
+                    body,
+                    span: start_span.combine(&end_span),
+                }));
+            }
+
+            let mut parameters = self.parse_parameter_list()?;
+            self.consume(TokenKind::RightParen, "Expected ')' after setter parameter")?;
+            if parameters.len() != 1 {
+                return Err(ParserError {
+                    message: "Setter must have exactly one parameter".to_string(),
+                    span: name.span,
+                });
+            }
+            let parameter = parameters.remove(0);
+            let body = self.parse_braced_block("setter")?;
+            let end_span = body.span;
+
+            return Ok(ClassMember::Setter(SetterDeclaration {
+                decorators,
+                access,
+                is_static,
+                name,
+                parameter,
+                body,
+                span: start_span.combine(&end_span),
+            }));
+        }
+
+        let name = self.parse_identifier()?;
+
+        // Method: name<T>(params): R { ... }
+        if self.check(&TokenKind::LeftParen) || self.check(&TokenKind::LessThan) {
+            let type_parameters = if self.match_token(&[TokenKind::LessThan]) {
+                Some(self.parse_type_parameters()?)
+            } else {
+                None
+            };
+
+            self.consume(TokenKind::LeftParen, "Expected '(' after method name")?;
+            let parameters = self.parse_parameter_list()?;
+            let mut end_span = self.current_span();
+            self.consume(TokenKind::RightParen, "Expected ')' after parameters")?;
+
+            let return_type = if self.match_token(&[TokenKind::Colon]) {
+                let ty = self.parse_type()?;
+                end_span = ty.span;
+                Some(ty)
+            } else {
+                None
+            };
+
+            // Abstract methods have no body; the type checker reports misuse
+            let body = if self.check(&TokenKind::LeftBrace) {
+                let block = self.parse_braced_block("method")?;
+                end_span = block.span;
+                Some(block)
+            } else {
+                None
+            };
+
+            return Ok(ClassMember::Method(MethodDeclaration {
+                decorators,
+                access,
+                is_static,
+                is_abstract,
+                is_override,
+                name,
+                type_parameters,
+                parameters,
+                return_type,
+                body,
+                span: start_span.combine(&end_span),
+            }));
+        }
+
+        // Constant: const NAME = literal, typed by its value
+        if is_const && self.check(&TokenKind::Equal) {
+            self.advance();
+            let initializer = self.parse_expression()?;
+            let type_annotation = match &initializer.kind {
+                ExpressionKind::Literal(literal) => {
+                    Type::new(TypeKind::Literal(literal.clone()), initializer.span)
+                }
+                _ => {
+                    return Err(ParserError {
+                        message: "Class constant needs a type annotation unless its value is a literal"
+                            .to_string(),
+                        span: initializer.span,
+                    })
+                }
+            };
+
+            return Ok(ClassMember::Property(PropertyDeclaration {
+                decorators,
+                access,
+                is_static: true,
+                is_readonly: true,
+                is_const,
+                name,
+                span: start_span.combine(&initializer.span),
+                type_annotation,
+                initializer: Some(initializer),
+            }));
+        }
+
+        // Property: name: T = initializer
+        self.consume(TokenKind::Colon, "Expected ':' after property name")?;
+        let type_annotation = self.parse_type()?;
+        let mut end_span = type_annotation.span;
+
+        let initializer = if self.match_token(&[TokenKind::Equal]) {
+            let expr = self.parse_expression()?;
+            end_span = expr.span;
+            Some(expr)
+        } else {
+            None
+        };
+
+        Ok(ClassMember::Property(PropertyDeclaration {
+            decorators,
+            access,
+            is_static: is_static || is_const,
+            is_readonly: is_readonly || is_const,
+            is_const,
+            name,
+            type_annotation,
+            initializer,
+            span: start_span.combine(&end_span),
+        }))
+    }
+
+    fn parse_braced_block(&mut self, context: &str) -> Result<Block, ParserError> {
+        let start_span = self.current_span();
+        self.consume(
+            TokenKind::LeftBrace,
+            &format!("Expected '{{' before {} body", context),
+        )?;
+        let mut block = self.parse_block()?;
+        let end_span = self.current_span();
+        self.consume(
+            TokenKind::RightBrace,
+            &format!("Expected '}}' after {} body", context),
+        )?;
+        block.span = start_span.combine(&end_span);
+        Ok(block)
+    }
+
     // Helper methods (pub so other parser modules can use them)
 
     fn parse_decorators(&mut self) -> Result<Vec<Decorator>, ParserError> {
@@ -880,9 +1470,17 @@ impl Parser {
             let is_rest = self.match_token(&[TokenKind::DotDotDot]);
 
             let pattern = self.parse_pattern()?;
+            // `name?: T` may be left out, so the parameter also admits nil
+            let is_optional = self.match_token(&[TokenKind::Question]);
 
             let type_annotation = if self.match_token(&[TokenKind::Colon]) {
-                Some(self.parse_type()?)
+                let typ = self.parse_type()?;
+                Some(if is_optional && !matches!(typ.kind, TypeKind::Nullable(_)) {
+                    let span = typ.span;
+                    Type::new(TypeKind::Nullable(Box::new(typ)), span)
+                } else {
+                    typ
+                })
             } else {
                 None
             };
@@ -912,33 +1510,18 @@ impl Parser {
     }
 }
 
-// Trait for getting span from Statement
-trait Spannable {
-    fn span(&self) -> Span;
-}
-
-impl Spannable for Statement {
-    fn span(&self) -> Span {
-        match self {
-            Statement::Variable(v) => v.span,
-            Statement::Function(f) => f.span,
-            Statement::Class(c) => c.span,
-            Statement::Interface(i) => i.span,
-            Statement::TypeAlias(t) => t.span,
-            Statement::Enum(e) => e.span,
-            Statement::Import(i) => i.span,
-            Statement::Export(e) => e.span,
-            Statement::If(i) => i.span,
-            Statement::While(w) => w.span,
-            Statement::For(f) => match f {
-                ForStatement::Numeric(n) => n.span,
-                ForStatement::Generic(g) => g.span,
-            },
-            Statement::Repeat(r) => r.span,
-            Statement::Return(r) => r.span,
-            Statement::Break(s) | Statement::Continue(s) => *s,
-            Statement::Expression(e) => e.span,
-            Statement::Block(b) => b.span,
-        }
+/// Where a declaration keeps its decorators, looking through `export`
+fn declaration_decorators(statement: &mut Statement) -> Option<&mut Vec<Decorator>> {
+    match statement {
+        Statement::Variable(decl) => Some(&mut decl.decorators),
+        Statement::Function(func) => Some(&mut func.decorators),
+        Statement::Interface(interface) => Some(&mut interface.decorators),
+        Statement::TypeAlias(alias) => Some(&mut alias.decorators),
+        Statement::Enum(decl) => Some(&mut decl.decorators),
+        Statement::Export(ExportDeclaration {
+            kind: ExportKind::Declaration(decl),
+            ..
+        }) => declaration_decorators(decl),
+        _ => None,
     }
 }
@@ -531,3 +531,756 @@ fn test_parse_template_literal_complex() {
         _ => panic!("Expected variable declaration"),
     }
 }
+
+#[test]
+fn test_parse_class_members() {
+    let source = r#"
+        abstract class Shape {
+            private static count: number = 0
+            readonly color: string
+
+            constructor(color: string) {
+                self.color = color
+            }
+
+            abstract area(): number
+
+            get label(): string {
+                return self.color
+            }
+
+            set label(value: string) {
+                self.color = value
+            }
+
+            describe(): void {
+                print(self.color)
+            }
+        }
+    "#;
+    let program = parse_source(source).expect("Parse failed");
+    assert_eq!(program.statements.len(), 1);
+
+    use crate::ast::statement::{AccessModifier, ClassMember, Statement};
+    match &program.statements[0] {
+        Statement::Class(class_decl) => {
+            assert!(class_decl.is_abstract);
+            assert_eq!(class_decl.members.len(), 7);
+
+            match &class_decl.members[0] {
+                ClassMember::Property(prop) => {
+                    assert_eq!(prop.access, Some(AccessModifier::Private));
+                    assert!(prop.is_static);
+                    assert!(prop.initializer.is_some());
+                }
+                _ => panic!("Expected property"),
+            }
+            match &class_decl.members[1] {
+                ClassMember::Property(prop) => assert!(prop.is_readonly),
+                _ => panic!("Expected property"),
+            }
+            assert!(matches!(&class_decl.members[2], ClassMember::Constructor(_)));
+            match &class_decl.members[3] {
+                ClassMember::Method(method) => {
+                    assert!(method.is_abstract);
+                    assert!(method.body.is_none());
+                }
+                _ => panic!("Expected abstract method"),
+            }
+            assert!(matches!(&class_decl.members[4], ClassMember::Getter(_)));
+            assert!(matches!(&class_decl.members[5], ClassMember::Setter(_)));
+            assert!(matches!(&class_decl.members[6], ClassMember::Method(_)));
+        }
+        _ => panic!("Expected class declaration"),
+    }
+}
+
+#[test]
+fn test_parse_class_constants_and_static_blocks() {
+    let source = r#"
+        class Grid {
+            const SIZE = 8
+            private const LABEL: string = "grid"
+            static do
+                print(Grid.SIZE)
+            end
+        }
+    "#;
+    let program = parse_source(source).expect("Parse failed");
+
+    use crate::ast::statement::{ClassMember, Statement};
+    use crate::ast::types::TypeKind;
+    let Statement::Class(class_decl) = &program.statements[0] else {
+        panic!("Expected class declaration");
+    };
+    match &class_decl.members[0] {
+        ClassMember::Property(prop) => {
+            assert!(prop.is_const && prop.is_static && prop.is_readonly);
+            assert!(matches!(prop.type_annotation.kind, TypeKind::Literal(_)));
+        }
+        _ => panic!("Expected constant"),
+    }
+    match &class_decl.members[1] {
+        ClassMember::Property(prop) => assert_eq!(prop.type_annotation.to_string(), "string"),
+        _ => panic!("Expected constant"),
+    }
+    match &class_decl.members[2] {
+        ClassMember::StaticBlock(block) => assert_eq!(block.body.statements.len(), 1),
+        _ => panic!("Expected static block"),
+    }
+
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let mut lexer = Lexer::new("class Grid {\n    const SIZE = 4 * 2\n}", handler.clone());
+    let tokens = lexer.tokenize().expect("Lexing failed");
+    let mut parser = Parser::new(tokens, handler.clone());
+    let _ = parser.parse();
+    assert!(handler.has_errors());
+}
+
+#[test]
+fn test_parse_method_named_get() {
+    let source = r#"
+        class Box {
+            get(): number {
+                return 1
+            }
+        }
+    "#;
+    let program = parse_source(source).expect("Parse failed");
+
+    match &program.statements[0] {
+        crate::ast::statement::Statement::Class(class_decl) => match &class_decl.members[0] {
+            crate::ast::statement::ClassMember::Method(method) => {
+                assert_eq!(method.name.node, "get");
+                assert!(method.body.is_some());
+            }
+            _ => panic!("Expected method"),
+        },
+        _ => panic!("Expected class declaration"),
+    }
+}
+
+#[test]
+fn test_parse_declare_with_target_guards() {
+    use crate::ast::statement::{DeclareKind, InterfaceMember, Statement};
+
+    let source = r#"
+        interface Utf8Lib {
+            @target(lua53, lua54)
+            char(...codes: number[]): string
+        }
+
+        @target(luau)
+        declare function typeof(value: unknown): string
+        declare const utf8: Utf8Lib
+        local declare = 1
+    "#;
+    let program = parse_source(source).expect("Parse failed");
+    assert_eq!(program.statements.len(), 4);
+
+    match &program.statements[0] {
+        Statement::Interface(interface) => match &interface.members[0] {
+            InterfaceMember::Method(method) => assert_eq!(method.decorators.len(), 1),
+            _ => panic!("Expected method signature"),
+        },
+        _ => panic!("Expected interface declaration"),
+    }
+
+    match &program.statements[1] {
+        Statement::Declare(decl) => {
+            assert_eq!(decl.decorators.len(), 1);
+            assert!(matches!(&decl.kind, DeclareKind::Function(f) if f.name.node == "typeof"));
+        }
+        _ => panic!("Expected declare statement"),
+    }
+
+    match &program.statements[2] {
+        Statement::Declare(decl) => {
+            assert!(decl.decorators.is_empty());
+            assert!(matches!(&decl.kind, DeclareKind::Variable(v) if v.name.node == "utf8"));
+        }
+        _ => panic!("Expected declare statement"),
+    }
+
+    // `declare` stays usable as an ordinary name
+    assert!(matches!(&program.statements[3], Statement::Variable(_)));
+}
+
+#[test]
+fn test_parse_export_equals() {
+    use crate::ast::expression::ExpressionKind;
+    use crate::ast::statement::{ExportKind, Statement};
+
+    let program = parse_source("export = createLogger").expect("Parse failed");
+    assert_eq!(program.statements.len(), 1);
+
+    match &program.statements[0] {
+        Statement::Export(export) => match &export.kind {
+            ExportKind::Equals(expr) => {
+                assert!(matches!(&expr.kind, ExpressionKind::Identifier(n) if n == "createLogger"))
+            }
+            _ => panic!("Expected export ="),
+        },
+        _ => panic!("Expected export declaration"),
+    }
+}
+
+#[test]
+fn test_parse_namespace_block() {
+    use crate::ast::statement::{ExportKind, Statement};
+    use crate::ast::types::TypeKind;
+
+    let source = r#"
+        namespace Geometry {
+            export interface Point {
+                x: number
+            }
+
+            export function origin(): Geometry.Point
+                return { x = 0 }
+            end
+        }
+        local namespace = 1
+    "#;
+    let program = parse_source(source).expect("Parse failed");
+    assert_eq!(program.statements.len(), 2);
+
+    let Statement::Namespace(namespace) = &program.statements[0] else {
+        panic!("Expected namespace declaration");
+    };
+    assert_eq!(namespace.name.node, "Geometry");
+    assert_eq!(namespace.body.statements.len(), 2);
+
+    match &namespace.body.statements[1] {
+        Statement::Export(export) => match &export.kind {
+            ExportKind::Declaration(decl) => match decl.as_ref() {
+                Statement::Function(func) => match &func.return_type.as_ref().unwrap().kind {
+                    TypeKind::Reference(reference) => {
+                        assert_eq!(reference.name.node, "Geometry.Point")
+                    }
+                    _ => panic!("Expected qualified type reference"),
+                },
+                _ => panic!("Expected function declaration"),
+            },
+            _ => panic!("Expected exported declaration"),
+        },
+        _ => panic!("Expected export"),
+    }
+
+    // `namespace` stays usable as an ordinary name
+    assert!(matches!(&program.statements[1], Statement::Variable(_)));
+}
+
+#[test]
+fn test_parse_const_string_enum() {
+    use crate::ast::statement::{EnumValue, ExportDeclaration, ExportKind, Statement};
+
+    let source = r#"
+        export const enum Color {
+            Red = "red",
+            Green = "green",
+            Count = 3,
+            Extra
+        }
+    "#;
+    let program = parse_source(source).expect("Parse failed");
+
+    let decl = match &program.statements[0] {
+        Statement::Export(ExportDeclaration {
+            kind: ExportKind::Declaration(decl),
+            ..
+        }) => decl.as_ref(),
+        _ => panic!("Expected export declaration"),
+    };
+    match decl {
+        Statement::Enum(enum_decl) => {
+            assert!(enum_decl.is_const);
+            assert_eq!(enum_decl.members.len(), 4);
+            assert!(matches!(
+                &enum_decl.members[0].value,
+                Some(EnumValue::String(s)) if s == "red"
+            ));
+            assert!(enum_decl.members[3].value.is_none());
+        }
+        _ => panic!("Expected enum declaration"),
+    }
+}
+
+#[test]
+fn test_enum_member_after_string_needs_value() {
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let mut lexer = Lexer::new("enum Color { Red = \"red\", Green }", handler.clone());
+    let tokens = lexer.tokenize().expect("Lexing failed");
+    let mut parser = Parser::new(tokens, handler.clone());
+    let _ = parser.parse();
+    assert!(handler.has_errors());
+}
+
+#[test]
+fn test_parse_type_only_import() {
+    use crate::ast::statement::{ImportClause, Statement};
+
+    let program =
+        parse_source("import type { Point, Size as Extent } from \"./geometry\"").expect("Parse failed");
+    match &program.statements[0] {
+        Statement::Import(import) => match &import.clause {
+            ImportClause::TypeOnly(specifiers) => {
+                assert_eq!(specifiers.len(), 2);
+                assert_eq!(specifiers[1].local.as_ref().unwrap().node, "Extent");
+            }
+            _ => panic!("Expected type-only import"),
+        },
+        _ => panic!("Expected import declaration"),
+    }
+}
+
+#[test]
+fn test_parse_class_with_mixins() {
+    use crate::ast::statement::Statement;
+
+    let source = "class Player extends mixin(Entity, Serializable) implements Named {\n}";
+    let program = parse_source(source).expect("Parse failed");
+    match &program.statements[0] {
+        Statement::Class(class) => {
+            assert!(class.extends.is_none());
+            let mixins: Vec<String> = class.mixins.iter().map(|m| m.to_string()).collect();
+            assert_eq!(mixins, vec!["Entity", "Serializable"]);
+            assert_eq!(class.implements.len(), 1);
+        }
+        _ => panic!("Expected class declaration"),
+    }
+}
+
+#[test]
+fn test_parse_pipeline_is_left_associative_and_loosest() {
+    use crate::ast::expression::{BinaryOp, ExpressionKind};
+
+    let program = parse_source("const r = a + 1 |> parse |> validate").expect("Parse failed");
+    let crate::ast::statement::Statement::Variable(decl) = &program.statements[0] else {
+        panic!("Expected variable declaration");
+    };
+    let ExpressionKind::Pipe(inner, last) = &decl.initializer.kind else {
+        panic!("Expected pipe, got {:?}", decl.initializer.kind);
+    };
+    assert!(matches!(&last.kind, ExpressionKind::Identifier(name) if name == "validate"));
+    let ExpressionKind::Pipe(value, first) = &inner.kind else {
+        panic!("Expected nested pipe, got {:?}", inner.kind);
+    };
+    assert!(matches!(&first.kind, ExpressionKind::Identifier(name) if name == "parse"));
+    assert!(matches!(value.kind, ExpressionKind::Binary(BinaryOp::Add, _, _)));
+}
+
+#[test]
+fn test_parse_assertion_return_types() {
+    use crate::ast::statement::Statement;
+    use crate::ast::types::TypeKind;
+
+    let source = r#"
+        function isPoint(value: unknown): asserts value is Point
+        end
+        function present(value: unknown): asserts value
+        end
+        local asserts = 1
+    "#;
+    let program = parse_source(source).expect("Parse failed");
+    assert_eq!(program.statements.len(), 3);
+
+    let predicates: Vec<String> = program.statements[..2]
+        .iter()
+        .map(|statement| match statement {
+            Statement::Function(func) => {
+                let return_type = func.return_type.as_ref().unwrap();
+                assert!(matches!(return_type.kind, TypeKind::Asserts(_)));
+                return_type.to_string()
+            }
+            _ => panic!("Expected function declaration"),
+        })
+        .collect();
+    assert_eq!(predicates, vec!["asserts value is Point", "asserts value"]);
+}
+
+#[test]
+fn test_parse_labeled_loops_and_jumps() {
+    use crate::ast::statement::{ForStatement, Statement};
+
+    let source = r#"
+        outer: for i = 1, 10 do
+            while true do
+                break outer
+            end
+            continue
+            other()
+        end
+    "#;
+    let program = parse_source(source).expect("Parse failed");
+    assert_eq!(program.statements.len(), 1);
+
+    let Statement::Labeled(labeled) = &program.statements[0] else {
+        panic!("Expected labeled loop");
+    };
+    assert_eq!(labeled.label.node, "outer");
+    let Statement::For(ForStatement::Numeric(numeric)) = labeled.body.as_ref() else {
+        panic!("Expected numeric for");
+    };
+    let Statement::While(inner) = &numeric.body.statements[0] else {
+        panic!("Expected while loop");
+    };
+    assert!(matches!(
+        &inner.body.statements[0],
+        Statement::Break(jump) if jump.label.as_ref().is_some_and(|l| l.node == "outer")
+    ));
+    // A label must follow on the same line
+    assert!(matches!(
+        &numeric.body.statements[1],
+        Statement::Continue(jump) if jump.label.is_none()
+    ));
+    assert_eq!(numeric.body.statements.len(), 3);
+}
+
+#[test]
+fn test_macro_argument_count_is_checked() {
+    use crate::ast::statement::Statement;
+
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let source = "macro log(value)\n    print(stringify(value), value)\nend\nlog(x)\nlog(1, 2)\n";
+    let tokens = Lexer::new(source, handler.clone())
+        .tokenize()
+        .expect("Lexing failed");
+    let program = Parser::new(tokens, handler.clone())
+        .parse()
+        .expect("Parse failed");
+
+    let diagnostics = handler.get_diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].message,
+        "Macro 'log' expects 1 argument(s), got 2"
+    );
+    assert_eq!(diagnostics[0].span.line, 5);
+    // The definition leaves nothing behind; the valid call becomes a block
+    // and the invalid one an error node
+    assert_eq!(program.statements.len(), 2);
+    assert!(matches!(&program.statements[0], Statement::Block(_)));
+    assert!(matches!(&program.statements[1], Statement::Error(_)));
+}
+
+#[test]
+fn test_parse_typeof_type() {
+    use crate::ast::statement::Statement;
+
+    let program =
+        parse_source("type Config = typeof settings.defaults | nil").expect("Parse failed");
+    let Statement::TypeAlias(alias) = &program.statements[0] else {
+        panic!("Expected type alias");
+    };
+    assert_eq!(
+        alias.type_annotation.to_string(),
+        "typeof settings.defaults | nil"
+    );
+}
+
+#[test]
+fn test_parse_keyof_constraint_and_array_shorthand() {
+    use crate::ast::statement::Statement;
+
+    let program = parse_source(
+        "function sortBy<T, K extends keyof T>(items: {T}, key: K, meta: { n: integer }) end",
+    )
+    .expect("Parse failed");
+    let Statement::Function(func) = &program.statements[0] else {
+        panic!("Expected function declaration");
+    };
+    let params = func
+        .type_parameters
+        .as_ref()
+        .expect("Expected type parameters");
+    assert_eq!(
+        params[1].constraint.as_ref().unwrap().to_string(),
+        "keyof T"
+    );
+    let annotation = |i: usize| {
+        func.parameters[i]
+            .type_annotation
+            .as_ref()
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(annotation(0), "T[]");
+    assert_eq!(annotation(2), "{ n: integer }");
+}
+
+#[test]
+fn test_parse_function_types_and_parenthesized_types() {
+    use crate::ast::statement::Statement;
+
+    let program = parse_source(
+        r#"
+        type Handler = (x: number, opts?: Options, ...rest: string[]) -> (boolean, string?)
+        type Parse = ((input: string) -> number) | ((input: number) -> number)
+        type Items = (string | number)[]
+        "#,
+    )
+    .expect("Parse failed");
+    let aliases: Vec<String> = program
+        .statements
+        .iter()
+        .map(|statement| match statement {
+            Statement::TypeAlias(alias) => alias.type_annotation.to_string(),
+            _ => panic!("Expected type alias"),
+        })
+        .collect();
+    assert_eq!(
+        aliases,
+        vec![
+            "(x: number, opts: Options?, ...rest: string[]) -> (boolean, string?)",
+            "((input: string) -> number) | ((input: number) -> number)",
+            "(string | number)[]",
+        ]
+    );
+}
+
+#[test]
+fn test_parse_attributes_on_declarations() {
+    let source = r#"
+        @deprecated("use bar")
+        function foo() end
+        @no_emit
+        type Id = number
+        @inline
+        export function bar() end
+    "#;
+    let program = parse_source(source).expect("Parse failed");
+    assert_eq!(program.statements.len(), 3);
+    for statement in &program.statements {
+        assert_eq!(
+            crate::ast::attributes::statement_decorators(statement).len(),
+            1
+        );
+    }
+
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let tokens = Lexer::new("@inline\nreturn 1", handler.clone())
+        .tokenize()
+        .expect("Lexing failed");
+    let _ = Parser::new(tokens, handler.clone()).parse();
+    let diagnostics = handler.get_diagnostics();
+    assert_eq!(
+        diagnostics[0].message,
+        "Decorators must precede a declaration"
+    );
+}
+
+#[test]
+fn test_parse_multiple_values() {
+    use crate::ast::pattern::{MultipleRest, Pattern};
+    use crate::ast::statement::Statement;
+
+    let program = parse_source(
+        r#"
+        type Find = (s: string) -> (boolean, string...)
+        local ok, first, ...rest = find("x")
+        local a, ... = find("y")
+        "#,
+    )
+    .expect("Parse failed");
+    match &program.statements[0] {
+        Statement::TypeAlias(alias) => assert_eq!(
+            alias.type_annotation.to_string(),
+            "(s: string) -> (boolean, string...)"
+        ),
+        _ => panic!("Expected type alias"),
+    }
+    match &program.statements[1] {
+        Statement::Variable(decl) => match &decl.pattern {
+            Pattern::Multiple(multiple) => {
+                assert_eq!(multiple.names.len(), 2);
+                assert!(matches!(
+                    &multiple.rest,
+                    Some(MultipleRest::Capture(name)) if name.node == "rest"
+                ));
+            }
+            _ => panic!("Expected multiple names"),
+        },
+        _ => panic!("Expected variable declaration"),
+    }
+    match &program.statements[2] {
+        Statement::Variable(decl) => assert!(matches!(
+            &decl.pattern,
+            Pattern::Multiple(multiple) if matches!(multiple.rest, Some(MultipleRest::Discard(_)))
+        )),
+        _ => panic!("Expected variable declaration"),
+    }
+}
+
+#[test]
+fn test_parse_using_declaration() {
+    use crate::ast::statement::{Statement, VariableKind};
+
+    let program = parse_source(
+        r#"
+        using file: File = open("save.dat")
+        using = 1
+        using(file)
+        "#,
+    )
+    .expect("Parse failed");
+    assert_eq!(program.statements.len(), 3);
+    match &program.statements[0] {
+        Statement::Variable(decl) => {
+            assert_eq!(decl.kind, VariableKind::Using);
+            assert!(decl.type_annotation.is_some());
+        }
+        _ => panic!("Expected using declaration"),
+    }
+    // Elsewhere `using` is an ordinary name
+    assert!(matches!(&program.statements[1], Statement::Expression(_)));
+    assert!(matches!(&program.statements[2], Statement::Expression(_)));
+}
+
+#[test]
+fn test_parser_recovers_inside_blocks() {
+    use crate::ast::statement::Statement;
+
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let source = r#"
+        function greet(name: string)
+            local message = = name
+            print(message); print(name)
+            if name then
+                local x: = 1
+            end
+        end
+        local after = 1
+    "#;
+    let tokens = Lexer::new(source, handler.clone())
+        .tokenize()
+        .expect("Lexing failed");
+    let program = Parser::new(tokens, handler.clone())
+        .parse()
+        .expect("Parse failed");
+
+    let lines: Vec<usize> = handler
+        .get_diagnostics()
+        .iter()
+        .map(|diagnostic| diagnostic.span.line)
+        .collect();
+    assert_eq!(lines, vec![3, 6]);
+    assert_eq!(program.statements.len(), 2);
+    let Statement::Function(func) = &program.statements[0] else {
+        panic!("Expected function declaration");
+    };
+    // Recovery skips to the `;`, then keeps `print(name)` and the `if`
+    assert!(matches!(func.body.statements[0], Statement::Error(_)));
+    assert_eq!(func.body.statements.len(), 3);
+    assert!(matches!(program.statements[1], Statement::Variable(_)));
+}
+
+#[test]
+fn test_fragment_entry_points() {
+    use crate::parser::{parse_expression, parse_statements, parse_type};
+
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let expr = parse_expression("a + b * 2", handler.clone()).expect("Parse failed");
+    assert!(matches!(
+        expr.kind,
+        crate::ast::expression::ExpressionKind::Binary(..)
+    ));
+    let typ = parse_type("(x: number) -> string?", handler.clone()).expect("Parse failed");
+    assert_eq!(typ.to_string(), "(x: number) -> string?");
+    let statements =
+        parse_statements("local x = 1\nprint(x)", handler.clone()).expect("Parse failed");
+    assert_eq!(statements.len(), 2);
+
+    let error = parse_expression("a + b c", handler.clone()).expect_err("Trailing tokens");
+    assert_eq!(error.message, "Unexpected 'c' after expression");
+    assert!(parse_type("number string", handler).is_err());
+}
+
+#[test]
+fn test_fragment_entry_points_never_panic() {
+    use crate::parser::{parse_expression, parse_statements, parse_type};
+
+    let deep = |open: &str, close: &str| format!("{}x{}", open.repeat(5000), close.repeat(5000));
+    let mut inputs = vec![
+        String::new(),
+        ")".to_string(),
+        "((".to_string(),
+        "function(".to_string(),
+        "{ a = ".to_string(),
+        "local".to_string(),
+        "end end".to_string(),
+        "@".to_string(),
+        "`${".to_string(),
+        "\"unterminated".to_string(),
+        "-".repeat(5000) + "x",
+        "not ".repeat(5000) + "x",
+        "x".to_string() + &"^x".repeat(5000),
+        "x".to_string() + &" = x".repeat(5000),
+        "x".to_string() + &" .. x".repeat(5000),
+        "if x then ".repeat(5000),
+        "local [".to_string() + &"[".repeat(5000),
+    ];
+    inputs.push(deep("(", ")"));
+    inputs.push(deep("{ a = ", " }"));
+    inputs.push(deep("Array<", ">"));
+    // Test threads get less stack than a program's main thread
+    std::thread::Builder::new()
+        .stack_size(8 * 1024 * 1024)
+        .spawn(move || {
+            for input in &inputs {
+                let handler = Arc::new(CollectingDiagnosticHandler::new());
+                let _ = parse_expression(input, handler.clone());
+                let _ = parse_type(input, handler.clone());
+                let _ = parse_statements(input, handler);
+            }
+
+            let handler = Arc::new(CollectingDiagnosticHandler::new());
+            let error = parse_expression(&deep("(", ")"), handler).expect_err("Too deep");
+            assert_eq!(error.message, "Nesting is too deep");
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+#[test]
+fn test_truncated_and_mutated_inputs_do_not_panic() {
+    let seeds = [
+        "local r = `${format(((item)}`",
+        "const s = `a ${x + 1} b ${y:%5.2f} c`",
+        "function f(a: number, b?: string): number\n  return a + #b\nend",
+        "local { x, y = 2, ...rest } = point",
+        "class A<T> extends B implements C { constructor(x: T) {} get v(): T { return self.x } }",
+        "match v { [a, ...b] when a > 1 => a, _ => 0 }",
+        "type F = (x: number) -> Array<Map<string, T>> | nil",
+    ];
+    let noise = [')', '(', '}', '{', '`', '$', ']', ',', ':', 'x'];
+    let mut inputs = Vec::new();
+    for seed in seeds {
+        let chars: Vec<char> = seed.chars().collect();
+        for cut in 0..=chars.len() {
+            inputs.push(chars[..cut].iter().collect::<String>());
+        }
+        for at in 0..chars.len() {
+            for c in noise {
+                let mut mutated = chars.clone();
+                mutated[at] = c;
+                inputs.push(mutated.iter().collect());
+            }
+        }
+    }
+    for input in &inputs {
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let _ = parse_expression(input, handler.clone());
+        let _ = parse_statements(input, handler);
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        if let Ok(tokens) = Lexer::new(input, handler.clone()).tokenize() {
+            let _ = Parser::new(tokens, handler).parse();
+        }
+    }
+
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    assert!(parse_expression("`${f(((x)}`", handler.clone()).is_err());
+    let error = parse_expression("`${a b}`", handler).expect_err("Two expressions");
+    assert_eq!(error.message, "Unexpected 'b' in template interpolation");
+}
@@ -1,5 +1,5 @@
-use super::{Parser, ParserError};
-use crate::ast::expression::Literal;
+use super::{number_literal, Parser, ParserError};
+use crate::ast::expression::{Expression, ExpressionKind, Literal};
 use crate::ast::types::*;
 use crate::ast::Spanned;
 use crate::lexer::TokenKind;
@@ -10,7 +10,7 @@ pub trait TypeParser {
 
 impl TypeParser for Parser {
     fn parse_type(&mut self) -> Result<Type, ParserError> {
-        self.parse_union_type()
+        self.nested(|parser| parser.parse_union_type())
     }
 }
 
@@ -58,7 +58,9 @@ impl Parser {
 
         loop {
             match &self.current().kind {
-                TokenKind::LeftBracket => {
+                // A '[' on a new line starts the next member (e.g. an index
+                // signature), not an array or index access type
+                TokenKind::LeftBracket if !self.starts_new_line() => {
                     self.advance();
                     if self.match_token(&[TokenKind::RightBracket]) {
                         // Array type: T[]
@@ -71,8 +73,7 @@ impl Parser {
                     } else {
                         // Index access type: T[K]
                         let index = self.parse_type()?;
-                        self.consume(TokenKind::RightBracket, "Expected ']'")?;
-                        let end_span = self.current_span();
+                        let end_span = self.consume(TokenKind::RightBracket, "Expected ']'")?.span;
                         let start_span = ty.span;
                         ty = Type {
                             kind: TypeKind::IndexAccess(Box::new(ty), Box::new(index)),
@@ -101,6 +102,76 @@ impl Parser {
         let start_span = self.current_span();
 
         match &self.current().kind.clone() {
+            // Assertion predicate: `asserts x` or `asserts x is T`
+            TokenKind::Identifier(name)
+                if name == "asserts"
+                    && matches!(self.peek(1).map(|t| &t.kind), Some(TokenKind::Identifier(_))) =>
+            {
+                self.advance();
+                let parameter = self.parse_identifier()?;
+                let type_predicate = match &self.current().kind {
+                    TokenKind::Identifier(word) if word == "is" => {
+                        self.advance();
+                        Some(Box::new(self.parse_type()?))
+                    }
+                    _ => None,
+                };
+                let end_span = type_predicate
+                    .as_ref()
+                    .map_or(parameter.span, |typ| typ.span);
+                let span = start_span.combine(&end_span);
+                Ok(Type {
+                    kind: TypeKind::Asserts(AssertsPredicate {
+                        parameter,
+                        type_predicate,
+                        span,
+                    }),
+                    span,
+                })
+            }
+
+            // Type of a value: `typeof config` or `typeof module.defaults`
+            TokenKind::Identifier(name)
+                if name == "typeof"
+                    && matches!(self.peek(1).map(|t| &t.kind), Some(TokenKind::Identifier(_))) =>
+            {
+                self.advance();
+                let root = self.parse_identifier()?;
+                let mut expr = Expression::new(ExpressionKind::Identifier(root.node), root.span);
+                while self.check(&TokenKind::Dot)
+                    && matches!(self.peek(1).map(|t| &t.kind), Some(TokenKind::Identifier(_)))
+                {
+                    self.advance();
+                    let member = self.parse_identifier()?;
+                    let span = expr.span.combine(&member.span);
+                    expr = Expression::new(ExpressionKind::Member(Box::new(expr), member), span);
+                }
+                let span = start_span.combine(&expr.span);
+                Ok(Type {
+                    kind: TypeKind::TypeQuery(Box::new(expr)),
+                    span,
+                })
+            }
+
+            // Keys of a type: `keyof T`
+            TokenKind::Identifier(name)
+                if name == "keyof"
+                    && matches!(
+                        self.peek(1).map(|t| &t.kind),
+                        Some(
+                            TokenKind::Identifier(_) | TokenKind::LeftBrace | TokenKind::LeftParen
+                        )
+                    ) =>
+            {
+                self.advance();
+                let operand = self.parse_postfix_type()?;
+                let span = start_span.combine(&operand.span);
+                Ok(Type {
+                    kind: TypeKind::KeyOf(Box::new(operand)),
+                    span,
+                })
+            }
+
             // Primitive types
             TokenKind::Identifier(name) => {
                 let primitive = match name.as_str() {
@@ -125,9 +196,19 @@ impl Parser {
                     });
                 }
 
-                // Type reference
-                let name_ident = Spanned::new(name.clone(), start_span);
+                // Type reference, possibly qualified by namespaces (`Ns.Name`)
+                let mut name = name.clone();
+                let mut name_span = start_span;
                 self.advance();
+                while self.check(&TokenKind::Dot)
+                    && matches!(self.peek(1).map(|t| &t.kind), Some(TokenKind::Identifier(_)))
+                {
+                    self.advance();
+                    let segment = self.parse_identifier()?;
+                    name = format!("{}.{}", name, segment.node);
+                    name_span = name_span.combine(&segment.span);
+                }
+                let name_ident = Spanned::new(name, name_span);
 
                 let type_arguments = if self.match_token(&[TokenKind::LessThan]) {
                     let args = self.parse_type_arguments()?;
@@ -151,13 +232,13 @@ impl Parser {
 
             // Literal types
             TokenKind::Number(s) => {
-                let num = s.parse::<f64>().map_err(|_| ParserError {
+                let literal = number_literal(s).ok_or_else(|| ParserError {
                     message: "Invalid number in type".to_string(),
                     span: start_span,
                 })?;
                 self.advance();
                 Ok(Type {
-                    kind: TypeKind::Literal(Literal::Number(num)),
+                    kind: TypeKind::Literal(literal),
                     span: start_span,
                 })
             }
@@ -198,10 +279,10 @@ impl Parser {
             TokenKind::LeftBracket => self.parse_tuple_type(),
 
             // Function type: (x: T) -> U
-            TokenKind::LeftParen => self.parse_function_type(),
+            TokenKind::LeftParen if self.is_function_type() => self.parse_function_type(),
 
-            // Parenthesized type: (T)
-            // TODO: Distinguish from function type
+            // Parenthesized type (T), or the values (T, U) a function returns
+            TokenKind::LeftParen => self.parse_parenthesized_type(),
 
             _ => Err(ParserError {
                 message: format!("Unexpected token in type: {:?}", self.current().kind),
@@ -214,6 +295,20 @@ impl Parser {
         let start_span = self.current_span();
         self.consume(TokenKind::LeftBrace, "Expected '{'")?;
 
+        // Array shorthand: {T} is T[]
+        if !self.check_object_type_member() {
+            let element = self.parse_type()?;
+            let end_span = self.current_span();
+            self.consume(
+                TokenKind::RightBrace,
+                "Expected '}' after array element type",
+            )?;
+            return Ok(Type {
+                kind: TypeKind::Array(Box::new(element)),
+                span: start_span.combine(&end_span),
+            });
+        }
+
         let mut members = Vec::new();
 
         while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
@@ -243,6 +338,7 @@ impl Parser {
 
                     members.push(ObjectTypeMember::Method(
                         crate::ast::statement::MethodSignature {
+                            decorators: Vec::new(),
                             name,
                             type_parameters,
                             parameters,
@@ -259,6 +355,7 @@ impl Parser {
 
                     members.push(ObjectTypeMember::Property(
                         crate::ast::statement::PropertySignature {
+                            decorators: Vec::new(),
                             is_readonly,
                             name,
                             is_optional,
@@ -285,6 +382,24 @@ impl Parser {
         })
     }
 
+    /// Whether the token after `{` starts a member rather than an element type
+    fn check_object_type_member(&self) -> bool {
+        if matches!(
+            self.current().kind,
+            TokenKind::RightBrace | TokenKind::LeftBracket | TokenKind::Readonly
+        ) {
+            return true;
+        }
+        match self.peek(1).map(|t| &t.kind) {
+            Some(TokenKind::Colon | TokenKind::LeftParen | TokenKind::LessThan) => true,
+            // `{ name?: T }`, not `{T?}`
+            Some(TokenKind::Question) => {
+                matches!(self.peek(2).map(|t| &t.kind), Some(TokenKind::Colon))
+            }
+            _ => false,
+        }
+    }
+
     fn parse_tuple_type(&mut self) -> Result<Type, ParserError> {
         let start_span = self.current_span();
         self.consume(TokenKind::LeftBracket, "Expected '['")?;
@@ -310,6 +425,85 @@ impl Parser {
         })
     }
 
+    /// One of the values in `(T, U...)`, where the last may be variadic
+    fn parse_return_value_type(&mut self) -> Result<Type, ParserError> {
+        let typ = self.parse_type()?;
+        if self.check(&TokenKind::DotDotDot) {
+            let end_span = self.current_span();
+            self.advance();
+            let span = typ.span.combine(&end_span);
+            return Ok(Type {
+                kind: TypeKind::Variadic(Box::new(typ)),
+                span,
+            });
+        }
+        Ok(typ)
+    }
+
+    /// Whether the `(` at the current token closes with `) ->`
+    fn is_function_type(&self) -> bool {
+        let mut depth = 0;
+        let mut offset = 0;
+        while let Some(token) = self.peek(offset) {
+            match token.kind {
+                TokenKind::LeftParen => depth += 1,
+                TokenKind::RightParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return matches!(
+                            self.peek(offset + 1).map(|t| &t.kind),
+                            Some(TokenKind::Arrow)
+                        );
+                    }
+                }
+                TokenKind::Eof => return false,
+                _ => {}
+            }
+            offset += 1;
+        }
+        false
+    }
+
+    fn parse_parenthesized_type(&mut self) -> Result<Type, ParserError> {
+        let start_span = self.current_span();
+        self.consume(TokenKind::LeftParen, "Expected '('")?;
+
+        let mut types = vec![self.parse_return_value_type()?];
+        while self.match_token(&[TokenKind::Comma]) {
+            if matches!(types.last().map(|t| &t.kind), Some(TypeKind::Variadic(_))) {
+                return Err(ParserError {
+                    message: "Only the last return value can be variadic".to_string(),
+                    span: self.current_span(),
+                });
+            }
+            types.push(self.parse_return_value_type()?);
+        }
+
+        let end_span = self.current_span();
+        self.consume(TokenKind::RightParen, "Expected ')' after type")?;
+        if let [single @ Type {
+            kind: TypeKind::Variadic(_),
+            ..
+        }] = types.as_slice()
+        {
+            // `(T...)` lists values, unlike a parenthesized `(T)`
+            return Ok(Type {
+                kind: TypeKind::Tuple(vec![single.clone()]),
+                span: start_span.combine(&end_span),
+            });
+        }
+
+        let kind = if types.len() == 1 {
+            TypeKind::Parenthesized(Box::new(types.remove(0)))
+        } else {
+            TypeKind::Tuple(types)
+        };
+        Ok(Type {
+            kind,
+            span: start_span.combine(&end_span),
+        })
+    }
+
     fn parse_function_type(&mut self) -> Result<Type, ParserError> {
         let start_span = self.current_span();
         self.consume(TokenKind::LeftParen, "Expected '('")?;
@@ -0,0 +1,45 @@
+//! Compiler plugins: project-specific transforms run as part of compiling
+//! each module, such as registering classes or injecting logging. Plugins
+//! are registered on the [`Container`](crate::di::Container) and run in
+//! the order they were added.
+
+use crate::ast::Program;
+use crate::config::CompilerConfig;
+use crate::diagnostics::DiagnosticHandler;
+use crate::resolve::ResolutionStrategy;
+use crate::typechecker::{SymbolTable, TypeEnvironment};
+use std::path::Path;
+use std::sync::Arc;
+
+/// What a plugin sees of the module being compiled
+pub struct PluginContext<'a> {
+    /// The module's file, when it was compiled from one
+    pub path: Option<&'a Path>,
+    pub config: &'a CompilerConfig,
+    /// The module's top-level names and their checked types
+    pub symbols: &'a SymbolTable,
+    /// The types and interfaces the module declares or imports
+    pub type_environment: &'a TypeEnvironment,
+    /// Errors reported here fail the module's compilation
+    pub diagnostic_handler: &'a dyn DiagnosticHandler,
+}
+
+/// A transform over modules that type checked. The hooks do nothing
+/// unless the plugin overrides them.
+pub trait Plugin: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// How imports find modules for an engine whose `require` does not take
+    /// file-relative paths. The first plugin returning one is used.
+    fn resolution(&self) -> Option<Arc<dyn ResolutionStrategy>> {
+        None
+    }
+
+    /// Rewrite the module before Lua is generated for it. The rewritten
+    /// module is not checked again.
+    fn transform(&self, _program: &mut Program, _context: &PluginContext) {}
+
+    /// Change the Lua generated for the module. A source map is not
+    /// written for Lua a plugin changed.
+    fn emit(&self, _lua: &mut String, _context: &PluginContext) {}
+}
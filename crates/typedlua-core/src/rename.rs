@@ -0,0 +1,594 @@
+//! Renaming a name together with its uses (`typedlua rename`). A name the
+//! module exports is renamed in its declaration file too, and in every
+//! module of the workspace importing it, following re-exports through the
+//! workspace's dependency graph.
+
+use crate::ast::expression::ExpressionKind;
+use crate::ast::statement::{ExportKind, ImportClause, Statement};
+use crate::ast::{Ident, Program};
+use crate::build::DependencyGraph;
+use crate::diagnostics::{CollectingDiagnosticHandler, DiagnosticHandler};
+use crate::errors::RenameError;
+use crate::fs::FileSystem;
+use crate::lexer::{Lexer, TokenKind};
+use crate::lint::{Analysis, BindingKind};
+use crate::parser::Parser;
+use crate::resolve::ModuleResolver;
+use crate::span::Span;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// A file's text before and after a rename
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileEdit {
+    pub path: PathBuf,
+    pub before: String,
+    pub after: String,
+}
+
+impl FileEdit {
+    /// The changed lines as a diff, to preview the rename with. A rename
+    /// never adds or removes lines, so lines pair up by number.
+    pub fn diff(&self) -> String {
+        let path = self.path.display();
+        let mut diff = format!("--- {}\n+++ {}\n", path, path);
+        let lines = self.before.lines().zip(self.after.lines());
+        for (number, (before, after)) in lines.enumerate() {
+            if before != after {
+                diff.push_str(&format!(
+                    "@@ -{0} +{0} @@\n-{1}\n+{2}\n",
+                    number + 1,
+                    before,
+                    after
+                ));
+            }
+        }
+        diff
+    }
+}
+
+/// A replacement of the text at a span
+type Replacement = (Span, String);
+
+struct Module {
+    path: PathBuf,
+    text: String,
+    program: Program,
+}
+
+impl Module {
+    fn read(path: &Path, file_system: &dyn FileSystem) -> Result<Self, RenameError> {
+        let text = file_system.read_file(path)?;
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let program = parse(&text, handler.clone())
+            .filter(|_| !handler.has_errors())
+            .ok_or_else(|| RenameError::ParseFailed(path.display().to_string()))?;
+        Ok(Module {
+            path: path.to_path_buf(),
+            text,
+            program,
+        })
+    }
+
+    /// Apply the replacements, failing if the new name is already declared
+    /// here, where it would clash with or capture the renamed uses
+    fn edit(
+        &self,
+        analysis: &Analysis,
+        replacements: Vec<Replacement>,
+        new_name: &str,
+    ) -> Result<FileEdit, RenameError> {
+        let declared = analysis.bindings.iter().any(|b| b.name == new_name)
+            || type_declarations(&self.program.statements)
+                .iter()
+                .any(|(name, _)| name.node == new_name);
+        if declared {
+            return Err(RenameError::NameTaken {
+                name: new_name.to_string(),
+                path: self.path.display().to_string(),
+            });
+        }
+
+        let mut replacements = replacements;
+        replacements.sort_by_key(|(span, _)| span.start);
+        replacements.dedup_by_key(|(span, _)| span.start);
+        let chars: Vec<char> = self.text.chars().collect();
+        let mut after = String::new();
+        let mut at = 0;
+        for (span, text) in replacements {
+            after.extend(&chars[at..span.start]);
+            after.push_str(&text);
+            at = span.end;
+        }
+        after.extend(&chars[at..]);
+        Ok(FileEdit {
+            path: self.path.clone(),
+            before: self.text.clone(),
+            after,
+        })
+    }
+}
+
+/// A name a module declares, with the replacements renaming it there
+struct Declared {
+    name: String,
+    replacements: Vec<Replacement>,
+    exported: bool,
+}
+
+/// Rename the name at a byte offset of `file` to `new_name`. When the
+/// module exports it, the rename continues into `declaration_file`, the
+/// declaration file compiled from the module, and into the `workspace`
+/// modules importing it from either. Nothing is written: the edits are
+/// returned, the renamed file first, so they can be previewed or applied
+/// together.
+pub fn rename(
+    file: &Path,
+    offset: usize,
+    new_name: &str,
+    declaration_file: Option<&Path>,
+    workspace: &[PathBuf],
+    file_system: Arc<dyn FileSystem>,
+) -> Result<Vec<FileEdit>, RenameError> {
+    if !is_identifier(new_name) {
+        return Err(RenameError::InvalidName(new_name.to_string()));
+    }
+    let fs = file_system.as_ref();
+    let module = Module::read(file, fs)?;
+    let analysis = Analysis::new(&module.program);
+    let declared = module
+        .text
+        .get(..offset)
+        .and_then(|prefix| declared_at(&module.program, &analysis, prefix.chars().count()))
+        .ok_or(RenameError::NoName(offset))?;
+    let old_name = declared.name.clone();
+    let mut edits = vec![module.edit(
+        &analysis,
+        rename_to(declared.replacements, new_name),
+        new_name,
+    )?];
+    if !declared.exported {
+        return Ok(edits);
+    }
+
+    let mut targets = vec![normalize(file)];
+    if let Some(path) = declaration_file.filter(|path| fs.exists(path)) {
+        let module = Module::read(path, fs)?;
+        let analysis = Analysis::new(&module.program);
+        if let Some(declared) = declared_named(&module.program, &analysis, &old_name) {
+            let replacements = rename_to(declared.replacements, new_name);
+            edits.push(module.edit(&analysis, replacements, new_name)?);
+        }
+        targets.push(normalize(path));
+    }
+
+    let resolver = ModuleResolver::new(file_system.clone());
+    let mut graph = DependencyGraph::new();
+    for path in workspace {
+        graph.update(&normalize(path), imported_paths(path, &resolver));
+    }
+    // Modules re-exporting the name under its old name pass the rename on
+    // to their own importers. They are all found first, as a module may
+    // import the name both from one of them and from the original.
+    let mut importers: Vec<PathBuf> = Vec::new();
+    let mut next = 0;
+    while next < targets.len() {
+        for dependent in graph.dependents(&targets[next]) {
+            if importers.contains(&dependent) || targets.contains(&dependent) {
+                continue;
+            }
+            let module = Module::read(&dependent, fs)?;
+            let analysis = Analysis::new(&module.program);
+            let (_, reexported) =
+                importer_replacements(&module, &analysis, &resolver, &targets, &old_name);
+            if reexported {
+                targets.push(dependent.clone());
+            }
+            importers.push(dependent);
+        }
+        next += 1;
+    }
+
+    for path in importers {
+        let module = Module::read(&path, fs)?;
+        let analysis = Analysis::new(&module.program);
+        let (replacements, _) =
+            importer_replacements(&module, &analysis, &resolver, &targets, &old_name);
+        if !replacements.is_empty() {
+            let replacements = rename_to(replacements, new_name);
+            edits.push(module.edit(&analysis, replacements, new_name)?);
+        }
+    }
+    Ok(edits)
+}
+
+/// Fill in the new name for the replacements that are just the name
+fn rename_to(replacements: Vec<Replacement>, new_name: &str) -> Vec<Replacement> {
+    replacements
+        .into_iter()
+        .map(|(span, text)| match text.is_empty() {
+            true => (span, new_name.to_string()),
+            false => (span, text.replace('\0', new_name)),
+        })
+        .collect()
+}
+
+/// The name declared or used at a character offset
+fn declared_at(program: &Program, analysis: &Analysis, offset: usize) -> Option<Declared> {
+    let contains = |span: &Span| span.start <= offset && offset <= span.end;
+    let binding = analysis
+        .references
+        .iter()
+        .find(|reference| contains(&reference.span))
+        .map(|reference| reference.binding)
+        .or_else(|| analysis.bindings.iter().position(|b| contains(&b.span)));
+    if let Some(index) = binding {
+        let name = analysis.bindings[index].name.clone();
+        return Some(declared(program, analysis, &name, Some(index)));
+    }
+
+    let name = analysis
+        .type_references
+        .iter()
+        .map(root_name)
+        .chain(
+            type_declarations(&program.statements)
+                .into_iter()
+                .map(|(name, _)| name.clone()),
+        )
+        .find(|name| contains(&name.span))?
+        .node;
+    let class = analysis
+        .bindings
+        .iter()
+        .position(|b| b.name == name && b.kind == BindingKind::Declaration);
+    Some(declared(program, analysis, &name, class))
+}
+
+/// The exported name `name` of a declaration file
+fn declared_named(program: &Program, analysis: &Analysis, name: &str) -> Option<Declared> {
+    let binding = analysis
+        .bindings
+        .iter()
+        .position(|b| b.name == name && b.exported);
+    let declared = declared(program, analysis, name, binding);
+    declared.exported.then_some(declared)
+}
+
+/// The replacements renaming a name through a module: the binding and its
+/// uses, and for a class, enum, interface or type alias, its uses as a
+/// type
+fn declared(
+    program: &Program,
+    analysis: &Analysis,
+    name: &str,
+    binding: Option<usize>,
+) -> Declared {
+    let mut replacements = Vec::new();
+    let mut exported = false;
+    let mut is_type = false;
+    if let Some(index) = binding {
+        let bound = &analysis.bindings[index];
+        exported = bound.exported;
+        is_type = bound.kind == BindingKind::Declaration;
+        replacements.extend(binding_replacements(program, analysis, index));
+    }
+    let types = type_declarations(&program.statements);
+    if let Some((ident, type_exported)) = types.iter().find(|(ident, _)| ident.node == name) {
+        replacements.push((ident.span, String::new()));
+        exported |= type_exported;
+        is_type = true;
+    }
+    if is_type {
+        replacements.extend(type_replacements(analysis, name));
+    }
+    Declared {
+        name: name.to_string(),
+        replacements,
+        exported,
+    }
+}
+
+/// A binding and the uses of it. Renaming the binding of an import that
+/// is not aliased gives it an alias instead, as the imported name belongs
+/// to the other module.
+fn binding_replacements(program: &Program, analysis: &Analysis, index: usize) -> Vec<Replacement> {
+    let binding = &analysis.bindings[index];
+    let declaration = match binding.kind {
+        BindingKind::Import if is_unaliased_import(program, binding.span) => {
+            format!("{} as \0", binding.name)
+        }
+        _ => String::new(),
+    };
+    let mut replacements = vec![(binding.span, declaration)];
+    replacements.extend(
+        analysis
+            .references
+            .iter()
+            .filter(|reference| reference.binding == index)
+            .map(|reference| (reference.span, String::new())),
+    );
+    replacements
+}
+
+fn is_unaliased_import(program: &Program, span: Span) -> bool {
+    program.statements.iter().any(|statement| match statement {
+        Statement::Import(import) => match &import.clause {
+            ImportClause::Named(specifiers) => specifiers
+                .iter()
+                .any(|specifier| specifier.local.is_none() && specifier.imported.span == span),
+            _ => false,
+        },
+        _ => false,
+    })
+}
+
+/// Uses of a name in types, including as the first part of `name.Type`
+fn type_replacements(analysis: &Analysis, name: &str) -> Vec<Replacement> {
+    analysis
+        .type_references
+        .iter()
+        .map(root_name)
+        .filter(|root| root.node == name)
+        .map(|root| (root.span, String::new()))
+        .collect()
+}
+
+/// The first part of a dotted type name
+fn root_name(name: &Ident) -> Ident {
+    let root = name.node.split('.').next().unwrap_or_default();
+    let end = name.span.start + root.chars().count();
+    Ident::new(
+        root.to_string(),
+        Span::new(name.span.start, end, name.span.line, name.span.column),
+    )
+}
+
+/// Interfaces and type aliases declared at the top level, and whether each
+/// is exported
+fn type_declarations(statements: &[Statement]) -> Vec<(&Ident, bool)> {
+    fn collect<'a>(statement: &'a Statement, exported: bool, found: &mut Vec<(&'a Ident, bool)>) {
+        match statement {
+            Statement::Interface(interface) => found.push((&interface.name, exported)),
+            Statement::TypeAlias(alias) => found.push((&alias.name, exported)),
+            Statement::Export(export) => {
+                if let ExportKind::Declaration(decl) = &export.kind {
+                    collect(decl, true, found);
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut found = Vec::new();
+    for statement in statements {
+        collect(statement, false, &mut found);
+    }
+    found
+}
+
+/// Where a module importing the renamed name from one of `targets` names
+/// it, and whether it exports it on under the old name
+fn importer_replacements(
+    module: &Module,
+    analysis: &Analysis,
+    resolver: &ModuleResolver,
+    targets: &[PathBuf],
+    old_name: &str,
+) -> (Vec<Replacement>, bool) {
+    let mut replacements = Vec::new();
+    let mut renames_local = false;
+    for statement in &module.program.statements {
+        let Statement::Import(import) = statement else {
+            continue;
+        };
+        let Some(resolved) = resolver.resolve(&module.path, &import.source) else {
+            continue;
+        };
+        if !targets.contains(&normalize(resolved.path())) {
+            continue;
+        }
+        match &import.clause {
+            ImportClause::Named(specifiers) | ImportClause::TypeOnly(specifiers) => {
+                for specifier in specifiers {
+                    if specifier.imported.node != old_name {
+                        continue;
+                    }
+                    if specifier.local.is_some() {
+                        replacements.push((specifier.imported.span, String::new()));
+                        continue;
+                    }
+                    renames_local = true;
+                    let span = specifier.imported.span;
+                    if let Some(index) = analysis.bindings.iter().position(|b| b.span == span) {
+                        replacements.extend(
+                            analysis
+                                .references
+                                .iter()
+                                .filter(|reference| reference.binding == index)
+                                .map(|reference| (reference.span, String::new())),
+                        );
+                    }
+                    replacements.push((span, String::new()));
+                    replacements.extend(type_replacements(analysis, old_name));
+                }
+            }
+            ImportClause::Namespace(namespace) => {
+                replacements.extend(namespace_replacements(analysis, &namespace.node, old_name))
+            }
+            ImportClause::Default(_) => {}
+        }
+    }
+
+    let reexported = renames_local
+        && module
+            .program
+            .statements
+            .iter()
+            .any(|statement| match statement {
+                Statement::Export(export) => match &export.kind {
+                    ExportKind::Named(specifiers) => specifiers.iter().any(|specifier| {
+                        specifier.local.node == old_name && specifier.exported.is_none()
+                    }),
+                    _ => false,
+                },
+                _ => false,
+            });
+    (replacements, reexported)
+}
+
+/// `namespace.name` in values and types
+fn namespace_replacements(analysis: &Analysis, namespace: &str, name: &str) -> Vec<Replacement> {
+    let mut replacements = Vec::new();
+    for expr in &analysis.expressions {
+        let (ExpressionKind::Member(object, member)
+        | ExpressionKind::OptionalMember(object, member)) = &expr.kind
+        else {
+            continue;
+        };
+        let on_namespace = matches!(&object.kind, ExpressionKind::Identifier(n) if n == namespace);
+        if on_namespace && member.node == name {
+            replacements.push((member.span, String::new()));
+        }
+    }
+    for reference in &analysis.type_references {
+        let mut parts = reference.node.split('.');
+        if parts.next() == Some(namespace) && parts.next() == Some(name) {
+            let start = reference.span.start + namespace.chars().count() + 1;
+            let end = start + name.chars().count();
+            let span = Span::new(start, end, reference.span.line, reference.span.column);
+            replacements.push((span, String::new()));
+        }
+    }
+    replacements
+}
+
+/// The modules a workspace module imports, as the resolver finds them. A
+/// module that cannot be read or lexed imports nothing.
+fn imported_paths(path: &Path, resolver: &ModuleResolver) -> Vec<PathBuf> {
+    let Ok(text) = resolver.file_system().read_file(path) else {
+        return Vec::new();
+    };
+    let Some(program) = parse(&text, Arc::new(CollectingDiagnosticHandler::new())) else {
+        return Vec::new();
+    };
+    program
+        .statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Import(import) => resolver.resolve(path, &import.source),
+            _ => None,
+        })
+        .map(|module| normalize(module.path()))
+        .collect()
+}
+
+/// A path without `.` parts, and with `..` parts folded into the directory
+/// before them, so paths the resolver builds compare equal to those given
+fn normalize(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(normal.components().next_back(), Some(Component::Normal(_))) =>
+            {
+                normal.pop();
+            }
+            other => normal.push(other),
+        }
+    }
+    normal
+}
+
+/// Whether `name` lexes as a single identifier, keywords excluded
+fn is_identifier(name: &str) -> bool {
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let Ok(tokens) = Lexer::new(name, handler).tokenize() else {
+        return false;
+    };
+    matches!(
+        tokens.as_slice(),
+        [token, end] if matches!(&token.kind, TokenKind::Identifier(ident) if ident == name)
+            && end.kind == TokenKind::Eof
+    )
+}
+
+fn parse(text: &str, handler: Arc<dyn DiagnosticHandler>) -> Option<Program> {
+    let tokens = Lexer::new(text, handler.clone()).tokenize().ok()?;
+    Parser::new(tokens, handler).parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::MockFileSystem;
+
+    const LIBRARY: &str = "export class User {\n    name: string = \"\"\n}\n\nexport function lookup(id: integer): User\n    const found: User = User.new()\n    return found\nend\n";
+
+    const DECLARATIONS: &str =
+        "export class User {\n    name: string\n}\nexport declare function lookup(id: integer): User\n";
+
+    const REEXPORT: &str = "import { User, lookup } from \"./user\"\nexport { User, lookup }\n";
+
+    const APP: &str = "import { User } from \"./models/index\"\nimport * as users from \"./models/user\"\n\nconst guest: User = users.lookup(0)\nlocal list: users.User[] = {}\n";
+
+    #[test]
+    fn test_rename_follows_importers_and_declarations() {
+        let mut fs = MockFileSystem::new();
+        fs.add_file("models/user.tl", LIBRARY);
+        fs.add_file("models/user.d.tl", DECLARATIONS);
+        fs.add_file("models/index.tl", REEXPORT);
+        fs.add_file("app.tl", APP);
+        let fs = Arc::new(fs);
+        let workspace: Vec<PathBuf> = ["app.tl", "models/index.tl", "models/user.tl"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+
+        let offset = LIBRARY.find("User").unwrap() + 2;
+        let edits = rename(
+            Path::new("models/user.tl"),
+            offset,
+            "Account",
+            Some(Path::new("models/user.d.tl")),
+            &workspace,
+            fs.clone(),
+        )
+        .expect("Rename failed");
+
+        let paths: Vec<&Path> = edits.iter().map(|edit| edit.path.as_path()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                Path::new("models/user.tl"),
+                Path::new("models/user.d.tl"),
+                Path::new("app.tl"),
+                Path::new("models/index.tl"),
+            ]
+        );
+        assert_eq!(edits[0].after, LIBRARY.replace("User", "Account"));
+        assert_eq!(edits[1].after, DECLARATIONS.replace("User", "Account"));
+        assert_eq!(edits[3].after, REEXPORT.replace("{ User", "{ Account"));
+        assert_eq!(edits[2].after, APP.replace("User", "Account"));
+        assert!(edits[2]
+            .diff()
+            .contains("@@ -4 +4 @@\n-const guest: User = users.lookup(0)\n+const guest: Account = users.lookup(0)\n"));
+
+        // Renaming where a module already has the name fails as a whole
+        let error = rename(
+            Path::new("models/user.tl"),
+            offset,
+            "guest",
+            None,
+            &workspace,
+            fs.clone(),
+        )
+        .expect_err("Clash passed");
+        assert!(matches!(error, RenameError::NameTaken { path, .. } if path == "app.tl"));
+
+        assert!(matches!(
+            rename(Path::new("app.tl"), 0, "end", None, &workspace, fs),
+            Err(RenameError::InvalidName(_))
+        ));
+    }
+}
@@ -0,0 +1,241 @@
+//! Evaluating TypedLua a line at a time (`typedlua repl`). Each input is
+//! checked as code at the end of everything entered before it, so earlier
+//! declarations keep their types, and compiled to Lua on its own, to run
+//! in a Lua state that lives as long as the session.
+
+use crate::ast::expression::ExpressionKind;
+use crate::ast::pattern::Pattern;
+use crate::ast::statement::{ReturnStatement, Statement, VariableDeclaration, VariableKind};
+use crate::ast::{Ident, Program};
+use crate::codegen::CodeGenerator;
+use crate::config::CompilerConfig;
+use crate::diagnostics::{
+    CollectingDiagnosticHandler, Diagnostic, DiagnosticHandler, DiagnosticLevel,
+};
+use crate::inspect::top_level_names;
+use crate::lexer::{Directive, Lexer};
+use crate::parser::Parser;
+use crate::span::Span;
+use crate::typechecker::TypeChecker;
+use std::sync::Arc;
+
+/// The name an expression input is bound to while its type is inferred;
+/// not a valid identifier, so it cannot clash with the session's names
+const RESULT_NAME: &str = "(result)";
+
+/// What an input to the session compiled to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Evaluation {
+    /// The input stops partway through a statement, as in the first line
+    /// of a function; more lines are needed
+    Incomplete,
+    /// Lua running the input's statements. It returns a table of the
+    /// top-level names they declare, for the state to keep.
+    Statements(String),
+    /// Lua returning the values of an expression, with its type
+    Expression { lua: String, typ: String },
+}
+
+/// A REPL session: the inputs entered so far that compiled
+pub struct Session {
+    config: Arc<CompilerConfig>,
+    source: String,
+}
+
+impl Session {
+    pub fn new(config: Arc<CompilerConfig>) -> Self {
+        Session {
+            config,
+            source: String::new(),
+        }
+    }
+
+    /// Check and compile one input. On success it becomes part of the
+    /// session; errors are reported at lines and columns of the input and
+    /// leave the session as it was.
+    pub fn evaluate(&mut self, input: &str) -> Result<Evaluation, Vec<Diagnostic>> {
+        let source = format!("{}{}\n", self.source, input);
+        let start = self.source.chars().count();
+        let end = source.chars().count();
+        let errors = |handler: &CollectingDiagnosticHandler| {
+            input_errors(handler, start, self.source.matches('\n').count())
+        };
+
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let Some((program, directives)) = parse(&source, handler.clone()) else {
+            return Err(errors(&handler));
+        };
+        if handler.has_errors() {
+            // Errors at the end of input mean the statement is not over yet
+            let diagnostics = handler.get_diagnostics();
+            if diagnostics.iter().any(|d| d.span.start >= end) {
+                return Ok(Evaluation::Incomplete);
+            }
+            return Err(errors(&handler));
+        }
+
+        let mut checker = TypeChecker::new(self.config.clone(), handler.clone());
+        checker.set_directives(&directives);
+        if checker.check_program(&program).is_err() || handler.has_errors() {
+            return Err(errors(&handler));
+        }
+
+        let statements: Vec<Statement> = program
+            .statements
+            .iter()
+            .filter(|statement| statement.span().start >= start)
+            .cloned()
+            .collect();
+        let evaluation = match self.expression_type(&program, &statements, &directives) {
+            Some(typ) => {
+                let Statement::Expression(expr) = &statements[0] else {
+                    unreachable!("only expression inputs have a type");
+                };
+                let value = Statement::Return(ReturnStatement {
+                    values: vec![expr.clone()],
+                    span: expr.span,
+                });
+                Evaluation::Expression {
+                    lua: self.generate(vec![value], program.span),
+                    typ,
+                }
+            }
+            None => {
+                let mut names = Vec::new();
+                for statement in &statements {
+                    top_level_names(statement, &mut names);
+                }
+                names.dedup();
+                let mut lua = self.generate(statements, program.span);
+                let fields: Vec<String> = names
+                    .iter()
+                    .map(|name| format!("{} = {}", name, name))
+                    .collect();
+                lua.push_str(&format!("return {{ {} }}\n", fields.join(", ")));
+                Evaluation::Statements(lua)
+            }
+        };
+        self.source = source;
+        Ok(evaluation)
+    }
+
+    /// The type of an input that is a lone expression with a value, found
+    /// by checking it bound to a name. Calls of functions returning
+    /// nothing, and assignments, run as statements.
+    fn expression_type(
+        &self,
+        program: &Program,
+        statements: &[Statement],
+        directives: &[Directive],
+    ) -> Option<String> {
+        let [Statement::Expression(expr)] = statements else {
+            return None;
+        };
+        if matches!(expr.kind, ExpressionKind::Assignment(..)) {
+            return None;
+        }
+
+        let mut bound = program.statements.clone();
+        bound.pop();
+        bound.push(Statement::Variable(VariableDeclaration {
+            decorators: Vec::new(),
+            kind: VariableKind::Local,
+            pattern: Pattern::Identifier(Ident::new(RESULT_NAME.to_string(), expr.span)),
+            type_annotation: None,
+            initializer: expr.clone(),
+            span: expr.span,
+        }));
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let mut checker = TypeChecker::new(self.config.clone(), handler.clone());
+        checker.set_directives(directives);
+        checker
+            .check_program(&Program::new(bound, program.span))
+            .ok()?;
+        if handler.has_errors() {
+            return None;
+        }
+        let symbol = checker.symbols().lookup(RESULT_NAME)?;
+        Some(symbol.typ.to_string())
+    }
+
+    fn generate(&self, statements: Vec<Statement>, span: Span) -> String {
+        CodeGenerator::new(self.config.clone()).generate(&Program::new(statements, span))
+    }
+}
+
+fn parse(source: &str, handler: Arc<dyn DiagnosticHandler>) -> Option<(Program, Vec<Directive>)> {
+    let mut lexer = Lexer::new(source, handler.clone());
+    let tokens = lexer.tokenize().ok()?;
+    let program = Parser::new(tokens, handler).parse().ok()?;
+    Some((program, lexer.directives().to_vec()))
+}
+
+/// The errors reported, moved from the session's source to the input
+/// starting at character `start` after `lines` earlier lines. An error in
+/// earlier input that the new input caused is placed at its start.
+fn input_errors(
+    handler: &CollectingDiagnosticHandler,
+    start: usize,
+    lines: usize,
+) -> Vec<Diagnostic> {
+    handler
+        .get_diagnostics()
+        .into_iter()
+        .filter(|d| d.level == DiagnosticLevel::Error)
+        .map(|d| {
+            let span = if d.span.start >= start && d.span.line > lines {
+                Span::new(
+                    d.span.start - start,
+                    d.span.end - start,
+                    d.span.line - lines,
+                    d.span.column,
+                )
+            } else {
+                Span::new(0, 0, 1, 1)
+            };
+            Diagnostic { span, ..d }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_keeps_types_across_inputs() {
+        let mut session = Session::new(Arc::new(CompilerConfig::default()));
+
+        let Ok(Evaluation::Statements(lua)) = session.evaluate("local count = 1") else {
+            panic!("Declaration did not compile");
+        };
+        assert_eq!(lua, "local count = 1\nreturn { count = count }\n");
+
+        assert_eq!(
+            session.evaluate("function double(n: integer): integer"),
+            Ok(Evaluation::Incomplete)
+        );
+        let Ok(Evaluation::Statements(_)) =
+            session.evaluate("function double(n: integer): integer\n    return n * 2\nend")
+        else {
+            panic!("Function did not compile");
+        };
+
+        let Ok(Evaluation::Expression { lua, typ }) = session.evaluate("double(count)") else {
+            panic!("Expected an expression");
+        };
+        assert_eq!(lua, "return double(count)\n");
+        assert_eq!(typ, "integer");
+
+        let errors = session
+            .evaluate("local name: string = count")
+            .expect_err("Mismatch passed");
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].span.line, errors[0].span.column), (1, 22));
+
+        // The failed input left no trace
+        let Ok(Evaluation::Statements(_)) = session.evaluate("local name = \"x\"") else {
+            panic!("Redeclaration after a failed input did not compile");
+        };
+    }
+}
@@ -0,0 +1,343 @@
+//! Build reports: a hook the build hands each module's diagnostics, type
+//! coverage, imports and emit size to, and a built-in reporter writing them
+//! as one shareable HTML file (`typedlua build --report html`)
+
+use crate::build::CompiledModule;
+use crate::diagnostics::{Diagnostic, DiagnosticLevel};
+use crate::errors::CompilationError;
+use crate::fs::FileSystem;
+use crate::typechecker::TypeCoverage;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// What a build learned about one module
+#[derive(Debug, Clone)]
+pub struct ModuleSummary {
+    pub path: PathBuf,
+    pub diagnostics: Vec<Diagnostic>,
+    pub source_bytes: usize,
+    /// Size of the emitted Lua, or `None` if the module failed to compile
+    pub lua_bytes: Option<usize>,
+    pub coverage: TypeCoverage,
+    /// Import paths as written, with the files they resolve to
+    pub imports: Vec<(String, Option<PathBuf>)>,
+}
+
+impl ModuleSummary {
+    pub fn new(
+        path: &Path,
+        source: &str,
+        compiled: Option<&CompiledModule>,
+        diagnostics: Vec<Diagnostic>,
+    ) -> Self {
+        ModuleSummary {
+            path: path.to_path_buf(),
+            diagnostics,
+            source_bytes: source.len(),
+            lua_bytes: compiled.map(|compiled| compiled.lua.len()),
+            coverage: compiled
+                .map(|compiled| compiled.coverage)
+                .unwrap_or_default(),
+            imports: compiled
+                .map(|compiled| compiled.imports.clone())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Receives a summary of each module as a build goes, for rendering
+/// reports the terminal output cannot carry. Modules arrive in the order
+/// their files were given.
+pub trait BuildReporter {
+    fn module(&mut self, summary: ModuleSummary);
+
+    /// Called once the last module is in
+    fn finish(&mut self) -> Result<(), CompilationError> {
+        Ok(())
+    }
+}
+
+/// Reporter that writes every module's summary to one self-contained HTML
+/// file: diagnostics, type coverage, module graph statistics and emit sizes
+pub struct HtmlReport {
+    output: PathBuf,
+    file_system: Arc<dyn FileSystem>,
+    modules: Vec<ModuleSummary>,
+}
+
+impl HtmlReport {
+    pub fn new(output: &Path, file_system: Arc<dyn FileSystem>) -> Self {
+        HtmlReport {
+            output: output.to_path_buf(),
+            file_system,
+            modules: Vec::new(),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut html = String::new();
+        html.push_str(concat!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n",
+            "<title>TypedLua build report</title>\n<style>\n",
+            "body { font-family: sans-serif; margin: 2em; }\n",
+            "table { border-collapse: collapse; margin-bottom: 2em; }\n",
+            "th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }\n",
+            "td.number { text-align: right; }\n",
+            ".error { color: #b00; }\n.warning { color: #a60; }\n",
+            "</style>\n</head>\n<body>\n<h1>TypedLua build report</h1>\n",
+        ));
+        self.render_summary(&mut html);
+        self.render_diagnostics(&mut html);
+        self.render_modules(&mut html);
+        self.render_graph(&mut html);
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    fn render_summary(&self, html: &mut String) {
+        let count = |level| {
+            self.modules
+                .iter()
+                .flat_map(|module| &module.diagnostics)
+                .filter(|diagnostic| diagnostic.level == level)
+                .count()
+        };
+        let failed = self
+            .modules
+            .iter()
+            .filter(|module| module.lua_bytes.is_none())
+            .count();
+        let mut coverage = TypeCoverage::default();
+        for module in &self.modules {
+            coverage.add(module.coverage);
+        }
+        let source_bytes: usize = self.modules.iter().map(|module| module.source_bytes).sum();
+        let lua_bytes: usize = self
+            .modules
+            .iter()
+            .filter_map(|module| module.lua_bytes)
+            .sum();
+
+        html.push_str("<h2>Summary</h2>\n<table>\n");
+        let rows = [
+            ("Modules", self.modules.len().to_string()),
+            ("Failed to compile", failed.to_string()),
+            ("Errors", count(DiagnosticLevel::Error).to_string()),
+            ("Warnings", count(DiagnosticLevel::Warning).to_string()),
+            (
+                "Type coverage",
+                format!(
+                    "{:.1}% ({} of {} expressions)",
+                    coverage.percent(),
+                    coverage.typed,
+                    coverage.typed + coverage.untyped
+                ),
+            ),
+            ("Source size", format!("{} bytes", source_bytes)),
+            ("Emitted Lua", format!("{} bytes", lua_bytes)),
+        ];
+        for (label, value) in rows {
+            let _ = writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", label, value);
+        }
+        html.push_str("</table>\n");
+    }
+
+    fn render_diagnostics(&self, html: &mut String) {
+        html.push_str("<h2>Diagnostics</h2>\n");
+        if self
+            .modules
+            .iter()
+            .all(|module| module.diagnostics.is_empty())
+        {
+            html.push_str("<p>None.</p>\n");
+            return;
+        }
+        html.push_str("<table>\n<tr><th>Location</th><th>Level</th><th>Message</th></tr>\n");
+        for module in &self.modules {
+            for diagnostic in &module.diagnostics {
+                let level = diagnostic.level.as_str();
+                let _ = writeln!(
+                    html,
+                    "<tr><td>{}:{}</td><td class=\"{}\">{}</td><td>{}</td></tr>",
+                    escape(&module.path.display().to_string()),
+                    diagnostic.span,
+                    level,
+                    level,
+                    escape(&diagnostic.message)
+                );
+            }
+        }
+        html.push_str("</table>\n");
+    }
+
+    fn render_modules(&self, html: &mut String) {
+        html.push_str(concat!(
+            "<h2>Modules</h2>\n<table>\n<tr><th>Module</th><th>Type coverage</th>",
+            "<th>Untyped expressions</th><th>Source bytes</th><th>Lua bytes</th></tr>\n",
+        ));
+        for module in &self.modules {
+            // A module that failed to compile was not fully checked
+            let (coverage, untyped, lua_bytes) = match module.lua_bytes {
+                Some(bytes) => (
+                    format!("{:.1}%", module.coverage.percent()),
+                    module.coverage.untyped.to_string(),
+                    bytes.to_string(),
+                ),
+                None => ("-".to_string(), "-".to_string(), "failed".to_string()),
+            };
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td></tr>",
+                escape(&module.path.display().to_string()),
+                coverage,
+                untyped,
+                module.source_bytes,
+                lua_bytes
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    /// Import counts across the modules of the build. Imports that resolve
+    /// to no file, such as C modules, are counted as external.
+    fn render_graph(&self, html: &mut String) {
+        let edges: usize = self.modules.iter().map(|module| module.imports.len()).sum();
+        let external = self
+            .modules
+            .iter()
+            .flat_map(|module| &module.imports)
+            .filter(|(_, path)| path.is_none())
+            .count();
+        let mut importers: HashMap<&Path, usize> = HashMap::new();
+        for path in self
+            .modules
+            .iter()
+            .flat_map(|module| &module.imports)
+            .filter_map(|(_, path)| path.as_deref())
+        {
+            *importers.entry(path).or_default() += 1;
+        }
+        let mut most_imported: Vec<(&Path, usize)> = importers.into_iter().collect();
+        most_imported.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+        html.push_str("<h2>Module graph</h2>\n<table>\n");
+        let _ = writeln!(html, "<tr><th>Imports</th><td>{}</td></tr>", edges);
+        let _ = writeln!(
+            html,
+            "<tr><th>Unresolved imports</th><td>{}</td></tr>",
+            external
+        );
+        html.push_str("</table>\n");
+        if most_imported.is_empty() {
+            return;
+        }
+        html.push_str("<table>\n<tr><th>Most imported</th><th>Importers</th></tr>\n");
+        for (path, count) in most_imported.iter().take(10) {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td class=\"number\">{}</td></tr>",
+                escape(&path.display().to_string()),
+                count
+            );
+        }
+        html.push_str("</table>\n");
+    }
+}
+
+impl BuildReporter for HtmlReport {
+    fn module(&mut self, summary: ModuleSummary) {
+        self.modules.push(summary);
+    }
+
+    fn finish(&mut self) -> Result<(), CompilationError> {
+        self.file_system
+            .write_if_changed(&self.output, &self.render())?;
+        Ok(())
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build::compile_file;
+    use crate::config::CompilerConfig;
+    use crate::diagnostics::{CollectingDiagnosticHandler, DiagnosticHandler};
+    use crate::fs::MockFileSystem;
+
+    #[test]
+    fn test_html_report_summarizes_modules() {
+        let mut file_system = MockFileSystem::new();
+        file_system.add_file("src/util.tl", "export const answer: number = 42");
+        file_system.add_file(
+            "src/main.tl",
+            "import { answer } from \"./util\"\nimport socket from \"socket\"\nlocal unused = 1\nprint(answer < 2, socket)",
+        );
+        let file_system: Arc<dyn FileSystem> = Arc::new(file_system);
+        let config = Arc::new(CompilerConfig::default());
+
+        let mut report = HtmlReport::new(Path::new("report.html"), file_system.clone());
+        for path in ["src/main.tl", "src/util.tl"] {
+            let path = Path::new(path);
+            let source = file_system.read_file(path).unwrap();
+            let handler = Arc::new(CollectingDiagnosticHandler::new());
+            let compiled = compile_file(
+                path,
+                &source,
+                file_system.clone(),
+                config.clone(),
+                handler.clone(),
+            )
+            .expect("Compilation failed");
+            report.module(ModuleSummary::new(
+                path,
+                &source,
+                Some(&compiled),
+                handler.get_diagnostics(),
+            ));
+        }
+        report.module(ModuleSummary::new(
+            Path::new("src/broken.tl"),
+            "const x: string = 1",
+            None,
+            vec![Diagnostic::error(
+                crate::span::Span::new(18, 19, 1, 19),
+                "Type mismatch: <string>",
+            )],
+        ));
+        report.finish().unwrap();
+
+        let html = file_system.read_file(Path::new("report.html")).unwrap();
+        assert!(
+            html.contains("<tr><th>Modules</th><td>3</td></tr>"),
+            "{}",
+            html
+        );
+        assert!(html.contains("<tr><th>Failed to compile</th><td>1</td></tr>"));
+        assert!(html.contains("<tr><th>Errors</th><td>1</td></tr>"));
+        assert!(html.contains(
+            "<tr><td>src/broken.tl:1:19</td><td class=\"error\">error</td><td>Type mismatch: &lt;string&gt;</td></tr>"
+        ));
+        // Both imports are read untyped
+        assert!(html.contains("<tr><td>src/main.tl</td><td class=\"number\">71.4%</td><td class=\"number\">2</td>"), "{}", html);
+        assert!(html.contains("<tr><td>src/broken.tl</td><td class=\"number\">-</td><td class=\"number\">-</td><td class=\"number\">19</td><td class=\"number\">failed</td></tr>"), "{}", html);
+        assert!(html.contains("<tr><th>Imports</th><td>2</td></tr>"));
+        assert!(html.contains("<tr><th>Unresolved imports</th><td>1</td></tr>"));
+        assert!(html.contains("<tr><td>src/util.tl</td><td class=\"number\">1</td></tr>"));
+    }
+}
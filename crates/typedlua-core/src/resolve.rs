@@ -0,0 +1,274 @@
+//! Finding the file an import path names, so imports of plain Lua modules
+//! can be typed from a hand-written declaration file (`.d.tl`). How a path
+//! names a module is a `ResolutionStrategy`, so engines whose `require`
+//! does not take file-relative paths can supply their own.
+
+use crate::fs::FileSystem;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// The file an import resolves to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolvedModule {
+    /// A TypedLua module (`.tl`), or a data module (`.data.tl`)
+    TypedLua(PathBuf),
+    /// A declaration file (`.d.tl`) describing a plain Lua module
+    Declaration(PathBuf),
+    /// A plain Lua module (`.lua`) without a declaration file
+    Lua(PathBuf),
+}
+
+impl ResolvedModule {
+    pub fn path(&self) -> &Path {
+        match self {
+            ResolvedModule::TypedLua(path)
+            | ResolvedModule::Declaration(path)
+            | ResolvedModule::Lua(path) => path,
+        }
+    }
+}
+
+/// A name an import path can continue with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleEntry {
+    /// The module name without its extension, or the directory name
+    pub name: String,
+    pub is_directory: bool,
+}
+
+/// Extensions `resolve` tries, longest first so `user.d.tl` is `user`
+const MODULE_EXTENSIONS: [&str; 4] = [".data.tl", ".d.tl", ".tl", ".lua"];
+
+/// How an import path names a module, such as Roblox instance paths,
+/// Defold's dotted paths or modules mounted at a virtual path in LÖVE.
+/// Plugins supply one with `Plugin::resolution`.
+pub trait ResolutionStrategy: Send + Sync {
+    /// The path of the module or directory `source` names when imported
+    /// from the file `importer`, without an extension. `None` when the
+    /// strategy does not recognize `source`.
+    fn module_path(
+        &self,
+        importer: &Path,
+        source: &str,
+        file_system: &dyn FileSystem,
+    ) -> Option<PathBuf>;
+}
+
+/// Import paths relative to the importing file's directory, as Lua's
+/// `require` reads them from the file system
+pub struct RelativeResolution;
+
+impl ResolutionStrategy for RelativeResolution {
+    fn module_path(
+        &self,
+        importer: &Path,
+        source: &str,
+        file_system: &dyn FileSystem,
+    ) -> Option<PathBuf> {
+        let dir = importer.parent().unwrap_or(Path::new(""));
+        Some(file_system.resolve_path(dir, source))
+    }
+}
+
+#[derive(Clone)]
+pub struct ModuleResolver {
+    file_system: Arc<dyn FileSystem>,
+    strategy: Arc<dyn ResolutionStrategy>,
+}
+
+impl ModuleResolver {
+    /// A resolver reading import paths relative to the importing file
+    pub fn new(file_system: Arc<dyn FileSystem>) -> Self {
+        Self::with_strategy(file_system, Arc::new(RelativeResolution))
+    }
+
+    pub fn with_strategy(
+        file_system: Arc<dyn FileSystem>,
+        strategy: Arc<dyn ResolutionStrategy>,
+    ) -> Self {
+        ModuleResolver {
+            file_system,
+            strategy,
+        }
+    }
+
+    pub fn file_system(&self) -> &Arc<dyn FileSystem> {
+        &self.file_system
+    }
+
+    /// Resolve `source` as imported from the file `importer`. The strategy
+    /// names the module's path, and a directory stands for its `init`
+    /// module. TypedLua source wins, then a data module, then a
+    /// declaration file, so a `.d.tl` next to a `.lua` file types it.
+    pub fn resolve(&self, importer: &Path, source: &str) -> Option<ResolvedModule> {
+        let module = self.import_path(importer, source)?;
+        [module.clone(), module.join("init")]
+            .into_iter()
+            .find_map(|base| self.resolve_base(&base))
+    }
+
+    /// The modules and subdirectories of the directory an import path
+    /// prefix such as `./models/` names, sorted by name. The importing file
+    /// itself and hidden entries are left out.
+    pub fn entries(&self, importer: &Path, directory: &str) -> Vec<ModuleEntry> {
+        let Some(dir) = self.import_path(importer, directory) else {
+            return Vec::new();
+        };
+        let Ok(listing) = self.file_system.read_dir(&dir) else {
+            return Vec::new();
+        };
+
+        let importer: PathBuf = importer
+            .components()
+            .filter(|component| !matches!(component, Component::CurDir))
+            .collect();
+        let mut entries: Vec<ModuleEntry> = Vec::new();
+        for entry in listing {
+            if entry.path == importer {
+                continue;
+            }
+            let Some(file_name) = entry.path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if file_name.starts_with('.') {
+                continue;
+            }
+            let name = if entry.is_dir {
+                file_name
+            } else {
+                match MODULE_EXTENSIONS
+                    .iter()
+                    .find_map(|extension| file_name.strip_suffix(extension))
+                {
+                    Some(name) => name,
+                    None => continue,
+                }
+            };
+            let entry = ModuleEntry {
+                name: name.to_string(),
+                is_directory: entry.is_dir,
+            };
+            if !entries.contains(&entry) {
+                entries.push(entry);
+            }
+        }
+        entries.sort_by(|a, b| {
+            a.name
+                .cmp(&b.name)
+                .then(a.is_directory.cmp(&b.is_directory))
+        });
+        entries
+    }
+
+    fn import_path(&self, importer: &Path, source: &str) -> Option<PathBuf> {
+        let path = self
+            .strategy
+            .module_path(importer, source, self.file_system.as_ref())?;
+        Some(
+            path.components()
+                .filter(|component| !matches!(component, Component::CurDir))
+                .collect(),
+        )
+    }
+
+    fn resolve_base(&self, base: &Path) -> Option<ResolvedModule> {
+        let candidate = |extension: &str| {
+            let path = PathBuf::from(format!("{}.{}", base.display(), extension));
+            self.file_system.exists(&path).then_some(path)
+        };
+        if let Some(path) = candidate("tl") {
+            return Some(ResolvedModule::TypedLua(path));
+        }
+        if let Some(path) = candidate("data.tl") {
+            return Some(ResolvedModule::TypedLua(path));
+        }
+        if let Some(path) = candidate("d.tl") {
+            return Some(ResolvedModule::Declaration(path));
+        }
+        candidate("lua").map(ResolvedModule::Lua)
+    }
+}
+
+/// Whether a file is a data module (`.data.tl`), which holds only types and
+/// constant tables and compiles to `name.lua` so it is required by its name
+pub fn is_data_module(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(".data.tl")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::MockFileSystem;
+
+    #[test]
+    fn test_resolve_prefers_source_then_declarations() {
+        let mut file_system = MockFileSystem::new();
+        file_system.add_file("src/user.tl", "");
+        file_system.add_file("src/user.d.tl", "");
+        file_system.add_file("src/levels.data.tl", "");
+        file_system.add_file("src/socket.lua", "");
+        file_system.add_file("src/socket.d.tl", "");
+        file_system.add_file("src/legacy.lua", "");
+        file_system.add_file("src/lib/json/init.lua", "");
+        let resolver = ModuleResolver::new(Arc::new(file_system));
+        let importer = Path::new("src/main.tl");
+
+        assert_eq!(
+            resolver.resolve(importer, "./user"),
+            Some(ResolvedModule::TypedLua(PathBuf::from("src/user.tl")))
+        );
+        assert_eq!(
+            resolver.resolve(importer, "./levels"),
+            Some(ResolvedModule::TypedLua(PathBuf::from(
+                "src/levels.data.tl"
+            )))
+        );
+        assert_eq!(
+            resolver.resolve(importer, "./socket"),
+            Some(ResolvedModule::Declaration(PathBuf::from(
+                "src/socket.d.tl"
+            )))
+        );
+        assert_eq!(
+            resolver.resolve(importer, "./legacy"),
+            Some(ResolvedModule::Lua(PathBuf::from("src/legacy.lua")))
+        );
+        assert_eq!(
+            resolver.resolve(importer, "lib/json"),
+            Some(ResolvedModule::Lua(PathBuf::from("src/lib/json/init.lua")))
+        );
+        assert_eq!(resolver.resolve(importer, "./missing"), None);
+    }
+
+    /// Dotted paths from the project root, as Defold's `require` takes them
+    struct DottedResolution;
+
+    impl ResolutionStrategy for DottedResolution {
+        fn module_path(&self, _: &Path, source: &str, _: &dyn FileSystem) -> Option<PathBuf> {
+            (!source.starts_with('.')).then(|| source.split('.').collect())
+        }
+    }
+
+    #[test]
+    fn test_resolve_with_strategy() {
+        let mut file_system = MockFileSystem::new();
+        file_system.add_file("main/player.tl", "");
+        file_system.add_file("main/util/init.lua", "");
+        file_system.add_file("main/util/init.d.tl", "");
+        let resolver =
+            ModuleResolver::with_strategy(Arc::new(file_system), Arc::new(DottedResolution));
+        let importer = Path::new("main/game/level.tl");
+
+        assert_eq!(
+            resolver.resolve(importer, "main.player"),
+            Some(ResolvedModule::TypedLua(PathBuf::from("main/player.tl")))
+        );
+        assert_eq!(
+            resolver.resolve(importer, "main.util"),
+            Some(ResolvedModule::Declaration(PathBuf::from(
+                "main/util/init.d.tl"
+            )))
+        );
+        assert_eq!(resolver.resolve(importer, "./player"), None);
+    }
+}
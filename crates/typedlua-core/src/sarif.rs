@@ -0,0 +1,250 @@
+//! Diagnostics as a SARIF 2.1.0 log (`--diagnostics-format sarif`), for
+//! GitHub code scanning and other dashboards that ingest static analysis
+//! results
+
+use crate::config::StrictLevel;
+use crate::diagnostics::{Diagnostic, DiagnosticLevel};
+use crate::explain::explain;
+use crate::lint::Linter;
+use crate::span::Span;
+use serde_json::{json, Value};
+use std::path::{Component, Path};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Gathers the diagnostics of a run, and the rules their codes name, into
+/// one SARIF log
+#[derive(Debug, Default)]
+pub struct SarifReport {
+    rules: Vec<Value>,
+    results: Vec<Value>,
+}
+
+impl SarifReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Describe a rule results can name by its code
+    pub fn add_rule(&mut self, code: &str, description: &str, level: StrictLevel) {
+        let level = match level {
+            StrictLevel::Off => "none",
+            StrictLevel::Warning => "warning",
+            StrictLevel::Error => "error",
+        };
+        let mut rule = json!({
+            "id": code,
+            "defaultConfiguration": {
+                "level": level,
+                "enabled": level != "none",
+            },
+        });
+        if !description.is_empty() {
+            rule["shortDescription"] = json!({ "text": description });
+        }
+        self.rules.push(rule);
+    }
+
+    /// Describe every rule of a linter, at the severity it runs at
+    pub fn add_lint_rules(&mut self, linter: &Linter) {
+        for (rule, level) in linter.rules() {
+            self.add_rule(rule.name(), rule.description(), level);
+        }
+    }
+
+    /// Add the diagnostics reported for the file at `path`
+    pub fn add_diagnostics(&mut self, path: &Path, diagnostics: &[Diagnostic]) {
+        let uri = uri(path);
+        for diagnostic in diagnostics {
+            let level = match diagnostic.level {
+                DiagnosticLevel::Error => "error",
+                DiagnosticLevel::Warning => "warning",
+                DiagnosticLevel::Info => "note",
+            };
+            let mut result = json!({
+                "level": level,
+                "message": { "text": diagnostic.message },
+                "locations": [location(&uri, diagnostic.span, None)],
+            });
+            if let Some(code) = &diagnostic.code {
+                self.add_explained_rule(code, diagnostic.level);
+                result["ruleId"] = json!(code);
+                let index = self.rules.iter().position(|rule| rule["id"] == *code);
+                if let Some(index) = index {
+                    result["ruleIndex"] = json!(index);
+                }
+            }
+            if !diagnostic.related.is_empty() {
+                let related: Vec<Value> = diagnostic
+                    .related
+                    .iter()
+                    .map(|related| {
+                        let uri = related.file.as_deref().map_or(uri.clone(), self::uri);
+                        location(&uri, related.span, Some(&related.message))
+                    })
+                    .collect();
+                result["relatedLocations"] = json!(related);
+            }
+            if !diagnostic.fixes.is_empty() {
+                let fixes: Vec<Value> = diagnostic
+                    .fixes
+                    .iter()
+                    .map(|fix| {
+                        let replacements: Vec<Value> = fix
+                            .replacements
+                            .iter()
+                            .map(|replacement| {
+                                json!({
+                                    "deletedRegion": region(replacement.span),
+                                    "insertedContent": { "text": replacement.text },
+                                })
+                            })
+                            .collect();
+                        json!({
+                            "description": { "text": fix.message },
+                            "artifactChanges": [{
+                                "artifactLocation": { "uri": uri },
+                                "replacements": replacements,
+                            }],
+                        })
+                    })
+                    .collect();
+                result["fixes"] = json!(fixes);
+            }
+            self.results.push(result);
+        }
+    }
+
+    /// Describe a compiler diagnostic code the first time a result names
+    /// it, with its explanation as the rule's help
+    fn add_explained_rule(&mut self, code: &str, level: DiagnosticLevel) {
+        if self.rules.iter().any(|rule| rule["id"] == code) {
+            return;
+        }
+        let Some(explanation) = explain(code) else {
+            return;
+        };
+        let level = match level {
+            DiagnosticLevel::Error => StrictLevel::Error,
+            DiagnosticLevel::Warning | DiagnosticLevel::Info => StrictLevel::Warning,
+        };
+        self.add_rule(code, explanation.title, level);
+        if let Some(rule) = self.rules.last_mut() {
+            rule["help"] = json!({ "text": explanation.text, "markdown": explanation.text });
+        }
+    }
+
+    /// The log, with one run holding every result added
+    pub fn to_json(&self) -> String {
+        let log = json!({
+            "$schema": SCHEMA,
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "typedlua",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": self.rules,
+                    },
+                },
+                // Columns and offsets count characters, not UTF-16 units
+                "columnKind": "unicodeCodePoints",
+                "results": self.results,
+            }],
+        });
+        serde_json::to_string_pretty(&log).expect("SARIF values serialize")
+    }
+}
+
+fn location(uri: &str, span: Span, message: Option<&str>) -> Value {
+    let mut location = json!({
+        "physicalLocation": {
+            "artifactLocation": { "uri": uri },
+            "region": region(span),
+        },
+    });
+    if let Some(message) = message {
+        location["message"] = json!({ "text": message });
+    }
+    location
+}
+
+fn region(span: Span) -> Value {
+    json!({
+        "startLine": span.line,
+        "startColumn": span.column,
+        "charOffset": span.start,
+        "charLength": span.len(),
+    })
+}
+
+/// The file's URI, with `/` separators on every platform: relative, as
+/// code scanning expects of paths within the repository, unless the path
+/// is absolute
+fn uri(path: &Path) -> String {
+    let parts: Vec<String> = path
+        .components()
+        .filter_map(|component| match component {
+            Component::RootDir | Component::CurDir => None,
+            component => Some(component.as_os_str().to_string_lossy().into_owned()),
+        })
+        .collect();
+    if path.has_root() {
+        format!("file:///{}", parts.join("/"))
+    } else {
+        parts.join("/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LintOptions;
+    use crate::diagnostics::{CollectingDiagnosticHandler, DiagnosticHandler};
+    use crate::lint::lint_source;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_sarif_log_has_rules_results_and_fixes() {
+        let linter = Linter::new(&LintOptions::default());
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        lint_source("local count = 0\n", &linter, handler.clone()).expect("Lint failed");
+
+        let mut report = SarifReport::new();
+        report.add_lint_rules(&linter);
+        report.add_diagnostics(Path::new("./src/main.tl"), &handler.get_diagnostics());
+        report.add_diagnostics(
+            Path::new("src/util.tl"),
+            &[Diagnostic::error(Span::new(0, 5, 1, 1), "Type mismatch").with_code("TL2001")],
+        );
+        let log: Value = serde_json::from_str(&report.to_json()).unwrap();
+
+        assert_eq!(log["version"], "2.1.0");
+        let run = &log["runs"][0];
+        let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+        assert_eq!(rules[0]["id"], "unused-local");
+        assert_eq!(rules[0]["defaultConfiguration"]["level"], "warning");
+
+        let unused = &run["results"][0];
+        assert_eq!(unused["ruleId"], "unused-local");
+        assert_eq!(unused["ruleIndex"], 0);
+        assert_eq!(unused["level"], "warning");
+        let location = &unused["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/main.tl");
+        assert_eq!(location["region"]["startColumn"], 7);
+        assert_eq!(location["region"]["charLength"], 5);
+        let change = &unused["fixes"][0]["artifactChanges"][0];
+        assert_eq!(change["replacements"][0]["insertedContent"]["text"], "_");
+
+        let mismatch = &run["results"][1];
+        assert_eq!(mismatch["level"], "error");
+        assert_eq!(mismatch["ruleId"], "TL2001");
+        let rule =
+            &run["tool"]["driver"]["rules"][mismatch["ruleIndex"].as_u64().unwrap() as usize];
+        assert_eq!(rule["shortDescription"]["text"], "Type mismatch");
+        assert!(rule["help"]["markdown"]
+            .as_str()
+            .unwrap()
+            .contains("```lua"));
+    }
+}
@@ -0,0 +1,309 @@
+//! Tree shaking: finding the top-level functions, types and enum members a
+//! module can leave out of its Lua because nothing that is emitted refers
+//! to them
+
+use crate::ast::attributes::has_attribute;
+use crate::ast::statement::{ExportKind, Statement};
+use crate::span::Span;
+use crate::typechecker::NameUsage;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// The exports of a module that something uses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UsedExports {
+    /// Every export, as for a module required from outside
+    All,
+    /// Only these, as for a module of a bundle whose importers name them
+    Only(HashSet<String>),
+}
+
+impl UsedExports {
+    fn includes(&self, name: &str) -> bool {
+        match self {
+            UsedExports::All => true,
+            UsedExports::Only(names) => names.contains(name),
+        }
+    }
+
+    /// Add the exports an import uses: the ones it names, or all of them
+    /// for an import of the whole module
+    pub fn add(&mut self, names: Option<Vec<String>>) {
+        match (self, names) {
+            (UsedExports::Only(used), Some(names)) => used.extend(names),
+            (used, None) => *used = UsedExports::All,
+            (UsedExports::All, Some(_)) => {}
+        }
+    }
+}
+
+/// Why a declaration was left out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RemovalReason {
+    /// Not exported, and nothing refers to it
+    Unreferenced,
+    /// Only declarations that were left out themselves refer to it
+    OnlyUsedByRemoved,
+    /// Exported, but no module of the bundle imports it
+    ExportUnused,
+}
+
+impl fmt::Display for RemovalReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RemovalReason::Unreferenced => write!(f, "never referenced"),
+            RemovalReason::OnlyUsedByRemoved => write!(f, "only referenced by removed code"),
+            RemovalReason::ExportUnused => write!(f, "exported but never imported"),
+        }
+    }
+}
+
+/// A declaration left out of the emitted Lua
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Removal {
+    /// `function`, `interface`, `type`, `enum` or `enum member`
+    pub kind: String,
+    /// The declaration's name; enum members are written `Enum.Member`
+    pub name: String,
+    pub line: usize,
+    pub column: usize,
+    pub reason: RemovalReason,
+}
+
+/// What tree shaking leaves out of a module
+#[derive(Debug, Clone, Default)]
+pub struct TreeShaking {
+    /// Top-level declarations to leave out, by span
+    pub(crate) statements: HashSet<Span>,
+    /// Members to leave out of each enum, by the enum's span
+    pub(crate) enum_members: HashMap<Span, HashSet<String>>,
+    /// Exported names to leave out of the module table
+    pub(crate) exports: HashSet<String>,
+    pub removals: Vec<Removal>,
+}
+
+/// A top-level declaration tree shaking may leave out
+struct Candidate<'a> {
+    statement: &'a Statement,
+    name: &'a str,
+    kind: &'static str,
+    exported: bool,
+}
+
+/// Find what a module can leave out. Exports in `used` and every statement
+/// that is not a candidate declaration are live; a function, type or enum
+/// is kept when something live refers to it, and an enum member when it is
+/// read by name or its enum is used as a whole. Scoping is ignored, so a
+/// local that shadows a declaration keeps it.
+pub fn shake(statements: &[Statement], used: &UsedExports) -> TreeShaking {
+    let mut candidates = Vec::new();
+    let mut live = NameUsage::default();
+    let mut shaking = TreeShaking::default();
+    for statement in statements {
+        let (decl, exported) = match statement {
+            Statement::Export(export) => match &export.kind {
+                ExportKind::Declaration(decl) => (decl.as_ref(), true),
+                ExportKind::Named(specifiers) => {
+                    for specifier in specifiers {
+                        let exported = specifier.exported.as_ref().unwrap_or(&specifier.local);
+                        if used.includes(&exported.node) {
+                            live.values
+                                .insert(specifier.local.node.clone(), specifier.local.span);
+                            live.whole_values.insert(specifier.local.node.clone());
+                        } else {
+                            shaking.exports.insert(exported.node.clone());
+                        }
+                    }
+                    continue;
+                }
+                _ => (statement, false),
+            },
+            statement => (statement, false),
+        };
+        match candidate(decl, exported) {
+            Some(candidate) if !(exported && used.includes(candidate.name)) => {
+                candidates.push(candidate)
+            }
+            // Importers may read any member of an exported enum
+            Some(candidate) => {
+                live.values.insert(candidate.name.to_string(), decl.span());
+                live.whole_values.insert(candidate.name.to_string());
+                candidates.push(candidate);
+            }
+            None => live.statement(statement),
+        }
+    }
+
+    // Follow references out from what is live
+    let references: Vec<NameUsage> = candidates
+        .iter()
+        .map(|candidate| {
+            let mut usage = NameUsage::default();
+            usage.statement(candidate.statement);
+            usage
+        })
+        .collect();
+    let mut kept: HashSet<&str> = HashSet::new();
+    let mut pending: Vec<&NameUsage> = vec![&live];
+    while let Some(usage) = pending.pop() {
+        for (candidate, references) in candidates.iter().zip(&references) {
+            if !kept.contains(candidate.name) && refers_to(usage, candidate.name) {
+                kept.insert(candidate.name);
+                pending.push(references);
+            }
+        }
+    }
+
+    let live_usages: Vec<&NameUsage> = std::iter::once(&live)
+        .chain(
+            candidates
+                .iter()
+                .zip(&references)
+                .filter(|(candidate, _)| kept.contains(candidate.name))
+                .map(|(_, references)| references),
+        )
+        .collect();
+    for candidate in &candidates {
+        let span = candidate.statement.span();
+        if kept.contains(candidate.name) {
+            if let Statement::Enum(decl) = candidate.statement {
+                let name = &decl.name.node;
+                if live_usages
+                    .iter()
+                    .any(|usage| usage.whole_values.contains(name))
+                {
+                    continue;
+                }
+                for member in &decl.members {
+                    let key = (name.clone(), member.name.node.clone());
+                    if live_usages.iter().any(|usage| usage.members.contains(&key)) {
+                        continue;
+                    }
+                    shaking
+                        .enum_members
+                        .entry(span)
+                        .or_default()
+                        .insert(member.name.node.clone());
+                    shaking.removals.push(Removal {
+                        kind: "enum member".to_string(),
+                        name: format!("{}.{}", name, member.name.node),
+                        line: member.span.line,
+                        column: member.span.column,
+                        reason: RemovalReason::Unreferenced,
+                    });
+                }
+            }
+            continue;
+        }
+
+        let reason =
+            if candidate.exported {
+                RemovalReason::ExportUnused
+            } else if candidates.iter().zip(&references).any(|(other, usage)| {
+                other.name != candidate.name && refers_to(usage, candidate.name)
+            }) {
+                RemovalReason::OnlyUsedByRemoved
+            } else {
+                RemovalReason::Unreferenced
+            };
+        shaking.statements.insert(span);
+        if candidate.exported {
+            shaking.exports.insert(candidate.name.to_string());
+        }
+        shaking.removals.push(Removal {
+            kind: candidate.kind.to_string(),
+            name: candidate.name.to_string(),
+            line: span.line,
+            column: span.column,
+            reason,
+        });
+    }
+    shaking
+}
+
+fn candidate(statement: &Statement, exported: bool) -> Option<Candidate<'_>> {
+    let (name, kind) = match statement {
+        Statement::Function(func) => (&func.name, "function"),
+        // `@schema` interfaces emit a validator, which may be read at runtime
+        Statement::Interface(interface) if !has_attribute(&interface.decorators, "schema") => {
+            (&interface.name, "interface")
+        }
+        Statement::TypeAlias(alias) => (&alias.name, "type"),
+        // Const enums emit nothing to remove
+        Statement::Enum(decl) if !decl.is_const => (&decl.name, "enum"),
+        _ => return None,
+    };
+    Some(Candidate {
+        statement,
+        name: &name.node,
+        kind,
+        exported,
+    })
+}
+
+fn refers_to(usage: &NameUsage, name: &str) -> bool {
+    usage.values.contains_key(name) || usage.types.contains(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::CollectingDiagnosticHandler;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use std::sync::Arc;
+
+    fn shake_source(source: &str, used: &UsedExports) -> Vec<String> {
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let tokens = Lexer::new(source, handler.clone()).tokenize().unwrap();
+        let program = Parser::new(tokens, handler).parse().unwrap();
+        shake(&program.statements, used)
+            .removals
+            .iter()
+            .map(|removal| format!("{} {}: {}", removal.kind, removal.name, removal.reason))
+            .collect()
+    }
+
+    #[test]
+    fn test_shake_follows_references_from_live_code() {
+        let source = r#"
+            enum Color { Red, Green, Blue }
+            enum Size { Small, Large }
+            type Unused = string
+            interface Point { x: number }
+            function helper(p: Point): number
+                return p.x
+            end
+            function unused(): number
+                return orphan()
+            end
+            function orphan(): number
+                return 1
+            end
+            export function area(): number
+                print(Color.Red, Size[1])
+                return helper({ x = 1 })
+            end
+            export function extra(): void end
+        "#;
+        assert_eq!(
+            shake_source(source, &UsedExports::All),
+            vec![
+                "enum member Color.Green: never referenced",
+                "enum member Color.Blue: never referenced",
+                "type Unused: never referenced",
+                "function unused: never referenced",
+                "function orphan: only referenced by removed code",
+            ]
+        );
+
+        let used = UsedExports::Only(HashSet::from(["area".to_string()]));
+        let removals = shake_source(source, &used);
+        assert_eq!(
+            removals.last().map(String::as_str),
+            Some("function extra: exported but never imported")
+        );
+    }
+}
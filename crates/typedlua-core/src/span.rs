@@ -1,7 +1,8 @@
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// Represents a location in source code with line and column information
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Span {
     /// Starting byte offset in the source
     pub start: usize,
@@ -44,13 +45,19 @@ impl Span {
         self.start == self.end
     }
 
-    /// Merge two spans into one that covers both
+    /// Merge two spans into one that covers both, starting where the
+    /// earlier one does
     pub fn merge(&self, other: &Span) -> Span {
+        let first = if self.start <= other.start {
+            self
+        } else {
+            other
+        };
         Span {
-            start: self.start.min(other.start),
+            start: first.start,
             end: self.end.max(other.end),
-            line: self.line.min(other.line),
-            column: self.column.min(other.column),
+            line: first.line,
+            column: first.column,
         }
     }
 
@@ -93,6 +100,12 @@ mod tests {
 
         assert_eq!(merged.start, 0);
         assert_eq!(merged.end, 15);
+
+        // The merged span starts at the earlier span's position
+        let indented = Span::new(20, 25, 3, 5);
+        let next_line = Span::new(30, 33, 4, 1);
+        assert_eq!(indented.merge(&next_line), Span::new(20, 33, 3, 5));
+        assert_eq!(next_line.merge(&indented), Span::new(20, 33, 3, 5));
     }
 
     #[test]
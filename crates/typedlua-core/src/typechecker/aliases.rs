@@ -0,0 +1,118 @@
+use super::{unknown_type, TypeChecker};
+use crate::ast::expression::{Expression, ExpressionKind};
+use crate::ast::statement::TypeAliasDeclaration;
+use crate::ast::types::{Type, TypeKind};
+use crate::errors::TypeCheckError;
+
+/// Aliases referring to aliases are followed this far, which also stops
+/// cycles such as `type A = B` and `type B = A`
+const MAX_ALIAS_DEPTH: usize = 16;
+
+impl TypeChecker {
+    /// Make a non-generic alias usable before its declaration. A `typeof`
+    /// in it is only resolved once the declaration itself is checked.
+    pub(super) fn collect_type_alias(&mut self, alias: &TypeAliasDeclaration) {
+        if alias.type_parameters.is_none() {
+            self.type_env
+                .register_alias(&alias.name.node, alias.type_annotation.clone());
+        }
+    }
+
+    /// Record what an alias stands for, with the values any `typeof` in it
+    /// names looked up where the alias is declared
+    pub(super) fn check_type_alias(&mut self, alias: &TypeAliasDeclaration) {
+        if alias.type_parameters.is_none() {
+            let resolved = self.resolve_type_queries(&alias.type_annotation);
+            self.type_env.register_alias(&alias.name.node, resolved);
+        }
+    }
+
+    /// A written annotation with `typeof` queries and aliases replaced by
+    /// the types they stand for
+    pub(super) fn resolve_annotation(&mut self, typ: &Type) -> Type {
+        self.check_type_argument_count(typ);
+        let typ = self.resolve_type_queries(typ);
+        self.expand_aliases(&typ, 0)
+    }
+
+    fn resolve_type_queries(&mut self, typ: &Type) -> Type {
+        let kind = match &typ.kind {
+            TypeKind::TypeQuery(expr) => {
+                // Unlike in expressions, a `typeof` must name a known value
+                let root = query_root(expr);
+                if let ExpressionKind::Identifier(name) = &root.kind {
+                    if self.symbols.lookup(name).is_none() {
+                        self.report_error(
+                            root.span,
+                            TypeCheckError::UndefinedVariable(name.clone()),
+                        );
+                        return unknown_type(typ.span);
+                    }
+                }
+                let queried = self.check_expression(expr);
+                return Type::new(queried.kind, typ.span);
+            }
+            TypeKind::Nullable(inner) => {
+                TypeKind::Nullable(Box::new(self.resolve_type_queries(inner)))
+            }
+            TypeKind::Array(inner) => TypeKind::Array(Box::new(self.resolve_type_queries(inner))),
+            TypeKind::Parenthesized(inner) => {
+                TypeKind::Parenthesized(Box::new(self.resolve_type_queries(inner)))
+            }
+            TypeKind::Union(types) => {
+                TypeKind::Union(types.iter().map(|t| self.resolve_type_queries(t)).collect())
+            }
+            _ => return typ.clone(),
+        };
+        Type::new(kind, typ.span)
+    }
+
+    pub(super) fn expand_aliases(&self, typ: &Type, depth: usize) -> Type {
+        if depth > MAX_ALIAS_DEPTH {
+            return typ.clone();
+        }
+        let kind = match &typ.kind {
+            TypeKind::Reference(reference) if reference.type_arguments.is_none() => {
+                match self.type_env.get_alias(&reference.name.node) {
+                    Some(aliased) => {
+                        let expanded = self.expand_aliases(aliased, depth + 1);
+                        return Type::new(expanded.kind, typ.span);
+                    }
+                    None => return typ.clone(),
+                }
+            }
+            TypeKind::Nullable(inner) => {
+                TypeKind::Nullable(Box::new(self.expand_aliases(inner, depth)))
+            }
+            TypeKind::Array(inner) => TypeKind::Array(Box::new(self.expand_aliases(inner, depth))),
+            TypeKind::Parenthesized(inner) => {
+                TypeKind::Parenthesized(Box::new(self.expand_aliases(inner, depth)))
+            }
+            TypeKind::Union(types) => TypeKind::Union(
+                types
+                    .iter()
+                    .map(|t| self.expand_aliases(t, depth))
+                    .collect(),
+            ),
+            TypeKind::Tuple(types) => TypeKind::Tuple(
+                types
+                    .iter()
+                    .map(|t| self.expand_aliases(t, depth))
+                    .collect(),
+            ),
+            TypeKind::Variadic(inner) => {
+                TypeKind::Variadic(Box::new(self.expand_aliases(inner, depth)))
+            }
+            _ => return typ.clone(),
+        };
+        Type::new(kind, typ.span)
+    }
+}
+
+/// The variable a `typeof a.b.c` query starts from
+fn query_root(expr: &Expression) -> &Expression {
+    match &expr.kind {
+        ExpressionKind::Member(object, _) => query_root(object),
+        _ => expr,
+    }
+}
@@ -0,0 +1,83 @@
+use super::flow::Narrowing;
+use super::TypeChecker;
+use crate::ast::expression::{Argument, Expression, ExpressionKind};
+use crate::ast::pattern::Pattern;
+use crate::ast::statement::Parameter;
+use crate::ast::types::{AssertsPredicate, FunctionType, Type, TypeKind};
+use crate::errors::TypeCheckError;
+
+impl TypeChecker {
+    /// An `asserts x` return type must name one of the function's parameters
+    pub(super) fn check_assertion_signature(
+        &mut self,
+        parameters: &[Parameter],
+        return_type: Option<&Type>,
+    ) {
+        let Some(predicate) = return_type.and_then(asserts_predicate) else {
+            return;
+        };
+        if parameter_index(parameters, predicate).is_none() {
+            self.report_error(
+                predicate.parameter.span,
+                TypeCheckError::UnknownAssertedParameter(predicate.parameter.node.clone()),
+            );
+        }
+    }
+
+    /// What a call proves about its arguments if it returns normally. The
+    /// global `assert` proves its first argument truthy; an assertion
+    /// function proves the predicate in its signature.
+    pub(super) fn call_assertion(
+        &self,
+        callee: &Expression,
+        callee_type: &Type,
+        args: &[Argument],
+    ) -> Option<Narrowing> {
+        let Some((func, predicate)) = assertion_signature(callee_type) else {
+            let first = &args.first()?.value;
+            return self
+                .is_builtin_assert(callee)
+                .then(|| self.condition_narrowing(first, true));
+        };
+        let index = parameter_index(&func.parameters, predicate)?;
+        let argument = &args.get(index)?.value;
+        match &predicate.type_predicate {
+            Some(typ) => match &argument.kind {
+                ExpressionKind::Identifier(name) => {
+                    Some(Narrowing::from([(name.clone(), (**typ).clone())]))
+                }
+                _ => None,
+            },
+            None => Some(self.condition_narrowing(argument, true)),
+        }
+    }
+
+    /// `assert` refers to the Lua builtin unless a local shadows it
+    fn is_builtin_assert(&self, callee: &Expression) -> bool {
+        matches!(&callee.kind, ExpressionKind::Identifier(name) if name == "assert")
+            && self.symbols.lookup("assert").is_none()
+    }
+}
+
+/// The signature of an assertion function and the predicate it returns
+fn assertion_signature(typ: &Type) -> Option<(&FunctionType, &AssertsPredicate)> {
+    match &typ.kind {
+        TypeKind::Function(func) => Some((func, asserts_predicate(&func.return_type)?)),
+        TypeKind::Parenthesized(inner) => assertion_signature(inner),
+        _ => None,
+    }
+}
+
+pub(super) fn asserts_predicate(typ: &Type) -> Option<&AssertsPredicate> {
+    match &typ.kind {
+        TypeKind::Asserts(predicate) => Some(predicate),
+        TypeKind::Parenthesized(inner) => asserts_predicate(inner),
+        _ => None,
+    }
+}
+
+fn parameter_index(parameters: &[Parameter], predicate: &AssertsPredicate) -> Option<usize> {
+    parameters.iter().position(|param| {
+        matches!(&param.pattern, Pattern::Identifier(name) if name.node == predicate.parameter.node)
+    })
+}
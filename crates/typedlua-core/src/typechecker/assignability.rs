@@ -0,0 +1,446 @@
+use super::readonly::readonly_inner;
+use super::{is_nil, MemberLookup, TypeChecker};
+use crate::ast::expression::Literal;
+use crate::ast::statement::InterfaceMember;
+use crate::ast::types::{ObjectType, ObjectTypeMember, PrimitiveType, Type, TypeKind};
+use crate::diagnostics::RelatedSpan;
+use crate::errors::TypeCheckError;
+use crate::span::Span;
+
+impl TypeChecker {
+    /// Report a mismatch when `source` cannot be used where `target` is
+    /// expected, pointing also at what made `target` expected. Object
+    /// literals are checked field by field so the error points at the
+    /// offending property.
+    pub(super) fn check_assignable(
+        &mut self,
+        source: &Type,
+        target: &Type,
+        span: Span,
+        because: Option<RelatedSpan>,
+    ) {
+        if let (TypeKind::Object(object), Some(interface)) =
+            (&source.kind, self.interface_name(target))
+        {
+            for member in &object.members {
+                let ObjectTypeMember::Property(prop) = member else {
+                    continue;
+                };
+                let MemberLookup::Found(expected) =
+                    self.lookup_interface_member(&interface, &prop.name.node)
+                else {
+                    continue;
+                };
+                if !self.is_assignable(&prop.type_annotation, &expected) {
+                    self.trace_mismatch(prop.span, &prop.type_annotation, &expected);
+                    self.report_error_with_related(
+                        prop.span,
+                        TypeCheckError::TypeMismatch {
+                            expected: expected.to_string(),
+                            actual: prop.type_annotation.to_string(),
+                        },
+                        because.clone().into_iter().collect(),
+                    );
+                }
+            }
+            if self.config.compiler_options.strict_initialization && partial_inner(target).is_none()
+            {
+                let missing = self.missing_properties(&interface, object);
+                if !missing.is_empty() {
+                    self.report_error(
+                        span,
+                        TypeCheckError::MissingProperties {
+                            type_name: interface,
+                            properties: missing.join(", "),
+                        },
+                    );
+                }
+            }
+            return;
+        }
+
+        if !self.is_assignable(source, target) {
+            self.trace_mismatch(span, source, target);
+            self.report_error_with_related(
+                span,
+                TypeCheckError::TypeMismatch {
+                    expected: target.to_string(),
+                    actual: source.to_string(),
+                },
+                because.into_iter().collect(),
+            );
+        }
+    }
+
+    /// The declared interface a type refers to, if any
+    pub(super) fn interface_name(&self, typ: &Type) -> Option<String> {
+        match &typ.kind {
+            TypeKind::Reference(reference)
+                if self.type_env.get_interface(&reference.name.node).is_some() =>
+            {
+                Some(reference.name.node.clone())
+            }
+            TypeKind::Parenthesized(inner) | TypeKind::Nullable(inner) => {
+                self.interface_name(inner)
+            }
+            _ => self.interface_name(&readonly_inner(typ).or_else(|| partial_inner(typ))?),
+        }
+    }
+
+    /// Required properties of an interface that an object literal leaves
+    /// out. Literals with spreads or computed keys may provide anything.
+    fn missing_properties(&self, interface: &str, object: &ObjectType) -> Vec<String> {
+        if object
+            .members
+            .iter()
+            .any(|member| matches!(member, ObjectTypeMember::Index(_)))
+        {
+            return Vec::new();
+        }
+
+        let (chain, _) = self.type_env.interface_chain(interface);
+        let mut missing: Vec<String> = Vec::new();
+        for prop in chain.iter().flat_map(|decl| &decl.members) {
+            let InterfaceMember::Property(prop) = prop else {
+                continue;
+            };
+            let name = &prop.name.node;
+            let provided = object.members.iter().any(
+                |member| matches!(member, ObjectTypeMember::Property(p) if &p.name.node == name),
+            );
+            if !prop.is_optional && !provided && !missing.contains(name) {
+                missing.push(name.clone());
+            }
+        }
+        missing
+    }
+
+    /// Whether a type names a declared interface or class
+    pub(super) fn is_declared_type(&self, typ: &Type) -> bool {
+        match &typ.kind {
+            TypeKind::Reference(reference) => {
+                let name = &reference.name.node;
+                self.type_env.get_interface(name).is_some()
+                    || self.type_env.get_class(name).is_some()
+            }
+            _ => false,
+        }
+    }
+
+    /// Structural assignability. Deliberately permissive: combinations the
+    /// checker cannot reason about yet are accepted.
+    pub(super) fn is_assignable(&self, source: &Type, target: &Type) -> bool {
+        use TypeKind::*;
+
+        match (&source.kind, &target.kind) {
+            (Parenthesized(inner), _) => self.is_assignable(inner, target),
+            (_, Parenthesized(inner)) => self.is_assignable(source, inner),
+            (Primitive(PrimitiveType::Unknown), _) | (_, Primitive(PrimitiveType::Unknown)) => true,
+            (Primitive(PrimitiveType::Never), _) => true,
+            (Union(types), _) => types.iter().all(|t| self.is_assignable(t, target)),
+            (_, Union(types)) => types.iter().any(|t| self.is_assignable(source, t)),
+            (Nullable(inner), _) => self.accepts_nil(target) && self.is_assignable(inner, target),
+            (_, Nullable(inner)) => is_nil(source) || self.is_assignable(source, inner),
+            _ if is_nil(source) => self.accepts_nil(target),
+            _ if readonly_inner(source).is_some() || readonly_inner(target).is_some() => {
+                self.is_readonly_assignable(source, target)
+            }
+            _ if partial_inner(source).is_some() || partial_inner(target).is_some() => {
+                match (partial_inner(source), partial_inner(target)) {
+                    (Some(source), Some(target)) => self.is_assignable(&source, &target),
+                    // A partially built value is not a complete one
+                    (Some(source), None) => {
+                        !(self.config.compiler_options.strict_initialization
+                            && self.interface_name(target).is_some())
+                            && self.is_assignable(&source, target)
+                    }
+                    (None, Some(target)) => self.is_assignable(source, &target),
+                    (None, None) => true,
+                }
+            }
+            _ if self.expand_utility(source).is_some() || self.expand_utility(target).is_some() => {
+                let source = self
+                    .expand_utility(source)
+                    .unwrap_or_else(|| source.clone());
+                let target = self
+                    .expand_utility(target)
+                    .unwrap_or_else(|| target.clone());
+                self.is_assignable(&source, &target)
+            }
+            (Literal(a), Literal(b)) => literals_equal(a, b),
+            (Literal(literal), _) => {
+                self.is_assignable(&literal_base_type(literal, source.span), target)
+            }
+            (Primitive(_), Literal(_)) => false,
+            // An integer is a number; a number is only an integer on targets
+            // without a separate integer subtype
+            (Primitive(a), Primitive(b)) => {
+                a == b
+                    || (is_numeric(*a)
+                        && is_numeric(*b)
+                        && !(*b == PrimitiveType::Integer && self.has_integer_subtype()))
+            }
+            (Array(a), Array(b)) => self.is_assignable(a, b),
+            (Tuple(source), Tuple(target)) => self.values_assignable(source, target),
+            // Fields both shapes declare must agree; tables may carry more
+            (Object(source), Object(target)) => source.members.iter().all(|member| {
+                let ObjectTypeMember::Property(prop) = member else {
+                    return true;
+                };
+                target.members.iter().all(|expected| match expected {
+                    ObjectTypeMember::Property(expected)
+                        if expected.name.node == prop.name.node =>
+                    {
+                        self.is_assignable(&prop.type_annotation, &expected.type_annotation)
+                    }
+                    _ => true,
+                })
+            }),
+            (Object(object), Reference(_)) => match self.interface_name(target) {
+                Some(interface) => object.members.iter().all(|member| match member {
+                    ObjectTypeMember::Property(prop) => {
+                        match self.lookup_interface_member(&interface, &prop.name.node) {
+                            MemberLookup::Found(expected) => {
+                                self.is_assignable(&prop.type_annotation, &expected)
+                            }
+                            _ => true,
+                        }
+                    }
+                    _ => true,
+                }),
+                None => true,
+            },
+            (Primitive(primitive), Reference(_)) | (Reference(_), Primitive(primitive)) => {
+                *primitive == PrimitiveType::Table
+                    || !(self.is_declared_type(source) || self.is_declared_type(target))
+            }
+            _ => true,
+        }
+    }
+
+    /// Why `source` is not assignable to `target`: the mismatch, then the
+    /// mismatch or rule behind each step, outermost first
+    pub(super) fn mismatch_chain(&self, source: &Type, target: &Type) -> Vec<String> {
+        let mut chain = Vec::new();
+        self.explain_mismatch(source, target, "", &mut chain);
+        chain
+    }
+
+    pub(super) fn explain_mismatch(
+        &self,
+        source: &Type,
+        target: &Type,
+        context: &str,
+        chain: &mut Vec<String>,
+    ) {
+        chain.push(format!(
+            "{}`{}` is not assignable to `{}`",
+            context, source, target
+        ));
+        self.mismatch_reason(source, target, chain);
+    }
+
+    /// Follow `is_assignable` down to the step that fails. Wrappers are
+    /// looked through silently; nested types get a line of their own.
+    fn mismatch_reason(&self, source: &Type, target: &Type, chain: &mut Vec<String>) {
+        use TypeKind::*;
+
+        match (&source.kind, &target.kind) {
+            (Parenthesized(inner), _) => self.mismatch_reason(inner, target, chain),
+            (_, Parenthesized(inner)) => self.mismatch_reason(source, inner, chain),
+            (Union(types), _) => {
+                if let Some(typ) = types.iter().find(|t| !self.is_assignable(t, target)) {
+                    self.explain_mismatch(typ, target, "union member ", chain);
+                }
+            }
+            (_, Union(_)) => chain.push("no member of the union accepts it".to_string()),
+            (Nullable(inner), _) => {
+                if self.accepts_nil(target) {
+                    self.mismatch_reason(inner, target, chain);
+                } else {
+                    chain.push(format!("`{}` does not accept nil", target));
+                }
+            }
+            (_, Nullable(inner)) => self.mismatch_reason(source, inner, chain),
+            _ if is_nil(source) => chain.push(format!("`{}` does not accept nil", target)),
+            _ if readonly_inner(source).is_some() || readonly_inner(target).is_some() => {
+                match (readonly_inner(source), readonly_inner(target)) {
+                    (Some(_), None) if self.is_mutable_structure(target) => chain.push(format!(
+                        "a read-only value cannot be used as a mutable `{}`",
+                        target
+                    )),
+                    (source_inner, target_inner) => self.mismatch_reason(
+                        &source_inner.unwrap_or_else(|| source.clone()),
+                        &target_inner.unwrap_or_else(|| target.clone()),
+                        chain,
+                    ),
+                }
+            }
+            _ if partial_inner(source).is_some() || partial_inner(target).is_some() => {
+                match (partial_inner(source), partial_inner(target)) {
+                    (Some(_), None)
+                        if self.config.compiler_options.strict_initialization
+                            && self.interface_name(target).is_some() =>
+                    {
+                        chain.push(format!(
+                            "a partial value may lack properties `{}` requires",
+                            target
+                        ))
+                    }
+                    (source_inner, target_inner) => self.mismatch_reason(
+                        &source_inner.unwrap_or_else(|| source.clone()),
+                        &target_inner.unwrap_or_else(|| target.clone()),
+                        chain,
+                    ),
+                }
+            }
+            _ if self.expand_utility(source).is_some() || self.expand_utility(target).is_some() => {
+                let source = self
+                    .expand_utility(source)
+                    .unwrap_or_else(|| source.clone());
+                let target = self
+                    .expand_utility(target)
+                    .unwrap_or_else(|| target.clone());
+                self.mismatch_reason(&source, &target, chain)
+            }
+            (Literal(_), Literal(_)) => chain.push("the literals differ".to_string()),
+            (Literal(literal), _) => {
+                self.mismatch_reason(&literal_base_type(literal, source.span), target, chain)
+            }
+            (Primitive(_), Literal(_)) => {
+                chain.push(format!("only the literal `{}` is accepted", target))
+            }
+            (Primitive(PrimitiveType::Number), Primitive(PrimitiveType::Integer)) => chain.push(
+                "a `number` may have a fractional part, and only an `integer` is accepted"
+                    .to_string(),
+            ),
+            (Primitive(a), Primitive(b)) => {
+                chain.push(format!("`{}` and `{}` are different types", a, b))
+            }
+            (Array(a), Array(b)) => self.explain_mismatch(a, b, "element type: ", chain),
+            (Tuple(source), Tuple(target)) => self.values_mismatch(source, target, chain),
+            (Object(source), Object(target)) => {
+                for member in &source.members {
+                    let ObjectTypeMember::Property(prop) = member else {
+                        continue;
+                    };
+                    let expected = target.members.iter().find_map(|expected| match expected {
+                        ObjectTypeMember::Property(expected)
+                            if expected.name.node == prop.name.node =>
+                        {
+                            Some(&expected.type_annotation)
+                        }
+                        _ => None,
+                    });
+                    if let Some(expected) = expected {
+                        if !self.is_assignable(&prop.type_annotation, expected) {
+                            let context = format!("property `{}`: ", prop.name.node);
+                            return self.explain_mismatch(
+                                &prop.type_annotation,
+                                expected,
+                                &context,
+                                chain,
+                            );
+                        }
+                    }
+                }
+            }
+            (Object(object), Reference(_)) => {
+                let Some(interface) = self.interface_name(target) else {
+                    return;
+                };
+                for member in &object.members {
+                    let ObjectTypeMember::Property(prop) = member else {
+                        continue;
+                    };
+                    if let MemberLookup::Found(expected) =
+                        self.lookup_interface_member(&interface, &prop.name.node)
+                    {
+                        if !self.is_assignable(&prop.type_annotation, &expected) {
+                            let context = format!("property `{}`: ", prop.name.node);
+                            return self.explain_mismatch(
+                                &prop.type_annotation,
+                                &expected,
+                                &context,
+                                chain,
+                            );
+                        }
+                    }
+                }
+            }
+            (Primitive(_), Reference(_)) | (Reference(_), Primitive(_)) => chain.push(
+                "a declared class or interface only converts to and from `table`".to_string(),
+            ),
+            _ => {}
+        }
+    }
+
+    /// Whether `nil` may be stored in a value of the given type
+    fn accepts_nil(&self, target: &Type) -> bool {
+        let options = &self.config.compiler_options;
+        if !options.strict_null_checks && !options.strict_nil {
+            return true;
+        }
+
+        match &target.kind {
+            TypeKind::Primitive(primitive) => matches!(
+                primitive,
+                PrimitiveType::Nil | PrimitiveType::Unknown | PrimitiveType::Void
+            ),
+            TypeKind::Literal(literal) => matches!(literal, Literal::Nil),
+            TypeKind::Nullable(_) => true,
+            TypeKind::Union(types) => types.iter().any(|t| self.accepts_nil(t)),
+            TypeKind::Parenthesized(inner) => self.accepts_nil(inner),
+            // Type parameters and aliases are not resolved yet
+            TypeKind::Reference(_) => !self.is_declared_type(target),
+            _ => false,
+        }
+    }
+}
+
+/// The type whose properties `Partial<T>` makes optional
+pub(super) fn partial_inner(typ: &Type) -> Option<Type> {
+    match &typ.kind {
+        TypeKind::Reference(reference) if reference.name.node == "Partial" => {
+            match reference.type_arguments.as_deref() {
+                Some([inner]) => Some(inner.clone()),
+                _ => None,
+            }
+        }
+        TypeKind::Parenthesized(inner) => partial_inner(inner),
+        _ => None,
+    }
+}
+
+fn is_numeric(primitive: PrimitiveType) -> bool {
+    matches!(primitive, PrimitiveType::Number | PrimitiveType::Integer)
+}
+
+pub(super) fn literals_equal(a: &Literal, b: &Literal) -> bool {
+    match (a, b) {
+        (Literal::Nil, Literal::Nil) => true,
+        (Literal::Boolean(a), Literal::Boolean(b)) => a == b,
+        (Literal::Number(a), Literal::Number(b)) => a == b,
+        (Literal::Integer(a), Literal::Integer(b)) => a == b,
+        (Literal::WrappedInteger(a, _), Literal::WrappedInteger(b, _)) => a == b,
+        (Literal::Number(a), Literal::Integer(b)) | (Literal::Integer(b), Literal::Number(a)) => {
+            *a == *b as f64
+        }
+        (Literal::String(a), Literal::String(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// The primitive type a literal type widens to
+pub(super) fn literal_base_type(literal: &Literal, span: Span) -> Type {
+    let primitive = match literal {
+        Literal::Nil => PrimitiveType::Nil,
+        Literal::Boolean(_) => PrimitiveType::Boolean,
+        Literal::Number(_) => PrimitiveType::Number,
+        Literal::Integer(_) | Literal::SuffixedInteger(..) | Literal::WrappedInteger(..) => {
+            PrimitiveType::Integer
+        }
+        Literal::String(_) => PrimitiveType::String,
+    };
+    Type::new(TypeKind::Primitive(primitive), span)
+}
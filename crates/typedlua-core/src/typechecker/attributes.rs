@@ -0,0 +1,75 @@
+use super::TypeChecker;
+use crate::ast::attributes::{
+    deprecation, find_attribute, has_attribute, inline_body, statement_decorators,
+};
+use crate::ast::pattern::Pattern;
+use crate::ast::statement::Statement;
+use crate::errors::TypeCheckError;
+use crate::span::Span;
+
+impl TypeChecker {
+    /// Report malformed `@deprecated` attributes, and `@inline` and
+    /// `@checked` where the code generator cannot honour them
+    pub(super) fn check_attributes(&mut self, statement: &Statement) {
+        if matches!(statement, Statement::Export(_)) {
+            return;
+        }
+        let decorators = statement_decorators(statement);
+        if let Some(Err(span)) = deprecation(decorators) {
+            self.report_error(span, TypeCheckError::InvalidDeprecation);
+        }
+        if let Some(checked) = find_attribute(decorators, "checked") {
+            if !matches!(statement, Statement::Function(_)) {
+                self.report_warning(checked.span, "TL3001", "@checked only applies to functions");
+            }
+        }
+        if !has_attribute(decorators, "inline") {
+            return;
+        }
+        let span = decorators[0].span;
+        match statement {
+            Statement::Function(func) if inline_body(func).is_none() => {
+                let message = format!(
+                    "@inline has no effect on '{}': its body must be a single return of one value, with plain parameters",
+                    func.name.node
+                );
+                self.report_warning(span, "TL3001", &message);
+            }
+            Statement::Function(_) => {}
+            _ => self.report_warning(span, "TL3001", "@inline only applies to functions"),
+        }
+    }
+
+    /// Remember a `@deprecated` declaration so its uses are flagged
+    pub(super) fn note_deprecation(&mut self, statement: &Statement) {
+        let name = match statement {
+            Statement::Variable(decl) => match &decl.pattern {
+                Pattern::Identifier(name) => name,
+                _ => return,
+            },
+            Statement::Function(func) => &func.name,
+            Statement::Class(class) => &class.name,
+            Statement::Interface(interface) => &interface.name,
+            Statement::TypeAlias(alias) => &alias.name,
+            Statement::Enum(decl) => &decl.name,
+            _ => return,
+        };
+        if let Some(Ok(message)) = deprecation(statement_decorators(statement)) {
+            self.deprecated
+                .insert(name.node.clone(), message.to_string());
+        }
+    }
+
+    /// Warn about a use of a deprecated value or type
+    pub(super) fn check_deprecated_use(&mut self, name: &str, span: Span) {
+        let Some(message) = self.deprecated.get(name) else {
+            return;
+        };
+        let warning = if message.is_empty() {
+            format!("'{}' is deprecated", name)
+        } else {
+            format!("'{}' is deprecated: {}", name, message)
+        };
+        self.report_warning(span, "TL3002", &warning);
+    }
+}
@@ -0,0 +1,206 @@
+use super::flow::may_be_nil;
+use super::{expression_label, return_type, unknown_type, TypeChecker};
+use crate::ast::expression::{Argument, Expression, ExpressionKind, Literal};
+use crate::ast::statement::Parameter;
+use crate::ast::types::{FunctionType, PrimitiveType, Type, TypeKind};
+use crate::errors::TypeCheckError;
+use crate::span::Span;
+
+impl TypeChecker {
+    /// The type a call produces, after checking the number of arguments.
+    /// A union of function types is a set of overloads, and the call uses
+    /// the first one its arguments fit.
+    pub(super) fn check_call_signature(
+        &mut self,
+        callee: &Expression,
+        callee_type: &Type,
+        args: &[Argument],
+        arg_types: &[Type],
+        span: Span,
+    ) -> Type {
+        match &callee_type.kind {
+            TypeKind::Parenthesized(inner) => {
+                self.check_call_signature(callee, inner, args, arg_types, span)
+            }
+            TypeKind::Function(func) => {
+                self.check_argument_count(func, args, span);
+                self.check_nil_arguments(func, args, arg_types);
+                return_type(callee_type, span)
+            }
+            TypeKind::Union(types) => {
+                let overloads: Vec<&FunctionType> =
+                    types.iter().filter_map(function_signature).collect();
+                if overloads.len() != types.len() {
+                    return return_type(callee_type, span);
+                }
+                match overloads
+                    .iter()
+                    .position(|func| self.accepts(func, args, arg_types))
+                {
+                    Some(i) => return_type(&types[i], span),
+                    None => {
+                        let arguments: Vec<String> =
+                            arg_types.iter().map(|t| t.to_string()).collect();
+                        self.report_error(
+                            span,
+                            TypeCheckError::NoMatchingOverload {
+                                callee: expression_label(callee),
+                                arguments: arguments.join(", "),
+                            },
+                        );
+                        unknown_type(span)
+                    }
+                }
+            }
+            _ => return_type(callee_type, span),
+        }
+    }
+
+    /// Unless relaxed by `strictArity: false` or a `// @varargs-ok`
+    /// directive, a call must pass as many arguments as the function takes
+    fn check_argument_count(&mut self, func: &FunctionType, args: &[Argument], span: Span) {
+        if !self.config.compiler_options.strict_arity || self.varargs_ok.contains(&span.line) {
+            return;
+        }
+        let (min, max) = self.arity(&func.parameters);
+        let (count, open) = argument_count(args);
+        let expected = match max {
+            Some(max) if max == min => min.to_string(),
+            Some(max) => format!("{} to {}", min, max),
+            None => format!("at least {}", min),
+        };
+        if (count < min && !open) || max.is_some_and(|max| count > max) {
+            self.report_error(
+                span,
+                TypeCheckError::ArgumentCount {
+                    expected,
+                    actual: count,
+                },
+            );
+        }
+    }
+
+    /// Under `strictNil`, an argument that may be nil must go to a
+    /// parameter that admits nil
+    fn check_nil_arguments(&mut self, func: &FunctionType, args: &[Argument], arg_types: &[Type]) {
+        if !self.config.compiler_options.strict_nil {
+            return;
+        }
+        let expected = self.expected_arguments(func, args);
+        for ((arg, typ), expected) in args.iter().zip(arg_types).zip(expected) {
+            if let Some(expected) = expected.filter(|_| may_be_nil(typ)) {
+                self.check_assignable(typ, &expected, arg.value.span, None);
+            }
+        }
+    }
+
+    /// Whether an overload takes this many arguments of these types
+    fn accepts(&self, func: &FunctionType, args: &[Argument], arg_types: &[Type]) -> bool {
+        let (min, max) = self.arity(&func.parameters);
+        let (count, open) = argument_count(args);
+        if (count < min && !open) || max.is_some_and(|max| count > max) {
+            return false;
+        }
+        self.expected_arguments(func, args)
+            .into_iter()
+            .zip(arg_types)
+            .all(|(expected, typ)| {
+                expected.is_none_or(|expected| self.is_assignable(typ, &expected))
+            })
+    }
+
+    /// The declared type of the parameter each argument goes to, up to the
+    /// first spread
+    fn expected_arguments(&self, func: &FunctionType, args: &[Argument]) -> Vec<Option<Type>> {
+        let mut expected = Vec::new();
+        let mut params = func.parameters.iter();
+        let mut rest = None;
+        for arg in args {
+            if arg.is_spread {
+                break;
+            }
+            // A rest parameter takes every remaining argument
+            let Some(param) = rest.or_else(|| params.next()) else {
+                break;
+            };
+            if param.is_rest {
+                rest = Some(param);
+            }
+            expected.push(param.type_annotation.as_ref().map(|annotation| {
+                let typ = self.expand_aliases(annotation, 0);
+                match (param.is_rest, &typ.kind) {
+                    (true, TypeKind::Array(element)) => (**element).clone(),
+                    _ => typ,
+                }
+            }));
+        }
+        expected
+    }
+
+    /// Fewest and most arguments a parameter list takes. A parameter can be
+    /// left out when it has a default or admits nil, and nothing after it
+    /// is required.
+    pub(super) fn arity(&self, parameters: &[Parameter]) -> (usize, Option<usize>) {
+        let max = if parameters.iter().any(|p| p.is_rest) {
+            None
+        } else {
+            Some(parameters.len())
+        };
+        let min = parameters
+            .iter()
+            .rposition(|param| {
+                !param.is_rest
+                    && param.default.is_none()
+                    && param
+                        .type_annotation
+                        .as_ref()
+                        .is_some_and(|typ| !self.admits_nil(typ))
+            })
+            .map_or(0, |i| i + 1);
+        (min, max)
+    }
+
+    fn admits_nil(&self, typ: &Type) -> bool {
+        let typ = self.expand_aliases(typ, 0);
+        match &typ.kind {
+            TypeKind::Nullable(_) | TypeKind::Literal(Literal::Nil) => true,
+            TypeKind::Primitive(primitive) => matches!(
+                primitive,
+                PrimitiveType::Nil | PrimitiveType::Unknown | PrimitiveType::Void
+            ),
+            TypeKind::Parenthesized(inner) => self.admits_nil(inner),
+            TypeKind::Union(types) => types.iter().any(|t| self.admits_nil(t)),
+            // A type parameter may be instantiated with nil
+            TypeKind::Reference(reference) => self
+                .type_parameters
+                .iter()
+                .any(|param| param.name.node == reference.name.node),
+            _ => false,
+        }
+    }
+}
+
+fn function_signature(typ: &Type) -> Option<&FunctionType> {
+    match &typ.kind {
+        TypeKind::Function(func) => Some(func),
+        TypeKind::Parenthesized(inner) => function_signature(inner),
+        _ => None,
+    }
+}
+
+/// How many arguments a call passes, and whether the last one may expand
+/// to more values (a spread or another call)
+fn argument_count(args: &[Argument]) -> (usize, bool) {
+    let open = args.last().is_some_and(|arg| {
+        arg.is_spread
+            || matches!(
+                arg.value.kind,
+                ExpressionKind::Call(..) | ExpressionKind::MethodCall(..)
+            )
+    });
+    if open {
+        (args.len() - 1, true)
+    } else {
+        (args.len(), false)
+    }
+}
@@ -0,0 +1,71 @@
+use super::assignability::literal_base_type;
+use super::symbol_table::SymbolKind;
+use super::TypeChecker;
+use crate::ast::expression::{Expression, Literal};
+use crate::ast::types::{PrimitiveType, Type, TypeKind};
+use crate::constant;
+use crate::diagnostics::RelatedSpan;
+
+impl TypeChecker {
+    /// The compile-time value of an expression built from literals and
+    /// `const`s with known values
+    pub(super) fn constant_value(&self, expr: &Expression) -> Option<Literal> {
+        constant::evaluate(expr, &|name| {
+            let symbol = self.symbols.lookup(name)?;
+            match (&symbol.kind, &symbol.typ.kind) {
+                (SymbolKind::Const, TypeKind::Literal(value)) => Some(value.clone()),
+                _ => None,
+            }
+        })
+    }
+
+    /// A `const` with a known value has that literal as its type, so it can
+    /// stand in for a member of a literal union
+    pub(super) fn constant_type(&self, initializer: &Expression) -> Option<Type> {
+        let value = self.constant_value(initializer)?;
+        Some(Type::new(TypeKind::Literal(value), initializer.span))
+    }
+
+    /// Check a value against a declared type. A value known at compile time
+    /// also satisfies literal types matching it.
+    pub(super) fn check_value_assignable(
+        &mut self,
+        value: &Expression,
+        typ: &Type,
+        target: &Type,
+        because: Option<RelatedSpan>,
+    ) {
+        let fits_constant = self
+            .constant_type(value)
+            .is_some_and(|constant| self.is_assignable(&constant, target));
+        if !fits_constant {
+            self.check_assignable(typ, target, value.span, because);
+        }
+    }
+
+    /// Values taken from a constant take its primitive type, since they
+    /// are not tied to that one value
+    pub(super) fn widen_constant(&self, expr: &Expression, typ: Type) -> Type {
+        match &typ.kind {
+            TypeKind::Literal(literal) if self.constant_value(expr).is_some() => {
+                literal_base_type(literal, typ.span)
+            }
+            _ => typ,
+        }
+    }
+
+    /// Mutable places holding a constant's value take its primitive type,
+    /// since they may be given other values later. An integer constant
+    /// makes a `number`, as in `local sum = 0` added floats to later; an
+    /// `integer` annotation keeps the place integral.
+    pub(super) fn widen_mutable(&self, expr: &Expression, typ: Type) -> Type {
+        match self.widen_constant(expr, typ) {
+            typ if matches!(typ.kind, TypeKind::Primitive(PrimitiveType::Integer))
+                && matches!(self.constant_value(expr), Some(Literal::Integer(_))) =>
+            {
+                Type::new(TypeKind::Primitive(PrimitiveType::Number), typ.span)
+            }
+            typ => typ,
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use super::TypeChecker;
+use crate::ast::expression::{Expression, ExpressionKind};
+use crate::ast::types::{PrimitiveType, Type, TypeKind};
+use serde::{Deserialize, Serialize};
+
+/// How many of a module's expressions the checker could give a type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeCoverage {
+    /// Expressions whose type is known
+    pub typed: usize,
+    /// Expressions left `unknown`, such as reads of untyped imports
+    pub untyped: usize,
+}
+
+impl TypeCoverage {
+    /// Typed expressions as a share of all of them, from 0 to 100. A module
+    /// without expressions is fully covered.
+    pub fn percent(&self) -> f64 {
+        let total = self.typed + self.untyped;
+        if total == 0 {
+            return 100.0;
+        }
+        self.typed as f64 * 100.0 / total as f64
+    }
+
+    pub fn add(&mut self, other: TypeCoverage) {
+        self.typed += other.typed;
+        self.untyped += other.untyped;
+    }
+}
+
+impl TypeChecker {
+    /// The coverage of every expression checked so far
+    pub fn type_coverage(&self) -> TypeCoverage {
+        self.coverage
+    }
+
+    pub(super) fn record_coverage(&mut self, expr: &Expression, typ: &Type) {
+        // `self` and `super` are typed by the member accesses on them
+        if matches!(
+            expr.kind,
+            ExpressionKind::SelfKeyword | ExpressionKind::SuperKeyword
+        ) {
+            return;
+        }
+        if matches!(typ.kind, TypeKind::Primitive(PrimitiveType::Unknown)) {
+            self.coverage.untyped += 1;
+        } else {
+            self.coverage.typed += 1;
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use super::TypeChecker;
+use crate::ast::expression::{ArrayElement, Expression, ExpressionKind, Literal, ObjectProperty};
+use crate::ast::pattern::Pattern;
+use crate::ast::statement::{
+    ExportKind, IndexKeyType, Statement, VariableDeclaration, VariableKind,
+};
+use crate::ast::types::{ObjectTypeMember, Type, TypeKind};
+use crate::errors::TypeCheckError;
+
+impl TypeChecker {
+    /// Check the module as a data module (`.data.tl`): one holding only
+    /// types and constant tables, each validated against its declared type
+    pub fn set_data_module(&mut self) {
+        self.data_module = true;
+    }
+
+    /// A data module may import and declare types, and declare constants
+    /// built from literals, tables and other constants. Anything that runs
+    /// code, such as functions, calls and loops, is reported.
+    pub(super) fn check_data_module(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            let statement = match statement {
+                Statement::Export(export) => match &export.kind {
+                    ExportKind::Declaration(decl) => decl.as_ref(),
+                    ExportKind::Named(_) => continue,
+                    ExportKind::Default(value) | ExportKind::Equals(value) => {
+                        self.check_data_expression(value);
+                        continue;
+                    }
+                },
+                statement => statement,
+            };
+            let what = match statement {
+                Statement::Import(_)
+                | Statement::Interface(_)
+                | Statement::TypeAlias(_)
+                | Statement::Enum(_)
+                | Statement::Declare(_)
+                | Statement::Error(_) => continue,
+                Statement::Variable(decl) if decl.kind == VariableKind::Const => {
+                    if decl.type_annotation.is_none() {
+                        let name = match &decl.pattern {
+                            Pattern::Identifier(name) => name.node.clone(),
+                            _ => "destructured constants".to_string(),
+                        };
+                        self.report_error(decl.span, TypeCheckError::UntypedData(name));
+                    }
+                    self.check_data_expression(&decl.initializer);
+                    continue;
+                }
+                Statement::Variable(_) => "variables",
+                Statement::Function(_) => "functions",
+                Statement::Class(_) => "classes",
+                Statement::Namespace(_) => "namespaces",
+                _ => "statements",
+            };
+            self.report_error(statement.span(), TypeCheckError::NotData(what.to_string()));
+        }
+    }
+
+    fn check_data_expression(&mut self, expr: &Expression) {
+        let what = match &expr.kind {
+            ExpressionKind::Identifier(_) | ExpressionKind::Literal(_) => return,
+            ExpressionKind::Template(_) => return,
+            ExpressionKind::Member(object, _) | ExpressionKind::Unary(_, object) => {
+                return self.check_data_expression(object)
+            }
+            ExpressionKind::Parenthesized(inner) | ExpressionKind::TypeAssertion(inner, _) => {
+                return self.check_data_expression(inner)
+            }
+            ExpressionKind::Binary(_, left, right) => {
+                self.check_data_expression(left);
+                return self.check_data_expression(right);
+            }
+            ExpressionKind::Array(elements) => {
+                for element in elements {
+                    match element {
+                        ArrayElement::Expression(value) | ArrayElement::Spread(value) => {
+                            self.check_data_expression(value)
+                        }
+                    }
+                }
+                return;
+            }
+            ExpressionKind::Object(properties) => {
+                for property in properties {
+                    match property {
+                        ObjectProperty::Property { value, .. }
+                        | ObjectProperty::Spread { value, .. } => self.check_data_expression(value),
+                        ObjectProperty::Computed { key, value, .. } => {
+                            self.check_data_expression(key);
+                            self.check_data_expression(value);
+                        }
+                    }
+                }
+                return;
+            }
+            ExpressionKind::Function(_) | ExpressionKind::Arrow(_) => "functions",
+            ExpressionKind::Call(..)
+            | ExpressionKind::MethodCall(..)
+            | ExpressionKind::Pipe(..) => "calls",
+            _ => "computed values",
+        };
+        self.report_error(expr.span, TypeCheckError::NotData(what.to_string()));
+    }
+
+    /// Validate a data constant against its declared type. Tables are
+    /// walked field by field rather than inferred whole, so large tables
+    /// check quickly and each problem is reported at the row and field it
+    /// is in, such as `levels[3].enemies[1].hp`.
+    pub(super) fn check_data_variable(&mut self, decl: &VariableDeclaration, annotation: &Type) {
+        let path = match &decl.pattern {
+            Pattern::Identifier(name) => name.node.clone(),
+            _ => "value".to_string(),
+        };
+        self.check_data_value(&decl.initializer, annotation, path);
+    }
+
+    fn check_data_value(&mut self, value: &Expression, expected: &Type, path: String) {
+        let expected = self.expand_aliases(expected, 0);
+        match (&value.kind, &expected.kind) {
+            (ExpressionKind::Parenthesized(inner), _) => {
+                return self.check_data_value(inner, &expected, path)
+            }
+            (_, TypeKind::Parenthesized(inner)) => {
+                return self.check_data_value(value, inner, path)
+            }
+            (ExpressionKind::Literal(Literal::Nil), TypeKind::Nullable(_)) => return,
+            (_, TypeKind::Nullable(inner)) => return self.check_data_value(value, inner, path),
+            // Spreads shift the rows after them, so those arrays are
+            // checked whole
+            (ExpressionKind::Array(elements), TypeKind::Array(element))
+                if elements
+                    .iter()
+                    .all(|item| matches!(item, ArrayElement::Expression(_))) =>
+            {
+                for (index, item) in elements.iter().enumerate() {
+                    if let ArrayElement::Expression(item) = item {
+                        self.check_data_value(item, element, format!("{}[{}]", path, index + 1))
+                    }
+                }
+                return;
+            }
+            (ExpressionKind::Object(properties), _) => {
+                if let Some(members) = self.shape_members(&expected) {
+                    return self.check_data_fields(value, properties, &members, &expected, path);
+                }
+            }
+            _ => {}
+        }
+        self.check_data_fallback(value, &expected, path);
+    }
+
+    /// Check each field of a table against the shape it must have, then
+    /// report the required fields it leaves out
+    fn check_data_fields(
+        &mut self,
+        value: &Expression,
+        properties: &[ObjectProperty],
+        members: &[ObjectTypeMember],
+        expected: &Type,
+        path: String,
+    ) {
+        let mut provided = Vec::new();
+        for property in properties {
+            let ObjectProperty::Property {
+                key, value: field, ..
+            } = property
+            else {
+                // Spreads and computed keys may provide anything
+                return self.check_data_fallback(value, expected, path);
+            };
+            provided.push(key.node.as_str());
+            let field_path = format!("{}.{}", path, key.node);
+            let member = members.iter().find_map(|member| match member {
+                ObjectTypeMember::Property(prop) if prop.name.node == key.node => {
+                    Some(prop.type_annotation.clone())
+                }
+                ObjectTypeMember::Index(index) if index.key_type == IndexKeyType::String => {
+                    Some(index.value_type.clone())
+                }
+                _ => None,
+            });
+            match member {
+                Some(typ) => self.check_data_value(field, &typ, field_path),
+                None => self.report_error(
+                    key.span,
+                    TypeCheckError::UnknownDataField {
+                        path: field_path,
+                        type_name: expected.to_string(),
+                    },
+                ),
+            }
+        }
+        for member in members {
+            if let ObjectTypeMember::Property(prop) = member {
+                let accepts_nil = matches!(prop.type_annotation.kind, TypeKind::Nullable(_));
+                if !prop.is_optional && !accepts_nil && !provided.contains(&prop.name.node.as_str())
+                {
+                    self.report_error(
+                        value.span,
+                        TypeCheckError::MissingDataField {
+                            path: path.clone(),
+                            field: prop.name.node.clone(),
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    /// Infer a value the walk cannot follow and check the whole of it
+    fn check_data_fallback(&mut self, value: &Expression, expected: &Type, path: String) {
+        let found = self.check_expression(value);
+        if !self.is_assignable(&found, expected) {
+            self.report_error(
+                value.span,
+                TypeCheckError::DataMismatch {
+                    path,
+                    expected: expected.to_string(),
+                    found: found.to_string(),
+                },
+            );
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use super::{expression_label, TypeChecker};
+use crate::ast::expression::{AssignmentOp, BinaryOp, Expression, ExpressionKind, UnaryOp};
+use crate::ast::statement::ForGeneric;
+use crate::span::Span;
+
+/// `math` functions computed by the platform's C library, whose last bits
+/// differ between machines
+const PLATFORM_MATH: &[&str] = &[
+    "sin", "cos", "tan", "asin", "acos", "atan", "atan2", "sinh", "cosh", "tanh", "exp", "log",
+    "log10", "pow",
+];
+
+impl TypeChecker {
+    /// In a `// @deterministic` module, warn about calls that can give
+    /// different results on machines running the same inputs: platform
+    /// float math, the global random generator, the clock, and iteration
+    /// in `pairs` order
+    pub(super) fn check_deterministic_call(&mut self, callee: &Expression, span: Span) {
+        if !self.deterministic {
+            return;
+        }
+        let label = expression_label(callee);
+        let root = label.split('.').next().unwrap_or_default();
+        if !self.symbols.is_builtin(root) {
+            return;
+        }
+        let message = match label.as_str() {
+            "pairs" | "next" => format!(
+                "'{}' visits keys in an unspecified order, which is not deterministic; iterate with 'ipairs' or over sorted keys",
+                label
+            ),
+            "math.random" | "math.randomseed" => format!(
+                "'{}' uses the global random generator, which is not deterministic; use a seeded 'Random' instead",
+                label
+            ),
+            "os.time" | "os.clock" | "os.date" => format!(
+                "'{}' reads the local clock, which is not deterministic",
+                label
+            ),
+            _ if label
+                .strip_prefix("math.")
+                .is_some_and(|name| PLATFORM_MATH.contains(&name)) =>
+            {
+                format!(
+                    "'{}' is computed by the platform's math library, which is not deterministic; use a fixed-point or software implementation",
+                    label
+                )
+            }
+            _ => return,
+        };
+        self.report_warning(span, "TL3012", &message);
+    }
+
+    /// In a `// @order-sensitive` module, run `check` with the variables of
+    /// a loop over `pairs` or `next` in scope of the order checks
+    pub(super) fn with_pairs_loop(&mut self, generic: &ForGeneric, check: impl FnOnce(&mut Self)) {
+        let tracked = self.order_sensitive && self.iterates_pairs(&generic.iterators);
+        if tracked {
+            let variables = generic.variables.iter().map(|v| v.node.clone()).collect();
+            self.pairs_loops.push(variables);
+        }
+        check(self);
+        if tracked {
+            self.pairs_loops.pop();
+        }
+    }
+
+    /// An ordering comparison of a `pairs` loop variable: which key wins a
+    /// tie depends on the order keys are visited
+    pub(super) fn check_order_dependent_comparison(
+        &mut self,
+        op: BinaryOp,
+        left: &Expression,
+        right: &Expression,
+        span: Span,
+    ) {
+        let is_ordering = matches!(
+            op,
+            BinaryOp::LessThan
+                | BinaryOp::LessThanOrEqual
+                | BinaryOp::GreaterThan
+                | BinaryOp::GreaterThanOrEqual
+        );
+        if !is_ordering {
+            return;
+        }
+        let variable = [left, right]
+            .into_iter()
+            .filter_map(root_name)
+            .find(|name| self.is_pairs_variable(name));
+        if let Some(variable) = variable {
+            let message = format!(
+                "Comparing '{}' inside a 'pairs' loop depends on the order keys are visited, which is unspecified; iterate over sorted keys",
+                variable
+            );
+            self.report_warning(span, "TL3013", &message);
+        }
+    }
+
+    /// Serializing inside a `pairs` loop, by concatenating onto a string or
+    /// appending to a sequence, records the order keys were visited
+    pub(super) fn check_order_dependent_assignment(
+        &mut self,
+        target: &Expression,
+        op: AssignmentOp,
+        value: &Expression,
+        span: Span,
+    ) {
+        if self.pairs_loops.is_empty() {
+            return;
+        }
+        let concatenates = match (&target.kind, op, &value.kind) {
+            (_, AssignmentOp::ConcatenateAssign, _) => true,
+            (
+                ExpressionKind::Identifier(name),
+                _,
+                ExpressionKind::Binary(BinaryOp::Concatenate, left, _),
+            ) => {
+                matches!(&left.kind, ExpressionKind::Identifier(left) if left == name)
+            }
+            _ => false,
+        };
+        if concatenates {
+            self.warn_order_dependent_build(&expression_label(target), span);
+        } else if let Some(list) = appended_list(target) {
+            self.warn_order_dependent_build(&expression_label(list), span);
+        }
+    }
+
+    /// `table.insert` inside a `pairs` loop appends in the order keys were
+    /// visited
+    pub(super) fn check_order_dependent_call(&mut self, callee: &Expression, span: Span) {
+        if self.pairs_loops.is_empty() || expression_label(callee) != "table.insert" {
+            return;
+        }
+        if self.symbols.is_builtin("table") {
+            self.warn_order_dependent_build("table.insert", span);
+        }
+    }
+
+    fn warn_order_dependent_build(&mut self, label: &str, span: Span) {
+        let message = format!(
+            "'{}' inside a 'pairs' loop records the order keys are visited, which is unspecified; iterate over sorted keys",
+            label
+        );
+        self.report_warning(span, "TL3013", &message);
+    }
+
+    fn iterates_pairs(&self, iterators: &[Expression]) -> bool {
+        let callee = match iterators.first().map(|iterator| &iterator.kind) {
+            Some(ExpressionKind::Call(callee, _)) => callee,
+            Some(_) => &iterators[0],
+            None => return false,
+        };
+        matches!(&callee.kind, ExpressionKind::Identifier(name)
+            if (name == "pairs" || name == "next") && self.symbols.is_builtin(name))
+    }
+
+    fn is_pairs_variable(&self, name: &str) -> bool {
+        self.pairs_loops
+            .iter()
+            .flatten()
+            .any(|variable| variable == name)
+    }
+}
+
+/// The variable an expression reads, through any members and indexes
+fn root_name(expr: &Expression) -> Option<&str> {
+    match &expr.kind {
+        ExpressionKind::Identifier(name) => Some(name),
+        ExpressionKind::Member(object, _) | ExpressionKind::Index(object, _) => root_name(object),
+        ExpressionKind::Parenthesized(inner) => root_name(inner),
+        _ => None,
+    }
+}
+
+/// The list of an append, `list[#list + 1]`
+fn appended_list(target: &Expression) -> Option<&Expression> {
+    let ExpressionKind::Index(list, index) = &target.kind else {
+        return None;
+    };
+    let ExpressionKind::Binary(BinaryOp::Add, length, _) = &index.kind else {
+        return None;
+    };
+    match &length.kind {
+        ExpressionKind::Unary(UnaryOp::Length, operand)
+            if root_name(operand).is_some() && root_name(operand) == root_name(list) =>
+        {
+            Some(list)
+        }
+        _ => None,
+    }
+}
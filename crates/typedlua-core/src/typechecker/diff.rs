@@ -0,0 +1,306 @@
+use super::type_environment::class_methods;
+use super::{method_type, property_type, unknown_type, TypeChecker};
+use crate::ast::statement::{ClassMember, InterfaceMember, Parameter};
+use crate::ast::types::{ObjectTypeMember, Type, TypeKind};
+use std::fmt;
+
+/// How a type compares to the type expected of it, member by member
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDiff {
+    pub source: String,
+    pub target: String,
+    /// Whether the source fits the target: every required member is there
+    /// and every shared member fits, or for other types, the checker's own
+    /// assignability
+    pub assignable: bool,
+    /// The members or parameters of both, in the target's order, then the
+    /// source's extra ones. Empty when neither type has members.
+    pub entries: Vec<DiffEntry>,
+    /// Why the source does not fit, outermost first, when it has no
+    /// members to compare
+    pub reasons: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffEntry {
+    /// A member name, `parameter n` or `return`
+    pub name: String,
+    pub change: DiffChange,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffChange {
+    /// Both have it and the source's type fits
+    Fits { actual: String, expected: String },
+    /// Both have it, but the source's type does not fit, for the reasons
+    /// given
+    Mismatch {
+        actual: String,
+        expected: String,
+        reasons: Vec<String>,
+    },
+    /// Only the target has it; an optional member may be left out
+    Missing { expected: String, optional: bool },
+    /// Only the source has it
+    Extra { actual: String },
+}
+
+impl DiffChange {
+    fn fits(&self) -> bool {
+        !matches!(
+            self,
+            DiffChange::Mismatch { .. }
+                | DiffChange::Missing {
+                    optional: false,
+                    ..
+                }
+        )
+    }
+}
+
+impl fmt::Display for TypeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let verdict = if self.assignable {
+            "assignable"
+        } else {
+            "not assignable"
+        };
+        writeln!(f, "`{}` -> `{}`: {}", self.source, self.target, verdict)?;
+        for entry in &self.entries {
+            match &entry.change {
+                DiffChange::Fits { actual, expected } if actual == expected => {
+                    writeln!(f, "  = {}: {}", entry.name, actual)?
+                }
+                DiffChange::Fits { actual, expected } => {
+                    writeln!(f, "  = {}: {} (fits {})", entry.name, actual, expected)?
+                }
+                DiffChange::Mismatch {
+                    actual,
+                    expected,
+                    reasons,
+                } => {
+                    writeln!(f, "  ~ {}: {}, expected {}", entry.name, actual, expected)?;
+                    for (depth, reason) in reasons.iter().enumerate() {
+                        writeln!(f, "      {}{}", "  ".repeat(depth), reason)?;
+                    }
+                }
+                DiffChange::Missing {
+                    expected,
+                    optional: false,
+                } => writeln!(f, "  - {}: {} (missing)", entry.name, expected)?,
+                DiffChange::Missing {
+                    expected,
+                    optional: true,
+                } => writeln!(f, "  ? {}: {} (optional, missing)", entry.name, expected)?,
+                DiffChange::Extra { actual } => writeln!(
+                    f,
+                    "  + {}: {} (only in `{}`)",
+                    entry.name, actual, self.source
+                )?,
+            }
+        }
+        for (depth, reason) in self.reasons.iter().enumerate() {
+            writeln!(f, "  {}{}", "  ".repeat(depth), reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// A member of a structural type: its name, type and whether it may be
+/// left out
+type Member = (String, Type, bool);
+
+impl TypeChecker {
+    /// Compare `source` with the `target` it is expected to fit. Interfaces,
+    /// classes and table shapes are compared by their members, and function
+    /// types by their parameters and return type.
+    pub fn diff_types(&self, source: &Type, target: &Type) -> TypeDiff {
+        let source = self.expand_aliases(source, 0);
+        let target = self.expand_aliases(target, 0);
+        let mut diff = TypeDiff {
+            source: source.to_string(),
+            target: target.to_string(),
+            assignable: true,
+            entries: Vec::new(),
+            reasons: Vec::new(),
+        };
+
+        if let (Some(actual), Some(expected)) = (self.members(&source), self.members(&target)) {
+            for (name, expected, optional) in &expected {
+                let change = match actual.iter().find(|(other, _, _)| other == name) {
+                    Some((_, actual, _)) => self.compare(actual, expected),
+                    None => DiffChange::Missing {
+                        expected: expected.to_string(),
+                        optional: *optional,
+                    },
+                };
+                diff.entries.push(DiffEntry {
+                    name: name.clone(),
+                    change,
+                });
+            }
+            for (name, actual, _) in &actual {
+                if !diff.entries.iter().any(|entry| entry.name == *name) {
+                    diff.entries.push(DiffEntry {
+                        name: name.clone(),
+                        change: DiffChange::Extra {
+                            actual: actual.to_string(),
+                        },
+                    });
+                }
+            }
+        } else if let (TypeKind::Function(actual), TypeKind::Function(expected)) =
+            (&strip(&source).kind, &strip(&target).kind)
+        {
+            let count = actual.parameters.len().max(expected.parameters.len());
+            for i in 0..count {
+                let parameter_type = |parameters: &[Parameter]| {
+                    parameters.get(i).map(|p| {
+                        p.type_annotation
+                            .clone()
+                            .unwrap_or_else(|| unknown_type(p.span))
+                    })
+                };
+                let change = match (
+                    parameter_type(&actual.parameters),
+                    parameter_type(&expected.parameters),
+                ) {
+                    // Parameters take what the target's callers pass, so the
+                    // expected type must fit the source's
+                    (Some(actual), Some(expected)) => match self.compare(&expected, &actual) {
+                        DiffChange::Mismatch { reasons, .. } => DiffChange::Mismatch {
+                            actual: actual.to_string(),
+                            expected: expected.to_string(),
+                            reasons,
+                        },
+                        _ => DiffChange::Fits {
+                            actual: actual.to_string(),
+                            expected: expected.to_string(),
+                        },
+                    },
+                    (Some(actual), None) => DiffChange::Extra {
+                        actual: actual.to_string(),
+                    },
+                    (None, Some(expected)) => DiffChange::Missing {
+                        expected: expected.to_string(),
+                        optional: true,
+                    },
+                    (None, None) => continue,
+                };
+                diff.entries.push(DiffEntry {
+                    name: format!("parameter {}", i + 1),
+                    change,
+                });
+            }
+            diff.entries.push(DiffEntry {
+                name: "return".to_string(),
+                change: self.compare(&actual.return_type, &expected.return_type),
+            });
+        } else if !self.is_assignable(&source, &target) {
+            diff.assignable = false;
+            diff.reasons = self.mismatch_chain(&source, &target);
+        }
+
+        if diff.entries.iter().any(|entry| !entry.change.fits()) {
+            diff.assignable = false;
+        }
+        diff
+    }
+
+    fn compare(&self, actual: &Type, expected: &Type) -> DiffChange {
+        if self.is_assignable(actual, expected) {
+            DiffChange::Fits {
+                actual: actual.to_string(),
+                expected: expected.to_string(),
+            }
+        } else {
+            DiffChange::Mismatch {
+                actual: actual.to_string(),
+                expected: expected.to_string(),
+                // The first line restates the mismatch
+                reasons: self
+                    .mismatch_chain(actual, expected)
+                    .into_iter()
+                    .skip(1)
+                    .collect(),
+            }
+        }
+    }
+
+    /// The members of an interface, class or table shape; `None` for types
+    /// compared as a whole
+    fn members(&self, typ: &Type) -> Option<Vec<Member>> {
+        let typ = strip(typ);
+        let mut members: Vec<Member> = Vec::new();
+        let mut add = |name: &str, typ: Type, optional: bool| {
+            if !members.iter().any(|(other, _, _)| other == name) {
+                members.push((name.to_string(), typ, optional));
+            }
+        };
+
+        match &typ.kind {
+            TypeKind::Object(object) => {
+                for member in &object.members {
+                    match member {
+                        ObjectTypeMember::Property(prop) => {
+                            add(&prop.name.node, property_type(prop), prop.is_optional)
+                        }
+                        ObjectTypeMember::Method(method) => {
+                            add(&method.name.node, method_type(method), false)
+                        }
+                        ObjectTypeMember::Index(_) => {}
+                    }
+                }
+            }
+            TypeKind::Reference(reference) => {
+                let name = &reference.name.node;
+                if let Some(class) = self.type_env.get_class(name) {
+                    let owners = std::iter::once(class)
+                        .chain(self.type_env.mixin_classes(class))
+                        .chain(self.type_env.ancestors(class));
+                    for owner in owners {
+                        let names = owner.members.iter().filter_map(|member| match member {
+                            ClassMember::Property(prop) if !prop.is_static => Some(&prop.name),
+                            ClassMember::Getter(getter) if !getter.is_static => Some(&getter.name),
+                            _ => None,
+                        });
+                        let methods = class_methods(owner)
+                            .filter(|method| !method.is_static)
+                            .map(|method| &method.name);
+                        for member in names.chain(methods) {
+                            if let Some(typ) = self.class_member_type(name, &member.node) {
+                                add(&member.node, typ, false);
+                            }
+                        }
+                    }
+                } else if self.type_env.get_interface(name).is_some() {
+                    let (chain, _) = self.type_env.interface_chain(name);
+                    for decl in chain {
+                        for member in &decl.members {
+                            match member {
+                                InterfaceMember::Property(prop) => {
+                                    add(&prop.name.node, property_type(prop), prop.is_optional)
+                                }
+                                InterfaceMember::Method(method) => {
+                                    add(&method.name.node, method_type(method), false)
+                                }
+                                InterfaceMember::Index(_) => {}
+                            }
+                        }
+                    }
+                } else {
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+        Some(members)
+    }
+}
+
+fn strip(typ: &Type) -> &Type {
+    match &typ.kind {
+        TypeKind::Parenthesized(inner) => strip(inner),
+        _ => typ,
+    }
+}
@@ -0,0 +1,136 @@
+use super::{function_type, SymbolKind, TypeChecker};
+use crate::ast::expression::Literal;
+use crate::ast::pattern::Pattern;
+use crate::ast::statement::{
+    EnumDeclaration, EnumValue, Parameter, PropertySignature, ENUM_HELPERS,
+};
+use crate::ast::types::{ObjectType, ObjectTypeMember, PrimitiveType, Type, TypeKind};
+use crate::ast::Ident;
+use crate::errors::TypeCheckError;
+
+impl TypeChecker {
+    /// An enum names both a type, the union of its member values, and a
+    /// value holding the members and the `values`, `fromName` and
+    /// `ordinal` helpers
+    pub(super) fn collect_enum(&mut self, decl: &EnumDeclaration) {
+        let span = decl.name.span;
+        let values: Vec<Type> = member_values(decl)
+            .into_iter()
+            .map(|value| Type::new(TypeKind::Literal(value), span))
+            .collect();
+        let enum_type = match values.as_slice() {
+            [single] => single.clone(),
+            _ => Type::new(TypeKind::Union(values), span),
+        };
+        self.type_env.register_alias(&decl.name.node, enum_type);
+
+        let mut members = Vec::new();
+        for (member, value) in decl.members.iter().zip(member_values(decl)) {
+            if ENUM_HELPERS.contains(&member.name.node.as_str()) {
+                self.report_error(
+                    member.name.span,
+                    TypeCheckError::ReservedEnumMember {
+                        enum_name: decl.name.node.clone(),
+                        member: member.name.node.clone(),
+                    },
+                );
+                continue;
+            }
+            members.push(property(
+                &member.name,
+                Type::new(TypeKind::Literal(value), member.span),
+            ));
+        }
+
+        let string = Type::new(TypeKind::Primitive(PrimitiveType::String), span);
+        let integer = Type::new(TypeKind::Primitive(PrimitiveType::Integer), span);
+        let reference = super::reference_type(&decl.name.node, span);
+        let helpers = [
+            (
+                "values",
+                function_type(
+                    &[],
+                    Some(&Type::new(
+                        TypeKind::Array(Box::new(reference.clone())),
+                        span,
+                    )),
+                    span,
+                ),
+            ),
+            (
+                "fromName",
+                function_type(
+                    &[parameter("name", string)],
+                    Some(&Type::new(
+                        TypeKind::Nullable(Box::new(reference.clone())),
+                        span,
+                    )),
+                    span,
+                ),
+            ),
+            (
+                "ordinal",
+                function_type(&[parameter("value", reference)], Some(&integer), span),
+            ),
+        ];
+        for (name, typ) in helpers {
+            members.push(property(&Ident::new(name.to_string(), span), typ));
+        }
+
+        self.declare_symbol(
+            &decl.name.node,
+            SymbolKind::Enum,
+            Type::new(TypeKind::Object(ObjectType { members, span }), span),
+            span,
+        );
+    }
+}
+
+/// The value of each member in declaration order. Members without a value
+/// count up from the previous number, starting at 1.
+fn member_values(decl: &EnumDeclaration) -> Vec<Literal> {
+    let mut next = 1.0;
+    decl.members
+        .iter()
+        .map(|member| match &member.value {
+            Some(EnumValue::String(s)) => Literal::String(s.clone()),
+            Some(EnumValue::Number(n)) => {
+                next = n + 1.0;
+                number_literal(*n)
+            }
+            None => {
+                next += 1.0;
+                number_literal(next - 1.0)
+            }
+        })
+        .collect()
+}
+
+fn number_literal(n: f64) -> Literal {
+    if n.fract() == 0.0 {
+        Literal::Integer(n as i64)
+    } else {
+        Literal::Number(n)
+    }
+}
+
+pub(super) fn property(name: &Ident, typ: Type) -> ObjectTypeMember {
+    ObjectTypeMember::Property(PropertySignature {
+        decorators: Vec::new(),
+        is_readonly: true,
+        name: name.clone(),
+        is_optional: false,
+        type_annotation: typ,
+        span: name.span,
+    })
+}
+
+pub(super) fn parameter(name: &str, typ: Type) -> Parameter {
+    Parameter {
+        span: typ.span,
+        pattern: Pattern::Identifier(Ident::new(name.to_string(), typ.span)),
+        type_annotation: Some(typ),
+        default: None,
+        is_rest: false,
+    }
+}
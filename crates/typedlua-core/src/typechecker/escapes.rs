@@ -0,0 +1,555 @@
+use super::{expression_label, TypeChecker};
+use crate::ast::expression::*;
+use crate::ast::pattern::{ArrayPatternElement, MultipleRest, Pattern};
+use crate::ast::statement::*;
+use crate::ast::types::{PrimitiveType, Type, TypeKind};
+use crate::span::Span;
+use std::collections::{HashMap, HashSet};
+
+/// Methods that register a callback with an event source
+const SUBSCRIBE_METHODS: &[&str] = &[
+    "on",
+    "connect",
+    "Connect",
+    "subscribe",
+    "listen",
+    "addListener",
+    "addEventListener",
+];
+
+/// Methods that remove a registered callback
+const UNSUBSCRIBE_METHODS: &[&str] = &[
+    "off",
+    "disconnect",
+    "Disconnect",
+    "unsubscribe",
+    "unlisten",
+    "removeListener",
+    "removeEventListener",
+    "removeAllListeners",
+];
+
+impl TypeChecker {
+    /// Warn about closures that commonly leak memory in long-running Lua
+    /// programs: callbacks capturing `self` that are handed to another
+    /// object with no way to remove them, and closures allocated on every
+    /// iteration of a loop to capture a table from outside it.
+    pub(super) fn check_closure_escapes(&mut self, statements: &[Statement]) {
+        let mut escapes = ClosureEscapes::default();
+        escapes.scopes.push(HashMap::new());
+        escapes.statements(statements);
+        for (span, message) in escapes.warnings {
+            self.report_warning(span, "TL3010", &message);
+        }
+    }
+}
+
+/// What a closure body reads from enclosing scopes
+struct Captures {
+    /// Scope depth at which the closure starts; names declared below it
+    /// are upvalues
+    depth: usize,
+    captures_self: bool,
+    /// Captured tables with the depth they were declared at
+    tables: Vec<(String, usize)>,
+}
+
+/// A callback capturing `self` that another object holds on to
+struct Registration {
+    span: Span,
+    label: String,
+    /// The member holding the callback for `obj.handler = fn`; `None` for
+    /// callbacks passed to a subscribe method
+    member: Option<String>,
+}
+
+/// Registrations within one class, and the ways it can undo them
+#[derive(Default)]
+struct ClassEscapes {
+    name: String,
+    registrations: Vec<Registration>,
+    unsubscribes: bool,
+    /// Member names some method sets back to `nil`
+    cleared: HashSet<String>,
+}
+
+#[derive(Default)]
+struct ClosureEscapes {
+    warnings: Vec<(Span, String)>,
+    /// Local names in scope, flagged when they hold a table
+    scopes: Vec<HashMap<String, bool>>,
+    /// Scope depth at the start of each enclosing loop of the current function
+    loops: Vec<usize>,
+    /// Closures being walked, innermost last
+    closures: Vec<Captures>,
+    class: Option<ClassEscapes>,
+}
+
+impl ClosureEscapes {
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.scopes.push(HashMap::new());
+        self.statements(&block.statements);
+        self.scopes.pop();
+    }
+
+    fn loop_body(&mut self, body: &Block) {
+        self.loops.push(self.scopes.len());
+        self.block(body);
+        self.loops.pop();
+    }
+
+    fn declare(&mut self, name: &str, is_table: bool) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), is_table);
+        }
+    }
+
+    fn declare_pattern(&mut self, pattern: &Pattern, is_table: bool) {
+        match pattern {
+            Pattern::Identifier(name) => self.declare(&name.node, is_table),
+            Pattern::Array(array) => {
+                for element in &array.elements {
+                    match element {
+                        ArrayPatternElement::Pattern(inner) => self.declare_pattern(inner, false),
+                        ArrayPatternElement::Rest(name) => self.declare(&name.node, true),
+                        ArrayPatternElement::Hole => {}
+                    }
+                }
+            }
+            Pattern::Object(object) => {
+                for property in &object.properties {
+                    match &property.value {
+                        Some(inner) => self.declare_pattern(inner, false),
+                        None => self.declare(&property.key.node, false),
+                    }
+                }
+            }
+            Pattern::Multiple(multiple) => {
+                for name in &multiple.names {
+                    self.declare(&name.node, false);
+                }
+                if let Some(MultipleRest::Capture(name)) = &multiple.rest {
+                    self.declare(&name.node, true);
+                }
+            }
+            Pattern::Literal(..) | Pattern::Wildcard(_) => {}
+        }
+    }
+
+    fn declare_parameters(&mut self, parameters: &[Parameter]) {
+        for param in parameters {
+            let is_table =
+                param.is_rest || param.type_annotation.as_ref().is_some_and(is_table_type);
+            self.declare_pattern(&param.pattern, is_table);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Variable(decl) => {
+                self.expression(&decl.initializer);
+                let is_table = is_table_constructor(&decl.initializer)
+                    || decl.type_annotation.as_ref().is_some_and(is_table_type);
+                self.declare_pattern(&decl.pattern, is_table);
+            }
+            Statement::Function(func) => {
+                self.declare(&func.name.node, false);
+                self.closure(func.span, &func.parameters, |walker| {
+                    walker.block(&func.body)
+                });
+            }
+            Statement::Class(class) => self.class(class),
+            Statement::Export(export) => match &export.kind {
+                ExportKind::Declaration(decl) => self.statement(decl),
+                ExportKind::Default(expr) | ExportKind::Equals(expr) => self.expression(expr),
+                ExportKind::Named(_) => {}
+            },
+            Statement::Namespace(namespace) => self.block(&namespace.body),
+            Statement::If(if_stmt) => {
+                self.expression(&if_stmt.condition);
+                self.block(&if_stmt.then_block);
+                for else_if in &if_stmt.else_ifs {
+                    self.expression(&else_if.condition);
+                    self.block(&else_if.block);
+                }
+                if let Some(else_block) = &if_stmt.else_block {
+                    self.block(else_block);
+                }
+            }
+            Statement::While(while_stmt) => {
+                self.expression(&while_stmt.condition);
+                self.loop_body(&while_stmt.body);
+            }
+            Statement::For(ForStatement::Numeric(for_num)) => {
+                self.expression(&for_num.start);
+                self.expression(&for_num.end);
+                if let Some(step) = &for_num.step {
+                    self.expression(step);
+                }
+                self.scopes.push(HashMap::new());
+                self.declare(&for_num.variable.node, false);
+                self.loop_body(&for_num.body);
+                self.scopes.pop();
+            }
+            Statement::For(ForStatement::Generic(for_gen)) => {
+                for iterator in &for_gen.iterators {
+                    self.expression(iterator);
+                }
+                self.scopes.push(HashMap::new());
+                for variable in &for_gen.variables {
+                    self.declare(&variable.node, false);
+                }
+                self.loop_body(&for_gen.body);
+                self.scopes.pop();
+            }
+            Statement::Repeat(repeat) => {
+                self.loop_body(&repeat.body);
+                self.expression(&repeat.until);
+            }
+            Statement::Labeled(labeled) => self.statement(&labeled.body),
+            Statement::Return(ret) => {
+                for value in &ret.values {
+                    self.expression(value);
+                }
+            }
+            // A call whose result is dropped leaves no handle to undo it with
+            Statement::Expression(expr) => match &expr.kind {
+                ExpressionKind::Call(..) | ExpressionKind::MethodCall(..) => self.call(expr, true),
+                _ => self.expression(expr),
+            },
+            Statement::Block(block) | Statement::Untyped(block) => self.block(block),
+            Statement::Interface(_)
+            | Statement::TypeAlias(_)
+            | Statement::Enum(_)
+            | Statement::Import(_)
+            | Statement::Declare(_)
+            | Statement::Break(_)
+            | Statement::Continue(_)
+            | Statement::Error(_) => {}
+        }
+    }
+
+    fn class(&mut self, class: &ClassDeclaration) {
+        let outer = self.class.replace(ClassEscapes {
+            name: class.name.node.clone(),
+            ..ClassEscapes::default()
+        });
+
+        for member in &class.members {
+            match member {
+                ClassMember::Property(prop) => {
+                    if let Some(initializer) = &prop.initializer {
+                        self.expression(initializer);
+                    }
+                }
+                ClassMember::Constructor(ctor) => {
+                    if let Some(body) = &ctor.body {
+                        self.member_body(&ctor.parameters, body);
+                    }
+                }
+                ClassMember::Method(method) => {
+                    if let Some(body) = &method.body {
+                        self.member_body(&method.parameters, body);
+                    }
+                }
+                ClassMember::Getter(getter) => self.member_body(&[], &getter.body),
+                ClassMember::Setter(setter) => {
+                    self.member_body(std::slice::from_ref(&setter.parameter), &setter.body);
+                }
+                ClassMember::StaticBlock(block) => self.member_body(&[], &block.body),
+            }
+        }
+
+        let escapes = std::mem::replace(&mut self.class, outer).unwrap_or_default();
+        for registration in escapes.registrations {
+            let message = if let Some(member) = &registration.member {
+                if escapes.cleared.contains(member) {
+                    continue;
+                }
+                format!(
+                    "Callback capturing 'self' is stored in '{}' and never cleared, which keeps '{}' instances alive; set it to nil when done",
+                    registration.label, escapes.name
+                )
+            } else {
+                if escapes.unsubscribes {
+                    continue;
+                }
+                format!(
+                    "Callback capturing 'self' is registered with '{}' and never unsubscribed, which keeps '{}' instances alive; keep the connection and disconnect it",
+                    registration.label, escapes.name
+                )
+            };
+            self.warnings.push((registration.span, message));
+        }
+    }
+
+    /// A member body runs as its own function, outside any loop
+    fn member_body(&mut self, parameters: &[Parameter], body: &Block) {
+        let loops = std::mem::take(&mut self.loops);
+        self.scopes.push(HashMap::new());
+        self.declare_parameters(parameters);
+        self.block(body);
+        self.scopes.pop();
+        self.loops = loops;
+    }
+
+    /// Walk a closure body, recording what it captures. A closure created
+    /// inside a loop is allocated again on every iteration.
+    fn closure(
+        &mut self,
+        span: Span,
+        parameters: &[Parameter],
+        body: impl FnOnce(&mut Self),
+    ) -> Captures {
+        let loop_depth = self.loops.last().copied();
+        let loops = std::mem::take(&mut self.loops);
+        self.closures.push(Captures {
+            depth: self.scopes.len(),
+            captures_self: false,
+            tables: Vec::new(),
+        });
+        self.scopes.push(HashMap::new());
+        self.declare_parameters(parameters);
+        body(self);
+        self.scopes.pop();
+        self.loops = loops;
+        let captures = self.closures.pop().expect("closure frame was pushed");
+
+        if let Some(loop_depth) = loop_depth {
+            let mut reported = HashSet::new();
+            for (name, depth) in &captures.tables {
+                if *depth < loop_depth && reported.insert(name) {
+                    self.warnings.push((
+                        span,
+                        format!(
+                            "Closure created inside a loop captures table '{}' as an upvalue, allocating a new closure every iteration; create it once outside the loop",
+                            name
+                        ),
+                    ));
+                }
+            }
+        }
+        captures
+    }
+
+    /// Record a read of `name` in every enclosing closure it is an upvalue of
+    fn capture(&mut self, name: &str) {
+        let found = self
+            .scopes
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(depth, scope)| scope.get(name).map(|is_table| (depth, *is_table)));
+        let Some((declared, true)) = found else {
+            return;
+        };
+        for closure in self.closures.iter_mut().rev() {
+            if declared >= closure.depth {
+                break;
+            }
+            closure.tables.push((name.to_string(), declared));
+        }
+    }
+
+    fn capture_self(&mut self) {
+        for closure in &mut self.closures {
+            closure.captures_self = true;
+        }
+    }
+
+    /// A call registering a callback that captures `self`. The handle it
+    /// returns is the way to undo it, so only `discarded` results count.
+    fn call(&mut self, expr: &Expression, discarded: bool) {
+        let (method, args) = match &expr.kind {
+            ExpressionKind::MethodCall(object, method, args) => {
+                self.expression(object);
+                (Some(method.node.as_str()), args)
+            }
+            ExpressionKind::Call(callee, args) => {
+                self.expression(callee);
+                let method = match &callee.kind {
+                    ExpressionKind::Member(_, member) => Some(member.node.as_str()),
+                    _ => None,
+                };
+                (method, args)
+            }
+            _ => return self.expression(expr),
+        };
+
+        if method.is_some_and(|m| UNSUBSCRIBE_METHODS.contains(&m)) {
+            if let Some(class) = &mut self.class {
+                class.unsubscribes = true;
+            }
+        }
+        let subscribes = discarded && method.is_some_and(|m| SUBSCRIBE_METHODS.contains(&m));
+        for arg in args {
+            if self.callback(&arg.value) && subscribes {
+                self.register(expr.span, expression_label(expr), None);
+            }
+        }
+    }
+
+    /// Walk a value that may be a closure, returning whether it captures `self`
+    fn callback(&mut self, value: &Expression) -> bool {
+        match &value.kind {
+            ExpressionKind::Function(func) => {
+                self.closure(value.span, &func.parameters, |walker| {
+                    walker.block(&func.body)
+                })
+                .captures_self
+            }
+            ExpressionKind::Arrow(arrow) => {
+                self.closure(value.span, &arrow.parameters, |walker| match &arrow.body {
+                    ArrowBody::Expression(body) => walker.expression(body),
+                    ArrowBody::Block(body) => walker.block(body),
+                })
+                .captures_self
+            }
+            ExpressionKind::Parenthesized(inner) => self.callback(inner),
+            _ => {
+                self.expression(value);
+                false
+            }
+        }
+    }
+
+    fn register(&mut self, span: Span, label: String, member: Option<String>) {
+        if let Some(class) = &mut self.class {
+            class.registrations.push(Registration {
+                span,
+                label,
+                member,
+            });
+        }
+    }
+
+    fn assignment(&mut self, target: &Expression, value: &Expression) {
+        self.expression(target);
+        let ExpressionKind::Member(object, member) = &target.kind else {
+            self.expression(value);
+            return;
+        };
+        if matches!(value.kind, ExpressionKind::Literal(Literal::Nil)) {
+            if let Some(class) = &mut self.class {
+                class.cleared.insert(member.node.clone());
+            }
+            return;
+        }
+        // A callback stored on `self` only forms a cycle, which Lua collects
+        if self.callback(value) && !is_self(object) {
+            self.register(
+                target.span,
+                expression_label(target),
+                Some(member.node.clone()),
+            );
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        match &expr.kind {
+            ExpressionKind::Identifier(name) if name == "self" => self.capture_self(),
+            ExpressionKind::SelfKeyword => self.capture_self(),
+            ExpressionKind::Identifier(name) => self.capture(name),
+            ExpressionKind::Literal(_) | ExpressionKind::SuperKeyword => {}
+            ExpressionKind::Assignment(target, _, value) => self.assignment(target, value),
+            ExpressionKind::Binary(_, left, right)
+            | ExpressionKind::Index(left, right)
+            | ExpressionKind::Pipe(left, right) => {
+                self.expression(left);
+                self.expression(right);
+            }
+            ExpressionKind::Unary(_, inner)
+            | ExpressionKind::Member(inner, _)
+            | ExpressionKind::OptionalMember(inner, _)
+            | ExpressionKind::Parenthesized(inner)
+            | ExpressionKind::TypeAssertion(inner, _) => self.expression(inner),
+            ExpressionKind::Call(..) | ExpressionKind::MethodCall(..) => self.call(expr, false),
+            ExpressionKind::Function(_) | ExpressionKind::Arrow(_) => {
+                self.callback(expr);
+            }
+            ExpressionKind::Array(elements) => {
+                for element in elements {
+                    match element {
+                        ArrayElement::Expression(value) | ArrayElement::Spread(value) => {
+                            self.expression(value)
+                        }
+                    }
+                }
+            }
+            ExpressionKind::Object(properties) => {
+                for property in properties {
+                    match property {
+                        ObjectProperty::Property { value, .. }
+                        | ObjectProperty::Spread { value, .. } => self.expression(value),
+                        ObjectProperty::Computed { key, value, .. } => {
+                            self.expression(key);
+                            self.expression(value);
+                        }
+                    }
+                }
+            }
+            ExpressionKind::Conditional(condition, then, otherwise) => {
+                self.expression(condition);
+                self.expression(then);
+                self.expression(otherwise);
+            }
+            ExpressionKind::Match(match_expr) => {
+                self.expression(&match_expr.value);
+                for arm in &match_expr.arms {
+                    self.scopes.push(HashMap::new());
+                    self.declare_pattern(&arm.pattern, false);
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard);
+                    }
+                    match &arm.body {
+                        MatchArmBody::Expression(body) => self.expression(body),
+                        MatchArmBody::Block(body) => self.block(body),
+                    }
+                    self.scopes.pop();
+                }
+            }
+            ExpressionKind::Template(template) => {
+                for part in &template.parts {
+                    if let TemplatePart::Expression(inner) | TemplatePart::Formatted(inner, _) =
+                        part
+                    {
+                        self.expression(inner);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn is_self(expr: &Expression) -> bool {
+    match &expr.kind {
+        ExpressionKind::Identifier(name) => name == "self",
+        ExpressionKind::SelfKeyword => true,
+        ExpressionKind::Parenthesized(inner) => is_self(inner),
+        _ => false,
+    }
+}
+
+fn is_table_constructor(expr: &Expression) -> bool {
+    matches!(
+        expr.kind,
+        ExpressionKind::Object(_) | ExpressionKind::Array(_)
+    )
+}
+
+fn is_table_type(typ: &Type) -> bool {
+    match &typ.kind {
+        TypeKind::Array(_)
+        | TypeKind::Tuple(_)
+        | TypeKind::Object(_)
+        | TypeKind::Primitive(PrimitiveType::Table) => true,
+        TypeKind::Nullable(inner) | TypeKind::Parenthesized(inner) => is_table_type(inner),
+        _ => false,
+    }
+}
@@ -0,0 +1,129 @@
+//! Errors that come with a fix for editors and `typedlua fix` to apply
+
+use super::{without_nil, TypeChecker};
+use crate::ast::expression::Expression;
+use crate::ast::types::{Type, TypeKind};
+use crate::ast::Ident;
+use crate::diagnostics::{Applicability, Diagnostic, RelatedSpan, Replacement, SuggestedFix};
+use crate::errors::TypeCheckError;
+use crate::span::Span;
+
+impl TypeChecker {
+    /// Report a member `type_name` does not declare, offering the closest
+    /// of its `declared` names when the member looks like a misspelling
+    pub(super) fn report_unknown_property<'a>(
+        &mut self,
+        member: &Ident,
+        type_name: String,
+        declared: impl IntoIterator<Item = &'a str>,
+    ) {
+        let error = TypeCheckError::UnknownProperty {
+            property: member.node.clone(),
+            type_name,
+        };
+        let mut diagnostic =
+            Diagnostic::error(member.span, error.to_string()).with_code(error.code());
+        if let Some(name) = closest_name(&member.node, declared) {
+            // Another field is read, so the intent needs confirming
+            diagnostic = diagnostic.with_fixes(vec![SuggestedFix::replace(
+                format!("Change to '{}'", name),
+                member.span,
+                name,
+                Applicability::MaybeIncorrect,
+            )]);
+        }
+        self.report_diagnostic(diagnostic);
+    }
+
+    /// Check a value against the annotation `written` on its variable,
+    /// resolved to `annotation`. A value that only fails to fit because it
+    /// may be nil is reported with a fix adding `| nil` to the annotation,
+    /// which is safe to apply since annotations never reach the emitted Lua.
+    pub(super) fn check_annotated_value(
+        &mut self,
+        value: &Expression,
+        typ: &Type,
+        annotation: &Type,
+        written: &Type,
+        because: RelatedSpan,
+    ) {
+        let fits_without_nil = !self.is_assignable(typ, annotation)
+            && self.is_assignable(&without_nil(typ), annotation);
+        if !fits_without_nil {
+            self.check_value_assignable(value, typ, annotation, Some(because));
+            return;
+        }
+        self.trace_mismatch(value.span, typ, annotation);
+        let error = TypeCheckError::TypeMismatch {
+            expected: annotation.to_string(),
+            actual: typ.to_string(),
+        };
+        let mut replacements = Vec::new();
+        // `(a) -> b | nil` would make only the return type optional
+        if matches!(written.kind, TypeKind::Function(_)) {
+            replacements.push(Replacement {
+                span: Span::new(
+                    written.span.start,
+                    written.span.start,
+                    written.span.line,
+                    written.span.column,
+                ),
+                text: "(".to_string(),
+            });
+        }
+        let close = if replacements.is_empty() { "" } else { ")" };
+        replacements.push(Replacement {
+            span: end_of(written.span),
+            text: format!("{} | nil", close),
+        });
+        let fix = SuggestedFix {
+            message: "Allow nil in the annotation".to_string(),
+            replacements,
+            applicability: Applicability::MachineApplicable,
+        };
+        self.report_diagnostic(
+            Diagnostic::error(value.span, error.to_string())
+                .with_code(error.code())
+                .with_related(vec![because])
+                .with_fixes(vec![fix]),
+        );
+    }
+}
+
+/// The empty span just past `span`, assuming it ends on the line it starts
+fn end_of(span: Span) -> Span {
+    Span::new(span.end, span.end, span.line, span.column + span.len())
+}
+
+/// The declared name `name` is most likely a misspelling of: the nearest
+/// by edit distance, if a third of its characters or fewer differ
+fn closest_name<'a>(name: &str, declared: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    declared
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance, counting a swap of neighbouring characters as one
+/// edit, since transposed letters are the commonest typo
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![(0..=b.len()).collect::<Vec<usize>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let substitution = rows[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            row[j] = substitution.min(rows[i - 1][j] + 1).min(row[j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
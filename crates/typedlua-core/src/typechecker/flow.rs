@@ -0,0 +1,499 @@
+use super::{expression_label, is_nil, without_nil, NarrowingCause, TypeChecker};
+use crate::ast::expression::{BinaryOp, Expression, ExpressionKind, Literal, UnaryOp};
+use crate::ast::statement::{
+    Block, ForStatement, IfStatement, LabeledLoop, LoopJump, RepeatStatement, Statement,
+    WhileStatement,
+};
+use crate::ast::types::{PrimitiveType, Type, TypeKind};
+use crate::diagnostics::RelatedSpan;
+use crate::errors::TypeCheckError;
+use crate::span::Span;
+use std::collections::{HashMap, HashSet};
+
+/// Variables whose type control flow has narrowed, such as a `T?` known to
+/// be non-nil after a check
+pub(super) type Narrowing = HashMap<String, Type>;
+
+/// Narrowing state where control can leave a loop, collected per loop
+pub(super) type LoopExits = Vec<Narrowing>;
+
+impl TypeChecker {
+    /// The type of a variable at this point in the flow, if narrowed
+    pub(super) fn narrowed_type(&self, name: &str) -> Option<&Type> {
+        self.flow.get(name)
+    }
+
+    /// A new binding shadows whatever was known about the name
+    pub(super) fn forget_narrowing(&mut self, name: &str) {
+        self.flow.remove(name);
+    }
+
+    /// After `name = value`, the variable holds a non-nil value exactly
+    /// when the assigned value cannot be nil. `span` is the value's.
+    pub(super) fn narrow_assignment(&mut self, name: &str, value: &Type, span: Span) {
+        let Some(symbol) = self.symbols.lookup(name) else {
+            return;
+        };
+        if may_be_nil(&symbol.typ) && !may_be_nil(value) && !is_unknown(value) {
+            let narrowing = Narrowing::from([(name.to_string(), without_nil(&symbol.typ))]);
+            self.trace_narrowing(&narrowing, NarrowingCause::Assignment(span));
+            self.flow.extend(narrowing);
+        } else {
+            self.flow.remove(name);
+        }
+    }
+
+    /// Check a block in its own scope. Narrowings of names declared inside
+    /// it do not outlive it.
+    pub(super) fn check_scoped_statements(&mut self, statements: &[Statement]) {
+        let before = self.flow.clone();
+        self.symbols.enter_scope();
+        self.check_statements(statements);
+        let declared: Vec<String> = self.symbols.innermost_names().cloned().collect();
+        self.symbols.exit_scope();
+        for name in declared {
+            match before.get(&name) {
+                Some(typ) => self.flow.insert(name, typ.clone()),
+                None => self.flow.remove(&name),
+            };
+        }
+    }
+
+    /// Run a nested function body with a fresh flow: it may run at any
+    /// later time, when outer narrowings no longer hold
+    pub(super) fn with_fresh_flow<T>(&mut self, check: impl FnOnce(&mut Self) -> T) -> T {
+        let flow = std::mem::take(&mut self.flow);
+        let reachable = std::mem::replace(&mut self.reachable, true);
+        let loops = std::mem::take(&mut self.loop_exits);
+        let labels = std::mem::take(&mut self.loop_labels);
+        let result = check(self);
+        self.flow = flow;
+        self.reachable = reachable;
+        self.loop_exits = loops;
+        self.loop_labels = labels;
+        result
+    }
+
+    /// Mark the rest of the block unreachable, recording the state a
+    /// `break` carries out of the loop it leaves
+    pub(super) fn exit_flow(&mut self, statement: &Statement) {
+        if let (Statement::Break(jump), true) = (statement, self.reachable) {
+            let target = match &jump.label {
+                Some(label) => self
+                    .loop_labels
+                    .iter()
+                    .rfind(|(name, _)| name.node == label.node)
+                    .map(|(_, index)| *index),
+                None => self.loop_exits.len().checked_sub(1),
+            };
+            if let Some(exits) = target.and_then(|index| self.loop_exits.get_mut(index)) {
+                exits.push(self.flow.clone());
+            }
+        }
+        self.reachable = false;
+    }
+
+    /// A labeled loop's label names the exits of the loop it wraps, so it
+    /// must not shadow the label of an enclosing loop
+    pub(super) fn check_labeled_loop(&mut self, labeled: &LabeledLoop) {
+        let label = &labeled.label;
+        let outer = self
+            .loop_labels
+            .iter()
+            .find(|(name, _)| name.node == label.node);
+        if let Some((outer, _)) = outer {
+            let related = RelatedSpan::new(
+                outer.span,
+                format!("'{}' is first defined here", outer.node),
+            );
+            self.report_error_with_related(
+                label.span,
+                TypeCheckError::DuplicateLoopLabel(label.node.clone()),
+                vec![related],
+            );
+        }
+        self.loop_labels
+            .push((label.clone(), self.loop_exits.len()));
+        self.check_statement(&labeled.body);
+        self.loop_labels.pop();
+    }
+
+    pub(super) fn check_loop_jump(&mut self, jump: &LoopJump) {
+        if let Some(label) = &jump.label {
+            if !self
+                .loop_labels
+                .iter()
+                .any(|(name, _)| name.node == label.node)
+            {
+                self.report_error(
+                    label.span,
+                    TypeCheckError::UnknownLoopLabel(label.node.clone()),
+                );
+            }
+        }
+    }
+
+    pub(super) fn check_if(&mut self, if_stmt: &IfStatement) {
+        let mut outcomes = Vec::new();
+
+        self.check_expression(&if_stmt.condition);
+        let mut otherwise = self.flow.clone();
+        let branches = std::iter::once((&if_stmt.condition, &if_stmt.then_block)).chain(
+            if_stmt
+                .else_ifs
+                .iter()
+                .map(|else_if| (&else_if.condition, &else_if.block)),
+        );
+
+        for (i, (condition, block)) in branches.enumerate() {
+            self.flow = otherwise.clone();
+            // The first condition was checked before the loop
+            if i > 0 {
+                self.check_expression(condition);
+            }
+            // Both narrowings start from the state before the condition
+            otherwise.extend(self.traced_narrowing(condition, false));
+            let narrowing = self.traced_narrowing(condition, true);
+            self.flow.extend(narrowing);
+            outcomes.push(self.check_branch(&block.statements));
+        }
+
+        let reachable = self.reachable;
+        self.flow = otherwise;
+        match &if_stmt.else_block {
+            Some(block) => outcomes.push(self.check_branch(&block.statements)),
+            None => outcomes.push(Some(self.flow.clone())),
+        }
+        self.reachable = reachable;
+        self.merge_outcomes(outcomes);
+    }
+
+    pub(super) fn check_while(&mut self, while_stmt: &WhileStatement) {
+        let reachable = self.enter_loop(&while_stmt.body.statements);
+        let head = self.flow.clone();
+        self.check_expression(&while_stmt.condition);
+
+        let narrowing = self.traced_narrowing(&while_stmt.condition, true);
+        self.flow.extend(narrowing);
+        self.check_scoped_statements(&while_stmt.body.statements);
+
+        // `while true` only ends through `break`
+        let mut exits = self.loop_exits.pop().unwrap_or_default();
+        if !is_true_literal(&while_stmt.condition) {
+            let mut finished = head;
+            finished.extend(self.traced_narrowing(&while_stmt.condition, false));
+            exits.push(finished);
+        }
+        self.leave_loop(exits, reachable);
+    }
+
+    pub(super) fn check_repeat(&mut self, repeat: &RepeatStatement) {
+        let reachable = self.enter_loop(&repeat.body.statements);
+
+        // The `until` condition can see the body's locals
+        let before = self.flow.clone();
+        self.symbols.enter_scope();
+        self.check_statements(&repeat.body.statements);
+        self.check_expression(&repeat.until);
+        let mut finished = None;
+        if self.reachable {
+            let mut state = self.flow.clone();
+            state.extend(self.traced_narrowing(&repeat.until, true));
+            finished = Some(state);
+        }
+        let declared: Vec<String> = self.symbols.innermost_names().cloned().collect();
+        self.symbols.exit_scope();
+
+        let mut exits = self.loop_exits.pop().unwrap_or_default();
+        exits.extend(finished);
+        for exit in &mut exits {
+            for name in &declared {
+                match before.get(name) {
+                    Some(typ) => exit.insert(name.clone(), typ.clone()),
+                    None => exit.remove(name),
+                };
+            }
+        }
+        self.leave_loop(exits, reachable);
+    }
+
+    /// Check a `for` body, which may run any number of times
+    pub(super) fn check_for_body(&mut self, body: &Block) {
+        let reachable = self.enter_loop(&body.statements);
+        let head = self.flow.clone();
+        self.check_scoped_statements(&body.statements);
+        let mut exits = self.loop_exits.pop().unwrap_or_default();
+        exits.push(head);
+        self.leave_loop(exits, reachable);
+    }
+
+    /// Start a loop. Narrowings of variables the body may set to nil do not
+    /// survive the back edge, so they are dropped before the first pass.
+    fn enter_loop(&mut self, body: &[Statement]) -> bool {
+        for name in nil_assigned_names(body) {
+            self.flow.remove(&name);
+        }
+        self.loop_exits.push(Vec::new());
+        self.reachable
+    }
+
+    /// Continue after a loop from the states at each of its exits
+    fn leave_loop(&mut self, exits: LoopExits, reachable: bool) {
+        self.reachable = reachable;
+        let outcomes = exits.into_iter().map(Some).collect();
+        self.merge_outcomes(outcomes);
+    }
+
+    /// Check one arm of a branch, returning its state at the end, or `None`
+    /// when control never reaches the end
+    fn check_branch(&mut self, statements: &[Statement]) -> Option<Narrowing> {
+        let reachable = self.reachable;
+        self.reachable = true;
+        self.check_scoped_statements(statements);
+        let outcome = self.reachable.then(|| self.flow.clone());
+        self.reachable = reachable;
+        outcome
+    }
+
+    /// Join the states of paths meeting at one point: a variable stays
+    /// narrowed only if every path narrowed it the same way
+    fn merge_outcomes(&mut self, outcomes: Vec<Option<Narrowing>>) {
+        let mut reaching = outcomes.into_iter().flatten();
+        match reaching.next() {
+            Some(first) => {
+                self.flow = reaching.fold(first, |merged, state| {
+                    merged
+                        .into_iter()
+                        .filter(|(name, typ)| {
+                            state
+                                .get(name)
+                                .is_some_and(|other| other.to_string() == typ.to_string())
+                        })
+                        .collect()
+                });
+            }
+            None => self.reachable = false,
+        }
+    }
+
+    /// Under `strictNil`, reading through a value that may be nil is an
+    /// error. Checking continues with the non-nil type.
+    pub(super) fn check_dereference(&mut self, expr: &Expression, typ: Type) -> Type {
+        if !self.config.compiler_options.strict_nil || !may_be_nil(&typ) {
+            return typ;
+        }
+        self.report_error(
+            expr.span,
+            TypeCheckError::PossiblyNil(expression_label(expr)),
+        );
+        without_nil(&typ)
+    }
+
+    /// Under `strictNil`, a function declared to return values that are
+    /// never nil must not return one that may be
+    pub(super) fn check_nil_returns(&mut self, values: &[Expression], types: &[Type]) {
+        if !self.config.compiler_options.strict_nil {
+            return;
+        }
+        let Some(Some(declared)) = self.declared_returns.last().cloned() else {
+            return;
+        };
+        let expected = match &declared.kind {
+            TypeKind::Tuple(types) => types.clone(),
+            _ => vec![declared.clone()],
+        };
+        // A trailing `T...` takes every remaining value
+        let variadic = expected
+            .last()
+            .filter(|typ| matches!(typ.kind, TypeKind::Variadic(_)));
+        let because = RelatedSpan::new(declared.span, "expected due to this return type");
+        for (i, (value, typ)) in values.iter().zip(types).enumerate() {
+            let Some(expected) = expected.get(i).or(variadic) else {
+                break;
+            };
+            let expected = match &expected.kind {
+                TypeKind::Variadic(inner) => inner.as_ref(),
+                _ => expected,
+            };
+            if may_be_nil(typ) && !matches!(expected.kind, TypeKind::Asserts(_)) {
+                self.check_assignable(typ, expected, value.span, Some(because.clone()));
+            }
+        }
+    }
+
+    /// Type an expression evaluated only when `condition` is truthy (or
+    /// falsy), such as the right side of `and`
+    pub(super) fn check_narrowed(
+        &mut self,
+        expr: &Expression,
+        condition: &Expression,
+        truthy: bool,
+    ) -> Type {
+        let saved = self.flow.clone();
+        let narrowing = self.traced_narrowing(condition, truthy);
+        self.flow.extend(narrowing);
+        let typ = self.check_expression(expr);
+        self.flow = saved;
+        typ
+    }
+
+    /// Narrowings implied by a condition being truthy (or falsy)
+    pub(super) fn condition_narrowing(&self, condition: &Expression, truthy: bool) -> Narrowing {
+        match &condition.kind {
+            ExpressionKind::Parenthesized(inner) => self.condition_narrowing(inner, truthy),
+            ExpressionKind::Unary(UnaryOp::Not, inner) => self.condition_narrowing(inner, !truthy),
+            ExpressionKind::Identifier(name) if truthy => self.non_nil(name),
+            ExpressionKind::Member(..) | ExpressionKind::Index(..) => {
+                self.presence_narrowing(condition, truthy)
+            }
+            ExpressionKind::Binary(op @ (BinaryOp::Equal | BinaryOp::NotEqual), left, right) => {
+                let equal = (*op == BinaryOp::Equal) == truthy;
+                let mut narrowing = match (&left.kind, &right.kind) {
+                    (_, ExpressionKind::Literal(_)) => {
+                        self.discriminant_narrowing(left, right, equal)
+                    }
+                    (ExpressionKind::Literal(_), _) => {
+                        self.discriminant_narrowing(right, left, equal)
+                    }
+                    _ => Narrowing::new(),
+                };
+                // `x ~= nil` when truthy, `x == nil` when falsy
+                if equal {
+                    return narrowing;
+                }
+                narrowing.extend(match (&left.kind, &right.kind) {
+                    (ExpressionKind::Identifier(name), ExpressionKind::Literal(Literal::Nil))
+                    | (ExpressionKind::Literal(Literal::Nil), ExpressionKind::Identifier(name)) => {
+                        self.non_nil(name)
+                    }
+                    _ => Narrowing::new(),
+                });
+                narrowing
+            }
+            // Both sides of `a and b` hold when it is truthy, both sides of
+            // `a or b` fail when it is falsy
+            ExpressionKind::Binary(BinaryOp::And, left, right) if truthy => {
+                let mut narrowing = self.condition_narrowing(left, true);
+                narrowing.extend(self.condition_narrowing(right, true));
+                narrowing
+            }
+            ExpressionKind::Binary(BinaryOp::Or, left, right) if !truthy => {
+                let mut narrowing = self.condition_narrowing(left, false);
+                narrowing.extend(self.condition_narrowing(right, false));
+                narrowing
+            }
+            ExpressionKind::Binary(op @ (BinaryOp::And | BinaryOp::Or), left, right) => {
+                let expected = *op == BinaryOp::Or;
+                let left = self.condition_narrowing(left, expected);
+                let right = self.condition_narrowing(right, expected);
+                left.into_iter()
+                    .filter(|(name, typ)| {
+                        right
+                            .get(name)
+                            .is_some_and(|other| other.to_string() == typ.to_string())
+                    })
+                    .collect()
+            }
+            _ => Narrowing::new(),
+        }
+    }
+
+    fn non_nil(&self, name: &str) -> Narrowing {
+        let current = match self.narrowed_type(name) {
+            Some(typ) => typ,
+            None => match self.symbols.lookup(name) {
+                Some(symbol) => &symbol.typ,
+                None => return Narrowing::new(),
+            },
+        };
+        if !may_be_nil(current) {
+            return Narrowing::new();
+        }
+        Narrowing::from([(name.to_string(), without_nil(current))])
+    }
+}
+
+/// Whether a declared type admits `nil`
+pub(super) fn may_be_nil(typ: &Type) -> bool {
+    match &typ.kind {
+        TypeKind::Nullable(_) => true,
+        TypeKind::Union(types) => types.iter().any(may_be_nil),
+        TypeKind::Parenthesized(inner) => may_be_nil(inner),
+        _ => is_nil(typ),
+    }
+}
+
+fn is_unknown(typ: &Type) -> bool {
+    matches!(typ.kind, TypeKind::Primitive(PrimitiveType::Unknown))
+}
+
+fn is_true_literal(condition: &Expression) -> bool {
+    match &condition.kind {
+        ExpressionKind::Literal(Literal::Boolean(value)) => *value,
+        ExpressionKind::Parenthesized(inner) => is_true_literal(inner),
+        _ => false,
+    }
+}
+
+/// Variables a loop body assigns something that might be nil. Values that
+/// are never nil (literals, tables, functions) keep a narrowing intact.
+fn nil_assigned_names(statements: &[Statement]) -> HashSet<String> {
+    let mut names = HashSet::new();
+    collect_nil_assignments(statements, &mut names);
+    names
+}
+
+fn collect_nil_assignments(statements: &[Statement], names: &mut HashSet<String>) {
+    for statement in statements {
+        match statement {
+            Statement::Expression(expr) => {
+                if let ExpressionKind::Assignment(target, _, value) = &expr.kind {
+                    if let ExpressionKind::Identifier(name) = &target.kind {
+                        if !is_never_nil(value) {
+                            names.insert(name.clone());
+                        }
+                    }
+                }
+            }
+            Statement::If(if_stmt) => {
+                collect_nil_assignments(&if_stmt.then_block.statements, names);
+                for else_if in &if_stmt.else_ifs {
+                    collect_nil_assignments(&else_if.block.statements, names);
+                }
+                if let Some(block) = &if_stmt.else_block {
+                    collect_nil_assignments(&block.statements, names);
+                }
+            }
+            Statement::While(while_stmt) => {
+                collect_nil_assignments(&while_stmt.body.statements, names)
+            }
+            Statement::Repeat(repeat) => collect_nil_assignments(&repeat.body.statements, names),
+            Statement::Labeled(labeled) => {
+                collect_nil_assignments(std::slice::from_ref(labeled.body.as_ref()), names)
+            }
+            Statement::For(ForStatement::Numeric(numeric)) => {
+                collect_nil_assignments(&numeric.body.statements, names)
+            }
+            Statement::For(ForStatement::Generic(generic)) => {
+                collect_nil_assignments(&generic.body.statements, names)
+            }
+            Statement::Block(block) | Statement::Untyped(block) => {
+                collect_nil_assignments(&block.statements, names)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn is_never_nil(value: &Expression) -> bool {
+    match &value.kind {
+        ExpressionKind::Literal(literal) => !matches!(literal, Literal::Nil),
+        ExpressionKind::Object(_)
+        | ExpressionKind::Array(_)
+        | ExpressionKind::Function(_)
+        | ExpressionKind::Arrow(_)
+        | ExpressionKind::Template(_) => true,
+        ExpressionKind::Parenthesized(inner) => is_never_nil(inner),
+        _ => false,
+    }
+}
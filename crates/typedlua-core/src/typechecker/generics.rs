@@ -0,0 +1,491 @@
+use super::{is_nil, without_nil, MemberLookup, TypeChecker};
+use crate::ast::expression::{Argument, Expression, ExpressionKind, Literal};
+use crate::ast::statement::{
+    IndexKeyType, IndexSignature, InterfaceMember, Parameter, PropertySignature, TypeParameter,
+};
+use crate::ast::types::{
+    FunctionType, ObjectType, ObjectTypeMember, Type, TypeKind, TypeReference,
+};
+use crate::errors::TypeCheckError;
+use crate::span::Span;
+use std::collections::HashMap;
+
+/// Types inferred for a call's type parameters, each with the span of the
+/// argument it was inferred from
+type Bindings = HashMap<String, (Type, Span)>;
+
+impl TypeChecker {
+    /// Check a generic function's body with its type parameters in scope, so
+    /// values of a constrained type parameter have the constraint's members
+    pub(super) fn with_type_parameters<T>(
+        &mut self,
+        type_parameters: &Option<Vec<TypeParameter>>,
+        check: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let outer = self.type_parameters.len();
+        self.type_parameters
+            .extend(type_parameters.iter().flatten().cloned());
+        let result = check(self);
+        self.type_parameters.truncate(outer);
+        result
+    }
+
+    /// The constraint of a type parameter in scope that `typ` names
+    pub(super) fn type_parameter_bound(&self, typ: &Type) -> Option<Type> {
+        let TypeKind::Reference(reference) = &typ.kind else {
+            return None;
+        };
+        if reference.type_arguments.is_some() {
+            return None;
+        }
+        let param = self
+            .type_parameters
+            .iter()
+            .rev()
+            .find(|param| param.name.node == reference.name.node)?;
+        param.constraint.as_deref().cloned()
+    }
+
+    /// Infer the type arguments of a call to a generic function from its
+    /// arguments and check each against its constraint. A violation is
+    /// reported at the argument, with a note at the constraint.
+    pub(super) fn check_type_arguments(
+        &mut self,
+        callee: &Expression,
+        args: &[Argument],
+        arg_types: &[Type],
+    ) {
+        let ExpressionKind::Identifier(name) = &callee.kind else {
+            return;
+        };
+        let Some((type_parameters, parameters)) = self.type_env.get_generic_function(name).cloned()
+        else {
+            return;
+        };
+        if type_parameters
+            .iter()
+            .all(|param| param.constraint.is_none())
+        {
+            return;
+        }
+
+        let names: Vec<&str> = type_parameters
+            .iter()
+            .map(|p| p.name.node.as_str())
+            .collect();
+        let mut bindings = Bindings::new();
+        for ((param, arg), arg_type) in parameters.iter().zip(args).zip(arg_types) {
+            // Arguments after a spread no longer line up with parameters
+            if arg.is_spread {
+                break;
+            }
+            let Some(annotation) = &param.type_annotation else {
+                continue;
+            };
+            let arg_type = self
+                .constant_type(&arg.value)
+                .unwrap_or_else(|| arg_type.clone());
+            infer(annotation, &arg_type, arg.value.span, &names, &mut bindings);
+        }
+
+        let mut types: HashMap<String, Type> = bindings
+            .iter()
+            .map(|(name, (typ, _))| (name.clone(), typ.clone()))
+            .collect();
+        // Parameters the arguments say nothing about take their defaults
+        for param in &type_parameters {
+            if let (false, Some(default)) = (types.contains_key(&param.name.node), &param.default) {
+                let default = substitute(default, &types);
+                types.insert(param.name.node.clone(), default);
+            }
+        }
+        for param in &type_parameters {
+            let (Some(constraint), Some(argument)) =
+                (&param.constraint, types.get(&param.name.node))
+            else {
+                continue;
+            };
+            // A defaulted parameter has no argument to point at
+            let span = bindings
+                .get(&param.name.node)
+                .map_or(callee.span, |(_, span)| *span);
+            let constraint = substitute(constraint, &types);
+            if !self.satisfies_constraint(argument, &constraint) {
+                self.report_error(
+                    span,
+                    TypeCheckError::ConstraintNotSatisfied {
+                        argument: argument.to_string(),
+                        constraint: constraint.to_string(),
+                        parameter: param.name.node.clone(),
+                    },
+                );
+                self.report_note(
+                    param.span,
+                    "TL2051",
+                    &format!(
+                        "'{}' is constrained to '{}' here",
+                        param.name.node, constraint
+                    ),
+                );
+            }
+        }
+    }
+
+    /// The types an interface's type parameters stand for in a reference to
+    /// it: the written arguments, then the defaults for the rest. A default
+    /// may mention earlier parameters, as in `interface Pair<K, V = K>`.
+    fn type_argument_bindings(&self, typ: &Type) -> HashMap<String, Type> {
+        let mut bindings = HashMap::new();
+        let TypeKind::Reference(reference) = &typ.kind else {
+            return bindings;
+        };
+        let Some(interface) = self.type_env.get_interface(&reference.name.node) else {
+            return bindings;
+        };
+        let arguments = reference.type_arguments.as_deref().unwrap_or_default();
+        for (i, param) in interface.type_parameters.iter().flatten().enumerate() {
+            let bound = match (arguments.get(i), &param.default) {
+                (Some(argument), _) => argument.clone(),
+                (None, Some(default)) => substitute(default, &bindings),
+                (None, None) => continue,
+            };
+            bindings.insert(param.name.node.clone(), bound);
+        }
+        bindings
+    }
+
+    /// A member type read from a generic interface, in terms of the type
+    /// arguments of the reference it was read through
+    pub(super) fn instantiate_member(&self, object_type: &Type, member_type: Type) -> Type {
+        let bindings = self.type_argument_bindings(object_type);
+        if bindings.is_empty() {
+            member_type
+        } else {
+            substitute(&member_type, &bindings)
+        }
+    }
+
+    /// Every reference to a generic interface or class in an annotation
+    /// must give an argument for each type parameter without a default
+    pub(super) fn check_type_argument_count(&mut self, typ: &Type) {
+        match &typ.kind {
+            TypeKind::Reference(reference) => {
+                self.check_deprecated_use(&reference.name.node, reference.name.span);
+                self.check_utility_arguments(typ);
+                let type_parameters = match self.type_env.get_interface(&reference.name.node) {
+                    Some(interface) => interface.type_parameters.as_deref(),
+                    None => self
+                        .type_env
+                        .get_class(&reference.name.node)
+                        .and_then(|class| class.type_parameters.as_deref()),
+                };
+                let arguments = reference.type_arguments.as_deref().unwrap_or_default();
+                if let Some(type_parameters) = type_parameters {
+                    let total = type_parameters.len();
+                    let required = type_parameters
+                        .iter()
+                        .filter(|param| param.default.is_none())
+                        .count();
+                    if arguments.len() < required || arguments.len() > total {
+                        let expected = if required == total {
+                            total.to_string()
+                        } else {
+                            format!("{} to {}", required, total)
+                        };
+                        self.report_error(
+                            typ.span,
+                            TypeCheckError::TypeArgumentCount {
+                                name: reference.name.node.clone(),
+                                expected,
+                                actual: arguments.len(),
+                            },
+                        );
+                    }
+                }
+                for argument in arguments {
+                    self.check_type_argument_count(argument);
+                }
+            }
+            TypeKind::Array(inner)
+            | TypeKind::Nullable(inner)
+            | TypeKind::Parenthesized(inner)
+            | TypeKind::Variadic(inner)
+            | TypeKind::KeyOf(inner) => self.check_type_argument_count(inner),
+            TypeKind::Union(types) | TypeKind::Intersection(types) | TypeKind::Tuple(types) => {
+                for typ in types {
+                    self.check_type_argument_count(typ);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Type parameters with defaults must come last, and a default must
+    /// satisfy its parameter's constraint
+    pub(super) fn check_type_parameter_defaults(
+        &mut self,
+        type_parameters: &Option<Vec<TypeParameter>>,
+    ) {
+        let mut defaults = HashMap::new();
+        let mut seen_default = false;
+        for param in type_parameters.iter().flatten() {
+            let Some(default) = &param.default else {
+                if seen_default {
+                    self.report_error(
+                        param.name.span,
+                        TypeCheckError::RequiredTypeParameterAfterDefault(param.name.node.clone()),
+                    );
+                }
+                continue;
+            };
+            seen_default = true;
+            let default = substitute(default, &defaults);
+            if let Some(constraint) = &param.constraint {
+                let constraint = substitute(constraint, &defaults);
+                if !self.satisfies_constraint(&default, &constraint) {
+                    self.report_error(
+                        default.span,
+                        TypeCheckError::ConstraintNotSatisfied {
+                            argument: default.to_string(),
+                            constraint: constraint.to_string(),
+                            parameter: param.name.node.clone(),
+                        },
+                    );
+                }
+            }
+            defaults.insert(param.name.node.clone(), default);
+        }
+    }
+
+    fn satisfies_constraint(&self, typ: &Type, constraint: &Type) -> bool {
+        match (&typ.kind, &constraint.kind) {
+            (_, TypeKind::Parenthesized(inner)) => self.satisfies_constraint(typ, inner),
+            (TypeKind::Union(types), _) => types
+                .iter()
+                .all(|t| self.satisfies_constraint(t, constraint)),
+            (TypeKind::Literal(Literal::String(key)), TypeKind::KeyOf(target)) => {
+                match self.property_names(target) {
+                    Some(names) => names.contains(key),
+                    None => true,
+                }
+            }
+            (_, TypeKind::KeyOf(_)) => true,
+            (_, TypeKind::Object(_) | TypeKind::Reference(_)) => {
+                self.is_assignable(typ, constraint)
+                    && self.required_properties(constraint).iter().all(
+                        |(name, expected)| match self.property_lookup(typ, name) {
+                            MemberLookup::Found(actual) => self.is_assignable(&actual, expected),
+                            MemberLookup::Missing => false,
+                            _ => true,
+                        },
+                    )
+            }
+            _ => self.is_assignable(typ, constraint),
+        }
+    }
+
+    /// Names of the fields and methods of a table type, if all are known
+    pub(super) fn property_names(&self, typ: &Type) -> Option<Vec<String>> {
+        match &typ.kind {
+            TypeKind::Parenthesized(inner) => self.property_names(inner),
+            TypeKind::Object(object) => object
+                .members
+                .iter()
+                .map(|member| match member {
+                    ObjectTypeMember::Property(prop) => Some(prop.name.node.clone()),
+                    ObjectTypeMember::Method(method) => Some(method.name.node.clone()),
+                    ObjectTypeMember::Index(_) => None,
+                })
+                .collect(),
+            TypeKind::Reference(_) => {
+                let interface = self.interface_name(typ)?;
+                let (chain, complete) = self.type_env.interface_chain(&interface);
+                if !complete {
+                    return None;
+                }
+                chain
+                    .iter()
+                    .flat_map(|decl| &decl.members)
+                    .map(|member| match member {
+                        InterfaceMember::Property(prop) => Some(prop.name.node.clone()),
+                        InterfaceMember::Method(method) => Some(method.name.node.clone()),
+                        InterfaceMember::Index(_) => None,
+                    })
+                    .collect()
+            }
+            _ => None,
+        }
+    }
+
+    /// Properties a value must have to fit a constraint
+    fn required_properties(&self, constraint: &Type) -> Vec<(String, Type)> {
+        match &constraint.kind {
+            TypeKind::Object(object) => object
+                .members
+                .iter()
+                .filter_map(|member| match member {
+                    ObjectTypeMember::Property(prop) if !prop.is_optional => {
+                        Some((prop.name.node.clone(), prop.type_annotation.clone()))
+                    }
+                    _ => None,
+                })
+                .collect(),
+            TypeKind::Reference(_) => {
+                let Some(interface) = self.interface_name(constraint) else {
+                    return Vec::new();
+                };
+                let (chain, _) = self.type_env.interface_chain(&interface);
+                chain
+                    .iter()
+                    .flat_map(|decl| &decl.members)
+                    .filter_map(|member| match member {
+                        InterfaceMember::Property(prop) if !prop.is_optional => {
+                            Some((prop.name.node.clone(), prop.type_annotation.clone()))
+                        }
+                        _ => None,
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Look a property up on any table type without reporting anything
+    pub(super) fn property_lookup(&self, typ: &Type, name: &str) -> MemberLookup {
+        if let Some(expanded) = self.expand_utility(typ) {
+            return self.property_lookup(&expanded, name);
+        }
+        match &typ.kind {
+            TypeKind::Parenthesized(inner) => self.property_lookup(inner, name),
+            TypeKind::Object(object) => {
+                let mut open = false;
+                for member in &object.members {
+                    match member {
+                        ObjectTypeMember::Property(prop) if prop.name.node == name => {
+                            return MemberLookup::Found(super::property_type(prop));
+                        }
+                        ObjectTypeMember::Index(index)
+                            if index.key_type == IndexKeyType::String =>
+                        {
+                            open = true
+                        }
+                        _ => {}
+                    }
+                }
+                if open {
+                    MemberLookup::Unknown
+                } else {
+                    MemberLookup::Missing
+                }
+            }
+            TypeKind::Reference(reference) => {
+                if self.type_env.get_class(&reference.name.node).is_some() {
+                    return match self.class_member_type(&reference.name.node, name) {
+                        Some(typ) => MemberLookup::Found(typ),
+                        None => MemberLookup::Missing,
+                    };
+                }
+                match self.interface_name(typ) {
+                    Some(interface) => match self.lookup_interface_member(&interface, name) {
+                        MemberLookup::Found(member) => {
+                            MemberLookup::Found(self.instantiate_member(typ, member))
+                        }
+                        lookup => lookup,
+                    },
+                    None => MemberLookup::Unknown,
+                }
+            }
+            _ => MemberLookup::Unknown,
+        }
+    }
+}
+
+/// Bind the type parameters a parameter's annotation mentions to the parts
+/// of the argument's type in the same position. The first binding wins.
+fn infer(annotation: &Type, arg_type: &Type, span: Span, names: &[&str], bindings: &mut Bindings) {
+    match (&annotation.kind, &arg_type.kind) {
+        (TypeKind::Reference(reference), _)
+            if reference.type_arguments.is_none()
+                && names.contains(&reference.name.node.as_str()) =>
+        {
+            bindings
+                .entry(reference.name.node.clone())
+                .or_insert_with(|| (arg_type.clone(), span));
+        }
+        (TypeKind::Parenthesized(inner), _) => infer(inner, arg_type, span, names, bindings),
+        (_, TypeKind::Parenthesized(inner)) => infer(annotation, inner, span, names, bindings),
+        (TypeKind::Nullable(_), _) if is_nil(arg_type) => {}
+        (TypeKind::Nullable(inner), _) => {
+            infer(inner, &without_nil(arg_type), span, names, bindings)
+        }
+        (TypeKind::Array(inner), TypeKind::Array(element)) => {
+            infer(inner, element, span, names, bindings)
+        }
+        _ => {}
+    }
+}
+
+/// A type with the type parameters it mentions replaced by the types bound
+/// to them, such as a constraint `K extends keyof T` with the actual `T`
+fn substitute(typ: &Type, bindings: &HashMap<String, Type>) -> Type {
+    let each = |types: &[Type]| types.iter().map(|t| substitute(t, bindings)).collect();
+    let kind = match &typ.kind {
+        TypeKind::Reference(reference) => match &reference.type_arguments {
+            None => match bindings.get(&reference.name.node) {
+                Some(bound) => return bound.clone(),
+                None => return typ.clone(),
+            },
+            Some(arguments) => TypeKind::Reference(TypeReference {
+                type_arguments: Some(each(arguments)),
+                ..reference.clone()
+            }),
+        },
+        TypeKind::KeyOf(inner) => TypeKind::KeyOf(Box::new(substitute(inner, bindings))),
+        TypeKind::Array(inner) => TypeKind::Array(Box::new(substitute(inner, bindings))),
+        TypeKind::Nullable(inner) => TypeKind::Nullable(Box::new(substitute(inner, bindings))),
+        TypeKind::Variadic(inner) => TypeKind::Variadic(Box::new(substitute(inner, bindings))),
+        TypeKind::Parenthesized(inner) => {
+            TypeKind::Parenthesized(Box::new(substitute(inner, bindings)))
+        }
+        TypeKind::Union(types) => TypeKind::Union(each(types)),
+        TypeKind::Intersection(types) => TypeKind::Intersection(each(types)),
+        TypeKind::Tuple(types) => TypeKind::Tuple(each(types)),
+        TypeKind::Function(func) => TypeKind::Function(FunctionType {
+            parameters: func
+                .parameters
+                .iter()
+                .map(|param| Parameter {
+                    type_annotation: param
+                        .type_annotation
+                        .as_ref()
+                        .map(|t| substitute(t, bindings)),
+                    ..param.clone()
+                })
+                .collect(),
+            return_type: Box::new(substitute(&func.return_type, bindings)),
+            span: func.span,
+        }),
+        TypeKind::Object(object) => TypeKind::Object(ObjectType {
+            members: object
+                .members
+                .iter()
+                .map(|member| match member {
+                    ObjectTypeMember::Property(prop) => {
+                        ObjectTypeMember::Property(PropertySignature {
+                            type_annotation: substitute(&prop.type_annotation, bindings),
+                            ..prop.clone()
+                        })
+                    }
+                    ObjectTypeMember::Index(index) => ObjectTypeMember::Index(IndexSignature {
+                        value_type: substitute(&index.value_type, bindings),
+                        ..index.clone()
+                    }),
+                    ObjectTypeMember::Method(_) => member.clone(),
+                })
+                .collect(),
+            span: object.span,
+        }),
+        _ => return typ.clone(),
+    };
+    Type::new(kind, typ.span)
+}
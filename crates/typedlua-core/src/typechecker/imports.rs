@@ -0,0 +1,501 @@
+use super::TypeChecker;
+use crate::ast::expression::*;
+use crate::ast::pattern::{ArrayPatternElement, Pattern};
+use crate::ast::statement::*;
+use crate::ast::types::{ObjectTypeMember, TemplateLiteralTypePart, Type, TypeKind};
+use crate::ast::Ident;
+use crate::errors::TypeCheckError;
+use crate::span::Span;
+use std::collections::{HashMap, HashSet};
+
+impl TypeChecker {
+    /// Type-only imports are erased at emit, so they must not be used as
+    /// values. Value imports used only in type positions are flagged since
+    /// they load a module at runtime for nothing.
+    pub(super) fn check_import_usage(&mut self, statements: &[Statement]) {
+        let mut usage = NameUsage::default();
+        usage.statements(statements);
+
+        for statement in statements {
+            let Statement::Import(import) = statement else {
+                continue;
+            };
+            match &import.clause {
+                ImportClause::TypeOnly(specifiers) => {
+                    for specifier in specifiers {
+                        let local = specifier.local.as_ref().unwrap_or(&specifier.imported);
+                        if let Some(span) = usage.values.get(&local.node) {
+                            self.report_error(
+                                *span,
+                                TypeCheckError::TypeOnlyImportUsedAsValue(local.node.clone()),
+                            );
+                        }
+                    }
+                }
+                ImportClause::Named(specifiers) => {
+                    for specifier in specifiers {
+                        let local = specifier.local.as_ref().unwrap_or(&specifier.imported);
+                        self.warn_type_only_use(&usage, local);
+                    }
+                }
+                ImportClause::Default(local) | ImportClause::Namespace(local) => {
+                    self.warn_type_only_use(&usage, local);
+                }
+            }
+        }
+    }
+
+    fn warn_type_only_use(&mut self, usage: &NameUsage, local: &Ident) {
+        if usage.types.contains(&local.node) && !usage.values.contains_key(&local.node) {
+            self.report_warning(
+                local.span,
+                "TL3014",
+                &format!(
+                    "'{}' is only used as a type; use 'import type' so the module is not loaded at runtime",
+                    local.node
+                ),
+            );
+        }
+    }
+}
+
+/// Names referenced in value positions (with the first use) and in type
+/// positions. Scoping is ignored, which errs towards treating a name as used.
+#[derive(Default)]
+pub(crate) struct NameUsage {
+    pub(crate) values: HashMap<String, Span>,
+    pub(crate) types: HashSet<String>,
+    /// Values used other than to read a member off them: `Color` in
+    /// `Color[key]` or `paint(Color)`, but not in `Color.Red`
+    pub(crate) whole_values: HashSet<String>,
+    /// Members read directly off a name, as (name, member)
+    pub(crate) members: HashSet<(String, String)>,
+}
+
+impl NameUsage {
+    fn value(&mut self, name: &str, span: Span) {
+        self.values.entry(name.to_string()).or_insert(span);
+        self.whole_values.insert(name.to_string());
+    }
+
+    pub(crate) fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.statements(&block.statements);
+    }
+
+    pub(crate) fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Variable(decl) => {
+                self.decorators(&decl.decorators);
+                self.pattern(&decl.pattern);
+                self.optional_type(&decl.type_annotation);
+                self.expression(&decl.initializer);
+            }
+            Statement::Function(func) => {
+                self.type_parameters(&func.type_parameters);
+                self.parameters(&func.parameters);
+                self.optional_type(&func.return_type);
+                self.block(&func.body);
+            }
+            Statement::Class(class) => self.class(class),
+            Statement::Interface(interface) => {
+                self.type_parameters(&interface.type_parameters);
+                for extended in &interface.extends {
+                    self.typ(extended);
+                }
+                for member in &interface.members {
+                    match member {
+                        InterfaceMember::Property(prop) => self.typ(&prop.type_annotation),
+                        InterfaceMember::Method(method) => {
+                            self.type_parameters(&method.type_parameters);
+                            self.parameters(&method.parameters);
+                            self.typ(&method.return_type);
+                        }
+                        InterfaceMember::Index(index) => self.typ(&index.value_type),
+                    }
+                }
+            }
+            Statement::TypeAlias(alias) => {
+                self.type_parameters(&alias.type_parameters);
+                self.typ(&alias.type_annotation);
+            }
+            Statement::Enum(_) | Statement::Import(_) => {}
+            Statement::Export(export) => match &export.kind {
+                ExportKind::Declaration(decl) => self.statement(decl),
+                ExportKind::Named(specifiers) => {
+                    for specifier in specifiers {
+                        self.value(&specifier.local.node, specifier.local.span);
+                    }
+                }
+                ExportKind::Default(expr) | ExportKind::Equals(expr) => self.expression(expr),
+            },
+            Statement::Declare(decl) => {
+                self.decorators(&decl.decorators);
+                match &decl.kind {
+                    DeclareKind::Function(func) => {
+                        self.type_parameters(&func.type_parameters);
+                        self.parameters(&func.parameters);
+                        self.typ(&func.return_type);
+                    }
+                    DeclareKind::Variable(var) => self.typ(&var.type_annotation),
+                }
+            }
+            Statement::Namespace(namespace) => self.block(&namespace.body),
+            Statement::If(if_stmt) => {
+                self.expression(&if_stmt.condition);
+                self.block(&if_stmt.then_block);
+                for else_if in &if_stmt.else_ifs {
+                    self.expression(&else_if.condition);
+                    self.block(&else_if.block);
+                }
+                if let Some(else_block) = &if_stmt.else_block {
+                    self.block(else_block);
+                }
+            }
+            Statement::While(while_stmt) => {
+                self.expression(&while_stmt.condition);
+                self.block(&while_stmt.body);
+            }
+            Statement::For(ForStatement::Numeric(for_num)) => {
+                self.expression(&for_num.start);
+                self.expression(&for_num.end);
+                if let Some(step) = &for_num.step {
+                    self.expression(step);
+                }
+                self.block(&for_num.body);
+            }
+            Statement::For(ForStatement::Generic(for_gen)) => {
+                for iterator in &for_gen.iterators {
+                    self.expression(iterator);
+                }
+                self.block(&for_gen.body);
+            }
+            Statement::Repeat(repeat) => {
+                self.block(&repeat.body);
+                self.expression(&repeat.until);
+            }
+            Statement::Labeled(labeled) => self.statement(&labeled.body),
+            Statement::Return(ret) => {
+                for value in &ret.values {
+                    self.expression(value);
+                }
+            }
+            Statement::Break(_) | Statement::Continue(_) | Statement::Error(_) => {}
+            Statement::Expression(expr) => self.expression(expr),
+            Statement::Block(block) | Statement::Untyped(block) => self.block(block),
+        }
+    }
+
+    fn class(&mut self, class: &ClassDeclaration) {
+        self.decorators(&class.decorators);
+        self.type_parameters(&class.type_parameters);
+        // Base classes and mixins are runtime values: their tables are
+        // linked or copied into the class
+        for heritage in class.extends.iter().chain(&class.mixins) {
+            if let TypeKind::Reference(base) = &heritage.kind {
+                self.value(root_name(&base.name.node), base.name.span);
+            }
+        }
+        for implemented in &class.implements {
+            self.typ(implemented);
+        }
+        for member in &class.members {
+            match member {
+                ClassMember::Property(prop) => {
+                    self.decorators(&prop.decorators);
+                    self.typ(&prop.type_annotation);
+                    if let Some(initializer) = &prop.initializer {
+                        self.expression(initializer);
+                    }
+                }
+                ClassMember::Constructor(ctor) => {
+                    self.decorators(&ctor.decorators);
+                    self.parameters(&ctor.parameters);
+                    if let Some(body) = &ctor.body {
+                        self.block(body);
+                    }
+                }
+                ClassMember::Method(method) => {
+                    self.decorators(&method.decorators);
+                    self.type_parameters(&method.type_parameters);
+                    self.parameters(&method.parameters);
+                    self.optional_type(&method.return_type);
+                    if let Some(body) = &method.body {
+                        self.block(body);
+                    }
+                }
+                ClassMember::Getter(getter) => {
+                    self.decorators(&getter.decorators);
+                    self.typ(&getter.return_type);
+                    self.block(&getter.body);
+                }
+                ClassMember::Setter(setter) => {
+                    self.decorators(&setter.decorators);
+                    self.parameters(std::slice::from_ref(&setter.parameter));
+                    self.block(&setter.body);
+                }
+                ClassMember::StaticBlock(block) => self.block(&block.body),
+            }
+        }
+    }
+
+    fn decorators(&mut self, decorators: &[Decorator]) {
+        for decorator in decorators {
+            self.decorator_expression(&decorator.expression);
+        }
+    }
+
+    fn decorator_expression(&mut self, expression: &DecoratorExpression) {
+        match expression {
+            DecoratorExpression::Identifier(name) => self.value(&name.node, name.span),
+            DecoratorExpression::Call {
+                callee, arguments, ..
+            } => {
+                self.decorator_expression(callee);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            }
+            DecoratorExpression::Member { object, .. } => self.decorator_expression(object),
+        }
+    }
+
+    fn parameters(&mut self, parameters: &[Parameter]) {
+        for param in parameters {
+            self.pattern(&param.pattern);
+            self.optional_type(&param.type_annotation);
+            if let Some(default) = &param.default {
+                self.expression(default);
+            }
+        }
+    }
+
+    fn type_parameters(&mut self, type_parameters: &Option<Vec<TypeParameter>>) {
+        for param in type_parameters.iter().flatten() {
+            if let Some(constraint) = &param.constraint {
+                self.typ(constraint);
+            }
+            if let Some(default) = &param.default {
+                self.typ(default);
+            }
+        }
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Array(array) => {
+                for element in &array.elements {
+                    if let ArrayPatternElement::Pattern(inner) = element {
+                        self.pattern(inner);
+                    }
+                }
+            }
+            Pattern::Object(object) => {
+                for property in &object.properties {
+                    if let Some(value) = &property.value {
+                        self.pattern(value);
+                    }
+                    if let Some(default) = &property.default {
+                        self.expression(default);
+                    }
+                }
+            }
+            Pattern::Identifier(_)
+            | Pattern::Literal(..)
+            | Pattern::Wildcard(_)
+            | Pattern::Multiple(_) => {}
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        match &expr.kind {
+            ExpressionKind::Identifier(name) => self.value(name, expr.span),
+            ExpressionKind::Literal(_) | ExpressionKind::SelfKeyword => {}
+            ExpressionKind::SuperKeyword => {}
+            ExpressionKind::Binary(_, left, right)
+            | ExpressionKind::Assignment(left, _, right)
+            | ExpressionKind::Index(left, right)
+            | ExpressionKind::Pipe(left, right) => {
+                self.expression(left);
+                self.expression(right);
+            }
+            ExpressionKind::Member(object, member) => match &object.kind {
+                ExpressionKind::Identifier(name) => {
+                    self.values.entry(name.clone()).or_insert(object.span);
+                    self.members.insert((name.clone(), member.node.clone()));
+                }
+                _ => self.expression(object),
+            },
+            ExpressionKind::Unary(_, inner)
+            | ExpressionKind::OptionalMember(inner, _)
+            | ExpressionKind::Parenthesized(inner) => self.expression(inner),
+            ExpressionKind::Call(callee, args) | ExpressionKind::MethodCall(callee, _, args) => {
+                self.expression(callee);
+                for arg in args {
+                    self.expression(&arg.value);
+                }
+            }
+            ExpressionKind::Array(elements) => {
+                for element in elements {
+                    match element {
+                        ArrayElement::Expression(value) | ArrayElement::Spread(value) => {
+                            self.expression(value)
+                        }
+                    }
+                }
+            }
+            ExpressionKind::Object(properties) => {
+                for property in properties {
+                    match property {
+                        ObjectProperty::Property { value, .. }
+                        | ObjectProperty::Spread { value, .. } => self.expression(value),
+                        ObjectProperty::Computed { key, value, .. } => {
+                            self.expression(key);
+                            self.expression(value);
+                        }
+                    }
+                }
+            }
+            ExpressionKind::Function(func) => {
+                self.type_parameters(&func.type_parameters);
+                self.parameters(&func.parameters);
+                self.optional_type(&func.return_type);
+                self.block(&func.body);
+            }
+            ExpressionKind::Arrow(arrow) => {
+                self.parameters(&arrow.parameters);
+                self.optional_type(&arrow.return_type);
+                match &arrow.body {
+                    ArrowBody::Expression(body) => self.expression(body),
+                    ArrowBody::Block(body) => self.block(body),
+                }
+            }
+            ExpressionKind::Conditional(condition, then, otherwise) => {
+                self.expression(condition);
+                self.expression(then);
+                self.expression(otherwise);
+            }
+            ExpressionKind::Match(match_expr) => {
+                self.expression(&match_expr.value);
+                for arm in &match_expr.arms {
+                    self.pattern(&arm.pattern);
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard);
+                    }
+                    match &arm.body {
+                        MatchArmBody::Expression(body) => self.expression(body),
+                        MatchArmBody::Block(body) => self.block(body),
+                    }
+                }
+            }
+            ExpressionKind::Template(template) => {
+                for part in &template.parts {
+                    if let TemplatePart::Expression(inner) | TemplatePart::Formatted(inner, _) =
+                        part
+                    {
+                        self.expression(inner);
+                    }
+                }
+            }
+            ExpressionKind::TypeAssertion(inner, typ) => {
+                self.expression(inner);
+                self.typ(typ);
+            }
+        }
+    }
+
+    fn optional_type(&mut self, typ: &Option<Type>) {
+        if let Some(typ) = typ {
+            self.typ(typ);
+        }
+    }
+
+    fn typ(&mut self, typ: &Type) {
+        match &typ.kind {
+            TypeKind::Primitive(_) | TypeKind::Literal(_) => {}
+            TypeKind::Reference(reference) => {
+                self.types
+                    .insert(root_name(&reference.name.node).to_string());
+                for arg in reference.type_arguments.iter().flatten() {
+                    self.typ(arg);
+                }
+            }
+            TypeKind::Union(types) | TypeKind::Intersection(types) | TypeKind::Tuple(types) => {
+                for inner in types {
+                    self.typ(inner);
+                }
+            }
+            TypeKind::Object(object) => {
+                for member in &object.members {
+                    match member {
+                        ObjectTypeMember::Property(prop) => self.typ(&prop.type_annotation),
+                        ObjectTypeMember::Method(method) => {
+                            self.parameters(&method.parameters);
+                            self.typ(&method.return_type);
+                        }
+                        ObjectTypeMember::Index(index) => self.typ(&index.value_type),
+                    }
+                }
+            }
+            TypeKind::Array(inner)
+            | TypeKind::KeyOf(inner)
+            | TypeKind::Nullable(inner)
+            | TypeKind::Parenthesized(inner)
+            | TypeKind::Variadic(inner) => self.typ(inner),
+            TypeKind::Asserts(predicate) => {
+                if let Some(typ) = &predicate.type_predicate {
+                    self.typ(typ);
+                }
+            }
+            TypeKind::Function(func) => {
+                self.parameters(&func.parameters);
+                self.typ(&func.return_type);
+            }
+            // `typeof x` only needs the type of `x`, not its value
+            TypeKind::TypeQuery(expr) => {
+                if let Some(name) = expression_root(expr) {
+                    self.types.insert(name.to_string());
+                }
+            }
+            TypeKind::IndexAccess(object, index) => {
+                self.typ(object);
+                self.typ(index);
+            }
+            TypeKind::Conditional(cond) => {
+                self.typ(&cond.check_type);
+                self.typ(&cond.extends_type);
+                self.typ(&cond.true_type);
+                self.typ(&cond.false_type);
+            }
+            TypeKind::Mapped(mapped) => {
+                self.typ(&mapped.in_type);
+                self.typ(&mapped.value_type);
+            }
+            TypeKind::TemplateLiteral(template) => {
+                for part in &template.parts {
+                    if let TemplateLiteralTypePart::Type(inner) = part {
+                        self.typ(inner);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// `Ns` for a qualified name such as `Ns.Name`
+fn root_name(name: &str) -> &str {
+    name.split('.').next().unwrap_or(name)
+}
+
+fn expression_root(expr: &Expression) -> Option<&str> {
+    match &expr.kind {
+        ExpressionKind::Identifier(name) => Some(name),
+        ExpressionKind::Member(object, _) => expression_root(object),
+        _ => None,
+    }
+}
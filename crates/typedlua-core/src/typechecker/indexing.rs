@@ -0,0 +1,251 @@
+use super::readonly::{deep_readonly, readonly_inner};
+use super::{unknown_type, TypeChecker};
+use crate::ast::expression::{Expression, ExpressionKind, Literal};
+use crate::ast::statement::{IndexKeyType, InterfaceDeclaration, InterfaceMember};
+use crate::ast::types::{ObjectType, ObjectTypeMember, PrimitiveType, Type, TypeKind};
+use crate::ast::Ident;
+use crate::errors::TypeCheckError;
+
+impl TypeChecker {
+    /// Value type of an interface's index signature for the given key kind,
+    /// including signatures inherited from extended interfaces
+    pub(super) fn index_signature_type(
+        &self,
+        interface: &str,
+        key_type: IndexKeyType,
+    ) -> Option<Type> {
+        let (chain, _) = self.type_env.interface_chain(interface);
+        chain.iter().find_map(|decl| {
+            decl.members.iter().find_map(|member| match member {
+                InterfaceMember::Index(index) if index.key_type == key_type => {
+                    Some(index.value_type.clone())
+                }
+                _ => None,
+            })
+        })
+    }
+
+    /// Declared fields must fit the string index signature, and a number
+    /// index signature must fit the string one, since any field can also be
+    /// read through the string index
+    pub(super) fn check_index_signatures(&mut self, interface: &InterfaceDeclaration) {
+        let name = &self.qualify(&interface.name.node);
+        let Some(string_index) = self.index_signature_type(name, IndexKeyType::String) else {
+            return;
+        };
+
+        let mut conflicts = Vec::new();
+        for member in &interface.members {
+            let (member_name, member_type) = match member {
+                InterfaceMember::Property(prop) => (&prop.name, super::property_type(prop)),
+                InterfaceMember::Method(method) => (&method.name, super::method_type(method)),
+                InterfaceMember::Index(_) => continue,
+            };
+            if !self.is_assignable(&member_type, &string_index) {
+                conflicts.push((member_name.clone(), member_type));
+            }
+        }
+
+        for (member_name, member_type) in conflicts {
+            self.report_error(
+                member_name.span,
+                TypeCheckError::IndexSignatureConflict {
+                    property: member_name.node,
+                    property_type: member_type.to_string(),
+                    index_type: string_index.to_string(),
+                },
+            );
+        }
+
+        // Merged declarations share signatures; only check where one is declared
+        let declares_index = interface
+            .members
+            .iter()
+            .any(|m| matches!(m, InterfaceMember::Index(_)));
+        if !declares_index {
+            return;
+        }
+        if let Some(number_index) = self.index_signature_type(name, IndexKeyType::Number) {
+            if !self.is_assignable(&number_index, &string_index) {
+                self.report_error(
+                    number_index.span,
+                    TypeCheckError::IndexSignatureMismatch {
+                        number_type: number_index.to_string(),
+                        string_type: string_index.to_string(),
+                    },
+                );
+            }
+        }
+    }
+
+    /// Type of `object[index]`
+    pub(super) fn index_access_type(
+        &mut self,
+        object_type: &Type,
+        index: &Expression,
+        index_type: &Type,
+    ) -> Type {
+        if let Some(inner) = readonly_inner(object_type) {
+            let typ = self.index_access_type(&inner, index, index_type);
+            return deep_readonly(typ);
+        }
+        if let Some(expanded) = self.expand_utility(object_type) {
+            return self.index_access_type(&expanded, index, index_type);
+        }
+        match &object_type.kind {
+            TypeKind::Parenthesized(inner) => self.index_access_type(inner, index, index_type),
+            TypeKind::Array(element) => (**element).clone(),
+            // A constant key reads the same field of every member
+            TypeKind::Union(_) => match &index.kind {
+                ExpressionKind::Literal(Literal::String(key)) => {
+                    self.member_type(object_type, &Ident::new(key.clone(), index.span))
+                }
+                _ => unknown_type(index.span),
+            },
+            TypeKind::Object(object) => {
+                let key = match &index.kind {
+                    ExpressionKind::Literal(Literal::String(key)) => Some(key),
+                    _ => None,
+                };
+                let key_type = index_key_type(index_type);
+                object
+                    .members
+                    .iter()
+                    .find_map(|member| match member {
+                        ObjectTypeMember::Property(prop) if Some(&prop.name.node) == key => {
+                            Some(prop.type_annotation.clone())
+                        }
+                        ObjectTypeMember::Index(signature)
+                            if Some(signature.key_type) == key_type =>
+                        {
+                            Some(signature.value_type.clone())
+                        }
+                        _ => None,
+                    })
+                    .unwrap_or_else(|| unknown_type(index.span))
+            }
+            TypeKind::Reference(_) => {
+                let Some(interface) = self.interface_name(object_type) else {
+                    return unknown_type(index.span);
+                };
+
+                // A constant string key reads a field just like `.name` does
+                if let ExpressionKind::Literal(Literal::String(key)) = &index.kind {
+                    return self.member_type(object_type, &Ident::new(key.clone(), index.span));
+                }
+
+                let Some(key_type) = index_key_type(index_type) else {
+                    return unknown_type(index.span);
+                };
+
+                self.index_signature_type(&interface, key_type)
+                    .unwrap_or_else(|| unknown_type(index.span))
+            }
+            _ => unknown_type(index.span),
+        }
+    }
+
+    /// Check the iterator expressions of a generic `for`. For
+    /// `pairs(t)` / `ipairs(t)` over a typed table, returns the key and
+    /// value types for the loop variables.
+    pub(super) fn check_generic_for_iterators(
+        &mut self,
+        iterators: &[Expression],
+    ) -> Option<Vec<Type>> {
+        if let [iterator] = iterators {
+            if let ExpressionKind::Call(callee, args) = &iterator.kind {
+                if let (ExpressionKind::Identifier(name), [arg]) = (&callee.kind, args.as_slice()) {
+                    if name == "pairs" || name == "ipairs" {
+                        self.check_expression(callee);
+                        self.check_deterministic_call(callee, iterator.span);
+                        let table_type = self.check_expression(&arg.value);
+                        let table_type = self.check_dereference(&arg.value, table_type);
+                        if name == "ipairs" {
+                            self.check_sequence_use(&arg.value, &table_type, true);
+                        }
+                        return self.iteration_types(name == "ipairs", &table_type);
+                    }
+                }
+            }
+        }
+
+        for iterator in iterators {
+            self.check_expression(iterator);
+        }
+        None
+    }
+
+    fn iteration_types(&self, sequence_only: bool, table_type: &Type) -> Option<Vec<Type>> {
+        let span = table_type.span;
+        let primitive = |p| Type::new(TypeKind::Primitive(p), span);
+        if let Some(expanded) = self.expand_utility(table_type) {
+            return self.iteration_types(sequence_only, &expanded);
+        }
+
+        match &table_type.kind {
+            TypeKind::Parenthesized(inner) => self.iteration_types(sequence_only, inner),
+            TypeKind::Array(element) => {
+                Some(vec![primitive(PrimitiveType::Number), (**element).clone()])
+            }
+            TypeKind::Reference(_) => {
+                let interface = self.interface_name(table_type)?;
+                let number = self.index_signature_type(&interface, IndexKeyType::Number);
+                let string = if sequence_only {
+                    None
+                } else {
+                    self.index_signature_type(&interface, IndexKeyType::String)
+                };
+
+                match (string, number) {
+                    (Some(value), None) => Some(vec![primitive(PrimitiveType::String), value]),
+                    (None, Some(value)) => Some(vec![primitive(PrimitiveType::Number), value]),
+                    (Some(string_value), Some(number_value)) => Some(vec![
+                        Type::new(
+                            TypeKind::Union(vec![
+                                primitive(PrimitiveType::String),
+                                primitive(PrimitiveType::Number),
+                            ]),
+                            span,
+                        ),
+                        Type::new(TypeKind::Union(vec![string_value, number_value]), span),
+                    ]),
+                    (None, None) => None,
+                }
+            }
+            // A dictionary such as `Record<string, V>`
+            TypeKind::Object(object) if !sequence_only && dictionary_value(object).is_some() => {
+                let value = dictionary_value(object)?;
+                Some(vec![primitive(PrimitiveType::String), value.clone()])
+            }
+            // Only the array part has a single key and value type
+            TypeKind::Object(_) | TypeKind::Intersection(_) => {
+                let parts = self.table_parts(table_type)?;
+                let element = parts.array.filter(|_| sequence_only || !parts.fields)?;
+                Some(vec![primitive(PrimitiveType::Number), element])
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Which index signature a key of the given type reads
+fn index_key_type(key: &Type) -> Option<IndexKeyType> {
+    match &key.kind {
+        TypeKind::Primitive(PrimitiveType::String) | TypeKind::Literal(Literal::String(_)) => {
+            Some(IndexKeyType::String)
+        }
+        TypeKind::Primitive(PrimitiveType::Number | PrimitiveType::Integer)
+        | TypeKind::Literal(Literal::Number(_) | Literal::Integer(_)) => Some(IndexKeyType::Number),
+        _ => None,
+    }
+}
+
+/// The value type of a table shape made of only a string index signature
+fn dictionary_value(object: &ObjectType) -> Option<&Type> {
+    match object.members.as_slice() {
+        [ObjectTypeMember::Index(index)] if index.key_type == IndexKeyType::String => {
+            Some(&index.value_type)
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,103 @@
+use super::{is_nil, TypeChecker};
+use crate::ast::pattern::Pattern;
+use crate::ast::statement::FunctionDeclaration;
+use crate::ast::types::{ObjectTypeMember, PrimitiveType, Type, TypeKind};
+use crate::ast::Ident;
+
+/// A type the checker inferred where the source has no annotation
+#[derive(Debug, Clone)]
+pub struct InferredAnnotation {
+    pub target: AnnotationTarget,
+    pub typ: Type,
+}
+
+#[derive(Debug, Clone)]
+pub enum AnnotationTarget {
+    /// An unannotated local or const; the annotation goes after the name
+    Variable(Ident),
+    /// A function without a declared return type
+    ReturnType(Box<FunctionDeclaration>),
+}
+
+impl TypeChecker {
+    pub(super) fn record_variable_type(&mut self, pattern: &Pattern, typ: &Type) {
+        if let Pattern::Identifier(name) = pattern {
+            if is_writable(typ) {
+                self.inferred.push(InferredAnnotation {
+                    target: AnnotationTarget::Variable(name.clone()),
+                    typ: typ.clone(),
+                });
+            }
+        }
+    }
+
+    pub(super) fn record_return_type(&mut self, func: &FunctionDeclaration, returned: Vec<Type>) {
+        let typ = join_types(returned, func.name.span);
+        if is_writable(&typ) {
+            self.inferred.push(InferredAnnotation {
+                target: AnnotationTarget::ReturnType(Box::new(func.clone())),
+                typ,
+            });
+        }
+    }
+}
+
+/// Combine the types of several `return` statements. No value at all is
+/// `void`; `nil` alongside other types makes the result nullable.
+pub(super) fn join_types(types: Vec<Type>, span: crate::span::Span) -> Type {
+    let mut distinct: Vec<Type> = Vec::new();
+    let mut nullable = false;
+    for typ in types {
+        if is_nil(&typ) {
+            nullable = true;
+        } else if matches!(typ.kind, TypeKind::Primitive(PrimitiveType::Void)) {
+            continue;
+        } else if !distinct.iter().any(|t| t.to_string() == typ.to_string()) {
+            distinct.push(typ);
+        }
+    }
+
+    let joined = match distinct.len() {
+        0 if nullable => return Type::new(TypeKind::Primitive(PrimitiveType::Nil), span),
+        0 => return Type::new(TypeKind::Primitive(PrimitiveType::Void), span),
+        1 => distinct.remove(0),
+        _ => Type::new(TypeKind::Union(distinct), span),
+    };
+
+    if nullable {
+        Type::new(TypeKind::Nullable(Box::new(joined)), span)
+    } else {
+        joined
+    }
+}
+
+/// Whether a type is worth writing back into source: anything still
+/// containing `unknown` would only lose information
+fn is_writable(typ: &Type) -> bool {
+    match &typ.kind {
+        TypeKind::Primitive(PrimitiveType::Unknown) => false,
+        TypeKind::Primitive(_) | TypeKind::Reference(_) | TypeKind::Literal(_) => true,
+        TypeKind::Union(types) | TypeKind::Intersection(types) | TypeKind::Tuple(types) => {
+            types.iter().all(is_writable)
+        }
+        TypeKind::Array(inner)
+        | TypeKind::Nullable(inner)
+        | TypeKind::Parenthesized(inner)
+        | TypeKind::Variadic(inner) => is_writable(inner),
+        TypeKind::Function(func) => {
+            is_writable(&func.return_type)
+                && func
+                    .parameters
+                    .iter()
+                    .all(|p| p.type_annotation.as_ref().is_some_and(is_writable))
+        }
+        TypeKind::Object(object) => {
+            !object.members.is_empty()
+                && object.members.iter().all(|member| match member {
+                    ObjectTypeMember::Property(prop) => is_writable(&prop.type_annotation),
+                    _ => false,
+                })
+        }
+        _ => false,
+    }
+}
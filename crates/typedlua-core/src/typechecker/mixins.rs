@@ -0,0 +1,77 @@
+use super::{function_type, TypeChecker};
+use crate::ast::statement::{ClassDeclaration, ClassMember};
+use crate::ast::types::TypeKind;
+use crate::errors::TypeCheckError;
+use std::collections::{HashMap, HashSet};
+
+impl TypeChecker {
+    /// Every mixin must be a class, and mixins may only share a member when
+    /// they agree on its type or the class declares it itself
+    pub(super) fn check_mixins(&mut self, class: &ClassDeclaration) {
+        for mixin in &class.mixins {
+            let is_class = match &mixin.kind {
+                TypeKind::Reference(reference) => {
+                    self.type_env.get_class(&reference.name.node).is_some()
+                }
+                _ => false,
+            };
+            if !is_class {
+                self.report_error(mixin.span, TypeCheckError::InvalidMixin(mixin.to_string()));
+            }
+        }
+
+        let own: HashSet<&str> = class.members.iter().filter_map(member_name).collect();
+        // Member name -> (providing mixin, member type)
+        let mut provided: HashMap<String, (String, String)> = HashMap::new();
+        let mut conflicts = Vec::new();
+
+        for mixin in self.type_env.mixin_classes(class) {
+            for member in &mixin.members {
+                let Some(name) = member_name(member) else {
+                    continue;
+                };
+                if own.contains(name) {
+                    continue;
+                }
+                let typ = match member {
+                    ClassMember::Property(prop) => prop.type_annotation.to_string(),
+                    ClassMember::Method(method) => function_type(
+                        method.parameters_without_self(),
+                        method.return_type.as_ref(),
+                        method.span,
+                    )
+                    .to_string(),
+                    _ => continue,
+                };
+                match provided.get(name) {
+                    Some((first, existing)) if *existing != typ => {
+                        conflicts.push(TypeCheckError::MixinMemberConflict {
+                            member: name.to_string(),
+                            first: first.clone(),
+                            second: mixin.name.node.clone(),
+                        })
+                    }
+                    Some(_) => {}
+                    None => {
+                        provided.insert(name.to_string(), (mixin.name.node.clone(), typ));
+                    }
+                }
+            }
+        }
+
+        for conflict in conflicts {
+            self.report_error(class.name.span, conflict);
+        }
+    }
+}
+
+/// Name of an instance member; statics are not part of the instance type
+fn member_name(member: &ClassMember) -> Option<&str> {
+    match member {
+        ClassMember::Property(prop) if !prop.is_static => Some(&prop.name.node),
+        ClassMember::Method(method) if !method.is_static => Some(&method.name.node),
+        ClassMember::Getter(getter) if !getter.is_static => Some(&getter.name.node),
+        ClassMember::Setter(setter) if !setter.is_static => Some(&setter.name.node),
+        _ => None,
+    }
+}
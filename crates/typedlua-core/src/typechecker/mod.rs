@@ -0,0 +1,1558 @@
+mod aliases;
+mod assertion;
+mod assignability;
+mod attributes;
+mod calls;
+mod constants;
+mod coverage;
+mod data;
+mod determinism;
+mod diff;
+mod enums;
+mod escapes;
+mod fixes;
+mod flow;
+mod generics;
+mod imports;
+mod indexing;
+mod inference;
+mod mixins;
+mod modules;
+mod namespaces;
+mod numbers;
+mod operators;
+mod overrides;
+mod pipeline;
+mod probe;
+mod readonly;
+mod schemas;
+mod self_type;
+mod statics;
+mod stdlib;
+mod suppressions;
+mod symbol_table;
+mod tables;
+mod taint;
+mod targets;
+mod templates;
+mod type_environment;
+mod unions;
+mod using;
+mod utility;
+mod values;
+mod weak;
+
+#[cfg(test)]
+mod tests;
+
+pub use coverage::TypeCoverage;
+pub use diff::{DiffChange, DiffEntry, TypeDiff};
+pub(crate) use imports::NameUsage;
+pub use inference::{AnnotationTarget, InferredAnnotation};
+pub use probe::{NameTrace, NarrowingCause, TypeTrace};
+pub use symbol_table::{Symbol, SymbolKind, SymbolTable};
+pub use targets::{target_guard, unavailable_platforms, TargetGuard};
+pub use type_environment::TypeEnvironment;
+
+use crate::ast::attributes::has_attribute;
+use crate::ast::expression::*;
+use crate::ast::pattern::{ArrayPatternElement, Pattern};
+use crate::ast::statement::*;
+use crate::ast::types::{
+    FunctionType, ObjectType, ObjectTypeMember, PrimitiveType, Type, TypeKind,
+};
+use crate::ast::{Ident, Program};
+use crate::config::{CompilerConfig, LuaVersion};
+use crate::diagnostics::{Diagnostic, DiagnosticHandler, RelatedSpan};
+use crate::errors::{CompilationError, TypeCheckError};
+use crate::lexer::Directive;
+use crate::resolve::ModuleResolver;
+use crate::span::Span;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use targets::describe_targets;
+use type_environment::class_methods;
+
+/// Type checker for TypedLua programs
+pub struct TypeChecker {
+    config: Arc<CompilerConfig>,
+    diagnostic_handler: Arc<dyn DiagnosticHandler>,
+    type_env: TypeEnvironment,
+    symbols: SymbolTable,
+    /// Enclosing namespace blocks, outermost first
+    namespace_path: Vec<String>,
+    /// Types returned so far by each enclosing function
+    return_types: Vec<Vec<Type>>,
+    /// Declared return type of each enclosing function, if annotated
+    declared_returns: Vec<Option<Type>>,
+    /// Types inferred where the source has no annotation
+    inferred: Vec<InferredAnnotation>,
+    /// Variables narrowed by control flow at the current point
+    flow: flow::Narrowing,
+    /// Whether control can reach the current statement
+    reachable: bool,
+    /// Narrowing states at the `break`s of each enclosing loop
+    loop_exits: Vec<flow::LoopExits>,
+    /// Labels of the enclosing loops, each with its index in `loop_exits`
+    loop_labels: Vec<(Ident, usize)>,
+    /// What the most recently checked call proves if it returns normally
+    assertion: Option<flow::Narrowing>,
+    /// Errors swallowed so far by the innermost enclosing `untyped` block
+    suppressed_errors: Option<usize>,
+    /// The `// @tl-ignore` comments of the module
+    suppressions: Vec<suppressions::Suppression>,
+    /// Type parameters of the enclosing generic functions
+    type_parameters: Vec<TypeParameter>,
+    /// Array variables `nil` was written into, with where it happened
+    array_holes: HashMap<String, Span>,
+    /// `@weak` table variables and their modes
+    weak_tables: HashMap<String, String>,
+    /// `@deprecated` declarations and their messages
+    deprecated: HashMap<String, String>,
+    /// Lines where a `// @varargs-ok` directive lets calls pass any number
+    /// of arguments
+    varargs_ok: HashSet<usize>,
+    /// Whether a `// @deterministic` directive asks for operations whose
+    /// results vary between machines to be flagged
+    deterministic: bool,
+    /// Whether a `// @order-sensitive` directive asks for logic that
+    /// depends on the order `pairs` visits keys to be flagged
+    order_sensitive: bool,
+    /// The variables of each enclosing loop over `pairs` or `next`, in an
+    /// order-sensitive module
+    pairs_loops: Vec<Vec<String>>,
+    /// Whether the module is a data module (`.data.tl`), holding only
+    /// types and constant tables
+    data_module: bool,
+    /// Resolves imports, with the path of the file being checked
+    module_resolver: Option<(ModuleResolver, PathBuf)>,
+    /// Declaration files read to type imports
+    declaration_files: Vec<PathBuf>,
+    /// Declarations of the exports of TypedLua modules, by path
+    module_exports: Arc<HashMap<PathBuf, String>>,
+    /// The position `probe_offset` asks the type of, if any
+    probe: Option<probe::TypeProbe>,
+    /// How many expressions checked so far have a known type
+    coverage: TypeCoverage,
+    error_count: usize,
+}
+
+/// Result of looking a member up on an interface
+enum MemberLookup {
+    Found(Type),
+    /// Declared, but hidden by a `@target` guard
+    Unavailable(Vec<LuaVersion>),
+    /// Declared, but marked `@unavailableOn` the configured platform
+    UnavailableOnPlatform(String),
+    /// Definitely not a member
+    Missing,
+    /// The interface cannot be fully resolved, so nothing can be said
+    Unknown,
+}
+
+impl TypeChecker {
+    pub fn new(
+        config: Arc<CompilerConfig>,
+        diagnostic_handler: Arc<dyn DiagnosticHandler>,
+    ) -> Self {
+        let mut checker = TypeChecker {
+            config,
+            diagnostic_handler,
+            type_env: TypeEnvironment::new(),
+            symbols: SymbolTable::new(),
+            namespace_path: Vec::new(),
+            return_types: Vec::new(),
+            declared_returns: Vec::new(),
+            inferred: Vec::new(),
+            flow: flow::Narrowing::new(),
+            reachable: true,
+            loop_exits: Vec::new(),
+            loop_labels: Vec::new(),
+            assertion: None,
+            suppressed_errors: None,
+            suppressions: Vec::new(),
+            type_parameters: Vec::new(),
+            array_holes: HashMap::new(),
+            weak_tables: HashMap::new(),
+            deprecated: HashMap::new(),
+            varargs_ok: HashSet::new(),
+            deterministic: false,
+            order_sensitive: false,
+            pairs_loops: Vec::new(),
+            data_module: false,
+            module_resolver: None,
+            declaration_files: Vec::new(),
+            module_exports: Arc::default(),
+            probe: None,
+            coverage: TypeCoverage::default(),
+            error_count: 0,
+        };
+        checker.load_stdlib();
+        checker
+    }
+
+    /// Apply the `// @name` directives found while lexing the program.
+    /// `// @varargs-ok` relaxes arity checks on its line and the next, and
+    /// `// @deterministic` flags non-deterministic calls in the module, and
+    /// `// @order-sensitive` flags logic that depends on `pairs` order.
+    /// `// @tl-ignore CODE...` silences the diagnostics with those codes on
+    /// its line and in the statement starting on the next.
+    pub fn set_directives(&mut self, directives: &[Directive]) {
+        self.set_suppressions(directives);
+        for directive in directives {
+            match directive.name.as_str() {
+                "varargs-ok" => {
+                    self.varargs_ok.insert(directive.line);
+                    self.varargs_ok.insert(directive.line + 1);
+                }
+                "deterministic" => self.deterministic = true,
+                "order-sensitive" => self.order_sensitive = true,
+                _ => {}
+            }
+        }
+    }
+
+    /// Load the declarations of a declaration file (`.d.tl`) without checking
+    /// any code. Declarations guarded by `@target(...)` are only made visible
+    /// when the configured target matches.
+    pub fn load_declarations(&mut self, program: &Program) {
+        self.collect_declarations(&program.statements);
+    }
+
+    /// Type check a whole program, reporting problems through the diagnostic handler
+    pub fn check_program(&mut self, program: &Program) -> Result<(), CompilationError> {
+        self.cover_suppressed_statements(&program.statements);
+        self.collect_declarations(&program.statements);
+        self.check_module_exports(&program.statements);
+        self.check_schemas(&program.statements);
+        if self.data_module {
+            self.check_data_module(&program.statements);
+        }
+
+        self.check_statements(&program.statements);
+        self.check_import_usage(&program.statements);
+        self.check_closure_escapes(&program.statements);
+        self.check_taint_flows(&program.statements);
+        self.report_unused_suppressions();
+
+        if self.error_count > 0 {
+            return Err(CompilationError::TypeErrors(self.error_count));
+        }
+
+        Ok(())
+    }
+
+    /// Get the type environment built while checking
+    pub fn type_environment(&self) -> &TypeEnvironment {
+        &self.type_env
+    }
+
+    /// Get the global symbols known to the checker
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// Types the checker inferred for unannotated locals and function
+    /// return types. Only fully known types are recorded.
+    pub fn inferred_annotations(&self) -> &[InferredAnnotation] {
+        &self.inferred
+    }
+
+    // Error reporting
+    fn report_error(&mut self, span: Span, error: TypeCheckError) {
+        self.report_error_with_related(span, error, Vec::new());
+    }
+
+    /// Report an error along with the other places that explain it, such
+    /// as the annotation a value was expected to match
+    fn report_error_with_related(
+        &mut self,
+        span: Span,
+        error: TypeCheckError,
+        related: Vec<RelatedSpan>,
+    ) {
+        self.report_diagnostic(
+            Diagnostic::error(span, error.to_string())
+                .with_code(error.code())
+                .with_related(related),
+        );
+    }
+
+    /// Report an error built by the caller, such as one carrying fixes
+    fn report_diagnostic(&mut self, diagnostic: Diagnostic) {
+        if let Some(suppressed) = &mut self.suppressed_errors {
+            *suppressed += 1;
+            return;
+        }
+        if self.suppress(&diagnostic) {
+            return;
+        }
+        self.error_count += 1;
+        self.diagnostic_handler.report(diagnostic);
+    }
+
+    fn report_warning(&mut self, span: Span, code: &str, message: &str) {
+        let warning = Diagnostic::warning(span, message).with_code(code);
+        if !self.suppress(&warning) {
+            self.diagnostic_handler.report(warning);
+        }
+    }
+
+    /// Point at a declaration an error refers to, under the error's code
+    fn report_note(&mut self, span: Span, code: &str, message: &str) {
+        let note = Diagnostic::info(span, message).with_code(code);
+        if !self.suppress(&note) {
+            self.diagnostic_handler.report(note);
+        }
+    }
+
+    // First pass: register named declarations so they can be used before their definition
+    fn collect_declarations(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::Class(class) => {
+                    self.collect_class(class.clone());
+                    self.declare_symbol(
+                        &class.name.node,
+                        SymbolKind::Class,
+                        reference_type(&class.name.node, class.name.span),
+                        class.name.span,
+                    );
+                }
+                Statement::Interface(interface) => {
+                    self.collect_interface(interface);
+                    self.collect_schema(interface);
+                }
+                Statement::Function(func) => self.declare_function(func),
+                Statement::Declare(decl) => self.collect_ambient(decl),
+                Statement::Namespace(namespace) => self.collect_namespace(namespace),
+                Statement::TypeAlias(alias) => self.collect_type_alias(alias),
+                Statement::Enum(decl) => self.collect_enum(decl),
+                Statement::Export(export) => {
+                    if let ExportKind::Declaration(decl) = &export.kind {
+                        self.collect_declarations(std::slice::from_ref(decl));
+                    }
+                }
+                _ => {}
+            }
+            self.note_deprecation(statement);
+        }
+    }
+
+    fn collect_class(&mut self, class: ClassDeclaration) {
+        self.collect_class_constants(&class);
+        let name = class.name.clone();
+        let first = self
+            .type_env
+            .get_class(&name.node)
+            .map(|first| first.name.span);
+        if !self.type_env.register_class(class) {
+            let related = first
+                .map(|span| {
+                    RelatedSpan::new(span, format!("'{}' is first defined here", name.node))
+                })
+                .into_iter()
+                .collect();
+            self.report_error_with_related(
+                name.span,
+                TypeCheckError::DuplicateDeclaration(name.node),
+                related,
+            );
+        }
+    }
+
+    /// A module using `export =` evaluates to that one value, so it cannot
+    /// also export other values. Type-only exports are still allowed.
+    fn check_module_exports(&mut self, statements: &[Statement]) {
+        let mut equals = false;
+        let mut values = false;
+        let mut conflicts = Vec::new();
+
+        for statement in statements {
+            let Statement::Export(export) = statement else {
+                continue;
+            };
+            match &export.kind {
+                ExportKind::Equals(_) => {
+                    if equals || values {
+                        conflicts.push(export.span);
+                    }
+                    equals = true;
+                }
+                ExportKind::Declaration(decl) if !exports_value(decl) => {}
+                _ => {
+                    if equals {
+                        conflicts.push(export.span);
+                    }
+                    values = true;
+                }
+            }
+        }
+
+        for span in conflicts {
+            self.report_error(span, TypeCheckError::ExportEqualsConflict);
+        }
+    }
+
+    /// Register an interface with the members unavailable on the configured
+    /// target filtered out
+    fn collect_interface(&mut self, interface: &InterfaceDeclaration) {
+        let mut visible = interface.clone();
+        visible.members.clear();
+
+        for member in &interface.members {
+            let (name, decorators) = match member {
+                InterfaceMember::Property(prop) => (&prop.name, &prop.decorators),
+                InterfaceMember::Method(method) => (&method.name, &method.decorators),
+                InterfaceMember::Index(_) => {
+                    visible.members.push(member.clone());
+                    continue;
+                }
+            };
+
+            match self.guarded_targets(decorators) {
+                Some(targets) => {
+                    self.type_env
+                        .mark_member_unavailable(&interface.name.node, &name.node, targets)
+                }
+                None if self.hidden_on_platform(decorators) => self
+                    .type_env
+                    .mark_member_platform_unavailable(&interface.name.node, &name.node),
+                None => visible.members.push(member.clone()),
+            }
+        }
+
+        self.check_interface_merge(&visible);
+        self.type_env.merge_interface(visible);
+    }
+
+    /// A repeated interface declaration merges into the earlier one, so it
+    /// must agree on type parameters and on the type of shared properties
+    fn check_interface_merge(&mut self, interface: &InterfaceDeclaration) {
+        let Some(existing) = self.type_env.get_interface(&interface.name.node) else {
+            return;
+        };
+
+        let mut errors = Vec::new();
+        if type_parameter_names(&existing.type_parameters)
+            != type_parameter_names(&interface.type_parameters)
+        {
+            errors.push((
+                interface.name.span,
+                TypeCheckError::MergedTypeParameterMismatch(interface.name.node.clone()),
+            ));
+        }
+
+        for member in &interface.members {
+            let InterfaceMember::Property(prop) = member else {
+                continue;
+            };
+            let previous = existing.members.iter().find_map(|m| match m {
+                InterfaceMember::Property(p) if p.name.node == prop.name.node => Some(p),
+                _ => None,
+            });
+            let Some(previous) = previous else {
+                continue;
+            };
+            let expected = property_type(previous).to_string();
+            let actual = property_type(prop).to_string();
+            if expected != actual {
+                errors.push((
+                    prop.name.span,
+                    TypeCheckError::MergedPropertyMismatch {
+                        property: prop.name.node.clone(),
+                        expected,
+                        actual,
+                    },
+                ));
+            }
+        }
+
+        for (span, error) in errors {
+            self.report_error(span, error);
+        }
+    }
+
+    /// Bring an ambient `declare` into the global scope unless its target
+    /// guard excludes the configured target
+    fn collect_ambient(&mut self, decl: &DeclareDeclaration) {
+        let (name, kind, typ) = match &decl.kind {
+            DeclareKind::Function(func) => {
+                self.register_generic_function(&func.name, &func.type_parameters, &func.parameters);
+                (
+                    &func.name,
+                    SymbolKind::Function,
+                    function_type(&func.parameters, Some(&func.return_type), func.span),
+                )
+            }
+            DeclareKind::Variable(var) => (
+                &var.name,
+                match var.kind {
+                    VariableKind::Const | VariableKind::Using => SymbolKind::Const,
+                    VariableKind::Local => SymbolKind::Variable,
+                },
+                var.type_annotation.clone(),
+            ),
+        };
+
+        match self.guarded_targets(&decl.decorators) {
+            Some(targets) => self.type_env.mark_unavailable(&name.node, targets),
+            None if self.hidden_on_platform(&decl.decorators) => {
+                self.type_env.mark_platform_unavailable(&name.node)
+            }
+            None => self.declare_symbol(&name.node, kind, typ, name.span),
+        }
+    }
+
+    /// Whether an `@unavailableOn(...)` marker names the configured platform
+    fn hidden_on_platform(&self, decorators: &[Decorator]) -> bool {
+        match &self.config.compiler_options.platform {
+            Some(platform) => unavailable_platforms(decorators)
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(platform)),
+            None => false,
+        }
+    }
+
+    /// If a `@target` guard hides a declaration from the configured target,
+    /// return the targets that do provide it
+    fn guarded_targets(&mut self, decorators: &[Decorator]) -> Option<Vec<LuaVersion>> {
+        let guard = target_guard(decorators)?;
+
+        for unknown in &guard.unknown {
+            self.report_warning(
+                unknown.span,
+                "TL3003",
+                &format!("Unknown target '{}' in @target guard", unknown.node),
+            );
+        }
+
+        if guard.allows(self.config.compiler_options.target) {
+            None
+        } else {
+            Some(guard.targets)
+        }
+    }
+
+    fn declare_symbol(&mut self, name: &str, kind: SymbolKind, typ: Type, span: Span) {
+        self.forget_narrowing(name);
+        self.array_holes.remove(name);
+        self.weak_tables.remove(name);
+        self.deprecated.remove(name);
+        self.record_probe(span, &typ);
+        self.trace_declaration(name, span);
+        self.symbols.declare(Symbol {
+            name: name.to_string(),
+            kind,
+            typ,
+            span,
+        });
+    }
+
+    /// Where the variable an assignment targets was declared, as the reason
+    /// for the type its new value must have
+    fn declared_here(&self, target: &Expression) -> Option<RelatedSpan> {
+        let ExpressionKind::Identifier(name) = &target.kind else {
+            return None;
+        };
+        let symbol = self
+            .symbols
+            .lookup(name)
+            .filter(|symbol| symbol.span.line > 0)?;
+        Some(RelatedSpan::new(
+            symbol.span,
+            format!("'{}' is declared here as '{}'", name, symbol.typ),
+        ))
+    }
+
+    fn declare_function(&mut self, func: &FunctionDeclaration) {
+        let typ = function_type(&func.parameters, func.return_type.as_ref(), func.span);
+        self.register_generic_function(&func.name, &func.type_parameters, &func.parameters);
+        self.declare_symbol(&func.name.node, SymbolKind::Function, typ, func.name.span);
+    }
+
+    fn register_generic_function(
+        &mut self,
+        name: &Ident,
+        type_parameters: &Option<Vec<TypeParameter>>,
+        parameters: &[Parameter],
+    ) {
+        if let Some(type_parameters) = type_parameters {
+            self.type_env.register_generic_function(
+                &name.node,
+                type_parameters.clone(),
+                parameters.to_vec(),
+            );
+        }
+    }
+
+    /// Bind every name introduced by a pattern; only a plain identifier gets
+    /// the full type, destructured names are left unknown for now
+    fn declare_pattern(&mut self, pattern: &Pattern, kind: SymbolKind, typ: Type) {
+        match pattern {
+            Pattern::Identifier(ident) => self.declare_symbol(&ident.node, kind, typ, ident.span),
+            Pattern::Array(array) => {
+                for element in &array.elements {
+                    match element {
+                        ArrayPatternElement::Pattern(inner) => {
+                            self.declare_pattern(inner, kind, unknown_type(array.span))
+                        }
+                        ArrayPatternElement::Rest(ident) => self.declare_symbol(
+                            &ident.node,
+                            kind,
+                            unknown_type(ident.span),
+                            ident.span,
+                        ),
+                        ArrayPatternElement::Hole => {}
+                    }
+                }
+            }
+            Pattern::Object(object) => {
+                for property in &object.properties {
+                    match &property.value {
+                        Some(inner) => {
+                            self.declare_pattern(inner, kind, unknown_type(property.span))
+                        }
+                        None => self.declare_symbol(
+                            &property.key.node,
+                            kind,
+                            unknown_type(property.span),
+                            property.key.span,
+                        ),
+                    }
+                }
+            }
+            Pattern::Multiple(multiple) => self.declare_values(multiple, kind, &typ),
+            Pattern::Literal(..) | Pattern::Wildcard(_) => {}
+        }
+    }
+
+    fn declare_parameters(&mut self, parameters: &[Parameter]) {
+        for param in parameters {
+            let typ = match &param.type_annotation {
+                Some(annotation) => self.resolve_annotation(annotation),
+                None => unknown_type(param.span),
+            };
+            self.declare_pattern(&param.pattern, SymbolKind::Parameter, typ);
+        }
+    }
+
+    /// Check a function body in its own scope with its parameters bound
+    /// Check a function body in its own scope, returning the types of the
+    /// values it returns
+    fn check_function_body(
+        &mut self,
+        parameters: &[Parameter],
+        return_type: Option<&Type>,
+        body: &Block,
+    ) -> Vec<Type> {
+        self.with_fresh_flow(|checker| {
+            checker.symbols.enter_scope();
+            checker.return_types.push(Vec::new());
+            let declared = return_type.map(|typ| checker.expand_aliases(typ, 0));
+            checker.declared_returns.push(declared);
+            checker.declare_parameters(parameters);
+            checker.check_block(body);
+            checker.symbols.exit_scope();
+            checker.declared_returns.pop();
+            checker.return_types.pop().unwrap_or_default()
+        })
+    }
+
+    fn check_statement(&mut self, statement: &Statement) {
+        self.check_attributes(statement);
+        match statement {
+            Statement::Variable(
+                decl @ VariableDeclaration {
+                    type_annotation: Some(annotation),
+                    ..
+                },
+            ) if self.data_module => {
+                let annotation = self.resolve_annotation(annotation);
+                self.check_data_variable(decl, &annotation);
+                self.declare_pattern(&decl.pattern, SymbolKind::Const, annotation);
+            }
+            Statement::Variable(decl) => {
+                let inferred = self.check_expression(&decl.initializer);
+                let inferred = values::bound_values(&decl.pattern, &decl.initializer, inferred);
+                let kind = match decl.kind {
+                    VariableKind::Const | VariableKind::Using => SymbolKind::Const,
+                    VariableKind::Local => SymbolKind::Variable,
+                };
+                let typ = match &decl.type_annotation {
+                    Some(annotation) => {
+                        let because =
+                            RelatedSpan::new(annotation.span, "expected due to this annotation");
+                        let resolved = self.resolve_annotation(annotation);
+                        self.check_annotated_value(
+                            &decl.initializer,
+                            &inferred,
+                            &resolved,
+                            annotation,
+                            because,
+                        );
+                        resolved
+                    }
+                    // A bare `nil` says nothing about what the variable will hold
+                    None if is_nil(&inferred) => unknown_type(decl.initializer.span),
+                    None => {
+                        let widened = self.widen_mutable(&decl.initializer, inferred.clone());
+                        self.record_variable_type(&decl.pattern, &widened);
+                        let constant = match decl.kind {
+                            VariableKind::Const => self.constant_type(&decl.initializer),
+                            VariableKind::Local | VariableKind::Using => None,
+                        };
+                        constant.unwrap_or(widened)
+                    }
+                };
+                if decl.kind == VariableKind::Using {
+                    self.check_closable(decl, &typ);
+                }
+                self.declare_pattern(&decl.pattern, kind, typ);
+                self.declare_weak_table(decl);
+                self.note_deprecation(statement);
+                if let (Some(_), Pattern::Identifier(name)) = (&decl.type_annotation, &decl.pattern)
+                {
+                    self.narrow_assignment(&name.node, &inferred, decl.initializer.span);
+                }
+            }
+            Statement::Function(func) => {
+                self.declare_function(func);
+                self.note_deprecation(statement);
+                self.check_type_parameter_defaults(&func.type_parameters);
+                self.check_assertion_signature(&func.parameters, func.return_type.as_ref());
+                let returned = self.with_type_parameters(&func.type_parameters, |checker| {
+                    checker.check_function_body(
+                        &func.parameters,
+                        func.return_type.as_ref(),
+                        &func.body,
+                    )
+                });
+                if func.return_type.is_none() {
+                    self.record_return_type(func, returned);
+                }
+            }
+            Statement::Class(class) => {
+                self.check_type_parameter_defaults(&class.type_parameters);
+                self.check_weak_class(class);
+                self.check_class(class)
+            }
+            Statement::Interface(interface) => {
+                self.check_type_parameter_defaults(&interface.type_parameters);
+                self.check_index_signatures(interface)
+            }
+            Statement::TypeAlias(alias) => self.check_type_alias(alias),
+            Statement::Enum(_) => {}
+            Statement::Import(import) => self.check_import(import),
+            Statement::Declare(_) => {}
+            // The parser has already reported it
+            Statement::Error(_) => {}
+            Statement::Namespace(namespace) => self.check_namespace(namespace),
+            Statement::Export(export) => match &export.kind {
+                ExportKind::Declaration(decl) => self.check_statement(decl),
+                ExportKind::Default(expr) | ExportKind::Equals(expr) => {
+                    self.check_expression(expr);
+                }
+                ExportKind::Named(_) => {}
+            },
+            Statement::If(if_stmt) => self.check_if(if_stmt),
+            Statement::While(while_stmt) => self.check_while(while_stmt),
+            Statement::For(for_stmt) => match for_stmt {
+                ForStatement::Numeric(numeric) => {
+                    let start = self.check_expression(&numeric.start);
+                    self.check_expression(&numeric.end);
+                    let step = numeric
+                        .step
+                        .as_ref()
+                        .map(|step| self.check_expression(step));
+                    // Integer start and step make an integer loop
+                    let primitive = if self.has_integer_subtype()
+                        && operators::is_integer(&start)
+                        && step.as_ref().is_none_or(operators::is_integer)
+                    {
+                        PrimitiveType::Integer
+                    } else {
+                        PrimitiveType::Number
+                    };
+                    self.symbols.enter_scope();
+                    self.declare_symbol(
+                        &numeric.variable.node,
+                        SymbolKind::Variable,
+                        Type::new(TypeKind::Primitive(primitive), numeric.variable.span),
+                        numeric.variable.span,
+                    );
+                    self.check_for_body(&numeric.body);
+                    self.symbols.exit_scope();
+                }
+                ForStatement::Generic(generic) => {
+                    let variable_types = self.check_generic_for_iterators(&generic.iterators);
+                    self.symbols.enter_scope();
+                    for (i, variable) in generic.variables.iter().enumerate() {
+                        let typ = variable_types
+                            .as_ref()
+                            .and_then(|types| types.get(i).cloned())
+                            .unwrap_or_else(|| unknown_type(variable.span));
+                        self.declare_symbol(
+                            &variable.node,
+                            SymbolKind::Variable,
+                            typ,
+                            variable.span,
+                        );
+                    }
+                    self.with_pairs_loop(generic, |checker| checker.check_for_body(&generic.body));
+                    self.symbols.exit_scope();
+                }
+            },
+            Statement::Repeat(repeat) => self.check_repeat(repeat),
+            Statement::Labeled(labeled) => self.check_labeled_loop(labeled),
+            Statement::Return(ret) => {
+                let types: Vec<Type> = ret
+                    .values
+                    .iter()
+                    .map(|value| {
+                        let typ = self.check_expression(value);
+                        self.widen_constant(value, typ)
+                    })
+                    .collect();
+                self.check_nil_returns(&ret.values, &types);
+                let returned = match types.len() {
+                    0 => Type::new(TypeKind::Primitive(PrimitiveType::Void), ret.span),
+                    1 => types.into_iter().next().unwrap(),
+                    _ => Type::new(TypeKind::Tuple(types), ret.span),
+                };
+                if let Some(frame) = self.return_types.last_mut() {
+                    frame.push(returned);
+                }
+                self.exit_flow(statement);
+            }
+            Statement::Break(jump) | Statement::Continue(jump) => {
+                self.check_loop_jump(jump);
+                self.exit_flow(statement);
+            }
+            Statement::Expression(expr) => {
+                self.check_expression(expr);
+                let assertion = self.assertion.take();
+                if is_error_call(expr) {
+                    self.exit_flow(statement);
+                } else if let (ExpressionKind::Call(..), Some(narrowing)) = (&expr.kind, assertion)
+                {
+                    self.trace_narrowing(&narrowing, NarrowingCause::Assertion(expr.span));
+                    self.flow.extend(narrowing);
+                }
+            }
+            Statement::Block(block) => self.check_block(block),
+            Statement::Untyped(block) => self.check_untyped_block(block),
+        }
+    }
+
+    fn check_block(&mut self, block: &Block) {
+        self.check_scoped_statements(&block.statements);
+    }
+
+    /// Check an `untyped` block with its errors replaced by one warning
+    /// counting them
+    fn check_untyped_block(&mut self, block: &Block) {
+        let outer = self.suppressed_errors.replace(0);
+        self.check_block(block);
+        let suppressed = std::mem::replace(&mut self.suppressed_errors, outer).unwrap_or(0);
+
+        let message = if suppressed == 0 {
+            "Untyped block has no type errors and can be removed".to_string()
+        } else {
+            format!("{} type error(s) suppressed in untyped block", suppressed)
+        };
+        self.report_warning(block.span, "TL3004", &message);
+    }
+
+    fn check_statements(&mut self, statements: &[Statement]) {
+        for (i, statement) in statements.iter().enumerate() {
+            self.check_statement(statement);
+            if using::is_using(statement) {
+                self.check_using_scope(&statements[i + 1..]);
+            }
+        }
+    }
+
+    fn check_class(&mut self, class: &ClassDeclaration) {
+        self.check_abstract_members(class);
+        self.check_mixins(class);
+        self.check_overrides(class);
+
+        if !class.is_abstract {
+            self.check_abstract_implementations(class);
+        }
+
+        for member in &class.members {
+            self.symbols.enter_scope();
+            if !member.is_static() {
+                self.declare_implicit_self(class);
+            }
+            match member {
+                ClassMember::Property(prop) => match &prop.initializer {
+                    Some(init) => {
+                        self.check_expression(init);
+                    }
+                    None if prop.is_const => self.report_error(
+                        prop.name.span,
+                        TypeCheckError::UninitializedClassConstant(prop.name.node.clone()),
+                    ),
+                    None => {}
+                },
+                ClassMember::Constructor(ctor) => match &ctor.body {
+                    Some(body) => {
+                        self.check_function_body(&ctor.parameters, None, body);
+                    }
+                    None => self.report_error(
+                        ctor.span,
+                        TypeCheckError::MissingMethodBody("constructor".to_string()),
+                    ),
+                },
+                ClassMember::Method(method) => {
+                    if let Some(body) = &method.body {
+                        self.check_function_body(
+                            &method.parameters,
+                            method.return_type.as_ref(),
+                            body,
+                        );
+                    }
+                }
+                ClassMember::Getter(getter) => {
+                    self.check_function_body(&[], Some(&getter.return_type), &getter.body);
+                }
+                ClassMember::Setter(setter) => {
+                    let parameters = std::slice::from_ref(&setter.parameter);
+                    self.check_function_body(parameters, None, &setter.body);
+                }
+                ClassMember::StaticBlock(block) => self.check_block(&block.body),
+            }
+            self.symbols.exit_scope();
+        }
+    }
+
+    /// Abstract methods must be bodiless and live in abstract classes;
+    /// every other method needs an implementation
+    fn check_abstract_members(&mut self, class: &ClassDeclaration) {
+        for method in class_methods(class) {
+            if method.is_abstract {
+                if !class.is_abstract {
+                    self.report_error(
+                        method.name.span,
+                        TypeCheckError::AbstractMethodInConcreteClass {
+                            method: method.name.node.clone(),
+                            class: class.name.node.clone(),
+                        },
+                    );
+                }
+                if method.body.is_some() {
+                    self.report_error(
+                        method.name.span,
+                        TypeCheckError::AbstractMethodWithBody(method.name.node.clone()),
+                    );
+                }
+            } else if method.body.is_none() {
+                self.report_error(
+                    method.name.span,
+                    TypeCheckError::MissingMethodBody(method.name.node.clone()),
+                );
+            }
+        }
+    }
+
+    /// A concrete class must override every abstract method it inherits
+    fn check_abstract_implementations(&mut self, class: &ClassDeclaration) {
+        let mut implemented: HashSet<String> = class_methods(class)
+            .filter(|m| !m.is_abstract)
+            .map(|m| m.name.node.clone())
+            .collect();
+
+        let mut missing = Vec::new();
+
+        // Walk from the nearest base outwards; a concrete override closer to
+        // the class satisfies an abstract declaration further up the chain
+        let mixins = self.type_env.mixin_classes(class);
+        for mixin in &mixins {
+            implemented.extend(
+                class_methods(mixin)
+                    .filter(|m| !m.is_abstract)
+                    .map(|m| m.name.node.clone()),
+            );
+        }
+
+        for base in mixins.into_iter().chain(self.type_env.ancestors(class)) {
+            for method in class_methods(base) {
+                if method.is_abstract {
+                    if !implemented.contains(&method.name.node) {
+                        missing.push((method.name.clone(), base.name.node.clone()));
+                    }
+                } else {
+                    implemented.insert(method.name.node.clone());
+                }
+            }
+        }
+
+        for (method, base) in missing {
+            self.report_error(
+                class.name.span,
+                TypeCheckError::MissingAbstractImplementation {
+                    class: class.name.node.clone(),
+                    method: method.node.clone(),
+                    base: base.clone(),
+                },
+            );
+            self.report_note(
+                method.span,
+                "TL2011",
+                &format!("'{}' is declared abstract in '{}' here", method.node, base),
+            );
+        }
+    }
+
+    /// Check an expression and infer its type. Anything the checker cannot
+    /// reason about yet is `unknown`.
+    fn check_expression(&mut self, expr: &Expression) -> Type {
+        let typ = self.infer_expression(expr);
+        self.record_probe(expr.span, &typ);
+        self.trace_expression(expr, &typ);
+        self.record_coverage(expr, &typ);
+        typ
+    }
+
+    fn infer_expression(&mut self, expr: &Expression) -> Type {
+        let span = expr.span;
+        match &expr.kind {
+            ExpressionKind::Identifier(name) => self.check_identifier(name, span),
+            ExpressionKind::Literal(literal) => {
+                let primitive = match literal {
+                    Literal::Nil => PrimitiveType::Nil,
+                    Literal::Boolean(_) => PrimitiveType::Boolean,
+                    Literal::Number(_) => PrimitiveType::Number,
+                    Literal::Integer(_) | Literal::SuffixedInteger(..) => {
+                        self.check_integer_literal(literal, span);
+                        PrimitiveType::Integer
+                    }
+                    // Only targets with integers wrap it; the rest read a float
+                    Literal::WrappedInteger(..) if self.has_integer_subtype() => {
+                        PrimitiveType::Integer
+                    }
+                    Literal::WrappedInteger(..) => PrimitiveType::Number,
+                    Literal::String(_) => PrimitiveType::String,
+                };
+                Type::new(TypeKind::Primitive(primitive), span)
+            }
+            ExpressionKind::SelfKeyword | ExpressionKind::SuperKeyword => unknown_type(span),
+            ExpressionKind::Binary(op, left, right) => {
+                self.check_order_dependent_comparison(*op, left, right, span);
+                self.check_binary(*op, left, right, span)
+            }
+            ExpressionKind::Assignment(left, op, right) => {
+                self.check_order_dependent_assignment(left, *op, right, span);
+                let target = self.check_assignment_target(left, right);
+                let value = self.check_expression(right);
+                if *op == AssignmentOp::Assign {
+                    let because = self.declared_here(left);
+                    self.check_value_assignable(right, &value, &target, because);
+                }
+                if let ExpressionKind::Identifier(name) = &left.kind {
+                    self.array_holes.remove(name);
+                    self.narrow_assignment(name, &value, right.span);
+                }
+                value
+            }
+            ExpressionKind::Unary(op, operand) => self.check_unary(*op, operand, span),
+            ExpressionKind::Member(object, member) => {
+                let object_type = self.check_expression(object);
+                let object_type = self.check_dereference(object, object_type);
+                self.check_static_access(object, &object_type, member);
+                let value = self.member_type(&object_type, member);
+                self.weak_member_read(object, &object_type, member, value)
+            }
+            ExpressionKind::OptionalMember(object, member) => {
+                let object_type = self.check_expression(object);
+                let member_type = self.member_type(&without_nil(&object_type), member);
+                match member_type.kind {
+                    TypeKind::Nullable(_) => member_type,
+                    TypeKind::Primitive(PrimitiveType::Unknown) => member_type,
+                    _ => Type::new(TypeKind::Nullable(Box::new(member_type)), span),
+                }
+            }
+            ExpressionKind::Index(object, index) => {
+                let object_type = self.check_expression(object);
+                let object_type = self.check_dereference(object, object_type);
+                let index_type = self.check_expression(index);
+                let value = self.index_access_type(&object_type, index, &index_type);
+                self.weak_index_read(object, &index_type, value)
+            }
+            ExpressionKind::Call(callee, args) => {
+                self.check_instantiation(callee);
+                self.check_deterministic_call(callee, span);
+                self.check_order_dependent_call(callee, span);
+                let callee_type = self.check_expression(callee);
+                let callee_type = self.check_dereference(callee, callee_type);
+                let arg_types: Vec<Type> = args
+                    .iter()
+                    .map(|arg| self.check_expression(&arg.value))
+                    .collect();
+                self.check_type_arguments(callee, args, &arg_types);
+                self.assertion = self.call_assertion(callee, &callee_type, args);
+                match self.instantiated_class(callee) {
+                    Some(class) => reference_type(&class, span),
+                    None => self.check_call_signature(callee, &callee_type, args, &arg_types, span),
+                }
+            }
+            ExpressionKind::MethodCall(object, method, args) => {
+                let object_type = self.check_expression(object);
+                let object_type = self.check_dereference(object, object_type);
+                let method_type = self.member_type(&object_type, method);
+                self.check_method_receiver(&object_type, &method_type, object.span);
+                for arg in args {
+                    self.check_expression(&arg.value);
+                }
+                return_type(&method_type, span)
+            }
+            ExpressionKind::Array(elements) => {
+                self.check_array_literal_holes(elements);
+                for element in elements {
+                    match element {
+                        ArrayElement::Expression(e) | ArrayElement::Spread(e) => {
+                            self.check_expression(e);
+                        }
+                    }
+                }
+                unknown_type(span)
+            }
+            ExpressionKind::Object(properties) => {
+                // Only named properties are tracked in the inferred shape
+                let mut members = Vec::new();
+                for property in properties {
+                    match property {
+                        ObjectProperty::Property { key, value, span } => {
+                            let typ = self.check_expression(value);
+                            let typ = self.widen_mutable(value, typ);
+                            members.push(ObjectTypeMember::Property(PropertySignature {
+                                decorators: Vec::new(),
+                                is_readonly: false,
+                                name: key.clone(),
+                                is_optional: false,
+                                type_annotation: typ,
+                                span: *span,
+                            }));
+                        }
+                        ObjectProperty::Spread { value, span } => {
+                            self.check_expression(value);
+                            members.push(open_index_signature(*span));
+                        }
+                        ObjectProperty::Computed { key, value, span } => {
+                            self.check_expression(key);
+                            self.check_expression(value);
+                            members.push(open_index_signature(*span));
+                        }
+                    }
+                }
+                Type::new(TypeKind::Object(ObjectType { members, span }), span)
+            }
+            ExpressionKind::Function(func) => {
+                self.check_function_body(&func.parameters, func.return_type.as_ref(), &func.body);
+                function_type(&func.parameters, func.return_type.as_ref(), span)
+            }
+            ExpressionKind::Arrow(arrow) => {
+                self.with_fresh_flow(|checker| {
+                    checker.symbols.enter_scope();
+                    checker.return_types.push(Vec::new());
+                    let declared = arrow
+                        .return_type
+                        .as_ref()
+                        .map(|typ| checker.expand_aliases(typ, 0));
+                    checker.declared_returns.push(declared);
+                    checker.declare_parameters(&arrow.parameters);
+                    match &arrow.body {
+                        ArrowBody::Expression(body) => {
+                            checker.check_expression(body);
+                        }
+                        ArrowBody::Block(block) => checker.check_block(block),
+                    }
+                    checker.declared_returns.pop();
+                    checker.return_types.pop();
+                    checker.symbols.exit_scope();
+                });
+                function_type(&arrow.parameters, arrow.return_type.as_ref(), span)
+            }
+            ExpressionKind::Conditional(cond, then_expr, else_expr) => {
+                self.check_expression(cond);
+                self.check_narrowed(then_expr, cond, true);
+                self.check_narrowed(else_expr, cond, false);
+                unknown_type(span)
+            }
+            ExpressionKind::Pipe(value, stage) => self.check_pipe(value, stage, span),
+            ExpressionKind::Match(match_expr) => {
+                self.check_expression(&match_expr.value);
+                for arm in &match_expr.arms {
+                    if let Some(guard) = &arm.guard {
+                        self.check_expression(guard);
+                    }
+                    match &arm.body {
+                        MatchArmBody::Expression(e) => {
+                            self.check_expression(e);
+                        }
+                        MatchArmBody::Block(block) => self.check_block(block),
+                    }
+                }
+                unknown_type(span)
+            }
+            ExpressionKind::Parenthesized(inner) => self.check_expression(inner),
+            ExpressionKind::Template(template) => {
+                self.check_template(template);
+                Type::new(TypeKind::Primitive(PrimitiveType::String), span)
+            }
+            ExpressionKind::TypeAssertion(inner, typ) => {
+                self.check_expression(inner);
+                typ.clone()
+            }
+        }
+    }
+
+    fn check_identifier(&mut self, name: &str, span: Span) -> Type {
+        // A declaration hidden from the target or platform also hides the
+        // standard library's binding of the name
+        let hidden = self.type_env.unavailable(name).is_some()
+            || self.type_env.is_platform_unavailable(name);
+        if let Some(symbol) = self.symbols.lookup(name) {
+            if !(hidden && self.symbols.is_builtin(name)) {
+                let declared = symbol.typ.clone();
+                self.check_deprecated_use(name, span);
+                return self.narrowed_type(name).cloned().unwrap_or(declared);
+            }
+        }
+
+        if let Some(targets) = self.type_env.unavailable(name) {
+            let error = TypeCheckError::UnavailableOnTarget {
+                name: name.to_string(),
+                target: self.config.compiler_options.target.to_string(),
+                available: describe_targets(targets),
+            };
+            self.report_error(span, error);
+        } else if self.type_env.is_platform_unavailable(name) {
+            let error = TypeCheckError::UnavailableOnPlatform {
+                name: name.to_string(),
+                platform: self.platform_name(),
+            };
+            self.report_error(span, error);
+        }
+
+        unknown_type(span)
+    }
+
+    fn platform_name(&self) -> String {
+        self.config
+            .compiler_options
+            .platform
+            .clone()
+            .unwrap_or_default()
+    }
+
+    /// Type of `object.member`, reporting members that don't exist or are
+    /// hidden on the configured target
+    fn member_type(&mut self, object_type: &Type, member: &Ident) -> Type {
+        if let Some(inner) = readonly::readonly_inner(object_type) {
+            let typ = self.member_type(&inner, member);
+            return readonly::deep_readonly(typ);
+        }
+        if let Some(bound) = self.type_parameter_bound(object_type) {
+            let bound = self.resolve_annotation(&bound);
+            return self.member_type(&bound, member);
+        }
+        if let Some(typ) = self.utility_member_type(object_type, member) {
+            return typ;
+        }
+        if let Some(inner) = assignability::partial_inner(object_type) {
+            let typ = self.member_type(&inner, member);
+            return match typ.kind {
+                TypeKind::Nullable(_) | TypeKind::Primitive(PrimitiveType::Unknown) => typ,
+                _ => Type::new(TypeKind::Nullable(Box::new(typ)), member.span),
+            };
+        }
+        let typ = self.declared_member_type(object_type, member);
+        self_type::substitute_self(typ, object_type)
+    }
+
+    fn declared_member_type(&mut self, object_type: &Type, member: &Ident) -> Type {
+        let interface = match &object_type.kind {
+            TypeKind::Reference(reference) => reference.name.node.clone(),
+            TypeKind::Parenthesized(inner) => return self.declared_member_type(inner, member),
+            TypeKind::Union(types) => return self.union_member_type(object_type, types, member),
+            // Fields of a table shape, such as one taken with `typeof`
+            TypeKind::Object(object) => {
+                return object
+                    .members
+                    .iter()
+                    .find_map(|m| match m {
+                        ObjectTypeMember::Property(prop) if prop.name.node == member.node => {
+                            Some(prop.type_annotation.clone())
+                        }
+                        _ => None,
+                    })
+                    .unwrap_or_else(|| unknown_type(member.span));
+            }
+            _ => return unknown_type(member.span),
+        };
+        if let Some(typ) = self.class_member_type(&interface, &member.node) {
+            return typ;
+        }
+
+        match self.lookup_interface_member(&interface, &member.node) {
+            MemberLookup::Found(typ) => self.instantiate_member(object_type, typ),
+            MemberLookup::Unavailable(targets) => {
+                let error = TypeCheckError::UnavailableOnTarget {
+                    name: format!("{}.{}", interface, member.node),
+                    target: self.config.compiler_options.target.to_string(),
+                    available: describe_targets(&targets),
+                };
+                self.report_error(member.span, error);
+                unknown_type(member.span)
+            }
+            MemberLookup::UnavailableOnPlatform(platform) => {
+                let error = TypeCheckError::UnavailableOnPlatform {
+                    name: format!("{}.{}", interface, member.node),
+                    platform,
+                };
+                self.report_error(member.span, error);
+                unknown_type(member.span)
+            }
+            MemberLookup::Missing => {
+                let (chain, _) = self.type_env.interface_chain(&interface);
+                let declared: Vec<String> = chain
+                    .iter()
+                    .flat_map(|decl| &decl.members)
+                    .filter_map(|candidate| match candidate {
+                        InterfaceMember::Property(prop) => Some(prop.name.node.clone()),
+                        InterfaceMember::Method(method) => Some(method.name.node.clone()),
+                        InterfaceMember::Index(_) => None,
+                    })
+                    .collect();
+                self.report_unknown_property(
+                    member,
+                    interface,
+                    declared.iter().map(String::as_str),
+                );
+                unknown_type(member.span)
+            }
+            MemberLookup::Unknown => unknown_type(member.span),
+        }
+    }
+
+    /// Find a member on an interface or any interface it extends, falling
+    /// back to a string index signature for undeclared names
+    fn lookup_interface_member(&self, interface: &str, member: &str) -> MemberLookup {
+        let (chain, complete) = self.type_env.interface_chain(interface);
+        let mut string_index = None;
+
+        for decl in &chain {
+            if let Some(targets) = self.type_env.unavailable_member(&decl.name.node, member) {
+                return MemberLookup::Unavailable(targets.to_vec());
+            }
+            if self
+                .type_env
+                .is_member_platform_unavailable(&decl.name.node, member)
+            {
+                return MemberLookup::UnavailableOnPlatform(self.platform_name());
+            }
+
+            for candidate in &decl.members {
+                match candidate {
+                    InterfaceMember::Property(prop) if prop.name.node == member => {
+                        return MemberLookup::Found(property_type(prop));
+                    }
+                    InterfaceMember::Method(method) if method.name.node == member => {
+                        return MemberLookup::Found(method_type(method));
+                    }
+                    InterfaceMember::Index(index)
+                        if index.key_type == IndexKeyType::String && string_index.is_none() =>
+                    {
+                        string_index = Some(index.value_type.clone());
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        match string_index {
+            Some(value_type) => MemberLookup::Found(value_type),
+            None if complete => MemberLookup::Missing,
+            None => MemberLookup::Unknown,
+        }
+    }
+
+    /// The class named by a `Class.new(...)` callee
+    fn instantiated_class(&self, callee: &Expression) -> Option<String> {
+        if let ExpressionKind::Member(object, method) = &callee.kind {
+            if let ExpressionKind::Identifier(name) = &object.kind {
+                if method.node == "new" && self.type_env.get_class(name).is_some() {
+                    return Some(name.clone());
+                }
+            }
+        }
+        None
+    }
+
+    /// `Shape.new(...)` is rejected when `Shape` is abstract
+    fn check_instantiation(&mut self, callee: &Expression) {
+        if let ExpressionKind::Member(object, method) = &callee.kind {
+            if method.node != "new" {
+                return;
+            }
+            if let ExpressionKind::Identifier(name) = &object.kind {
+                let is_abstract = self
+                    .type_env
+                    .get_class(name)
+                    .is_some_and(|class| class.is_abstract);
+                if is_abstract {
+                    self.report_error(
+                        callee.span,
+                        TypeCheckError::AbstractInstantiation(name.clone()),
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn unknown_type(span: Span) -> Type {
+    Type::new(TypeKind::Primitive(PrimitiveType::Unknown), span)
+}
+
+fn reference_type(name: &str, span: Span) -> Type {
+    Type::new(
+        TypeKind::Reference(crate::ast::types::TypeReference {
+            name: Ident::new(name.to_string(), span),
+            type_arguments: None,
+            span,
+        }),
+        span,
+    )
+}
+
+/// `[key: string]: unknown`, marking an object shape whose keys are not
+/// all known statically
+fn open_index_signature(span: Span) -> ObjectTypeMember {
+    ObjectTypeMember::Index(IndexSignature {
+        key_name: Ident::new("key".to_string(), span),
+        key_type: IndexKeyType::String,
+        value_type: unknown_type(span),
+        span,
+    })
+}
+
+fn property_type(prop: &PropertySignature) -> Type {
+    if prop.is_optional {
+        Type::new(
+            TypeKind::Nullable(Box::new(prop.type_annotation.clone())),
+            prop.span,
+        )
+    } else {
+        prop.type_annotation.clone()
+    }
+}
+
+fn method_type(method: &MethodSignature) -> Type {
+    function_type(&method.parameters, Some(&method.return_type), method.span)
+}
+
+fn function_type(parameters: &[Parameter], return_type: Option<&Type>, span: Span) -> Type {
+    let return_type = return_type.cloned().unwrap_or_else(|| unknown_type(span));
+    Type::new(
+        TypeKind::Function(FunctionType {
+            parameters: parameters.to_vec(),
+            return_type: Box::new(return_type),
+            span,
+        }),
+        span,
+    )
+}
+
+/// The type with `nil` removed, as seen after a nil check
+fn without_nil(typ: &Type) -> Type {
+    match &typ.kind {
+        TypeKind::Nullable(inner) | TypeKind::Parenthesized(inner) => without_nil(inner),
+        TypeKind::Union(types) => {
+            let mut remaining: Vec<Type> = types.iter().filter(|t| !is_nil(t)).cloned().collect();
+            match remaining.len() {
+                1 => remaining.remove(0),
+                _ => Type::new(TypeKind::Union(remaining), typ.span),
+            }
+        }
+        _ => typ.clone(),
+    }
+}
+
+fn is_nil(typ: &Type) -> bool {
+    matches!(
+        typ.kind,
+        TypeKind::Primitive(PrimitiveType::Nil) | TypeKind::Literal(Literal::Nil)
+    )
+}
+
+/// How an expression is named in diagnostics
+fn expression_label(expr: &Expression) -> String {
+    match &expr.kind {
+        ExpressionKind::Identifier(name) => name.clone(),
+        ExpressionKind::Member(object, member) => {
+            format!("{}.{}", expression_label(object), member.node)
+        }
+        ExpressionKind::MethodCall(object, method, _) => {
+            format!("{}::{}", expression_label(object), method.node)
+        }
+        ExpressionKind::Call(callee, _) | ExpressionKind::Parenthesized(callee) => {
+            expression_label(callee)
+        }
+        ExpressionKind::Function(_) | ExpressionKind::Arrow(_) => "anonymous function".to_string(),
+        ExpressionKind::SelfKeyword => "self".to_string(),
+        _ => "expression".to_string(),
+    }
+}
+
+/// A call that never returns: `error(...)`, or `assert` of a falsy literal
+fn is_error_call(expr: &Expression) -> bool {
+    let ExpressionKind::Call(callee, args) = &expr.kind else {
+        return false;
+    };
+    match &callee.kind {
+        ExpressionKind::Identifier(name) if name == "error" => true,
+        ExpressionKind::Identifier(name) if name == "assert" => matches!(
+            args.first().map(|arg| &arg.value.kind),
+            Some(ExpressionKind::Literal(
+                Literal::Nil | Literal::Boolean(false)
+            ))
+        ),
+        _ => false,
+    }
+}
+
+/// The result type of calling a value of the given type
+fn return_type(callee: &Type, span: Span) -> Type {
+    match &callee.kind {
+        // An assertion function returns nothing; it only narrows
+        TypeKind::Function(func) if assertion::asserts_predicate(&func.return_type).is_some() => {
+            Type::new(TypeKind::Primitive(PrimitiveType::Void), span)
+        }
+        TypeKind::Function(func) => (*func.return_type).clone(),
+        TypeKind::Parenthesized(inner) => return_type(inner, span),
+        _ => unknown_type(span),
+    }
+}
+
+/// Whether an exported declaration introduces a runtime value
+fn exports_value(statement: &Statement) -> bool {
+    match statement {
+        Statement::Interface(interface) => has_attribute(&interface.decorators, "schema"),
+        Statement::TypeAlias(_) | Statement::Declare(_) => false,
+        _ => true,
+    }
+}
+
+fn type_parameter_names(params: &Option<Vec<TypeParameter>>) -> Vec<&str> {
+    params
+        .iter()
+        .flatten()
+        .map(|p| p.name.node.as_str())
+        .collect()
+}
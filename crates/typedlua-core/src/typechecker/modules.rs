@@ -0,0 +1,280 @@
+use super::enums::property;
+use super::{reference_type, unknown_type, SymbolKind, TypeChecker};
+use crate::ast::pattern::Pattern;
+use crate::ast::statement::*;
+use crate::ast::types::{ObjectType, Type, TypeKind};
+use crate::ast::{Ident, Program};
+use crate::diagnostics::{CollectingDiagnosticHandler, DiagnosticHandler, RelatedSpan};
+use crate::errors::TypeCheckError;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::resolve::{ModuleResolver, ResolvedModule};
+use crate::span::Span;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+impl TypeChecker {
+    /// Resolve the program's imports as seen from the file `importer`, so
+    /// imports of plain Lua modules are typed by their declaration files
+    pub fn set_module_resolver(&mut self, resolver: ModuleResolver, importer: &Path) {
+        self.module_resolver = Some((resolver, importer.to_path_buf()));
+    }
+
+    /// Type imports of TypedLua modules by the declarations of their
+    /// exports, keyed by the module's path. Modules left out import as
+    /// untyped.
+    pub fn set_module_exports(&mut self, exports: Arc<HashMap<PathBuf, String>>) {
+        self.module_exports = exports;
+    }
+
+    /// The declaration files read to type imports, in the order read
+    pub fn declaration_files(&self) -> &[PathBuf] {
+        &self.declaration_files
+    }
+
+    /// Type the names an import binds from the module's declaration file.
+    /// Plain Lua modules without one bind `unknown`, or are an error
+    /// without `allowNonTypedLua`. TypedLua modules are typed by the
+    /// declarations of their exports, when those were given.
+    pub(super) fn check_import(&mut self, import: &ImportDeclaration) {
+        let Some((resolver, importer)) = &self.module_resolver else {
+            return;
+        };
+        match resolver.resolve(importer, &import.source) {
+            Some(ResolvedModule::Declaration(path)) => {
+                let text = resolver.file_system().read_file(&path);
+                match text
+                    .map_err(|e| (e.to_string(), None))
+                    .and_then(|text| parse_declarations(&text))
+                {
+                    Ok(program) => {
+                        self.import_declarations(import, &program, &path);
+                        self.declaration_files.push(path);
+                    }
+                    Err((message, span)) => {
+                        let related = span
+                            .map(|(span, problem)| RelatedSpan::new(span, problem).in_file(&path))
+                            .into_iter()
+                            .collect();
+                        self.report_error_with_related(
+                            import.span,
+                            TypeCheckError::InvalidDeclarationFile {
+                                path: path.display().to_string(),
+                                message,
+                            },
+                            related,
+                        );
+                    }
+                }
+            }
+            Some(ResolvedModule::Lua(path)) => {
+                if !self.config.compiler_options.allow_non_typed_lua {
+                    let related = RelatedSpan::new(
+                        Span::new(0, 0, 1, 1),
+                        format!("'{}' resolves to this Lua module", import.source),
+                    )
+                    .in_file(&path);
+                    self.report_error_with_related(
+                        import.span,
+                        TypeCheckError::UntypedLuaImport(import.source.clone()),
+                        vec![related],
+                    );
+                }
+                for local in imported_locals(&import.clause) {
+                    self.declare_symbol(
+                        &local.node,
+                        SymbolKind::Const,
+                        unknown_type(local.span),
+                        local.span,
+                    );
+                }
+            }
+            Some(ResolvedModule::TypedLua(path)) => {
+                let exports = self.module_exports.clone();
+                // A module that does not parse reports that itself
+                if let Some(Ok(program)) = exports.get(&path).map(|text| parse_declarations(text)) {
+                    self.import_declarations(import, &program, &path);
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn import_declarations(&mut self, import: &ImportDeclaration, program: &Program, path: &Path) {
+        // The module's types stay visible, as its signatures refer to them;
+        // its values are only read through the import
+        self.symbols.enter_scope();
+        self.collect_declarations(&program.statements);
+        let (values, types) = exported_names(&program.statements);
+        let values: Vec<(String, Type)> = values
+            .into_iter()
+            .filter_map(|(exported, local)| {
+                Some((exported, self.symbols.lookup(&local)?.typ.clone()))
+            })
+            .collect();
+        self.symbols.exit_scope();
+
+        match &import.clause {
+            ImportClause::Named(specifiers) | ImportClause::TypeOnly(specifiers) => {
+                for specifier in specifiers {
+                    let imported = &specifier.imported;
+                    let local = specifier.local.as_ref().unwrap_or(imported);
+                    let value = values.iter().find(|(name, _)| *name == imported.node);
+                    let is_type = types.contains(&imported.node);
+                    if let Some((_, typ)) = value {
+                        self.declare_symbol(
+                            &local.node,
+                            SymbolKind::Const,
+                            typ.clone(),
+                            local.span,
+                        );
+                    }
+                    if is_type && local.node != imported.node {
+                        self.type_env.register_alias(
+                            &local.node,
+                            reference_type(&imported.node, local.span),
+                        );
+                    }
+                    if value.is_none() && !is_type {
+                        // Point at the declaration left unexported, or at
+                        // the file that lacks one
+                        let related = match declared_name(&program.statements, &imported.node) {
+                            Some(span) => RelatedSpan::new(
+                                span,
+                                format!("'{}' is declared here but not exported", imported.node),
+                            ),
+                            None => RelatedSpan::new(
+                                Span::new(0, 0, 1, 1),
+                                format!("'{}' is typed by this declaration file", import.source),
+                            ),
+                        };
+                        self.report_error_with_related(
+                            imported.span,
+                            TypeCheckError::NotExported {
+                                module: import.source.clone(),
+                                name: imported.node.clone(),
+                            },
+                            vec![related.in_file(path)],
+                        );
+                    }
+                }
+            }
+            // The module table, holding every exported value
+            ImportClause::Default(local) | ImportClause::Namespace(local) => {
+                let span = local.span;
+                let members = values
+                    .into_iter()
+                    .map(|(name, typ)| property(&Ident::new(name, span), typ))
+                    .collect();
+                self.declare_symbol(
+                    &local.node,
+                    SymbolKind::Const,
+                    Type::new(TypeKind::Object(ObjectType { members, span }), span),
+                    span,
+                );
+            }
+        }
+    }
+}
+
+/// A declaration file's program, or what is wrong with it along with the
+/// place and problem of the first syntax error
+type ParsedDeclarations = Result<Program, (String, Option<(Span, String)>)>;
+
+fn parse_declarations(text: &str) -> ParsedDeclarations {
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let mut lexer = Lexer::new(text, handler.clone());
+    let program = lexer
+        .tokenize()
+        .ok()
+        .and_then(|tokens| Parser::new(tokens, handler.clone()).parse().ok());
+    match (program, handler.get_diagnostics().first()) {
+        (Some(program), None) => Ok(program),
+        (_, Some(diagnostic)) => Err((
+            format!("{}: {}", diagnostic.span, diagnostic.message),
+            Some((diagnostic.span, diagnostic.message.clone())),
+        )),
+        (None, None) => Err(("invalid syntax".to_string(), None)),
+    }
+}
+
+/// Where a top-level declaration of `name` is, exported or not
+fn declared_name(statements: &[Statement], name: &str) -> Option<Span> {
+    statements.iter().find_map(|statement| {
+        let ident = match statement {
+            Statement::Function(func) => &func.name,
+            Statement::Variable(VariableDeclaration {
+                pattern: Pattern::Identifier(name),
+                ..
+            }) => name,
+            Statement::Declare(decl) => match &decl.kind {
+                DeclareKind::Function(func) => &func.name,
+                DeclareKind::Variable(var) => &var.name,
+            },
+            Statement::Namespace(namespace) => &namespace.name,
+            Statement::Class(class) => &class.name,
+            Statement::Enum(decl) => &decl.name,
+            Statement::Interface(interface) => &interface.name,
+            Statement::TypeAlias(alias) => &alias.name,
+            _ => return None,
+        };
+        (ident.node == name).then_some(ident.span)
+    })
+}
+
+fn imported_locals(clause: &ImportClause) -> Vec<&Ident> {
+    match clause {
+        ImportClause::Default(local) | ImportClause::Namespace(local) => vec![local],
+        ImportClause::Named(specifiers) => specifiers
+            .iter()
+            .map(|specifier| specifier.local.as_ref().unwrap_or(&specifier.imported))
+            .collect(),
+        ImportClause::TypeOnly(_) => Vec::new(),
+    }
+}
+
+/// The values a declaration file may export, as (exported name, local
+/// name), and the names of the types it may export. Named exports are
+/// listed as both, since they can name either.
+fn exported_names(statements: &[Statement]) -> (Vec<(String, String)>, Vec<String>) {
+    let mut values = Vec::new();
+    let mut types = Vec::new();
+    for statement in statements {
+        let Statement::Export(export) = statement else {
+            continue;
+        };
+        match &export.kind {
+            ExportKind::Declaration(decl) => {
+                let (value, typ) = match decl.as_ref() {
+                    Statement::Function(func) => (Some(&func.name), None),
+                    Statement::Variable(decl) => match &decl.pattern {
+                        Pattern::Identifier(name) => (Some(name), None),
+                        _ => (None, None),
+                    },
+                    Statement::Declare(decl) => match &decl.kind {
+                        DeclareKind::Function(func) => (Some(&func.name), None),
+                        DeclareKind::Variable(var) => (Some(&var.name), None),
+                    },
+                    Statement::Namespace(namespace) => (Some(&namespace.name), None),
+                    Statement::Class(class) => (Some(&class.name), Some(&class.name)),
+                    Statement::Enum(decl) => (Some(&decl.name), Some(&decl.name)),
+                    Statement::Interface(interface) => (None, Some(&interface.name)),
+                    Statement::TypeAlias(alias) => (None, Some(&alias.name)),
+                    _ => (None, None),
+                };
+                values.extend(value.map(|name| (name.node.clone(), name.node.clone())));
+                types.extend(typ.map(|name| name.node.clone()));
+            }
+            ExportKind::Named(specifiers) => {
+                for specifier in specifiers {
+                    let exported = specifier.exported.as_ref().unwrap_or(&specifier.local);
+                    values.push((exported.node.clone(), specifier.local.node.clone()));
+                    types.push(exported.node.clone());
+                }
+            }
+            ExportKind::Default(_) | ExportKind::Equals(_) => {}
+        }
+    }
+    (values, types)
+}
@@ -0,0 +1,157 @@
+use super::{function_type, reference_type, unknown_type, SymbolKind, TypeChecker};
+use crate::ast::pattern::Pattern;
+use crate::ast::statement::*;
+use crate::ast::types::Type;
+use crate::ast::{Ident, Spanned};
+use crate::errors::TypeCheckError;
+
+impl TypeChecker {
+    /// Name of a declaration as seen from outside every enclosing namespace
+    pub(super) fn qualify(&self, name: &str) -> String {
+        if self.namespace_path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", self.namespace_path.join("."), name)
+        }
+    }
+
+    /// Register the types a namespace declares under qualified names
+    /// (`Ns.Name`) and record the values it exports
+    pub(super) fn collect_namespace(&mut self, namespace: &NamespaceDeclaration) {
+        let qualified = self.qualify(&namespace.name.node);
+        self.namespace_path.push(namespace.name.node.clone());
+
+        let mut members = Vec::new();
+        for statement in &namespace.body.statements {
+            let (decl, exported) = unwrap_export(statement);
+            match decl {
+                Statement::Interface(interface) => {
+                    let mut interface = interface.clone();
+                    interface.name.node = self.qualify(&interface.name.node);
+                    self.collect_interface(&interface);
+                }
+                Statement::Class(class) => {
+                    let mut class = class.clone();
+                    class.name.node = self.qualify(&class.name.node);
+                    self.collect_class(class);
+                }
+                Statement::Namespace(inner) => self.collect_namespace(inner),
+                _ => {}
+            }
+            if exported {
+                for (name, typ) in self.namespace_values(decl) {
+                    members.push(InterfaceMember::Property(PropertySignature {
+                        decorators: Vec::new(),
+                        is_readonly: true,
+                        span: name.span,
+                        name,
+                        is_optional: false,
+                        type_annotation: typ,
+                    }));
+                }
+            }
+        }
+
+        self.namespace_path.pop();
+        self.type_env.merge_namespace(InterfaceDeclaration {
+            decorators: Vec::new(),
+            name: Spanned::new(qualified.clone(), namespace.name.span),
+            type_parameters: None,
+            extends: Vec::new(),
+            members,
+            span: namespace.span,
+        });
+
+        if self.namespace_path.is_empty() {
+            self.declare_symbol(
+                &namespace.name.node,
+                SymbolKind::Namespace,
+                reference_type(&qualified, namespace.name.span),
+                namespace.name.span,
+            );
+        }
+    }
+
+    /// Check a namespace body in its own scope, where every member is
+    /// visible by its short name whether exported or not
+    pub(super) fn check_namespace(&mut self, namespace: &NamespaceDeclaration) {
+        self.symbols.enter_scope();
+        self.namespace_path.push(namespace.name.node.clone());
+
+        for statement in &namespace.body.statements {
+            let (decl, _) = unwrap_export(statement);
+            match decl {
+                Statement::Function(func) => self.declare_function(func),
+                Statement::Declare(ambient) => self.collect_ambient(ambient),
+                Statement::Class(class) => self.declare_symbol(
+                    &class.name.node,
+                    SymbolKind::Class,
+                    reference_type(&self.qualify(&class.name.node), class.name.span),
+                    class.name.span,
+                ),
+                Statement::Namespace(inner) => self.declare_symbol(
+                    &inner.name.node,
+                    SymbolKind::Namespace,
+                    reference_type(&self.qualify(&inner.name.node), inner.name.span),
+                    inner.name.span,
+                ),
+                _ => {}
+            }
+        }
+
+        for statement in &namespace.body.statements {
+            if let Statement::Export(ExportDeclaration {
+                kind: ExportKind::Equals(_),
+                span,
+            }) = statement
+            {
+                self.report_error(*span, TypeCheckError::ExportEqualsInNamespace);
+            }
+            self.check_statement(statement);
+        }
+
+        self.namespace_path.pop();
+        self.symbols.exit_scope();
+    }
+
+    /// Values a declaration contributes to its namespace's table
+    fn namespace_values(&self, statement: &Statement) -> Vec<(Ident, Type)> {
+        match statement {
+            Statement::Function(func) => vec![(
+                func.name.clone(),
+                function_type(&func.parameters, func.return_type.as_ref(), func.span),
+            )],
+            Statement::Variable(decl) => match &decl.pattern {
+                Pattern::Identifier(name) => {
+                    let typ = decl
+                        .type_annotation
+                        .clone()
+                        .unwrap_or_else(|| unknown_type(name.span));
+                    vec![(name.clone(), typ)]
+                }
+                _ => Vec::new(),
+            },
+            Statement::Class(class) => vec![(
+                class.name.clone(),
+                reference_type(&self.qualify(&class.name.node), class.name.span),
+            )],
+            Statement::Namespace(inner) => vec![(
+                inner.name.clone(),
+                reference_type(&self.qualify(&inner.name.node), inner.name.span),
+            )],
+            Statement::Enum(decl) => vec![(decl.name.clone(), unknown_type(decl.name.span))],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// A statement inside a namespace, and whether it is exported from it
+fn unwrap_export(statement: &Statement) -> (&Statement, bool) {
+    match statement {
+        Statement::Export(ExportDeclaration {
+            kind: ExportKind::Declaration(decl),
+            ..
+        }) => (decl, true),
+        other => (other, false),
+    }
+}
@@ -0,0 +1,50 @@
+use super::TypeChecker;
+use crate::ast::expression::{format_suffixed_integer, IntegerSuffix, Literal};
+use crate::features::LuaFeature;
+use crate::span::Span;
+
+/// Integers beyond 2^53 have no exact double representation
+const MAX_SAFE_INTEGER: u64 = 1 << 53;
+
+impl TypeChecker {
+    /// Warn about integer literals the selected target cannot represent as
+    /// written: `LL`/`ULL` suffixes outside LuaJIT, and integers too large
+    /// for a double on targets without 64-bit integers
+    pub(super) fn check_integer_literal(&mut self, literal: &Literal, span: Span) {
+        let target = self.config.compiler_options.target;
+        let (value, suffix) = match literal {
+            Literal::Integer(value) => (*value, None),
+            Literal::SuffixedInteger(value, suffix) => (*value, Some(*suffix)),
+            _ => return,
+        };
+
+        if let Some(suffix) = suffix {
+            if !target.supports(LuaFeature::Int64Literals) {
+                let message = format!(
+                    "The '{}' suffix of '{}' is LuaJIT-only and is dropped for target {}",
+                    suffix.as_str(),
+                    format_suffixed_integer(value, suffix),
+                    target
+                );
+                self.report_warning(span, "TL3005", &message);
+            }
+            if target.supports(LuaFeature::Int64Literals) || self.has_integer_subtype() {
+                return;
+            }
+        } else if self.has_integer_subtype() {
+            return;
+        }
+
+        let magnitude = match suffix {
+            Some(IntegerSuffix::Unsigned) => value as u64,
+            _ => value.unsigned_abs(),
+        };
+        if magnitude > MAX_SAFE_INTEGER {
+            let message = format!(
+                "Integer literal '{}' cannot be represented exactly on target {}",
+                magnitude, target
+            );
+            self.report_warning(span, "TL3006", &message);
+        }
+    }
+}
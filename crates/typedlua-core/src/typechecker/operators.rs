@@ -0,0 +1,424 @@
+use super::tables::has_length;
+use super::targets::describe_targets;
+use super::type_environment::class_methods;
+use super::{unknown_type, TypeChecker};
+use crate::ast::expression::{BinaryOp, Expression, ExpressionKind, Literal, UnaryOp};
+use crate::ast::statement::{InterfaceMember, Parameter};
+use crate::ast::types::{PrimitiveType, Type, TypeKind};
+use crate::config::LuaVersion;
+use crate::diagnostics::RelatedSpan;
+use crate::errors::TypeCheckError;
+use crate::features::LuaFeature;
+use crate::span::Span;
+
+/// A metamethod declared on a class or interface, without its receiver
+pub(super) struct Metamethod {
+    parameters: Vec<Parameter>,
+    return_type: Option<Type>,
+}
+
+impl TypeChecker {
+    /// Type a binary operator. Operands whose type declares the matching
+    /// metamethod (`__add`, `__lt`, ...) are checked against its signature;
+    /// other class and interface operands are rejected.
+    pub(super) fn check_binary(
+        &mut self,
+        op: BinaryOp,
+        left: &Expression,
+        right: &Expression,
+        span: Span,
+    ) -> Type {
+        let left_type = self.check_expression(left);
+        // The right side of `and` runs only when the left is truthy, and
+        // that of `or` only when it is falsy
+        let right_type = match op {
+            BinaryOp::And => self.check_narrowed(right, left, true),
+            BinaryOp::Or => self.check_narrowed(right, left, false),
+            _ => self.check_expression(right),
+        };
+        // Only equality and the logical operators take nil operands
+        let (left_type, right_type) = match op {
+            BinaryOp::Equal | BinaryOp::NotEqual | BinaryOp::And | BinaryOp::Or => {
+                (left_type, right_type)
+            }
+            _ => (
+                self.check_dereference(left, left_type),
+                self.check_dereference(right, right_type),
+            ),
+        };
+        if is_bitwise(op) {
+            self.check_bitwise_available(binary_symbol(op), span);
+        }
+
+        let primitive = match op {
+            BinaryOp::Concatenate => PrimitiveType::String,
+            BinaryOp::Equal
+            | BinaryOp::NotEqual
+            | BinaryOp::LessThan
+            | BinaryOp::LessThanOrEqual
+            | BinaryOp::GreaterThan
+            | BinaryOp::GreaterThanOrEqual => PrimitiveType::Boolean,
+            BinaryOp::And | BinaryOp::Or => PrimitiveType::Unknown,
+            _ => PrimitiveType::Number,
+        };
+        let fallback = Type::new(TypeKind::Primitive(primitive), span);
+
+        let Some(name) = binary_metamethod(op) else {
+            return fallback;
+        };
+        // `a > b` is evaluated by Lua as `b < a`
+        let (first, first_type, second, second_type) = match op {
+            BinaryOp::GreaterThan | BinaryOp::GreaterThanOrEqual => {
+                (right, &right_type, left, &left_type)
+            }
+            _ => (left, &left_type, right, &right_type),
+        };
+
+        // Lua tries the left operand's metamethod first, then the right's
+        if let Some(method) = self.find_metamethod(first_type, name) {
+            if let Some(expected) = method
+                .parameters
+                .first()
+                .and_then(|p| p.type_annotation.as_ref())
+            {
+                let because = RelatedSpan::new(
+                    expected.span,
+                    format!("expected by the parameter of '{}'", name),
+                );
+                self.check_assignable(second_type, expected, second.span, Some(because));
+            }
+            return metamethod_result(op, method, fallback);
+        }
+        if let Some(method) = self.find_metamethod(second_type, name) {
+            return metamethod_result(op, method, fallback);
+        }
+
+        // Equality falls back to identity, which works on any value
+        let is_equality = matches!(op, BinaryOp::Equal | BinaryOp::NotEqual);
+        if !is_equality && (self.is_declared_type(first_type) || self.is_declared_type(second_type))
+        {
+            self.report_error(
+                first.span.combine(&second.span),
+                TypeCheckError::OperatorNotSupported {
+                    operator: binary_symbol(op).to_string(),
+                    left: left_type.to_string(),
+                    right: right_type.to_string(),
+                },
+            );
+            return unknown_type(span);
+        }
+
+        match op {
+            BinaryOp::Concatenate
+            | BinaryOp::Equal
+            | BinaryOp::NotEqual
+            | BinaryOp::LessThan
+            | BinaryOp::LessThanOrEqual
+            | BinaryOp::GreaterThan
+            | BinaryOp::GreaterThanOrEqual => fallback,
+            _ => self.check_numeric_binary(op, left, &left_type, right, &right_type, span),
+        }
+    }
+
+    /// Type the arithmetic and bitwise operators on plain numbers. On targets
+    /// with an integer subtype, `+ - * %` keep integers integral, `/` and
+    /// `^` always produce floats, and bitwise operators need operands with
+    /// an integer representation.
+    fn check_numeric_binary(
+        &mut self,
+        op: BinaryOp,
+        left: &Expression,
+        left_type: &Type,
+        right: &Expression,
+        right_type: &Type,
+        span: Span,
+    ) -> Type {
+        let number = Type::new(TypeKind::Primitive(PrimitiveType::Number), span);
+        if !self.has_integer_subtype() {
+            return number;
+        }
+        let integer = Type::new(TypeKind::Primitive(PrimitiveType::Integer), span);
+
+        match op {
+            BinaryOp::BitwiseAnd
+            | BinaryOp::BitwiseOr
+            | BinaryOp::BitwiseXor
+            | BinaryOp::ShiftLeft
+            | BinaryOp::ShiftRight => {
+                let operator = binary_symbol(op);
+                self.check_integer_operand(operator, left, left_type);
+                self.check_integer_operand(operator, right, right_type);
+                integer
+            }
+            BinaryOp::Add | BinaryOp::Subtract | BinaryOp::Multiply | BinaryOp::Modulo => {
+                // Without both operand types the subtype cannot be known
+                if is_unknown(left_type) || is_unknown(right_type) {
+                    return unknown_type(span);
+                }
+                if !is_integer(left_type) || !is_integer(right_type) {
+                    return number;
+                }
+                if op == BinaryOp::Modulo && is_zero(right) {
+                    self.report_error(
+                        right.span,
+                        TypeCheckError::IntegerDivisionByZero(binary_symbol(op).to_string()),
+                    );
+                }
+                integer
+            }
+            _ => number,
+        }
+    }
+
+    /// Lua converts a bitwise operand to an integer and raises an error
+    /// when a float has no exact integer value
+    fn check_integer_operand(&mut self, operator: &str, operand: &Expression, typ: &Type) {
+        let exact = match &operand.kind {
+            ExpressionKind::Literal(Literal::Number(n)) => n.fract() == 0.0,
+            _ => !matches!(typ.kind, TypeKind::Primitive(PrimitiveType::Number)),
+        };
+        if !exact {
+            self.report_error(
+                operand.span,
+                TypeCheckError::FloatToIntegerConversion {
+                    operator: operator.to_string(),
+                    operand: typ.to_string(),
+                },
+            );
+        }
+    }
+
+    /// Lua 5.3 and 5.4 split numbers into integer and float subtypes
+    pub(super) fn has_integer_subtype(&self) -> bool {
+        self.config
+            .compiler_options
+            .target
+            .supports(LuaFeature::IntegerSubtype)
+    }
+
+    /// Bitwise operators are native on Lua 5.3 and 5.4, and compile to
+    /// calls into `bit32` or LuaJIT's `bit` on targets with such a library
+    fn check_bitwise_available(&mut self, operator: &str, span: Span) {
+        let available = |target: LuaVersion| {
+            target.supports(LuaFeature::BitwiseOperators) || target.supports(LuaFeature::BitLibrary)
+        };
+        let target = self.config.compiler_options.target;
+        if !available(target) {
+            let targets: Vec<LuaVersion> = LuaVersion::ALL
+                .into_iter()
+                .filter(|target| available(*target))
+                .collect();
+            self.report_error(
+                span,
+                TypeCheckError::UnavailableOnTarget {
+                    name: operator.to_string(),
+                    target: target.to_string(),
+                    available: describe_targets(&targets),
+                },
+            );
+        }
+    }
+
+    /// Type a unary operator, using `__unm`, `__len` or `__bnot` when the
+    /// operand declares one
+    pub(super) fn check_unary(&mut self, op: UnaryOp, operand: &Expression, span: Span) -> Type {
+        let operand_type = self.check_expression(operand);
+        let operand_type = match op {
+            UnaryOp::Not => operand_type,
+            _ => self.check_dereference(operand, operand_type),
+        };
+        if op == UnaryOp::BitwiseNot {
+            self.check_bitwise_available("~", span);
+        }
+        let primitive = match op {
+            UnaryOp::Not => PrimitiveType::Boolean,
+            UnaryOp::Negate | UnaryOp::Length | UnaryOp::BitwiseNot => PrimitiveType::Number,
+        };
+        let fallback = Type::new(TypeKind::Primitive(primitive), span);
+
+        let (name, symbol) = match op {
+            UnaryOp::Not => return fallback,
+            UnaryOp::Negate => ("__unm", "-"),
+            UnaryOp::Length => ("__len", "#"),
+            UnaryOp::BitwiseNot => ("__bnot", "~"),
+        };
+
+        if let Some(method) = self.find_metamethod(&operand_type, name) {
+            return method.return_type.unwrap_or(fallback);
+        }
+        if op == UnaryOp::Length {
+            self.check_sequence_use(operand, &operand_type, false);
+            self.check_length_after_holes(operand);
+        }
+        let integer = Type::new(TypeKind::Primitive(PrimitiveType::Integer), span);
+        // Strings and tables have a length without a metamethod
+        let unsupported = match op {
+            UnaryOp::Length => !has_length(&operand_type),
+            _ => self.is_declared_type(&operand_type),
+        };
+        if unsupported {
+            self.report_error(
+                operand.span,
+                TypeCheckError::UnaryOperatorNotSupported {
+                    operator: symbol.to_string(),
+                    operand: operand_type.to_string(),
+                },
+            );
+            return unknown_type(span);
+        }
+
+        if !self.has_integer_subtype() {
+            return fallback;
+        }
+        match op {
+            UnaryOp::Negate if is_integer(&operand_type) => integer,
+            UnaryOp::Length => integer,
+            UnaryOp::BitwiseNot => {
+                self.check_integer_operand(symbol, operand, &operand_type);
+                integer
+            }
+            _ => fallback,
+        }
+    }
+
+    /// Look a metamethod up on a class (including its bases) or an interface
+    pub(super) fn find_metamethod(&self, typ: &Type, name: &str) -> Option<Metamethod> {
+        let TypeKind::Reference(reference) = &typ.kind else {
+            return match &typ.kind {
+                TypeKind::Parenthesized(inner) => self.find_metamethod(inner, name),
+                _ => None,
+            };
+        };
+
+        let mut class_name = Some(reference.name.node.clone());
+        while let Some(current) = class_name.take() {
+            let Some(class) = self.type_env.get_class(&current) else {
+                break;
+            };
+            // Own methods win over mixed-in ones, which win over the base's
+            let found = std::iter::once(class)
+                .chain(self.type_env.mixin_classes(class))
+                .find_map(|owner| {
+                    class_methods(owner).find(|m| m.name.node == name && !m.is_static)
+                });
+            if let Some(method) = found {
+                return Some(Metamethod {
+                    parameters: method.parameters_without_self().to_vec(),
+                    return_type: method.return_type.clone(),
+                });
+            }
+            class_name = match class.extends.as_ref().map(|base| &base.kind) {
+                Some(TypeKind::Reference(base)) => Some(base.name.node.clone()),
+                _ => None,
+            };
+        }
+
+        let (chain, _) = self.type_env.interface_chain(&reference.name.node);
+        chain
+            .iter()
+            .flat_map(|decl| &decl.members)
+            .find_map(|member| match member {
+                InterfaceMember::Method(method) if method.name.node == name => Some(Metamethod {
+                    parameters: method.parameters.clone(),
+                    return_type: Some(method.return_type.clone()),
+                }),
+                _ => None,
+            })
+    }
+}
+
+pub(super) fn is_integer(typ: &Type) -> bool {
+    match &typ.kind {
+        TypeKind::Primitive(PrimitiveType::Integer) | TypeKind::Literal(Literal::Integer(_)) => {
+            true
+        }
+        TypeKind::Parenthesized(inner) => is_integer(inner),
+        _ => false,
+    }
+}
+
+fn is_unknown(typ: &Type) -> bool {
+    matches!(typ.kind, TypeKind::Primitive(PrimitiveType::Unknown))
+}
+
+fn is_zero(expr: &Expression) -> bool {
+    match &expr.kind {
+        ExpressionKind::Literal(Literal::Integer(0)) => true,
+        ExpressionKind::Parenthesized(inner) => is_zero(inner),
+        _ => false,
+    }
+}
+
+fn metamethod_result(op: BinaryOp, method: Metamethod, fallback: Type) -> Type {
+    match op {
+        // Lua converts comparison results to booleans
+        BinaryOp::Equal
+        | BinaryOp::NotEqual
+        | BinaryOp::LessThan
+        | BinaryOp::LessThanOrEqual
+        | BinaryOp::GreaterThan
+        | BinaryOp::GreaterThanOrEqual => fallback,
+        _ => method.return_type.unwrap_or(fallback),
+    }
+}
+
+fn binary_metamethod(op: BinaryOp) -> Option<&'static str> {
+    let name = match op {
+        BinaryOp::Add => "__add",
+        BinaryOp::Subtract => "__sub",
+        BinaryOp::Multiply => "__mul",
+        BinaryOp::Divide => "__div",
+        BinaryOp::Modulo => "__mod",
+        BinaryOp::IntegerDivide => "__idiv",
+        BinaryOp::Power => "__pow",
+        BinaryOp::Concatenate => "__concat",
+        BinaryOp::BitwiseAnd => "__band",
+        BinaryOp::BitwiseOr => "__bor",
+        BinaryOp::BitwiseXor => "__bxor",
+        BinaryOp::ShiftLeft => "__shl",
+        BinaryOp::ShiftRight => "__shr",
+        BinaryOp::Equal | BinaryOp::NotEqual => "__eq",
+        BinaryOp::LessThan | BinaryOp::GreaterThan => "__lt",
+        BinaryOp::LessThanOrEqual | BinaryOp::GreaterThanOrEqual => "__le",
+        BinaryOp::And | BinaryOp::Or => return None,
+    };
+    Some(name)
+}
+
+/// The operator as written in TypedLua source
+fn binary_symbol(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "+",
+        BinaryOp::Subtract => "-",
+        BinaryOp::Multiply => "*",
+        BinaryOp::Divide => "/",
+        BinaryOp::Modulo => "%",
+        BinaryOp::IntegerDivide => "//",
+        BinaryOp::Power => "^",
+        BinaryOp::Equal => "==",
+        BinaryOp::NotEqual => "~=",
+        BinaryOp::LessThan => "<",
+        BinaryOp::LessThanOrEqual => "<=",
+        BinaryOp::GreaterThan => ">",
+        BinaryOp::GreaterThanOrEqual => ">=",
+        BinaryOp::And => "and",
+        BinaryOp::Or => "or",
+        BinaryOp::Concatenate => "..",
+        BinaryOp::BitwiseAnd => "&",
+        BinaryOp::BitwiseOr => "|",
+        BinaryOp::BitwiseXor => "~",
+        BinaryOp::ShiftLeft => "<<",
+        BinaryOp::ShiftRight => ">>",
+    }
+}
+
+fn is_bitwise(op: BinaryOp) -> bool {
+    matches!(
+        op,
+        BinaryOp::BitwiseAnd
+            | BinaryOp::BitwiseOr
+            | BinaryOp::BitwiseXor
+            | BinaryOp::ShiftLeft
+            | BinaryOp::ShiftRight
+    )
+}
@@ -0,0 +1,164 @@
+use super::type_environment::class_methods;
+use super::{function_type, TypeChecker};
+use crate::ast::statement::{ClassDeclaration, InterfaceMember, MethodDeclaration, Parameter};
+use crate::ast::types::{Type, TypeKind};
+use crate::errors::TypeCheckError;
+use crate::span::Span;
+
+/// A method a class method replaces
+struct Overridden {
+    owner: String,
+    parameters: Vec<Parameter>,
+    return_type: Option<Type>,
+    generic: bool,
+    span: Span,
+}
+
+impl TypeChecker {
+    /// Methods replacing a base class or interface method must keep a
+    /// compatible signature, `override` may only appear on such methods, and
+    /// under `noImplicitOverride` they must say so
+    pub(super) fn check_overrides(&mut self, class: &ClassDeclaration) {
+        for method in class_methods(class) {
+            let Some(base) = self.overridden_method(class, method) else {
+                if method.is_override {
+                    self.report_error(
+                        method.name.span,
+                        TypeCheckError::NothingToOverride(method.name.node.clone()),
+                    );
+                }
+                continue;
+            };
+
+            if !method.is_override && self.config.compiler_options.no_implicit_override {
+                self.report_error(
+                    method.name.span,
+                    TypeCheckError::MissingOverride {
+                        method: method.name.node.clone(),
+                        base: base.owner.clone(),
+                    },
+                );
+            }
+
+            // Generic signatures are not compared until they can be
+            // instantiated against each other
+            if base.generic || method.type_parameters.is_some() {
+                continue;
+            }
+            let parameters = method.parameters_without_self();
+            if !self.is_compatible_override(parameters, method.return_type.as_ref(), &base) {
+                self.report_error(
+                    method.name.span,
+                    TypeCheckError::OverrideSignatureMismatch {
+                        method: method.name.node.clone(),
+                        base: base.owner.clone(),
+                        expected: function_type(
+                            &base.parameters,
+                            base.return_type.as_ref(),
+                            base.span,
+                        )
+                        .to_string(),
+                        actual: function_type(parameters, method.return_type.as_ref(), method.span)
+                            .to_string(),
+                    },
+                );
+                self.report_note(
+                    base.span,
+                    "TL2043",
+                    &format!(
+                        "'{}' is declared in '{}' here",
+                        method.name.node, base.owner
+                    ),
+                );
+            }
+        }
+    }
+
+    /// The nearest method `method` replaces: a method of a mixin or base
+    /// class, or for instance methods, one of an interface the class or a
+    /// base implements
+    fn overridden_method(
+        &self,
+        class: &ClassDeclaration,
+        method: &MethodDeclaration,
+    ) -> Option<Overridden> {
+        let mixins = self.type_env.mixin_classes(class);
+        let ancestors = self.type_env.ancestors(class);
+
+        for owner in mixins.iter().chain(&ancestors) {
+            if let Some(base) = class_methods(owner)
+                .find(|m| m.name.node == method.name.node && m.is_static == method.is_static)
+            {
+                return Some(Overridden {
+                    owner: owner.name.node.clone(),
+                    parameters: base.parameters_without_self().to_vec(),
+                    return_type: base.return_type.clone(),
+                    generic: base.type_parameters.is_some(),
+                    span: base.name.span,
+                });
+            }
+        }
+        if method.is_static {
+            return None;
+        }
+
+        for implemented in std::iter::once(class)
+            .chain(ancestors)
+            .flat_map(|owner| &owner.implements)
+        {
+            let TypeKind::Reference(reference) = &implemented.kind else {
+                continue;
+            };
+            let (chain, _) = self.type_env.interface_chain(&reference.name.node);
+            for interface in chain {
+                for member in &interface.members {
+                    let InterfaceMember::Method(base) = member else {
+                        continue;
+                    };
+                    if base.name.node == method.name.node {
+                        return Some(Overridden {
+                            owner: interface.name.node.clone(),
+                            parameters: base.parameters.clone(),
+                            return_type: Some(base.return_type.clone()),
+                            generic: base.type_parameters.is_some(),
+                            span: base.name.span,
+                        });
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// An override may take parameters of wider types and return a narrower
+    /// type, and must accept every call the method it replaces accepts
+    fn is_compatible_override(
+        &self,
+        parameters: &[Parameter],
+        return_type: Option<&Type>,
+        base: &Overridden,
+    ) -> bool {
+        let (min, _) = self.arity(parameters);
+        let (base_min, _) = self.arity(&base.parameters);
+        if min > base_min {
+            return false;
+        }
+
+        let parameters_match = parameters
+            .iter()
+            .zip(&base.parameters)
+            .all(
+                |(param, base)| match (&param.type_annotation, &base.type_annotation) {
+                    (Some(typ), Some(base_type)) if !param.is_rest && !base.is_rest => {
+                        self.is_assignable(base_type, typ)
+                    }
+                    _ => true,
+                },
+            );
+        let returns_match = match (return_type, &base.return_type) {
+            (Some(typ), Some(base_type)) => self.is_assignable(typ, base_type),
+            _ => true,
+        };
+        parameters_match && returns_match
+    }
+}
@@ -0,0 +1,81 @@
+use super::{expression_label, unknown_type, TypeChecker};
+use crate::ast::expression::{Expression, ExpressionKind};
+use crate::ast::pattern::Pattern;
+use crate::ast::types::{PrimitiveType, Type, TypeKind};
+use crate::errors::TypeCheckError;
+use crate::span::Span;
+
+impl TypeChecker {
+    /// Type `value |> stage`. The value becomes the stage's first argument,
+    /// so it is checked against that parameter and the stage's return type
+    /// flows on to the next step of the pipeline.
+    pub(super) fn check_pipe(
+        &mut self,
+        value: &Expression,
+        stage: &Expression,
+        span: Span,
+    ) -> Type {
+        let value_type = self.check_expression(value);
+
+        let (stage_type, receiver_call) = match &stage.kind {
+            ExpressionKind::Call(callee, args) => {
+                let callee_type = self.check_expression(callee);
+                for arg in args {
+                    self.check_expression(&arg.value);
+                }
+                (callee_type, false)
+            }
+            ExpressionKind::MethodCall(object, method, args) => {
+                let object_type = self.check_expression(object);
+                let method_type = self.member_type(&object_type, method);
+                self.check_method_receiver(&object_type, &method_type, object.span);
+                for arg in args {
+                    self.check_expression(&arg.value);
+                }
+                (method_type, true)
+            }
+            _ => (self.check_expression(stage), false),
+        };
+
+        let func = match &stage_type.kind {
+            TypeKind::Function(func) => func,
+            TypeKind::Primitive(PrimitiveType::Unknown | PrimitiveType::Table)
+            | TypeKind::Reference(_) => return unknown_type(span),
+            _ => {
+                self.report_error(
+                    stage.span,
+                    TypeCheckError::PipelineStageNotCallable {
+                        stage: expression_label(stage),
+                        actual: stage_type.to_string(),
+                    },
+                );
+                return unknown_type(span);
+            }
+        };
+
+        // A colon call supplies `self` itself, ahead of the piped value
+        let mut parameters = func.parameters.iter();
+        if receiver_call {
+            if let Some(first) = func.parameters.first() {
+                if matches!(&first.pattern, Pattern::Identifier(name) if name.node == "self") {
+                    parameters.next();
+                }
+            }
+        }
+        let expected = parameters.next().and_then(|p| p.type_annotation.as_ref());
+        if let Some(expected) = expected {
+            if !self.is_assignable(&value_type, expected) {
+                self.report_error(
+                    stage.span,
+                    TypeCheckError::PipelineStageMismatch {
+                        stage: expression_label(stage),
+                        expected: expected.to_string(),
+                        actual: value_type.to_string(),
+                    },
+                );
+            }
+        }
+
+        (*func.return_type).clone()
+    }
+}
@@ -0,0 +1,215 @@
+use super::flow::Narrowing;
+use super::{AnnotationTarget, SymbolKind, TypeChecker};
+use crate::ast::expression::{Expression, ExpressionKind};
+use crate::ast::types::Type;
+use crate::span::Span;
+
+/// A source position whose type the checker is asked for, with the
+/// narrowest expression or binding found there so far
+#[derive(Debug, Clone)]
+pub(super) struct TypeProbe {
+    offset: usize,
+    found: Option<(Span, Type)>,
+    /// With `explain_offset`, how the checker arrived at the types there
+    trace: Option<TypeTrace>,
+}
+
+/// The steps behind the types at a probed position
+#[derive(Debug, Clone, Default)]
+pub struct TypeTrace {
+    /// Every expression covering the position with the type inferred for
+    /// it, innermost first
+    pub expressions: Vec<(Span, Type)>,
+    /// The variable, parameter or declared name at the position
+    pub name: Option<NameTrace>,
+    /// Type mismatches reported over the position, each with the chain of
+    /// reasons assignability failed, outermost first
+    pub mismatches: Vec<(Span, Vec<String>)>,
+    /// Every narrowing control flow applied, in checking order
+    narrowings: Vec<(String, Type, NarrowingCause)>,
+}
+
+/// How the checker typed a name
+#[derive(Debug, Clone)]
+pub struct NameTrace {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Where the name was declared, and the type it was declared with
+    pub declared_at: Span,
+    pub declared: Type,
+    /// Whether `declared` was inferred from an initializer rather than
+    /// written
+    pub inferred: bool,
+    /// The narrower type control flow gave the name at the position, and
+    /// the last narrowing to that type
+    pub narrowed: Option<(Type, Option<NarrowingCause>)>,
+}
+
+/// What narrowed a variable's type
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NarrowingCause {
+    /// A condition known to be true or false
+    Condition { span: Span, truthy: bool },
+    /// A call to `assert` or an assertion function returning normally
+    Assertion(Span),
+    /// Assigning a value that cannot be nil
+    Assignment(Span),
+}
+
+impl TypeChecker {
+    /// Record the type of the innermost expression or declared name at a
+    /// character offset of the source, read back with `probed_type`
+    pub fn probe_offset(&mut self, offset: usize) {
+        self.probe = Some(TypeProbe {
+            offset,
+            found: None,
+            trace: None,
+        });
+    }
+
+    /// Probe a character offset as `probe_offset` does, also tracing how
+    /// the types there were inferred, read back with `type_trace`
+    pub fn explain_offset(&mut self, offset: usize) {
+        self.probe = Some(TypeProbe {
+            offset,
+            found: None,
+            trace: Some(TypeTrace::default()),
+        });
+    }
+
+    /// The type found at the probed offset, with the span it covers.
+    /// Types are as the checker saw them there, narrowing included.
+    pub fn probed_type(&self) -> Option<&(Span, Type)> {
+        self.probe.as_ref()?.found.as_ref()
+    }
+
+    /// What `explain_offset` traced
+    pub fn type_trace(&self) -> Option<&TypeTrace> {
+        self.probe.as_ref()?.trace.as_ref()
+    }
+
+    pub(super) fn record_probe(&mut self, span: Span, typ: &Type) {
+        let Some(probe) = &mut self.probe else {
+            return;
+        };
+        if !covers(span, probe.offset) {
+            return;
+        }
+        if let Some((found, _)) = &probe.found {
+            if found.len() <= span.len() {
+                return;
+            }
+        }
+        probe.found = Some((span, typ.clone()));
+    }
+
+    /// Trace an expression checked to `typ`, and the variable it reads
+    pub(super) fn trace_expression(&mut self, expr: &Expression, typ: &Type) {
+        if !self.is_tracing(expr.span) {
+            return;
+        }
+        if let ExpressionKind::Identifier(name) = &expr.kind {
+            let narrowed = self.narrowed_type(name).cloned();
+            self.trace_name(name, narrowed);
+        }
+        let Some(trace) = self.trace_mut() else {
+            return;
+        };
+        // Loops and narrowed operands may be checked more than once
+        if let Some(seen) = trace
+            .expressions
+            .iter_mut()
+            .find(|(span, _)| *span == expr.span)
+        {
+            seen.1 = typ.clone();
+            return;
+        }
+        let position = trace
+            .expressions
+            .iter()
+            .position(|(span, _)| span.len() >= expr.span.len())
+            .unwrap_or(trace.expressions.len());
+        trace.expressions.insert(position, (expr.span, typ.clone()));
+    }
+
+    /// Trace a name declared over the probed position
+    pub(super) fn trace_declaration(&mut self, name: &str, span: Span) {
+        if self.is_tracing(span) {
+            self.trace_name(name, None);
+        }
+    }
+
+    /// Note narrowings entering the flow, to explain later reads
+    pub(super) fn trace_narrowing(&mut self, narrowing: &Narrowing, cause: NarrowingCause) {
+        if let Some(trace) = self.trace_mut() {
+            for (name, typ) in narrowing {
+                trace.narrowings.push((name.clone(), typ.clone(), cause));
+            }
+        }
+    }
+
+    /// Narrowings implied by a condition, traced as caused by it
+    pub(super) fn traced_narrowing(&mut self, condition: &Expression, truthy: bool) -> Narrowing {
+        let narrowing = self.condition_narrowing(condition, truthy);
+        let cause = NarrowingCause::Condition {
+            span: condition.span,
+            truthy,
+        };
+        self.trace_narrowing(&narrowing, cause);
+        narrowing
+    }
+
+    /// Explain a mismatch reported at `span`
+    pub(super) fn trace_mismatch(&mut self, span: Span, source: &Type, target: &Type) {
+        if self.is_tracing(span) {
+            let chain = self.mismatch_chain(source, target);
+            if let Some(trace) = self.trace_mut() {
+                trace.mismatches.push((span, chain));
+            }
+        }
+    }
+
+    fn trace_name(&mut self, name: &str, narrowed: Option<Type>) {
+        let Some(symbol) = self.symbols.lookup(name) else {
+            return;
+        };
+        let inferred = self.inferred.iter().any(|annotation| {
+            matches!(&annotation.target, AnnotationTarget::Variable(ident) if ident.span == symbol.span)
+        });
+        let mut trace = NameTrace {
+            name: name.to_string(),
+            kind: symbol.kind,
+            declared_at: symbol.span,
+            declared: symbol.typ.clone(),
+            inferred,
+            narrowed: None,
+        };
+        let Some(types) = self.trace_mut() else {
+            return;
+        };
+        trace.narrowed = narrowed.map(|typ| {
+            let cause = types
+                .narrowings
+                .iter()
+                .rev()
+                .find(|(narrowed, to, _)| narrowed == name && to.to_string() == typ.to_string())
+                .map(|(_, _, cause)| *cause);
+            (typ, cause)
+        });
+        types.name = Some(trace);
+    }
+
+    fn is_tracing(&self, span: Span) -> bool {
+        self.probe
+            .as_ref()
+            .is_some_and(|probe| probe.trace.is_some() && covers(span, probe.offset))
+    }
+
+    fn trace_mut(&mut self) -> Option<&mut TypeTrace> {
+        self.probe.as_mut()?.trace.as_mut()
+    }
+}
+
+fn covers(span: Span, offset: usize) -> bool {
+    span.start <= offset && offset < span.end
+}
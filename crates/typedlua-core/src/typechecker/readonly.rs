@@ -0,0 +1,144 @@
+use super::TypeChecker;
+use crate::ast::expression::{Expression, ExpressionKind};
+use crate::ast::statement::InterfaceMember;
+use crate::ast::types::{PrimitiveType, Type, TypeKind, TypeReference};
+use crate::ast::Spanned;
+use crate::errors::TypeCheckError;
+
+impl TypeChecker {
+    /// Type the left side of an assignment, rejecting writes through a
+    /// `Readonly<T>` view, to `readonly` interface properties and to class
+    /// constants
+    pub(super) fn check_assignment_target(
+        &mut self,
+        target: &Expression,
+        value: &Expression,
+    ) -> Type {
+        match &target.kind {
+            ExpressionKind::Member(object, member) => {
+                let object_type = self.check_expression(object);
+                self.check_static_access(object, &object_type, member);
+                if self.is_readonly_member(&object_type, &member.node) {
+                    self.report_error(
+                        member.span,
+                        TypeCheckError::ReadonlyProperty(member.node.clone()),
+                    );
+                }
+                self.member_type(&object_type, member)
+            }
+            ExpressionKind::Index(object, index) => {
+                let object_type = self.check_expression(object);
+                let index_type = self.check_expression(index);
+                if readonly_inner(&object_type).is_some() {
+                    self.report_error(
+                        target.span,
+                        TypeCheckError::ReadonlyIndex(object_type.to_string()),
+                    );
+                }
+                self.check_array_hole(target, &object_type, &index_type, value);
+                self.index_access_type(&object_type, index, &index_type)
+            }
+            ExpressionKind::Identifier(name) => {
+                // A narrowed variable still takes anything its declared
+                // type allows; the assignment narrows it again
+                let typ = self.check_expression(target);
+                match self.symbols.lookup(name) {
+                    Some(symbol) if self.narrowed_type(name).is_some() => symbol.typ.clone(),
+                    _ => typ,
+                }
+            }
+            _ => self.check_expression(target),
+        }
+    }
+
+    fn is_readonly_member(&self, object_type: &Type, member: &str) -> bool {
+        if readonly_inner(object_type).is_some() {
+            return true;
+        }
+        if let TypeKind::Reference(reference) = &object_type.kind {
+            if self.is_class_constant(&reference.name.node, member) {
+                return true;
+            }
+        }
+        let Some(interface) = self.interface_name(object_type) else {
+            return false;
+        };
+        let (chain, _) = self.type_env.interface_chain(&interface);
+        chain
+            .iter()
+            .flat_map(|decl| &decl.members)
+            .any(|candidate| match candidate {
+                InterfaceMember::Property(prop) => prop.name.node == member && prop.is_readonly,
+                _ => false,
+            })
+    }
+
+    /// Assignability when either side is a read-only view. A read-only value
+    /// may only flow into types that cannot be written through.
+    pub(super) fn is_readonly_assignable(&self, source: &Type, target: &Type) -> bool {
+        match (readonly_inner(source), readonly_inner(target)) {
+            (Some(source), Some(target)) => self.is_assignable(&source, &target),
+            (Some(source), None) => {
+                !self.is_mutable_structure(target) && self.is_assignable(&source, target)
+            }
+            (None, Some(target)) => self.is_assignable(source, &target),
+            (None, None) => self.is_assignable(source, target),
+        }
+    }
+
+    pub(super) fn is_mutable_structure(&self, typ: &Type) -> bool {
+        match &typ.kind {
+            TypeKind::Object(_)
+            | TypeKind::Array(_)
+            | TypeKind::Tuple(_)
+            | TypeKind::Primitive(PrimitiveType::Table) => true,
+            TypeKind::Reference(_) => self.is_declared_type(typ),
+            TypeKind::Parenthesized(inner) => self.is_mutable_structure(inner),
+            _ => false,
+        }
+    }
+}
+
+/// The type viewed through `Readonly<T>`; `ReadonlyTable` views a plain table
+pub(super) fn readonly_inner(typ: &Type) -> Option<Type> {
+    match &typ.kind {
+        TypeKind::Reference(reference) => match reference.name.node.as_str() {
+            "Readonly" => match reference.type_arguments.as_deref() {
+                Some([inner]) => Some(inner.clone()),
+                _ => None,
+            },
+            "ReadonlyTable" if reference.type_arguments.is_none() => Some(Type::new(
+                TypeKind::Primitive(PrimitiveType::Table),
+                typ.span,
+            )),
+            _ => None,
+        },
+        TypeKind::Parenthesized(inner) => readonly_inner(inner),
+        _ => None,
+    }
+}
+
+/// Values read through a read-only view are read-only too. Primitives,
+/// functions and unresolved types need no wrapper.
+pub(super) fn deep_readonly(typ: Type) -> Type {
+    match &typ.kind {
+        TypeKind::Primitive(PrimitiveType::Table)
+        | TypeKind::Object(_)
+        | TypeKind::Array(_)
+        | TypeKind::Tuple(_)
+        | TypeKind::Reference(_)
+            if readonly_inner(&typ).is_none() =>
+        {
+            let span = typ.span;
+            Type::new(
+                TypeKind::Reference(TypeReference {
+                    name: Spanned::new("Readonly".to_string(), span),
+                    type_arguments: Some(vec![typ]),
+                    span,
+                }),
+                span,
+            )
+        }
+        _ => typ,
+    }
+}
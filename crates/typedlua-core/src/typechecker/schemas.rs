@@ -0,0 +1,138 @@
+use super::enums::{parameter, property};
+use super::{function_type, reference_type, SymbolKind, TypeChecker};
+use crate::ast::attributes::{
+    find_attribute, migration_step, schema_version, statement_decorators,
+};
+use crate::ast::expression::Literal;
+use crate::ast::statement::{ExportKind, InterfaceDeclaration, Statement};
+use crate::ast::types::{ObjectType, PrimitiveType, Type, TypeKind};
+use crate::ast::Ident;
+use crate::errors::TypeCheckError;
+use crate::span::Span;
+use std::collections::HashSet;
+
+impl TypeChecker {
+    /// A `@schema` record also names a value holding its version and the
+    /// generated `serialize` and `deserialize` functions
+    pub(super) fn collect_schema(&mut self, interface: &InterfaceDeclaration) {
+        let Some(Ok(version)) = schema_version(&interface.decorators) else {
+            return;
+        };
+        let span = interface.name.span;
+        let record = reference_type(&interface.name.node, span);
+        let table = Type::new(TypeKind::Primitive(PrimitiveType::Table), span);
+        let member = |name: &str, typ| property(&Ident::new(name.to_string(), span), typ);
+        let members = vec![
+            member(
+                "version",
+                Type::new(TypeKind::Literal(Literal::Integer(version)), span),
+            ),
+            member(
+                "serialize",
+                function_type(&[parameter("value", record.clone())], Some(&table), span),
+            ),
+            member(
+                "deserialize",
+                function_type(&[parameter("saved", table)], Some(&record), span),
+            ),
+        ];
+        self.declare_symbol(
+            &interface.name.node,
+            SymbolKind::Const,
+            Type::new(TypeKind::Object(ObjectType { members, span }), span),
+            span,
+        );
+    }
+
+    /// Report malformed `@schema` and `@migration` attributes, and check
+    /// that each top-level `@schema` record has exactly one migration from
+    /// every version before its current one
+    pub(super) fn check_schemas(&mut self, statements: &[Statement]) {
+        // Each record's name, version and attribute span
+        let mut schemas: Vec<(&str, i64, Span)> = Vec::new();
+        // Each migration's function name and parameter count, record,
+        // version migrated from and attribute span
+        let mut migrations = Vec::new();
+
+        for statement in statements {
+            let statement = match statement {
+                Statement::Export(export) => match &export.kind {
+                    ExportKind::Declaration(decl) => decl.as_ref(),
+                    _ => continue,
+                },
+                statement => statement,
+            };
+            let decorators = statement_decorators(statement);
+            if let Some(version) = schema_version(decorators) {
+                let span = find_attribute(decorators, "schema").map_or(Span::dummy(), |d| d.span);
+                match (statement, version) {
+                    (Statement::Interface(interface), Ok(version)) => {
+                        schemas.push((&interface.name.node, version, span))
+                    }
+                    (Statement::Interface(_), Err(span)) => {
+                        self.report_error(span, TypeCheckError::InvalidSchema)
+                    }
+                    _ => self.report_warning(span, "TL3001", "@schema only applies to interfaces"),
+                }
+            }
+            if let Some(step) = migration_step(decorators) {
+                let span =
+                    find_attribute(decorators, "migration").map_or(Span::dummy(), |d| d.span);
+                match (statement, step) {
+                    (Statement::Function(func), Ok((schema, from))) => migrations.push((
+                        &func.name.node,
+                        func.parameters.len(),
+                        schema,
+                        from,
+                        span,
+                    )),
+                    (Statement::Function(_), Err(span)) => {
+                        self.report_error(span, TypeCheckError::InvalidMigration)
+                    }
+                    _ => {
+                        self.report_warning(span, "TL3001", "@migration only applies to functions")
+                    }
+                }
+            }
+        }
+
+        let mut covered = HashSet::new();
+        for (function, parameters, schema, from, span) in migrations {
+            if parameters != 1 {
+                self.report_error(span, TypeCheckError::MigrationParameters(function.clone()));
+            }
+            let Some(&(_, version, _)) = schemas.iter().find(|(name, _, _)| *name == schema) else {
+                self.report_error(span, TypeCheckError::UnknownSchema(schema.to_string()));
+                continue;
+            };
+            let schema = schema.to_string();
+            if from >= version {
+                self.report_error(
+                    span,
+                    TypeCheckError::MigrationOutOfRange {
+                        schema,
+                        version,
+                        from,
+                    },
+                );
+            } else if !covered.insert((schema.clone(), from)) {
+                self.report_error(span, TypeCheckError::DuplicateMigration { schema, from });
+            }
+        }
+
+        for (schema, version, span) in schemas {
+            for from in 1..version {
+                if !covered.contains(&(schema.to_string(), from)) {
+                    self.report_error(
+                        span,
+                        TypeCheckError::MissingMigration {
+                            schema: schema.to_string(),
+                            version,
+                            from,
+                        },
+                    );
+                }
+            }
+        }
+    }
+}
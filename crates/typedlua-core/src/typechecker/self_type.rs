@@ -0,0 +1,230 @@
+use super::symbol_table::SymbolKind;
+use super::type_environment::class_methods;
+use super::{function_type, method_type, property_type, reference_type, MemberLookup, TypeChecker};
+use crate::ast::pattern::Pattern;
+use crate::ast::statement::{ClassDeclaration, ClassMember, InterfaceMember};
+use crate::ast::types::{FunctionType, ObjectTypeMember, Type, TypeKind};
+use crate::errors::TypeCheckError;
+use crate::span::Span;
+
+impl TypeChecker {
+    /// Bind the implicit `self` of an instance member to the class type.
+    /// An explicit `self` parameter shadows it.
+    pub(super) fn declare_implicit_self(&mut self, class: &ClassDeclaration) {
+        self.declare_symbol(
+            "self",
+            SymbolKind::Parameter,
+            reference_type(&class.name.node, class.name.span),
+            class.name.span,
+        );
+    }
+
+    /// Type of a member declared on a class, its mixins or its bases.
+    /// `None` when the class is unknown or does not declare the member.
+    pub(super) fn class_member_type(&self, class_name: &str, member: &str) -> Option<Type> {
+        let class = self.type_env.get_class(class_name)?;
+        let owners = std::iter::once(class)
+            .chain(self.type_env.mixin_classes(class))
+            .chain(self.type_env.ancestors(class));
+
+        for owner in owners {
+            for candidate in &owner.members {
+                match candidate {
+                    ClassMember::Property(prop) if prop.name.node == member => {
+                        return Some(prop.type_annotation.clone());
+                    }
+                    ClassMember::Getter(getter) if getter.name.node == member => {
+                        return Some(getter.return_type.clone());
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(method) = class_methods(owner).find(|m| m.name.node == member) {
+                return Some(function_type(
+                    &method.parameters,
+                    method.return_type.as_ref(),
+                    method.span,
+                ));
+            }
+        }
+        None
+    }
+
+    /// A colon call passes its receiver as `self`, so it must satisfy a
+    /// method's explicitly declared `self` type
+    pub(super) fn check_method_receiver(&mut self, receiver: &Type, method: &Type, span: Span) {
+        let Some(expected) = explicit_self_type(method) else {
+            return;
+        };
+        if !self.is_receiver_compatible(receiver, expected) {
+            self.report_error(
+                span,
+                TypeCheckError::SelfTypeMismatch {
+                    expected: expected.to_string(),
+                    actual: receiver.to_string(),
+                },
+            );
+        }
+    }
+
+    /// Classes are compared nominally: the receiver must be the expected
+    /// class, one of its subclasses or a class mixing it in. Interfaces and
+    /// object aliases are compared structurally: the receiver must provide
+    /// every required member with an assignable type.
+    fn is_receiver_compatible(&self, receiver: &Type, expected: &Type) -> bool {
+        if let (Some(receiver_class), Some(expected_class)) =
+            (self.class_of(receiver), self.class_of(expected))
+        {
+            return receiver_class.name.node == expected_class.name.node
+                || self
+                    .type_env
+                    .ancestors(receiver_class)
+                    .into_iter()
+                    .chain(self.type_env.mixin_classes(receiver_class))
+                    .any(|base| base.name.node == expected_class.name.node);
+        }
+
+        let receiver = self.expand_aliases(receiver, 0);
+        let expected = self.expand_aliases(expected, 0);
+        let structural = self.class_of(&receiver).is_some()
+            || self.interface_name(&receiver).is_some()
+            || matches!(receiver.kind, TypeKind::Object(_));
+        let Some(required) = self.required_members(&expected).filter(|_| structural) else {
+            return self.is_assignable(&receiver, &expected);
+        };
+        required
+            .iter()
+            .all(|(name, typ)| match self.receiver_member(&receiver, name) {
+                MemberLookup::Found(actual) => self.is_assignable(&actual, typ),
+                MemberLookup::Unknown => true,
+                _ => false,
+            })
+    }
+
+    /// The non-optional members an interface or object type declares.
+    /// `None` for types that are not compared structurally.
+    fn required_members(&self, typ: &Type) -> Option<Vec<(String, Type)>> {
+        let members = match &typ.kind {
+            TypeKind::Parenthesized(inner) => return self.required_members(inner),
+            TypeKind::Object(object) => object
+                .members
+                .iter()
+                .filter_map(|member| match member {
+                    ObjectTypeMember::Property(prop) if !prop.is_optional => {
+                        Some((prop.name.node.clone(), prop.type_annotation.clone()))
+                    }
+                    ObjectTypeMember::Method(method) => {
+                        Some((method.name.node.clone(), method_type(method)))
+                    }
+                    _ => None,
+                })
+                .collect(),
+            TypeKind::Reference(reference)
+                if self.type_env.get_interface(&reference.name.node).is_some() =>
+            {
+                let (chain, _) = self.type_env.interface_chain(&reference.name.node);
+                chain
+                    .iter()
+                    .flat_map(|decl| &decl.members)
+                    .filter_map(|member| match member {
+                        InterfaceMember::Property(prop) if !prop.is_optional => {
+                            Some((prop.name.node.clone(), prop.type_annotation.clone()))
+                        }
+                        InterfaceMember::Method(method) => {
+                            Some((method.name.node.clone(), method_type(method)))
+                        }
+                        _ => None,
+                    })
+                    .collect()
+            }
+            _ => return None,
+        };
+        Some(members)
+    }
+
+    /// Look up a member on a class, interface or object receiver without
+    /// reporting anything
+    fn receiver_member(&self, receiver: &Type, member: &str) -> MemberLookup {
+        if let Some(class) = self.class_of(receiver) {
+            return match self.class_member_type(&class.name.node, member) {
+                Some(typ) => MemberLookup::Found(typ),
+                None => MemberLookup::Missing,
+            };
+        }
+        if let Some(interface) = self.interface_name(receiver) {
+            return self.lookup_interface_member(&interface, member);
+        }
+        match &receiver.kind {
+            TypeKind::Parenthesized(inner) => self.receiver_member(inner, member),
+            TypeKind::Object(object) => object
+                .members
+                .iter()
+                .find_map(|candidate| match candidate {
+                    ObjectTypeMember::Property(prop) if prop.name.node == member => {
+                        Some(property_type(prop))
+                    }
+                    ObjectTypeMember::Method(method) if method.name.node == member => {
+                        Some(method_type(method))
+                    }
+                    _ => None,
+                })
+                .map_or(MemberLookup::Missing, MemberLookup::Found),
+            _ => MemberLookup::Unknown,
+        }
+    }
+
+    fn class_of(&self, typ: &Type) -> Option<&ClassDeclaration> {
+        match &typ.kind {
+            TypeKind::Reference(reference) => self.type_env.get_class(&reference.name.node),
+            TypeKind::Parenthesized(inner) => self.class_of(inner),
+            _ => None,
+        }
+    }
+}
+
+/// The declared type of a function's leading `self` parameter
+fn explicit_self_type(typ: &Type) -> Option<&Type> {
+    match &typ.kind {
+        TypeKind::Function(func) => {
+            let first = func.parameters.first()?;
+            match &first.pattern {
+                Pattern::Identifier(name) if name.node == "self" => first.type_annotation.as_ref(),
+                _ => None,
+            }
+        }
+        TypeKind::Parenthesized(inner) => explicit_self_type(inner),
+        _ => None,
+    }
+}
+
+/// Replace `Self` in a member's type with the type it was accessed through,
+/// so a method returning `Self` keeps the subclass type
+pub(super) fn substitute_self(typ: Type, receiver: &Type) -> Type {
+    let span = typ.span;
+    let kind = match typ.kind {
+        TypeKind::Reference(reference)
+            if reference.name.node == "Self" && reference.type_arguments.is_none() =>
+        {
+            return receiver.clone();
+        }
+        TypeKind::Function(func) => TypeKind::Function(FunctionType {
+            return_type: Box::new(substitute_self(*func.return_type, receiver)),
+            ..func
+        }),
+        TypeKind::Nullable(inner) => {
+            TypeKind::Nullable(Box::new(substitute_self(*inner, receiver)))
+        }
+        TypeKind::Parenthesized(inner) => {
+            TypeKind::Parenthesized(Box::new(substitute_self(*inner, receiver)))
+        }
+        TypeKind::Array(element) => TypeKind::Array(Box::new(substitute_self(*element, receiver))),
+        TypeKind::Union(types) => TypeKind::Union(
+            types
+                .into_iter()
+                .map(|t| substitute_self(t, receiver))
+                .collect(),
+        ),
+        kind => kind,
+    };
+    Type::new(kind, span)
+}
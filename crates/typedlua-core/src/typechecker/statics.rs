@@ -0,0 +1,116 @@
+use super::symbol_table::SymbolKind;
+use super::TypeChecker;
+use crate::ast::expression::{Expression, ExpressionKind};
+use crate::ast::statement::{ClassDeclaration, ClassMember};
+use crate::ast::types::{Type, TypeKind};
+use crate::ast::Ident;
+use crate::errors::TypeCheckError;
+
+impl TypeChecker {
+    /// Class constants can be named in types: `const SIDES = 4` in `Shape`
+    /// makes `Shape.SIDES` the literal type `4`
+    pub(super) fn collect_class_constants(&mut self, class: &ClassDeclaration) {
+        for member in &class.members {
+            if let ClassMember::Property(prop) = member {
+                if prop.is_const {
+                    let name = format!("{}.{}", class.name.node, prop.name.node);
+                    self.type_env
+                        .register_alias(&name, prop.type_annotation.clone());
+                }
+            }
+        }
+    }
+
+    /// Static members live on the class table and instance members on
+    /// instances, so `Class.member` must name a static member and
+    /// `instance.member` an instance one
+    pub(super) fn check_static_access(
+        &mut self,
+        object: &Expression,
+        object_type: &Type,
+        member: &Ident,
+    ) {
+        let TypeKind::Reference(reference) = &object_type.kind else {
+            return;
+        };
+        let class = &reference.name.node;
+        let Some(is_static) = self
+            .class_member(class, &member.node)
+            .map(ClassMember::is_static)
+        else {
+            return;
+        };
+
+        let path = value_path(object);
+        if path.as_deref() == Some(class.as_str()) {
+            if !is_static {
+                self.report_error(
+                    member.span,
+                    TypeCheckError::InstanceMemberThroughClass {
+                        member: member.node.clone(),
+                        class: class.clone(),
+                    },
+                );
+            }
+            return;
+        }
+
+        // Imports and constants may name the class itself under another
+        // name, so only locals, parameters and computed values are taken
+        // to be instances
+        let is_instance = match path {
+            Some(path) => {
+                let root = path.split('.').next().unwrap_or_default();
+                self.symbols.lookup(root).is_some_and(|symbol| {
+                    matches!(symbol.kind, SymbolKind::Variable | SymbolKind::Parameter)
+                })
+            }
+            None => true,
+        };
+        if is_static && is_instance {
+            self.report_error(
+                member.span,
+                TypeCheckError::StaticMemberThroughInstance {
+                    member: member.node.clone(),
+                    class: class.clone(),
+                },
+            );
+        }
+    }
+
+    /// Whether `member` is a constant of the class or one of its bases
+    pub(super) fn is_class_constant(&self, class: &str, member: &str) -> bool {
+        matches!(
+            self.class_member(class, member),
+            Some(ClassMember::Property(prop)) if prop.is_const
+        )
+    }
+
+    /// The nearest declaration of a named member in a class, its mixins or
+    /// its bases
+    fn class_member(&self, class_name: &str, member: &str) -> Option<&ClassMember> {
+        let class = self.type_env.get_class(class_name)?;
+        std::iter::once(class)
+            .chain(self.type_env.mixin_classes(class))
+            .chain(self.type_env.ancestors(class))
+            .flat_map(|owner| &owner.members)
+            .find(|candidate| match candidate {
+                ClassMember::Property(prop) => prop.name.node == member,
+                ClassMember::Method(method) => method.name.node == member,
+                ClassMember::Getter(getter) => getter.name.node == member,
+                ClassMember::Setter(setter) => setter.name.node == member,
+                ClassMember::Constructor(_) | ClassMember::StaticBlock(_) => false,
+            })
+    }
+}
+
+/// `a.b.c` for a chain of member accesses on a name
+fn value_path(expr: &Expression) -> Option<String> {
+    match &expr.kind {
+        ExpressionKind::Identifier(name) => Some(name.clone()),
+        ExpressionKind::Member(object, member) => {
+            Some(format!("{}.{}", value_path(object)?, member.node))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,59 @@
+use super::TypeChecker;
+use crate::ast::Program;
+use crate::diagnostics::{CollectingDiagnosticHandler, DiagnosticHandler};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use std::sync::{Arc, OnceLock};
+
+/// Declarations of Lua's standard library, one file per library, and of
+/// the runtime's own classes. Members that only some targets provide are
+/// guarded with `@target(...)`.
+const STDLIB: &[(&str, &str)] = &[
+    ("globals.d.tl", include_str!("stdlib/globals.d.tl")),
+    ("string.d.tl", include_str!("stdlib/string.d.tl")),
+    ("table.d.tl", include_str!("stdlib/table.d.tl")),
+    ("math.d.tl", include_str!("stdlib/math.d.tl")),
+    ("io.d.tl", include_str!("stdlib/io.d.tl")),
+    ("os.d.tl", include_str!("stdlib/os.d.tl")),
+    ("coroutine.d.tl", include_str!("stdlib/coroutine.d.tl")),
+    ("debug.d.tl", include_str!("stdlib/debug.d.tl")),
+    ("random.d.tl", include_str!("stdlib/random.d.tl")),
+];
+
+/// The standard library declarations, parsed once per process
+fn stdlib() -> &'static [Program] {
+    static PROGRAMS: OnceLock<Vec<Program>> = OnceLock::new();
+    PROGRAMS.get_or_init(|| {
+        STDLIB
+            .iter()
+            .map(|(name, source)| parse(name, source))
+            .collect()
+    })
+}
+
+fn parse(name: &str, source: &str) -> Program {
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let program = Lexer::new(source, handler.clone())
+        .tokenize()
+        .ok()
+        .and_then(|tokens| Parser::new(tokens, handler.clone()).parse().ok());
+    match (program, handler.get_diagnostics().first()) {
+        (Some(program), None) => program,
+        (_, diagnostic) => panic!(
+            "Invalid standard library file {}: {:?}",
+            name,
+            diagnostic.map(|d| &d.message)
+        ),
+    }
+}
+
+impl TypeChecker {
+    /// Declare the standard library for the configured target in the
+    /// outermost scope. A program's own declarations shadow it.
+    pub(super) fn load_stdlib(&mut self) {
+        for program in stdlib() {
+            self.collect_declarations(&program.statements);
+        }
+        self.symbols.enter_scope();
+    }
+}
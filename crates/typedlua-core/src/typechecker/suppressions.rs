@@ -0,0 +1,133 @@
+//! `// @tl-ignore CODE...` comments, silencing the checker's diagnostics
+//! with those codes, or every code when they name none, on the comment's
+//! line and in the whole statement starting on the next line, such as a
+//! function with its body. Comments that silence nothing are reported, so
+//! they do not outlive the problems they were written for.
+
+use super::TypeChecker;
+use crate::ast::statement::Statement;
+use crate::ast::visit::Visit;
+use crate::diagnostics::Diagnostic;
+use crate::errors::TypeCheckError;
+use crate::lexer::Directive;
+use crate::span::Span;
+
+/// One `// @tl-ignore` comment
+pub(super) struct Suppression {
+    /// The comment
+    span: Span,
+    codes: Vec<String>,
+    /// The character offsets of the statement starting on the next line
+    statement: Option<(usize, usize)>,
+    /// The codes something was silenced under
+    used: Vec<String>,
+}
+
+impl Suppression {
+    fn covers(&self, span: Span, code: &str) -> bool {
+        let named = self.codes.is_empty() || self.codes.iter().any(|c| c == code);
+        let inside = span.line == self.span.line
+            || self
+                .statement
+                .is_some_and(|(start, end)| start <= span.start && span.start < end);
+        named && inside
+    }
+}
+
+impl TypeChecker {
+    /// Read the `// @tl-ignore` directives, which are errors instead when
+    /// `noSuppressions` forbids them
+    pub(super) fn set_suppressions(&mut self, directives: &[Directive]) {
+        for directive in directives {
+            if directive.name != "tl-ignore" {
+                continue;
+            }
+            if self.config.compiler_options.no_suppressions {
+                self.report_error(
+                    directive.span,
+                    TypeCheckError::SuppressionForbidden(directive.name.clone()),
+                );
+                continue;
+            }
+            self.suppressions.push(Suppression {
+                span: directive.span,
+                codes: directive.arguments.clone(),
+                statement: None,
+                used: Vec::new(),
+            });
+        }
+    }
+
+    /// Extend each suppression over the statement starting on the line
+    /// after it, the outermost one where several start there
+    pub(super) fn cover_suppressed_statements(&mut self, statements: &[Statement]) {
+        struct Starts {
+            lines: Vec<usize>,
+            found: Vec<(usize, Span)>,
+        }
+
+        impl Visit for Starts {
+            fn visit_statement(&mut self, statement: &Statement) {
+                let span = statement.span();
+                let line = span.line;
+                if self.lines.contains(&line) && !self.found.iter().any(|(l, _)| *l == line) {
+                    self.found.push((line, span));
+                }
+                self.walk_statement(statement);
+            }
+        }
+
+        if self.suppressions.is_empty() {
+            return;
+        }
+        let mut starts = Starts {
+            lines: self.suppressions.iter().map(|s| s.span.line + 1).collect(),
+            found: Vec::new(),
+        };
+        for statement in statements {
+            starts.visit_statement(statement);
+        }
+        for suppression in &mut self.suppressions {
+            suppression.statement = starts
+                .found
+                .iter()
+                .find(|(line, _)| *line == suppression.span.line + 1)
+                .map(|(_, span)| (span.start, span.end));
+        }
+    }
+
+    /// Whether a suppression silences a diagnostic, noting that it did
+    pub(super) fn suppress(&mut self, diagnostic: &Diagnostic) -> bool {
+        let Some(code) = &diagnostic.code else {
+            return false;
+        };
+        let covering = self
+            .suppressions
+            .iter_mut()
+            .find(|suppression| suppression.covers(diagnostic.span, code));
+        match covering {
+            Some(suppression) => {
+                if !suppression.used.contains(code) {
+                    suppression.used.push(code.clone());
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Warn about suppressions, or the codes they name, that silenced
+    /// nothing
+    pub(super) fn report_unused_suppressions(&mut self) {
+        for suppression in std::mem::take(&mut self.suppressions) {
+            if let Some(diagnostic) = Diagnostic::unused_suppression(
+                suppression.span,
+                "tl-ignore",
+                &suppression.codes,
+                &suppression.used,
+            ) {
+                self.diagnostic_handler.report(diagnostic);
+            }
+        }
+    }
+}
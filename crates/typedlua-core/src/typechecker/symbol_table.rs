@@ -0,0 +1,85 @@
+use crate::ast::types::Type;
+use crate::span::Span;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Const,
+    Variable,
+    Function,
+    Class,
+    Namespace,
+    Enum,
+    Parameter,
+}
+
+/// A named value visible in some scope
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub typ: Type,
+    pub span: Span,
+}
+
+/// Lexically scoped value bindings. The outermost scope holds the standard
+/// library and is never popped; the program's globals and ambient
+/// declarations go in the scope above it.
+#[derive(Debug)]
+pub struct SymbolTable {
+    scopes: Vec<HashMap<String, Symbol>>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        SymbolTable {
+            scopes: vec![HashMap::new()],
+        }
+    }
+
+    pub fn enter_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    pub fn exit_scope(&mut self) {
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
+
+    /// Declare a symbol in the innermost scope, shadowing any outer binding
+    pub fn declare(&mut self, symbol: Symbol) {
+        self.scopes
+            .last_mut()
+            .expect("Symbol table always has a global scope")
+            .insert(symbol.name.clone(), symbol);
+    }
+
+    /// Names declared directly in the innermost scope
+    pub fn innermost_names(&self) -> impl Iterator<Item = &String> {
+        self.scopes
+            .last()
+            .into_iter()
+            .flat_map(|scope| scope.keys())
+    }
+
+    /// Whether a name resolves to the standard library rather than to a
+    /// binding of the program's own
+    pub fn is_builtin(&self, name: &str) -> bool {
+        self.scopes
+            .iter()
+            .rposition(|scope| scope.contains_key(name))
+            == Some(0)
+    }
+
+    /// Look a name up from the innermost scope outwards
+    pub fn lookup(&self, name: &str) -> Option<&Symbol> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+}
+
+impl Default for SymbolTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,201 @@
+use super::readonly::readonly_inner;
+use super::TypeChecker;
+use crate::ast::expression::{ArrayElement, Expression, ExpressionKind, Literal, UnaryOp};
+use crate::ast::statement::{IndexKeyType, InterfaceMember};
+use crate::ast::types::{ObjectTypeMember, PrimitiveType, Type, TypeKind};
+use crate::errors::TypeCheckError;
+
+/// The two halves of a Lua table as a type describes them: the sequence
+/// read by `#` and `ipairs`, and the named fields only `pairs` reaches
+#[derive(Debug, Clone)]
+pub(super) struct TableParts {
+    /// Element type of the array part, if the table has one
+    pub array: Option<Type>,
+    /// Whether the table has named fields or string keys
+    pub fields: bool,
+}
+
+impl TypeChecker {
+    /// The array and hash parts of a table type, when the type says enough
+    /// to tell. Classes, primitives and unresolved names give `None`.
+    pub(super) fn table_parts(&self, typ: &Type) -> Option<TableParts> {
+        if let Some(inner) = readonly_inner(typ) {
+            return self.table_parts(&inner);
+        }
+        match &typ.kind {
+            TypeKind::Parenthesized(inner) => self.table_parts(inner),
+            TypeKind::Array(element) => Some(TableParts {
+                array: Some((**element).clone()),
+                fields: false,
+            }),
+            TypeKind::Object(object) => {
+                let mut parts = TableParts {
+                    array: None,
+                    fields: false,
+                };
+                for member in &object.members {
+                    match member {
+                        ObjectTypeMember::Index(index)
+                            if index.key_type == IndexKeyType::Number =>
+                        {
+                            parts.array = Some(index.value_type.clone());
+                        }
+                        // Spreads and computed keys may add anything
+                        ObjectTypeMember::Index(index) if is_unknown(&index.value_type) => {
+                            return None
+                        }
+                        _ => parts.fields = true,
+                    }
+                }
+                Some(parts)
+            }
+            TypeKind::Intersection(types) => {
+                let mut combined = TableParts {
+                    array: None,
+                    fields: false,
+                };
+                for part in types {
+                    let parts = self.table_parts(part)?;
+                    combined.array = combined.array.or(parts.array);
+                    combined.fields |= parts.fields;
+                }
+                Some(combined)
+            }
+            TypeKind::Reference(_) => {
+                let interface = self.interface_name(typ)?;
+                let (chain, complete) = self.type_env.interface_chain(&interface);
+                if !complete {
+                    return None;
+                }
+                let fields = chain.iter().flat_map(|decl| &decl.members).any(|member| {
+                    !matches!(member, InterfaceMember::Index(index) if index.key_type == IndexKeyType::Number)
+                });
+                Some(TableParts {
+                    array: self.index_signature_type(&interface, IndexKeyType::Number),
+                    fields,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// `#` and `ipairs` only see the array part, so using them on a table
+    /// made only of named fields is a mistake
+    pub(super) fn check_sequence_use(&mut self, operand: &Expression, typ: &Type, ipairs: bool) {
+        let Some(parts) = self.table_parts(typ) else {
+            return;
+        };
+        if parts.array.is_some() || !parts.fields {
+            return;
+        }
+        let error = if ipairs {
+            TypeCheckError::IpairsWithoutArrayPart(typ.to_string())
+        } else {
+            TypeCheckError::LengthWithoutArrayPart(typ.to_string())
+        };
+        self.report_error(operand.span, error);
+    }
+
+    /// Storing `nil` in the array part leaves a hole, after which `#` and
+    /// `ipairs` give unreliable results
+    pub(super) fn check_array_hole(
+        &mut self,
+        target: &Expression,
+        object_type: &Type,
+        index_type: &Type,
+        value: &Expression,
+    ) {
+        if !matches!(value.kind, ExpressionKind::Literal(Literal::Nil)) {
+            return;
+        }
+        let numeric = matches!(
+            index_type.kind,
+            TypeKind::Primitive(PrimitiveType::Number | PrimitiveType::Integer)
+                | TypeKind::Literal(Literal::Number(_) | Literal::Integer(_))
+        );
+        let has_array = self
+            .table_parts(object_type)
+            .is_some_and(|parts| parts.array.is_some());
+        let ExpressionKind::Index(object, index) = &target.kind else {
+            return;
+        };
+        // `t[#t] = nil` removes the last element without leaving a hole
+        if !numeric || !has_array || is_length_of(index, object) {
+            return;
+        }
+        self.report_warning(
+            target.span,
+            "TL3007",
+            "Assigning nil to an array element leaves a hole; '#' and ipairs stop at the first nil, so use table.remove instead",
+        );
+        if let ExpressionKind::Identifier(name) = &object.kind {
+            self.array_holes.insert(name.clone(), target.span);
+        }
+    }
+
+    /// `#t` after `nil` was written into the middle of `t` may give the
+    /// index of any border, not the element count
+    pub(super) fn check_length_after_holes(&mut self, operand: &Expression) {
+        let ExpressionKind::Identifier(name) = &operand.kind else {
+            return;
+        };
+        if let Some(hole) = self.array_holes.get(name) {
+            let message = format!(
+                "'#{}' is unreliable after nil was written into it on line {}",
+                name, hole.line
+            );
+            self.report_warning(operand.span, "TL3008", &message);
+        }
+    }
+
+    /// A `nil` written into an array literal makes it sparse from the start
+    pub(super) fn check_array_literal_holes(&mut self, elements: &[ArrayElement]) {
+        let last = elements.len().saturating_sub(1);
+        for (i, element) in elements.iter().enumerate() {
+            if let ArrayElement::Expression(expr) = element {
+                if matches!(expr.kind, ExpressionKind::Literal(Literal::Nil)) {
+                    let message = if i == last {
+                        "A trailing nil is not part of the array; '#' does not count it"
+                    } else {
+                        "A nil element makes the array sparse; '#' and ipairs may stop before it"
+                    };
+                    self.report_warning(expr.span, "TL3009", message);
+                }
+            }
+        }
+    }
+}
+
+/// Whether `index` is `#object`, for a plain variable `object`
+fn is_length_of(index: &Expression, object: &Expression) -> bool {
+    match (&index.kind, &object.kind) {
+        (ExpressionKind::Unary(UnaryOp::Length, operand), ExpressionKind::Identifier(name)) => {
+            matches!(&operand.kind, ExpressionKind::Identifier(n) if n == name)
+        }
+        _ => false,
+    }
+}
+
+/// Whether `#` applies to a value of this type: strings and tables do,
+/// numbers, booleans, `nil` and functions do not
+pub(super) fn has_length(typ: &Type) -> bool {
+    match &typ.kind {
+        TypeKind::Primitive(primitive) => !matches!(
+            primitive,
+            PrimitiveType::Number
+                | PrimitiveType::Integer
+                | PrimitiveType::Boolean
+                | PrimitiveType::Nil
+                | PrimitiveType::Void
+        ),
+        TypeKind::Literal(literal) => matches!(literal, Literal::String(_)),
+        TypeKind::Function(_) => false,
+        TypeKind::Parenthesized(inner) => has_length(inner),
+        TypeKind::Union(types) => types.iter().all(has_length),
+        _ => true,
+    }
+}
+
+fn is_unknown(typ: &Type) -> bool {
+    matches!(typ.kind, TypeKind::Primitive(PrimitiveType::Unknown))
+}
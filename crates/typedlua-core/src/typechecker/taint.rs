@@ -0,0 +1,428 @@
+use super::{expression_label, TypeChecker};
+use crate::ast::expression::*;
+use crate::ast::pattern::{ArrayPatternElement, MultipleRest, Pattern};
+use crate::ast::statement::*;
+use crate::ast::Ident;
+use crate::span::Span;
+use std::collections::HashMap;
+
+/// Functions that run the string they are given as code or as a shell
+/// command
+const SINKS: &[&str] = &[
+    "load",
+    "loadstring",
+    "loadfile",
+    "dofile",
+    "os.execute",
+    "io.popen",
+];
+
+impl TypeChecker {
+    /// With `taintAnalysis`, warn where a value read from one of the
+    /// `taintSources` can reach a sink, listing the variables it passed
+    /// through. Taint is tracked per variable and never cleared, and it
+    /// does not follow values through function parameters or returns.
+    pub(super) fn check_taint_flows(&mut self, statements: &[Statement]) {
+        if !self.config.compiler_options.taint_analysis {
+            return;
+        }
+        let mut flow = TaintFlow {
+            sources: &self.config.compiler_options.taint_sources,
+            scopes: vec![HashMap::new()],
+            warnings: Vec::new(),
+        };
+        flow.statements(statements);
+        for (span, message) in flow.warnings {
+            self.report_warning(span, "TL3011", &message);
+        }
+    }
+}
+
+/// Where an untrusted value was read, then each variable it was stored in
+type TaintPath = Vec<(String, Span)>;
+
+struct TaintFlow<'a> {
+    sources: &'a [String],
+    /// Names in scope, with the path of the untrusted value each may hold.
+    /// The outermost scope also holds globals.
+    scopes: Vec<HashMap<String, Option<TaintPath>>>,
+    warnings: Vec<(Span, String)>,
+}
+
+impl TaintFlow<'_> {
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.scopes.push(HashMap::new());
+        self.statements(&block.statements);
+        self.scopes.pop();
+    }
+
+    /// Walk a function body, whose parameters start out trusted
+    fn function(&mut self, parameters: &[Parameter], body: impl FnOnce(&mut Self)) {
+        self.scopes.push(HashMap::new());
+        for param in parameters {
+            self.bind(&param.pattern, None);
+        }
+        body(self);
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Ident, taint: Option<TaintPath>) {
+        let taint = taint.map(|mut path| {
+            path.push((format!("'{}'", name.node), name.span));
+            path
+        });
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.node.clone(), taint);
+        }
+    }
+
+    fn bind(&mut self, pattern: &Pattern, taint: Option<TaintPath>) {
+        match pattern {
+            Pattern::Identifier(name) => self.declare(name, taint),
+            Pattern::Array(array) => {
+                for element in &array.elements {
+                    match element {
+                        ArrayPatternElement::Pattern(inner) => self.bind(inner, taint.clone()),
+                        ArrayPatternElement::Rest(name) => self.declare(name, taint.clone()),
+                        ArrayPatternElement::Hole => {}
+                    }
+                }
+            }
+            Pattern::Object(object) => {
+                for property in &object.properties {
+                    match &property.value {
+                        Some(inner) => self.bind(inner, taint.clone()),
+                        None => self.declare(&property.key, taint.clone()),
+                    }
+                }
+            }
+            Pattern::Multiple(multiple) => {
+                for name in &multiple.names {
+                    self.declare(name, taint.clone());
+                }
+                if let Some(MultipleRest::Capture(name)) = &multiple.rest {
+                    self.declare(name, taint);
+                }
+            }
+            Pattern::Literal(..) | Pattern::Wildcard(_) => {}
+        }
+    }
+
+    /// Store an untrusted value into an existing variable, or a global. A
+    /// trusted value leaves the variable as it was, since the variable may
+    /// still hold an untrusted one on another path.
+    fn store(&mut self, name: &str, span: Span, taint: Option<TaintPath>) {
+        let Some(mut path) = taint else {
+            return;
+        };
+        path.push((format!("'{}'", name), span));
+        let scope = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find(|scope| scope.contains_key(name));
+        match scope {
+            Some(scope) => scope.insert(name.to_string(), Some(path)),
+            None => self.scopes[0].insert(name.to_string(), Some(path)),
+        };
+    }
+
+    fn lookup(&self, name: &str) -> Option<TaintPath> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .cloned()
+            .flatten()
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Variable(decl) => {
+                let taint = self.expression(&decl.initializer);
+                self.bind(&decl.pattern, taint);
+            }
+            Statement::Function(func) => {
+                self.declare(&func.name, None);
+                self.function(&func.parameters, |flow| flow.block(&func.body));
+            }
+            Statement::Class(class) => {
+                for member in &class.members {
+                    match member {
+                        ClassMember::Property(prop) => {
+                            if let Some(initializer) = &prop.initializer {
+                                self.expression(initializer);
+                            }
+                        }
+                        ClassMember::Constructor(ctor) => {
+                            if let Some(body) = &ctor.body {
+                                self.function(&ctor.parameters, |flow| flow.block(body));
+                            }
+                        }
+                        ClassMember::Method(method) => {
+                            if let Some(body) = &method.body {
+                                self.function(&method.parameters, |flow| flow.block(body));
+                            }
+                        }
+                        ClassMember::Getter(getter) => {
+                            self.function(&[], |flow| flow.block(&getter.body));
+                        }
+                        ClassMember::Setter(setter) => {
+                            self.function(std::slice::from_ref(&setter.parameter), |flow| {
+                                flow.block(&setter.body)
+                            });
+                        }
+                        ClassMember::StaticBlock(block) => self.block(&block.body),
+                    }
+                }
+            }
+            Statement::Export(export) => match &export.kind {
+                ExportKind::Declaration(decl) => self.statement(decl),
+                ExportKind::Default(expr) | ExportKind::Equals(expr) => {
+                    self.expression(expr);
+                }
+                ExportKind::Named(_) => {}
+            },
+            Statement::Namespace(namespace) => self.block(&namespace.body),
+            Statement::If(if_stmt) => {
+                self.expression(&if_stmt.condition);
+                self.block(&if_stmt.then_block);
+                for else_if in &if_stmt.else_ifs {
+                    self.expression(&else_if.condition);
+                    self.block(&else_if.block);
+                }
+                if let Some(else_block) = &if_stmt.else_block {
+                    self.block(else_block);
+                }
+            }
+            Statement::While(while_stmt) => {
+                self.expression(&while_stmt.condition);
+                self.block(&while_stmt.body);
+            }
+            Statement::For(ForStatement::Numeric(for_num)) => {
+                self.expression(&for_num.start);
+                self.expression(&for_num.end);
+                if let Some(step) = &for_num.step {
+                    self.expression(step);
+                }
+                self.scopes.push(HashMap::new());
+                self.declare(&for_num.variable, None);
+                self.block(&for_num.body);
+                self.scopes.pop();
+            }
+            // `for line in io.lines()`: the loop variables hold whatever the
+            // iterator produces
+            Statement::For(ForStatement::Generic(for_gen)) => {
+                let mut taint = None;
+                for iterator in &for_gen.iterators {
+                    taint = taint.or(self.expression(iterator));
+                }
+                self.scopes.push(HashMap::new());
+                for variable in &for_gen.variables {
+                    self.declare(variable, taint.clone());
+                }
+                self.block(&for_gen.body);
+                self.scopes.pop();
+            }
+            Statement::Repeat(repeat) => {
+                self.block(&repeat.body);
+                self.expression(&repeat.until);
+            }
+            Statement::Labeled(labeled) => self.statement(&labeled.body),
+            Statement::Return(ret) => {
+                for value in &ret.values {
+                    self.expression(value);
+                }
+            }
+            Statement::Expression(expr) => {
+                self.expression(expr);
+            }
+            Statement::Block(block) | Statement::Untyped(block) => self.block(block),
+            Statement::Interface(_)
+            | Statement::TypeAlias(_)
+            | Statement::Enum(_)
+            | Statement::Import(_)
+            | Statement::Declare(_)
+            | Statement::Break(_)
+            | Statement::Continue(_)
+            | Statement::Error(_) => {}
+        }
+    }
+
+    /// Walk an expression, returning how an untrusted value got into it
+    fn expression(&mut self, expr: &Expression) -> Option<TaintPath> {
+        match &expr.kind {
+            ExpressionKind::Identifier(name) => self.lookup(name),
+            ExpressionKind::Literal(_)
+            | ExpressionKind::SelfKeyword
+            | ExpressionKind::SuperKeyword => None,
+            ExpressionKind::Assignment(target, _, value) => {
+                let taint = self.expression(value);
+                self.expression(target);
+                // Storing into a field taints the whole table
+                if let Some((name, span)) = root_identifier(target) {
+                    self.store(name, span, taint.clone());
+                }
+                taint
+            }
+            // `value |> sink` calls the sink with the value
+            ExpressionKind::Pipe(value, callee) => {
+                let taint = self.expression(value);
+                self.expression(callee);
+                let label = expression_label(callee);
+                self.check_sink(&label, taint.as_ref(), expr.span);
+                taint
+            }
+            ExpressionKind::Binary(_, left, right) | ExpressionKind::Index(left, right) => {
+                let left = self.expression(left);
+                let right = self.expression(right);
+                left.or(right)
+            }
+            ExpressionKind::Unary(_, inner)
+            | ExpressionKind::Member(inner, _)
+            | ExpressionKind::OptionalMember(inner, _)
+            | ExpressionKind::Parenthesized(inner)
+            | ExpressionKind::TypeAssertion(inner, _) => self.expression(inner),
+            ExpressionKind::Call(callee, args) => {
+                self.expression(callee);
+                let label = expression_label(callee);
+                let taints = self.arguments(args);
+                if let Some(first) = taints.first() {
+                    self.check_sink(&label, first.as_ref(), expr.span);
+                }
+                if self.sources.contains(&label) {
+                    return Some(vec![(format!("{}()", label), expr.span)]);
+                }
+                // Functions such as `string.format` pass their arguments on
+                taints.into_iter().flatten().next()
+            }
+            ExpressionKind::MethodCall(object, method, args) => {
+                let object = self.expression(object);
+                let taints = self.arguments(args);
+                let source = format!("::{}", method.node);
+                if self.sources.contains(&source) {
+                    return Some(vec![(format!("{}()", expression_label(expr)), expr.span)]);
+                }
+                // `line:lower()` is as untrusted as `line`
+                object.or(taints.into_iter().flatten().next())
+            }
+            ExpressionKind::Function(func) => {
+                self.function(&func.parameters, |flow| flow.block(&func.body));
+                None
+            }
+            ExpressionKind::Arrow(arrow) => {
+                self.function(&arrow.parameters, |flow| match &arrow.body {
+                    ArrowBody::Expression(body) => {
+                        flow.expression(body);
+                    }
+                    ArrowBody::Block(body) => flow.block(body),
+                });
+                None
+            }
+            ExpressionKind::Array(elements) => {
+                let mut taint = None;
+                for element in elements {
+                    match element {
+                        ArrayElement::Expression(value) | ArrayElement::Spread(value) => {
+                            taint = taint.or(self.expression(value));
+                        }
+                    }
+                }
+                taint
+            }
+            ExpressionKind::Object(properties) => {
+                let mut taint = None;
+                for property in properties {
+                    match property {
+                        ObjectProperty::Property { value, .. }
+                        | ObjectProperty::Spread { value, .. } => {
+                            taint = taint.or(self.expression(value));
+                        }
+                        ObjectProperty::Computed { key, value, .. } => {
+                            self.expression(key);
+                            taint = taint.or(self.expression(value));
+                        }
+                    }
+                }
+                taint
+            }
+            ExpressionKind::Conditional(condition, then, otherwise) => {
+                self.expression(condition);
+                let then = self.expression(then);
+                let otherwise = self.expression(otherwise);
+                then.or(otherwise)
+            }
+            ExpressionKind::Match(match_expr) => {
+                let value = self.expression(&match_expr.value);
+                let mut taint = None;
+                for arm in &match_expr.arms {
+                    self.scopes.push(HashMap::new());
+                    self.bind(&arm.pattern, value.clone());
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard);
+                    }
+                    match &arm.body {
+                        MatchArmBody::Expression(body) => taint = taint.or(self.expression(body)),
+                        MatchArmBody::Block(body) => self.block(body),
+                    }
+                    self.scopes.pop();
+                }
+                taint
+            }
+            ExpressionKind::Template(template) => {
+                let mut taint = None;
+                for part in &template.parts {
+                    if let TemplatePart::Expression(inner) | TemplatePart::Formatted(inner, _) =
+                        part
+                    {
+                        taint = taint.or(self.expression(inner));
+                    }
+                }
+                taint
+            }
+        }
+    }
+
+    fn arguments(&mut self, args: &[Argument]) -> Vec<Option<TaintPath>> {
+        args.iter().map(|arg| self.expression(&arg.value)).collect()
+    }
+
+    fn check_sink(&mut self, label: &str, taint: Option<&TaintPath>, span: Span) {
+        let Some(path) = taint else {
+            return;
+        };
+        if !SINKS.contains(&label) {
+            return;
+        }
+        let steps: Vec<String> = path
+            .iter()
+            .map(|(step, at)| format!("{} at {}", step, at))
+            .collect();
+        self.warnings.push((
+            span,
+            format!(
+                "Untrusted value reaches '{}': {}",
+                label,
+                steps.join(" -> ")
+            ),
+        ));
+    }
+}
+
+/// The variable an assignment target writes into, with where it is named:
+/// `x` for `x`, `x.a` and `x[i]`
+fn root_identifier(target: &Expression) -> Option<(&str, Span)> {
+    match &target.kind {
+        ExpressionKind::Identifier(name) => Some((name, target.span)),
+        ExpressionKind::Member(object, _) | ExpressionKind::Index(object, _) => {
+            root_identifier(object)
+        }
+        _ => None,
+    }
+}
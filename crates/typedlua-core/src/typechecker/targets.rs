@@ -0,0 +1,94 @@
+use crate::ast::expression::{ExpressionKind, Literal};
+use crate::ast::statement::{Decorator, DecoratorExpression};
+use crate::ast::Spanned;
+use crate::config::LuaVersion;
+
+/// A `@target(lua53, lua54)` guard restricting a declaration to some targets
+#[derive(Debug, Clone)]
+pub struct TargetGuard {
+    pub targets: Vec<LuaVersion>,
+    /// Names in the guard that are not known targets
+    pub unknown: Vec<Spanned<String>>,
+}
+
+impl TargetGuard {
+    pub fn allows(&self, target: LuaVersion) -> bool {
+        self.targets.contains(&target)
+    }
+}
+
+/// Find the `@target(...)` guard among a declaration's decorators.
+/// Targets may be written as identifiers (`lua51`) or strings (`"5.1"`).
+pub fn target_guard(decorators: &[Decorator]) -> Option<TargetGuard> {
+    let arguments = decorators
+        .iter()
+        .find_map(|decorator| match &decorator.expression {
+            DecoratorExpression::Call {
+                callee, arguments, ..
+            } => match callee.as_ref() {
+                DecoratorExpression::Identifier(name) if name.node == "target" => Some(arguments),
+                _ => None,
+            },
+            _ => None,
+        })?;
+
+    let mut guard = TargetGuard {
+        targets: Vec::new(),
+        unknown: Vec::new(),
+    };
+
+    for argument in arguments {
+        let name = match &argument.kind {
+            ExpressionKind::Identifier(name) => name.clone(),
+            ExpressionKind::Literal(Literal::String(name)) => name.clone(),
+            _ => {
+                guard
+                    .unknown
+                    .push(Spanned::new("<expression>".to_string(), argument.span));
+                continue;
+            }
+        };
+
+        match LuaVersion::from_target_name(&name) {
+            Some(target) => guard.targets.push(target),
+            None => guard.unknown.push(Spanned::new(name, argument.span)),
+        }
+    }
+
+    Some(guard)
+}
+
+/// Platforms named by an `@unavailableOn(roblox, sandbox)` marker, which
+/// hides a declaration from builds for those host platforms
+pub fn unavailable_platforms(decorators: &[Decorator]) -> Vec<String> {
+    decorators
+        .iter()
+        .filter_map(|decorator| match &decorator.expression {
+            DecoratorExpression::Call {
+                callee, arguments, ..
+            } => match callee.as_ref() {
+                DecoratorExpression::Identifier(name) if name.node == "unavailableOn" => {
+                    Some(arguments)
+                }
+                _ => None,
+            },
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|argument| match &argument.kind {
+            ExpressionKind::Identifier(name) => Some(name.clone()),
+            ExpressionKind::Literal(Literal::String(name)) => Some(name.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Human-readable list of targets, e.g. "Lua 5.3 or Lua 5.4"
+pub fn describe_targets(targets: &[LuaVersion]) -> String {
+    let names: Vec<String> = targets.iter().map(|t| t.to_string()).collect();
+    match names.split_last() {
+        None => "no known target".to_string(),
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+    }
+}
@@ -0,0 +1,77 @@
+use super::TypeChecker;
+use crate::ast::expression::{TemplateLiteral, TemplatePart};
+use crate::ast::types::{PrimitiveType, Type, TypeKind};
+use crate::errors::TypeCheckError;
+
+impl TypeChecker {
+    /// Check each interpolated expression, and that a `${value:%...}`
+    /// specifier is one `string.format` conversion accepting the value.
+    /// Targets with an integer subtype reject floats for the integer
+    /// conversions, as `string.format` does at runtime.
+    pub(super) fn check_template(&mut self, template: &TemplateLiteral) {
+        for part in &template.parts {
+            match part {
+                TemplatePart::String(_) => {}
+                TemplatePart::Expression(expr) => {
+                    self.check_expression(expr);
+                }
+                TemplatePart::Formatted(expr, format) => {
+                    let typ = self.check_expression(expr);
+                    let Some(conversion) = format_conversion(format) else {
+                        self.report_error(
+                            expr.span,
+                            TypeCheckError::InvalidFormatSpecifier(format.clone()),
+                        );
+                        continue;
+                    };
+                    // `%s` and `%q` take any value; the rest convert numbers
+                    if matches!(conversion, 's' | 'q') {
+                        continue;
+                    }
+                    let (primitive, expected) =
+                        if "diuoxXc".contains(conversion) && self.has_integer_subtype() {
+                            (PrimitiveType::Integer, "an integer")
+                        } else {
+                            (PrimitiveType::Number, "a number")
+                        };
+                    let expected_type = Type::new(TypeKind::Primitive(primitive), expr.span);
+                    if !self.is_assignable(&typ, &expected_type) {
+                        self.report_error(
+                            expr.span,
+                            TypeCheckError::FormatSpecifierMismatch {
+                                specifier: format.clone(),
+                                expected: expected.to_string(),
+                                found: typ.to_string(),
+                            },
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The conversion character of a specifier such as `%-8.2f`: flags, then
+/// a width and precision of at most two digits each, as `string.format`
+/// accepts
+fn format_conversion(format: &str) -> Option<char> {
+    let rest = format.strip_prefix('%')?;
+    let rest = rest.trim_start_matches(['-', '+', ' ', '#', '0']);
+    let digits = |s: &str| s.len() - s.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let width = digits(rest);
+    let mut rest = &rest[width..];
+    if let Some(after) = rest.strip_prefix('.') {
+        let precision = digits(after);
+        if precision > 2 {
+            return None;
+        }
+        rest = &after[precision..];
+    }
+    let mut chars = rest.chars();
+    match (chars.next(), chars.next()) {
+        (Some(conversion), None) if width <= 2 && "diucoxXeEfgGaAqs".contains(conversion) => {
+            Some(conversion)
+        }
+        _ => None,
+    }
+}
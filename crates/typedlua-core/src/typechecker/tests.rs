@@ -0,0 +1,2944 @@
+use super::*;
+use crate::config::LuaVersion;
+use crate::diagnostics::{CollectingDiagnosticHandler, DiagnosticLevel};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+fn parse(source: &str, handler: &Arc<CollectingDiagnosticHandler>) -> Program {
+    let mut lexer = Lexer::new(source, handler.clone());
+    let tokens = lexer.tokenize().expect("Lexing failed");
+    let mut parser = Parser::new(tokens, handler.clone());
+    let program = parser.parse().expect("Parse failed");
+    assert!(
+        !handler.has_errors(),
+        "Unexpected parse errors: {:?}",
+        handler.get_diagnostics()
+    );
+    program
+}
+
+fn check_source(source: &str) -> Arc<CollectingDiagnosticHandler> {
+    check_with_declarations("", source, LuaVersion::Lua54)
+}
+
+fn check_with_declarations(
+    declarations: &str,
+    source: &str,
+    target: LuaVersion,
+) -> Arc<CollectingDiagnosticHandler> {
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let declarations = parse(declarations, &handler);
+    let program = parse(source, &handler);
+
+    let mut config = CompilerConfig::default();
+    config.compiler_options.target = target;
+
+    let mut checker = TypeChecker::new(Arc::new(config), handler.clone());
+    checker.load_declarations(&declarations);
+    let _ = checker.check_program(&program);
+    handler
+}
+
+fn error_messages(handler: &CollectingDiagnosticHandler) -> Vec<String> {
+    handler
+        .get_diagnostics()
+        .into_iter()
+        .filter(|d| d.level == DiagnosticLevel::Error)
+        .map(|d| d.message)
+        .collect()
+}
+
+fn warning_messages(handler: &CollectingDiagnosticHandler) -> Vec<String> {
+    handler
+        .get_diagnostics()
+        .into_iter()
+        .filter(|d| d.level == DiagnosticLevel::Warning)
+        .map(|d| d.message)
+        .collect()
+}
+
+const SHAPE: &str = r#"
+    abstract class Shape {
+        color: string
+
+        constructor(color: string) {
+            self.color = color
+        }
+
+        abstract area(): number
+        abstract perimeter(): number
+
+        describe(): void {
+            print(self.color)
+        }
+    }
+"#;
+
+#[test]
+fn test_concrete_subclass_implementing_all_abstract_methods() {
+    let source = format!(
+        "{}{}",
+        SHAPE,
+        r#"
+        class Circle extends Shape {
+            radius: number
+
+            area(): number {
+                return 3.14159 * self.radius * self.radius
+            }
+
+            perimeter(): number {
+                return 2 * 3.14159 * self.radius
+            }
+        }
+
+        const circle = Circle.new("blue", 5)
+    "#
+    );
+    let handler = check_source(&source);
+    assert_eq!(error_messages(&handler), Vec::<String>::new());
+}
+
+#[test]
+fn test_abstract_class_cannot_be_instantiated() {
+    let source = format!("{}{}", SHAPE, "const shape = Shape.new(\"red\")");
+    let handler = check_source(&source);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["Cannot instantiate abstract class 'Shape'".to_string()]
+    );
+}
+
+#[test]
+fn test_missing_abstract_override_is_reported_per_method() {
+    let source = format!(
+        "{}{}",
+        SHAPE,
+        r#"
+        class Square extends Shape {
+            side: number
+
+            area(): number {
+                return self.side * self.side
+            }
+        }
+    "#
+    );
+    let handler = check_source(&source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "Non-abstract class 'Square' does not implement inherited abstract method 'perimeter' from 'Shape'"
+                .to_string()
+        ]
+    );
+
+    // A note points at the abstract declaration that still needs an override
+    let notes: Vec<_> = handler
+        .get_diagnostics()
+        .into_iter()
+        .filter(|d| d.level == DiagnosticLevel::Info)
+        .collect();
+    assert_eq!(notes.len(), 1);
+    assert!(notes[0].message.contains("perimeter"));
+}
+
+#[test]
+fn test_intermediate_abstract_class_may_defer_implementation() {
+    let source = format!(
+        "{}{}",
+        SHAPE,
+        r#"
+        abstract class Polygon extends Shape {
+            area(): number {
+                return 0
+            }
+        }
+
+        class Triangle extends Polygon {
+            perimeter(): number {
+                return 3
+            }
+        }
+    "#
+    );
+    let handler = check_source(&source);
+    assert_eq!(error_messages(&handler), Vec::<String>::new());
+}
+
+#[test]
+fn test_abstract_method_rules() {
+    let source = r#"
+        class Widget {
+            constructor(size: number)
+            abstract render(): void
+
+            update(): void
+        }
+
+        abstract class Base {
+            abstract draw(): void {
+                print("nope")
+            }
+        }
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "Abstract method 'render' can only appear in an abstract class, but 'Widget' is not abstract"
+                .to_string(),
+            "Method 'update' is missing an implementation".to_string(),
+            "Method 'constructor' is missing an implementation".to_string(),
+            "Abstract method 'draw' cannot have an implementation".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_check_program_returns_error_count() {
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let source = "abstract class A {}\nconst a = A.new()\nconst b = A.new()";
+    let mut lexer = Lexer::new(source, handler.clone());
+    let tokens = lexer.tokenize().unwrap();
+    let program = Parser::new(tokens, handler.clone()).parse().unwrap();
+
+    let mut checker = TypeChecker::new(Arc::new(CompilerConfig::default()), handler);
+    assert!(matches!(
+        checker.check_program(&program),
+        Err(CompilationError::TypeErrors(2))
+    ));
+}
+
+const STDLIB: &str = r#"
+    interface StringLib {
+        format(fmt: string, ...args: unknown[]): string
+        @target(lua53, lua54)
+        pack(fmt: string, ...values: unknown[]): string
+        @target(luau)
+        split(s: string, sep: string): string[]
+    }
+
+    declare const string: StringLib
+    declare function print(...values: unknown[]): void
+
+    @target(lua51, luajit)
+    declare function setfenv(f: unknown, env: table): unknown
+
+    @target("5.4")
+    declare function warn(message: string): void
+"#;
+
+#[test]
+fn test_target_guarded_members_visible_on_matching_target() {
+    let source = r#"
+        const packed = string.pack("i4", 1)
+        const text = string.format("%d", 1)
+        warn("careful")
+    "#;
+    let handler = check_with_declarations(STDLIB, source, LuaVersion::Lua54);
+    assert_eq!(error_messages(&handler), Vec::<String>::new());
+}
+
+#[test]
+fn test_target_guarded_members_hidden_on_other_targets() {
+    let source = r#"
+        const packed = string.pack("i4", 1)
+        const parts = string.split("a,b", ",")
+        setfenv(print, {})
+    "#;
+    let handler = check_with_declarations(STDLIB, source, LuaVersion::Lua51);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "'StringLib.pack' is not available on target Lua 5.1; it requires Lua 5.3 or Lua 5.4"
+                .to_string(),
+            "'StringLib.split' is not available on target Lua 5.1; it requires Luau".to_string(),
+        ]
+    );
+
+    let handler = check_with_declarations(STDLIB, "warn(\"x\")", LuaVersion::LuaJIT);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["'warn' is not available on target LuaJIT; it requires Lua 5.4".to_string()]
+    );
+}
+
+#[test]
+fn test_local_binding_shadows_unavailable_global() {
+    let source = r#"
+        local warn = function(message: string): void end
+        warn("shadowed")
+    "#;
+    let handler = check_with_declarations(STDLIB, source, LuaVersion::Lua51);
+    assert_eq!(error_messages(&handler), Vec::<String>::new());
+}
+
+#[test]
+fn test_unknown_member_on_declared_interface() {
+    let handler = check_with_declarations(STDLIB, "string.reverse(\"abc\")", LuaVersion::Lua54);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["Property 'reverse' does not exist on type 'StringLib'".to_string()]
+    );
+}
+
+#[test]
+fn test_unknown_target_name_warns() {
+    let declarations = "@target(lua99)\ndeclare function future(): void";
+    let handler = check_with_declarations(declarations, "", LuaVersion::Lua54);
+    assert_eq!(
+        warning_messages(&handler),
+        vec!["Unknown target 'lua99' in @target guard".to_string()]
+    );
+}
+
+const HEADERS: &str = r#"
+    interface Headers {
+        [key: string]: string
+    }
+"#;
+
+#[test]
+fn test_index_signature_types_field_access() {
+    let source = format!(
+        "{}{}",
+        HEADERS,
+        r#"
+        const headers: Headers = { accept = "json", host = "example.com" }
+        const accept: string = headers.accept
+        const host: string = headers["host"]
+        const broken: number = headers.anything
+        headers.retries = 3
+    "#
+    );
+    let handler = check_source(&source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "Type mismatch: expected number, found string".to_string(),
+            "Type mismatch: expected string, found integer".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_object_literal_checked_against_index_signature() {
+    let source = format!(
+        "{}{}",
+        HEADERS, "const headers: Headers = { accept = \"json\", retries = 3 }"
+    );
+    let handler = check_source(&source);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["Type mismatch: expected string, found number".to_string()]
+    );
+}
+
+#[test]
+fn test_declared_fields_must_fit_string_index() {
+    let source = r#"
+        interface Config {
+            [key: string]: string
+            name: string
+            port: number
+            [index: number]: boolean
+        }
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "Property 'port' of type 'number' is not assignable to string index type 'string'"
+                .to_string(),
+            "Number index type 'boolean' is not assignable to string index type 'string'"
+                .to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_declared_fields_take_precedence_over_index() {
+    let source = r#"
+        interface Scores {
+            [name: string]: number | string
+            total: number
+        }
+
+        const scores: Scores = { total = 10, alice = "n/a" }
+        const total: number = scores.total
+        const alice: number = scores.alice
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["Type mismatch: expected number, found number | string".to_string()]
+    );
+}
+
+#[test]
+fn test_pairs_iteration_uses_index_signature() {
+    let source = format!(
+        "{}{}",
+        HEADERS,
+        r#"
+        interface Lines {
+            [line: number]: string
+        }
+
+        const headers: Headers = {}
+        const lines: Lines = {}
+
+        for name, value in pairs(headers) do
+            const n: string = name
+            const v: string = value
+            const wrong: number = name
+        end
+
+        for i, line in ipairs(lines) do
+            const index: number = i
+            const text: string = line
+        end
+    "#
+    );
+    let handler = check_source(&source);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["Type mismatch: expected number, found string".to_string()]
+    );
+}
+
+#[test]
+fn test_export_equals_allows_only_type_exports_alongside() {
+    let source = r#"
+        export interface Options {
+            level: string
+        }
+
+        function createLogger(options: Options)
+            return options
+        end
+
+        export = createLogger
+    "#;
+    let handler = check_source(source);
+    assert_eq!(error_messages(&handler), Vec::<String>::new());
+}
+
+#[test]
+fn test_export_equals_conflicts_with_value_exports() {
+    let source = r#"
+        export const level = "info"
+        export = level
+        export default level
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "A module using 'export =' cannot have other value exports".to_string(),
+            "A module using 'export =' cannot have other value exports".to_string(),
+        ]
+    );
+}
+
+const HOST_CONFIG: &str = r#"
+    interface Config {
+        name: string
+    }
+
+    declare const config: Config
+"#;
+
+#[test]
+fn test_interface_declarations_merge_across_files() {
+    let source = r#"
+        interface Config {
+            verbose: boolean
+        }
+
+        const name: string = config.name
+        const verbose: boolean = config.verbose
+        const broken: number = config.verbose
+    "#;
+    let handler = check_with_declarations(HOST_CONFIG, source, LuaVersion::Lua54);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["Type mismatch: expected number, found boolean".to_string()]
+    );
+}
+
+#[test]
+fn test_merged_interface_extends_are_combined() {
+    let source = r#"
+        interface Named { name: string }
+        interface Aged { age: number }
+        interface Person extends Named { }
+        interface Person extends Aged { }
+
+        const person: Person = { name = "Ada", age = 36 }
+        const age: number = person.age
+        const name: string = person.name
+        const wrong: string = person.age
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["Type mismatch: expected string, found number".to_string()]
+    );
+}
+
+#[test]
+fn test_merged_interface_conflicts() {
+    let source = r#"
+        interface Box<T> {
+            value: number
+        }
+
+        interface Box<U> {
+            value: string
+            label: string
+        }
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "All declarations of 'Box' must have identical type parameters".to_string(),
+            "Subsequent declarations of property 'value' must have the same type: expected 'number', found 'string'"
+                .to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_optional_member_access_is_nullable() {
+    let source = r#"
+        interface Address {
+            city: string
+        }
+
+        interface User {
+            address?: Address
+        }
+
+        const user: User = {}
+        const city: string? = user.address?.city
+        const strict: string = user.address?.city
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["Type mismatch: expected string, found string?".to_string()]
+    );
+}
+
+const GEOMETRY: &str = r#"
+    namespace Geometry {
+        export interface Point {
+            x: number
+            y: number
+        }
+
+        function square(n: number): number
+            return n * n
+        end
+
+        export function distance(a: Geometry.Point, b: Geometry.Point): number
+            return square(a.x - b.x) + square(a.y - b.y)
+        end
+
+        export const unit: number = 1
+    }
+"#;
+
+#[test]
+fn test_namespace_exports_are_members() {
+    let source = format!(
+        "{}{}",
+        GEOMETRY,
+        r#"
+        const p: Geometry.Point = { x = 1, y = 2 }
+        const d: number = Geometry.distance(p, p)
+        const u: string = Geometry.unit
+        Geometry.square(2)
+    "#
+    );
+    let handler = check_source(&source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "Type mismatch: expected string, found number".to_string(),
+            "Property 'square' does not exist on type 'Geometry'".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_namespaces_merge_and_nest() {
+    let source = r#"
+        namespace App {
+            export const name: string = "demo"
+        }
+
+        namespace App {
+            export namespace Version {
+                export const major: number = 1
+            }
+            export = 1
+        }
+
+        const name: string = App.name
+        const major: string = App.Version.major
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "'export =' is only allowed at the top level of a module".to_string(),
+            "Type mismatch: expected string, found number".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_value_import_used_only_as_type_warns() {
+    let source = r#"
+        import { Point, distance } from "./geometry"
+        import Shapes from "./shapes"
+        const origin: Point = { x = 0, y = 0 }
+        const d = distance(origin, origin)
+        const s: Shapes.Circle? = nil
+    "#;
+    let handler = check_source(source);
+    assert!(error_messages(&handler).is_empty());
+    assert_eq!(
+        warning_messages(&handler),
+        vec![
+            "'Point' is only used as a type; use 'import type' so the module is not loaded at runtime"
+                .to_string(),
+            "'Shapes' is only used as a type; use 'import type' so the module is not loaded at runtime"
+                .to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_type_only_import_cannot_be_used_as_value() {
+    let source = r#"
+        import type { Point } from "./geometry"
+        const origin: Point = { x = 0, y = 0 }
+        const copy = Point
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "'Point' cannot be used as a value because it was imported using 'import type'"
+                .to_string()
+        ]
+    );
+    assert!(warning_messages(&handler).is_empty());
+}
+
+const VEC2: &str = r#"
+    class Vec2 {
+        x: number
+        y: number
+
+        constructor(x: number, y: number) {
+            self.x = x
+            self.y = y
+        }
+
+        __add(self, other: Vec2): Vec2 {
+            return Vec2.new(self.x + other.x, self.y + other.y)
+        }
+
+        __mul(scale: number): Vec2 {
+            return Vec2.new(self.x * scale, self.y * scale)
+        }
+
+        __lt(other: Vec2): boolean {
+            return self.x < other.x
+        }
+    }
+"#;
+
+#[test]
+fn test_operators_use_metamethod_signatures() {
+    let source = format!(
+        r#"{}
+        const a = Vec2.new(1, 2)
+        const b: Vec2 = a + a
+        const c: Vec2 = a * 2
+        const smaller: boolean = a > b
+        const same = a == b
+        "#,
+        VEC2
+    );
+    let handler = check_source(&source);
+    assert!(error_messages(&handler).is_empty());
+}
+
+#[test]
+fn test_operator_operand_must_match_metamethod() {
+    let source = format!(
+        "{}\nconst a = Vec2.new(1, 2)\nconst b = a * a\nconst c: number = a + a",
+        VEC2
+    );
+    let handler = check_source(&source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "Type mismatch: expected number, found Vec2".to_string(),
+            "Type mismatch: expected number, found Vec2".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_operator_without_metamethod_is_rejected() {
+    let source = format!(
+        "{}\nconst a = Vec2.new(1, 2)\nconst b = a - a\nconst c = -a\nconst n = #a",
+        VEC2
+    );
+    let handler = check_source(&source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "Operator '-' cannot be applied to types 'Vec2' and 'Vec2'".to_string(),
+            "Operator '-' cannot be applied to type 'Vec2'".to_string(),
+        ]
+    );
+}
+
+const MIXINS: &str = r#"
+    class Entity {
+        id: number = 0
+        describe(): string {
+            return "entity"
+        }
+    }
+
+    class Serializable {
+        serialize(): string {
+            return "{}"
+        }
+    }
+
+    class Printable {
+        describe(verbose: boolean): string {
+            return "printable"
+        }
+    }
+
+    abstract class Shape {
+        abstract serialize(): string
+    }
+"#;
+
+#[test]
+fn test_mixins_satisfy_abstract_members() {
+    let source = format!(
+        "{}\nclass Square extends mixin(Shape, Serializable) {{\n}}\nclass Blob extends mixin(Shape, Entity) {{\n}}",
+        MIXINS
+    );
+    let handler = check_source(&source);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["Non-abstract class 'Blob' does not implement inherited abstract method 'serialize' from 'Shape'".to_string()]
+    );
+}
+
+#[test]
+fn test_conflicting_mixin_members_are_reported() {
+    let source = format!(
+        "{}\nclass Bad extends mixin(Entity, Printable) {{\n}}\nclass Fixed extends mixin(Entity, Printable) {{\n    describe(): string {{\n        return \"fixed\"\n    }}\n}}",
+        MIXINS
+    );
+    let handler = check_source(&source);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["Mixins 'Entity' and 'Printable' both provide 'describe' with different types; declare it in the class to resolve the conflict".to_string()]
+    );
+}
+
+#[test]
+fn test_mixin_must_be_a_class() {
+    let source = "interface Named {\n    name: string\n}\nclass Tag extends mixin(Named) {\n}";
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["'Named' is not a class and cannot be used as a mixin".to_string()]
+    );
+}
+
+const OVERRIDES: &str = r#"
+    interface Greeter {
+        greet(name: string): string
+    }
+
+    class Animal {
+        speak(times: number): string {
+            return "..."
+        }
+        move(): void {
+        }
+    }
+"#;
+
+#[test]
+fn test_override_signatures_are_checked() {
+    let source = format!(
+        "{}{}",
+        OVERRIDES,
+        r#"
+    class Dog extends Animal implements Greeter {
+        override speak(times: number, loud?: boolean): string {
+            return "woof"
+        }
+        greet(name: number): string {
+            return "hi"
+        }
+        override move(speed: number): void {
+        }
+        override fetch(): void {
+        }
+    }
+"#
+    );
+    let handler = check_source(&source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "Method 'greet' is not compatible with the method it overrides in 'Greeter': expected (name: string) -> string, found (name: number) -> string".to_string(),
+            "Method 'move' is not compatible with the method it overrides in 'Animal': expected () -> void, found (speed: number) -> void".to_string(),
+            "Method 'fetch' is marked 'override' but does not override a base class or interface method".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_no_implicit_override_requires_the_keyword() {
+    let source = format!(
+        "{}{}",
+        OVERRIDES,
+        r#"
+    class Cat extends Animal implements Greeter {
+        speak(times: number): string {
+            return "meow"
+        }
+        override greet(name: string): string {
+            return "hi " .. name
+        }
+        purr(): void {
+        }
+    }
+"#
+    );
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let program = parse(&source, &handler);
+    let mut config = CompilerConfig::default();
+    config.compiler_options.no_implicit_override = true;
+    let mut checker = TypeChecker::new(Arc::new(config), handler.clone());
+    let _ = checker.check_program(&program);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "Method 'speak' overrides a method of 'Animal' and must be marked 'override'"
+                .to_string()
+        ]
+    );
+    assert!(error_messages(&check_source(&source)).is_empty());
+}
+
+#[test]
+fn test_static_members_and_class_constants() {
+    let source = r#"
+    class Board {
+        const SIZE = 8
+        static boards: number = 0
+        cells: number = 0
+
+        static do
+            Board.boards = Board.SIZE
+        end
+
+        fill(): void {
+            self.cells = Board.SIZE * Board.SIZE
+        }
+    }
+
+    local size: Board.SIZE = Board.SIZE
+    local board = Board.new()
+    board.fill()
+    local wrong: Board.SIZE = 9
+    print(board.boards, Board.cells)
+    Board.SIZE = 9
+"#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "Type mismatch: expected 8, found integer".to_string(),
+            "'boards' is a static member of 'Board'; access it as 'Board.boards'".to_string(),
+            "'cells' is an instance member of 'Board' and cannot be accessed through the class"
+                .to_string(),
+            "Cannot assign to 'SIZE' because it is a read-only property".to_string(),
+            "Type mismatch: expected 8, found integer".to_string(),
+        ]
+    );
+}
+
+const PLATFORM_APIS: &str = r#"
+    interface OsLib {
+        time(): number
+        @unavailableOn(sandbox)
+        execute(command: string): number
+    }
+
+    interface IoLib {
+        write(text: string): void
+    }
+
+    declare const os: OsLib
+
+    @unavailableOn(roblox, "sandbox")
+    declare const io: IoLib
+"#;
+
+fn check_on_platform(source: &str, platform: Option<&str>) -> Arc<CollectingDiagnosticHandler> {
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let declarations = parse(PLATFORM_APIS, &handler);
+    let program = parse(source, &handler);
+
+    let mut config = CompilerConfig::default();
+    config.compiler_options.platform = platform.map(str::to_string);
+
+    let mut checker = TypeChecker::new(Arc::new(config), handler.clone());
+    checker.load_declarations(&declarations);
+    let _ = checker.check_program(&program);
+    handler
+}
+
+#[test]
+fn test_platform_unavailable_apis_are_rejected() {
+    let source = "io.write(\"hi\")\nconst status = os.execute(\"ls\")\nconst now = os.time()";
+
+    assert!(error_messages(&check_on_platform(source, None)).is_empty());
+    assert_eq!(
+        error_messages(&check_on_platform(source, Some("roblox"))),
+        vec!["'io' is not available on platform 'roblox'".to_string()]
+    );
+    assert_eq!(
+        error_messages(&check_on_platform(source, Some("sandbox"))),
+        vec![
+            "'io' is not available on platform 'sandbox'".to_string(),
+            "'OsLib.execute' is not available on platform 'sandbox'".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_stdlib_hides_process_and_code_loading_apis_on_roblox() {
+    let source = r#"
+        const status = os.execute("ls")
+        const chunk = load("return 1")
+        const file = loadfile("main.lua")
+        const result = dofile("main.lua")
+        const now = os.time()
+        os.exit(1)
+    "#;
+    let check = |platform: Option<&str>| {
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let program = parse(source, &handler);
+        let mut config = CompilerConfig::default();
+        config.compiler_options.platform = platform.map(str::to_string);
+        let _ = TypeChecker::new(Arc::new(config), handler.clone()).check_program(&program);
+        error_messages(&handler)
+    };
+
+    assert!(check(None).is_empty());
+    assert_eq!(
+        check(Some("roblox")),
+        vec![
+            "'OsLibrary.execute' is not available on platform 'roblox'".to_string(),
+            "'load' is not available on platform 'roblox'".to_string(),
+            "'loadfile' is not available on platform 'roblox'".to_string(),
+            "'dofile' is not available on platform 'roblox'".to_string(),
+            "'OsLibrary.exit' is not available on platform 'roblox'".to_string(),
+        ]
+    );
+}
+
+const READONLY_CONFIG: &str = r#"
+    interface Window {
+        width: number
+    }
+
+    interface Config {
+        readonly name: string
+        window: Window
+        [key: string]: unknown
+    }
+"#;
+
+#[test]
+fn test_readonly_parameters_reject_deep_mutation() {
+    let source = r#"
+        function apply(config: Readonly<Config>): number
+            config.window.width = 10
+            config["debug"] = true
+            return config.window.width
+        end
+    "#;
+    let handler = check_with_declarations(READONLY_CONFIG, source, LuaVersion::Lua54);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "Cannot assign to 'width' because it is a read-only property".to_string(),
+            "Index signature of read-only type 'Readonly<Config>' only permits reading".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_readonly_interface_property_assignment() {
+    let source = r#"
+        function rename(config: Config)
+            config.name = "other"
+            config.window.width = 10
+        end
+    "#;
+    let handler = check_with_declarations(READONLY_CONFIG, source, LuaVersion::Lua54);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["Cannot assign to 'name' because it is a read-only property".to_string()]
+    );
+}
+
+#[test]
+fn test_readonly_values_do_not_flow_into_mutable_types() {
+    let source = r#"
+        function run(config: Config, view: Readonly<Config>, t: table)
+            const a: Readonly<Config> = config
+            const b: Readonly<Config> = view
+            const c: ReadonlyTable = t
+            const d: Config = view
+            const e: table = c
+        end
+    "#;
+    let handler = check_with_declarations(READONLY_CONFIG, source, LuaVersion::Lua54);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "Type mismatch: expected Config, found Readonly<Config>".to_string(),
+            "Type mismatch: expected table, found ReadonlyTable".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_methods_returning_self_keep_the_subclass_type() {
+    let source = r#"
+        class Builder {
+            name: string
+
+            named(name: string): Self {
+                self.name = name
+                return self
+            }
+        }
+
+        class HtmlBuilder extends Builder {
+            render(): string {
+                return self.name
+            }
+        }
+
+        const builder = HtmlBuilder.new()
+        const html: string = builder::named("page")::render()
+        const count: number = builder::named("page")
+        const label: number = builder.name
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "Type mismatch: expected number, found HtmlBuilder".to_string(),
+            "Type mismatch: expected number, found string".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_explicit_self_parameter_checks_colon_calls() {
+    let source = r#"
+        class Dog {
+            name: string
+            trick: (self: Dog) -> string
+        }
+
+        class Puppy extends Dog {}
+
+        class Cat {
+            speak: (self: Dog) -> string
+        }
+
+        function bark(self: Dog): string
+            return self.name
+        end
+
+        const puppy = Puppy.new()
+        const cat = Cat.new()
+        puppy::trick()
+        cat::speak()
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "The 'self' of type 'Cat' is not assignable to the method's 'self' of type 'Dog'"
+                .to_string()
+        ]
+    );
+}
+
+#[test]
+fn test_explicit_self_parameter_checks_interface_receivers() {
+    let source = r#"
+        interface Vec {
+            x: number
+            y: number
+        }
+
+        type Flat = { x: number, y: number }
+
+        interface Describer {
+            describe: (self: Vec) -> string
+        }
+
+        interface Point {
+            x: number
+            y: number
+            describe: (self: Vec) -> string
+            flatten: (self: Flat) -> string
+        }
+
+        interface Label {
+            x: string
+            y: number
+            flatten: (self: Flat) -> string
+        }
+
+        function show(d: Describer, p: Point, l: Label): void
+            d::describe()
+            p::describe()
+            p::flatten()
+            l::flatten()
+        end
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "The 'self' of type 'Describer' is not assignable to the method's 'self' of type 'Vec'"
+                .to_string(),
+            "The 'self' of type 'Label' is not assignable to the method's 'self' of type 'Flat'"
+                .to_string(),
+        ]
+    );
+}
+
+fn check_strict_initialization(source: &str) -> Arc<CollectingDiagnosticHandler> {
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let program = parse(source, &handler);
+
+    let mut config = CompilerConfig::default();
+    config.compiler_options.strict_initialization = true;
+
+    let mut checker = TypeChecker::new(Arc::new(config), handler.clone());
+    let _ = checker.check_program(&program);
+    handler
+}
+
+#[test]
+fn test_strict_initialization_requires_every_property() {
+    let source = r#"
+        interface ServerConfig {
+            host: string
+            port: number
+            timeout?: number
+        }
+
+        const full: ServerConfig = { host = "localhost", port = 8080 }
+        const half: ServerConfig = { host = "localhost" }
+        const draft: Partial<ServerConfig> = { host = "localhost" }
+        const merged: ServerConfig = { ...draft }
+        const port: number = draft.port
+        const copy: ServerConfig = draft
+    "#;
+
+    assert!(error_messages(&check_source(source))
+        .iter()
+        .all(|m| !m.contains("missing")));
+    assert_eq!(
+        error_messages(&check_strict_initialization(source)),
+        vec![
+            "Object literal is missing required properties of 'ServerConfig': port".to_string(),
+            "Type mismatch: expected number, found number?".to_string(),
+            "Type mismatch: expected ServerConfig, found Partial<ServerConfig>".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_pipeline_threads_types_through_each_stage() {
+    let source = r#"
+        interface Order {
+            id: number
+        }
+
+        function parse(text: string): Order
+            return { id = 1 }
+        end
+
+        function validate(order: Order): Order
+            return order
+        end
+
+        function save(order: Order, retries: number): boolean
+            return true
+        end
+
+        function describe(count: number): string
+            return "n"
+        end
+
+        const saved: boolean = "{}" |> parse |> validate |> save(3)
+        const broken = "{}" |> parse |> describe |> validate
+        const label: number = "{}" |> parse |> validate
+        const oops = 1 |> 2
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "Pipeline stage 'describe' expects 'number', but the previous stage produces 'Order'"
+                .to_string(),
+            "Pipeline stage 'validate' expects 'Order', but the previous stage produces 'string'"
+                .to_string(),
+            "Type mismatch: expected number, found Order".to_string(),
+            "Pipeline stage 'expression' is not a function; it has type 'integer'".to_string(),
+        ]
+    );
+}
+
+const LOOKUPS: &str = r#"
+    function lookup(key: string): string?
+        return nil
+    end
+
+    function ready(): boolean
+        return true
+    end
+"#;
+
+#[test]
+fn test_nil_checks_narrow_branches_and_early_exits() {
+    let source = format!(
+        "{}{}",
+        LOOKUPS,
+        r#"
+        local name: string? = lookup("name")
+        if name ~= nil then
+            const inside: string = name
+        end
+        const before: string = name
+
+        if name == nil then
+            return
+        end
+        const after: string = name
+        const both: string = name ~= nil and name or "none"
+    "#
+    );
+    let handler = check_source(&source);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["Type mismatch: expected string, found string?".to_string()]
+    );
+}
+
+#[test]
+fn test_narrowing_survives_loops_that_do_not_reassign() {
+    let source = format!(
+        "{}{}",
+        LOOKUPS,
+        r#"
+        local limit: string? = lookup("limit")
+        if limit == nil then
+            error("no limit")
+        end
+
+        for i = 1, 10 do
+            const kept: string = limit
+        end
+        while ready() do
+            const still: string = limit
+            limit = "fixed"
+        end
+        while ready() do
+            const lost: string = limit
+            limit = lookup("limit")
+        end
+    "#
+    );
+    let handler = check_source(&source);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["Type mismatch: expected string, found string?".to_string()]
+    );
+}
+
+#[test]
+fn test_narrowed_variables_are_assigned_their_declared_type() {
+    let source = format!(
+        "{}{}",
+        LOOKUPS,
+        r#"
+        interface Node {
+            value: number
+            next: Node?
+        }
+        function last(head: Node?): Node?
+            local m = head
+            while m ~= nil do
+                if m.next == nil then
+                    return m
+                end
+                m = m.next
+            end
+            return nil
+        end
+
+        local x: string? = lookup("x")
+        if x ~= nil then
+            x = nil
+            const cleared: string = x
+        end
+    "#
+    );
+    let handler = check_source(&source);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["Type mismatch: expected string, found string?".to_string()]
+    );
+}
+
+#[test]
+fn test_loops_exiting_through_break_or_until_narrow_after_the_loop() {
+    let source = format!(
+        "{}{}",
+        LOOKUPS,
+        r#"
+        local found: string? = nil
+        while true do
+            found = lookup("key")
+            if found ~= nil then
+                break
+            end
+        end
+        const first: string = found
+
+        local line: string? = nil
+        repeat
+            line = lookup("line")
+        until line ~= nil
+        const second: string = line
+
+        local maybe: string? = nil
+        while ready() do
+            maybe = lookup("key")
+            if maybe ~= nil then
+                break
+            end
+        end
+        const third: string = maybe
+    "#
+    );
+    let handler = check_source(&source);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["Type mismatch: expected string, found string?".to_string()]
+    );
+}
+
+fn check_strict_nil(source: &str) -> Arc<CollectingDiagnosticHandler> {
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let program = parse(source, &handler);
+
+    let mut config = CompilerConfig::default();
+    config.compiler_options.strict_null_checks = false;
+    config.compiler_options.strict_nil = true;
+
+    let mut checker = TypeChecker::new(Arc::new(config), handler.clone());
+    let _ = checker.check_program(&program);
+    handler
+}
+
+#[test]
+fn test_strict_nil_rejects_nil_and_unchecked_dereferences() {
+    let source = r#"
+        interface User {
+            name: string
+        }
+
+        function find(id: number): User?
+            return nil
+        end
+
+        const missing: number = nil
+        const optional: number | nil = nil
+
+        local user = find(1)
+        const name = user.name
+        if user ~= nil then
+            const checked = user.name
+        end
+        type Handler = () -> void
+        const callback: Handler? = nil
+        callback()
+    "#;
+
+    assert!(error_messages(&check_source(source))
+        .iter()
+        .all(|m| !m.contains("possibly nil")));
+    assert_eq!(
+        error_messages(&check_strict_nil(source)),
+        vec![
+            "Type mismatch: expected number, found nil".to_string(),
+            "'user' is possibly nil".to_string(),
+            "'callback' is possibly nil".to_string(),
+        ]
+    );
+}
+
+const OPTIONALS: &str = r#"
+    function maybeNumber(): number?
+        return nil
+    end
+    function maybeString(): string?
+        return nil
+    end
+    function maybeList(): number[]?
+        return nil
+    end
+    const y = maybeNumber()
+    const s = maybeString()
+    const t = maybeList()
+"#;
+
+#[test]
+fn test_strict_nil_rejects_nil_operands() {
+    let source = format!(
+        "{}{}",
+        OPTIONALS,
+        r#"
+        const sum = y + 1
+        const size = #s
+        const joined = s .. "x"
+        const less = y < 2
+        const same = y == nil
+        const fallback = y or 0
+        const negated = not y
+        if y ~= nil and s ~= nil then
+            const checked = y + #s
+        end
+    "#
+    );
+
+    assert!(error_messages(&check_source(&source)).is_empty());
+    assert_eq!(
+        error_messages(&check_strict_nil(&source)),
+        vec![
+            "'y' is possibly nil".to_string(),
+            "'s' is possibly nil".to_string(),
+            "'s' is possibly nil".to_string(),
+            "'y' is possibly nil".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_strict_nil_rejects_nil_arguments() {
+    let source = format!(
+        "{}{}",
+        OPTIONALS,
+        r#"
+        function h(value: number): number
+            return value
+        end
+        function g(value: number?): void
+        end
+        for i, v in ipairs(t) do
+        end
+        h(y)
+        h(nil)
+        g(y)
+        g(nil)
+        if y ~= nil then
+            h(y)
+        end
+    "#
+    );
+
+    assert!(error_messages(&check_source(&source)).is_empty());
+    assert_eq!(
+        error_messages(&check_strict_nil(&source)),
+        vec![
+            "'t' is possibly nil".to_string(),
+            "Type mismatch: expected number, found number?".to_string(),
+            "Type mismatch: expected number, found nil".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_strict_nil_rejects_nil_returns() {
+    let source = format!(
+        "{}{}",
+        OPTIONALS,
+        r#"
+        function unchecked(): number
+            return y
+        end
+        function checked(): number
+            if y == nil then
+                return 0
+            end
+            return y
+        end
+        function optional(): number?
+            return y
+        end
+        function pair(): (string, number)
+            return "y", y
+        end
+        const f = (): number => {
+            return y
+        }
+    "#
+    );
+
+    assert!(error_messages(&check_source(&source)).is_empty());
+    assert_eq!(
+        error_messages(&check_strict_nil(&source)),
+        vec![
+            "Type mismatch: expected number, found number?".to_string(),
+            "Type mismatch: expected number, found number?".to_string(),
+            "Type mismatch: expected number, found number?".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_assertions_narrow_their_arguments() {
+    let source = format!(
+        "{}{}",
+        LOOKUPS,
+        r#"
+        interface Point {
+            x: number
+        }
+
+        function isPoint(value: unknown): asserts value is Point
+            if value == nil then
+                error("not a point")
+            end
+        end
+
+        function present(value: unknown): asserts value
+        end
+
+        function broken(value: unknown): asserts other
+        end
+
+        local name: string? = lookup("name")
+        const unchecked: string = name
+        assert(name, "name is required")
+        const checked: string = name
+
+        local point: Point? = nil
+        isPoint(point)
+        const p: Point = point
+
+        local label: string? = lookup("label")
+        present(label)
+        const l: string = label
+        const nothing: string = present(label)
+
+        local key: string? = lookup("key")
+        if key == nil then
+            assert(false, "missing key")
+        end
+        const k: string = key
+    "#
+    );
+    let handler = check_source(&source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "Cannot find parameter 'other' named by the assertion signature".to_string(),
+            "Type mismatch: expected string, found string?".to_string(),
+            "Type mismatch: expected string, found void".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_integer_and_float_are_distinct_on_lua53_and_later() {
+    let source = r#"
+        function ratio(a: number, b: number): number
+            return a / b
+        end
+
+        const count: integer = 10
+        const half: integer = count / 2
+        const sum: integer = count + 1
+        const mixed: integer = count + 0.5
+        const widened: number = count
+        const scaled: integer = ratio(1, 2)
+
+        const mask: integer = count & 0xFF
+        const shifted = ratio(1, 2) << 1
+        const inverted = ~1.5
+        const exact = 2.0 | 1
+        const remainder = count % 0
+        const float = ratio(1, 2) % 0
+        const nested: Map<string, Array<integer>>? = nil
+
+        for i = 1, count do
+            const index: integer = i
+        end
+        for x = 0, 1, 0.25 do
+            const step: integer = x
+        end
+    "#;
+    assert_eq!(
+        error_messages(&check_source(source)),
+        vec![
+            "Type mismatch: expected integer, found number".to_string(),
+            "Type mismatch: expected integer, found number".to_string(),
+            "Type mismatch: expected integer, found number".to_string(),
+            "Operand of '<<' has type 'number', which may have no integer representation at runtime"
+                .to_string(),
+            "Operand of '~' has type 'number', which may have no integer representation at runtime"
+                .to_string(),
+            "Integer '%' by zero raises an error at runtime".to_string(),
+            "Type mismatch: expected integer, found number".to_string(),
+        ]
+    );
+
+    // Older targets have a single number type, and bitwise operators only
+    // through a bit library
+    let handler = check_with_declarations("", source, LuaVersion::Lua51);
+    let unavailable = |operator: &str| {
+        format!(
+            "'{}' is not available on target Lua 5.1; it requires Lua 5.2, Lua 5.3, Lua 5.4, LuaJIT or Luau",
+            operator
+        )
+    };
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            unavailable("&"),
+            unavailable("<<"),
+            unavailable("~"),
+            unavailable("|")
+        ]
+    );
+    let handler = check_with_declarations("", source, LuaVersion::LuaJIT);
+    assert!(error_messages(&handler).is_empty());
+
+    let handler = check_with_declarations("", "const env = _ENV", LuaVersion::Lua51);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "'_ENV' is not available on target Lua 5.1; it requires Lua 5.2, Lua 5.3 or Lua 5.4"
+                .to_string()
+        ]
+    );
+}
+
+#[test]
+fn test_unannotated_locals_given_integers_accumulate_floats() {
+    let source = r#"
+        declare function values(): number[]
+        const xs = values()
+        local sum = 0
+        for _, v in ipairs(xs) do
+            sum = sum + v
+        end
+        local stats = { total = 0 }
+        stats.total = stats.total + 0.5
+
+        local count: integer = 0
+        count = count + 0.5
+        local length = #xs
+        length = length + 0.5
+    "#;
+    assert_eq!(
+        error_messages(&check_source(source)),
+        vec![
+            "Type mismatch: expected integer, found number".to_string(),
+            "Type mismatch: expected integer, found number".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_integer_literals_the_target_cannot_represent_warn() {
+    let source = r#"
+        const big = 9_007_199_254_740_993
+        const safe = 9_007_199_254_740_992
+        const id = 12345678901234567LL
+    "#;
+    assert!(warning_messages(&check_source(source))
+        .iter()
+        .all(|message| message.contains("LuaJIT-only")));
+    assert_eq!(
+        warning_messages(&check_with_declarations("", source, LuaVersion::Lua51)),
+        vec![
+            "Integer literal '9007199254740993' cannot be represented exactly on target Lua 5.1"
+                .to_string(),
+            "The 'LL' suffix of '12345678901234567LL' is LuaJIT-only and is dropped for target Lua 5.1"
+                .to_string(),
+            "Integer literal '12345678901234567' cannot be represented exactly on target Lua 5.1"
+                .to_string(),
+        ]
+    );
+    assert_eq!(
+        warning_messages(&check_with_declarations("", source, LuaVersion::LuaJIT)),
+        vec![
+            "Integer literal '9007199254740993' cannot be represented exactly on target LuaJIT"
+                .to_string()
+        ]
+    );
+}
+
+#[test]
+fn test_closures_capturing_self_without_unsubscribe_warn() {
+    let source = r#"
+        class Door {
+            open: boolean
+
+            watch(button: table, bus: table): void {
+                button::on("click", () => self::toggle())
+                bus.onReset = () => { self.open = false }
+                self.onChange = () => self::toggle()
+                const connection = bus::connect(() => self::toggle())
+                bus::on("log", (message) => print(message))
+            }
+
+            toggle(): void {
+                self.open = not self.open
+            }
+        }
+
+        class Lamp {
+            watch(bus: table): void {
+                bus::subscribe(function() self::flicker() end)
+                bus.onPower = () => self::flicker()
+            }
+
+            release(bus: table): void {
+                bus::unsubscribe()
+                bus.onPower = nil
+            }
+
+            flicker(): void {
+            }
+        }
+    "#;
+    let handler = check_source(source);
+    assert!(error_messages(&handler).is_empty());
+    assert_eq!(
+        warning_messages(&handler),
+        vec![
+            "Callback capturing 'self' is registered with 'button::on' and never unsubscribed, which keeps 'Door' instances alive; keep the connection and disconnect it".to_string(),
+            "Callback capturing 'self' is stored in 'bus.onReset' and never cleared, which keeps 'Door' instances alive; set it to nil when done".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_closures_created_in_loops_capturing_tables_warn() {
+    let source = r#"
+        local cache = {}
+        local scale = 2
+        local handlers: table = {}
+        for i = 1, 10 do
+            local scratch = {}
+            handlers[i] = () => cache[i] or scratch
+            handlers[i] = () => i * scale
+        end
+        const build = () => cache
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        warning_messages(&handler),
+        vec!["Closure created inside a loop captures table 'cache' as an upvalue, allocating a new closure every iteration; create it once outside the loop".to_string()]
+    );
+}
+
+#[test]
+fn test_loop_labels_must_name_an_enclosing_loop() {
+    let source = r#"
+        outer: while true do
+            for i = 1, 3 do
+                break outer
+            end
+            outer: repeat
+                continue inner
+            until true
+            const callback = () => {
+                while true do
+                    break outer
+                end
+            }
+        end
+    "#;
+    assert_eq!(
+        error_messages(&check_source(source)),
+        vec![
+            "Label 'outer' is already used by an enclosing loop".to_string(),
+            "Cannot find an enclosing loop labeled 'inner'".to_string(),
+            "Cannot find an enclosing loop labeled 'outer'".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_labeled_break_carries_narrowing_out_of_the_labeled_loop() {
+    let source = format!(
+        "{}{}",
+        LOOKUPS,
+        r#"
+        local found: string? = nil
+        search: while true do
+            while ready() do
+                found = lookup("key")
+                if found ~= nil then
+                    break search
+                end
+            end
+        end
+        const first: string = found
+
+        local maybe: string? = nil
+        scan: while true do
+            while true do
+                maybe = lookup("key")
+                break scan
+            end
+        end
+        const second: string = maybe
+    "#
+    );
+    let handler = check_source(&source);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["Type mismatch: expected string, found string?".to_string()]
+    );
+}
+
+#[test]
+fn test_const_values_known_at_compile_time_have_literal_types() {
+    let source = r#"
+        const NORTH = "north"
+        const SOUTH = "so" .. "uth"
+        const heading: "north" | "south" = NORTH
+        const back: "north" | "south" = SOUTH
+        const literal: "north" | "south" = "north"
+        const lost: "north" | "south" = "we" .. "st"
+
+        local current = NORTH
+        current = "east"
+        local facing: "north" | "south" = "south"
+        facing = NORTH
+        facing = current
+
+        const SIZE = 4 * 4
+        const cells: integer = SIZE * SIZE
+        const shout = NORTH::upper()
+    "#;
+    assert_eq!(
+        error_messages(&check_source(source)),
+        vec![
+            "Type mismatch: expected \"north\" | \"south\", found string".to_string(),
+            "Type mismatch: expected \"north\" | \"south\", found string".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_untyped_blocks_aggregate_suppressed_errors() {
+    let source = r#"
+        local count: number = 0
+        untyped do
+            local name: string = 42
+            count = "many"
+        end
+        trust do
+            count = count + 1
+        end
+        local after: string = count
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["Type mismatch: expected string, found number".to_string()]
+    );
+    assert_eq!(
+        warning_messages(&handler),
+        vec![
+            "2 type error(s) suppressed in untyped block".to_string(),
+            "Untyped block has no type errors and can be removed".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_typeof_anchors_types_to_values() {
+    let source = r#"
+        local defaultConfig = { port = 8080, host = "localhost" }
+        type Config = typeof defaultConfig
+
+        local custom: Config = { port = 80, host = false }
+        function serve(config: Config): void
+            local host: number = config.host
+        end
+        local port: typeof defaultConfig.port = 443
+        local name: typeof defaultConfig.host = 1
+        local missing: typeof nowhere = 1
+    "#;
+    assert_eq!(
+        error_messages(&check_source(source)),
+        vec![
+            "Type mismatch: expected { port: number, host: string }, found { port: number, host: boolean }".to_string(),
+            "Type mismatch: expected number, found string".to_string(),
+            "Type mismatch: expected string, found integer".to_string(),
+            "Undefined variable: nowhere".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_mixed_tables_separate_array_and_named_parts() {
+    let source = r#"
+        interface Point {
+            x: number
+            y: number
+        }
+        type Row = string[] & { n: integer }
+
+        const point: Point = { x = 1, y = 2 }
+        function count(row: Row, list: { [i: number]: boolean, name: string })
+            local size = #row + #list
+            for i, cell in ipairs(row) do
+                local text: string = cell
+            end
+            for i, flag in ipairs(list) do
+                local wrong: string = flag
+            end
+            local n = #point
+            for i, v in ipairs(point) do
+            end
+            for k, v in pairs(point) do
+            end
+        end
+    "#;
+    assert_eq!(
+        error_messages(&check_source(source)),
+        vec![
+            "Type mismatch: expected string, found boolean".to_string(),
+            "'#' counts the array part, but 'Point' has only named fields".to_string(),
+            "ipairs only visits the array part, but 'Point' has only named fields; use pairs"
+                .to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_sparse_arrays_warn() {
+    let source = r#"
+        function clear(items: string?[], names: { [key: string]: string? })
+            items[2] = nil
+            names["a"] = nil
+        end
+        local values = [1, nil, 3]
+        local trailing = [1, 2, nil]
+    "#;
+    let handler = check_source(source);
+    assert!(error_messages(&handler).is_empty());
+    assert_eq!(
+        warning_messages(&handler),
+        vec![
+            "Assigning nil to an array element leaves a hole; '#' and ipairs stop at the first nil, so use table.remove instead".to_string(),
+            "A nil element makes the array sparse; '#' and ipairs may stop before it".to_string(),
+            "A trailing nil is not part of the array; '#' does not count it".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_generic_constraints_checked_at_calls() {
+    let source = r#"
+        interface Named {
+            name: string
+        }
+        interface Person {
+            name: string
+            age: number
+        }
+
+        function sortBy<T, K extends keyof T>(items: {T}, key: K): {T}
+            return items
+        end
+        function greet<T extends Named>(value: T): string
+            local missing = value.nickname
+            return value.name
+        end
+
+        const people: Person[] = []
+        const pet: { legs: number } = { legs = 4 }
+        sortBy(people, "age")
+        sortBy(people, "height")
+        greet(people[1])
+        greet(pet)
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "Property 'nickname' does not exist on type 'Named'".to_string(),
+            "Type '\"height\"' does not satisfy the constraint 'keyof Person' of type parameter 'K'".to_string(),
+            "Type '{ legs: number }' does not satisfy the constraint 'Named' of type parameter 'T'".to_string(),
+        ]
+    );
+    let notes: Vec<usize> = handler
+        .get_diagnostics()
+        .into_iter()
+        .filter(|d| d.level == DiagnosticLevel::Info)
+        .map(|d| d.span.line)
+        .collect();
+    assert_eq!(notes, vec![10, 13]);
+}
+
+#[test]
+fn test_default_type_arguments() {
+    let source = r#"
+        interface Emitter<E = string> {
+            last: E
+            emit(event: E): void
+        }
+        interface Pair<K, V = K> {
+            key: K
+            value: V
+        }
+        interface Broken<A = string, B> {
+            a: A
+        }
+
+        function use(plain: Emitter, numbers: Emitter<number>, pair: Pair<integer>)
+            local name: number = plain.last
+            local count: number = numbers.last
+            local value: string = pair.value
+        end
+        local tooMany: Pair<string, string, string>? = nil
+        local tooFew: Pair? = nil
+
+        function pick<T, K extends keyof T = "name">(item: T, key: K?): unknown
+            return nil
+        end
+        pick({ id = 1 }, nil)
+    "#;
+    assert_eq!(
+        error_messages(&check_source(source)),
+        vec![
+            "Type parameter 'B' has no default but follows one that does".to_string(),
+            "Type mismatch: expected number, found string".to_string(),
+            "Type mismatch: expected string, found integer".to_string(),
+            "Generic type 'Pair' expects 1 to 2 type argument(s), got 3".to_string(),
+            "Generic type 'Pair' expects 1 to 2 type argument(s), got 0".to_string(),
+            "Type '\"name\"' does not satisfy the constraint 'keyof { id: number }' of type parameter 'K'".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_length_operator_typing() {
+    let source = r#"
+        interface Stack {
+            __len(): string
+        }
+        function sizes(name: string, items: number[], stack: Stack, count: number, flag: boolean)
+            local a: integer = #name
+            local b: integer = #items
+            local c: string = #stack
+            local d = #count
+            local e = #flag
+            local f: string = #items
+        end
+    "#;
+    assert_eq!(
+        error_messages(&check_source(source)),
+        vec![
+            "Operator '#' cannot be applied to type 'number'".to_string(),
+            "Operator '#' cannot be applied to type 'boolean'".to_string(),
+            "Type mismatch: expected string, found integer".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_length_after_array_holes_warns() {
+    let source = r#"
+        function trim(items: string?[])
+            items[#items] = nil
+            local kept = #items
+            items[1] = nil
+            local count = #items
+            items = {}
+            local fresh = #items
+        end
+    "#;
+    assert_eq!(
+        warning_messages(&check_source(source)),
+        vec![
+            "Assigning nil to an array element leaves a hole; '#' and ipairs stop at the first nil, so use table.remove instead".to_string(),
+            "'#items' is unreliable after nil was written into it on line 5".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_builtin_utility_types() {
+    let source = r#"
+        interface User {
+            id: number
+            name: string
+            email?: string
+        }
+
+        function show(user: User, full: Required<User>, summary: Pick<User, "id" | "name">, rest: Omit<User, "id">, scores: Record<string, number>, flags: Record<"debug" | "trace", boolean>)
+            local email: string = full.email
+            local name: string = summary.name
+            local missing = summary.email
+            local id = rest.id
+            local score: string = scores["alice"]
+            local debug: boolean = flags.debug
+            for name, value in pairs(scores) do
+                local n: number = value
+            end
+        end
+        local view: Pick<User, "nickname">? = nil
+        local record: Record<string>? = nil
+        const wrong: Pick<User, "id"> = { id = "one" }
+    "#;
+    assert_eq!(
+        error_messages(&check_source(source)),
+        vec![
+            "Property 'email' does not exist on type 'Pick<User, \"id\" | \"name\">'".to_string(),
+            "Property 'id' does not exist on type 'Omit<User, \"id\">'".to_string(),
+            "Type mismatch: expected string, found number".to_string(),
+            "Type '\"nickname\"' does not satisfy the constraint 'keyof User' of type parameter 'K'".to_string(),
+            "Generic type 'Record' expects 2 type argument(s), got 1".to_string(),
+            "Type mismatch: expected Pick<User, \"id\">, found { id: string }".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_union_member_access_and_discriminant_narrowing() {
+    let source = r#"
+        interface Circle {
+            kind: "circle"
+            radius: number
+            label?: string
+        }
+
+        interface Square {
+            kind: "square"
+            size: number
+        }
+
+        function area(s: Circle | Square): number
+            local kind: "circle" | "square" = s["kind"]
+            if s.kind == "circle" then
+                local r: number = s.radius
+                local label: string? = s.label
+                return r * r
+            end
+            local side: number = s.size
+            local radius = s.radius
+            return side * side
+        end
+
+        function describe(s: Circle | Square)
+            local radius: number = s.radius
+            local label: string? = s.label
+            local color = s.color
+        end
+    "#;
+    assert_eq!(
+        error_messages(&check_source(source)),
+        vec![
+            "Property 'radius' does not exist on type 'Square'".to_string(),
+            "Type mismatch: expected number, found number?".to_string(),
+            "Property 'color' does not exist on type 'Circle | Square'".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_enum_helpers_are_typed() {
+    let source = r#"
+        enum Color { Red, Green, Blue }
+        enum Tool { Hammer = "hammer", values = "values" }
+
+        for _, color in ipairs(Color.values()) do
+            const c: Color = color
+        end
+        const found: Color = Color.fromName("Red")
+        const position: integer = Color.ordinal(Color.Green)
+        const later: boolean = Color.ordinal(Color.Red) < Color.ordinal(Color.Blue)
+        const name: string = Color.ordinal(Color.Red)
+        const one: Color = 1
+        const wrong: Color = 4
+    "#;
+    assert_eq!(
+        error_messages(&check_source(source)),
+        vec![
+            "Enum member 'values' conflicts with the generated 'Tool.values'".to_string(),
+            "Type mismatch: expected 1 | 2 | 3, found Color?".to_string(),
+            "Type mismatch: expected string, found integer".to_string(),
+            "Type mismatch: expected 1 | 2 | 3, found integer".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_weak_tables_read_as_possibly_nil() {
+    let source = r#"
+        interface Node {
+            id: number
+        }
+
+        @weak("v")
+        local nodes: { [key: string]: Node } = {}
+        @weak("v")
+        local names: { [key: string]: string } = {}
+        @weak("v")
+        class Handle {
+            target: Node
+            label: string
+            constructor(target: Node) {
+                self.target = target
+                self.label = "handle"
+            }
+        }
+        @weak("k")
+        class Plain {
+        }
+        @weak("x")
+        local broken: { [key: string]: number } = {}
+
+        const node: Node = nodes["a"]
+        const name: string = names["a"]
+        function follow(handle: Handle)
+            const target: Node = handle.target
+            const label: string = handle.label
+        end
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "@weak expects \"k\", \"v\" or \"kv\"".to_string(),
+            "Type mismatch: expected Node, found Node?".to_string(),
+            "Type mismatch: expected Node, found Node?".to_string(),
+        ]
+    );
+    assert_eq!(
+        warning_messages(&handler),
+        vec!["@weak(\"k\") has no effect on a class: instance fields have string keys, which are never collected".to_string()]
+    );
+}
+
+#[test]
+fn test_call_arity_and_overloaded_function_types() {
+    let source = r#"
+        interface Options {
+            verbose: boolean
+        }
+        type Handler = (event: string, opts?: Options) -> (boolean, string?)
+        type Parse = ((input: string) -> number) | ((input: number, base: integer) -> integer)
+
+        function run(handle: Handler, parse: Parse, log: (level: integer, ...parts: string[]) -> nil)
+            const ok = handle("click")
+            handle("click", { verbose = true })
+            handle()
+            handle("a", nil, 3)
+            const n: number = parse("12")
+            const m: integer = parse(12, 10)
+            parse(true)
+            log()
+            log(1, "a", "b", "c")
+            handle(parse("1"))
+        end
+
+        function greet(name: string, greeting: string = "hi") end
+        greet("a")
+        greet()
+        greet("a", "b", "c")
+    "#;
+    assert_eq!(
+        error_messages(&check_source(source)),
+        vec![
+            "Expected 1 to 2 argument(s), got 0".to_string(),
+            "Expected 1 to 2 argument(s), got 3".to_string(),
+            "No overload of 'parse' matches arguments (boolean)".to_string(),
+            "Expected at least 1 argument(s), got 0".to_string(),
+            "Expected 1 to 2 argument(s), got 0".to_string(),
+            "Expected 1 to 2 argument(s), got 3".to_string(),
+        ]
+    );
+}
+
+/// Check a program honouring its `// @name` directives
+fn check_with_directives(source: &str, config: CompilerConfig) -> Arc<CollectingDiagnosticHandler> {
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let mut lexer = Lexer::new(source, handler.clone());
+    let tokens = lexer.tokenize().expect("Lexing failed");
+    let program = Parser::new(tokens, handler.clone())
+        .parse()
+        .expect("Parse failed");
+
+    let mut checker = TypeChecker::new(Arc::new(config), handler.clone());
+    checker.set_directives(lexer.directives());
+    let _ = checker.check_program(&program);
+    handler
+}
+
+fn check_arity(source: &str, strict_arity: bool) -> Vec<String> {
+    let mut config = CompilerConfig::default();
+    config.compiler_options.strict_arity = strict_arity;
+    error_messages(&check_with_directives(source, config))
+}
+
+#[test]
+fn test_arity_relaxed_by_directive_or_config() {
+    let source = r#"
+        function pair(a: number, b: number): number
+            return a + b
+        end
+        pair(1)
+        pair(1, 2, 3) // @varargs-ok
+        // @varargs-ok
+        pair()
+        pair(1, 2, 3)
+    "#;
+    assert_eq!(
+        check_arity(source, true),
+        vec![
+            "Expected 2 argument(s), got 1".to_string(),
+            "Expected 2 argument(s), got 3".to_string(),
+        ]
+    );
+    assert!(check_arity(source, false).is_empty());
+}
+
+#[test]
+fn test_deprecated_and_inline_attributes() {
+    let source = r#"
+        @deprecated("use area instead")
+        function size(r: number): number
+            return r * r
+        end
+        @deprecated
+        interface OldShape {
+            r: number
+        }
+        @deprecated(42)
+        local broken = 1
+        @inline
+        function twice(x: number): number
+            return x * 2
+        end
+        @inline
+        function noisy(x: number): number
+            print(x)
+            return x
+        end
+
+        const a = size(2)
+        local shape: OldShape = { r = 1 }
+        function shadowed(size: number): number
+            return size
+        end
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["@deprecated expects at most one message string".to_string()]
+    );
+    assert_eq!(
+        warning_messages(&handler),
+        vec![
+            "@inline has no effect on 'noisy': its body must be a single return of one value, with plain parameters".to_string(),
+            "'size' is deprecated: use area instead".to_string(),
+            "'OldShape' is deprecated".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_template_format_specifiers() {
+    let source = r#"
+        local score = 9.5
+        local rank: integer = 3
+        local name = "Ada"
+        const a = `${name}: ${score:%.2f} (${rank:%5d}, ${rank:%x}, ${rank:%.1f})`
+        const b = `${name:%-10s}|${name:%q}`
+        const c = `${name:%.2f}`
+        const d = `${score:%y}`
+        const e = `${score:%.123f}`
+        const f = `${score:%d} ${score:%X}`
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "Format specifier '%.2f' expects a number, found string".to_string(),
+            "Invalid format specifier '%y'".to_string(),
+            "Invalid format specifier '%.123f'".to_string(),
+            "Format specifier '%d' expects an integer, found number".to_string(),
+            "Format specifier '%X' expects an integer, found number".to_string(),
+        ]
+    );
+
+    // Without an integer subtype every number is accepted
+    let handler = check_with_declarations("", source, LuaVersion::Lua51);
+    assert_eq!(error_messages(&handler).len(), 3);
+}
+
+#[test]
+fn test_multiple_return_values() {
+    let source = r#"
+        declare function pcall(f: () -> unknown): (boolean, string...)
+        declare function pair(): (number, string)
+        local ok, message, ...rest = pcall(() => 1)
+        const a: boolean = ok
+        const b: string = message
+        const c: string[] = rest
+        const d: number = message
+        local n, s = pair()
+        const e: string = n
+        local x, y, z = pair()
+        local first = pair()
+        const f: number = first
+        local g, h: (number, string...) = pair()
+        local i, j: (string, number) = pair()
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "Type mismatch: expected number, found string".to_string(),
+            "Type mismatch: expected string, found number".to_string(),
+            "Expression produces 2 value(s), but 3 names are bound".to_string(),
+            "Type mismatch: expected [string, number], found [number, string]".to_string(),
+        ]
+    );
+}
+
+fn taint_warnings(source: &str, enabled: bool) -> Vec<String> {
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let program = parse(source, &handler);
+
+    let mut config = CompilerConfig::default();
+    config.compiler_options.taint_analysis = enabled;
+
+    let mut checker = TypeChecker::new(Arc::new(config), handler.clone());
+    let _ = checker.check_program(&program);
+    warning_messages(&handler)
+}
+
+#[test]
+fn test_taint_flows_into_sinks() {
+    let source = r#"
+        local line = io.read("l")
+        local command = "ls " .. line
+        os.execute(command)
+        os.execute("ls")
+        local request = {}
+        request.body = client::receive("*a")
+        local chunk = load(request)
+        for name in string.gmatch(os.getenv("NAMES"), "%w+") do
+            io.popen(name::lower())
+        end
+        local shadowed = line
+        do_work(function(line: string)
+            os.execute(line)
+        end)
+        local safe = "echo"
+        safe = command
+        command |> os.execute
+    "#;
+    assert!(taint_warnings(source, false).is_empty());
+    assert_eq!(
+        taint_warnings(source, true),
+        vec![
+            "Untrusted value reaches 'os.execute': io.read() at 2:22 -> 'line' at 2:15 -> 'command' at 3:15".to_string(),
+            "Untrusted value reaches 'load': client::receive() at 7:24 -> 'request' at 7:9".to_string(),
+            "Untrusted value reaches 'io.popen': os.getenv() at 9:35 -> 'name' at 9:13".to_string(),
+            "Untrusted value reaches 'os.execute': io.read() at 2:22 -> 'line' at 2:15 -> 'command' at 3:15".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_schema_migrations_must_cover_every_version() {
+    let source = r#"
+        @schema(version = 3)
+        interface SaveData {
+            gold: number
+        }
+        @migration(SaveData, 1)
+        function fromV1(data: table): table
+            return data
+        end
+        @migration(SaveData, 1)
+        function again(data: table): table
+            return data
+        end
+        @migration(SaveData, 3)
+        function tooNew(data: table): table
+            return data
+        end
+        @migration(Settings, 1)
+        function unknown(data: table, extra: number): table
+            return data
+        end
+        @schema(3)
+        interface Broken {}
+
+        const saved: table = SaveData.serialize({ gold = 1 })
+        const loaded: SaveData = SaveData.deserialize(saved)
+        const version: 3 = SaveData.version
+    "#;
+    let handler = check_source(source);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "@schema expects 'version = N', with N a positive integer".to_string(),
+            "'SaveData' already has a migration from version 1".to_string(),
+            "'SaveData' is at version 3, so has no version 3 to migrate from".to_string(),
+            "Migration 'unknown' must take the saved data as its only parameter".to_string(),
+            "'Settings' is not a @schema record".to_string(),
+            "'SaveData' is at version 3 but has no migration from version 2".to_string(),
+        ]
+    );
+}
+
+const SOCKET_DECLARATIONS: &str = r#"
+export interface Client {
+    send(data: string): number
+}
+export declare function connect(host: string, port: number): Client
+export declare const VERSION: string
+"#;
+
+fn check_with_modules(source: &str, allow_non_typed_lua: bool) -> Arc<CollectingDiagnosticHandler> {
+    let mut file_system = crate::fs::MockFileSystem::new();
+    file_system.add_file("src/socket.lua", "return {}");
+    file_system.add_file("src/socket.d.tl", SOCKET_DECLARATIONS);
+    file_system.add_file("src/legacy.lua", "return {}");
+
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let program = parse(source, &handler);
+    let mut config = CompilerConfig::default();
+    config.compiler_options.allow_non_typed_lua = allow_non_typed_lua;
+    let mut checker = TypeChecker::new(Arc::new(config), handler.clone());
+    let resolver = crate::resolve::ModuleResolver::new(Arc::new(file_system));
+    checker.set_module_resolver(resolver, std::path::Path::new("src/main.tl"));
+    let _ = checker.check_program(&program);
+    handler
+}
+
+#[test]
+fn test_lua_imports_are_typed_by_declaration_files() {
+    let source = r#"
+        import { connect as open, VERSION, Client, listen } from "./socket"
+        import * as socket from "./socket"
+        import { anything } from "./legacy"
+        const client: Client = open("localhost", 80)
+        const sent: string = client.send("hi")
+        const version: string = socket.VERSION
+        const wrong = socket.connect("localhost")
+        const count: number = socket.VERSION
+        const untyped: number = anything
+    "#;
+    let handler = check_with_modules(source, true);
+    assert_eq!(
+        error_messages(&handler),
+        vec![
+            "Module './socket' does not export 'listen'",
+            "Type mismatch: expected string, found number",
+            "Expected 2 argument(s), got 1",
+            "Type mismatch: expected number, found string",
+        ]
+    );
+
+    let handler = check_with_modules("import { anything } from \"./legacy\"", false);
+    assert_eq!(
+        error_messages(&handler),
+        vec!["Cannot import './legacy': it is plain Lua without a declaration file (.d.tl)"]
+    );
+}
+
+#[test]
+fn test_errors_point_at_related_places() {
+    let source = r#"import { listen } from "./socket"
+const label: string = 1
+local count: number = 0
+count = "many"
+class Point {}
+class Point {}
+"#;
+    let handler = check_with_modules(source, true);
+    let related: Vec<(String, Option<String>, usize, String)> = handler
+        .get_diagnostics()
+        .into_iter()
+        .filter(|d| d.level == DiagnosticLevel::Error)
+        .flat_map(|d| {
+            let message = d.message;
+            d.related.into_iter().map(move |related| {
+                let file = related.file.map(|file| file.display().to_string());
+                (message.clone(), file, related.span.line, related.message)
+            })
+        })
+        .collect();
+    let expect = |message: &str, file: Option<&str>, line: usize, related: &str| {
+        let file = file.map(str::to_string);
+        (message.to_string(), file, line, related.to_string())
+    };
+    assert_eq!(
+        related,
+        vec![
+            expect(
+                "Duplicate declaration: Point",
+                None,
+                5,
+                "'Point' is first defined here"
+            ),
+            expect(
+                "Module './socket' does not export 'listen'",
+                Some("src/socket.d.tl"),
+                1,
+                "'./socket' is typed by this declaration file"
+            ),
+            expect(
+                "Type mismatch: expected string, found integer",
+                None,
+                2,
+                "expected due to this annotation"
+            ),
+            expect(
+                "Type mismatch: expected number, found string",
+                None,
+                3,
+                "'count' is declared here as 'number'"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_standard_library_is_typed_per_target() {
+    let source = r#"
+        const text: number = string.format("%d", 1)
+        const floor: integer = math.floor(1.5)
+        const repeated = string.rep("a")
+        const parts = string.split("a,b", ",")
+        const ok, result = pcall(print, "hi")
+        const failed: string = ok
+        local file, message = io.open("save.dat")
+    "#;
+    assert_eq!(
+        error_messages(&check_source(source)),
+        vec![
+            "Type mismatch: expected number, found string".to_string(),
+            "Expected 2 to 3 argument(s), got 1".to_string(),
+            "'StringLibrary.split' is not available on target Lua 5.4; it requires Luau"
+                .to_string(),
+            "Type mismatch: expected string, found boolean".to_string(),
+        ]
+    );
+
+    let source = "const values = unpack([1, 2])\nconst packed = table.unpack([1, 2])\nwarn(\"x\")";
+    assert_eq!(
+        error_messages(&check_with_declarations("", source, LuaVersion::Lua51)),
+        vec![
+            "'TableLibrary.unpack' is not available on target Lua 5.1; it requires Lua 5.2, Lua 5.3, Lua 5.4 or Luau"
+                .to_string(),
+            "'warn' is not available on target Lua 5.1; it requires Lua 5.4".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_using_requires_closable_values() {
+    let source = r#"
+        class Lock {
+            close(): void {}
+        }
+        class Counter {
+            count: number = 0
+        }
+        function read(path: string): void
+            using file = io.open(path)
+            using lock = Lock.new()
+            using counter = Counter.new()
+            using count = 1
+        end
+    "#;
+    assert_eq!(
+        error_messages(&check_source(source)),
+        vec![
+            "Type 'Lock' cannot be used with 'using': it has no '__close' metamethod".to_string(),
+            "Type 'Counter' cannot be used with 'using': it has no '__close' metamethod"
+                .to_string(),
+            "Type 'number' cannot be used with 'using': it has no '__close' metamethod".to_string(),
+        ]
+    );
+
+    let source = r#"
+        class Lock {
+            close(): void {}
+        }
+        for i = 1, 3 do
+            using lock = Lock.new()
+            if i == 2 then
+                break
+            end
+            while true do
+                break
+            end
+        end
+    "#;
+    assert_eq!(
+        error_messages(&check_with_declarations("", source, LuaVersion::Lua51)),
+        vec![
+            "'break' cannot leave the scope of a 'using' declaration on target Lua 5.1".to_string()
+        ]
+    );
+    // `<close>` needs `__close`, but leaves the block open to `break`
+    assert_eq!(
+        error_messages(&check_source(source)),
+        vec!["Type 'Lock' cannot be used with 'using': it has no '__close' metamethod".to_string()]
+    );
+}
+
+#[test]
+fn test_checked_attribute_applies_to_functions() {
+    let source = r#"
+        @checked
+        function step(position: integer): integer
+            return position + 1
+        end
+        @checked
+        local speed = 2
+    "#;
+    assert_eq!(
+        warning_messages(&check_source(source)),
+        vec!["@checked only applies to functions".to_string()]
+    );
+}
+
+#[test]
+fn test_deterministic_modules_flag_nondeterministic_calls() {
+    let source = r#"
+        // @deterministic
+        function step(units: { [string]: number }, angle: number): number
+            local total = 0
+            for id, hp in pairs(units) do
+                total = total + hp
+            end
+            for i, hp in ipairs([1, 2]) do
+                total = total + hp
+            end
+            const roll = math.random(1, 6)
+            const dx = math.cos(angle)
+            const root = math.sqrt(angle)
+            const now = os.clock()
+            return total + roll + dx + root + now
+        end
+    "#;
+    assert_eq!(
+        warning_messages(&check_with_directives(source, CompilerConfig::default())),
+        vec![
+            "'pairs' visits keys in an unspecified order, which is not deterministic; iterate with 'ipairs' or over sorted keys".to_string(),
+            "'math.random' uses the global random generator, which is not deterministic; use a seeded 'Random' instead".to_string(),
+            "'math.cos' is computed by the platform's math library, which is not deterministic; use a fixed-point or software implementation".to_string(),
+            "'os.clock' reads the local clock, which is not deterministic".to_string(),
+        ]
+    );
+
+    // Without the directive, and for the program's own `math`, nothing is flagged
+    let source = source.replace("// @deterministic", "");
+    assert!(
+        warning_messages(&check_with_directives(&source, CompilerConfig::default())).is_empty()
+    );
+    let source = "// @deterministic\nconst math = { cos = (x: number): number => x }\nconst one = math.cos(1)";
+    assert!(warning_messages(&check_with_directives(source, CompilerConfig::default())).is_empty());
+}
+
+#[test]
+fn test_tl_ignore_silences_codes_and_reports_unused_ones() {
+    let source = r#"
+        const a: number = "one" // @tl-ignore TL2001
+        // @tl-ignore TL2001 TL2014
+        function f(): number
+            const b: string = 2
+            return 1
+        end
+        // @tl-ignore TL2001
+        const c: number = 3
+        const d: number = "four"
+    "#;
+    let checked = check_with_directives(source, CompilerConfig::default());
+    assert_eq!(
+        error_messages(&checked),
+        vec!["Type mismatch: expected number, found string".to_string()]
+    );
+    assert_eq!(
+        warning_messages(&checked),
+        vec![
+            "This '@tl-ignore' silences nothing under TL2014".to_string(),
+            "This '@tl-ignore' silences nothing under TL2001".to_string(),
+        ]
+    );
+
+    let mut config = CompilerConfig::default();
+    config.compiler_options.no_suppressions = true;
+    let checked = check_with_directives(source, config);
+    assert_eq!(error_messages(&checked).len(), 6);
+    assert!(warning_messages(&checked).is_empty());
+}
+
+#[test]
+fn test_seeded_random_is_typed() {
+    let source = r#"
+        // @deterministic
+        const rng = Random.new(42)
+        const roll: integer = rng::nextInt(1, 6)
+        const chance: number = rng::nextFloat()
+        const bad: string = rng::next()
+    "#;
+    let checked = check_with_directives(source, CompilerConfig::default());
+    assert!(warning_messages(&checked).is_empty());
+    assert_eq!(error_messages(&checked).len(), 1);
+}
+
+#[test]
+fn test_order_sensitive_modules_flag_pairs_order_dependence() {
+    let source = r#"
+        // @order-sensitive
+        function summarize(scores: { [string]: number }): string
+            local best = ""
+            local top = 0
+            local text = ""
+            const names: string[] = []
+            for name, score in pairs(scores) do
+                if score > top then
+                    best = name
+                    top = score
+                end
+                text = text .. name
+                table.insert(names, name)
+                names[#names + 1] = name
+            end
+            for i, score in ipairs([1, 2]) do
+                text = text .. tostring(score)
+                if score > top then
+                    top = score
+                end
+            end
+            return best .. text
+        end
+    "#;
+    assert_eq!(
+        warning_messages(&check_with_directives(source, CompilerConfig::default())),
+        vec![
+            "Comparing 'score' inside a 'pairs' loop depends on the order keys are visited, which is unspecified; iterate over sorted keys".to_string(),
+            "'text' inside a 'pairs' loop records the order keys are visited, which is unspecified; iterate over sorted keys".to_string(),
+            "'table.insert' inside a 'pairs' loop records the order keys are visited, which is unspecified; iterate over sorted keys".to_string(),
+            "'names' inside a 'pairs' loop records the order keys are visited, which is unspecified; iterate over sorted keys".to_string(),
+        ]
+    );
+
+    let source = source.replace("// @order-sensitive", "");
+    assert!(
+        warning_messages(&check_with_directives(&source, CompilerConfig::default())).is_empty()
+    );
+}
+
+fn check_data_module(source: &str) -> Arc<CollectingDiagnosticHandler> {
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let program = parse(source, &handler);
+    let mut checker = TypeChecker::new(Arc::new(CompilerConfig::default()), handler.clone());
+    checker.set_data_module();
+    let _ = checker.check_program(&program);
+    handler
+}
+
+#[test]
+fn test_data_modules_validate_each_row_against_the_schema() {
+    let source = r#"
+        interface Enemy {
+            kind: string
+            hp: number
+            loot?: string[]
+        }
+        interface Level {
+            name: string
+            enemies: Enemy[]
+        }
+        export const levels: Level[] = [
+            { name = "cave", enemies = [{ kind = "bat", hp = 3 }] },
+            { name = "keep", enemies = [{ kind = "knight", hp = "ten" }, { kind = "archer" }] },
+            { name = "tower", enemies = [], boss = "lich" },
+            { name = "crypt", enemies = [{ kind = "ghoul", hp = 5, loot = ["bone", 7] }] },
+        ]
+        const spawn = levels[1]
+        export function load(): Level[]
+            return levels
+        end
+    "#;
+    assert_eq!(
+        error_messages(&check_data_module(source)),
+        vec![
+            "Data 'spawn' needs a declared type to be validated against".to_string(),
+            "Data modules hold only types and constant tables, not computed values".to_string(),
+            "Data modules hold only types and constant tables, not functions".to_string(),
+            "levels[2].enemies[1].hp: expected number, found string".to_string(),
+            "levels[2].enemies[2]: missing field 'hp'".to_string(),
+            "levels[3].boss: not a field of Level".to_string(),
+            "levels[4].enemies[1].loot[2]: expected string, found integer".to_string(),
+        ]
+    );
+}
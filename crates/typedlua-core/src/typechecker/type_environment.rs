@@ -0,0 +1,243 @@
+use crate::ast::statement::{
+    ClassDeclaration, ClassMember, InterfaceDeclaration, MethodDeclaration, Parameter,
+    TypeParameter,
+};
+use crate::ast::types::{Type, TypeKind};
+use crate::config::LuaVersion;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Named declarations visible to the type checker
+#[derive(Debug, Default)]
+pub struct TypeEnvironment {
+    classes: HashMap<String, ClassDeclaration>,
+    interfaces: HashMap<String, InterfaceDeclaration>,
+    /// Non-generic type aliases and the types they stand for
+    type_aliases: HashMap<String, Type>,
+    /// Type parameters and parameters of generic functions, so calls can
+    /// infer type arguments and check their constraints
+    generic_functions: HashMap<String, (Vec<TypeParameter>, Vec<Parameter>)>,
+    /// Exported members of each namespace, keyed by qualified name and
+    /// shaped like an interface so member lookups can share one path
+    namespaces: HashMap<String, InterfaceDeclaration>,
+    /// Globals hidden by a `@target` guard, with the targets that provide them
+    unavailable: HashMap<String, Vec<LuaVersion>>,
+    /// Interface members hidden by a `@target` guard, keyed by interface name
+    unavailable_members: HashMap<String, HashMap<String, Vec<LuaVersion>>>,
+    /// Globals marked `@unavailableOn` the configured platform
+    platform_unavailable: HashSet<String>,
+    /// Interface members marked `@unavailableOn` the configured platform
+    platform_unavailable_members: HashMap<String, HashSet<String>>,
+}
+
+impl TypeEnvironment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a class declaration, returning false if the name is already taken
+    pub fn register_class(&mut self, class: ClassDeclaration) -> bool {
+        if self.classes.contains_key(&class.name.node) {
+            return false;
+        }
+        self.classes.insert(class.name.node.clone(), class);
+        true
+    }
+
+    pub fn get_class(&self, name: &str) -> Option<&ClassDeclaration> {
+        self.classes.get(name)
+    }
+
+    /// Register an interface declaration. A later declaration with the same
+    /// name merges into the earlier one: members are appended and the
+    /// extended interfaces are combined.
+    pub fn merge_interface(&mut self, interface: InterfaceDeclaration) {
+        match self.interfaces.get_mut(&interface.name.node) {
+            Some(existing) => {
+                for base in interface.extends {
+                    let base_name = base.to_string();
+                    if !existing.extends.iter().any(|b| b.to_string() == base_name) {
+                        existing.extends.push(base);
+                    }
+                }
+                existing.members.extend(interface.members);
+            }
+            None => {
+                self.interfaces
+                    .insert(interface.name.node.clone(), interface);
+            }
+        }
+    }
+
+    pub fn get_interface(&self, name: &str) -> Option<&InterfaceDeclaration> {
+        self.interfaces.get(name)
+    }
+
+    pub fn register_alias(&mut self, name: &str, typ: Type) {
+        self.type_aliases.insert(name.to_string(), typ);
+    }
+
+    pub fn get_alias(&self, name: &str) -> Option<&Type> {
+        self.type_aliases.get(name)
+    }
+
+    pub fn register_generic_function(
+        &mut self,
+        name: &str,
+        type_parameters: Vec<TypeParameter>,
+        parameters: Vec<Parameter>,
+    ) {
+        self.generic_functions
+            .insert(name.to_string(), (type_parameters, parameters));
+    }
+
+    pub fn get_generic_function(
+        &self,
+        name: &str,
+    ) -> Option<&(Vec<TypeParameter>, Vec<Parameter>)> {
+        self.generic_functions.get(name)
+    }
+
+    /// Register the exported members of a namespace block. Repeated blocks
+    /// with the same qualified name merge like interfaces do.
+    pub fn merge_namespace(&mut self, namespace: InterfaceDeclaration) {
+        match self.namespaces.get_mut(&namespace.name.node) {
+            Some(existing) => existing.members.extend(namespace.members),
+            None => {
+                self.namespaces
+                    .insert(namespace.name.node.clone(), namespace);
+            }
+        }
+    }
+
+    pub fn get_namespace(&self, name: &str) -> Option<&InterfaceDeclaration> {
+        self.namespaces.get(name)
+    }
+
+    /// An interface followed by every interface it extends, nearest first.
+    /// The flag is false when some base could not be resolved.
+    pub fn interface_chain(&self, name: &str) -> (Vec<&InterfaceDeclaration>, bool) {
+        let mut chain: Vec<&InterfaceDeclaration> = Vec::new();
+        let mut pending = VecDeque::from([name.to_string()]);
+        let mut complete = true;
+
+        while let Some(name) = pending.pop_front() {
+            if chain.iter().any(|i| i.name.node == name) {
+                continue;
+            }
+            let found = self
+                .get_interface(&name)
+                .or_else(|| self.get_namespace(&name));
+            let Some(interface) = found else {
+                complete = false;
+                continue;
+            };
+            for base in &interface.extends {
+                match &base.kind {
+                    TypeKind::Reference(reference) => {
+                        pending.push_back(reference.name.node.clone())
+                    }
+                    _ => complete = false,
+                }
+            }
+            chain.push(interface);
+        }
+
+        (chain, complete)
+    }
+
+    /// Record a global that exists, but not on the configured target
+    pub fn mark_unavailable(&mut self, name: &str, targets: Vec<LuaVersion>) {
+        self.unavailable.insert(name.to_string(), targets);
+    }
+
+    /// Targets providing a global hidden from the configured target
+    pub fn unavailable(&self, name: &str) -> Option<&[LuaVersion]> {
+        self.unavailable.get(name).map(Vec::as_slice)
+    }
+
+    /// Record an interface member that exists, but not on the configured target
+    pub fn mark_member_unavailable(&mut self, owner: &str, member: &str, targets: Vec<LuaVersion>) {
+        self.unavailable_members
+            .entry(owner.to_string())
+            .or_default()
+            .insert(member.to_string(), targets);
+    }
+
+    /// Targets providing an interface member hidden from the configured target
+    pub fn unavailable_member(&self, owner: &str, member: &str) -> Option<&[LuaVersion]> {
+        self.unavailable_members
+            .get(owner)?
+            .get(member)
+            .map(Vec::as_slice)
+    }
+
+    /// Record a global that the configured platform does not provide
+    pub fn mark_platform_unavailable(&mut self, name: &str) {
+        self.platform_unavailable.insert(name.to_string());
+    }
+
+    pub fn is_platform_unavailable(&self, name: &str) -> bool {
+        self.platform_unavailable.contains(name)
+    }
+
+    /// Record an interface member that the configured platform does not provide
+    pub fn mark_member_platform_unavailable(&mut self, owner: &str, member: &str) {
+        self.platform_unavailable_members
+            .entry(owner.to_string())
+            .or_default()
+            .insert(member.to_string());
+    }
+
+    pub fn is_member_platform_unavailable(&self, owner: &str, member: &str) -> bool {
+        self.platform_unavailable_members
+            .get(owner)
+            .is_some_and(|members| members.contains(member))
+    }
+
+    /// Get the direct base class of a class, if it extends a known class
+    pub fn base_class(&self, class: &ClassDeclaration) -> Option<&ClassDeclaration> {
+        match &class.extends.as_ref()?.kind {
+            TypeKind::Reference(reference) => self.get_class(&reference.name.node),
+            _ => None,
+        }
+    }
+
+    /// Get the known classes a class mixes in, in declaration order
+    pub fn mixin_classes(&self, class: &ClassDeclaration) -> Vec<&ClassDeclaration> {
+        class
+            .mixins
+            .iter()
+            .filter_map(|mixin| match &mixin.kind {
+                TypeKind::Reference(reference) => self.get_class(&reference.name.node),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Walk the inheritance chain starting at (and excluding) the given class.
+    /// Stops on unknown bases and on cycles.
+    pub fn ancestors<'a>(&'a self, class: &'a ClassDeclaration) -> Vec<&'a ClassDeclaration> {
+        let mut chain: Vec<&ClassDeclaration> = Vec::new();
+        let mut current = class;
+
+        while let Some(base) = self.base_class(current) {
+            if base.name.node == class.name.node
+                || chain.iter().any(|c| c.name.node == base.name.node)
+            {
+                break;
+            }
+            chain.push(base);
+            current = base;
+        }
+
+        chain
+    }
+}
+
+/// Iterate over the methods declared directly in a class body
+pub fn class_methods(class: &ClassDeclaration) -> impl Iterator<Item = &MethodDeclaration> {
+    class.members.iter().filter_map(|member| match member {
+        ClassMember::Method(method) => Some(method),
+        _ => None,
+    })
+}
@@ -0,0 +1,175 @@
+use super::assignability::literals_equal;
+use super::flow::Narrowing;
+use super::inference::join_types;
+use super::{is_nil, unknown_type, MemberLookup, TypeChecker};
+use crate::ast::expression::{Expression, ExpressionKind, Literal};
+use crate::ast::types::{PrimitiveType, Type, TypeKind};
+use crate::ast::Ident;
+use crate::errors::TypeCheckError;
+
+impl TypeChecker {
+    /// Type of `t.name` where `t` is a union of table shapes: the field's
+    /// type in each shape, joined. Shapes without the field contribute
+    /// `nil`, since reading an absent field gives nil in Lua.
+    pub(super) fn union_member_type(
+        &mut self,
+        union: &Type,
+        types: &[Type],
+        member: &Ident,
+    ) -> Type {
+        let mut found = Vec::new();
+        let mut absent = false;
+        for typ in types.iter().filter(|t| !is_nil(t)) {
+            match self.property_lookup(typ, &member.node) {
+                MemberLookup::Found(typ) => found.push(typ),
+                MemberLookup::Missing => absent = true,
+                _ => return unknown_type(member.span),
+            }
+        }
+        if found.is_empty() {
+            self.report_error(
+                member.span,
+                TypeCheckError::UnknownProperty {
+                    property: member.node.clone(),
+                    type_name: union.to_string(),
+                },
+            );
+            return unknown_type(member.span);
+        }
+        if absent {
+            found.push(Type::new(
+                TypeKind::Primitive(PrimitiveType::Nil),
+                member.span,
+            ));
+        }
+        join_types(found, member.span)
+    }
+
+    /// Narrowing from comparing a field of a union-typed variable with a
+    /// literal: `t.kind == "circle"` keeps the shapes whose `kind` can be
+    /// `"circle"`, `t.kind ~= "circle"` drops those where it must be
+    pub(super) fn discriminant_narrowing(
+        &self,
+        access: &Expression,
+        value: &Expression,
+        equal: bool,
+    ) -> Narrowing {
+        let (Some((name, key)), ExpressionKind::Literal(literal)) =
+            (field_access(access), &value.kind)
+        else {
+            return Narrowing::new();
+        };
+        self.filter_union(name, |checker, typ| {
+            let field = checker.field_type(typ, key);
+            if equal {
+                field.is_none_or(|field| may_equal(&field, literal))
+            } else {
+                !field.is_some_and(|field| must_equal(&field, literal))
+            }
+        })
+    }
+
+    /// Narrowing from testing a field of a union-typed variable for
+    /// truthiness: `if t.radius then` keeps the shapes that declare `radius`
+    pub(super) fn presence_narrowing(&self, access: &Expression, truthy: bool) -> Narrowing {
+        let Some((name, key)) = field_access(access) else {
+            return Narrowing::new();
+        };
+        self.filter_union(name, |checker, typ| {
+            let field = checker.field_type(typ, key);
+            if truthy {
+                field.is_some_and(|field| !is_nil(&field))
+            } else {
+                // Only a field that is always truthy rules the shape out
+                field.is_none_or(|field| may_be_falsy(&field))
+            }
+        })
+    }
+
+    /// The variable's current type with the union members `keep` rejects
+    /// removed, or no narrowing if that would change nothing or leave none
+    fn filter_union(&self, name: &str, keep: impl Fn(&Self, &Type) -> bool) -> Narrowing {
+        let current = match self.narrowed_type(name) {
+            Some(typ) => typ,
+            None => match self.symbols.lookup(name) {
+                Some(symbol) => &symbol.typ,
+                None => return Narrowing::new(),
+            },
+        };
+        let TypeKind::Union(types) = &current.kind else {
+            return Narrowing::new();
+        };
+        let mut kept: Vec<Type> = types.iter().filter(|t| keep(self, t)).cloned().collect();
+        if kept.len() == types.len() || kept.is_empty() {
+            return Narrowing::new();
+        }
+        let narrowed = match kept.len() {
+            1 => kept.remove(0),
+            _ => Type::new(TypeKind::Union(kept), current.span),
+        };
+        Narrowing::from([(name.to_string(), narrowed)])
+    }
+
+    /// A field's declared type in one shape; `None` when the shape
+    /// definitely lacks it, `unknown` when it cannot be told
+    fn field_type(&self, typ: &Type, key: &str) -> Option<Type> {
+        match self.property_lookup(typ, key) {
+            MemberLookup::Found(field) => Some(field),
+            MemberLookup::Missing => None,
+            _ => Some(unknown_type(typ.span)),
+        }
+    }
+}
+
+/// `name.key` or `name["key"]` on a plain variable
+fn field_access(expr: &Expression) -> Option<(&str, &str)> {
+    match &expr.kind {
+        ExpressionKind::Parenthesized(inner) => field_access(inner),
+        ExpressionKind::Member(object, member) => match &object.kind {
+            ExpressionKind::Identifier(name) => Some((name, &member.node)),
+            _ => None,
+        },
+        ExpressionKind::Index(object, index) => match (&object.kind, &index.kind) {
+            (ExpressionKind::Identifier(name), ExpressionKind::Literal(Literal::String(key))) => {
+                Some((name, key))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether a value of the field's type could equal the literal
+fn may_equal(field: &Type, literal: &Literal) -> bool {
+    match &field.kind {
+        TypeKind::Literal(value) => literals_equal(value, literal),
+        TypeKind::Union(types) => types.iter().any(|t| may_equal(t, literal)),
+        TypeKind::Nullable(inner) => matches!(literal, Literal::Nil) || may_equal(inner, literal),
+        TypeKind::Parenthesized(inner) => may_equal(inner, literal),
+        _ => true,
+    }
+}
+
+/// Whether every value of the field's type equals the literal
+fn must_equal(field: &Type, literal: &Literal) -> bool {
+    match &field.kind {
+        TypeKind::Literal(value) => literals_equal(value, literal),
+        TypeKind::Parenthesized(inner) => must_equal(inner, literal),
+        _ => false,
+    }
+}
+
+/// Whether a value of the type may be `nil` or `false`
+fn may_be_falsy(typ: &Type) -> bool {
+    match &typ.kind {
+        TypeKind::Primitive(primitive) => matches!(
+            primitive,
+            PrimitiveType::Boolean | PrimitiveType::Nil | PrimitiveType::Unknown
+        ),
+        TypeKind::Literal(literal) => matches!(literal, Literal::Nil | Literal::Boolean(false)),
+        TypeKind::Nullable(_) => true,
+        TypeKind::Union(types) => types.iter().any(may_be_falsy),
+        TypeKind::Parenthesized(inner) => may_be_falsy(inner),
+        _ => false,
+    }
+}
@@ -0,0 +1,188 @@
+use super::TypeChecker;
+use crate::ast::statement::{ForStatement, Statement, VariableDeclaration, VariableKind};
+use crate::ast::types::{PrimitiveType, Type, TypeKind};
+use crate::errors::TypeCheckError;
+use crate::features::LuaFeature;
+use crate::span::Span;
+
+impl TypeChecker {
+    /// A `using` value must be nil or closable. Lua's `<close>` only calls
+    /// `__close`; targets without it also fall back to a `close` method.
+    pub(super) fn check_closable(&mut self, decl: &VariableDeclaration, typ: &Type) {
+        let names: &[&str] = if self.has_close_attribute() {
+            &["__close"]
+        } else {
+            &["__close", "close"]
+        };
+        if self.is_closable(typ, names) == Some(false) {
+            let expected = if names.len() == 1 {
+                "'__close' metamethod"
+            } else {
+                "'__close' metamethod or 'close' method"
+            };
+            self.report_error(
+                decl.initializer.span,
+                TypeCheckError::NotClosable {
+                    type_name: typ.to_string(),
+                    expected: expected.to_string(),
+                },
+            );
+        }
+    }
+
+    /// Without `<close>`, the statements after a `using` run in a closure
+    /// that closes the value when it returns. Jumps out of the block, and
+    /// exports that must stay visible to the module's end, cannot leave it.
+    pub(super) fn check_using_scope(&mut self, rest: &[Statement]) {
+        if self.has_close_attribute() {
+            return;
+        }
+        let mut escapes = Vec::new();
+        scope_escapes(rest, &mut Vec::new(), 0, true, &mut escapes);
+        let target = self.config.compiler_options.target.to_string();
+        for (span, statement) in escapes {
+            self.report_error(
+                span,
+                TypeCheckError::UsingScopeEscape {
+                    statement: statement.to_string(),
+                    target: target.clone(),
+                },
+            );
+        }
+    }
+
+    fn has_close_attribute(&self) -> bool {
+        self.config
+            .compiler_options
+            .target
+            .supports(LuaFeature::LocalAttributes)
+    }
+
+    /// Whether values of a type have one of the `names` members, or `None`
+    /// when the type says too little to tell
+    fn is_closable(&self, typ: &Type, names: &[&str]) -> Option<bool> {
+        match &typ.kind {
+            TypeKind::Parenthesized(inner) | TypeKind::Nullable(inner) => {
+                self.is_closable(inner, names)
+            }
+            TypeKind::Union(types) => {
+                let mut closable = Some(true);
+                for typ in types {
+                    match self.is_closable(typ, names) {
+                        Some(false) => return Some(false),
+                        None => closable = None,
+                        Some(true) => {}
+                    }
+                }
+                closable
+            }
+            TypeKind::Primitive(PrimitiveType::Nil) => Some(true),
+            TypeKind::Primitive(PrimitiveType::Unknown | PrimitiveType::Table) => None,
+            TypeKind::Primitive(_)
+            | TypeKind::Literal(_)
+            | TypeKind::Array(_)
+            | TypeKind::Tuple(_)
+            | TypeKind::Function(_) => Some(false),
+            TypeKind::Reference(reference) => {
+                if names
+                    .iter()
+                    .any(|name| self.find_metamethod(typ, name).is_some())
+                {
+                    return Some(true);
+                }
+                if self.type_env.get_class(&reference.name.node).is_some() {
+                    return Some(false);
+                }
+                let members = self.property_names(typ)?;
+                Some(names.iter().any(|name| members.iter().any(|m| m == name)))
+            }
+            TypeKind::Object(_) => {
+                let members = self.property_names(typ)?;
+                Some(names.iter().any(|name| members.iter().any(|m| m == name)))
+            }
+            _ => None,
+        }
+    }
+}
+
+pub(super) fn is_using(statement: &Statement) -> bool {
+    matches!(statement, Statement::Variable(decl) if decl.kind == VariableKind::Using)
+}
+
+/// Statements that would leave the closure a `using` lowers to: `break`
+/// and `continue` out of it, and, directly in the block, `export`. A later
+/// `using` covers the rest of its own block.
+fn scope_escapes<'a>(
+    statements: &'a [Statement],
+    labels: &mut Vec<&'a str>,
+    loops: usize,
+    top: bool,
+    escapes: &mut Vec<(Span, &'static str)>,
+) {
+    for statement in statements {
+        match statement {
+            Statement::Break(jump) | Statement::Continue(jump) => {
+                let escapes_loop = match &jump.label {
+                    Some(label) => !labels.contains(&label.node.as_str()),
+                    None => loops == 0,
+                };
+                if escapes_loop {
+                    let keyword = match statement {
+                        Statement::Break(_) => "break",
+                        _ => "continue",
+                    };
+                    escapes.push((jump.span, keyword));
+                }
+            }
+            Statement::Export(export) if top => escapes.push((export.span, "export")),
+            Statement::If(if_stmt) => {
+                scope_escapes(
+                    &if_stmt.then_block.statements,
+                    labels,
+                    loops,
+                    false,
+                    escapes,
+                );
+                for else_if in &if_stmt.else_ifs {
+                    scope_escapes(&else_if.block.statements, labels, loops, false, escapes);
+                }
+                if let Some(block) = &if_stmt.else_block {
+                    scope_escapes(&block.statements, labels, loops, false, escapes);
+                }
+            }
+            Statement::Block(block) | Statement::Untyped(block) => {
+                scope_escapes(&block.statements, labels, loops, false, escapes)
+            }
+            Statement::While(_) | Statement::For(_) | Statement::Repeat(_) => scope_escapes(
+                loop_statements(statement),
+                labels,
+                loops + 1,
+                false,
+                escapes,
+            ),
+            Statement::Labeled(labeled) => {
+                labels.push(&labeled.label.node);
+                scope_escapes(
+                    loop_statements(&labeled.body),
+                    labels,
+                    loops + 1,
+                    false,
+                    escapes,
+                );
+                labels.pop();
+            }
+            statement if is_using(statement) => return,
+            _ => {}
+        }
+    }
+}
+
+fn loop_statements(statement: &Statement) -> &[Statement] {
+    match statement {
+        Statement::While(while_stmt) => &while_stmt.body.statements,
+        Statement::For(ForStatement::Numeric(numeric)) => &numeric.body.statements,
+        Statement::For(ForStatement::Generic(generic)) => &generic.body.statements,
+        Statement::Repeat(repeat) => &repeat.body.statements,
+        _ => &[],
+    }
+}
@@ -0,0 +1,271 @@
+use super::{unknown_type, TypeChecker};
+use crate::ast::expression::Literal;
+use crate::ast::statement::{IndexKeyType, IndexSignature, InterfaceMember, PropertySignature};
+use crate::ast::types::{ObjectType, ObjectTypeMember, PrimitiveType, Type, TypeKind};
+use crate::ast::Ident;
+use crate::errors::TypeCheckError;
+
+/// The built-in utility types and how many type arguments each takes.
+/// `Partial` is handled separately, since a partial value is one still
+/// being built rather than a new shape.
+const UTILITY_TYPES: &[(&str, usize)] = &[
+    ("Partial", 1),
+    ("Required", 1),
+    ("Pick", 2),
+    ("Omit", 2),
+    ("Record", 2),
+];
+
+impl TypeChecker {
+    /// The type arguments of a reference to a built-in utility type, unless
+    /// a declaration of the same name shadows it
+    fn utility_arguments<'a>(&self, typ: &'a Type) -> Option<(&'a str, &'a [Type])> {
+        let TypeKind::Reference(reference) = &typ.kind else {
+            return None;
+        };
+        let name = reference.name.node.as_str();
+        if !UTILITY_TYPES.iter().any(|(utility, _)| *utility == name)
+            || self.type_env.get_interface(name).is_some()
+            || self.type_env.get_class(name).is_some()
+            || self.type_env.get_alias(name).is_some()
+        {
+            return None;
+        }
+        Some((
+            name,
+            reference.type_arguments.as_deref().unwrap_or_default(),
+        ))
+    }
+
+    /// The table shape a `Required`, `Pick`, `Omit` or `Record` stands for.
+    /// Diagnostics keep printing the reference as written.
+    pub(super) fn expand_utility(&self, typ: &Type) -> Option<Type> {
+        let (name, arguments) = match &typ.kind {
+            TypeKind::Parenthesized(inner) => return self.expand_utility(inner),
+            _ => self.utility_arguments(typ)?,
+        };
+        let members = match (name, arguments) {
+            ("Required", [target]) => self
+                .shape_members(target)?
+                .into_iter()
+                .map(|member| match member {
+                    ObjectTypeMember::Property(prop) => {
+                        ObjectTypeMember::Property(PropertySignature {
+                            is_optional: false,
+                            ..prop
+                        })
+                    }
+                    member => member,
+                })
+                .collect(),
+            ("Pick" | "Omit", [target, keys]) => {
+                let keys = literal_keys(keys)?;
+                let keep = name == "Pick";
+                self.shape_members(target)?
+                    .into_iter()
+                    .filter(|member| match member {
+                        ObjectTypeMember::Property(prop) => keys.contains(&prop.name.node) == keep,
+                        ObjectTypeMember::Method(method) => {
+                            keys.contains(&method.name.node) == keep
+                        }
+                        ObjectTypeMember::Index(_) => !keep,
+                    })
+                    .collect()
+            }
+            ("Record", [keys, value]) => match literal_keys(keys) {
+                Some(keys) => keys
+                    .into_iter()
+                    .map(|key| {
+                        ObjectTypeMember::Property(PropertySignature {
+                            decorators: Vec::new(),
+                            is_readonly: false,
+                            name: Ident::new(key, typ.span),
+                            is_optional: false,
+                            type_annotation: value.clone(),
+                            span: typ.span,
+                        })
+                    })
+                    .collect(),
+                None => {
+                    let key_type = match without_parens(keys) {
+                        TypeKind::Primitive(PrimitiveType::String) => IndexKeyType::String,
+                        TypeKind::Primitive(PrimitiveType::Number | PrimitiveType::Integer) => {
+                            IndexKeyType::Number
+                        }
+                        _ => return None,
+                    };
+                    vec![ObjectTypeMember::Index(IndexSignature {
+                        key_name: Ident::new("key".to_string(), typ.span),
+                        key_type,
+                        value_type: value.clone(),
+                        span: typ.span,
+                    })]
+                }
+            },
+            _ => return None,
+        };
+        Some(Type::new(
+            TypeKind::Object(ObjectType {
+                members,
+                span: typ.span,
+            }),
+            typ.span,
+        ))
+    }
+
+    /// Members of an interface or table shape, with a generic interface's
+    /// type arguments applied
+    pub(super) fn shape_members(&self, typ: &Type) -> Option<Vec<ObjectTypeMember>> {
+        if let Some(expanded) = self.expand_utility(typ) {
+            return self.shape_members(&expanded);
+        }
+        match &typ.kind {
+            TypeKind::Parenthesized(inner) => self.shape_members(inner),
+            TypeKind::Object(object) => Some(object.members.clone()),
+            TypeKind::Reference(_) => {
+                let interface = self.interface_name(typ)?;
+                let (chain, complete) = self.type_env.interface_chain(&interface);
+                if !complete {
+                    return None;
+                }
+                let mut members: Vec<ObjectTypeMember> = Vec::new();
+                for member in chain.iter().flat_map(|decl| &decl.members) {
+                    let member = match member {
+                        InterfaceMember::Property(prop) => {
+                            ObjectTypeMember::Property(PropertySignature {
+                                type_annotation: self
+                                    .instantiate_member(typ, prop.type_annotation.clone()),
+                                ..prop.clone()
+                            })
+                        }
+                        InterfaceMember::Method(method) => ObjectTypeMember::Method(method.clone()),
+                        InterfaceMember::Index(index) => ObjectTypeMember::Index(index.clone()),
+                    };
+                    // Nearer declarations override inherited ones
+                    let name = member_name(&member);
+                    if name.is_none() || !members.iter().any(|m| member_name(m) == name) {
+                        members.push(member);
+                    }
+                }
+                Some(members)
+            }
+            _ => None,
+        }
+    }
+
+    /// Type of `object.member` on a utility type, which has exactly the
+    /// fields its expansion lists
+    pub(super) fn utility_member_type(
+        &mut self,
+        object_type: &Type,
+        member: &Ident,
+    ) -> Option<Type> {
+        let expanded = self.expand_utility(object_type)?;
+        let TypeKind::Object(object) = &expanded.kind else {
+            return None;
+        };
+        let mut string_index = None;
+        for candidate in &object.members {
+            match candidate {
+                ObjectTypeMember::Property(prop) if prop.name.node == member.node => {
+                    return Some(super::property_type(prop));
+                }
+                ObjectTypeMember::Method(method) if method.name.node == member.node => {
+                    return Some(super::method_type(method));
+                }
+                ObjectTypeMember::Index(index) if index.key_type == IndexKeyType::String => {
+                    string_index = Some(index.value_type.clone());
+                }
+                _ => {}
+            }
+        }
+        if string_index.is_none() {
+            let declared = object
+                .members
+                .iter()
+                .filter_map(|candidate| match candidate {
+                    ObjectTypeMember::Property(prop) => Some(prop.name.node.as_str()),
+                    ObjectTypeMember::Method(method) => Some(method.name.node.as_str()),
+                    _ => None,
+                });
+            self.report_unknown_property(member, object_type.to_string(), declared);
+        }
+        Some(string_index.unwrap_or_else(|| unknown_type(member.span)))
+    }
+
+    /// A utility type must get its number of type arguments, and the keys
+    /// given to `Pick` and `Omit` must name fields of the type
+    pub(super) fn check_utility_arguments(&mut self, typ: &Type) {
+        let Some((name, arguments)) = self.utility_arguments(typ) else {
+            return;
+        };
+        let expected = UTILITY_TYPES
+            .iter()
+            .find_map(|(utility, count)| (*utility == name).then_some(*count))
+            .unwrap_or_default();
+        if arguments.len() != expected {
+            self.report_error(
+                typ.span,
+                TypeCheckError::TypeArgumentCount {
+                    name: name.to_string(),
+                    expected: expected.to_string(),
+                    actual: arguments.len(),
+                },
+            );
+            return;
+        }
+
+        let ("Pick" | "Omit", [target, keys]) = (name, arguments) else {
+            return;
+        };
+        let (Some(members), Some(keys)) = (self.shape_members(target), literal_keys(keys)) else {
+            return;
+        };
+        for key in keys {
+            if !members
+                .iter()
+                .any(|member| member_name(member) == Some(key.as_str()))
+            {
+                self.report_error(
+                    arguments[1].span,
+                    TypeCheckError::ConstraintNotSatisfied {
+                        argument: format!("\"{}\"", key),
+                        constraint: format!("keyof {}", target),
+                        parameter: "K".to_string(),
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// The names in a key type made of string literals, such as `"a" | "b"`
+fn literal_keys(keys: &Type) -> Option<Vec<String>> {
+    match &keys.kind {
+        TypeKind::Literal(Literal::String(key)) => Some(vec![key.clone()]),
+        TypeKind::Parenthesized(inner) => literal_keys(inner),
+        TypeKind::Union(types) => {
+            let mut names = Vec::new();
+            for typ in types {
+                names.extend(literal_keys(typ)?);
+            }
+            Some(names)
+        }
+        _ => None,
+    }
+}
+
+fn member_name(member: &ObjectTypeMember) -> Option<&str> {
+    match member {
+        ObjectTypeMember::Property(prop) => Some(&prop.name.node),
+        ObjectTypeMember::Method(method) => Some(&method.name.node),
+        ObjectTypeMember::Index(_) => None,
+    }
+}
+
+fn without_parens(typ: &Type) -> &TypeKind {
+    match &typ.kind {
+        TypeKind::Parenthesized(inner) => without_parens(inner),
+        kind => kind,
+    }
+}
@@ -0,0 +1,156 @@
+use super::inference::join_types;
+use super::{unknown_type, SymbolKind, TypeChecker};
+use crate::ast::expression::{Expression, ExpressionKind};
+use crate::ast::pattern::{MultiplePattern, MultipleRest, Pattern};
+use crate::ast::types::{PrimitiveType, Type, TypeKind};
+use crate::errors::TypeCheckError;
+use crate::span::Span;
+
+impl TypeChecker {
+    /// Bind `a, b, ...rest` to the values an expression produces: a call
+    /// returning `(T, U...)` gives `a: T`, `b: U` and `rest: U[]`
+    pub(super) fn declare_values(
+        &mut self,
+        pattern: &MultiplePattern,
+        kind: SymbolKind,
+        values: &Type,
+    ) {
+        let (fixed, variadic) = value_list(values);
+        for (i, name) in pattern.names.iter().enumerate() {
+            let typ = match fixed.get(i).or(variadic.as_ref()) {
+                Some(typ) => typ.clone(),
+                None => Type::new(TypeKind::Primitive(PrimitiveType::Nil), name.span),
+            };
+            self.declare_symbol(&name.node, kind, typ, name.span);
+        }
+        if variadic.is_none() && pattern.names.len() > fixed.len() {
+            self.report_error(
+                pattern.span,
+                TypeCheckError::TooManyValueNames {
+                    values: fixed.len(),
+                    names: pattern.names.len(),
+                },
+            );
+        }
+
+        if let Some(MultipleRest::Capture(name)) = &pattern.rest {
+            let remaining: Vec<Type> = fixed
+                .into_iter()
+                .skip(pattern.names.len())
+                .chain(variadic)
+                .collect();
+            let element = if remaining.is_empty() {
+                unknown_type(name.span)
+            } else {
+                join_types(remaining, name.span)
+            };
+            let typ = Type::new(TypeKind::Array(Box::new(element)), name.span);
+            self.declare_symbol(&name.node, kind, typ, name.span);
+        }
+    }
+
+    /// Whether the values a function returns fit its declared list of
+    /// return values. Missing values are nil; extra ones need a variadic
+    /// slot to go in.
+    pub(super) fn values_assignable(&self, source: &[Type], target: &[Type]) -> bool {
+        let (source_fixed, source_variadic) = split_variadic(source);
+        let (target_fixed, target_variadic) = split_variadic(target);
+        let nil = Type::new(TypeKind::Primitive(PrimitiveType::Nil), Span::dummy());
+        for i in 0..source_fixed.len().max(target_fixed.len()) {
+            let Some(expected) = target_fixed.get(i).or(target_variadic) else {
+                return false;
+            };
+            let value = source_fixed.get(i).or(source_variadic).unwrap_or(&nil);
+            if !self.is_assignable(value, expected) {
+                return false;
+            }
+        }
+        match (source_variadic, target_variadic) {
+            (Some(value), Some(expected)) => self.is_assignable(value, expected),
+            _ => true,
+        }
+    }
+
+    /// Why `values_assignable` failed, for `mismatch_chain`
+    pub(super) fn values_mismatch(
+        &self,
+        source: &[Type],
+        target: &[Type],
+        chain: &mut Vec<String>,
+    ) {
+        let (source_fixed, source_variadic) = split_variadic(source);
+        let (target_fixed, target_variadic) = split_variadic(target);
+        let nil = Type::new(TypeKind::Primitive(PrimitiveType::Nil), Span::dummy());
+        for i in 0..source_fixed.len().max(target_fixed.len()) {
+            let Some(expected) = target_fixed.get(i).or(target_variadic) else {
+                chain.push(format!("only {} values are expected", target_fixed.len()));
+                return;
+            };
+            let value = source_fixed.get(i).or(source_variadic).unwrap_or(&nil);
+            if !self.is_assignable(value, expected) {
+                let context = format!("value {}: ", i + 1);
+                return self.explain_mismatch(value, expected, &context, chain);
+            }
+        }
+        if let (Some(value), Some(expected)) = (source_variadic, target_variadic) {
+            self.explain_mismatch(value, expected, "variadic values: ", chain);
+        }
+    }
+}
+
+/// The type a variable declaration binds its names to. A call produces
+/// all of its return values, but only the first when bound to one name;
+/// any other expression is a single value.
+pub(super) fn bound_values(pattern: &Pattern, initializer: &Expression, typ: Type) -> Type {
+    let produces_values = matches!(
+        initializer.kind,
+        ExpressionKind::Call(..) | ExpressionKind::MethodCall(..)
+    );
+    match (pattern, produces_values) {
+        (Pattern::Multiple(_), true) => typ,
+        (Pattern::Multiple(_), false) => Type::new(TypeKind::Tuple(vec![typ]), initializer.span),
+        (_, true) => first_value(typ),
+        (_, false) => typ,
+    }
+}
+
+fn first_value(typ: Type) -> Type {
+    match typ.kind {
+        TypeKind::Tuple(mut types) if !types.is_empty() => match types.remove(0) {
+            Type {
+                kind: TypeKind::Variadic(inner),
+                ..
+            } => *inner,
+            first => first,
+        },
+        _ => typ,
+    }
+}
+
+/// A type as a list of values: the fixed ones, then the type of any number
+/// of further values. `void` is no value, and `unknown` may be any number.
+fn value_list(typ: &Type) -> (Vec<Type>, Option<Type>) {
+    match &typ.kind {
+        TypeKind::Tuple(types) => {
+            let (fixed, variadic) = split_variadic(types);
+            (fixed.to_vec(), variadic.cloned())
+        }
+        TypeKind::Parenthesized(inner) => value_list(inner),
+        TypeKind::Primitive(PrimitiveType::Void) => (Vec::new(), None),
+        TypeKind::Primitive(PrimitiveType::Unknown) => (Vec::new(), Some(typ.clone())),
+        _ => (vec![typ.clone()], None),
+    }
+}
+
+fn split_variadic(types: &[Type]) -> (&[Type], Option<&Type>) {
+    match types.split_last() {
+        Some((
+            Type {
+                kind: TypeKind::Variadic(inner),
+                ..
+            },
+            fixed,
+        )) => (fixed, Some(inner)),
+        _ => (types, None),
+    }
+}
@@ -0,0 +1,136 @@
+use super::TypeChecker;
+use crate::ast::attributes::{find_attribute, weak_mode};
+use crate::ast::expression::{Expression, ExpressionKind};
+use crate::ast::pattern::Pattern;
+use crate::ast::statement::{ClassDeclaration, ClassMember, Decorator, VariableDeclaration};
+use crate::ast::types::{PrimitiveType, Type, TypeKind};
+use crate::ast::Ident;
+use crate::errors::TypeCheckError;
+
+impl TypeChecker {
+    /// The mode of a `@weak` marker, reporting a malformed one
+    fn check_weak_marker(&mut self, decorators: &[Decorator]) -> Option<String> {
+        match weak_mode(decorators)? {
+            Ok(mode) => Some(mode.to_string()),
+            Err(span) => {
+                self.report_error(span, TypeCheckError::InvalidWeakMode);
+                None
+            }
+        }
+    }
+
+    /// Remember a `@weak` variable so reads through it account for entries
+    /// the collector may have cleared
+    pub(super) fn declare_weak_table(&mut self, decl: &VariableDeclaration) {
+        let Some(mode) = self.check_weak_marker(&decl.decorators) else {
+            return;
+        };
+        if let Pattern::Identifier(name) = &decl.pattern {
+            self.weak_tables.insert(name.node.clone(), mode);
+        }
+    }
+
+    /// Instance fields have string keys, which are never collected, so only
+    /// weak values change anything for a class
+    pub(super) fn check_weak_class(&mut self, class: &ClassDeclaration) {
+        if self.check_weak_marker(&class.decorators).as_deref() == Some("k") {
+            let span =
+                find_attribute(&class.decorators, "weak").map_or(class.name.span, |d| d.span);
+            self.report_warning(
+                span,
+                "TL3001",
+                "@weak(\"k\") has no effect on a class: instance fields have string keys, which are never collected",
+            );
+        }
+    }
+
+    /// `t[key]` on a weak table is nil once the collector clears the entry,
+    /// which it can only do for a collectable key or value
+    pub(super) fn weak_index_read(
+        &self,
+        object: &Expression,
+        index_type: &Type,
+        value: Type,
+    ) -> Type {
+        let Some(mode) = self.weak_table_mode(object) else {
+            return value;
+        };
+        let cleared = (mode.contains('k') && collectable(index_type))
+            || (mode.contains('v') && collectable(&value));
+        possibly_cleared(value, cleared)
+    }
+
+    /// `t.name` has a string key, so only weak values can clear it. On an
+    /// instance of a weak class that only applies to its fields; methods
+    /// live on the class.
+    pub(super) fn weak_member_read(
+        &self,
+        object: &Expression,
+        object_type: &Type,
+        member: &Ident,
+        value: Type,
+    ) -> Type {
+        let mode = match self.weak_table_mode(object) {
+            Some(mode) => mode.to_string(),
+            None => match self.weak_class(object_type) {
+                Some((class, mode)) if has_instance_field(class, &member.node) => mode.to_string(),
+                _ => return value,
+            },
+        };
+        let cleared = mode.contains('v') && collectable(&value);
+        possibly_cleared(value, cleared)
+    }
+
+    fn weak_table_mode(&self, object: &Expression) -> Option<&str> {
+        match &object.kind {
+            ExpressionKind::Identifier(name) => self.weak_tables.get(name).map(String::as_str),
+            ExpressionKind::Parenthesized(inner) => self.weak_table_mode(inner),
+            _ => None,
+        }
+    }
+
+    fn weak_class(&self, typ: &Type) -> Option<(&ClassDeclaration, &str)> {
+        let TypeKind::Reference(reference) = &typ.kind else {
+            return None;
+        };
+        let class = self.type_env.get_class(&reference.name.node)?;
+        let mode = weak_mode(&class.decorators)?.ok()?;
+        Some((class, mode))
+    }
+}
+
+fn has_instance_field(class: &ClassDeclaration, name: &str) -> bool {
+    class.members.iter().any(|member| {
+        matches!(member, ClassMember::Property(prop) if !prop.is_static && prop.name.node == name)
+    })
+}
+
+/// Whether the collector can reclaim values of this type. Strings, numbers
+/// and booleans are values, not objects, and are never cleared.
+fn collectable(typ: &Type) -> bool {
+    match &typ.kind {
+        TypeKind::Primitive(primitive) => !matches!(
+            primitive,
+            PrimitiveType::String
+                | PrimitiveType::Number
+                | PrimitiveType::Integer
+                | PrimitiveType::Boolean
+                | PrimitiveType::Nil
+                | PrimitiveType::Unknown
+                | PrimitiveType::Never
+                | PrimitiveType::Void
+        ),
+        TypeKind::Literal(_) => false,
+        TypeKind::Parenthesized(inner) | TypeKind::Nullable(inner) => collectable(inner),
+        TypeKind::Union(types) => types.iter().any(collectable),
+        _ => true,
+    }
+}
+
+fn possibly_cleared(value: Type, cleared: bool) -> Type {
+    if !cleared || matches!(value.kind, TypeKind::Nullable(_)) {
+        return value;
+    }
+    let span = value.span;
+    Type::new(TypeKind::Nullable(Box::new(value)), span)
+}
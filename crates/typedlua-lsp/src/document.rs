@@ -0,0 +1,569 @@
+use crate::line_index::LineIndex;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tower_lsp::lsp_types::{
+    self, CompletionItem, CompletionItemKind, DiagnosticRelatedInformation, DiagnosticSeverity,
+    DocumentSymbol, Hover, HoverContents, Location, MarkupContent, MarkupKind, NumberOrString,
+    SymbolKind, Url,
+};
+use typedlua_core::ast::pattern::{ArrayPatternElement, MultipleRest, Pattern};
+use typedlua_core::ast::statement::{
+    ClassMember, DeclareKind, ExportKind, ImportClause, InterfaceMember, Statement,
+};
+use typedlua_core::ast::{Ident, Program};
+use typedlua_core::build::{parse_source, ParsedModule};
+use typedlua_core::completion::{self, CompletionKind};
+use typedlua_core::config::CompilerConfig;
+use typedlua_core::diagnostics::{
+    CollectingDiagnosticHandler, Diagnostic, DiagnosticHandler, DiagnosticLevel, RelatedSpan,
+};
+use typedlua_core::fs::{FileSystem, RealFileSystem};
+use typedlua_core::lint::{Analysis, Binding, BindingKind, Linter};
+use typedlua_core::resolve::ModuleResolver;
+use typedlua_core::span::Span;
+use typedlua_core::typechecker::TypeChecker;
+
+/// An open file and what was last learned from it
+pub struct Document {
+    pub text: String,
+    pub index: LineIndex,
+    /// The file on disk, which imports are resolved against
+    pub path: Option<PathBuf>,
+    /// Names declared by the last version that parsed, kept for completion
+    /// while the text is mid-edit
+    names: Vec<Binding>,
+}
+
+/// Lex and parse a module. The parser recovers from syntax errors, so a
+/// program comes back unless lexing stopped, which is reported to `handler`
+/// like any other error.
+fn parse(text: &str, handler: Arc<dyn DiagnosticHandler>) -> Option<ParsedModule> {
+    parse_source(text, handler).ok()
+}
+
+impl Document {
+    pub fn new(text: String, path: Option<PathBuf>) -> Self {
+        Document {
+            index: LineIndex::new(&text),
+            text,
+            path,
+            names: Vec::new(),
+        }
+    }
+
+    fn resolver() -> ModuleResolver {
+        ModuleResolver::new(Arc::new(RealFileSystem::new()))
+    }
+
+    fn checker(
+        &self,
+        config: &Arc<CompilerConfig>,
+        handler: Arc<dyn DiagnosticHandler>,
+    ) -> TypeChecker {
+        let mut checker = TypeChecker::new(config.clone(), handler);
+        if let Some(path) = &self.path {
+            checker.set_module_resolver(Self::resolver(), path);
+        }
+        checker
+    }
+
+    /// Check the text as the build would: syntax errors first, then type
+    /// errors, then lint findings when the build runs the lint rules
+    pub fn check(&mut self, config: &Arc<CompilerConfig>) -> Vec<lsp_types::Diagnostic> {
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        if let Some(parsed) = parse(&self.text, handler.clone()) {
+            self.names = Analysis::new(&parsed.program).bindings;
+            if !handler.has_errors() {
+                let mut checker = self.checker(config, handler.clone());
+                checker.set_directives(&parsed.directives);
+                let typed = checker.check_program(&parsed.program).is_ok();
+                if typed && config.lint.on_build {
+                    let _ = Linter::for_config(config).lint(
+                        &parsed.program,
+                        &parsed.directives,
+                        &parsed.comments,
+                        handler.as_ref(),
+                    );
+                }
+            }
+        }
+        handler
+            .get_diagnostics()
+            .into_iter()
+            .map(|diagnostic| self.diagnostic(diagnostic))
+            .collect()
+    }
+
+    fn diagnostic(&self, diagnostic: Diagnostic) -> lsp_types::Diagnostic {
+        let severity = match diagnostic.level {
+            DiagnosticLevel::Error => DiagnosticSeverity::ERROR,
+            DiagnosticLevel::Warning => DiagnosticSeverity::WARNING,
+            DiagnosticLevel::Info => DiagnosticSeverity::INFORMATION,
+        };
+        let related: Vec<DiagnosticRelatedInformation> = diagnostic
+            .related
+            .iter()
+            .filter_map(|related| self.related_information(related))
+            .collect();
+        lsp_types::Diagnostic {
+            range: self.index.range(diagnostic.span),
+            severity: Some(severity),
+            code: diagnostic.code.map(NumberOrString::String),
+            source: Some("typedlua".to_string()),
+            message: diagnostic.message,
+            related_information: (!related.is_empty()).then_some(related),
+            ..lsp_types::Diagnostic::default()
+        }
+    }
+
+    /// A related span as a location the editor can link to, in this
+    /// document or the file it names. Unsaved documents have no location.
+    fn related_information(&self, related: &RelatedSpan) -> Option<DiagnosticRelatedInformation> {
+        let (path, range) = match &related.file {
+            Some(file) => {
+                let text = RealFileSystem::new().read_file(file).ok()?;
+                (file.as_path(), LineIndex::new(&text).range(related.span))
+            }
+            None => (self.path.as_deref()?, self.index.range(related.span)),
+        };
+        let uri = Url::from_file_path(std::fs::canonicalize(path).ok()?).ok()?;
+        Some(DiagnosticRelatedInformation {
+            location: Location::new(uri, range),
+            message: related.message.clone(),
+        })
+    }
+
+    /// The type of the innermost expression or declared name at a
+    /// character offset, as the checker saw it there
+    pub fn hover(&self, offset: usize, config: &Arc<CompilerConfig>) -> Option<Hover> {
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let parsed = parse(&self.text, handler.clone())?;
+        let mut checker = self.checker(config, handler);
+        checker.set_directives(&parsed.directives);
+        checker.probe_offset(offset);
+        let _ = checker.check_program(&parsed.program);
+
+        let (span, typ) = checker.probed_type()?;
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```typedlua\n{}\n```", typ),
+            }),
+            range: Some(self.index.range(*span)),
+        })
+    }
+
+    /// Where the name at a character offset is declared. An imported name
+    /// leads to its declaration in the module it comes from.
+    pub fn definition(&self, uri: &Url, offset: usize) -> Option<Location> {
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let parsed = parse(&self.text, handler)?;
+        let analysis = Analysis::new(&parsed.program);
+
+        let contains = |span: &Span| span.start <= offset && offset < span.end;
+        let binding = analysis
+            .references
+            .iter()
+            .find(|reference| contains(&reference.span))
+            .map(|reference| &analysis.bindings[reference.binding])
+            .or_else(|| {
+                analysis
+                    .bindings
+                    .iter()
+                    .find(|binding| contains(&binding.span))
+            })?;
+
+        if binding.kind == BindingKind::Import {
+            if let Some(location) = self.imported_definition(&parsed.program, binding.span) {
+                return Some(location);
+            }
+        }
+        Some(Location::new(uri.clone(), self.index.range(binding.span)))
+    }
+
+    fn imported_definition(&self, program: &Program, local: Span) -> Option<Location> {
+        let (source, imported) = program.statements.iter().find_map(|statement| {
+            let Statement::Import(import) = statement else {
+                return None;
+            };
+            match &import.clause {
+                ImportClause::Default(name) | ImportClause::Namespace(name)
+                    if name.span == local =>
+                {
+                    Some((import.source.clone(), None))
+                }
+                ImportClause::Named(specifiers) => specifiers.iter().find_map(|specifier| {
+                    let name = specifier.local.as_ref().unwrap_or(&specifier.imported);
+                    (name.span == local)
+                        .then(|| (import.source.clone(), Some(specifier.imported.node.clone())))
+                }),
+                _ => None,
+            }
+        })?;
+
+        let module = Self::resolver().resolve(self.path.as_deref()?, &source)?;
+        let path = module.path();
+        let uri = Url::from_file_path(std::fs::canonicalize(path).ok()?).ok()?;
+        let text = RealFileSystem::new().read_file(path).ok()?;
+        let span = imported
+            .and_then(|name| top_level_declaration(&text, &name))
+            .unwrap_or(Span::new(0, 0, 1, 1));
+        Some(Location::new(uri, LineIndex::new(&text).range(span)))
+    }
+
+    /// Module paths inside an import string, and otherwise the names in
+    /// scope at a character offset
+    pub fn completions(&self, offset: usize) -> Vec<CompletionItem> {
+        let importer = self.path.as_deref().unwrap_or(Path::new(""));
+        let byte_offset = self.index.byte_offset(offset);
+        if let Some(paths) =
+            completion::complete(&self.text, byte_offset, importer, &Self::resolver())
+        {
+            return paths
+                .into_iter()
+                .map(|path| CompletionItem {
+                    kind: Some(match path.kind {
+                        CompletionKind::Module => CompletionItemKind::FILE,
+                        CompletionKind::Directory => CompletionItemKind::FOLDER,
+                    }),
+                    label: path.label,
+                    ..CompletionItem::default()
+                })
+                .collect();
+        }
+
+        let mut items: Vec<CompletionItem> = Vec::new();
+        let visible = |binding: &&Binding| {
+            binding
+                .scope
+                .is_none_or(|scope| scope.start <= offset && offset <= scope.end)
+        };
+        for binding in self.names.iter().filter(visible) {
+            if items.iter().any(|item| item.label == binding.name) {
+                continue;
+            }
+            let kind = match binding.kind {
+                BindingKind::Function => CompletionItemKind::FUNCTION,
+                BindingKind::Declaration => CompletionItemKind::CLASS,
+                BindingKind::Import => CompletionItemKind::MODULE,
+                BindingKind::Const | BindingKind::Using => CompletionItemKind::CONSTANT,
+                _ => CompletionItemKind::VARIABLE,
+            };
+            items.push(CompletionItem {
+                label: binding.name.clone(),
+                kind: Some(kind),
+                ..CompletionItem::default()
+            });
+        }
+        items
+    }
+
+    /// The declarations of the module as an outline: classes, interfaces,
+    /// enums and namespaces with their members nested inside
+    pub fn symbols(&self) -> Vec<DocumentSymbol> {
+        let handler = Arc::new(CollectingDiagnosticHandler::new());
+        let Some(parsed) = parse(&self.text, handler) else {
+            return Vec::new();
+        };
+        let mut symbols = Vec::new();
+        for statement in &parsed.program.statements {
+            self.statement_symbols(statement, &mut symbols);
+        }
+        symbols
+    }
+
+    fn statement_symbols(&self, statement: &Statement, symbols: &mut Vec<DocumentSymbol>) {
+        let span = statement.span();
+        match statement {
+            Statement::Variable(decl) => {
+                let mut names = Vec::new();
+                bound_names(&decl.pattern, &mut names);
+                for name in names {
+                    symbols.push(self.symbol(name, SymbolKind::VARIABLE, span, Vec::new()));
+                }
+            }
+            Statement::Function(func) => {
+                symbols.push(self.symbol(&func.name, SymbolKind::FUNCTION, span, Vec::new()))
+            }
+            Statement::Class(class) => {
+                let members = class
+                    .members
+                    .iter()
+                    .filter_map(|member| {
+                        let (name, kind, span) = match member {
+                            ClassMember::Property(prop) if prop.is_const => {
+                                (&prop.name, SymbolKind::CONSTANT, prop.span)
+                            }
+                            ClassMember::Property(prop) => {
+                                (&prop.name, SymbolKind::PROPERTY, prop.span)
+                            }
+                            ClassMember::Method(method) => {
+                                (&method.name, SymbolKind::METHOD, method.span)
+                            }
+                            ClassMember::Getter(getter) => {
+                                (&getter.name, SymbolKind::PROPERTY, getter.span)
+                            }
+                            ClassMember::Setter(setter) => {
+                                (&setter.name, SymbolKind::PROPERTY, setter.span)
+                            }
+                            ClassMember::Constructor(ctor) => {
+                                let name = Ident::new("constructor".to_string(), ctor.span);
+                                return Some(self.symbol(
+                                    &name,
+                                    SymbolKind::CONSTRUCTOR,
+                                    ctor.span,
+                                    Vec::new(),
+                                ));
+                            }
+                            ClassMember::StaticBlock(_) => return None,
+                        };
+                        Some(self.symbol(name, kind, span, Vec::new()))
+                    })
+                    .collect();
+                symbols.push(self.symbol(&class.name, SymbolKind::CLASS, span, members));
+            }
+            Statement::Interface(interface) => {
+                let members = interface
+                    .members
+                    .iter()
+                    .filter_map(|member| match member {
+                        InterfaceMember::Property(prop) => Some(self.symbol(
+                            &prop.name,
+                            SymbolKind::PROPERTY,
+                            prop.span,
+                            Vec::new(),
+                        )),
+                        InterfaceMember::Method(method) => Some(self.symbol(
+                            &method.name,
+                            SymbolKind::METHOD,
+                            method.span,
+                            Vec::new(),
+                        )),
+                        InterfaceMember::Index(_) => None,
+                    })
+                    .collect();
+                symbols.push(self.symbol(&interface.name, SymbolKind::INTERFACE, span, members));
+            }
+            Statement::TypeAlias(alias) => {
+                symbols.push(self.symbol(&alias.name, SymbolKind::TYPE_PARAMETER, span, Vec::new()))
+            }
+            Statement::Enum(decl) => {
+                let members = decl
+                    .members
+                    .iter()
+                    .map(|member| {
+                        self.symbol(
+                            &member.name,
+                            SymbolKind::ENUM_MEMBER,
+                            member.span,
+                            Vec::new(),
+                        )
+                    })
+                    .collect();
+                symbols.push(self.symbol(&decl.name, SymbolKind::ENUM, span, members));
+            }
+            Statement::Namespace(namespace) => {
+                let mut members = Vec::new();
+                for statement in &namespace.body.statements {
+                    self.statement_symbols(statement, &mut members);
+                }
+                symbols.push(self.symbol(&namespace.name, SymbolKind::NAMESPACE, span, members));
+            }
+            Statement::Declare(decl) => match &decl.kind {
+                DeclareKind::Function(func) => {
+                    symbols.push(self.symbol(&func.name, SymbolKind::FUNCTION, span, Vec::new()))
+                }
+                DeclareKind::Variable(var) => {
+                    symbols.push(self.symbol(&var.name, SymbolKind::VARIABLE, span, Vec::new()))
+                }
+            },
+            Statement::Export(export) => {
+                if let ExportKind::Declaration(decl) = &export.kind {
+                    self.statement_symbols(decl, symbols);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn symbol(
+        &self,
+        name: &Ident,
+        kind: SymbolKind,
+        span: Span,
+        children: Vec<DocumentSymbol>,
+    ) -> DocumentSymbol {
+        #[allow(deprecated)]
+        DocumentSymbol {
+            name: name.node.clone(),
+            detail: None,
+            kind,
+            tags: None,
+            deprecated: None,
+            range: self.index.range(span),
+            selection_range: self.index.range(name.span),
+            children: (!children.is_empty()).then_some(children),
+        }
+    }
+}
+
+/// The names a declaration pattern binds
+fn bound_names<'a>(pattern: &'a Pattern, names: &mut Vec<&'a Ident>) {
+    match pattern {
+        Pattern::Identifier(name) => names.push(name),
+        Pattern::Literal(..) | Pattern::Wildcard(_) => {}
+        Pattern::Multiple(multiple) => {
+            names.extend(&multiple.names);
+            if let Some(MultipleRest::Capture(rest)) = &multiple.rest {
+                names.push(rest);
+            }
+        }
+        Pattern::Array(array) => {
+            for element in &array.elements {
+                match element {
+                    ArrayPatternElement::Pattern(pattern) => bound_names(pattern, names),
+                    ArrayPatternElement::Rest(name) => names.push(name),
+                    ArrayPatternElement::Hole => {}
+                }
+            }
+        }
+        Pattern::Object(object) => {
+            for property in &object.properties {
+                match &property.value {
+                    Some(pattern) => bound_names(pattern, names),
+                    None => names.push(&property.key),
+                }
+            }
+        }
+    }
+}
+
+/// The name of the top-level declaration `name` in a module's source
+fn top_level_declaration(text: &str, name: &str) -> Option<Span> {
+    let handler = Arc::new(CollectingDiagnosticHandler::new());
+    let parsed = parse(text, handler)?;
+    let analysis = Analysis::new(&parsed.program);
+    analysis
+        .bindings
+        .iter()
+        .find(|binding| binding.name == name && binding.exported)
+        .map(|binding| binding.span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::{Position, Range};
+
+    const SOURCE: &str = r#"interface Named {
+    name: string
+}
+
+export class User implements Named {
+    name: string = "guest"
+
+    greet(): string {
+        return "hi " .. self.name
+    }
+}
+
+local count = 1
+count = count + 1
+function describe(user: User): string
+    return user.greet()
+end
+"#;
+
+    #[test]
+    fn test_hover_and_definition() {
+        let document = Document::new(SOURCE.to_string(), None);
+        let config = Arc::new(CompilerConfig::default());
+        let uri = Url::parse("file:///project/user.tl").unwrap();
+
+        let offset = document.index.offset(Position::new(13, 10));
+        let hover = document.hover(offset, &config).expect("No hover");
+        let HoverContents::Markup(markup) = hover.contents else {
+            panic!("Expected markdown");
+        };
+        assert_eq!(markup.value, "```typedlua\nnumber\n```");
+
+        let definition = document
+            .definition(&uri, document.index.offset(Position::new(13, 9)))
+            .expect("No definition");
+        assert_eq!(
+            definition.range,
+            Range::new(Position::new(12, 6), Position::new(12, 11))
+        );
+        assert!(document.definition(&uri, 0).is_none());
+    }
+
+    #[test]
+    fn test_diagnostics_completions_and_symbols() {
+        let mut document = Document::new(format!("{}local broken: string = count\n", SOURCE), None);
+        let diagnostics = document.check(&Arc::new(CompilerConfig::default()));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start, Position::new(17, 23));
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+
+        let labels: Vec<String> = document
+            .completions(0)
+            .into_iter()
+            .map(|item| item.label)
+            .collect();
+        assert_eq!(labels, vec!["User", "describe", "count", "broken"]);
+        let in_describe = document.index.offset(Position::new(15, 4));
+        let labels: Vec<String> = document
+            .completions(in_describe)
+            .into_iter()
+            .map(|item| item.label)
+            .collect();
+        assert!(labels.contains(&"user".to_string()));
+
+        let symbols = document.symbols();
+        let outline: Vec<(&str, SymbolKind, usize)> = symbols
+            .iter()
+            .map(|symbol| {
+                let children = symbol.children.as_ref().map_or(0, Vec::len);
+                (symbol.name.as_str(), symbol.kind, children)
+            })
+            .collect();
+        assert_eq!(
+            outline,
+            vec![
+                ("Named", SymbolKind::INTERFACE, 1),
+                ("User", SymbolKind::CLASS, 2),
+                ("count", SymbolKind::VARIABLE, 0),
+                ("describe", SymbolKind::FUNCTION, 0),
+                ("broken", SymbolKind::VARIABLE, 0),
+            ]
+        );
+        // `describe` ends with its `end`, not at the next statement
+        assert_eq!(
+            symbols[3].range,
+            Range::new(Position::new(14, 0), Position::new(16, 3))
+        );
+    }
+
+    #[test]
+    fn test_unterminated_strings_are_diagnosed() {
+        let config = Arc::new(CompilerConfig::default());
+        for (text, column) in [
+            ("local a = 1\nlocal s = \"open\n", 10),
+            ("local t = `open ${a}", 10),
+        ] {
+            let line = text.matches('\n').count().saturating_sub(1) as u32;
+            let mut document = Document::new(text.to_string(), None);
+            let diagnostics = document.check(&config);
+            assert_eq!(diagnostics.len(), 1, "{}", text);
+            assert_eq!(
+                diagnostics[0].range,
+                Range::new(Position::new(line, column), Position::new(line, column + 1))
+            );
+            assert_eq!(
+                diagnostics[0].code,
+                Some(NumberOrString::String("TL1004".to_string()))
+            );
+            assert_eq!(diagnostics[0].message, "Unterminated string literal");
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use tower_lsp::lsp_types::{Position, Range};
+use typedlua_core::span::Span;
+
+/// Converts between the compiler's character offsets and the editor's
+/// positions, whose columns count UTF-16 code units
+pub struct LineIndex {
+    chars: Vec<char>,
+    /// Character offset at which each line starts
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let chars: Vec<char> = text.chars().collect();
+        let mut line_starts = vec![0];
+        for (i, c) in chars.iter().enumerate() {
+            if *c == '\n' {
+                line_starts.push(i + 1);
+            }
+        }
+        LineIndex { chars, line_starts }
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.chars.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let character: usize = self.chars[self.line_starts[line]..offset]
+            .iter()
+            .map(|c| c.len_utf16())
+            .sum();
+        Position::new(line as u32, character as u32)
+    }
+
+    pub fn range(&self, span: Span) -> Range {
+        Range::new(self.position(span.start), self.position(span.end))
+    }
+
+    /// The character offset of a position, clamped to its line
+    pub fn offset(&self, position: Position) -> usize {
+        let Some(&start) = self.line_starts.get(position.line as usize) else {
+            return self.chars.len();
+        };
+        let mut units = 0;
+        let mut offset = start;
+        while offset < self.chars.len() && self.chars[offset] != '\n' {
+            if units >= position.character as usize {
+                break;
+            }
+            units += self.chars[offset].len_utf16();
+            offset += 1;
+        }
+        offset
+    }
+
+    /// The byte offset of a character offset
+    pub fn byte_offset(&self, offset: usize) -> usize {
+        self.chars[..offset.min(self.chars.len())]
+            .iter()
+            .map(|c| c.len_utf8())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions_count_utf16_units() {
+        let index = LineIndex::new("local a = 1\nlocal é = \"😀\" .. x\n");
+        assert_eq!(index.position(0), Position::new(0, 0));
+        assert_eq!(index.position(12), Position::new(1, 0));
+        // After the emoji, which takes two UTF-16 units
+        assert_eq!(index.position(24), Position::new(1, 13));
+        assert_eq!(index.offset(Position::new(1, 13)), 24);
+        assert_eq!(index.offset(Position::new(0, 99)), 11);
+        assert_eq!(index.byte_offset(24), 12 + 16);
+    }
+}
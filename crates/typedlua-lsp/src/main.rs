@@ -1,3 +1,18 @@
-fn main() {
-    println!("TypedLua LSP - Coming soon!");
+//! TypedLua language server. Speaks the Language Server Protocol over
+//! stdio: diagnostics as files are opened and edited, hover types,
+//! go-to-definition, completion and document symbols.
+
+mod document;
+mod line_index;
+mod server;
+
+use server::Backend;
+use tower_lsp::{LspService, Server};
+
+#[tokio::main]
+async fn main() {
+    let stdin = tokio::io::stdin();
+    let stdout = tokio::io::stdout();
+    let (service, socket) = LspService::new(Backend::new);
+    Server::new(stdin, stdout, socket).serve(service).await;
 }
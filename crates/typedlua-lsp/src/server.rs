@@ -0,0 +1,275 @@
+use crate::document::Document;
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tower_lsp::jsonrpc::Result;
+use tower_lsp::lsp_types::*;
+use tower_lsp::{Client, LanguageServer};
+use typedlua_core::config::CompilerConfig;
+use typedlua_core::config_check::check_config;
+
+pub struct Backend {
+    client: Client,
+    documents: Mutex<HashMap<Url, Arc<Document>>>,
+    config: Mutex<Arc<CompilerConfig>>,
+}
+
+impl Backend {
+    pub fn new(client: Client) -> Self {
+        Backend {
+            client,
+            documents: Mutex::new(HashMap::new()),
+            config: Mutex::new(Arc::new(CompilerConfig::default())),
+        }
+    }
+
+    fn config(&self) -> Arc<CompilerConfig> {
+        self.config
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// The open documents. The lock is only held to look documents up or
+    /// replace them, never while analyzing one, so a poisoned lock still
+    /// guards a consistent map.
+    fn documents(&self) -> MutexGuard<'_, HashMap<Url, Arc<Document>>> {
+        self.documents
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Run an analysis, logging a panic in it instead of taking the server
+    /// down with it. Text mid-edit is whatever the user has typed so far,
+    /// so a bug in the compiler must cost one answer, not the session.
+    async fn isolate<T>(&self, what: &str, analyze: impl FnOnce() -> T) -> Option<T> {
+        match catch_unwind(AssertUnwindSafe(analyze)) {
+            Ok(result) => Some(result),
+            Err(panic) => {
+                let reason = panic
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                self.client
+                    .log_message(
+                        MessageType::ERROR,
+                        format!("{} failed with an internal error: {}", what, reason),
+                    )
+                    .await;
+                None
+            }
+        }
+    }
+
+    /// Read `typedlua.json` from the workspace root. A missing or invalid
+    /// file leaves the defaults in place; `typedlua config check` reports
+    /// what is wrong with it.
+    fn load_config(&self, root: &Url) {
+        let Ok(root) = root.to_file_path() else {
+            return;
+        };
+        let Ok(source) = std::fs::read_to_string(root.join("typedlua.json")) else {
+            return;
+        };
+        if let (Some(config), _) = check_config(&source) {
+            *self.config.lock().unwrap_or_else(PoisonError::into_inner) = Arc::new(config);
+        }
+    }
+
+    /// Store the new text of a document and publish what checking it found
+    async fn update(&self, uri: Url, text: String) {
+        let path = uri.to_file_path().ok();
+        let config = self.config();
+        let checked = self
+            .isolate("Checking", || {
+                let mut document = Document::new(text.clone(), path.clone());
+                let diagnostics = document.check(&config);
+                (document, diagnostics)
+            })
+            .await;
+        // A document that could not be checked still answers completion
+        // and the like as well as it can
+        let (document, diagnostics) =
+            checked.unwrap_or_else(|| (Document::new(text, path), Vec::new()));
+        self.documents().insert(uri.clone(), Arc::new(document));
+        self.client
+            .publish_diagnostics(uri, diagnostics, None)
+            .await;
+    }
+
+    /// Run `f` on an open document with the character offset of `position`
+    async fn with_document<T>(
+        &self,
+        uri: &Url,
+        position: Position,
+        f: impl FnOnce(&Document, usize) -> Option<T>,
+    ) -> Option<T> {
+        let document = self.documents().get(uri)?.clone();
+        self.isolate("Answering a request", || {
+            f(&document, document.index.offset(position))
+        })
+        .await
+        .flatten()
+    }
+}
+
+#[tower_lsp::async_trait]
+impl LanguageServer for Backend {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        let root = params
+            .workspace_folders
+            .as_ref()
+            .and_then(|folders| folders.first())
+            .map(|folder| folder.uri.clone())
+            .or(params.root_uri);
+        if let Some(root) = root {
+            self.load_config(&root);
+        }
+
+        Ok(InitializeResult {
+            capabilities: ServerCapabilities {
+                text_document_sync: Some(TextDocumentSyncCapability::Kind(
+                    TextDocumentSyncKind::FULL,
+                )),
+                hover_provider: Some(HoverProviderCapability::Simple(true)),
+                definition_provider: Some(OneOf::Left(true)),
+                completion_provider: Some(CompletionOptions {
+                    trigger_characters: Some(["/", "\"", "'", "."].map(String::from).to_vec()),
+                    ..CompletionOptions::default()
+                }),
+                document_symbol_provider: Some(OneOf::Left(true)),
+                ..ServerCapabilities::default()
+            },
+            server_info: Some(ServerInfo {
+                name: "typedlua-lsp".to_string(),
+                version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            }),
+        })
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn did_open(&self, params: DidOpenTextDocumentParams) {
+        self.update(params.text_document.uri, params.text_document.text)
+            .await;
+    }
+
+    async fn did_change(&self, mut params: DidChangeTextDocumentParams) {
+        // Full sync: the last change holds the whole text
+        if let Some(change) = params.content_changes.pop() {
+            self.update(params.text_document.uri, change.text).await;
+        }
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri = params.text_document.uri;
+        self.documents().remove(&uri);
+        self.client.publish_diagnostics(uri, Vec::new(), None).await;
+    }
+
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let position = params.text_document_position_params;
+        let config = self.config();
+        Ok(self
+            .with_document(
+                &position.text_document.uri,
+                position.position,
+                |document, offset| document.hover(offset, &config),
+            )
+            .await)
+    }
+
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Result<Option<GotoDefinitionResponse>> {
+        let position = params.text_document_position_params;
+        let uri = position.text_document.uri;
+        Ok(self
+            .with_document(&uri, position.position, |document, offset| {
+                document.definition(&uri, offset)
+            })
+            .await
+            .map(GotoDefinitionResponse::Scalar))
+    }
+
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let position = params.text_document_position;
+        Ok(self
+            .with_document(
+                &position.text_document.uri,
+                position.position,
+                |document, offset| Some(document.completions(offset)),
+            )
+            .await
+            .map(CompletionResponse::Array))
+    }
+
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> Result<Option<DocumentSymbolResponse>> {
+        let Some(document) = self.documents().get(&params.text_document.uri).cloned() else {
+            return Ok(None);
+        };
+        Ok(self
+            .isolate("Listing symbols", || document.symbols())
+            .await
+            .map(DocumentSymbolResponse::Nested))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::LspService;
+
+    #[tokio::test]
+    async fn test_survives_panics_and_a_poisoned_lock() {
+        let (service, _socket) = LspService::new(Backend::new);
+        let backend = service.inner();
+        let panicked = backend.isolate("Testing", || -> usize { panic!("boom") });
+        assert_eq!(panicked.await, None);
+
+        std::thread::scope(|scope| {
+            let poisoning = scope.spawn(|| {
+                let _documents = backend.documents.lock().unwrap();
+                panic!("poisoned");
+            });
+            assert!(poisoning.join().is_err());
+        });
+        assert!(backend.documents.is_poisoned());
+
+        let uri = Url::parse("file:///project/main.tl").unwrap();
+        let text = "local r = `${format(((item)}`\nlocal count = 1\n";
+        backend
+            .did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(
+                    uri.clone(),
+                    "typedlua".to_string(),
+                    1,
+                    text.to_string(),
+                ),
+            })
+            .await;
+        let completion = backend
+            .completion(CompletionParams {
+                text_document_position: TextDocumentPositionParams::new(
+                    TextDocumentIdentifier::new(uri),
+                    Position::new(2, 0),
+                ),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+                context: None,
+            })
+            .await
+            .unwrap();
+        let Some(CompletionResponse::Array(items)) = completion else {
+            panic!("No completions");
+        };
+        assert!(items.iter().any(|item| item.label == "count"));
+    }
+}